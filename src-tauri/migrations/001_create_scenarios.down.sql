@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_scenarios_order;
+DROP TABLE IF EXISTS scenarios;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_step_images_scenario;
+DROP TABLE IF EXISTS step_images;
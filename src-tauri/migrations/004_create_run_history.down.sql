@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_step_results_run;
+DROP TABLE IF EXISTS step_results;
+DROP INDEX IF EXISTS idx_runs_started_at;
+DROP INDEX IF EXISTS idx_runs_scenario;
+DROP TABLE IF EXISTS runs;
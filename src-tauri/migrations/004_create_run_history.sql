@@ -0,0 +1,28 @@
+-- Run history tables for recording every scenario execution
+CREATE TABLE IF NOT EXISTS runs (
+    id TEXT PRIMARY KEY NOT NULL,
+    scenario_id TEXT NOT NULL,  -- Not a foreign key: history outlives deleted scenarios
+    scenario_title TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'running',  -- running | passed | failed | stopped
+    error_message TEXT,
+    started_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    finished_at TEXT,
+    duration_ms INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_runs_scenario ON runs(scenario_id);
+CREATE INDEX IF NOT EXISTS idx_runs_started_at ON runs(started_at);
+
+CREATE TABLE IF NOT EXISTS step_results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,
+    step_index INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    status TEXT NOT NULL,  -- passed | failed | skipped
+    error_message TEXT,
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    FOREIGN KEY (run_id) REFERENCES runs(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_step_results_run ON step_results(run_id);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_llm_usage_created_at;
+DROP INDEX IF EXISTS idx_llm_usage_run;
+DROP TABLE IF EXISTS llm_usage;
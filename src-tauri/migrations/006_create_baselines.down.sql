@@ -0,0 +1,2 @@
+-- Baseline image files under app data are left in place
+DROP TABLE IF EXISTS baselines;
@@ -0,0 +1,12 @@
+-- Visual regression baselines (image crops stored as PNG files under app data)
+CREATE TABLE IF NOT EXISTS baselines (
+    name TEXT PRIMARY KEY,
+    file_path TEXT NOT NULL,
+    monitor_id INTEGER,  -- NULL means the primary monitor
+    region_x INTEGER NOT NULL,
+    region_y INTEGER NOT NULL,
+    region_width INTEGER NOT NULL,
+    region_height INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now'))
+);
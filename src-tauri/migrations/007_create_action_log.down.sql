@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_action_log_run;
+DROP TABLE IF EXISTS action_log;
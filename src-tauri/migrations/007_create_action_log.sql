@@ -0,0 +1,18 @@
+-- Audit trail of executed input commands
+-- Each entry stores a SHA-256 hash over its fields and the previous entry's hash
+-- (per run), so edited or deleted rows break the chain.
+CREATE TABLE IF NOT EXISTS action_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT,  -- NULL for actions executed outside a recorded run
+    timestamp_ms INTEGER NOT NULL,
+    action TEXT NOT NULL,  -- Command name, e.g. left_click
+    x INTEGER,
+    y INTEGER,
+    details TEXT NOT NULL DEFAULT '{}',  -- JSON of the remaining arguments
+    status TEXT NOT NULL,  -- succeeded | failed
+    error_message TEXT,
+    prev_hash TEXT,
+    hash TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_action_log_run ON action_log(run_id);
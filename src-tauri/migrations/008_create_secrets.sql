@@ -0,0 +1,8 @@
+-- Encrypted secret values referenced by name from scenarios
+-- value is Base64 of nonce || AES-256-GCM ciphertext; the key lives in the OS credential store
+CREATE TABLE IF NOT EXISTS secrets (
+    name TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now'))
+);
@@ -0,0 +1,3 @@
+-- Scripted steps of a scenario as a JSON array (see services::steps)
+-- NULL when the scenario is run from its description alone
+ALTER TABLE scenarios ADD COLUMN steps TEXT;
@@ -0,0 +1,10 @@
+-- Uploads waiting for the next Supabase sync (see services::sync)
+-- Rows stay queued while offline and are removed once uploaded
+CREATE TABLE IF NOT EXISTS sync_queue (
+    entity_type TEXT NOT NULL,  -- scenario | run | artifact
+    entity_id TEXT NOT NULL,    -- scenario or run ID, "<run ID>/<file name>" for artifacts
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    queued_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    PRIMARY KEY (entity_type, entity_id)
+);
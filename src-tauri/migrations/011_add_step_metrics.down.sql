@@ -0,0 +1,2 @@
+ALTER TABLE step_results DROP COLUMN confidence;
+ALTER TABLE step_results DROP COLUMN attempts;
@@ -0,0 +1,5 @@
+-- Per-step execution metrics of the backend runner
+-- attempts: 1 plus the retries the step needed
+-- confidence: template match confidence of image steps, NULL for other steps
+ALTER TABLE step_results ADD COLUMN attempts INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE step_results ADD COLUMN confidence REAL;
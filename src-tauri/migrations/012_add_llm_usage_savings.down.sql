@@ -0,0 +1,2 @@
+ALTER TABLE llm_usage DROP COLUMN saved_usd;
+ALTER TABLE llm_usage DROP COLUMN screenshots_skipped;
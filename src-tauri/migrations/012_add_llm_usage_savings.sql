@@ -0,0 +1,5 @@
+-- Savings of prompt caching and screenshot deduplication per API call
+-- screenshots_skipped: screenshots not sent because the screen had not changed
+-- saved_usd: estimated cost avoided compared to sending everything uncached
+ALTER TABLE llm_usage ADD COLUMN screenshots_skipped INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE llm_usage ADD COLUMN saved_usd REAL NOT NULL DEFAULT 0;
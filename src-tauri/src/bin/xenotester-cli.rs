@@ -0,0 +1,369 @@
+//! Headless scenario runner
+//!
+//! Runs scenarios from the application database or a JSON file through the
+//! backend agent loop, without the webview. Results are printed to stdout as
+//! JSON and run events are optionally posted to a webhook. Logs go to stderr.
+//!
+//! Exit code: 0 when every scenario passed, 1 when any failed, 2 on usage errors.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use xenotester_lib::commands::agent::DEFAULT_MAX_ITERATIONS;
+use xenotester_lib::services::database::{default_db_path, open_pool};
+use xenotester_lib::services::dataset::load_dataset;
+use xenotester_lib::services::http;
+use xenotester_lib::services::llm::anthropic::ModelConfig;
+use xenotester_lib::services::notify::{Notifier, WebhookChannel};
+use xenotester_lib::services::runner::{
+    self, RunOptions, RunTimeouts, Scenario, ScenarioRunResult, ScenarioStatus,
+};
+use xenotester_lib::services::webhook::{is_valid_webhook_url, NotificationFormat};
+use xenotester_lib::utils::cancel::CancellationToken;
+use xenotester_lib::utils::logging;
+
+const USAGE: &str = "Usage: xenotester-cli [OPTIONS] (--scenario <ID>... | --all | --file <PATH>)
+
+Options:
+  --scenario <ID>        Run a stored scenario (repeatable)
+  --all                  Run all stored scenarios in order
+  --file <PATH>          Run scenarios from a JSON file (one object or an array
+                         of {\"title\", \"description\", \"steps\"})
+  --db <PATH>            Application database (default: the app's database)
+  --model <ID>           Model ID (gemini-* models run on Gemini,
+                         local/<name> on the LOCAL_LLM_BASE_URL server)
+  --max-iterations <N>   Agent turns per scenario (default 30)
+  --step-timeout <MS>    Time limit of each scripted step (default: the app's)
+  --run-timeout <MS>     Time limit of each scenario (default: the app's)
+  --var <NAME=VALUE>     Value for ${NAME} placeholders (repeatable)
+  --dataset <PATH>       Run each scenario once per row of a CSV or JSON file;
+                         columns are ${NAME} placeholders
+  --stop-on-failure      Skip the remaining scenarios after a failure
+  --webhook <URL>        Post run events to this webhook
+  --webhook-format <F>   raw, slack, discord or teams (default raw)
+  --webhook-events <E>   Comma-separated events to post, or * for all (default
+                         test_failure,step_failed,emergency_stop)
+  -h, --help             Show this help";
+
+/// Where the scenarios come from
+enum Source {
+    Database(Vec<String>),
+    AllInDatabase,
+    File(PathBuf),
+}
+
+struct Args {
+    source: Source,
+    db_path: Option<PathBuf>,
+    model: Option<String>,
+    max_iterations: u32,
+    step_timeout_ms: Option<u64>,
+    run_timeout_ms: Option<u64>,
+    variables: HashMap<String, String>,
+    dataset: Option<PathBuf>,
+    stop_on_failure: bool,
+    webhook: Option<WebhookChannel>,
+}
+
+/// Summary printed to stdout
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    success: bool,
+    passed: usize,
+    failed: usize,
+    /// Scenarios stopped by Ctrl+C or not run after a failure or Ctrl+C
+    skipped: usize,
+    results: Vec<ScenarioRunResult>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut scenario_ids = Vec::new();
+    let mut all = false;
+    let mut file = None;
+    let mut db_path = None;
+    let mut model = None;
+    let mut max_iterations = DEFAULT_MAX_ITERATIONS;
+    let mut step_timeout_ms = None;
+    let mut run_timeout_ms = None;
+    let mut variables = HashMap::new();
+    let mut dataset = None;
+    let mut stop_on_failure = false;
+    let mut webhook_url = None;
+    let mut webhook_format = NotificationFormat::default();
+    let mut webhook_events = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--scenario" => scenario_ids.push(value("--scenario")?),
+            "--all" => all = true,
+            "--file" => file = Some(PathBuf::from(value("--file")?)),
+            "--db" => db_path = Some(PathBuf::from(value("--db")?)),
+            "--model" => model = Some(value("--model")?),
+            "--max-iterations" => {
+                let raw = value("--max-iterations")?;
+                max_iterations = raw
+                    .parse()
+                    .map_err(|_| format!("Invalid --max-iterations: {}", raw))?;
+            }
+            "--step-timeout" => step_timeout_ms = Some(timeout_ms(value("--step-timeout")?)?),
+            "--run-timeout" => run_timeout_ms = Some(timeout_ms(value("--run-timeout")?)?),
+            "--var" => {
+                let raw = value("--var")?;
+                let (name, value) = raw
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid --var, expected NAME=VALUE: {}", raw))?;
+                variables.insert(name.trim().to_string(), value.to_string());
+            }
+            "--dataset" => dataset = Some(PathBuf::from(value("--dataset")?)),
+            "--stop-on-failure" => stop_on_failure = true,
+            "--webhook" => webhook_url = Some(value("--webhook")?),
+            "--webhook-format" => {
+                let raw = value("--webhook-format")?;
+                webhook_format = serde_json::from_value(Value::String(raw.to_lowercase()))
+                    .map_err(|_| format!("Invalid --webhook-format: {}", raw))?;
+            }
+            "--webhook-events" => {
+                let raw = value("--webhook-events")?;
+                let events: Vec<String> = raw
+                    .split(',')
+                    .map(|event| event.trim().to_string())
+                    .filter(|event| !event.is_empty())
+                    .collect();
+                if events.is_empty() {
+                    return Err(format!("Invalid --webhook-events: {}", raw));
+                }
+                webhook_events = Some(events);
+            }
+            "-h" | "--help" => return Err(String::new()),
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    let source = match (scenario_ids.is_empty(), all, file) {
+        (false, false, None) => Source::Database(scenario_ids),
+        (true, true, None) => Source::AllInDatabase,
+        (true, false, Some(path)) => Source::File(path),
+        _ => return Err("Specify exactly one of --scenario, --all or --file".to_string()),
+    };
+
+    if let Some(url) = &webhook_url {
+        if !is_valid_webhook_url(url) {
+            return Err(format!("Invalid --webhook URL: {}", url));
+        }
+    }
+
+    Ok(Args {
+        source,
+        db_path,
+        model,
+        max_iterations,
+        step_timeout_ms,
+        run_timeout_ms,
+        variables,
+        dataset,
+        stop_on_failure,
+        webhook: webhook_url.map(|url| {
+            let mut channel = WebhookChannel::new(url, webhook_format);
+            if let Some(events) = webhook_events {
+                channel.events = events;
+            }
+            channel
+        }),
+    })
+}
+
+/// Timeout in milliseconds, greater than zero
+fn timeout_ms(raw: String) -> Result<u64, String> {
+    match raw.parse() {
+        Ok(ms) if ms > 0 => Ok(ms),
+        _ => Err(format!("Invalid timeout: {}", raw)),
+    }
+}
+
+/// Read one scenario or an array of scenarios from a JSON file
+fn read_scenario_file(path: &PathBuf) -> Result<Vec<Scenario>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let value: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid JSON in {}: {}", path.display(), e))?;
+
+    let mut scenarios: Vec<Scenario> = match value {
+        Value::Array(_) => serde_json::from_value(value),
+        _ => serde_json::from_value(value).map(|scenario| vec![scenario]),
+    }
+    .map_err(|e| format!("Invalid scenario in {}: {}", path.display(), e))?;
+
+    // Scenarios without an ID are identified by file name and position
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    for (index, scenario) in scenarios.iter_mut().enumerate() {
+        if scenario.id.is_empty() {
+            scenario.id = format!("{}#{}", stem, index + 1);
+        }
+    }
+    Ok(scenarios)
+}
+
+async fn run(args: Args) -> Result<Report, String> {
+    let db_path = args.db_path.clone().or_else(default_db_path);
+    let needs_db = !matches!(args.source, Source::File(_));
+
+    // File runs are recorded in the run history only when the app's database exists
+    let pool = match db_path {
+        Some(path) if needs_db || path.exists() => {
+            Some(open_pool(&path).await.map_err(|e| e.to_string())?)
+        }
+        _ if needs_db => return Err("Cannot locate the application database, use --db".into()),
+        _ => None,
+    };
+
+    // Proxy and CA settings stored in the app apply to headless runs as well
+    if let Some(pool) = &pool {
+        if let Err(e) = http::load_config(pool).await {
+            tracing::warn!("Failed to apply the stored network settings: {}", e);
+        }
+    }
+
+    let scenarios = match &args.source {
+        Source::File(path) => read_scenario_file(path)?,
+        Source::AllInDatabase => runner::load_all_scenarios(pool.as_ref().expect("database"))
+            .await
+            .map_err(|e| e.to_string())?,
+        Source::Database(ids) => {
+            let mut scenarios = Vec::new();
+            for id in ids {
+                let pool = pool.as_ref().expect("database");
+                scenarios.push(
+                    runner::load_scenario(pool, id)
+                        .await
+                        .map_err(|e| e.to_string())?,
+                );
+            }
+            scenarios
+        }
+    };
+
+    let rows = match &args.dataset {
+        Some(path) => Some(load_dataset(path).map_err(|e| e.to_string())?),
+        None => None,
+    };
+
+    // Timeouts stored in the app apply unless given on the command line
+    let mut timeouts = match &pool {
+        Some(pool) => runner::load_timeouts(pool).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to read the stored run timeouts: {}", e);
+            RunTimeouts::default()
+        }),
+        None => RunTimeouts::default(),
+    };
+    timeouts.step_ms = args.step_timeout_ms.or(timeouts.step_ms);
+    timeouts.run_ms = args.run_timeout_ms.or(timeouts.run_ms);
+
+    let options = RunOptions {
+        model_config: args.model.map(ModelConfig::for_model).unwrap_or_default(),
+        max_iterations: args.max_iterations,
+        variables: args.variables,
+        notifier: match args.webhook {
+            Some(channel) => Notifier::disabled().with_webhook(channel),
+            None => Notifier::disabled(),
+        },
+        capture_history: None,
+        journal: None,
+        debugger: None,
+        timeouts,
+        // The governor and confirmation rules are set in the app, which is not running
+        guard: None,
+    };
+
+    // Ctrl+C stops the current scenario and skips the rest
+    let cancel = CancellationToken::new();
+    let on_interrupt = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            on_interrupt.cancel();
+        }
+    });
+
+    let mut results = Vec::new();
+    for scenario in &scenarios {
+        if cancel.is_cancelled() {
+            break;
+        }
+        tracing::info!("Running scenario: {}", scenario.title);
+        let result = match &rows {
+            Some(rows) => {
+                runner::run_dataset(scenario, &options, rows, pool.as_ref(), &cancel).await
+            }
+            None => runner::run_scenario(scenario, &options, pool.as_ref(), &cancel).await,
+        };
+        tracing::info!(
+            "{}: {:?} - {}",
+            scenario.title,
+            result.status,
+            result.message
+        );
+
+        let failed = !result.success && result.status != ScenarioStatus::Stopped;
+        results.push(result);
+        if failed && args.stop_on_failure {
+            break;
+        }
+    }
+
+    let passed = results.iter().filter(|r| r.success).count();
+    let failed = results
+        .iter()
+        .filter(|r| !r.success && r.status != ScenarioStatus::Stopped)
+        .count();
+    Ok(Report {
+        success: passed == scenarios.len(),
+        passed,
+        failed,
+        skipped: scenarios.len() - passed - failed,
+        results,
+    })
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    if dotenv::dotenv().is_err() {
+        let _ = dotenv::from_filename("../.env");
+    }
+    logging::init(None);
+
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("{}\n", message);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args).await {
+        Ok(report) => {
+            match serde_json::to_string_pretty(&report) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Failed to serialize results: {}", e),
+            }
+            if report.success {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::from(2)
+        }
+    }
+}
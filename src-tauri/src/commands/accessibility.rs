@@ -0,0 +1,97 @@
+//! Accessibility (UI element inspection) commands
+//!
+//! Accessibility queries message the target app and can block for seconds on an
+//! unresponsive one, so they run on the blocking thread pool.
+
+use serde_json::json;
+use tauri::{AppHandle, State};
+
+use crate::commands::input::submit_logged;
+use crate::error::{IpcError, XenotesterError};
+use crate::services::accessibility::{self, ElementBounds, UiElement, DEFAULT_TREE_DEPTH};
+use crate::services::action_log::ActionRecord;
+use crate::services::mouse::{self, MouseButton};
+use crate::state::AppState;
+
+/// Read the UI element tree of an app
+/// bundle_id: app to inspect, a bundle ID on macOS or an executable name on Windows
+/// (the frontmost app when omitted); max_depth defaults to 8
+#[tauri::command]
+pub async fn get_ui_tree(
+    bundle_id: Option<String>,
+    max_depth: Option<u32>,
+) -> Result<UiElement, IpcError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        accessibility::get_ui_tree(
+            bundle_id.as_deref(),
+            max_depth.unwrap_or(DEFAULT_TREE_DEPTH),
+        )
+        .map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Accessibility task failed: {}", e)))?
+}
+
+/// Find the first element with a role (e.g., "button") whose title or value contains `title`
+/// Returns None when no element matches.
+#[tauri::command]
+pub async fn find_element(
+    role: String,
+    title: Option<String>,
+    bundle_id: Option<String>,
+) -> Result<Option<UiElement>, IpcError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        accessibility::find_element(&role, title.as_deref(), bundle_id.as_deref())
+            .map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Accessibility task failed: {}", e)))?
+}
+
+/// Current screen bounds of an element returned by `get_ui_tree` or `find_element`
+#[tauri::command]
+pub async fn get_element_bounds(element_id: String) -> Result<ElementBounds, IpcError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        accessibility::get_element_bounds(&element_id).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Accessibility task failed: {}", e)))?
+}
+
+/// Click the center of an element returned by `get_ui_tree` or `find_element`
+/// button: "left" (default), "right", "middle", "back" or "forward"
+/// The click is checked and logged like `click_button` at the element's current center.
+#[tauri::command]
+pub async fn click_element(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    element_id: String,
+    button: Option<String>,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let mouse_button = match button.as_deref() {
+        Some(name) => MouseButton::from_name(name).ok_or_else(|| {
+            XenotesterError::InvalidArgument(format!("Invalid mouse button: {}", name))
+        })?,
+        None => MouseButton::Left,
+    };
+
+    let id = element_id.clone();
+    let (x, y) = tauri::async_runtime::spawn_blocking(move || {
+        accessibility::get_element_bounds(&id).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Accessibility task failed: {}", e)))??
+    .center();
+
+    let record = ActionRecord::new(
+        "click_element",
+        Some((x, y)),
+        json!({ "elementId": element_id, "button": button }),
+    );
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::click(x, y, mouse_button, &cancel).map_err(IpcError::from)
+    })
+    .await
+}
@@ -0,0 +1,255 @@
+//! Computer Use agent commands
+//!
+//! Drive the LLM conversation loop in the backend. Screenshots and tool calls
+//! stay inside Rust; only compact step summaries cross the IPC bridge.
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, State};
+use tracing::error;
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::database::get_pool;
+use crate::services::governor::ActionGuard;
+use crate::services::llm;
+use crate::services::llm::anthropic::{
+    AgentLoopResult, AgentSession, AgentStepResult, ModelConfig, StreamEvent, Usage,
+};
+use crate::services::usage::record_usage;
+use crate::services::variables::Variables;
+use crate::state::AppState;
+use crate::utils::operation::Operation;
+
+/// Default iteration cap, matching the frontend's maxIterationsPerScenario
+pub const DEFAULT_MAX_ITERATIONS: u32 = 30;
+
+/// Payload of the `llm-token` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmTokenEvent {
+    pub session_id: String,
+    pub text: String,
+}
+
+/// Payload of the `llm-tool-call` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmToolCallEvent {
+    pub session_id: String,
+    pub tool_use_id: String,
+    pub name: String,
+    pub input: Value,
+}
+
+/// Build a stream callback that forwards LLM output to the frontend as events
+fn event_emitter(app: AppHandle, session_id: String) -> impl FnMut(StreamEvent) + Send {
+    move |event| {
+        let result = match event {
+            StreamEvent::Token(text) => app.emit(
+                "llm-token",
+                LlmTokenEvent {
+                    session_id: session_id.clone(),
+                    text,
+                },
+            ),
+            StreamEvent::ToolCall { id, name, input } => app.emit(
+                "llm-tool-call",
+                LlmToolCallEvent {
+                    session_id: session_id.clone(),
+                    tool_use_id: id,
+                    name,
+                    input,
+                },
+            ),
+        };
+        if let Err(e) = result {
+            error!("Failed to emit stream event: {}", e);
+        }
+    }
+}
+
+/// Record token usage for cost accounting
+/// Failures are logged only; accounting must never fail an agent run
+async fn record_session_usage(app: &AppHandle, run_id: Option<&str>, model: &str, usage: &Usage) {
+    if usage.input_tokens == 0 && usage.output_tokens == 0 {
+        return;
+    }
+    let result = match get_pool(app).await {
+        Ok(pool) => record_usage(&pool, run_id, model, usage).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        error!("Failed to record token usage: {}", e);
+    }
+}
+
+/// Take a session out of state (or start a new one) so it can be used across awaits
+async fn take_or_start_session(
+    app: &AppHandle,
+    state: &AppState,
+    session_id: Option<String>,
+    instruction: Option<String>,
+    model_config: Option<ModelConfig>,
+    system_prompt: Option<String>,
+) -> Result<AgentSession, IpcError> {
+    if let Some(id) = session_id {
+        return state
+            .agent_sessions
+            .lock()
+            .map_err(IpcError::from)?
+            .remove(&id)
+            .ok_or_else(|| XenotesterError::NotFound(format!("agent session {}", id)).into());
+    }
+
+    let instruction = instruction.ok_or_else(|| {
+        XenotesterError::InvalidArgument(
+            "instruction is required to start a new session".to_string(),
+        )
+    })?;
+    start_session(app, state, &instruction, model_config, system_prompt).await
+}
+
+/// Start a session with the run variables substituted into the instruction
+/// Secrets the instruction references are substituted only when the model types them.
+/// The model's actions are recorded in the action log.
+async fn start_session(
+    app: &AppHandle,
+    state: &AppState,
+    instruction: &str,
+    model_config: Option<ModelConfig>,
+    system_prompt: Option<String>,
+) -> Result<AgentSession, IpcError> {
+    let values = state
+        .run_variables
+        .lock()
+        .map_err(IpcError::from)?
+        .clone();
+    let mut variables = Variables::new(values);
+    let pool = get_pool(app).await.ok();
+    let instruction = variables
+        .prepare_instruction(pool.as_ref(), instruction)
+        .await
+        .map_err(IpcError::from)?;
+
+    let mut session = AgentSession::start(
+        &instruction,
+        model_config.unwrap_or_default(),
+        system_prompt,
+    )
+    .await
+    .map_err(IpcError::from)?;
+    session.set_variables(variables);
+    if let Some(pool) = pool {
+        session.set_action_log(pool);
+    }
+    Ok(session)
+}
+
+/// Safety checks for the model's actions, counted against `run_id` or else the active run
+fn action_guard(state: &AppState, run_id: Option<&str>) -> ActionGuard {
+    let mut guard = state.action_guard();
+    if let Some(run_id) = run_id {
+        guard.run_id = Some(run_id.to_string());
+    }
+    guard
+}
+
+/// Return an unfinished session to state so the next step can continue it
+fn store_session(state: &AppState, session: AgentSession) {
+    if session.is_done() {
+        return;
+    }
+    if let Ok(mut sessions) = state.agent_sessions.lock() {
+        sessions.insert(session.id.clone(), session);
+    }
+}
+
+/// Run a single agent turn
+///
+/// Pass `instruction` (and optionally `model_config` / `system_prompt`) to start a
+/// new session, or `session_id` from a previous result to continue it.
+/// Token usage is attributed to `run_id` (from `start_run`) when given.
+/// The turn is cancelled through `token_id` (from `create_run_token`) or the shared stop.
+/// Output is streamed as `llm-token` / `llm-tool-call` events while the turn runs,
+/// and the turn is reported as an operation (see `utils::operation`).
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_agent_step(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: Option<String>,
+    instruction: Option<String>,
+    model_config: Option<ModelConfig>,
+    system_prompt: Option<String>,
+    run_id: Option<String>,
+    token_id: Option<String>,
+) -> Result<AgentStepResult, IpcError> {
+    let operation = Operation::start(&app, "run_agent_step", token_id.as_deref());
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let mut session = take_or_start_session(
+        &app,
+        &state,
+        session_id,
+        instruction,
+        model_config,
+        system_prompt,
+    )
+    .await?;
+    let client = llm::create_backend(session.provider()).map_err(IpcError::from)?;
+    session.set_guard(action_guard(&state, run_id.as_deref()));
+    session.set_capture_history(state.capture_history.clone());
+
+    let mut on_event = event_emitter(app.clone(), session.id.clone());
+    let result = session.step(&*client, &cancel, &mut on_event).await;
+    if let Ok(step) = &result {
+        record_session_usage(&app, run_id.as_deref(), session.model(), &step.usage).await;
+    }
+
+    // Cancelled or failed sessions are dropped; the caller starts over
+    if result.is_ok() {
+        store_session(&state, session);
+    }
+    let result = result.map_err(IpcError::from);
+    operation.finish(&result);
+    result
+}
+
+/// Run the agent loop for an instruction until the model finishes
+/// Stops early (with an error) if the run is cancelled mid-generation or between tool calls
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_agent_loop(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    instruction: String,
+    model_config: Option<ModelConfig>,
+    system_prompt: Option<String>,
+    max_iterations: Option<u32>,
+    run_id: Option<String>,
+    token_id: Option<String>,
+) -> Result<AgentLoopResult, IpcError> {
+    let operation = Operation::start(&app, "run_agent_loop", token_id.as_deref());
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let mut session =
+        start_session(&app, &state, &instruction, model_config, system_prompt).await?;
+    let client = llm::create_backend(session.provider()).map_err(IpcError::from)?;
+    session.set_guard(action_guard(&state, run_id.as_deref()));
+    session.set_capture_history(state.capture_history.clone());
+
+    let mut on_event = event_emitter(app.clone(), session.id.clone());
+    let result = session
+        .run_loop(
+            &*client,
+            &cancel,
+            max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS),
+            &mut on_event,
+        )
+        .await;
+
+    // Record usage even when the loop was stopped or failed part-way
+    record_session_usage(&app, run_id.as_deref(), session.model(), session.total_usage()).await;
+
+    let result = result.map_err(IpcError::from);
+    operation.finish(&result);
+    result
+}
@@ -0,0 +1,154 @@
+//! Run artifact commands
+//!
+//! Artifacts live in the app data `artifacts` directory, or under the directory
+//! named by `XENOTESTER_ARTIFACT_ROOT` (see `services::artifacts`).
+//! The cleanup policy is applied at startup and after every saved artifact.
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use std::env;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, warn};
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::artifacts::{self, ArtifactInfo, CleanupPolicy, CleanupReport};
+use crate::state::AppState;
+
+/// Environment variable naming a user-chosen directory for artifacts
+const ARTIFACT_ROOT_ENV: &str = "XENOTESTER_ARTIFACT_ROOT";
+
+/// Subdirectory of the artifact root that cleanup manages
+/// (so cleanup never removes the user's other files there)
+const ARTIFACT_ROOT_SUBDIR: &str = "xenotester-artifacts";
+
+/// App data subdirectories the path-based file commands may write into
+/// (not the app data directory itself, which holds the database and the master key)
+const WRITABLE_APP_DATA_SUBDIRS: &[&str] = &["artifacts", "exports"];
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, IpcError> {
+    app.path().app_data_dir().map_err(|e| {
+        XenotesterError::Internal(format!("Failed to resolve app data directory: {}", e)).into()
+    })
+}
+
+/// Artifact root configured by the user, if any
+fn user_artifact_root() -> Option<PathBuf> {
+    env::var_os(ARTIFACT_ROOT_ENV)
+        .filter(|root| !root.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Directory holding run artifacts
+pub(crate) fn artifacts_dir(app: &AppHandle) -> Result<PathBuf, IpcError> {
+    match user_artifact_root() {
+        Some(root) => Ok(root.join(ARTIFACT_ROOT_SUBDIR)),
+        None => app_data_dir(app).map(|dir| dir.join("artifacts")),
+    }
+}
+
+/// Directories the path-based file commands may write into
+/// (the app data `artifacts` and `exports` directories and the user's artifact root)
+pub fn writable_roots(app: &AppHandle) -> Result<Vec<PathBuf>, IpcError> {
+    let app_data = app_data_dir(app)?;
+    let mut roots: Vec<PathBuf> = WRITABLE_APP_DATA_SUBDIRS
+        .iter()
+        .map(|subdir| app_data.join(subdir))
+        .collect();
+    roots.extend(user_artifact_root());
+    Ok(roots)
+}
+
+fn current_policy(state: &AppState) -> Result<CleanupPolicy, IpcError> {
+    state
+        .artifact_policy
+        .lock()
+        .map(|policy| policy.clone())
+        .map_err(IpcError::from)
+}
+
+/// Save base64 data as an artifact of `run_id` (default: the active run)
+/// name: plain file name such as "verification-1.png"; paths are rejected
+#[tauri::command]
+pub async fn save_artifact(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    data_base64: String,
+    run_id: Option<String>,
+) -> Result<ArtifactInfo, IpcError> {
+    let dir = artifacts_dir(&app)?;
+    let run_id = run_id.or_else(|| state.active_run());
+    let policy = current_policy(&state)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let data = BASE64_STANDARD
+            .decode(&data_base64)
+            .map_err(|e| XenotesterError::ImageError(format!("Failed to decode base64: {}", e)))?;
+        let artifact = artifacts::save_artifact(&dir, run_id.as_deref(), &name, &data)
+            .map_err(IpcError::from)?;
+        if let Err(e) = artifacts::cleanup(&dir, &policy) {
+            warn!("Artifact cleanup failed: {}", e);
+        }
+        Ok(artifact)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Save artifact task failed: {}", e)))?
+}
+
+/// List the artifacts of a run, or of all runs when `run_id` is omitted (oldest first)
+#[tauri::command]
+pub async fn list_artifacts(
+    app: AppHandle,
+    run_id: Option<String>,
+) -> Result<Vec<ArtifactInfo>, IpcError> {
+    let dir = artifacts_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        artifacts::list_artifacts(&dir, run_id.as_deref()).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("List artifacts task failed: {}", e)))?
+}
+
+/// Apply the cleanup policy now
+#[tauri::command]
+pub async fn cleanup_artifacts(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<CleanupReport, IpcError> {
+    let dir = artifacts_dir(&app)?;
+    let policy = current_policy(&state)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        artifacts::cleanup(&dir, &policy).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Artifact cleanup task failed: {}", e)))?
+}
+
+/// Get the artifact cleanup policy
+#[tauri::command]
+pub fn get_artifact_policy(state: State<AppState>) -> Result<CleanupPolicy, IpcError> {
+    current_policy(&state)
+}
+
+/// Set the artifact cleanup policy (applied from the next save or cleanup)
+#[tauri::command]
+pub fn set_artifact_policy(state: State<AppState>, policy: CleanupPolicy) -> Result<(), IpcError> {
+    *state.artifact_policy.lock().map_err(IpcError::from)? = policy;
+    Ok(())
+}
+
+/// Apply the cleanup policy in the background at startup
+pub fn cleanup_on_startup(app: &AppHandle) {
+    let (dir, policy) = match (artifacts_dir(app), current_policy(&app.state::<AppState>())) {
+        (Ok(dir), Ok(policy)) => (dir, policy),
+        _ => return,
+    };
+    tauri::async_runtime::spawn_blocking(move || match artifacts::cleanup(&dir, &policy) {
+        Ok(report) if report.removed_files > 0 => info!(
+            "Removed {} old artifacts ({} bytes)",
+            report.removed_files, report.freed_bytes
+        ),
+        Ok(_) => {}
+        Err(e) => warn!("Artifact cleanup failed: {}", e),
+    });
+}
@@ -0,0 +1,64 @@
+//! Assertion commands
+//!
+//! Verification steps return a structured pass/fail result with evidence
+//! (see `services::assertion`) instead of relying on an LLM judgment.
+//! A failed assertion is a successful command with `passed: false`.
+//! `assert_text_visible` will be added once OCR is available.
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::assertion::{self, AssertionResult};
+use crate::services::screen_check::{DEFAULT_COLOR_TOLERANCE, DEFAULT_CONFIDENCE_THRESHOLD};
+
+/// Assert that a template image (base64, original size) is visible on a monitor
+/// confidence_threshold: minimum match score (default 0.8)
+#[tauri::command]
+pub async fn assert_template_visible(
+    template_image: String,
+    monitor_id: Option<u32>,
+    confidence_threshold: Option<f32>,
+) -> Result<AssertionResult, IpcError> {
+    assert_template(template_image, monitor_id, confidence_threshold, true).await
+}
+
+/// Assert that a template image (base64, original size) is not visible on a monitor
+/// confidence_threshold: minimum match score counted as visible (default 0.8)
+#[tauri::command]
+pub async fn assert_template_absent(
+    template_image: String,
+    monitor_id: Option<u32>,
+    confidence_threshold: Option<f32>,
+) -> Result<AssertionResult, IpcError> {
+    assert_template(template_image, monitor_id, confidence_threshold, false).await
+}
+
+async fn assert_template(
+    template_image: String,
+    monitor_id: Option<u32>,
+    confidence_threshold: Option<f32>,
+    expect_visible: bool,
+) -> Result<AssertionResult, IpcError> {
+    let threshold = confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD);
+    tauri::async_runtime::spawn_blocking(move || {
+        assertion::assert_template(&template_image, monitor_id, threshold, expect_visible)
+            .map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Assertion task failed: {}", e)))?
+}
+
+/// Assert the color ("#rrggbb") of the pixel at a screen position
+/// tolerance: maximum difference per RGB channel (default 16)
+#[tauri::command]
+pub async fn assert_pixel_color(
+    x: i32,
+    y: i32,
+    color: String,
+    tolerance: Option<u8>,
+) -> Result<AssertionResult, IpcError> {
+    let tolerance = tolerance.unwrap_or(DEFAULT_COLOR_TOLERANCE);
+    tauri::async_runtime::spawn_blocking(move || {
+        assertion::assert_pixel_color(x, y, &color, tolerance).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Assertion task failed: {}", e)))?
+}
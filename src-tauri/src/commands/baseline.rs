@@ -0,0 +1,55 @@
+//! Visual regression baseline commands
+
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::baseline::{
+    self, BaselineAssertion, BaselineInfo, Region, DEFAULT_BASELINE_TOLERANCE,
+};
+use crate::services::database::get_pool;
+
+/// Directory holding baseline images and diff artifacts
+fn baselines_dir(app: &AppHandle) -> Result<PathBuf, IpcError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("baselines"))
+        .map_err(|e| {
+            XenotesterError::Internal(format!("Failed to resolve app data directory: {}", e)).into()
+        })
+}
+
+/// Save the current screen (or `region`, in physical monitor pixels) as a named baseline
+#[tauri::command]
+pub async fn save_baseline(
+    app: AppHandle,
+    name: String,
+    region: Option<Region>,
+    monitor_id: Option<u32>,
+) -> Result<BaselineInfo, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    let dir = baselines_dir(&app)?;
+    baseline::save_baseline(&pool, &dir, &name, region, monitor_id)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Compare the current screen against a named baseline
+/// `tolerance` is the fraction of pixels allowed to differ (default 0.01)
+#[tauri::command]
+pub async fn assert_matches_baseline(
+    app: AppHandle,
+    name: String,
+    tolerance: Option<f64>,
+) -> Result<BaselineAssertion, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    let dir = baselines_dir(&app)?;
+    baseline::assert_matches_baseline(
+        &pool,
+        &dir,
+        &name,
+        tolerance.unwrap_or(DEFAULT_BASELINE_TOLERANCE),
+    )
+    .await
+    .map_err(IpcError::from)
+}
@@ -1,6 +1,13 @@
-//! Configuration commands (API key retrieval)
+//! Configuration commands (API keys, network settings and language)
 
 use std::env;
+use tauri::AppHandle;
+use tracing::warn;
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::database::get_pool;
+use crate::services::http::{self, ConnectivityReport, NetworkConfig};
+use crate::services::locale::{self, Locale};
 
 #[derive(serde::Serialize)]
 pub struct SupabaseConfig {
@@ -14,7 +21,7 @@ pub struct SupabaseConfig {
 /// 1. Runtime environment variables (for development)
 /// 2. Compile-time environment variables (for release builds)
 #[tauri::command]
-pub fn get_supabase_config() -> Result<SupabaseConfig, String> {
+pub fn get_supabase_config() -> Result<SupabaseConfig, IpcError> {
     // Try runtime env first (for development), then compile-time (for release)
     let url = env::var("SUPABASE_URL")
         .or_else(|_| option_env!("SUPABASE_URL").map(String::from).ok_or(()))
@@ -30,14 +37,20 @@ pub fn get_supabase_config() -> Result<SupabaseConfig, String> {
 /// Get API key by name
 /// Supported keys: "anthropic", "gemini"
 #[tauri::command]
-pub fn get_api_key(key_name: String) -> Result<String, String> {
+pub fn get_api_key(key_name: String) -> Result<String, IpcError> {
     let env_key = match key_name.to_lowercase().as_str() {
         "anthropic" => "ANTHROPIC_API_KEY",
         "gemini" => "GEMINI_API_KEY",
-        _ => return Err(format!("Unknown key name: {}", key_name)),
+        _ => {
+            return Err(
+                XenotesterError::InvalidArgument(format!("Unknown key name: {}", key_name)).into(),
+            )
+        }
     };
 
-    env::var(env_key).map_err(|_| format!("{} is not set in environment", env_key))
+    env::var(env_key).map_err(|_| {
+        XenotesterError::ConfigError(format!("{} is not set in environment", env_key)).into()
+    })
 }
 
 /// Check if API key is configured
@@ -51,3 +64,69 @@ pub fn is_api_key_configured(key_name: String) -> bool {
 
     env::var(env_key).is_ok()
 }
+
+/// Get the proxy and CA settings for outbound requests
+#[tauri::command]
+pub fn get_network_config() -> NetworkConfig {
+    http::config()
+}
+
+/// Apply and store proxy and CA settings for outbound requests
+/// Invalid settings (bad proxy URL, unreadable CA file) are rejected unchanged.
+#[tauri::command]
+pub async fn set_network_config(app: AppHandle, config: NetworkConfig) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    http::save_config(&pool, config)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Send a GET request to `url` with the current network settings
+/// Reports whether and how fast it answered, or why it could not be reached.
+#[tauri::command]
+pub async fn test_connectivity(url: String) -> Result<ConnectivityReport, IpcError> {
+    http::test_connectivity(&url).await.map_err(IpcError::from)
+}
+
+/// Apply the stored network settings at startup
+pub fn load_network_config(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let loaded = match get_pool(&app).await {
+            Ok(pool) => http::load_config(&pool).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = loaded {
+            warn!("Failed to apply the stored network settings: {}", e);
+        }
+    });
+}
+
+/// Language of error messages: "en" (default), "ja" or "de"
+#[tauri::command]
+pub fn get_locale() -> Locale {
+    locale::locale()
+}
+
+/// Show error messages in a language and remember it across restarts
+#[tauri::command]
+pub async fn set_locale(app: AppHandle, locale: Locale) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    locale::save_locale(&pool, locale)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Apply the stored language at startup
+pub fn load_locale(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let loaded = match get_pool(&app).await {
+            Ok(pool) => locale::load_locale(&pool).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = loaded {
+            warn!("Failed to apply the stored language: {}", e);
+        }
+    });
+}
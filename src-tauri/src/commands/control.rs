@@ -1,8 +1,14 @@
-//! Control commands for stop/clear operations
+//! Control commands for stop/clear operations, per-run cancellation, the
+//! input safety governor and confirmation gate, and user activity detection
 
+use crate::error::IpcError;
+use crate::services::confirmation::ConfirmationRules;
+use crate::services::governor::GovernorConfig;
+use crate::services::user_activity::{self, UserActivityConfig};
 use crate::state::AppState;
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 use std::time::Duration;
+use tracing::error;
 
 /// Request stop of all operations
 #[tauri::command]
@@ -22,26 +28,107 @@ pub fn is_stop_requested(state: State<AppState>) -> bool {
     state.is_stop_requested()
 }
 
-/// Wait for specified duration (cancellable via stop request)
+/// Create a cancellation token for a new run
+/// Pass the returned ID as `tokenId` to input, capture, matching and wait commands
+#[tauri::command]
+pub fn create_run_token(state: State<AppState>) -> Result<String, IpcError> {
+    state.create_run_token().map_err(IpcError::from)
+}
+
+/// Cancel a single run without affecting others
+/// Returns false if the token is unknown (e.g., already released)
+#[tauri::command]
+pub fn cancel_run(state: State<AppState>, token_id: String) -> Result<bool, IpcError> {
+    state.cancel_run(&token_id).map_err(IpcError::from)
+}
+
+/// Release a finished run's token
+#[tauri::command]
+pub fn release_run_token(state: State<AppState>, token_id: String) -> Result<(), IpcError> {
+    state.release_run_token(&token_id).map_err(IpcError::from)
+}
+
+/// Wait for specified duration (cancellable via stop request or `cancel_run`)
 /// Returns true if completed, false if cancelled
 #[tauri::command]
-pub async fn wait(state: State<'_, AppState>, duration_ms: u64) -> Result<bool, String> {
-    let check_interval = Duration::from_millis(100);
-    let total_duration = Duration::from_millis(duration_ms);
-    let mut elapsed = Duration::ZERO;
+pub async fn wait(
+    state: State<'_, AppState>,
+    duration_ms: u64,
+    token_id: Option<String>,
+) -> Result<bool, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    Ok(cancel
+        .sleep_async(Duration::from_millis(duration_ms))
+        .await
+        .is_ok())
+}
 
-    while elapsed < total_duration {
-        // Check for stop request
-        if state.is_stop_requested() {
-            return Ok(false);
-        }
+/// Get the safety governor limits applied to input actions
+#[tauri::command]
+pub fn get_governor_config(state: State<AppState>) -> GovernorConfig {
+    state.governor.config()
+}
 
-        // Sleep for check interval or remaining time, whichever is shorter
-        let remaining = total_duration - elapsed;
-        let sleep_time = check_interval.min(remaining);
-        tokio::time::sleep(sleep_time).await;
-        elapsed += sleep_time;
-    }
+/// Set the safety governor limits (rate caps and forbidden click regions)
+#[tauri::command]
+pub fn set_governor_config(state: State<AppState>, config: GovernorConfig) {
+    state.governor.set_config(config);
+}
 
-    Ok(true)
+/// Get the rules for actions that wait for approval
+#[tauri::command]
+pub fn get_confirmation_rules(state: State<AppState>) -> ConfirmationRules {
+    state.confirmations.rules()
+}
+
+/// Set the rules for actions that wait for approval (shortcuts and danger zones)
+/// A matching action emits `confirmation-required` and waits for `confirm_action`.
+#[tauri::command]
+pub fn set_confirmation_rules(state: State<AppState>, rules: ConfirmationRules) {
+    state.confirmations.set_rules(rules);
+}
+
+/// Approve or decline an action announced by `confirmation-required`
+/// Returns false if the action is no longer waiting (e.g., its run was cancelled)
+#[tauri::command]
+pub fn confirm_action(state: State<AppState>, action_id: String, approved: bool) -> bool {
+    state.confirmations.respond(&action_id, approved)
+}
+
+/// Send confirmation requests to the frontend as `confirmation-required` events
+pub fn emit_confirmation_requests(app: &AppHandle) {
+    let emitter = app.clone();
+    app.state::<AppState>()
+        .confirmations
+        .set_notifier(Box::new(move |request| {
+            if let Err(e) = emitter.emit("confirmation-required", request) {
+                error!("Failed to emit confirmation request: {}", e);
+            }
+        }));
+}
+
+/// Get the user intervention detection settings
+#[tauri::command]
+pub fn get_user_activity_config(state: State<AppState>) -> UserActivityConfig {
+    state.user_activity.config()
+}
+
+/// Set the user intervention detection settings
+/// response: "pause" (hold input actions until the user is idle) or "notify"
+#[tauri::command]
+pub fn set_user_activity_config(state: State<AppState>, config: UserActivityConfig) {
+    state.user_activity.set_config(config);
+}
+
+/// Watch for the user taking the mouse during automation and emit
+/// `user-intervention-detected` events
+pub fn watch_user_activity(app: &AppHandle) {
+    let emitter = app.clone();
+    let activity = app.state::<AppState>().user_activity.clone();
+    activity.set_notifier(Box::new(move |intervention| {
+        if let Err(e) = emitter.emit("user-intervention-detected", intervention) {
+            error!("Failed to emit user intervention: {}", e);
+        }
+    }));
+    user_activity::spawn_watcher(activity);
 }
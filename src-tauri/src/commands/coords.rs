@@ -0,0 +1,24 @@
+//! Coordinate transformation commands
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::coords::{self, CoordinateContext, CoordinateSpace, TranslatedPoint};
+
+/// Convert a point between screenshot, physical and logical coordinate space
+/// `context` can be the `CaptureResult` the coordinate refers to
+/// This is a lightweight operation, no need for spawn_blocking
+#[tauri::command]
+pub fn translate_coordinates(
+    x: f64,
+    y: f64,
+    from: CoordinateSpace,
+    to: CoordinateSpace,
+    context: CoordinateContext,
+) -> Result<TranslatedPoint, IpcError> {
+    if context.scale_factor <= 0.0 || context.display_scale_factor <= 0.0 {
+        return Err(
+            XenotesterError::InvalidArgument("Scale factors must be positive".to_string()).into(),
+        );
+    }
+
+    Ok(coords::translate(x, y, from, to, &context))
+}
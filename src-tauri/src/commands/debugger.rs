@@ -0,0 +1,55 @@
+//! Scenario debugger commands
+//!
+//! Breakpoints on the steps of stored scenarios; backend runs pause before
+//! them, emit `breakpoint-hit` and wait for `step_over` or `continue_run`.
+
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::error;
+
+use crate::services::debugger::Resume;
+use crate::state::AppState;
+
+/// Pause runs of a scenario before its step `step_index` (0-based)
+#[tauri::command]
+pub fn set_breakpoint(state: State<AppState>, scenario_id: String, step_index: usize) {
+    state.debugger.set_breakpoint(&scenario_id, step_index);
+}
+
+/// Remove a breakpoint
+/// Returns false if the step had none
+#[tauri::command]
+pub fn clear_breakpoint(state: State<AppState>, scenario_id: String, step_index: usize) -> bool {
+    state.debugger.clear_breakpoint(&scenario_id, step_index)
+}
+
+/// Steps of a scenario that have a breakpoint
+#[tauri::command]
+pub fn get_breakpoints(state: State<AppState>, scenario_id: String) -> Vec<usize> {
+    state.debugger.breakpoints(&scenario_id)
+}
+
+/// Run the step a run is paused at, then pause again before the next one
+/// Returns false if the run is no longer paused (e.g., it was cancelled)
+#[tauri::command]
+pub fn step_over(state: State<AppState>, pause_id: String) -> bool {
+    state.debugger.resume(&pause_id, Resume::StepOver)
+}
+
+/// Resume a paused run until its next breakpoint
+/// Returns false if the run is no longer paused (e.g., it was cancelled)
+#[tauri::command]
+pub fn continue_run(state: State<AppState>, pause_id: String) -> bool {
+    state.debugger.resume(&pause_id, Resume::Continue)
+}
+
+/// Send paused runs to the frontend as `breakpoint-hit` events
+pub fn emit_breakpoint_hits(app: &AppHandle) {
+    let emitter = app.clone();
+    app.state::<AppState>()
+        .debugger
+        .set_notifier(Box::new(move |hit| {
+            if let Err(e) = emitter.emit("breakpoint-hit", hit) {
+                error!("Failed to emit breakpoint hit: {}", e);
+            }
+        }));
+}
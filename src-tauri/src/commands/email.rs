@@ -0,0 +1,70 @@
+//! Email notification commands
+//!
+//! Configure the SMTP channel of `services::notify::email`. The password is
+//! kept in the encrypted secret store, never in the settings table.
+
+use serde_json::json;
+use tauri::AppHandle;
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::database::get_pool;
+use crate::services::notify::email::{self, EmailChannel, EmailConfig, PASSWORD_SECRET};
+use crate::services::secrets;
+use crate::services::webhook::{WebhookPayload, TEST_EVENT};
+
+/// Get the stored email configuration
+#[tauri::command]
+pub async fn get_email_config(app: AppHandle) -> Result<Option<EmailConfig>, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    email::load_config(&pool).await.map_err(IpcError::from)
+}
+
+/// Store the email configuration (used by runs started afterwards)
+/// `password` replaces the stored SMTP password; an empty string removes it.
+#[tauri::command]
+pub async fn set_email_config(
+    app: AppHandle,
+    config: EmailConfig,
+    password: Option<String>,
+) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    email::save_config(&pool, &config)
+        .await
+        .map_err(IpcError::from)?;
+
+    match password {
+        Some(password) if password.is_empty() => {
+            secrets::delete_internal(&pool, PASSWORD_SECRET)
+                .await
+                .map_err(IpcError::from)?;
+        }
+        Some(password) => secrets::store_internal(&pool, PASSWORD_SECRET, &password)
+            .await
+            .map_err(IpcError::from)?,
+        None => {}
+    }
+    Ok(())
+}
+
+/// Send a test email with the stored password
+/// Uses `config` when given (to check settings before storing them), else the stored configuration.
+#[tauri::command]
+pub async fn send_test_email(app: AppHandle, config: Option<EmailConfig>) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    let config = match config {
+        Some(config) => config,
+        None => email::load_config(&pool)
+            .await
+            .map_err(IpcError::from)?
+            .ok_or_else(|| {
+                XenotesterError::ConfigError("No email notifications are configured".to_string())
+            })?,
+    };
+    config.validate().map_err(IpcError::from)?;
+    let password = email::load_password(&pool).await.map_err(IpcError::from)?;
+
+    EmailChannel::new(config, password)
+        .send(&WebhookPayload::new(TEST_EVENT, json!({})), None)
+        .await
+        .map_err(IpcError::from)
+}
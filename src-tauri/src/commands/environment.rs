@@ -0,0 +1,48 @@
+//! Environment snapshot commands
+//!
+//! Call `snapshot_environment` before a run and `restore_environment` after it
+//! to leave the user's desktop as it was.
+
+use tauri::State;
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::environment::{self, EnvironmentSnapshot, RestoreReport};
+use crate::services::input_worker;
+use crate::state::AppState;
+
+/// Record the mouse position, clipboard text, frontmost app and volume
+/// The snapshot is kept for `restore_environment` and also returned.
+#[tauri::command]
+pub async fn snapshot_environment(
+    state: State<'_, AppState>,
+) -> Result<EnvironmentSnapshot, IpcError> {
+    // Read on the input worker so a running input action can't move the mouse meanwhile
+    let snapshot = input_worker::submit(environment::snapshot)
+        .await
+        .map_err(IpcError::from)?;
+    *state.environment_snapshot.lock().map_err(IpcError::from)? = Some(snapshot.clone());
+    Ok(snapshot)
+}
+
+/// Restore a snapshot (default: the one taken by `snapshot_environment`, which is consumed)
+/// Parts that could not be restored are listed in the report.
+#[tauri::command]
+pub async fn restore_environment(
+    state: State<'_, AppState>,
+    snapshot: Option<EnvironmentSnapshot>,
+    token_id: Option<String>,
+) -> Result<RestoreReport, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let snapshot = match snapshot {
+        Some(snapshot) => snapshot,
+        None => state
+            .environment_snapshot
+            .lock()
+            .map_err(IpcError::from)?
+            .take()
+            .ok_or_else(|| XenotesterError::NotFound("environment snapshot".to_string()))?,
+    };
+    input_worker::submit(move || environment::restore(&snapshot, &cancel))
+        .await
+        .map_err(IpcError::from)
+}
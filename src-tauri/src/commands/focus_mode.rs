@@ -0,0 +1,67 @@
+//! Do Not Disturb commands
+//!
+//! With `enableDuringRuns` set, `start_run` turns Do Not Disturb on and
+//! `finish_run` restores it; it is also restored when the app exits.
+
+use std::sync::Arc;
+use tauri::State;
+use tracing::warn;
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::focus_mode::{FocusMode, FocusModeConfig};
+use crate::state::AppState;
+
+/// Silence OS notifications until `restore_focus_mode`
+/// Returns false if they were already silenced by the app
+#[tauri::command]
+pub async fn enable_focus_mode(state: State<'_, AppState>) -> Result<bool, IpcError> {
+    let focus_mode = state.focus_mode.clone();
+    tauri::async_runtime::spawn_blocking(move || focus_mode.enable().map_err(IpcError::from))
+        .await
+        .map_err(|e| XenotesterError::Internal(format!("Focus mode task failed: {}", e)))?
+}
+
+/// Return notifications to the state before `enable_focus_mode`
+/// Returns false if there was nothing to restore
+#[tauri::command]
+pub async fn restore_focus_mode(state: State<'_, AppState>) -> Result<bool, IpcError> {
+    let focus_mode = state.focus_mode.clone();
+    tauri::async_runtime::spawn_blocking(move || focus_mode.restore().map_err(IpcError::from))
+        .await
+        .map_err(|e| XenotesterError::Internal(format!("Focus mode task failed: {}", e)))?
+}
+
+/// Get the Do Not Disturb settings
+#[tauri::command]
+pub fn get_focus_mode_config(state: State<AppState>) -> FocusModeConfig {
+    state.focus_mode.config()
+}
+
+/// Set the Do Not Disturb settings
+#[tauri::command]
+pub fn set_focus_mode_config(state: State<AppState>, config: FocusModeConfig) {
+    state.focus_mode.set_config(config);
+}
+
+/// Turn Do Not Disturb on for a starting run, if configured
+/// Failures are logged; a run never fails because notifications stay on.
+pub async fn enable_for_run(focus_mode: Arc<FocusMode>) {
+    if !focus_mode.config().enable_during_runs {
+        return;
+    }
+    let result = tauri::async_runtime::spawn_blocking(move || focus_mode.enable()).await;
+    if let Ok(Err(e)) = result {
+        warn!("Failed to enable Do Not Disturb: {}", e);
+    }
+}
+
+/// Restore notifications after a run, if they were silenced for it
+pub async fn restore_after_run(focus_mode: Arc<FocusMode>) {
+    if !focus_mode.config().enable_during_runs {
+        return;
+    }
+    let result = tauri::async_runtime::spawn_blocking(move || focus_mode.restore()).await;
+    if let Ok(Err(e)) = result {
+        warn!("Failed to restore Do Not Disturb: {}", e);
+    }
+}
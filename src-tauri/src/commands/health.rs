@@ -0,0 +1,136 @@
+//! System health check command
+//!
+//! Aggregates the readiness of every subsystem a scenario run depends on, so a
+//! single call can explain why "nothing happens" on a user's machine.
+
+use serde::Serialize;
+use std::env;
+use tauri::AppHandle;
+
+use crate::commands::config::is_api_key_configured;
+use crate::commands::permission::{check_permissions, PermissionStatus};
+use crate::error::{IpcError, XenotesterError};
+use crate::services::capture::list_monitors;
+use crate::services::database::get_pool;
+use crate::services::input_worker;
+use crate::services::mouse;
+use crate::utils::hotkey::is_emergency_stop_registered;
+
+/// Configured LLM API keys
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyStatus {
+    pub anthropic: bool,
+    pub gemini: bool,
+    /// LOCAL_LLM_BASE_URL is set; the local backend needs no key
+    pub local: bool,
+}
+
+impl ApiKeyStatus {
+    /// Whether at least one model backend can run
+    fn any_backend(&self) -> bool {
+        self.anthropic || self.gemini || self.local
+    }
+}
+
+/// Readiness report of all subsystems
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemHealth {
+    /// True when no issues were found
+    pub healthy: bool,
+    pub permissions: PermissionStatus,
+    pub monitor_count: usize,
+    /// The input device (enigo) could be initialized
+    pub input_ready: bool,
+    /// The emergency stop hotkey (Shift+Escape) is registered
+    pub hotkey_registered: bool,
+    pub database_ready: bool,
+    pub api_keys: ApiKeyStatus,
+    /// Human-readable description of each failed check
+    pub issues: Vec<String>,
+}
+
+/// Check all subsystems and report their status
+#[tauri::command]
+pub async fn system_health(app: AppHandle) -> Result<SystemHealth, IpcError> {
+    let mut issues = Vec::new();
+
+    let permissions = check_permissions();
+    if !permissions.screen_recording {
+        issues.push("Screen recording permission is not granted".to_string());
+    }
+    if !permissions.accessibility {
+        issues.push("Accessibility (input) permission is not granted".to_string());
+    }
+    if let Some(linux) = &permissions.linux {
+        issues.extend(linux.issues.iter().cloned());
+    }
+
+    let monitors = tauri::async_runtime::spawn_blocking(list_monitors)
+        .await
+        .map_err(|e| XenotesterError::Internal(format!("Health check task failed: {}", e)))?;
+    let monitor_count = match monitors {
+        Ok(monitors) if monitors.is_empty() => {
+            issues.push("No monitors detected".to_string());
+            0
+        }
+        Ok(monitors) => monitors.len(),
+        Err(e) => {
+            issues.push(format!("Monitor enumeration failed: {}", e));
+            0
+        }
+    };
+
+    // Reading the cursor position initializes the shared input device
+    let input_ready = match input_worker::submit(mouse::get_position).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) | Err(e) => {
+            issues.push(format!("Input device unavailable: {}", e));
+            false
+        }
+    };
+
+    let hotkey_registered = is_emergency_stop_registered();
+    if !hotkey_registered {
+        issues.push("Emergency stop hotkey (Shift+Escape) is not registered".to_string());
+    }
+
+    let database_ready = match get_pool(&app).await {
+        Ok(pool) => match sqlx::query("SELECT 1").execute(&pool).await {
+            Ok(_) => true,
+            Err(e) => {
+                issues.push(format!("Database query failed: {}", e));
+                false
+            }
+        },
+        Err(e) => {
+            issues.push(e.to_string());
+            false
+        }
+    };
+
+    let api_keys = ApiKeyStatus {
+        anthropic: is_api_key_configured("anthropic".to_string()),
+        gemini: is_api_key_configured("gemini".to_string()),
+        local: env::var_os("LOCAL_LLM_BASE_URL").is_some_and(|url| !url.is_empty()),
+    };
+    if !api_keys.any_backend() {
+        issues.push(
+            "No model backend is configured (set ANTHROPIC_API_KEY, GEMINI_API_KEY or \
+             LOCAL_LLM_BASE_URL)"
+                .to_string(),
+        );
+    }
+
+    Ok(SystemHealth {
+        healthy: issues.is_empty(),
+        permissions,
+        monitor_count,
+        input_ready,
+        hotkey_registered,
+        database_ready,
+        api_keys,
+        issues,
+    })
+}
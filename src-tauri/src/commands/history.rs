@@ -0,0 +1,131 @@
+//! Run history commands
+//!
+//! Persist scenario executions and step results to SQLite so history views
+//! and flaky-step analysis don't need their own storage in the frontend.
+//! Input commands executed between `start_run` and `finish_run` are recorded
+//! in that run's action log, and Do Not Disturb is on if so configured.
+
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+use crate::commands::artifacts::writable_roots;
+use crate::commands::focus_mode;
+use crate::error::IpcError;
+use crate::services::action_log::{self, ActionLog};
+use crate::services::database::get_pool;
+use crate::services::report::{self, ReportFormat};
+use crate::services::run_analytics::{self, FlakinessReport, RunSummary};
+use crate::services::run_history::{self, RunStatus, StepResultInput};
+use crate::state::AppState;
+use crate::utils::sandbox::resolve_within;
+
+/// Start recording a scenario run
+/// Returns the new run ID to pass to subsequent history commands
+#[tauri::command]
+pub async fn start_run(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    scenario_id: String,
+    scenario_title: String,
+) -> Result<String, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    let run_id = run_history::start_run(&pool, &scenario_id, &scenario_title)
+        .await
+        .map_err(IpcError::from)?;
+
+    *state.active_run.lock().map_err(IpcError::from)? = Some(run_id.clone());
+    focus_mode::enable_for_run(state.focus_mode.clone()).await;
+    Ok(run_id)
+}
+
+/// Record the result of a single step within a run
+#[tauri::command]
+pub async fn record_step_result(
+    app: AppHandle,
+    run_id: String,
+    step: StepResultInput,
+) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    run_history::record_step_result(&pool, &run_id, &step)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Mark a run as finished with its final status
+#[tauri::command]
+pub async fn finish_run(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    run_id: String,
+    status: RunStatus,
+    error_message: Option<String>,
+) -> Result<(), IpcError> {
+    if let Ok(mut active_run) = state.active_run.lock() {
+        if active_run.as_deref() == Some(run_id.as_str()) {
+            *active_run = None;
+            if let Ok(mut variables) = state.run_variables.lock() {
+                variables.clear();
+            }
+        }
+    }
+    state.governor.finish_run(&run_id);
+    focus_mode::restore_after_run(state.focus_mode.clone()).await;
+
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    run_history::finish_run(&pool, &run_id, status, error_message.as_deref())
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Get the input actions executed during a run, with the result of the tamper check
+/// Without `run_id`, returns the actions executed outside any run
+#[tauri::command]
+pub async fn get_action_log(app: AppHandle, run_id: Option<String>) -> Result<ActionLog, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    action_log::get_action_log(&pool, run_id.as_deref())
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Export a run as a JUnit XML ("junit") or standalone HTML ("html") report to `path`
+/// HTML reports of failed runs include the frames leading up to the failure
+/// that are still in the capture history.
+/// Only paths inside the app data `artifacts` or `exports` directory or
+/// `XENOTESTER_ARTIFACT_ROOT` are accepted.
+#[tauri::command]
+pub async fn export_run_report(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    run_id: String,
+    format: ReportFormat,
+    path: String,
+) -> Result<(), IpcError> {
+    let path = resolve_within(Path::new(&path), &writable_roots(&app)?).map_err(IpcError::from)?;
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    report::export_run_report(&pool, &run_id, format, &path, Some(&state.capture_history))
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Get the step statistics of a run: step counts, retries, match confidence,
+/// the slowest steps, and the flakiest steps of its scenario across recent runs
+#[tauri::command]
+pub async fn get_run_summary(app: AppHandle, run_id: String) -> Result<RunSummary, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    run_analytics::get_run_summary(&pool, &run_id)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Rank the steps of a scenario that failed intermittently, needed retries, or
+/// matched with low or varying confidence across its recent runs
+#[tauri::command]
+pub async fn analyze_flakiness(
+    app: AppHandle,
+    scenario_id: String,
+) -> Result<FlakinessReport, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    run_analytics::analyze_flakiness(&pool, &scenario_id)
+        .await
+        .map_err(IpcError::from)
+}
@@ -1,152 +1,722 @@
 //! Input operation commands (mouse, keyboard)
 //!
-//! All input commands are async and use `spawn_blocking` to prevent UI blocking.
+//! Mouse and keyboard operations are submitted to the input worker thread, which
+//! runs them one at a time so concurrent calls cannot interleave their events.
 //! Mouse operations include intentional delays (thread::sleep) for reliable input,
 //! which would block the Tauri main thread if run synchronously.
+//! Each command observes the run token given as `token_id` (from `create_run_token`),
+//! or the shared stop token when omitted. Executed commands are recorded in the
+//! action log (see `services::action_log`).
 
-use crate::services::keyboard;
-use crate::services::mouse::{self, MouseButton, ScrollDirection};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tracing::warn;
 
-/// Move mouse to absolute position
+use crate::error::{IpcError, XenotesterError};
+use crate::services::action_log::{self, ActionRecord};
+use crate::services::capture::find_monitor_at;
+use crate::services::database::get_pool;
+use crate::services::governor::ActionGuard;
+use crate::services::input_player;
+use crate::services::input_recorder::InputScript;
+use crate::services::input_worker;
+use crate::services::keyboard::{self, KeyName, KeyboardLayout, TypingOptions};
+use crate::services::mouse::{self, MouseButton, MovePath, Point, ScrollDirection};
+use crate::services::retry::{with_retry, RetryPolicy};
+use crate::services::screen_check::{
+    find_template_on_screen, ScreenMatch, Verification, DEFAULT_CONFIDENCE_THRESHOLD,
+};
+use crate::services::template_matcher::{Anchor, MatchTarget};
+use crate::state::AppState;
+
+/// Current cursor position
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MousePosition {
+    pub x: i32,
+    pub y: i32,
+    /// ID of the monitor containing the cursor (None if it is outside all known monitors)
+    pub monitor_id: Option<u32>,
+}
+
+/// Run an input operation on the input worker and record it in the action log
+/// under the active run. Actions refused by the safety governor or declined in
+/// a confirmation are not run but are still recorded. Failing to record only
+/// logs a warning.
+pub(crate) async fn submit_logged<T: Send + 'static>(
+    app: &AppHandle,
+    state: &AppState,
+    token_id: Option<&str>,
+    record: ActionRecord,
+    operation: impl FnOnce() -> Result<T, IpcError> + Send + 'static,
+) -> Result<T, IpcError> {
+    let guard = state.action_guard();
+    let result = match check_action(&guard, state, token_id, &record).await {
+        Ok(()) => input_worker::submit(operation)
+            .await
+            .map_err(IpcError::from)
+            .and_then(|result| result),
+        Err(e) => Err(e),
+    };
+
+    log_action(app, guard.run_id.as_deref(), &record, &result).await;
+    result
+}
+
+/// Record an action's outcome in the action log; failing to record only logs a warning
+async fn log_action<T>(
+    app: &AppHandle,
+    run_id: Option<&str>,
+    record: &ActionRecord,
+    result: &Result<T, IpcError>,
+) {
+    let error = result.as_ref().err().map(|e| e.detail.as_str());
+    let recorded = match get_pool(app).await {
+        Ok(pool) => action_log::record_action(&pool, run_id, record, error).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = recorded {
+        warn!(
+            "Failed to record {} in the action log: {}",
+            record.action, e
+        );
+    }
+}
+
+/// Admit an action through the guard, waiting for approval if it needs one
+async fn check_action(
+    guard: &ActionGuard,
+    state: &AppState,
+    token_id: Option<&str>,
+    record: &ActionRecord,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id)?;
+    let keys = record
+        .details
+        .get("keys")
+        .or_else(|| record.details.get("key"))
+        .and_then(Value::as_str);
+    guard
+        .check(record.action, record.position, keys, &cancel)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Get current absolute cursor position and the monitor it is on
 #[tauri::command]
-pub async fn mouse_move(x: i32, y: i32) -> Result<(), String> {
+pub async fn get_mouse_position() -> Result<MousePosition, IpcError> {
     tauri::async_runtime::spawn_blocking(move || {
-        mouse::move_mouse(x, y).map_err(|e| e.to_string())
+        let (x, y) = mouse::get_position().map_err(IpcError::from)?;
+
+        let monitor_id = find_monitor_at(x, y)
+            .map_err(IpcError::from)?
+            .map(|m| m.id);
+
+        Ok(MousePosition { x, y, monitor_id })
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Input task failed: {}", e)))?
+}
+
+/// Move mouse to absolute position
+#[tauri::command]
+pub async fn mouse_move(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("mouse_move", Some((x, y)), json!({}));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::move_mouse(x, y, &cancel).map_err(IpcError::from)
+    })
+    .await
+}
+
+/// Move mouse to absolute position along an interpolated path
+/// path: "linear" or "bezier" (default)
+#[tauri::command]
+pub async fn mouse_move_smooth(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    duration_ms: u64,
+    path: Option<String>,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new(
+        "mouse_move_smooth",
+        Some((x, y)),
+        json!({ "durationMs": duration_ms, "path": path }),
+    );
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        let move_path = match path.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("bezier") => MovePath::Bezier,
+            Some("linear") => MovePath::Linear,
+            Some(other) => {
+                return Err(XenotesterError::InvalidArgument(format!(
+                    "Invalid move path: {}",
+                    other
+                ))
+                .into())
+            }
+        };
+
+        mouse::move_mouse_smooth(x, y, duration_ms, move_path, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Left click at position
 #[tauri::command]
-pub async fn left_click(x: i32, y: i32) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        mouse::click(x, y, MouseButton::Left).map_err(|e| e.to_string())
+pub async fn left_click(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("left_click", Some((x, y)), json!({}));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::click(x, y, MouseButton::Left, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Right click at position
 #[tauri::command]
-pub async fn right_click(x: i32, y: i32) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        mouse::click(x, y, MouseButton::Right).map_err(|e| e.to_string())
+pub async fn right_click(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("right_click", Some((x, y)), json!({}));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::click(x, y, MouseButton::Right, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Middle click at position
 #[tauri::command]
-pub async fn middle_click(x: i32, y: i32) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        mouse::click(x, y, MouseButton::Middle).map_err(|e| e.to_string())
+pub async fn middle_click(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("middle_click", Some((x, y)), json!({}));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::click(x, y, MouseButton::Middle, &cancel).map_err(IpcError::from)
+    })
+    .await
+}
+
+/// Click any mouse button at position
+/// button: "left", "right", "middle", "back" (button4) or "forward" (button5)
+#[tauri::command]
+pub async fn click_button(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    button: String,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let mouse_button = MouseButton::from_name(&button).ok_or_else(|| {
+        XenotesterError::InvalidArgument(format!("Invalid mouse button: {}", button))
+    })?;
+
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("click_button", Some((x, y)), json!({ "button": button }));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::click(x, y, mouse_button, &cancel).map_err(IpcError::from)
+    })
+    .await
+}
+
+/// Click with bounded retries
+/// button: as for `click_button` (default "left")
+/// verify: template or pixel check that must pass `retry.settleMs` after each click;
+/// without it only failing clicks are retried. Returns the number of attempts made.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn click_with_retry(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    button: Option<String>,
+    retry: Option<RetryPolicy>,
+    verify: Option<Verification>,
+    token_id: Option<String>,
+) -> Result<u32, IpcError> {
+    let mouse_button = match button.as_deref() {
+        None => MouseButton::Left,
+        Some(name) => {
+            MouseButton::from_name(name).ok_or_else(|| {
+                XenotesterError::InvalidArgument(format!("Invalid mouse button: {}", name))
+            })?
+        }
+    };
+    let policy = retry.unwrap_or_default();
+
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new(
+        "click_with_retry",
+        Some((x, y)),
+        json!({ "button": button, "retries": policy.retries, "verified": verify.is_some() }),
+    );
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        with_retry(&policy, &cancel, |_| {
+            mouse::click(x, y, mouse_button, &cancel)?;
+            if let Some(verification) = &verify {
+                cancel.sleep(Duration::from_millis(policy.settle_ms))?;
+                if !verification.check()? {
+                    return Err(XenotesterError::InputError(
+                        "Verification failed after click".to_string(),
+                    ));
+                }
+            }
+            Ok(())
+        })
+        .map(|((), attempts)| attempts)
+        .map_err(IpcError::from)
+    })
+    .await
+}
+
+/// Template that was found and clicked
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateClick {
+    #[serde(flatten)]
+    pub screen_match: ScreenMatch,
+    /// Clicked position in screen coordinates (anchor plus offset)
+    pub click_x: i32,
+    pub click_y: i32,
+}
+
+/// Capture the screen, find a template and click it in one call
+/// template_base64: original-size image data, as it appears on screen
+/// button: as for `click_button` (default "left")
+/// anchor: point of the matched rectangle to click relative to (default center)
+/// offset: added to the anchor, in screen points
+/// Fails without clicking when the template is not found; a click point in a
+/// danger zone waits for approval (see `set_confirmation_rules`).
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn click_template(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    template_base64: String,
+    confidence_threshold: Option<f32>,
+    button: Option<String>,
+    anchor: Option<Anchor>,
+    offset: Option<Point>,
+    monitor_id: Option<u32>,
+    token_id: Option<String>,
+) -> Result<TemplateClick, IpcError> {
+    let mouse_button = match button.as_deref() {
+        None => MouseButton::Left,
+        Some(name) => {
+            MouseButton::from_name(name).ok_or_else(|| {
+                XenotesterError::InvalidArgument(format!("Invalid mouse button: {}", name))
+            })?
+        }
+    };
+    let threshold = confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD);
+    let offset = offset.unwrap_or(Point { x: 0, y: 0 });
+    let target = MatchTarget {
+        anchor: anchor.unwrap_or_default(),
+        offset_x: offset.x,
+        offset_y: offset.y,
+    };
+
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let details = json!({
+        "button": button,
+        "anchor": target.anchor,
+        "offset": [offset.x, offset.y],
+        "monitorId": monitor_id,
+    });
+
+    // Find the click point first, so it is checked and approved like any other click
+    let find_cancel = cancel.clone();
+    let found = input_worker::submit(move || {
+        find_cancel.check()?;
+        let screen_match = find_template_on_screen(&template_base64, monitor_id, threshold)?;
+        let (Some(x), Some(y)) = (screen_match.x, screen_match.y) else {
+            return Err(XenotesterError::NotFound(format!(
+                "template on screen (best confidence {:.2})",
+                screen_match.confidence.unwrap_or(0.0)
+            )));
+        };
+        let size = (screen_match.width, screen_match.height);
+        Ok((screen_match, target.resolve((x, y), size)))
+    })
+    .await
+    .and_then(|result| result)
+    .map_err(IpcError::from);
+
+    let (screen_match, (click_x, click_y)) = match found {
+        Ok(found) => found,
+        Err(e) => {
+            let record = ActionRecord::new("click_template", None, details);
+            let result = Err(e);
+            log_action(&app, state.active_run().as_deref(), &record, &result).await;
+            return result;
+        }
+    };
+
+    let record = ActionRecord::new("click_template", Some((click_x, click_y)), details);
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::click(click_x, click_y, mouse_button, &cancel).map_err(IpcError::from)?;
+        Ok(TemplateClick {
+            screen_match,
+            click_x,
+            click_y,
+        })
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Double click at position
 #[tauri::command]
-pub async fn double_click(x: i32, y: i32) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        mouse::double_click(x, y).map_err(|e| e.to_string())
+pub async fn double_click(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("double_click", Some((x, y)), json!({}));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::double_click(x, y, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Triple click at position
 #[tauri::command]
-pub async fn triple_click(x: i32, y: i32) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        mouse::triple_click(x, y).map_err(|e| e.to_string())
+pub async fn triple_click(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("triple_click", Some((x, y)), json!({}));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::triple_click(x, y, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Mouse down (press without release)
 #[tauri::command]
-pub async fn left_mouse_down(x: i32, y: i32) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        mouse::mouse_down(x, y, MouseButton::Left).map_err(|e| e.to_string())
+pub async fn left_mouse_down(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("left_mouse_down", Some((x, y)), json!({}));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::mouse_down(x, y, MouseButton::Left, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Mouse up (release)
 #[tauri::command]
-pub async fn left_mouse_up(x: i32, y: i32) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        mouse::mouse_up(x, y, MouseButton::Left).map_err(|e| e.to_string())
+pub async fn left_mouse_up(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("left_mouse_up", Some((x, y)), json!({}));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::mouse_up(x, y, MouseButton::Left, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Drag from start to end position
+/// steps: optional number of intermediate moves while the button is held
+/// Both ends must be allowed by the safety governor.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn left_click_drag(
+    app: AppHandle,
+    state: State<'_, AppState>,
     start_x: i32,
     start_y: i32,
     end_x: i32,
     end_y: i32,
-) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        mouse::drag(start_x, start_y, end_x, end_y).map_err(|e| e.to_string())
+    steps: Option<u32>,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new(
+        "left_click_drag",
+        Some((start_x, start_y)),
+        json!({ "endX": end_x, "endY": end_y, "steps": steps }),
+    );
+    let governor = state.governor.clone();
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        governor
+            .check_position(end_x, end_y)
+            .map_err(IpcError::from)?;
+        match steps {
+            Some(steps) if steps > 1 => {
+                mouse::drag_smooth(start_x, start_y, end_x, end_y, steps, &cancel)
+            }
+            _ => mouse::drag(start_x, start_y, end_x, end_y, &cancel),
+        }
+        .map_err(IpcError::from)
+    })
+    .await
+}
+
+/// Drag through a sequence of waypoints (button held from first to last point)
+/// Both the first and the last point must be allowed by the safety governor.
+#[tauri::command]
+pub async fn left_click_drag_path(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    points: Vec<Point>,
+    step_delay_ms: Option<u64>,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let step_delay_ms = step_delay_ms.unwrap_or(mouse::SMOOTH_MOVE_INTERVAL_MS);
+
+    let record = ActionRecord::new(
+        "left_click_drag_path",
+        points.first().map(|p| (p.x, p.y)),
+        json!({ "points": points.iter().map(|p| [p.x, p.y]).collect::<Vec<_>>() }),
+    );
+    let governor = state.governor.clone();
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        if let Some(end) = points.last() {
+            governor
+                .check_position(end.x, end.y)
+                .map_err(IpcError::from)?;
+        }
+        mouse::drag_path(&points, step_delay_ms, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Scroll at position
 /// direction: "up", "down", "left", "right"
 #[tauri::command]
-pub async fn scroll(x: i32, y: i32, direction: String, amount: i32) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
+pub async fn scroll(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    direction: String,
+    amount: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new(
+        "scroll",
+        Some((x, y)),
+        json!({ "direction": direction, "amount": amount }),
+    );
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
         let dir = match direction.to_lowercase().as_str() {
             "up" => ScrollDirection::Up,
             "down" => ScrollDirection::Down,
             "left" => ScrollDirection::Left,
             "right" => ScrollDirection::Right,
-            _ => return Err(format!("Invalid scroll direction: {}", direction)),
+            _ => {
+                return Err(XenotesterError::InvalidArgument(format!(
+                    "Invalid scroll direction: {}",
+                    direction
+                ))
+                .into())
+            }
         };
 
-        mouse::scroll(x, y, dir, amount).map_err(|e| e.to_string())
+        mouse::scroll(x, y, dir, amount, &cancel).map_err(IpcError::from)
+    })
+    .await
+}
+
+/// Scroll at position by a pixel distance with smooth incremental wheel events
+/// dx / dy: positive scrolls right / down
+#[tauri::command]
+pub async fn scroll_pixels(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    x: i32,
+    y: i32,
+    dx: i32,
+    dy: i32,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("scroll_pixels", Some((x, y)), json!({ "dx": dx, "dy": dy }));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        mouse::scroll_pixels(x, y, dx, dy, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Type text
+/// layout: "us", "de" or "jp" to override the detected keyboard layout
+/// chars_per_second / per_char_delay_ms: slow typing down for apps that drop fast input
 #[tauri::command]
-pub async fn type_text(text: String) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        keyboard::type_text(&text).map_err(|e| e.to_string())
+pub async fn type_text(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    text: String,
+    layout: Option<KeyboardLayout>,
+    chars_per_second: Option<f64>,
+    per_char_delay_ms: Option<u64>,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    if let Some(cps) = chars_per_second {
+        if !(cps > 0.0 && cps.is_finite()) {
+            return Err(XenotesterError::InvalidArgument(format!(
+                "chars_per_second must be positive, got {}",
+                cps
+            ))
+            .into());
+        }
+    }
+
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let options = TypingOptions {
+        layout,
+        chars_per_second,
+        per_char_delay_ms,
+    };
+    let record = ActionRecord::new("type_text", None, json!({ "text": text }));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        keyboard::type_text(&text, &options, &cancel).map_err(IpcError::from)
+    })
+    .await
+}
+
+/// Enter text through the clipboard and the paste shortcut
+/// Use for CJK (IME-dependent) text and emoji; restore_clipboard puts the previous
+/// clipboard text back afterwards
+#[tauri::command]
+pub async fn type_text_paste(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    text: String,
+    restore_clipboard: bool,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("type_text_paste", None, json!({ "text": text }));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        keyboard::paste_text(&text, restore_clipboard, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Press key combination (e.g., "ctrl+s", "cmd+shift+p")
+/// layout: "us", "de" or "jp" to override the detected keyboard layout
 #[tauri::command]
-pub async fn key(keys: String) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        keyboard::key_combination(&keys).map_err(|e| e.to_string())
+pub async fn key(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    keys: String,
+    layout: Option<KeyboardLayout>,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("key", None, json!({ "keys": keys }));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        keyboard::key_combination(&keys, layout, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
 }
 
 /// Hold key (press or release)
 #[tauri::command]
-pub async fn hold_key(key_name: String, hold: bool) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        keyboard::hold_key(&key_name, hold).map_err(|e| e.to_string())
+pub async fn hold_key(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    key_name: String,
+    hold: bool,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new("hold_key", None, json!({ "key": key_name, "hold": hold }));
+    submit_logged(&app, &state, token_id.as_deref(), record, move || {
+        keyboard::hold_key(&key_name, hold, &cancel).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Input task failed: {}", e))?
+}
+
+/// Replay a recorded input script
+/// speed: timing multiplier (default 1.0; 2.0 plays twice as fast)
+/// Each replayed action is admitted by the safety governor on its own; the
+/// script is recorded in the action log as one entry.
+#[tauri::command]
+pub async fn play_input_script(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    script: InputScript,
+    speed: Option<f64>,
+    token_id: Option<String>,
+) -> Result<(), IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let record = ActionRecord::new(
+        "play_input_script",
+        None,
+        json!({ "actionCount": script.actions.len(), "speed": speed }),
+    );
+    let guard = state.action_guard();
+    let result = match guard.user_activity.wait_until_idle(&cancel).await {
+        Ok(()) => {
+            let (governor, run_id) = (guard.governor.clone(), guard.run_id.clone());
+            input_worker::submit(move || {
+                let speed = speed.unwrap_or(1.0);
+                input_player::play(&script, speed, Some(&governor), run_id.as_deref(), &cancel)
+                    .map_err(IpcError::from)
+            })
+            .await
+            .map_err(IpcError::from)
+            .and_then(|result| result)
+        }
+        Err(e) => Err(e.into()),
+    };
+
+    log_action(&app, guard.run_id.as_deref(), &record, &result).await;
+    result
+}
+
+/// List key names accepted by `key` and `hold_key` on this platform
+/// This is a lightweight operation, no need for spawn_blocking
+#[tauri::command]
+pub fn list_supported_keys() -> Vec<KeyName> {
+    keyboard::supported_keys()
 }
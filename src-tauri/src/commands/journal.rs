@@ -0,0 +1,104 @@
+//! Run journal commands
+//!
+//! Report the backend runs a crash or forced quit cut short (see
+//! `services::run_journal`) and resume them at the step they were at.
+//! Journals live in the app data `run_journal` directory.
+
+use tauri::{AppHandle, Manager, State};
+use tracing::warn;
+
+use crate::commands::scenario;
+use crate::error::{IpcError, XenotesterError};
+use crate::services::database::get_pool;
+use crate::services::run_journal::{InterruptedRun, RunJournal};
+use crate::services::runner::{self, ScenarioRunResult};
+use crate::state::AppState;
+
+/// Journal of the app's backend runs; None when the app data directory is unknown
+pub fn run_journal(app: &AppHandle) -> Option<RunJournal> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| RunJournal::new(dir.join("run_journal")))
+        .map_err(|e| warn!("Runs will not be journaled: {}", e))
+        .ok()
+}
+
+fn require_journal(app: &AppHandle) -> Result<RunJournal, IpcError> {
+    run_journal(app).ok_or_else(|| {
+        XenotesterError::ConfigError("The run journal is not available".to_string()).into()
+    })
+}
+
+/// List the runs that were interrupted, with the step each was at
+#[tauri::command]
+pub fn get_interrupted_runs(app: AppHandle) -> Result<Vec<InterruptedRun>, IpcError> {
+    require_journal(&app)?
+        .interrupted_runs()
+        .map_err(IpcError::from)
+}
+
+/// Run an interrupted run's scenario again from the step it was interrupted at
+/// The resumed run is recorded as a new run; the interrupted one is closed as failed.
+#[tauri::command]
+pub async fn resume_run(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    run_id: String,
+    token_id: Option<String>,
+) -> Result<ScenarioRunResult, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let journal = require_journal(&app)?;
+    let record = journal.load(&run_id).map_err(IpcError::from)?;
+    let pool = get_pool(&app).await.ok();
+
+    let start = record.run.step_index;
+    let steps = record.scenario.steps.len();
+    if steps > 0 && start >= steps {
+        journal
+            .close_interrupted(
+                pool.as_ref(),
+                &record.run,
+                "Interrupted after its last step",
+            )
+            .await
+            .map_err(IpcError::from)?;
+        return Err(XenotesterError::InvalidArgument(format!(
+            "Run {} had no steps left to resume",
+            run_id
+        ))
+        .into());
+    }
+    journal
+        .close_interrupted(
+            pool.as_ref(),
+            &record.run,
+            &format!("Interrupted at step {}, resumed", start + 1),
+        )
+        .await
+        .map_err(IpcError::from)?;
+
+    let options = scenario::run_options(
+        &app,
+        Some(record.variables),
+        Some(record.model),
+        Some(record.max_iterations),
+    )
+    .await;
+    Ok(runner::run_scenario_from(&record.scenario, &options, pool.as_ref(), &cancel, start).await)
+}
+
+/// Dismiss an interrupted run without resuming it; it is closed as failed
+#[tauri::command]
+pub async fn discard_interrupted_run(app: AppHandle, run_id: String) -> Result<(), IpcError> {
+    let journal = require_journal(&app)?;
+    let record = journal.load(&run_id).map_err(IpcError::from)?;
+    let pool = get_pool(&app).await.ok();
+    journal
+        .close_interrupted(
+            pool.as_ref(),
+            &record.run,
+            &format!("Interrupted at step {}", record.run.step_index + 1),
+        )
+        .await
+        .map_err(IpcError::from)
+}
@@ -0,0 +1,28 @@
+//! Log inspection commands
+
+use crate::error::IpcError;
+use crate::utils::logging::{self, LogEntry};
+
+/// Number of entries returned by `get_recent_logs` when no limit is given
+const DEFAULT_LOG_LIMIT: usize = 200;
+
+/// Get the most recent log entries at or above `level` (default "info"), oldest first
+#[tauri::command]
+pub fn get_recent_logs(
+    level: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LogEntry>, IpcError> {
+    let min_level =
+        logging::parse_level(level.as_deref().unwrap_or("info")).map_err(IpcError::from)?;
+    Ok(logging::recent_logs(
+        min_level,
+        limit.unwrap_or(DEFAULT_LOG_LIMIT),
+    ))
+}
+
+/// Change the log level ("error", "warn", "info", "debug" or "trace")
+#[tauri::command]
+pub fn set_log_level(level: String) -> Result<(), IpcError> {
+    let level = logging::parse_level(&level).map_err(IpcError::from)?;
+    logging::set_level(level).map_err(IpcError::from)
+}
@@ -1,9 +1,35 @@
 //! IPC command modules
 
+pub mod accessibility;
+pub mod agent;
+pub mod artifacts;
+pub mod assert;
+pub mod baseline;
 pub mod config;
 pub mod control;
+pub mod coords;
+pub mod debugger;
+pub mod email;
+pub mod environment;
+pub mod focus_mode;
+pub mod health;
+pub mod history;
 pub mod input;
+pub mod journal;
+pub mod logs;
+pub mod oauth;
+pub mod orchestrator;
 pub mod permission;
+pub mod privacy;
+pub mod process;
+pub mod recording;
+pub mod remote;
+pub mod scenario;
+pub mod schema;
 pub mod screenshot;
+pub mod secrets;
+pub mod sync;
 pub mod template_match;
+pub mod usage;
+pub mod variables;
 pub mod webhook;
@@ -0,0 +1,59 @@
+//! OAuth session commands
+//!
+//! The frontend hands the Supabase session over once after sign-in; from then
+//! on the backend stores it encrypted and refreshes it (see `services::oauth`).
+
+use tauri::AppHandle;
+
+use crate::commands::config::get_supabase_config;
+use crate::error::IpcError;
+use crate::services::database::get_pool;
+use crate::services::oauth::{self, OAuthTokens};
+
+/// Store the session after sign-in, replacing any previous one
+/// Pass either `expires_at` (seconds since the Unix epoch) or `expires_in` (seconds).
+#[tauri::command]
+pub async fn store_oauth_tokens(
+    app: AppHandle,
+    access_token: String,
+    refresh_token: String,
+    expires_at: Option<i64>,
+    expires_in: Option<i64>,
+) -> Result<(), IpcError> {
+    let tokens = OAuthTokens {
+        access_token,
+        refresh_token,
+        expires_at: oauth::expiry(expires_at, expires_in)?,
+    };
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    oauth::store_tokens(&pool, &tokens)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Refresh the stored session now, returning the new tokens
+#[tauri::command]
+pub async fn refresh_access_token(app: AppHandle) -> Result<OAuthTokens, IpcError> {
+    let config = get_supabase_config()?;
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    oauth::refresh_access_token(&pool, &config.url, &config.anon_key)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Access token of the stored session, refreshed first when about to expire
+#[tauri::command]
+pub async fn get_valid_access_token(app: AppHandle) -> Result<String, IpcError> {
+    let config = get_supabase_config()?;
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    oauth::get_valid_access_token(&pool, &config.url, &config.anon_key)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Forget the stored session (sign-out); returns false if there was none
+#[tauri::command]
+pub async fn clear_oauth_tokens(app: AppHandle) -> Result<bool, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    oauth::clear_tokens(&pool).await.map_err(IpcError::from)
+}
@@ -0,0 +1,149 @@
+//! Orchestration bridge commands
+//!
+//! Configure and run the bridge of `services::orchestrator`, which executes
+//! scenario jobs queued on a central server. The bearer token is kept in the
+//! encrypted secret store, never in the settings table.
+
+use tauri::{AppHandle, Manager, State};
+use tracing::warn;
+
+use crate::commands::agent::DEFAULT_MAX_ITERATIONS;
+use crate::commands::artifacts::artifacts_dir;
+use crate::commands::journal::run_journal;
+use crate::commands::webhook::load_notifier;
+use crate::error::{IpcError, XenotesterError};
+use crate::services::database::get_pool;
+use crate::services::llm::anthropic::ModelConfig;
+use crate::services::orchestrator::{
+    self, BridgeContext, BridgeStatus, OrchestratorConfig, TOKEN_SECRET,
+};
+use crate::services::runner::{self, RunOptions};
+use crate::services::secrets;
+use crate::state::AppState;
+
+/// Get the stored orchestrator configuration
+#[tauri::command]
+pub async fn get_orchestrator_config(
+    app: AppHandle,
+) -> Result<Option<OrchestratorConfig>, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    orchestrator::load_config(&pool)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Store the orchestrator configuration (applied on the next start)
+/// `token` replaces the stored bearer token; an empty string removes it.
+#[tauri::command]
+pub async fn set_orchestrator_config(
+    app: AppHandle,
+    config: OrchestratorConfig,
+    token: Option<String>,
+) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    orchestrator::save_config(&pool, &config)
+        .await
+        .map_err(IpcError::from)?;
+
+    match token {
+        Some(token) if token.is_empty() => {
+            secrets::delete_internal(&pool, TOKEN_SECRET)
+                .await
+                .map_err(IpcError::from)?;
+        }
+        Some(token) => secrets::store_internal(&pool, TOKEN_SECRET, &token)
+            .await
+            .map_err(IpcError::from)?,
+        None => {}
+    }
+    Ok(())
+}
+
+/// Register with the configured orchestrator and start executing its jobs
+/// Jobs observe the emergency stop like any other run.
+#[tauri::command]
+pub async fn start_orchestrator_bridge(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BridgeStatus, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    let config = orchestrator::load_config(&pool)
+        .await
+        .map_err(IpcError::from)?
+        .ok_or_else(|| XenotesterError::ConfigError("No orchestrator is configured".to_string()))?;
+    start_bridge(&app, &state, config).await
+}
+
+/// Stop polling and cancel the job in progress; returns false if it wasn't running
+#[tauri::command]
+pub fn stop_orchestrator_bridge(state: State<AppState>) -> bool {
+    state.orchestrator.stop()
+}
+
+/// Connection state and job counts of the bridge
+#[tauri::command]
+pub fn get_orchestrator_status(state: State<AppState>) -> BridgeStatus {
+    state.orchestrator.status()
+}
+
+async fn stored_token(pool: &sqlx::SqlitePool) -> Result<Option<String>, IpcError> {
+    secrets::resolve_internal(pool, TOKEN_SECRET)
+        .await
+        .map_err(IpcError::from)
+}
+
+async fn start_bridge(
+    app: &AppHandle,
+    state: &AppState,
+    config: OrchestratorConfig,
+) -> Result<BridgeStatus, IpcError> {
+    let pool = get_pool(app).await.map_err(IpcError::from)?;
+    let token = stored_token(&pool).await?;
+    let timeouts = runner::load_timeouts(&pool).await.map_err(IpcError::from)?;
+    let context = BridgeContext {
+        pool: Some(pool),
+        artifacts_dir: artifacts_dir(app)
+            .map_err(|e| warn!("Artifacts will not be uploaded: {}", e))
+            .ok(),
+        defaults: RunOptions {
+            model_config: ModelConfig::default(),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            variables: Default::default(),
+            notifier: load_notifier(app).await,
+            capture_history: Some(state.capture_history.clone()),
+            journal: run_journal(app),
+            // Jobs run unattended; nobody would resume them
+            debugger: None,
+            timeouts,
+            guard: Some(state.action_guard()),
+        },
+        run_tokens: state.run_tokens.clone(),
+    };
+    state
+        .orchestrator
+        .start(config, token, context)
+        .map_err(IpcError::from)
+}
+
+/// Start the bridge at startup when the stored configuration asks for it
+pub fn start_on_startup(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let config = match get_pool(&app).await {
+            Ok(pool) => orchestrator::load_config(&pool).await,
+            Err(e) => Err(e),
+        };
+        let config = match config {
+            Ok(Some(config)) if config.auto_start => config,
+            Ok(_) => return,
+            Err(e) => {
+                warn!("Failed to load the orchestrator configuration: {}", e);
+                return;
+            }
+        };
+        let state = app.state::<AppState>();
+        if let Err(e) = start_bridge(&app, &state, config).await {
+            warn!("Failed to start the orchestrator bridge: {}", e);
+        }
+    });
+}
@@ -1,4 +1,4 @@
-//! Permission management commands for macOS
+//! Permission management commands for macOS, plus display server checks on Linux
 
 use serde::Serialize;
 
@@ -6,11 +6,33 @@ use serde::Serialize;
 use std::ffi::c_void;
 
 /// Permission status for macOS
+/// On Linux, the flags reflect whether capture and input can work in the current session.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PermissionStatus {
     pub screen_recording: bool,
     pub accessibility: bool,
+    /// Display server details (Linux only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linux: Option<LinuxEnvironment>,
+}
+
+/// Linux session capabilities relevant to capture and input injection
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinuxEnvironment {
+    /// "x11", "wayland" or "unknown"
+    pub display_server: String,
+    /// X11 clients can connect through XWayland (DISPLAY is set in a Wayland session)
+    pub xwayland: bool,
+    /// xdg-desktop-portal ScreenCast interface (screen capture on Wayland)
+    pub screen_cast_portal: bool,
+    /// xdg-desktop-portal RemoteDesktop interface (input injection on Wayland)
+    pub remote_desktop_portal: bool,
+    /// /dev/uinput is writable (kernel-level input injection)
+    pub uinput_access: bool,
+    /// What to fix for capture or input to work
+    pub issues: Vec<String>,
 }
 
 // macOS API bindings for permission checks
@@ -29,7 +51,7 @@ extern "C" {
     fn AXIsProcessTrustedWithOptions(options: *const c_void) -> u8;
 }
 
-/// Check all required permissions (macOS only)
+/// Check all required permissions (macOS) or session capabilities (Linux)
 #[tauri::command]
 pub fn check_permissions() -> PermissionStatus {
     #[cfg(target_os = "macos")]
@@ -37,15 +59,28 @@ pub fn check_permissions() -> PermissionStatus {
         PermissionStatus {
             screen_recording: check_screen_recording(),
             accessibility: check_accessibility(),
+            linux: None,
         }
     }
 
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let environment = check_linux_environment();
+        let x11 = environment.display_server == "x11";
+        PermissionStatus {
+            screen_recording: x11 || environment.screen_cast_portal,
+            accessibility: x11 || environment.remote_desktop_portal || environment.uinput_access,
+            linux: Some(environment),
+        }
+    }
+
+    #[cfg(target_os = "windows")]
     {
-        // On non-macOS platforms, assume permissions are granted
+        // No permissions are needed on Windows
         PermissionStatus {
             screen_recording: true,
             accessibility: true,
+            linux: None,
         }
     }
 }
@@ -128,3 +163,74 @@ fn check_accessibility() -> bool {
     // Returns u8 (0 = false, non-zero = true)
     unsafe { AXIsProcessTrusted() != 0 }
 }
+
+/// Detect the display server and the capture/input mechanisms available to it
+#[cfg(all(unix, not(target_os = "macos")))]
+fn check_linux_environment() -> LinuxEnvironment {
+    let session_type = std::env::var("XDG_SESSION_TYPE").unwrap_or_default().to_lowercase();
+    let has_display = std::env::var_os("DISPLAY").is_some();
+    let wayland = session_type == "wayland" || std::env::var_os("WAYLAND_DISPLAY").is_some();
+
+    let display_server = if wayland {
+        "wayland"
+    } else if has_display || session_type == "x11" {
+        "x11"
+    } else {
+        "unknown"
+    };
+
+    let screen_cast_portal = has_portal_interface("org.freedesktop.portal.ScreenCast");
+    let remote_desktop_portal = has_portal_interface("org.freedesktop.portal.RemoteDesktop");
+    let uinput_access = std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/uinput")
+        .is_ok();
+
+    let mut issues = Vec::new();
+    if display_server == "unknown" {
+        issues.push(
+            "No display server found: neither DISPLAY nor WAYLAND_DISPLAY is set".to_string(),
+        );
+    }
+    if wayland && !screen_cast_portal {
+        issues.push(
+            "Screen capture on Wayland needs xdg-desktop-portal with a ScreenCast backend \
+             (xdg-desktop-portal-gnome, -kde or -wlr)"
+                .to_string(),
+        );
+    }
+    if wayland && !remote_desktop_portal && !uinput_access {
+        issues.push(
+            "Input on Wayland needs the RemoteDesktop portal or write access to /dev/uinput \
+             (add the user to the 'input' group or install a udev rule)"
+                .to_string(),
+        );
+    }
+
+    LinuxEnvironment {
+        display_server: display_server.to_string(),
+        xwayland: wayland && has_display,
+        screen_cast_portal,
+        remote_desktop_portal,
+        uinput_access,
+        issues,
+    }
+}
+
+/// Check whether xdg-desktop-portal provides an interface by reading its version property
+#[cfg(all(unix, not(target_os = "macos")))]
+fn has_portal_interface(interface: &str) -> bool {
+    std::process::Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.portal.Desktop",
+            "/org/freedesktop/portal/desktop",
+            "org.freedesktop.DBus.Properties.Get",
+            &format!("string:{}", interface),
+            "string:version",
+        ])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
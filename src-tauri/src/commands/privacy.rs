@@ -0,0 +1,30 @@
+//! Privacy commands (screenshot redaction)
+
+use crate::services::privacy::{self, PrivacyMode, RedactionZone};
+
+/// Get the zones masked in every capture
+#[tauri::command]
+pub fn get_redaction_zones() -> Vec<RedactionZone> {
+    privacy::zones()
+}
+
+/// Set the zones masked in every capture (desktop rectangles in logical points)
+/// mode: "blackout" (default) or "blur"
+#[tauri::command]
+pub fn set_redaction_zones(zones: Vec<RedactionZone>) {
+    privacy::set_zones(zones);
+}
+
+/// Get the automatic PII detection level
+#[tauri::command]
+pub fn get_privacy_mode() -> PrivacyMode {
+    privacy::mode()
+}
+
+/// Set the automatic PII detection level applied on top of the redaction zones
+/// level: "off" (default), "windows" (password manager windows) or "strict"
+/// (also email addresses and card numbers shown in the frontmost app)
+#[tauri::command]
+pub fn set_privacy_mode(level: PrivacyMode) {
+    privacy::set_mode(level);
+}
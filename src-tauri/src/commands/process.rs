@@ -0,0 +1,58 @@
+//! Application launch and process management commands
+
+use std::time::Duration;
+use tauri::State;
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::process;
+use crate::state::AppState;
+
+/// Launch an application (executable path, or on macOS a .app bundle or bundle ID)
+/// Returns the process ID when known
+#[tauri::command]
+pub async fn launch_app(path: String, args: Option<Vec<String>>) -> Result<Option<u32>, IpcError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        process::launch_app(&path, &args.unwrap_or_default()).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Process task failed: {}", e)))?
+}
+
+/// Terminate an application by process ID or name
+/// Returns whether a process was terminated
+#[tauri::command]
+pub async fn terminate_app(pid_or_name: String) -> Result<bool, IpcError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        process::terminate_app(&pid_or_name).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Process task failed: {}", e)))?
+}
+
+/// Check whether an application is running
+#[tauri::command]
+pub async fn is_app_running(name: String) -> Result<bool, IpcError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        process::is_app_running(&name).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Process task failed: {}", e)))?
+}
+
+/// Wait until an application is running
+/// Returns false if it did not start within `timeout_ms`
+#[tauri::command]
+pub async fn wait_for_app(
+    state: State<'_, AppState>,
+    name: String,
+    timeout_ms: u64,
+    token_id: Option<String>,
+) -> Result<bool, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        process::wait_for_app(&name, Duration::from_millis(timeout_ms), &cancel)
+            .map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Process task failed: {}", e)))?
+}
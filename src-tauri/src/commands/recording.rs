@@ -0,0 +1,79 @@
+//! Screen and input recording commands
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::input_recorder::{self, InputScript};
+use crate::services::recorder::{RecordingInfo, RecordingOptions};
+use crate::state::AppState;
+
+/// Start recording the screen into the app data `recordings` directory
+/// The file is named after `run_id` when given, otherwise after the current time
+/// Returns the output path
+#[tauri::command]
+pub async fn start_recording(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    options: Option<RecordingOptions>,
+    run_id: Option<String>,
+) -> Result<String, IpcError> {
+    let options = options.unwrap_or_default();
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| {
+            XenotesterError::Internal(format!("Failed to resolve app data directory: {}", e))
+        })?
+        .join("recordings");
+
+    let stem = match run_id {
+        Some(id) => format!("run-{}", id),
+        None => {
+            let millis = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default();
+            format!("recording-{}", millis)
+        }
+    };
+    let path = dir.join(format!("{}.{}", stem, options.format.extension()));
+
+    let recorder = state.recorder.clone();
+    let result_path = path.to_string_lossy().into_owned();
+    tauri::async_runtime::spawn_blocking(move || {
+        recorder.start(path, options).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Recording task failed: {}", e)))??;
+
+    Ok(result_path)
+}
+
+/// Stop the active recording and return the finished file's details
+#[tauri::command]
+pub async fn stop_recording(state: State<'_, AppState>) -> Result<RecordingInfo, IpcError> {
+    let recorder = state.recorder.clone();
+    // Joining the recorder thread waits for encoding to finish
+    tauri::async_runtime::spawn_blocking(move || recorder.stop().map_err(IpcError::from))
+        .await
+        .map_err(|e| XenotesterError::Internal(format!("Recording task failed: {}", e)))?
+}
+
+/// Path of the active recording, or of the last finished one
+#[tauri::command]
+pub fn get_recording_path(state: State<AppState>) -> Option<String> {
+    state.recorder.current_path()
+}
+
+/// Start recording the user's mouse and keyboard input
+#[tauri::command]
+pub fn start_recording_inputs() -> Result<(), IpcError> {
+    input_recorder::start().map_err(IpcError::from)
+}
+
+/// Stop recording input and return the recorded action script
+#[tauri::command]
+pub fn stop_recording_inputs() -> Result<InputScript, IpcError> {
+    input_recorder::stop().map_err(IpcError::from)
+}
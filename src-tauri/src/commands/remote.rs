@@ -0,0 +1,340 @@
+//! Remote control commands
+//!
+//! Start and stop the localhost server of `services::remote`, and map its
+//! requests onto the Tauri commands, so remote calls get the same run tokens,
+//! input guards and action log as calls from the frontend. Exposed: capture,
+//! input, template matching, run control and the scenario runner.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::template_match::{MonitorScreenshot, TemplateImage};
+use crate::commands::{control, input, scenario, screenshot, template_match};
+use crate::error::{IpcError, XenotesterError};
+use crate::services::baseline::Region;
+use crate::services::database::get_pool;
+use crate::services::image_processor::{ImageEncoding, ResizeQuality};
+use crate::services::keyboard::KeyboardLayout;
+use crate::services::mouse::Point;
+use crate::services::remote::{Dispatcher, RemoteInfo};
+use crate::services::retry::RetryPolicy;
+use crate::services::runner::{self, Scenario, ScenarioRunResult};
+use crate::services::screen_check::Verification;
+use crate::services::template_matcher::{Anchor, MatchOptions};
+use crate::state::AppState;
+
+/// Start the remote control server on localhost
+/// `port` defaults to 47820 (0 picks a free port); a random token is generated
+/// unless one is given. Returns the URL and token clients must use.
+#[tauri::command]
+pub fn start_remote_server(
+    app: AppHandle,
+    state: State<AppState>,
+    port: Option<u16>,
+    token: Option<String>,
+) -> Result<RemoteInfo, IpcError> {
+    let dispatcher: Dispatcher =
+        Arc::new(move |command, args| Box::pin(invoke(app.clone(), command, args)));
+    state
+        .remote
+        .start(port, token, dispatcher)
+        .map_err(IpcError::from)
+}
+
+/// Stop the remote control server; returns false if it wasn't running
+#[tauri::command]
+pub fn stop_remote_server(state: State<AppState>) -> bool {
+    state.remote.stop()
+}
+
+/// URL and token of the running remote control server
+#[tauri::command]
+pub fn get_remote_server(state: State<AppState>) -> Option<RemoteInfo> {
+    state.remote.info()
+}
+
+/// Deserialize the listed arguments from the JSON object and evaluate `$call`
+macro_rules! with_args {
+    ($args:expr, { $($name:ident: $ty:ty),* $(,)? }, $call:expr) => {{
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Args {
+            $($name: $ty,)*
+        }
+        let Args { $($name),* } = serde_json::from_value::<Args>($args)
+            .map_err(|e| format!("Invalid arguments: {}", e))?;
+        to_json($call)
+    }};
+}
+
+fn to_json<T: Serialize>(result: Result<T, IpcError>) -> Result<Value, String> {
+    serde_json::to_value(result.map_err(|e| e.detail)?)
+        .map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+/// Run a remote request as the Tauri command of the same name
+async fn invoke(app: AppHandle, command: String, args: Value) -> Result<Value, String> {
+    let a = app.clone();
+    match command.as_str() {
+        // Capture
+        "get_monitors" => with_args!(args, {}, screenshot::get_monitors()),
+        "capture_screen" => with_args!(args, {
+            encoding: Option<ImageEncoding>,
+            include_cursor: Option<bool>,
+            resize_quality: Option<ResizeQuality>,
+            token_id: Option<String>,
+        }, screenshot::capture_screen(
+            a, app.state(), encoding, include_cursor, resize_quality, token_id
+        ).await),
+        "capture_monitor_by_id" => with_args!(args, {
+            monitor_id: u32,
+            encoding: Option<ImageEncoding>,
+            include_cursor: Option<bool>,
+            resize_quality: Option<ResizeQuality>,
+            token_id: Option<String>,
+        }, screenshot::capture_monitor_by_id(
+            a, app.state(), monitor_id, encoding, include_cursor, resize_quality, token_id
+        ).await),
+        "capture_all_monitors" => with_args!(args, {
+            encoding: Option<ImageEncoding>,
+            include_cursor: Option<bool>,
+            resize_quality: Option<ResizeQuality>,
+            token_id: Option<String>,
+        }, screenshot::capture_all_monitors(
+            a, app.state(), encoding, include_cursor, resize_quality, token_id
+        ).await),
+        "capture_thumbnail" => with_args!(args, {
+            max_edge: Option<u32>,
+            monitor_id: Option<u32>,
+            encoding: Option<ImageEncoding>,
+            include_cursor: Option<bool>,
+            resize_quality: Option<ResizeQuality>,
+            token_id: Option<String>,
+        }, screenshot::capture_thumbnail(
+            a, app.state(), max_edge, monitor_id, encoding, include_cursor, resize_quality,
+            token_id
+        ).await),
+        "capture_screen_if_changed" => with_args!(args, {
+            monitor_id: Option<u32>,
+            threshold: Option<f64>,
+            encoding: Option<ImageEncoding>,
+            include_cursor: Option<bool>,
+            resize_quality: Option<ResizeQuality>,
+            token_id: Option<String>,
+        }, screenshot::capture_screen_if_changed(
+            a, app.state(), monitor_id, threshold, encoding, include_cursor, resize_quality,
+            token_id
+        ).await),
+        "wait_for_screen_idle" => with_args!(args, {
+            monitor_id: Option<u32>,
+            region: Option<Region>,
+            stability_ms: Option<u64>,
+            timeout_ms: Option<u64>,
+            threshold: Option<f64>,
+            token_id: Option<String>,
+        }, screenshot::wait_for_screen_idle(
+            a, app.state(), monitor_id, region, stability_ms, timeout_ms, threshold, token_id
+        ).await),
+        "compare_screenshots" => with_args!(args, {
+            before: String,
+            after: String,
+            threshold: Option<u8>,
+            include_diff_image: Option<bool>,
+        }, screenshot::compare_screenshots(before, after, threshold, include_diff_image).await),
+
+        // Input
+        "get_mouse_position" => with_args!(args, {}, input::get_mouse_position().await),
+        "mouse_move" => with_args!(args, { x: i32, y: i32, token_id: Option<String> },
+            input::mouse_move(a, app.state(), x, y, token_id).await),
+        "left_click" => with_args!(args, { x: i32, y: i32, token_id: Option<String> },
+            input::left_click(a, app.state(), x, y, token_id).await),
+        "right_click" => with_args!(args, { x: i32, y: i32, token_id: Option<String> },
+            input::right_click(a, app.state(), x, y, token_id).await),
+        "middle_click" => with_args!(args, { x: i32, y: i32, token_id: Option<String> },
+            input::middle_click(a, app.state(), x, y, token_id).await),
+        "double_click" => with_args!(args, { x: i32, y: i32, token_id: Option<String> },
+            input::double_click(a, app.state(), x, y, token_id).await),
+        "triple_click" => with_args!(args, { x: i32, y: i32, token_id: Option<String> },
+            input::triple_click(a, app.state(), x, y, token_id).await),
+        "click_button" => with_args!(args, {
+            x: i32,
+            y: i32,
+            button: String,
+            token_id: Option<String>,
+        }, input::click_button(a, app.state(), x, y, button, token_id).await),
+        "click_with_retry" => with_args!(args, {
+            x: i32,
+            y: i32,
+            button: Option<String>,
+            retry: Option<RetryPolicy>,
+            verify: Option<Verification>,
+            token_id: Option<String>,
+        }, input::click_with_retry(a, app.state(), x, y, button, retry, verify, token_id).await),
+        "left_click_drag" => with_args!(args, {
+            start_x: i32,
+            start_y: i32,
+            end_x: i32,
+            end_y: i32,
+            steps: Option<u32>,
+            token_id: Option<String>,
+        }, input::left_click_drag(
+            a, app.state(), start_x, start_y, end_x, end_y, steps, token_id
+        ).await),
+        "scroll" => with_args!(args, {
+            x: i32,
+            y: i32,
+            direction: String,
+            amount: i32,
+            token_id: Option<String>,
+        }, input::scroll(a, app.state(), x, y, direction, amount, token_id).await),
+        "scroll_pixels" => with_args!(args, {
+            x: i32,
+            y: i32,
+            dx: i32,
+            dy: i32,
+            token_id: Option<String>,
+        }, input::scroll_pixels(a, app.state(), x, y, dx, dy, token_id).await),
+        "type_text" => with_args!(args, {
+            text: String,
+            layout: Option<KeyboardLayout>,
+            chars_per_second: Option<f64>,
+            per_char_delay_ms: Option<u64>,
+            token_id: Option<String>,
+        }, input::type_text(
+            a, app.state(), text, layout, chars_per_second, per_char_delay_ms, token_id
+        ).await),
+        "type_text_paste" => with_args!(args, {
+            text: String,
+            restore_clipboard: bool,
+            token_id: Option<String>,
+        }, input::type_text_paste(a, app.state(), text, restore_clipboard, token_id).await),
+        "key" => with_args!(args, {
+            keys: String,
+            layout: Option<KeyboardLayout>,
+            token_id: Option<String>,
+        }, input::key(a, app.state(), keys, layout, token_id).await),
+        "hold_key" => with_args!(args, {
+            key_name: String,
+            hold: bool,
+            token_id: Option<String>,
+        }, input::hold_key(a, app.state(), key_name, hold, token_id).await),
+
+        // Template matching
+        "match_hint_images" => with_args!(args, {
+            screenshot_base64: Option<String>,
+            template_images: Vec<TemplateImage>,
+            scale_factor: Option<f64>,
+            confidence_threshold: Option<f32>,
+            token_id: Option<String>,
+            options: Option<MatchOptions>,
+            monitor_id: Option<u32>,
+            screenshots: Option<Vec<MonitorScreenshot>>,
+            stop_after_first_match: Option<bool>,
+        }, template_match::match_hint_images(
+            a, app.state(), screenshot_base64, template_images, scale_factor,
+            confidence_threshold, token_id, options, monitor_id, screenshots,
+            stop_after_first_match
+        ).await),
+        "match_with_retry" => with_args!(args, {
+            template_image: String,
+            monitor_id: Option<u32>,
+            confidence_threshold: Option<f32>,
+            retry: Option<RetryPolicy>,
+            token_id: Option<String>,
+        }, template_match::match_with_retry(
+            app.state(), template_image, monitor_id, confidence_threshold, retry, token_id
+        ).await),
+        "click_template" => with_args!(args, {
+            template_base64: String,
+            confidence_threshold: Option<f32>,
+            button: Option<String>,
+            anchor: Option<Anchor>,
+            offset: Option<Point>,
+            monitor_id: Option<u32>,
+            token_id: Option<String>,
+        }, input::click_template(
+            a, app.state(), template_base64, confidence_threshold, button, anchor, offset,
+            monitor_id, token_id
+        ).await),
+
+        // Run control
+        "create_run_token" => with_args!(args, {}, control::create_run_token(app.state())),
+        "cancel_run" => with_args!(args, { token_id: String },
+            control::cancel_run(app.state(), token_id)),
+        "release_run_token" => with_args!(args, { token_id: String },
+            control::release_run_token(app.state(), token_id)),
+        "request_stop" => with_args!(args, {}, {
+            control::request_stop(app.state());
+            Ok(())
+        }),
+        "clear_stop" => with_args!(args, {}, {
+            control::clear_stop(app.state());
+            Ok(())
+        }),
+        "wait" => with_args!(args, { duration_ms: u64, token_id: Option<String> },
+            control::wait(app.state(), duration_ms, token_id).await),
+
+        // Scenario runner
+        "list_scenarios" => with_args!(args, {}, scenario::list_scenarios(a).await),
+        "get_scenario" => with_args!(args, { id: String }, scenario::get_scenario(a, id).await),
+        "run_scenario" => with_args!(args, {
+            scenario_id: Option<String>,
+            scenario: Option<Scenario>,
+            variables: Option<HashMap<String, String>>,
+            model: Option<String>,
+            max_iterations: Option<u32>,
+            token_id: Option<String>,
+        }, run_scenario(&app, scenario_id, scenario, variables, model, max_iterations, token_id)
+            .await),
+        "run_scenario_from_step" => with_args!(args, {
+            scenario_id: String,
+            step_index: usize,
+            variables: Option<HashMap<String, String>>,
+            model: Option<String>,
+            max_iterations: Option<u32>,
+            token_id: Option<String>,
+        }, scenario::run_scenario_from_step(
+            a, app.state(), scenario_id, step_index, variables, model, max_iterations, token_id
+        ).await),
+
+        _ => Err(format!("Unknown command: {}", command)),
+    }
+}
+
+/// Run a stored scenario (`scenario_id`) or an inline one through the backend runner
+/// The run is recorded in the run history like a headless run.
+async fn run_scenario(
+    app: &AppHandle,
+    scenario_id: Option<String>,
+    scenario: Option<Scenario>,
+    variables: Option<HashMap<String, String>>,
+    model: Option<String>,
+    max_iterations: Option<u32>,
+    token_id: Option<String>,
+) -> Result<ScenarioRunResult, IpcError> {
+    let cancel = app.state::<AppState>().cancel_token(token_id.as_deref())?;
+    let pool = get_pool(app).await.ok();
+    let scenario = match (scenario, scenario_id) {
+        (Some(scenario), _) => scenario,
+        (None, Some(id)) => {
+            let pool = pool.as_ref().ok_or_else(|| {
+                XenotesterError::DatabaseError("The database is not available".to_string())
+            })?;
+            runner::load_scenario(pool, &id)
+                .await
+                .map_err(IpcError::from)?
+        }
+        (None, None) => {
+            return Err(XenotesterError::InvalidArgument(
+                "Either scenarioId or scenario is required".to_string(),
+            )
+            .into())
+        }
+    };
+
+    let options = scenario::run_options(app, variables, model, max_iterations).await;
+    Ok(runner::run_scenario(&scenario, &options, pool.as_ref(), &cancel).await)
+}
@@ -0,0 +1,159 @@
+//! Scenario commands
+//!
+//! CRUD for stored scenarios, so validation and cascading deletes happen in
+//! one place instead of the frontend issuing SQL through tauri-plugin-sql,
+//! and partial runs of a stored scenario through the backend runner.
+
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, State};
+use tracing::warn;
+
+use crate::commands::agent::DEFAULT_MAX_ITERATIONS;
+use crate::commands::journal::run_journal;
+use crate::commands::webhook::load_notifier;
+use crate::error::IpcError;
+use crate::services::database::get_pool;
+use crate::services::llm::anthropic::ModelConfig;
+use crate::services::runner::{self, RunOptions, RunTimeouts, ScenarioRunResult};
+use crate::services::scenario_store::{self, StoredScenario};
+use crate::services::steps::Step;
+use crate::state::AppState;
+
+/// List all scenarios in display order
+#[tauri::command]
+pub async fn list_scenarios(app: AppHandle) -> Result<Vec<StoredScenario>, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    scenario_store::list_scenarios(&pool)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Get a scenario by ID (null when it does not exist)
+#[tauri::command]
+pub async fn get_scenario(app: AppHandle, id: String) -> Result<Option<StoredScenario>, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    scenario_store::get_scenario(&pool, &id)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Create a scenario at the end of the list
+/// An empty title is generated from the first line of the description.
+#[tauri::command]
+pub async fn create_scenario(
+    app: AppHandle,
+    title: String,
+    description: String,
+) -> Result<StoredScenario, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    scenario_store::create_scenario(&pool, &title, &description)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Update the title and description of a scenario
+#[tauri::command]
+pub async fn update_scenario(
+    app: AppHandle,
+    id: String,
+    title: String,
+    description: String,
+) -> Result<StoredScenario, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    scenario_store::update_scenario(&pool, &id, &title, &description)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Replace the scripted steps of a scenario; an empty list clears them
+#[tauri::command]
+pub async fn set_scenario_steps(
+    app: AppHandle,
+    id: String,
+    steps: Vec<Step>,
+) -> Result<StoredScenario, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    scenario_store::set_scenario_steps(&pool, &id, &steps)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Delete a scenario and its step images
+#[tauri::command]
+pub async fn delete_scenario(app: AppHandle, id: String) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    scenario_store::delete_scenario(&pool, &id)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Options of a backend run started from the app
+pub async fn run_options(
+    app: &AppHandle,
+    variables: Option<HashMap<String, String>>,
+    model: Option<String>,
+    max_iterations: Option<u32>,
+) -> RunOptions {
+    RunOptions {
+        model_config: model.map(ModelConfig::for_model).unwrap_or_default(),
+        max_iterations: max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS),
+        variables: variables.unwrap_or_default(),
+        notifier: load_notifier(app).await,
+        capture_history: Some(app.state::<AppState>().capture_history.clone()),
+        journal: run_journal(app),
+        debugger: Some(app.state::<AppState>().debugger.clone()),
+        timeouts: load_timeouts(app).await,
+        guard: Some(app.state::<AppState>().action_guard()),
+    }
+}
+
+/// Stored run timeouts; none when they cannot be read
+async fn load_timeouts(app: &AppHandle) -> RunTimeouts {
+    let timeouts = match get_pool(app).await {
+        Ok(pool) => runner::load_timeouts(&pool).await,
+        Err(e) => Err(e),
+    };
+    timeouts.unwrap_or_else(|e| {
+        warn!("Runs have no time limits: {}", e);
+        RunTimeouts::default()
+    })
+}
+
+/// Get the step and run time limits of backend runs
+#[tauri::command]
+pub async fn get_run_timeouts(app: AppHandle) -> Result<RunTimeouts, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    runner::load_timeouts(&pool).await.map_err(IpcError::from)
+}
+
+/// Set the step and run time limits of backend runs
+/// A step's own `timeoutMs` overrides the step limit.
+#[tauri::command]
+pub async fn set_run_timeouts(app: AppHandle, timeouts: RunTimeouts) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    runner::save_timeouts(&pool, &timeouts)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Run a stored scenario from its step `step_index` (0-based), skipping the
+/// steps before it; the run is recorded in the run history
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_scenario_from_step(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    scenario_id: String,
+    step_index: usize,
+    variables: Option<HashMap<String, String>>,
+    model: Option<String>,
+    max_iterations: Option<u32>,
+    token_id: Option<String>,
+) -> Result<ScenarioRunResult, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    let options = run_options(&app, variables, model, max_iterations).await;
+    runner::run_scenario_from_step(&scenario_id, step_index, &options, &pool, &cancel)
+        .await
+        .map_err(IpcError::from)
+}
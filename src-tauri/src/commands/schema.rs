@@ -0,0 +1,32 @@
+//! Database schema commands
+
+use tauri::AppHandle;
+
+use crate::error::{IpcError, XenotesterError};
+use crate::services::database::get_pool;
+use crate::services::schema::{self, SchemaVersion};
+
+/// Get the applied schema version and the latest version this build knows
+#[tauri::command]
+pub async fn get_schema_version(app: AppHandle) -> Result<SchemaVersion, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    schema::get_schema_version(&pool)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Revert migrations above `target_version` before downgrading the app
+#[tauri::command]
+pub async fn revert_schema(app: AppHandle, target_version: i64) -> Result<SchemaVersion, IpcError> {
+    if target_version < 0 {
+        return Err(XenotesterError::InvalidArgument(format!(
+            "Invalid target version: {}",
+            target_version
+        ))
+        .into());
+    }
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    schema::revert_to(&pool, target_version)
+        .await
+        .map_err(IpcError::from)
+}
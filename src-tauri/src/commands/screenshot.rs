@@ -2,67 +2,522 @@
 //!
 //! All commands that involve CPU-intensive operations (capture, image processing,
 //! Base64 decode, file I/O) are async and use `spawn_blocking` to prevent UI blocking.
+//! Capture commands return `Cancelled` as soon as the run token (`token_id`) or the
+//! shared stop token fires, without waiting for the capture to finish.
 
+use crate::commands::artifacts::writable_roots;
+use crate::error::{IpcError, XenotesterError};
+use crate::services::annotate::{annotate_base64, Annotation};
+use crate::services::baseline::Region;
 use crate::services::capture::{
-    capture_monitor, capture_primary_monitor, list_monitors, CaptureResult, MonitorInfo,
+    self, capture_monitor, capture_monitor_raw, capture_primary_monitor,
+    capture_primary_monitor_raw, capture_thumbnail as capture_thumbnail_image,
+    capture_virtual_desktop, list_monitors, watch_monitors,
+    CaptureOptions, CaptureResult, CaptureThrottle, MonitorInfo, RawCaptureResult,
+    VirtualDesktopCapture,
 };
+use crate::services::capture_cache::{ChangeCaptureResult, DEFAULT_CHANGE_THRESHOLD};
+use crate::services::capture_history::{CaptureHistorySettings, RecentCapture};
+use crate::services::capture_stream::{StreamFrame, StreamOptions, StreamStats};
+use crate::services::image_diff::{compare_base64, DiffResult, DEFAULT_DIFF_THRESHOLD};
+use crate::services::image_processor::{ImageEncoding, ResizeQuality};
+use crate::services::screen_check::{self, ScreenIdle, DEFAULT_IDLE_THRESHOLD};
+use crate::state::AppState;
+use crate::utils::operation::track;
+use crate::utils::sandbox::resolve_within;
 use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
+use tauri::ipc::Response;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{error, info, warn};
+
+/// Default time the screen must stay unchanged in `wait_for_screen_idle`
+const DEFAULT_IDLE_STABILITY_MS: u64 = 500;
+/// Default upper bound on `wait_for_screen_idle`
+const DEFAULT_IDLE_TIMEOUT_MS: u64 = 10_000;
+/// Default long edge of `capture_thumbnail` previews
+const DEFAULT_THUMBNAIL_MAX_EDGE: u32 = 320;
+/// Directory of the capture history in the app cache directory
+const CAPTURE_HISTORY_DIR: &str = "capture_history";
+
+/// Build capture options from optional command arguments
+fn capture_options(
+    encoding: Option<ImageEncoding>,
+    include_cursor: Option<bool>,
+    resize_quality: Option<ResizeQuality>,
+) -> CaptureOptions {
+    CaptureOptions {
+        encoding: encoding.unwrap_or_default(),
+        include_cursor: include_cursor.unwrap_or(false),
+        resize_quality,
+    }
+}
 
 /// Get list of all available monitors
 /// This is a lightweight operation, no need for spawn_blocking
 #[tauri::command]
-pub fn get_monitors() -> Result<Vec<MonitorInfo>, String> {
-    list_monitors().map_err(|e| e.to_string())
+pub fn get_monitors() -> Result<Vec<MonitorInfo>, IpcError> {
+    list_monitors().map_err(IpcError::from)
+}
+
+/// Get the frame-rate cap of the screenshot commands
+#[tauri::command]
+pub fn get_capture_throttle() -> CaptureThrottle {
+    capture::throttle()
+}
+
+/// Set the frame-rate cap of the screenshot commands
+/// mode: "wait" (queue until the interval has passed) or "reuse" (return the last frame)
+#[tauri::command]
+pub fn set_capture_throttle(throttle: CaptureThrottle) {
+    capture::set_throttle(throttle);
 }
 
 /// Capture screenshot from primary monitor (for Computer Use API)
 /// Now async with spawn_blocking to prevent UI blocking during capture and image processing
+/// `encoding` defaults to PNG; JPEG/WebP shrink the payload sent to the vision API
+/// `include_cursor` draws the mouse pointer onto the screenshot
 #[tauri::command]
-pub async fn capture_screen() -> Result<CaptureResult, String> {
+pub async fn capture_screen(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    encoding: Option<ImageEncoding>,
+    include_cursor: Option<bool>,
+    resize_quality: Option<ResizeQuality>,
+    token_id: Option<String>,
+) -> Result<CaptureResult, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let history = state.capture_history.clone();
+    let options = capture_options(encoding, include_cursor, resize_quality);
     // Offload CPU-intensive capture and image processing to worker thread
-    tauri::async_runtime::spawn_blocking(move || capture_primary_monitor().map_err(|e| e.to_string()))
+    track(&app, "capture_screen", token_id.as_deref(), async move {
+        let task = tauri::async_runtime::spawn_blocking(move || {
+            let capture = capture_primary_monitor(&options).map_err(IpcError::from)?;
+            history.record(&capture);
+            Ok::<_, IpcError>(capture)
+        });
+        cancel
+            .run_until_cancelled(task)
+            .await
+            .map_err(IpcError::from)?
+            .map_err(|e| XenotesterError::Internal(format!("Capture task failed: {}", e)))?
+    })
+    .await
+}
+
+/// Capture a small preview for the UI (primary monitor when `monitor_id` is omitted)
+/// Defaults to fast filtering (`resize_quality: "fast"`); not meant for the model.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn capture_thumbnail(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    max_edge: Option<u32>,
+    monitor_id: Option<u32>,
+    encoding: Option<ImageEncoding>,
+    include_cursor: Option<bool>,
+    resize_quality: Option<ResizeQuality>,
+    token_id: Option<String>,
+) -> Result<CaptureResult, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let options = capture_options(encoding, include_cursor, resize_quality);
+    let max_edge = max_edge.unwrap_or(DEFAULT_THUMBNAIL_MAX_EDGE).max(1);
+    track(&app, "capture_thumbnail", token_id.as_deref(), async move {
+        let task = tauri::async_runtime::spawn_blocking(move || {
+            capture_thumbnail_image(monitor_id, max_edge, &options).map_err(IpcError::from)
+        });
+        cancel
+            .run_until_cancelled(task)
+            .await
+            .map_err(IpcError::from)?
+            .map_err(|e| XenotesterError::Internal(format!("Capture task failed: {}", e)))?
+    })
+    .await
+}
+
+/// Start streaming downscaled frames as `capture-stream-frame` events
+/// `fps` and `monitor_id` override the corresponding `options` fields.
+#[tauri::command]
+pub async fn start_capture_stream(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    fps: Option<u32>,
+    monitor_id: Option<u32>,
+    options: Option<StreamOptions>,
+) -> Result<(), IpcError> {
+    let mut options = options.unwrap_or_default();
+    options.fps = fps.unwrap_or(options.fps);
+    options.monitor_id = monitor_id.or(options.monitor_id);
+
+    let stream = state.capture_stream.clone();
+    let on_frame = Box::new(move |frame: StreamFrame| {
+        if let Err(e) = app.emit("capture-stream-frame", frame) {
+            error!("Failed to emit stream frame: {}", e);
+        }
+    });
+    tauri::async_runtime::spawn_blocking(move || {
+        stream.start(options, on_frame).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Capture stream task failed: {}", e)))?
+}
+
+/// Stop the capture stream and return how many frames it sent
+#[tauri::command]
+pub async fn stop_capture_stream(state: State<'_, AppState>) -> Result<StreamStats, IpcError> {
+    let stream = state.capture_stream.clone();
+    // Joining the stream thread waits for the frame in flight
+    tauri::async_runtime::spawn_blocking(move || stream.stop().map_err(IpcError::from))
         .await
-        .map_err(|e| format!("Capture task failed: {}", e))?
+        .map_err(|e| XenotesterError::Internal(format!("Capture stream task failed: {}", e)))?
 }
 
 /// Capture screenshot from specific monitor
 /// Now async with spawn_blocking to prevent UI blocking
 #[tauri::command]
-pub async fn capture_monitor_by_id(monitor_id: u32) -> Result<CaptureResult, String> {
-    tauri::async_runtime::spawn_blocking(move || capture_monitor(monitor_id).map_err(|e| e.to_string()))
-        .await
-        .map_err(|e| format!("Capture task failed: {}", e))?
+pub async fn capture_monitor_by_id(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    monitor_id: u32,
+    encoding: Option<ImageEncoding>,
+    include_cursor: Option<bool>,
+    resize_quality: Option<ResizeQuality>,
+    token_id: Option<String>,
+) -> Result<CaptureResult, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let history = state.capture_history.clone();
+    let options = capture_options(encoding, include_cursor, resize_quality);
+    track(
+        &app,
+        "capture_monitor_by_id",
+        token_id.as_deref(),
+        async move {
+            let task = tauri::async_runtime::spawn_blocking(move || {
+                let capture = capture_monitor(monitor_id, &options).map_err(IpcError::from)?;
+                history.record(&capture);
+                Ok::<_, IpcError>(capture)
+            });
+            cancel
+                .run_until_cancelled(task)
+                .await
+                .map_err(IpcError::from)?
+                .map_err(|e| XenotesterError::Internal(format!("Capture task failed: {}", e)))?
+        },
+    )
+    .await
+}
+
+/// Capture all monitors as one composite image with per-monitor placement metadata
+#[tauri::command]
+pub async fn capture_all_monitors(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    encoding: Option<ImageEncoding>,
+    include_cursor: Option<bool>,
+    resize_quality: Option<ResizeQuality>,
+    token_id: Option<String>,
+) -> Result<VirtualDesktopCapture, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let options = capture_options(encoding, include_cursor, resize_quality);
+    track(
+        &app,
+        "capture_all_monitors",
+        token_id.as_deref(),
+        async move {
+            let task = tauri::async_runtime::spawn_blocking(move || {
+                capture_virtual_desktop(&options).map_err(IpcError::from)
+            });
+            cancel
+                .run_until_cancelled(task)
+                .await
+                .map_err(IpcError::from)?
+                .map_err(|e| XenotesterError::Internal(format!("Capture task failed: {}", e)))?
+        },
+    )
+    .await
+}
+
+/// Capture a screenshot only if the screen changed since the last call for that monitor
+/// `threshold` is the fraction of the screen (0.0 - 1.0) allowed to change while still
+/// reporting `unchanged: true`; in that case no image is encoded or returned
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn capture_screen_if_changed(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    monitor_id: Option<u32>,
+    threshold: Option<f64>,
+    encoding: Option<ImageEncoding>,
+    include_cursor: Option<bool>,
+    resize_quality: Option<ResizeQuality>,
+    token_id: Option<String>,
+) -> Result<ChangeCaptureResult, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let cache = state.capture_cache.clone();
+    let history = state.capture_history.clone();
+    let threshold = threshold.unwrap_or(DEFAULT_CHANGE_THRESHOLD);
+    let options = capture_options(encoding, include_cursor, resize_quality);
+
+    track(
+        &app,
+        "capture_screen_if_changed",
+        token_id.as_deref(),
+        async move {
+            let task = tauri::async_runtime::spawn_blocking(move || {
+                let result = cache
+                    .capture_if_changed(monitor_id, threshold, &options)
+                    .map_err(IpcError::from)?;
+                if let Some(capture) = &result.capture {
+                    history.record(capture);
+                }
+                Ok::<_, IpcError>(result)
+            });
+            cancel
+                .run_until_cancelled(task)
+                .await
+                .map_err(IpcError::from)?
+                .map_err(|e| XenotesterError::Internal(format!("Capture task failed: {}", e)))?
+        },
+    )
+    .await
+}
+
+/// Capture a screenshot and return it as a binary IPC response (no base64/JSON overhead)
+///
+/// Body layout: `[u32 LE metadata length][metadata JSON][encoded image bytes]`.
+/// Captures the primary monitor when `monitor_id` is omitted.
+#[tauri::command]
+pub async fn capture_screen_raw(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    monitor_id: Option<u32>,
+    encoding: Option<ImageEncoding>,
+    include_cursor: Option<bool>,
+    resize_quality: Option<ResizeQuality>,
+    token_id: Option<String>,
+) -> Result<Response, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let options = capture_options(encoding, include_cursor, resize_quality);
+    track(
+        &app,
+        "capture_screen_raw",
+        token_id.as_deref(),
+        async move {
+            let task = tauri::async_runtime::spawn_blocking(move || {
+                let capture = match monitor_id {
+                    Some(id) => capture_monitor_raw(id, &options),
+                    None => capture_primary_monitor_raw(&options),
+                }
+                .map_err(IpcError::from)?;
+
+                frame_raw_capture(&capture).map(Response::new)
+            });
+            cancel
+                .run_until_cancelled(task)
+                .await
+                .map_err(IpcError::from)?
+                .map_err(|e| XenotesterError::Internal(format!("Capture task failed: {}", e)))?
+        },
+    )
+    .await
+}
+
+/// Prefix the image bytes with the length-delimited metadata JSON
+fn frame_raw_capture(capture: &RawCaptureResult) -> Result<Vec<u8>, IpcError> {
+    let metadata = serde_json::to_vec(capture).map_err(IpcError::from)?;
+
+    let mut body = Vec::with_capacity(4 + metadata.len() + capture.image_bytes.len());
+    body.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+    body.extend_from_slice(&metadata);
+    body.extend_from_slice(&capture.image_bytes);
+    Ok(body)
+}
+
+/// Draw boxes, crosshairs and labels onto an image (coordinates in image pixels)
+/// Returns the annotated image as base64 PNG
+#[tauri::command]
+pub async fn annotate_screenshot(
+    image_base64: String,
+    annotations: Vec<Annotation>,
+) -> Result<String, IpcError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        annotate_base64(&image_base64, &annotations).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Annotate task failed: {}", e)))?
+}
+
+/// Compare two base64 screenshots and report similarity plus changed regions
+/// `threshold` is the per-channel tolerance (0-255); `include_diff_image` adds a
+/// highlighted diff image to the result
+#[tauri::command]
+pub async fn compare_screenshots(
+    before: String,
+    after: String,
+    threshold: Option<u8>,
+    include_diff_image: Option<bool>,
+) -> Result<DiffResult, IpcError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        compare_base64(
+            &before,
+            &after,
+            threshold.unwrap_or(DEFAULT_DIFF_THRESHOLD),
+            include_diff_image.unwrap_or(false),
+        )
+        .map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Compare task failed: {}", e)))?
+}
+
+/// Wait until the screen stops changing, e.g. after navigation
+/// `region` limits the check to part of the monitor (physical pixels); `threshold` is
+/// the fraction of pixels (0.0 - 1.0) allowed to change between idle frames.
+/// Returns `idle: false` if the screen was still changing after `timeout_ms`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn wait_for_screen_idle(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    monitor_id: Option<u32>,
+    region: Option<Region>,
+    stability_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+    threshold: Option<f64>,
+    token_id: Option<String>,
+) -> Result<ScreenIdle, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let stability = Duration::from_millis(stability_ms.unwrap_or(DEFAULT_IDLE_STABILITY_MS));
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_IDLE_TIMEOUT_MS));
+    let threshold = threshold.unwrap_or(DEFAULT_IDLE_THRESHOLD);
+
+    track(
+        &app,
+        "wait_for_screen_idle",
+        token_id.as_deref(),
+        async move {
+            tauri::async_runtime::spawn_blocking(move || {
+                screen_check::wait_for_screen_idle(
+                    monitor_id, region, stability, timeout, threshold, &cancel,
+                )
+                .map_err(IpcError::from)
+            })
+            .await
+            .map_err(|e| XenotesterError::Internal(format!("Capture task failed: {}", e)))?
+        },
+    )
+    .await
 }
 
 /// Ensure a directory exists (create if needed)
+/// Prefer `save_artifact` for files produced during runs.
+/// Only paths inside the app data `artifacts` or `exports` directory or
+/// `XENOTESTER_ARTIFACT_ROOT` are accepted.
 /// Now async with spawn_blocking to prevent UI blocking during directory operations
 #[tauri::command]
-pub async fn ensure_directory(path: String) -> Result<(), String> {
+pub async fn ensure_directory(app: AppHandle, path: String) -> Result<(), IpcError> {
+    let roots = writable_roots(&app)?;
     tauri::async_runtime::spawn_blocking(move || {
-        fs::create_dir_all(&path).map_err(|e| e.to_string())
+        let path = resolve_within(Path::new(&path), &roots).map_err(IpcError::from)?;
+        fs::create_dir_all(&path).map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Directory creation task failed: {}", e))?
+    .map_err(|e| XenotesterError::Internal(format!("Directory creation task failed: {}", e)))?
 }
 
 /// Save base64-encoded image data to a file
+/// Prefer `save_artifact`, which keeps run files in the artifact directory.
+/// Only paths inside the app data `artifacts` or `exports` directory or
+/// `XENOTESTER_ARTIFACT_ROOT` are accepted.
 /// Now async with spawn_blocking to prevent UI blocking during Base64 decode and file I/O
 #[tauri::command]
-pub async fn save_base64_image(base64_data: String, file_path: String) -> Result<(), String> {
+pub async fn save_base64_image(
+    app: AppHandle,
+    base64_data: String,
+    file_path: String,
+) -> Result<(), IpcError> {
+    let roots = writable_roots(&app)?;
     tauri::async_runtime::spawn_blocking(move || {
+        let file_path =
+            resolve_within(Path::new(&file_path), &roots).map_err(IpcError::from)?;
         let image_data = BASE64_STANDARD
             .decode(&base64_data)
-            .map_err(|e| format!("Failed to decode base64: {}", e))?;
+            .map_err(|e| XenotesterError::ImageError(format!("Failed to decode base64: {}", e)))?;
 
         // Ensure parent directory exists
-        if let Some(parent) = Path::new(&file_path).parent() {
-            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).map_err(IpcError::from)?;
+        }
+
+        fs::write(&file_path, image_data).map_err(IpcError::from)
+    })
+    .await
+    .map_err(|e| XenotesterError::Internal(format!("Save image task failed: {}", e)))?
+}
+
+/// Emit `monitors-changed` when displays are connected, disconnected or reconfigured
+/// Cached frames are dropped, since their monitor IDs and sizes may no longer apply.
+pub fn emit_monitor_changes(app: &AppHandle) {
+    let app = app.clone();
+    watch_monitors(move |layout| {
+        info!("Monitor layout changed: {} monitor(s)", layout.monitors.len());
+        app.state::<AppState>().capture_cache.clear();
+        if let Err(e) = app.emit("monitors-changed", layout) {
+            error!("Failed to emit monitor change: {}", e);
         }
+    });
+}
 
-        fs::write(&file_path, image_data).map_err(|e| format!("Failed to write file: {}", e))
+/// Get the last `count` captured frames (default: all kept), oldest first
+/// Frames come from the capture commands and agent sessions.
+#[tauri::command]
+pub async fn get_recent_captures(
+    state: State<'_, AppState>,
+    count: Option<usize>,
+) -> Result<Vec<RecentCapture>, IpcError> {
+    let history = state.capture_history.clone();
+    // Frames stored on disk are read back
+    tauri::async_runtime::spawn_blocking(move || {
+        history
+            .recent(count.unwrap_or(usize::MAX))
+            .map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Save image task failed: {}", e))?
+    .map_err(|e| XenotesterError::Internal(format!("Capture history task failed: {}", e)))?
+}
+
+/// Get the size and storage of the capture history
+#[tauri::command]
+pub fn get_capture_history_settings(state: State<AppState>) -> CaptureHistorySettings {
+    state.capture_history.settings()
+}
+
+/// Set the size and storage of the capture history ("memory" or "disk")
+/// A capacity of 0 disables the history; frames beyond the capacity are dropped.
+#[tauri::command]
+pub fn set_capture_history_settings(
+    state: State<AppState>,
+    settings: CaptureHistorySettings,
+) -> Result<(), IpcError> {
+    state
+        .capture_history
+        .set_settings(settings)
+        .map_err(IpcError::from)
+}
+
+/// Keep frames of the disk-backed capture history in the app cache directory
+/// Without one, the history stays in memory.
+pub fn init_capture_history(app: &AppHandle) {
+    let dir = match app.path().app_cache_dir() {
+        Ok(dir) => dir.join(CAPTURE_HISTORY_DIR),
+        Err(e) => {
+            warn!("Capture history is kept in memory: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = app.state::<AppState>().capture_history.set_dir(dir) {
+        warn!("Capture history is kept in memory: {}", e);
+    }
 }
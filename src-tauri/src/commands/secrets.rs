@@ -0,0 +1,46 @@
+//! Secret commands
+//!
+//! Values are encrypted at rest and redacted from logs and the action log
+//! once stored or resolved.
+
+use tauri::AppHandle;
+
+use crate::error::IpcError;
+use crate::services::database::get_pool;
+use crate::services::secrets;
+
+/// Encrypt and store a secret under `name`, replacing any previous value
+#[tauri::command]
+pub async fn store_secret(app: AppHandle, name: String, value: String) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    secrets::store_secret(&pool, &name, &value)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Decrypt the secret stored under `name`
+#[tauri::command]
+pub async fn resolve_secret(app: AppHandle, name: String) -> Result<String, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    secrets::resolve_secret(&pool, &name)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// List the names of stored secrets (values are never listed)
+#[tauri::command]
+pub async fn list_secrets(app: AppHandle) -> Result<Vec<String>, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    secrets::list_secret_names(&pool)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Delete the secret stored under `name`
+#[tauri::command]
+pub async fn delete_secret(app: AppHandle, name: String) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    secrets::delete_secret(&pool, &name)
+        .await
+        .map_err(IpcError::from)
+}
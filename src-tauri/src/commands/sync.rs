@@ -0,0 +1,58 @@
+//! Supabase sync commands
+//!
+//! Upload local scenarios, run history and failure screenshots to the
+//! Supabase project from `get_supabase_config` (see `services::sync`).
+
+use tauri::{AppHandle, State};
+use tracing::warn;
+
+use crate::commands::artifacts::artifacts_dir;
+use crate::commands::config::get_supabase_config;
+use crate::error::{IpcError, XenotesterError};
+use crate::services::database::get_pool;
+use crate::services::oauth;
+use crate::services::sync::{self, SupabaseClient, SyncReport, SyncStatus};
+use crate::state::AppState;
+
+/// Upload everything changed since the last sync, plus uploads still queued
+/// `access_token` overrides the stored session's token (see `commands::oauth`);
+/// without either, requests are made with the anon key.
+#[tauri::command]
+pub async fn sync_now(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    access_token: Option<String>,
+) -> Result<SyncReport, IpcError> {
+    let _guard = state.sync_lock.try_acquire().ok_or_else(|| {
+        XenotesterError::InvalidArgument("A sync is already in progress".to_string())
+    })?;
+    let config = get_supabase_config()?;
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    let access_token = match access_token {
+        Some(token) => Some(token),
+        None if oauth::load_tokens(&pool).await?.is_some() => {
+            Some(oauth::get_valid_access_token(&pool, &config.url, &config.anon_key).await?)
+        }
+        None => None,
+    };
+    let client = SupabaseClient::new(&config.url, &config.anon_key, access_token)?;
+    let dir = artifacts_dir(&app)
+        .map_err(|e| warn!("Screenshots will not be synced: {}", e))
+        .ok();
+
+    sync::sync_now(&pool, &client, dir)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Queue size and the outcome of the last sync
+#[tauri::command]
+pub async fn get_sync_status(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<SyncStatus, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    sync::get_sync_status(&pool, state.sync_lock.is_held())
+        .await
+        .map_err(IpcError::from)
+}
@@ -2,8 +2,26 @@
 //!
 //! Provides Tauri commands for matching hint images against screenshots.
 
-use crate::services::template_matcher::{match_templates_batch, MatchResult};
+use crate::error::{IpcError, XenotesterError};
+use crate::services::capture::grab_frame;
+use crate::services::coords::{translate, CoordinateContext, CoordinateSpace};
+use crate::services::match_cache::MatchCache;
+use crate::services::retry::{with_retry, RetryPolicy};
+use crate::services::screen_check::{
+    find_template_on_screen, ScreenMatch, DEFAULT_CONFIDENCE_THRESHOLD,
+};
+use crate::services::template_matcher::{
+    find_template_in_image, match_templates_batch_with_progress, MatchErrorCode, MatchOptions,
+    MatchResult, TemplateSource,
+};
+use crate::state::AppState;
+use crate::utils::operation::Operation;
 use serde::{Deserialize, Serialize};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+use tracing::error;
 
 /// Input template image data
 #[derive(Debug, Clone, Deserialize)]
@@ -11,6 +29,9 @@ use serde::{Deserialize, Serialize};
 pub struct TemplateImage {
     /// Base64 encoded image data (original size)
     pub image_data: String,
+    /// Optional Base64 encoded mask of the same size; black pixels are ignored
+    #[serde(default)]
+    pub mask_data: Option<String>,
     /// Original file name for identification
     pub file_name: String,
 }
@@ -23,10 +44,105 @@ pub struct HintImageMatchResult {
     pub index: usize,
     /// Original file name
     pub file_name: String,
+    /// Monitor the result comes from (monitor or multi-screenshot matching only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor_id: Option<u32>,
+    /// Match target (or center) in desktop coordinates (monitor or multi-screenshot matching only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen_x: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen_y: Option<i32>,
     /// Match result with coordinates or error
     pub match_result: MatchResult,
 }
 
+/// Screenshot of one monitor with the capture parameters needed for desktop coordinates
+/// Field names match `CaptureResult`, so a capture can be passed as-is
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorScreenshot {
+    pub monitor_id: u32,
+    /// Base64 encoded screenshot (already resized for API)
+    pub image_base64: String,
+    #[serde(flatten)]
+    pub context: CoordinateContext,
+}
+
+/// Payload of the `match-progress` event, sent as each template match completes
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchProgressEvent {
+    /// Run token of the batch, to tell concurrent batches apart
+    pub token_id: Option<String>,
+    /// Template matches finished so far (cache hits included)
+    pub completed: usize,
+    /// Template matches in the batch (templates x screenshots)
+    pub total: usize,
+    /// Index of the hint image in the input array
+    pub index: usize,
+    /// Monitor the template was matched on (monitor or multi-screenshot matching only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor_id: Option<u32>,
+    pub match_result: MatchResult,
+}
+
+/// Reports finished template matches as `match-progress` events
+struct MatchProgress {
+    app: AppHandle,
+    token_id: Option<String>,
+    completed: AtomicUsize,
+    total: usize,
+    /// Stop the batch once any template is found
+    stop_after_first_match: bool,
+    stopped: AtomicBool,
+    operation: Arc<Operation>,
+}
+
+impl MatchProgress {
+    /// Report one finished match, breaking once the batch should stop
+    fn report(
+        &self,
+        index: usize,
+        monitor_id: Option<u32>,
+        result: &MatchResult,
+    ) -> ControlFlow<()> {
+        let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+        let event = MatchProgressEvent {
+            token_id: self.token_id.clone(),
+            completed,
+            total: self.total,
+            index,
+            monitor_id,
+            match_result: result.clone(),
+        };
+        if let Err(e) = self.app.emit("match-progress", event) {
+            error!("Failed to emit match progress: {}", e);
+        }
+        self.operation.progress(completed as u64, self.total as u64);
+
+        if self.stop_after_first_match && result.found {
+            self.stopped.store(true, Ordering::Relaxed);
+        }
+        if self.is_stopped() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+/// Match results of every template against one monitor (or a bare screenshot)
+/// Templates skipped after an early stop have no result
+struct MonitorMatches {
+    monitor_id: Option<u32>,
+    context: Option<CoordinateContext>,
+    results: Vec<Option<MatchResult>>,
+}
+
 /// Match multiple hint images against a screenshot
 ///
 /// # Arguments
@@ -34,10 +150,23 @@ pub struct HintImageMatchResult {
 /// * `template_images` - Array of hint images to match
 /// * `scale_factor` - Scale factor applied to screenshot (e.g., 0.6)
 /// * `confidence_threshold` - Optional minimum confidence (default: 0.7)
+/// * `token_id` - Optional run token; matching is abandoned with `Cancelled` when it fires
+/// * `options` - Optional matching options (transparent pixels are ignored by default)
+/// * `monitor_id` - Capture this monitor instead of taking `screenshot_base64`
+/// * `screenshots` - Match against several monitor screenshots instead
+/// * `stop_after_first_match` - Return as soon as any template is found (default: false)
 ///
 /// # Returns
 /// Array of match results, one per hint image. Each image is processed independently;
-/// errors in one image don't affect others.
+/// errors in one image don't affect others. After an early stop, templates that
+/// were not matched yet are left out.
+///
+/// # Multi-monitor Matching
+/// With `monitor_id` or `screenshots` the result also carries the monitor the
+/// template was found on and its center in desktop coordinates, ready for the
+/// mouse commands. Across several monitors the best match is kept. A captured
+/// monitor is matched at full resolution, so `center_x`/`center_y` are then
+/// physical pixels of that monitor.
 ///
 /// # Design Decision: Per-image Error Handling
 /// Individual image decode/matching failures are captured in `MatchResult.error`
@@ -47,57 +176,326 @@ pub struct HintImageMatchResult {
 /// # Performance Optimization
 /// Screenshot is decoded and converted to grayscale only once, then reused
 /// for all template matches. This significantly reduces CPU/memory usage
-/// when matching multiple hint images.
+/// when matching multiple hint images. Results are cached per screenshot,
+/// template and parameters, so repeated calls against an unchanged screen
+/// only match the templates not seen before.
 ///
 /// # Threading Model
 /// This command is async and uses `spawn_blocking` to offload CPU-intensive
-/// template matching to a worker thread, preventing UI blocking.
+/// template matching to a worker thread, preventing UI blocking. A
+/// `match-progress` event carrying the result is emitted as each template
+/// completes, so the UI can show progress and act on partial results.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn match_hint_images(
-    screenshot_base64: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    screenshot_base64: Option<String>,
     template_images: Vec<TemplateImage>,
-    scale_factor: f64,
+    scale_factor: Option<f64>,
     confidence_threshold: Option<f32>,
-) -> Result<Vec<HintImageMatchResult>, String> {
+    token_id: Option<String>,
+    options: Option<MatchOptions>,
+    monitor_id: Option<u32>,
+    screenshots: Option<Vec<MonitorScreenshot>>,
+    stop_after_first_match: Option<bool>,
+) -> Result<Vec<HintImageMatchResult>, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
     let threshold = confidence_threshold.unwrap_or(0.7);
+    let options = options.unwrap_or_default();
 
-    // Clone data for the blocking task
-    let screenshot = screenshot_base64;
-    let templates_owned: Vec<(String, String)> = template_images
-        .into_iter()
-        .map(|t| (t.image_data, t.file_name))
-        .collect();
+    let cache = state.match_cache.clone();
+    let sources = match (&screenshots, monitor_id) {
+        (Some(screenshots), _) => screenshots.len(),
+        _ => 1,
+    };
+    let operation = Arc::new(Operation::start(
+        &app,
+        "match_hint_images",
+        token_id.as_deref(),
+    ));
+    let progress = MatchProgress {
+        app,
+        token_id,
+        completed: AtomicUsize::new(0),
+        total: template_images.len() * sources,
+        stop_after_first_match: stop_after_first_match.unwrap_or(false),
+        stopped: AtomicBool::new(false),
+        operation: operation.clone(),
+    };
 
     // Offload CPU-intensive template matching to a worker thread
     // This prevents blocking the Tauri main thread and keeps UI responsive
-    let results = tauri::async_runtime::spawn_blocking(move || {
-        // Create references for batch processing
-        let templates: Vec<(&str, &str)> = templates_owned
+    let task = tauri::async_runtime::spawn_blocking(move || {
+        let monitors = if let Some(screenshots) = screenshots {
+            screenshots
+                .iter()
+                .take_while(|_| !progress.is_stopped())
+                .map(|screenshot| MonitorMatches {
+                    monitor_id: Some(screenshot.monitor_id),
+                    context: Some(screenshot.context),
+                    results: match_screenshot_cached(
+                        &cache,
+                        &screenshot.image_base64,
+                        &template_images,
+                        screenshot.context.scale_factor,
+                        threshold,
+                        &options,
+                        |i, result| progress.report(i, Some(screenshot.monitor_id), result),
+                    ),
+                })
+                .collect()
+        } else if let Some(monitor_id) = monitor_id {
+            let frame = grab_frame(Some(monitor_id), false).map_err(IpcError::from)?;
+            let results = template_images
+                .iter()
+                .enumerate()
+                .map(|(i, t)| {
+                    if progress.is_stopped() {
+                        return None;
+                    }
+                    let source = TemplateSource {
+                        image: t.image_data.as_str(),
+                        mask: t.mask_data.as_deref(),
+                    };
+                    let result = find_template_in_image(&frame.image, source, threshold, &options);
+                    let _ = progress.report(i, Some(frame.monitor_id), &result);
+                    Some(result)
+                })
+                .collect();
+            vec![MonitorMatches {
+                monitor_id: Some(frame.monitor_id),
+                context: Some(CoordinateContext {
+                    scale_factor: 1.0,
+                    display_scale_factor: frame.display_scale_factor,
+                    monitor_x: frame.monitor_x,
+                    monitor_y: frame.monitor_y,
+                }),
+                results,
+            }]
+        } else {
+            let screenshot = screenshot_base64.ok_or_else(|| {
+                XenotesterError::InvalidArgument(
+                    "Either screenshotBase64, monitorId or screenshots is required".to_string(),
+                )
+            })?;
+            let scale_factor = scale_factor.ok_or_else(|| {
+                XenotesterError::InvalidArgument(
+                    "scaleFactor is required with a screenshot".to_string(),
+                )
+            })?;
+            vec![MonitorMatches {
+                monitor_id: None,
+                context: None,
+                results: match_screenshot_cached(
+                    &cache,
+                    &screenshot,
+                    &template_images,
+                    scale_factor,
+                    threshold,
+                    &options,
+                    |i, result| progress.report(i, None, result),
+                ),
+            }]
+        };
+
+        Ok::<_, IpcError>(best_per_template(template_images, monitors))
+    });
+    let result = async move {
+        cancel
+            .run_until_cancelled(task)
+            .await
+            .map_err(IpcError::from)?
+            .map_err(|e| {
+                XenotesterError::Internal(format!("Template matching task failed: {}", e))
+            })?
+    }
+    .await;
+    operation.finish(&result);
+    result
+}
+
+/// Match every template against one screenshot, reusing cached results
+///
+/// `report` is called with the template index as each result is known; once it
+/// breaks, the remaining templates are skipped and have no result.
+fn match_screenshot_cached(
+    cache: &MatchCache,
+    screenshot: &str,
+    template_images: &[TemplateImage],
+    scale_factor: f64,
+    threshold: f32,
+    options: &MatchOptions,
+    report: impl Fn(usize, &MatchResult) -> ControlFlow<()> + Sync,
+) -> Vec<Option<MatchResult>> {
+    let screenshot_hash = MatchCache::hash_screenshot(screenshot);
+    let sources: Vec<TemplateSource<'_>> = template_images
+        .iter()
+        .map(|t| TemplateSource {
+            image: t.image_data.as_str(),
+            mask: t.mask_data.as_deref(),
+        })
+        .collect();
+    let keys: Vec<_> = sources
+        .iter()
+        .map(|source| MatchCache::key(&screenshot_hash, *source, scale_factor, threshold, options))
+        .collect();
+    let mut cached: Vec<Option<MatchResult>> = keys.iter().map(|key| cache.get(key)).collect();
+    for (i, result) in cached.iter().enumerate() {
+        if let Some(result) = result {
+            if report(i, result).is_break() {
+                return cached;
+            }
+        }
+    }
+
+    // Process the uncached templates with single screenshot decode
+    let misses: Vec<usize> = (0..sources.len())
+        .filter(|&i| cached[i].is_none())
+        .collect();
+    if !misses.is_empty() {
+        let templates: Vec<(TemplateSource<'_>, &str)> = misses
             .iter()
-            .map(|(data, name)| (data.as_str(), name.as_str()))
+            .map(|&i| (sources[i], template_images[i].file_name.as_str()))
             .collect();
-
-        // Process all templates with single screenshot decode
-        let batch_results = match_templates_batch(
-            &screenshot,
+        let batch_results = match_templates_batch_with_progress(
+            screenshot,
             templates,
             scale_factor,
             threshold,
+            options,
+            |j, result| report(misses[j], result),
         );
+        for (&i, (_, match_result)) in misses.iter().zip(batch_results) {
+            let Some(match_result) = match_result else {
+                continue;
+            };
+            // A screenshot that failed to decode may be fine next time
+            if match_result.error_code != Some(MatchErrorCode::ScreenshotDecodeError) {
+                cache.insert(keys[i], match_result.clone());
+            }
+            cached[i] = Some(match_result);
+        }
+    }
 
-        // Rebuild results with array index (matches input order)
-        batch_results
-            .into_iter()
-            .enumerate()
-            .map(|(index, (file_name, match_result))| HintImageMatchResult {
+    cached
+}
+
+/// Keep the best match of each template across monitors (found first, then confidence)
+fn best_per_template(
+    template_images: Vec<TemplateImage>,
+    monitors: Vec<MonitorMatches>,
+) -> Vec<HintImageMatchResult> {
+    let mut best: Vec<Option<(usize, MatchResult)>> =
+        template_images.iter().map(|_| None).collect();
+    for (m, monitor) in monitors.iter().enumerate() {
+        for (slot, result) in best.iter_mut().zip(&monitor.results) {
+            let Some(result) = result else {
+                continue;
+            };
+            let better = slot.as_ref().is_none_or(|(_, current)| {
+                (result.found, result.confidence.unwrap_or(0.0))
+                    > (current.found, current.confidence.unwrap_or(0.0))
+            });
+            if better {
+                *slot = Some((m, result.clone()));
+            }
+        }
+    }
+
+    // Rebuild results with array index (matches input order)
+    template_images
+        .into_iter()
+        .zip(best)
+        .enumerate()
+        .filter_map(|(index, (template, best))| {
+            let (m, match_result) = best?;
+            let monitor = &monitors[m];
+            // The target when one was requested, otherwise the center
+            let point = match_result
+                .target_x
+                .zip(match_result.target_y)
+                .or(match_result.center_x.zip(match_result.center_y))
+                .filter(|_| match_result.found);
+            let screen = point.zip(monitor.context).map(|((x, y), context)| {
+                let point = translate(
+                    x as f64,
+                    y as f64,
+                    CoordinateSpace::Screenshot,
+                    CoordinateSpace::Logical,
+                    &context,
+                );
+                (point.x.round() as i32, point.y.round() as i32)
+            });
+            Some(HintImageMatchResult {
                 index,
-                file_name,
+                file_name: template.file_name,
+                monitor_id: monitor.monitor_id,
+                screen_x: screen.map(|(x, _)| x),
+                screen_y: screen.map(|(_, y)| y),
                 match_result,
             })
-            .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Template found on the live screen after one or more attempts
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetriedMatch {
+    #[serde(flatten)]
+    pub screen_match: ScreenMatch,
+    pub attempts: u32,
+}
+
+/// Capture the screen and look for a template, retrying with backoff until it is found
+///
+/// # Arguments
+/// * `template_image` - Base64 encoded template (original size, as it appears on screen)
+/// * `monitor_id` - Monitor to search (primary when omitted)
+/// * `confidence_threshold` - Optional minimum confidence (default: 0.8)
+/// * `retry` - Retry policy (default: 2 retries, 500ms doubling backoff)
+/// * `token_id` - Optional run token observed between attempts
+///
+/// Fails with the last error once the retries are used up. The returned position
+/// is in screen coordinates, ready for the mouse commands.
+#[tauri::command]
+pub async fn match_with_retry(
+    state: State<'_, AppState>,
+    template_image: String,
+    monitor_id: Option<u32>,
+    confidence_threshold: Option<f32>,
+    retry: Option<RetryPolicy>,
+    token_id: Option<String>,
+) -> Result<RetriedMatch, IpcError> {
+    let cancel = state.cancel_token(token_id.as_deref())?;
+    let threshold = confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD);
+    let policy = retry.unwrap_or_default();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        with_retry(&policy, &cancel, |_| {
+            let screen_match = find_template_on_screen(&template_image, monitor_id, threshold)?;
+            if screen_match.found {
+                Ok(screen_match)
+            } else {
+                Err(XenotesterError::ImageError(format!(
+                    "Template not found (best confidence {:.2})",
+                    screen_match.confidence.unwrap_or(0.0)
+                )))
+            }
+        })
+        .map(|(screen_match, attempts)| RetriedMatch {
+            screen_match,
+            attempts,
+        })
+        .map_err(IpcError::from)
     })
     .await
-    .map_err(|e| format!("Template matching task failed: {}", e))?;
+    .map_err(|e| XenotesterError::Internal(format!("Template matching task failed: {}", e)))?
+}
 
-    Ok(results)
+/// Drop all cached template match results, returning how many there were
+#[tauri::command]
+pub fn clear_match_cache(state: State<'_, AppState>) -> usize {
+    state.match_cache.clear()
 }
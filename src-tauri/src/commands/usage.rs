@@ -0,0 +1,20 @@
+//! Token usage commands
+
+use tauri::AppHandle;
+
+use crate::error::IpcError;
+use crate::services::database::get_pool;
+use crate::services::usage::{self, UsagePeriod, UsageSummary};
+
+/// Get aggregated token usage and estimated cost
+/// period: "day", "week", "month", or "all"
+#[tauri::command]
+pub async fn get_usage_summary(
+    app: AppHandle,
+    period: UsagePeriod,
+) -> Result<UsageSummary, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    usage::get_usage_summary(&pool, period)
+        .await
+        .map_err(IpcError::from)
+}
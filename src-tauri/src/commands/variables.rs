@@ -0,0 +1,50 @@
+//! Run variable commands
+//!
+//! Values for `${NAME}` placeholders in scenarios. They apply to agent
+//! sessions started afterwards and are cleared when the run finishes.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+
+use crate::error::IpcError;
+use crate::services::database::get_pool;
+use crate::services::dataset::{self, DatasetRow};
+use crate::services::variables::Variables;
+use crate::state::AppState;
+
+/// Set the variables of the current run, replacing previous ones
+#[tauri::command]
+pub async fn set_run_variables(
+    state: State<'_, AppState>,
+    variables: HashMap<String, String>,
+) -> Result<(), IpcError> {
+    *state.run_variables.lock().map_err(IpcError::from)? = variables;
+    Ok(())
+}
+
+/// Resolve the placeholders in a step parameter (text, URL, expected value)
+/// Secrets are decrypted, so don't display the result.
+#[tauri::command]
+pub async fn resolve_variables(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    text: String,
+) -> Result<String, IpcError> {
+    let values = state.run_variables.lock().map_err(IpcError::from)?.clone();
+    let mut variables = Variables::new(values);
+
+    let pool = get_pool(&app).await.ok();
+    variables
+        .load_secrets(pool.as_ref(), &[&text])
+        .await
+        .map_err(IpcError::from)?;
+    variables.substitute(&text).map_err(IpcError::from)
+}
+
+/// Load the rows of a CSV or JSON dataset, for running a scenario once per
+/// row with `set_run_variables`. Each row is a list of [column, value] pairs.
+#[tauri::command]
+pub async fn load_dataset(path: String) -> Result<Vec<DatasetRow>, IpcError> {
+    dataset::load_dataset(&PathBuf::from(path)).map_err(IpcError::from)
+}
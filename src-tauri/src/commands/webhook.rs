@@ -1,83 +1,155 @@
 //! Webhook commands for sending HTTP notifications
 //!
 //! This module handles sending webhook notifications from the Rust backend
-//! to avoid CORS restrictions that would occur in the frontend.
+//! to avoid CORS restrictions that would occur in the frontend. It also
+//! assembles the notifier of backend runs, including desktop notifications.
 
-use serde::{Deserialize, Serialize};
-use url::Url;
+use serde_json::json;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tracing::warn;
 
-/// Webhook payload structure
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WebhookPayload {
-    pub event: String,
-    pub timestamp: String,
-    pub scenario: ScenarioInfo,
-    pub error: ErrorInfo,
-}
+use crate::error::{IpcError, XenotesterError};
+use crate::services::database::get_pool;
+use crate::services::image_processor::create_thumbnail_png;
+use crate::services::notify::desktop::{self, DesktopChannel, DesktopPreferences};
+use crate::services::notify::{self, Notifier, WebhookSettings};
+use crate::services::webhook::{
+    is_valid_webhook_url, post_webhook, NotificationFormat, ScreenshotDelivery, WebhookPayload,
+    EMERGENCY_STOP_EVENT,
+};
+use crate::state::AppState;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ScenarioInfo {
-    pub id: String,
-    pub title: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ErrorInfo {
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub failed_at_action: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub last_successful_action: Option<String>,
-    pub completed_actions: i32,
-}
+/// Maximum long edge of the screenshot thumbnail attached to notifications
+const THUMBNAIL_MAX_EDGE: u32 = 640;
 
 /// Send a POST request to the specified webhook URL
 /// Returns silently on error to avoid interrupting test execution
+///
+/// `payload` is an event envelope (`event`, `timestamp`, `data`).
+/// `notification_format` renders the payload for Slack/Discord/Teams (default: raw JSON).
+/// `screenshot_base64` is downscaled to a thumbnail and attached where the format supports it,
+/// to raw payloads as `screenshot_delivery` says (default: inline).
 #[tauri::command]
-pub async fn send_webhook(url: String, payload: WebhookPayload) -> Result<bool, String> {
-    // Validate URL format
-    if url.trim().is_empty() {
+pub async fn send_webhook(
+    url: String,
+    payload: WebhookPayload,
+    notification_format: Option<NotificationFormat>,
+    screenshot_base64: Option<String>,
+    screenshot_delivery: Option<ScreenshotDelivery>,
+) -> Result<bool, IpcError> {
+    if !is_valid_webhook_url(&url) {
         return Ok(false);
     }
 
-    let parsed_url = match Url::parse(&url) {
-        Ok(u) => u,
-        Err(e) => {
-            eprintln!("[Webhook] Invalid URL: {}", e);
-            return Ok(false);
-        }
+    // Thumbnail generation is CPU-bound, keep it off the async runtime
+    let thumbnail = match screenshot_base64 {
+        Some(screenshot) => tauri::async_runtime::spawn_blocking(move || {
+            create_thumbnail_png(&screenshot, THUMBNAIL_MAX_EDGE)
+        })
+        .await
+        .map_err(|e| XenotesterError::Internal(format!("Thumbnail task failed: {}", e)))?
+        .map_err(|e| warn!("Failed to create screenshot thumbnail: {}", e))
+        .ok(),
+        None => None,
     };
 
-    // Only allow http/https schemes
-    if parsed_url.scheme() != "http" && parsed_url.scheme() != "https" {
-        eprintln!("[Webhook] Invalid URL scheme: {}", parsed_url.scheme());
-        return Ok(false);
-    }
+    post_webhook(
+        &url,
+        &payload,
+        notification_format.unwrap_or_default(),
+        thumbnail,
+        screenshot_delivery.unwrap_or_default(),
+    )
+    .await
+    .map_err(IpcError::from)
+}
 
-    let client = reqwest::Client::new();
-    match client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&payload)
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
+/// Get the webhook format and subscribed events
+#[tauri::command]
+pub async fn get_webhook_settings(app: AppHandle) -> Result<WebhookSettings, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    notify::load_webhook_settings(&pool)
         .await
-    {
-        Ok(response) => {
-            if response.status().is_success() {
-                Ok(true)
-            } else {
-                eprintln!(
-                    "[Webhook] Request failed: {} {}",
-                    response.status().as_u16(),
-                    response.status().canonical_reason().unwrap_or("Unknown")
-                );
-                Ok(false)
-            }
-        }
+        .map_err(IpcError::from)
+}
+
+/// Set the webhook format and subscribed events
+/// Events: test_failure, run_started, run_completed, step_failed, emergency_stop or "*"
+/// With `attachScreenshot`, failure events of backend runs carry a screenshot thumbnail.
+#[tauri::command]
+pub async fn set_webhook_settings(
+    app: AppHandle,
+    settings: WebhookSettings,
+) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    notify::save_webhook_settings(&pool, &settings)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Get which run outcomes show a desktop notification
+#[tauri::command]
+pub async fn get_desktop_notification_preferences(
+    app: AppHandle,
+) -> Result<DesktopPreferences, IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    desktop::load_preferences(&pool)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Set which run outcomes show a desktop notification
+#[tauri::command]
+pub async fn set_desktop_notification_preferences(
+    app: AppHandle,
+    preferences: DesktopPreferences,
+) -> Result<(), IpcError> {
+    let pool = get_pool(&app).await.map_err(IpcError::from)?;
+    desktop::save_preferences(&pool, &preferences)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Notifier for backend runs, from the stored settings
+/// Runs proceed without notifications when the settings cannot be read.
+pub async fn load_notifier(app: &AppHandle) -> Notifier {
+    let pool = match get_pool(app).await {
+        Ok(pool) => pool,
         Err(e) => {
-            eprintln!("[Webhook] Request error: {}", e);
-            Ok(false)
+            warn!("Notifications are disabled: {}", e);
+            return Notifier::disabled();
         }
-    }
+    };
+    let notifier = Notifier::from_settings(&pool).await.unwrap_or_else(|e| {
+        warn!("Webhook and email notifications are disabled: {}", e);
+        Notifier::disabled()
+    });
+
+    let preferences = desktop::load_preferences(&pool).await.unwrap_or_else(|e| {
+        warn!("Using the default desktop notification preferences: {}", e);
+        DesktopPreferences::default()
+    });
+    let app = app.clone();
+    notifier.with_desktop(DesktopChannel::new(
+        preferences,
+        Arc::new(move |title: &str, body: &str| {
+            if let Err(e) = app.notification().builder().title(title).body(body).show() {
+                warn!("Failed to show a desktop notification: {}", e);
+            }
+        }),
+    ))
+}
+
+/// Report an emergency stop to the subscribed channels
+pub fn notify_emergency_stop(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let active_run = app.state::<AppState>().active_run();
+        load_notifier(&app)
+            .await
+            .emit(EMERGENCY_STOP_EVENT, json!({ "activeRun": active_run }))
+            .await;
+    });
 }
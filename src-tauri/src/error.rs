@@ -3,6 +3,8 @@
 use serde::Serialize;
 use thiserror::Error;
 
+use crate::services::locale;
+
 /// Application-level errors
 #[derive(Error, Debug)]
 pub enum XenotesterError {
@@ -21,34 +23,246 @@ pub enum XenotesterError {
     #[error("Image processing error: {0}")]
     ImageError(String),
 
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+
+    #[error("LLM request failed: {0}")]
+    LlmError(String),
+
+    #[error("Recording failed: {0}")]
+    RecordingError(String),
+
+    #[error("Accessibility query failed: {0}")]
+    AccessibilityError(String),
+
+    #[error("Process operation failed: {0}")]
+    ProcessError(String),
+
+    #[error("Secret storage error: {0}")]
+    SecretError(String),
+
+    #[error("Remote control error: {0}")]
+    RemoteError(String),
+
+    #[error("Authentication failed: {0}")]
+    AuthError(String),
+
+    #[error("Notification failed: {0}")]
+    NotificationError(String),
+
+    #[error("Network error: {0}")]
+    NetworkError(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// A command argument was rejected; the message says which and why
+    #[error("{0}")]
+    InvalidArgument(String),
+
+    /// An input action was refused by the safety governor or declined in a
+    /// confirmation; the message says by which and why
+    #[error("{0}")]
+    Blocked(String),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
     #[error("Operation cancelled")]
     Cancelled,
 }
 
-/// Serializable error for IPC responses
-#[derive(Debug, Serialize)]
-pub struct IpcError {
-    pub code: String,
-    pub message: String,
+/// Part of the app an error comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Capture,
+    Input,
+    Permissions,
+    Config,
+    Image,
+    Database,
+    Llm,
+    Recording,
+    Accessibility,
+    Process,
+    Secrets,
+    Remote,
+    Auth,
+    Notifications,
+    Network,
+    /// Run control: timeouts and cancellation
+    Runner,
+    /// Lookups, argument checks and internal failures
+    General,
 }
 
-impl From<XenotesterError> for IpcError {
-    fn from(err: XenotesterError) -> Self {
-        let code = match &err {
+impl XenotesterError {
+    /// Stable identifier of the error kind
+    pub fn code(&self) -> &'static str {
+        match self {
             XenotesterError::CaptureError(_) => "CAPTURE_ERROR",
             XenotesterError::InputError(_) => "INPUT_ERROR",
             XenotesterError::PermissionError(_) => "PERMISSION_ERROR",
             XenotesterError::ConfigError(_) => "CONFIG_ERROR",
             XenotesterError::ImageError(_) => "IMAGE_ERROR",
+            XenotesterError::DatabaseError(_) => "DATABASE_ERROR",
+            XenotesterError::LlmError(_) => "LLM_ERROR",
+            XenotesterError::RecordingError(_) => "RECORDING_ERROR",
+            XenotesterError::AccessibilityError(_) => "ACCESSIBILITY_ERROR",
+            XenotesterError::ProcessError(_) => "PROCESS_ERROR",
+            XenotesterError::SecretError(_) => "SECRET_ERROR",
+            XenotesterError::RemoteError(_) => "REMOTE_ERROR",
+            XenotesterError::AuthError(_) => "AUTH_ERROR",
+            XenotesterError::NotificationError(_) => "NOTIFICATION_ERROR",
+            XenotesterError::NetworkError(_) => "NETWORK_ERROR",
+            XenotesterError::NotFound(_) => "NOT_FOUND",
+            XenotesterError::InvalidArgument(_) => "INVALID_ARGUMENT",
+            XenotesterError::Blocked(_) => "BLOCKED",
+            XenotesterError::Internal(_) => "INTERNAL_ERROR",
+            XenotesterError::Timeout(_) => "TIMEOUT",
             XenotesterError::Cancelled => "CANCELLED",
-        };
+        }
+    }
+
+    pub fn subsystem(&self) -> Subsystem {
+        match self {
+            XenotesterError::CaptureError(_) => Subsystem::Capture,
+            XenotesterError::InputError(_) | XenotesterError::Blocked(_) => Subsystem::Input,
+            XenotesterError::PermissionError(_) => Subsystem::Permissions,
+            XenotesterError::ConfigError(_) => Subsystem::Config,
+            XenotesterError::ImageError(_) => Subsystem::Image,
+            XenotesterError::DatabaseError(_) => Subsystem::Database,
+            XenotesterError::LlmError(_) => Subsystem::Llm,
+            XenotesterError::RecordingError(_) => Subsystem::Recording,
+            XenotesterError::AccessibilityError(_) => Subsystem::Accessibility,
+            XenotesterError::ProcessError(_) => Subsystem::Process,
+            XenotesterError::SecretError(_) => Subsystem::Secrets,
+            XenotesterError::RemoteError(_) => Subsystem::Remote,
+            XenotesterError::AuthError(_) => Subsystem::Auth,
+            XenotesterError::NotificationError(_) => Subsystem::Notifications,
+            XenotesterError::NetworkError(_) => Subsystem::Network,
+            XenotesterError::NotFound(_)
+            | XenotesterError::InvalidArgument(_)
+            | XenotesterError::Internal(_) => Subsystem::General,
+            XenotesterError::Timeout(_) | XenotesterError::Cancelled => Subsystem::Runner,
+        }
+    }
+
+    /// What went wrong, without the English category of `Display`
+    fn reason(&self) -> Option<&str> {
+        match self {
+            XenotesterError::CaptureError(reason)
+            | XenotesterError::InputError(reason)
+            | XenotesterError::PermissionError(reason)
+            | XenotesterError::ConfigError(reason)
+            | XenotesterError::ImageError(reason)
+            | XenotesterError::DatabaseError(reason)
+            | XenotesterError::LlmError(reason)
+            | XenotesterError::RecordingError(reason)
+            | XenotesterError::AccessibilityError(reason)
+            | XenotesterError::ProcessError(reason)
+            | XenotesterError::SecretError(reason)
+            | XenotesterError::RemoteError(reason)
+            | XenotesterError::AuthError(reason)
+            | XenotesterError::NotificationError(reason)
+            | XenotesterError::NetworkError(reason)
+            | XenotesterError::NotFound(reason)
+            | XenotesterError::InvalidArgument(reason)
+            | XenotesterError::Blocked(reason)
+            | XenotesterError::Internal(reason)
+            | XenotesterError::Timeout(reason) => Some(reason),
+            XenotesterError::Cancelled => None,
+        }
+    }
+
+    /// Message for the user: the catalog category in `locale` followed by the
+    /// reason, or the English text when the catalog has none
+    fn localized_message(&self, locale: locale::Locale) -> String {
+        match (locale::error_message(self.code(), locale), self.reason()) {
+            (Some(category), Some(reason)) => format!("{}: {}", category, reason),
+            (Some(category), None) => category.to_string(),
+            (None, _) => self.to_string(),
+        }
+    }
+
+    /// Whether trying the same operation again may succeed
+    /// Screen, input and network conditions are transient; configuration,
+    /// permissions, stored data, rejected requests and blocked actions are not.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            XenotesterError::CaptureError(_)
+                | XenotesterError::InputError(_)
+                | XenotesterError::ImageError(_)
+                | XenotesterError::AccessibilityError(_)
+                | XenotesterError::NetworkError(_)
+                | XenotesterError::Timeout(_)
+        )
+    }
+}
+
+/// Serializable error for IPC responses, returned by every command
+#[derive(Debug, Serialize)]
+pub struct IpcError {
+    pub code: String,
+    /// Message for the user, in the language selected with `set_locale`
+    pub message: String,
+    /// English description from the failing service, for logs and reports
+    pub detail: String,
+    /// Whether the frontend may try the same call again
+    pub retryable: bool,
+    pub subsystem: Subsystem,
+}
+
+impl From<XenotesterError> for IpcError {
+    fn from(err: XenotesterError) -> Self {
         IpcError {
-            code: code.to_string(),
-            message: err.to_string(),
+            code: err.code().to_string(),
+            message: err.localized_message(locale::locale()),
+            detail: err.to_string(),
+            retryable: err.is_retryable(),
+            subsystem: err.subsystem(),
         }
     }
 }
 
+impl std::fmt::Display for IpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.detail)
+    }
+}
+
+/// Errors that reach commands through `?`, converted like `XenotesterError`
+macro_rules! ipc_error_from {
+    ($($source:ty),* $(,)?) => {$(
+        impl From<$source> for IpcError {
+            fn from(err: $source) -> Self {
+                XenotesterError::from(err).into()
+            }
+        }
+    )*};
+}
+
+ipc_error_from!(
+    std::io::Error,
+    image::ImageError,
+    sqlx::Error,
+    reqwest::Error,
+    tauri::Error,
+    tokio::task::JoinError,
+    serde_json::Error,
+);
+
+impl<T> From<std::sync::PoisonError<T>> for IpcError {
+    fn from(err: std::sync::PoisonError<T>) -> Self {
+        XenotesterError::from(err).into()
+    }
+}
+
 impl From<XenotesterError> for String {
     fn from(err: XenotesterError) -> Self {
         err.to_string()
@@ -66,3 +280,95 @@ impl From<image::ImageError> for XenotesterError {
         XenotesterError::ImageError(err.to_string())
     }
 }
+
+impl From<sqlx::Error> for XenotesterError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => XenotesterError::NotFound("database row".to_string()),
+            sqlx::Error::PoolTimedOut => {
+                XenotesterError::Timeout("waiting for a database connection".to_string())
+            }
+            err => XenotesterError::DatabaseError(err.to_string()),
+        }
+    }
+}
+
+impl From<tauri::Error> for XenotesterError {
+    fn from(err: tauri::Error) -> Self {
+        XenotesterError::Internal(err.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for XenotesterError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        XenotesterError::Internal(format!("Background task failed: {}", err))
+    }
+}
+
+impl From<serde_json::Error> for XenotesterError {
+    fn from(err: serde_json::Error) -> Self {
+        XenotesterError::Internal(format!("JSON error: {}", err))
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for XenotesterError {
+    fn from(err: std::sync::PoisonError<T>) -> Self {
+        XenotesterError::Internal(err.to_string())
+    }
+}
+
+impl From<reqwest::Error> for XenotesterError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            XenotesterError::Timeout(err.to_string())
+        } else {
+            XenotesterError::NetworkError(err.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ipc_error_carries_retryability() {
+        let err = IpcError::from(XenotesterError::Timeout("step".to_string()));
+        assert_eq!(err.code, "TIMEOUT");
+        assert!(err.retryable);
+        assert_eq!(err.subsystem, Subsystem::Runner);
+
+        let err = IpcError::from(XenotesterError::NotFound("scenario s1".to_string()));
+        assert_eq!(
+            (err.code.as_str(), err.retryable, err.message.as_str()),
+            ("NOT_FOUND", false, "Not found: scenario s1")
+        );
+        assert_eq!(err.detail, "Not found: scenario s1");
+        assert!(!XenotesterError::Cancelled.is_retryable());
+
+        let err = IpcError::from(XenotesterError::Blocked("declined".to_string()));
+        assert_eq!((err.code.as_str(), err.retryable), ("BLOCKED", false));
+        assert_eq!(err.subsystem, Subsystem::Input);
+    }
+
+    #[test]
+    fn test_localized_message_keeps_the_reason() {
+        let err = XenotesterError::NotFound("scenario s1".to_string());
+        assert_eq!(
+            err.localized_message(locale::Locale::Ja),
+            "対象が見つかりません: scenario s1"
+        );
+        assert_eq!(
+            err.localized_message(locale::Locale::De),
+            "Nicht gefunden: scenario s1"
+        );
+        assert_eq!(
+            err.localized_message(locale::Locale::En),
+            "Not found: scenario s1"
+        );
+        assert_eq!(
+            XenotesterError::Cancelled.localized_message(locale::Locale::De),
+            "Der Vorgang wurde abgebrochen"
+        );
+    }
+}
@@ -9,12 +9,14 @@ pub mod services;
 pub mod state;
 pub mod utils;
 
-use commands::{config, control, input, permission, screenshot, template_match, webhook};
+use commands::{accessibility, agent, artifacts, assert, baseline, config, control, coords, debugger, email, environment, focus_mode, health, history, input, journal, logs, oauth, orchestrator, permission, privacy, process, recording, remote, scenario, schema, screenshot, secrets, sync, template_match, usage, variables, webhook};
 use state::AppState;
+use tauri::Manager;
 use tauri_plugin_sql::{Migration, MigrationKind};
 use utils::hotkey::register_emergency_stop;
 
 /// Get SQLite migrations
+/// Every version has a Down entry; see `services::schema` for reverting.
 fn get_migrations() -> Vec<Migration> {
     vec![
         Migration {
@@ -23,18 +25,144 @@ fn get_migrations() -> Vec<Migration> {
             sql: include_str!("../migrations/001_create_scenarios.sql"),
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 1,
+            description: "drop_scenarios_table",
+            sql: include_str!("../migrations/001_create_scenarios.down.sql"),
+            kind: MigrationKind::Down,
+        },
         Migration {
             version: 2,
             description: "create_step_images_table",
             sql: include_str!("../migrations/002_create_step_images.sql"),
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 2,
+            description: "drop_step_images_table",
+            sql: include_str!("../migrations/002_create_step_images.down.sql"),
+            kind: MigrationKind::Down,
+        },
         Migration {
             version: 3,
             description: "create_settings_table",
             sql: include_str!("../migrations/003_create_settings.sql"),
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 3,
+            description: "drop_settings_table",
+            sql: include_str!("../migrations/003_create_settings.down.sql"),
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 4,
+            description: "create_run_history_tables",
+            sql: include_str!("../migrations/004_create_run_history.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 4,
+            description: "drop_run_history_tables",
+            sql: include_str!("../migrations/004_create_run_history.down.sql"),
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 5,
+            description: "create_llm_usage_table",
+            sql: include_str!("../migrations/005_create_llm_usage.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "drop_llm_usage_table",
+            sql: include_str!("../migrations/005_create_llm_usage.down.sql"),
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 6,
+            description: "create_baselines_table",
+            sql: include_str!("../migrations/006_create_baselines.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "drop_baselines_table",
+            sql: include_str!("../migrations/006_create_baselines.down.sql"),
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 7,
+            description: "create_action_log_table",
+            sql: include_str!("../migrations/007_create_action_log.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 7,
+            description: "drop_action_log_table",
+            sql: include_str!("../migrations/007_create_action_log.down.sql"),
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 8,
+            description: "create_secrets_table",
+            sql: include_str!("../migrations/008_create_secrets.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 8,
+            description: "drop_secrets_table",
+            sql: include_str!("../migrations/008_create_secrets.down.sql"),
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 9,
+            description: "add_scenario_steps",
+            sql: include_str!("../migrations/009_add_scenario_steps.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 9,
+            description: "drop_scenario_steps",
+            sql: include_str!("../migrations/009_add_scenario_steps.down.sql"),
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 10,
+            description: "create_sync_queue_table",
+            sql: include_str!("../migrations/010_create_sync_queue.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 10,
+            description: "drop_sync_queue_table",
+            sql: include_str!("../migrations/010_create_sync_queue.down.sql"),
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 11,
+            description: "add_step_metrics",
+            sql: include_str!("../migrations/011_add_step_metrics.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 11,
+            description: "drop_step_metrics",
+            sql: include_str!("../migrations/011_add_step_metrics.down.sql"),
+            kind: MigrationKind::Down,
+        },
+        Migration {
+            version: 12,
+            description: "add_llm_usage_savings",
+            sql: include_str!("../migrations/012_add_llm_usage_savings.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 12,
+            description: "drop_llm_usage_savings",
+            sql: include_str!("../migrations/012_add_llm_usage_savings.down.sql"),
+            kind: MigrationKind::Down,
+        },
     ]
 }
 
@@ -52,61 +180,261 @@ pub fn run() {
         .plugin(tauri_plugin_oauth::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_notification::init())
         // SQLite plugin with migrations
         .plugin(
             tauri_plugin_sql::Builder::default()
-                .add_migrations("sqlite:xenotester.db", get_migrations())
+                .add_migrations(services::database::DB_URL, get_migrations())
                 .build(),
         )
         // Set up emergency stop hotkey
         .setup(|app| {
+            // Log to stderr and to daily files in the app log directory
+            utils::logging::init(app.path().app_log_dir().ok().as_deref());
+
             // Register emergency stop hotkey (Shift+Escape)
             register_emergency_stop(app.handle().clone());
 
+            // Ask the frontend to approve actions matching the confirmation rules
+            control::emit_confirmation_requests(app.handle());
+
+            // Announce backend runs paused at breakpoints
+            debugger::emit_breakpoint_hits(app.handle());
+
+            // Yield to the user when they take the mouse during automation
+            control::watch_user_activity(app.handle());
+
+            // Report displays being connected, disconnected or reconfigured
+            screenshot::emit_monitor_changes(app.handle());
+
+            // Route outbound requests through the stored proxy and CA settings
+            config::load_network_config(app.handle());
+
+            // Show error messages in the stored language
+            config::load_locale(app.handle());
+
+            // Keep disk-backed capture history frames in the cache directory
+            screenshot::init_capture_history(app.handle());
+
+            // Remove run artifacts beyond the cleanup policy
+            artifacts::cleanup_on_startup(app.handle());
+
+            // Resume executing orchestrator jobs if configured to
+            orchestrator::start_on_startup(app.handle());
+
             Ok(())
         })
         // Manage application state
         .manage(AppState::new())
         // Register IPC command handlers
         .invoke_handler(tauri::generate_handler![
+            // Health commands
+            health::system_health,
             // Permission commands
             permission::check_permissions,
             permission::request_screen_recording_permission,
             permission::request_accessibility_permission,
+            // Accessibility commands
+            accessibility::get_ui_tree,
+            accessibility::find_element,
+            accessibility::get_element_bounds,
+            accessibility::click_element,
             // Screenshot commands
             screenshot::get_monitors,
+            screenshot::get_capture_throttle,
+            screenshot::set_capture_throttle,
             screenshot::capture_screen,
+            screenshot::capture_screen_raw,
+            screenshot::capture_screen_if_changed,
+            screenshot::capture_thumbnail,
+            screenshot::start_capture_stream,
+            screenshot::stop_capture_stream,
+            screenshot::annotate_screenshot,
+            screenshot::compare_screenshots,
+            screenshot::wait_for_screen_idle,
             screenshot::capture_monitor_by_id,
+            screenshot::capture_all_monitors,
+            screenshot::get_recent_captures,
+            screenshot::get_capture_history_settings,
+            screenshot::set_capture_history_settings,
             screenshot::ensure_directory,
             screenshot::save_base64_image,
+            // Privacy commands
+            privacy::get_redaction_zones,
+            privacy::set_redaction_zones,
+            privacy::get_privacy_mode,
+            privacy::set_privacy_mode,
+            // Artifact commands
+            artifacts::save_artifact,
+            artifacts::list_artifacts,
+            artifacts::cleanup_artifacts,
+            artifacts::get_artifact_policy,
+            artifacts::set_artifact_policy,
+            // Coordinate commands
+            coords::translate_coordinates,
             // Input commands
+            input::get_mouse_position,
             input::mouse_move,
+            input::mouse_move_smooth,
             input::left_click,
             input::right_click,
             input::middle_click,
+            input::click_button,
+            input::click_with_retry,
+            input::click_template,
             input::double_click,
             input::triple_click,
             input::left_mouse_down,
             input::left_mouse_up,
             input::left_click_drag,
+            input::left_click_drag_path,
             input::scroll,
+            input::scroll_pixels,
             input::type_text,
+            input::type_text_paste,
             input::key,
             input::hold_key,
+            input::play_input_script,
+            input::list_supported_keys,
             // Control commands
             control::request_stop,
             control::clear_stop,
             control::is_stop_requested,
+            control::create_run_token,
+            control::cancel_run,
+            control::release_run_token,
             control::wait,
+            control::get_governor_config,
+            control::set_governor_config,
+            control::get_confirmation_rules,
+            control::set_confirmation_rules,
+            control::confirm_action,
+            control::get_user_activity_config,
+            control::set_user_activity_config,
+            // Environment snapshot commands
+            environment::snapshot_environment,
+            environment::restore_environment,
+            // Do Not Disturb commands
+            focus_mode::enable_focus_mode,
+            focus_mode::restore_focus_mode,
+            focus_mode::get_focus_mode_config,
+            focus_mode::set_focus_mode_config,
+            // Assertion commands
+            assert::assert_template_visible,
+            assert::assert_template_absent,
+            assert::assert_pixel_color,
+            // Baseline commands
+            baseline::save_baseline,
+            baseline::assert_matches_baseline,
             // Config commands
             config::get_api_key,
             config::is_api_key_configured,
             config::get_supabase_config,
+            config::get_network_config,
+            config::set_network_config,
+            config::test_connectivity,
+            config::get_locale,
+            config::set_locale,
+            // Agent commands
+            agent::run_agent_step,
+            agent::run_agent_loop,
+            // Scenario commands
+            scenario::list_scenarios,
+            scenario::get_scenario,
+            scenario::create_scenario,
+            scenario::update_scenario,
+            scenario::delete_scenario,
+            scenario::set_scenario_steps,
+            scenario::run_scenario_from_step,
+            scenario::get_run_timeouts,
+            scenario::set_run_timeouts,
+            // Scenario debugger commands
+            debugger::set_breakpoint,
+            debugger::clear_breakpoint,
+            debugger::get_breakpoints,
+            debugger::step_over,
+            debugger::continue_run,
+            // Schema commands
+            schema::get_schema_version,
+            schema::revert_schema,
+            // Secret commands
+            secrets::store_secret,
+            secrets::resolve_secret,
+            secrets::list_secrets,
+            secrets::delete_secret,
+            // Variable commands
+            variables::set_run_variables,
+            variables::resolve_variables,
+            variables::load_dataset,
+            // Run history commands
+            history::start_run,
+            history::record_step_result,
+            history::finish_run,
+            history::get_action_log,
+            history::export_run_report,
+            history::get_run_summary,
+            history::analyze_flakiness,
+            // Run journal commands
+            journal::get_interrupted_runs,
+            journal::resume_run,
+            journal::discard_interrupted_run,
+            // Process commands
+            process::launch_app,
+            process::terminate_app,
+            process::is_app_running,
+            process::wait_for_app,
+            // Recording commands
+            recording::start_recording,
+            recording::stop_recording,
+            recording::get_recording_path,
+            recording::start_recording_inputs,
+            recording::stop_recording_inputs,
+            // OAuth session commands
+            oauth::store_oauth_tokens,
+            oauth::refresh_access_token,
+            oauth::get_valid_access_token,
+            oauth::clear_oauth_tokens,
+            // Orchestrator bridge commands
+            orchestrator::get_orchestrator_config,
+            orchestrator::set_orchestrator_config,
+            orchestrator::start_orchestrator_bridge,
+            orchestrator::stop_orchestrator_bridge,
+            orchestrator::get_orchestrator_status,
+            // Remote control commands
+            remote::start_remote_server,
+            remote::stop_remote_server,
+            remote::get_remote_server,
+            // Supabase sync commands
+            sync::sync_now,
+            sync::get_sync_status,
             // Template matching commands
             template_match::match_hint_images,
+            template_match::match_with_retry,
+            template_match::clear_match_cache,
+            // Usage commands
+            usage::get_usage_summary,
             // Webhook commands
             webhook::send_webhook,
+            webhook::get_webhook_settings,
+            webhook::set_webhook_settings,
+            webhook::get_desktop_notification_preferences,
+            webhook::set_desktop_notification_preferences,
+            // Email notification commands
+            email::get_email_config,
+            email::set_email_config,
+            email::send_test_email,
+            // Log commands
+            logs::get_recent_logs,
+            logs::set_log_level,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // Don't leave notifications silenced after the app exits
+            if let tauri::RunEvent::Exit = event {
+                if let Err(e) = app.state::<AppState>().focus_mode.restore() {
+                    tracing::warn!("Failed to restore Do Not Disturb: {}", e);
+                }
+            }
+        });
 }
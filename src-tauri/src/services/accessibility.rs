@@ -0,0 +1,633 @@
+//! Accessibility-based UI inspection
+//!
+//! Reads the element tree of an application through the platform accessibility
+//! API (the AX API on macOS, UI Automation on Windows), giving exact element
+//! coordinates where template matching is thrown off by themes or scaling.
+//! Returned elements are registered under an ID so later calls can refer to them;
+//! IDs stay valid until the next `get_ui_tree` call. Bounds are screen
+//! coordinates as used by the mouse service.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+use crate::error::XenotesterError;
+
+use platform::Element;
+
+/// Default depth of `get_ui_tree`
+pub const DEFAULT_TREE_DEPTH: u32 = 8;
+
+/// Maximum number of elements returned by one `get_ui_tree` call
+const MAX_TREE_NODES: usize = 5000;
+
+/// Maximum number of elements visited by one `find_element` search
+const MAX_SEARCH_NODES: usize = 20000;
+
+/// Maximum number of elements visited by one `find_bounds_matching` scan
+/// (kept small, since it runs for every capture in strict privacy mode)
+const MAX_SCAN_NODES: usize = 2000;
+
+/// Registered elements are dropped once this many have accumulated
+const MAX_REGISTERED_ELEMENTS: usize = 50000;
+
+/// Element rectangle in logical screen points
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementBounds {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl ElementBounds {
+    /// Center point, rounded for the mouse service
+    pub fn center(&self) -> (i32, i32) {
+        (
+            (self.x + self.width / 2.0).round() as i32,
+            (self.y + self.height / 2.0).round() as i32,
+        )
+    }
+}
+
+/// Snapshot of a UI element
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UiElement {
+    /// ID for follow-up calls such as `get_element_bounds`
+    pub id: String,
+    /// Platform role (e.g., "AXButton")
+    pub role: String,
+    pub title: Option<String>,
+    pub value: Option<String>,
+    pub bounds: Option<ElementBounds>,
+    pub children: Vec<UiElement>,
+}
+
+/// Elements handed out to the frontend, keyed by ID
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    elements: HashMap<String, Element>,
+}
+
+impl Registry {
+    fn register(&mut self, element: Element) -> String {
+        if self.elements.len() >= MAX_REGISTERED_ELEMENTS {
+            self.elements.clear();
+        }
+        self.next_id += 1;
+        let id = format!("el-{}", self.next_id);
+        self.elements.insert(id.clone(), element);
+        id
+    }
+}
+
+static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+
+fn registry() -> MutexGuard<'static, Registry> {
+    REGISTRY
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Snapshot an element (without children) and register it
+fn snapshot(element: Element, registry: &mut Registry) -> UiElement {
+    let role = element.role();
+    let title = element.title();
+    let value = element.value();
+    let bounds = element.bounds();
+    UiElement {
+        id: registry.register(element),
+        role,
+        title,
+        value,
+        bounds,
+        children: Vec::new(),
+    }
+}
+
+fn build_tree(
+    element: Element,
+    depth: u32,
+    budget: &mut usize,
+    registry: &mut Registry,
+) -> UiElement {
+    *budget = budget.saturating_sub(1);
+    let children = if depth > 0 {
+        element.children()
+    } else {
+        Vec::new()
+    };
+    let mut node = snapshot(element, registry);
+
+    for child in children {
+        if *budget == 0 {
+            break;
+        }
+        node.children
+            .push(build_tree(child, depth - 1, budget, registry));
+    }
+    node
+}
+
+/// Role without the platform prefix, lowercased ("AXButton" -> "button")
+fn normalize_role(role: &str) -> String {
+    role.trim().trim_start_matches("AX").to_lowercase()
+}
+
+/// Read the element tree of an app (the frontmost app when `app_id` is omitted)
+/// `app_id` is a bundle ID on macOS and an executable name on Windows.
+/// Invalidates the IDs returned by earlier calls.
+pub fn get_ui_tree(app_id: Option<&str>, max_depth: u32) -> Result<UiElement, XenotesterError> {
+    let root = platform::application(app_id)?;
+    let mut registry = registry();
+    registry.elements.clear();
+
+    let mut budget = MAX_TREE_NODES;
+    Ok(build_tree(root, max_depth, &mut budget, &mut registry))
+}
+
+/// Find the first element (breadth-first) with `role` whose title or value contains `title`
+/// Roles are compared without the platform prefix, so "button" matches "AXButton".
+pub fn find_element(
+    role: &str,
+    title: Option<&str>,
+    app_id: Option<&str>,
+) -> Result<Option<UiElement>, XenotesterError> {
+    let role = normalize_role(role);
+    let title = title.map(str::to_lowercase);
+    let matches_title = |element: &Element| match &title {
+        None => true,
+        Some(title) => [element.title(), element.value()]
+            .into_iter()
+            .flatten()
+            .any(|text| text.to_lowercase().contains(title.as_str())),
+    };
+
+    let mut queue = VecDeque::from([platform::application(app_id)?]);
+    let mut visited = 0;
+
+    while let Some(element) = queue.pop_front() {
+        visited += 1;
+        if visited > MAX_SEARCH_NODES {
+            break;
+        }
+        if normalize_role(&element.role()) == role && matches_title(&element) {
+            return Ok(Some(snapshot(element, &mut registry())));
+        }
+        queue.extend(element.children());
+    }
+
+    Ok(None)
+}
+
+/// Whether any element's title or value contains `text` (case-insensitive)
+pub fn contains_text(text: &str, app_id: Option<&str>) -> Result<bool, XenotesterError> {
+    let text = text.to_lowercase();
+    let mut queue = VecDeque::from([platform::application(app_id)?]);
+    let mut visited = 0;
+
+    while let Some(element) = queue.pop_front() {
+        visited += 1;
+        if visited > MAX_SEARCH_NODES {
+            break;
+        }
+        if [element.title(), element.value()]
+            .into_iter()
+            .flatten()
+            .any(|value| value.to_lowercase().contains(text.as_str()))
+        {
+            return Ok(true);
+        }
+        queue.extend(element.children());
+    }
+
+    Ok(false)
+}
+
+/// Bounds of the elements of the frontmost app whose title or value matches
+pub fn find_bounds_matching(
+    matches: impl Fn(&str) -> bool,
+) -> Result<Vec<ElementBounds>, XenotesterError> {
+    let mut queue = VecDeque::from([platform::application(None)?]);
+    let mut visited = 0;
+    let mut found = Vec::new();
+
+    while let Some(element) = queue.pop_front() {
+        visited += 1;
+        if visited > MAX_SCAN_NODES {
+            break;
+        }
+        let matched = [element.title(), element.value()]
+            .into_iter()
+            .flatten()
+            .any(|value| matches(&value));
+        if matched {
+            found.extend(element.bounds());
+        } else {
+            queue.extend(element.children());
+        }
+    }
+
+    Ok(found)
+}
+
+/// Current bounds of a registered element
+pub fn get_element_bounds(element_id: &str) -> Result<ElementBounds, XenotesterError> {
+    let registry = registry();
+    let element = registry.elements.get(element_id).ok_or_else(|| {
+        XenotesterError::AccessibilityError(format!("Unknown element: {}", element_id))
+    })?;
+    element.bounds().ok_or_else(|| {
+        XenotesterError::AccessibilityError(format!("Element has no bounds: {}", element_id))
+    })
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use core_foundation::array::CFArray;
+    use core_foundation::base::{CFType, CFTypeRef, TCFType};
+    use core_foundation::string::{CFString, CFStringRef};
+    use std::ffi::c_void;
+    use std::process::Command;
+
+    use super::ElementBounds;
+    use crate::error::XenotesterError;
+
+    type AXUIElementRef = CFTypeRef;
+    type AXError = i32;
+
+    const AX_ERROR_SUCCESS: AXError = 0;
+    const AX_VALUE_CG_POINT_TYPE: u32 = 1;
+    const AX_VALUE_CG_SIZE_TYPE: u32 = 2;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct CGPoint {
+        x: f64,
+        y: f64,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct CGSize {
+        width: f64,
+        height: f64,
+    }
+
+    // Boolean results are unsigned char in C, mapped to u8
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> u8;
+        fn AXUIElementCreateApplication(pid: i32) -> AXUIElementRef;
+        fn AXUIElementCreateSystemWide() -> AXUIElementRef;
+        fn AXUIElementCopyAttributeValue(
+            element: AXUIElementRef,
+            attribute: CFStringRef,
+            value: *mut CFTypeRef,
+        ) -> AXError;
+        fn AXValueGetValue(value: CFTypeRef, value_type: u32, value_ptr: *mut c_void) -> u8;
+    }
+
+    /// Retained AXUIElement
+    pub struct Element(CFType);
+
+    // AXUIElement references can be used from any thread
+    unsafe impl Send for Element {}
+
+    impl Element {
+        fn attribute(&self, name: &str) -> Option<CFType> {
+            let name = CFString::new(name);
+            let mut value: CFTypeRef = std::ptr::null();
+            let error = unsafe {
+                AXUIElementCopyAttributeValue(
+                    self.0.as_CFTypeRef(),
+                    name.as_concrete_TypeRef(),
+                    &mut value,
+                )
+            };
+            (error == AX_ERROR_SUCCESS && !value.is_null())
+                .then(|| unsafe { CFType::wrap_under_create_rule(value) })
+        }
+
+        fn string(&self, name: &str) -> Option<String> {
+            self.attribute(name)?
+                .downcast::<CFString>()
+                .map(|s| s.to_string())
+                .filter(|s| !s.is_empty())
+        }
+
+        pub fn role(&self) -> String {
+            self.string("AXRole").unwrap_or_default()
+        }
+
+        /// Title, falling back to the description (icon buttons often have no title)
+        pub fn title(&self) -> Option<String> {
+            self.string("AXTitle")
+                .or_else(|| self.string("AXDescription"))
+        }
+
+        /// Value when it is text (numeric values such as slider positions are skipped)
+        pub fn value(&self) -> Option<String> {
+            self.string("AXValue")
+        }
+
+        pub fn bounds(&self) -> Option<ElementBounds> {
+            let position = self.attribute("AXPosition")?;
+            let size = self.attribute("AXSize")?;
+            let mut origin = CGPoint::default();
+            let mut extent = CGSize::default();
+
+            let ok = unsafe {
+                AXValueGetValue(
+                    position.as_CFTypeRef(),
+                    AX_VALUE_CG_POINT_TYPE,
+                    &mut origin as *mut CGPoint as *mut c_void,
+                ) != 0
+                    && AXValueGetValue(
+                        size.as_CFTypeRef(),
+                        AX_VALUE_CG_SIZE_TYPE,
+                        &mut extent as *mut CGSize as *mut c_void,
+                    ) != 0
+            };
+
+            ok.then_some(ElementBounds {
+                x: origin.x,
+                y: origin.y,
+                width: extent.width,
+                height: extent.height,
+            })
+        }
+
+        pub fn children(&self) -> Vec<Element> {
+            let Some(children) = self
+                .attribute("AXChildren")
+                .and_then(|value| value.downcast::<CFArray>())
+            else {
+                return Vec::new();
+            };
+            children
+                .iter()
+                .map(|child| Element(unsafe { CFType::wrap_under_get_rule(*child) }))
+                .collect()
+        }
+    }
+
+    /// Root element of the app with the given bundle ID, or of the frontmost app
+    pub fn application(bundle_id: Option<&str>) -> Result<Element, XenotesterError> {
+        if unsafe { AXIsProcessTrusted() } == 0 {
+            return Err(XenotesterError::PermissionError(
+                "Accessibility permission is required to inspect UI elements".to_string(),
+            ));
+        }
+
+        match bundle_id {
+            Some(bundle_id) => {
+                let pid = pid_for_bundle(bundle_id)?;
+                let app = unsafe { AXUIElementCreateApplication(pid) };
+                Ok(Element(unsafe { CFType::wrap_under_create_rule(app) }))
+            }
+            None => {
+                let system = unsafe { AXUIElementCreateSystemWide() };
+                let system = Element(unsafe { CFType::wrap_under_create_rule(system) });
+                system
+                    .attribute("AXFocusedApplication")
+                    .map(Element)
+                    .ok_or_else(|| {
+                        XenotesterError::AccessibilityError("No focused application".to_string())
+                    })
+            }
+        }
+    }
+
+    /// Process ID of a running app; `lsappinfo` prints `"pid"=1234`
+    fn pid_for_bundle(bundle_id: &str) -> Result<i32, XenotesterError> {
+        let output = Command::new("lsappinfo")
+            .args(["info", "-only", "pid", "-app", bundle_id])
+            .output()
+            .map_err(|e| XenotesterError::AccessibilityError(e.to_string()))?;
+
+        String::from_utf8_lossy(&output.stdout)
+            .split('=')
+            .nth(1)
+            .and_then(|pid| pid.trim().parse().ok())
+            .ok_or_else(|| {
+                XenotesterError::AccessibilityError(format!(
+                    "Application is not running: {}",
+                    bundle_id
+                ))
+            })
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::collections::HashSet;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED,
+    };
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+    use windows::Win32::UI::Accessibility::{
+        CUIAutomation, IUIAutomation, IUIAutomationElement, IUIAutomationTreeWalker,
+        IUIAutomationValuePattern, UIA_ValuePatternId,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+    use super::ElementBounds;
+    use crate::error::XenotesterError;
+
+    /// Control type names indexed from UIA_ButtonControlTypeId (50000)
+    const CONTROL_TYPES: &[&str] = &[
+        "Button", "Calendar", "CheckBox", "ComboBox", "Edit", "Hyperlink", "Image",
+        "ListItem", "List", "Menu", "MenuBar", "MenuItem", "ProgressBar", "RadioButton",
+        "ScrollBar", "Slider", "Spinner", "StatusBar", "Tab", "TabItem", "Text", "ToolBar",
+        "ToolTip", "Tree", "TreeItem", "Custom", "Group", "Thumb", "DataGrid", "DataItem",
+        "Document", "SplitButton", "Window", "Pane", "Header", "HeaderItem", "Table",
+        "TitleBar", "Separator", "SemanticZoom", "AppBar",
+    ];
+    const FIRST_CONTROL_TYPE_ID: i32 = 50000;
+
+    /// UI Automation element with the walker used to enumerate its children
+    pub struct Element {
+        element: IUIAutomationElement,
+        walker: IUIAutomationTreeWalker,
+    }
+
+    // UI Automation objects are free-threaded (created in the multithreaded apartment)
+    unsafe impl Send for Element {}
+
+    impl Element {
+        fn child(&self, element: IUIAutomationElement) -> Element {
+            Element {
+                element,
+                walker: self.walker.clone(),
+            }
+        }
+
+        pub fn role(&self) -> String {
+            let id = unsafe { self.element.CurrentControlType() }.map_or(0, |t| t.0);
+            usize::try_from(id - FIRST_CONTROL_TYPE_ID)
+                .ok()
+                .and_then(|index| CONTROL_TYPES.get(index))
+                .map_or_else(|| format!("ControlType{}", id), |name| name.to_string())
+        }
+
+        pub fn title(&self) -> Option<String> {
+            unsafe { self.element.CurrentName() }
+                .ok()
+                .map(|name| name.to_string())
+                .filter(|name| !name.is_empty())
+        }
+
+        /// Value of elements supporting the Value pattern (edits, combo boxes)
+        pub fn value(&self) -> Option<String> {
+            let pattern = unsafe {
+                self.element
+                    .GetCurrentPatternAs::<IUIAutomationValuePattern>(UIA_ValuePatternId)
+            }
+            .ok()?;
+            unsafe { pattern.CurrentValue() }
+                .ok()
+                .map(|value| value.to_string())
+                .filter(|value| !value.is_empty())
+        }
+
+        pub fn bounds(&self) -> Option<ElementBounds> {
+            let rect = unsafe { self.element.CurrentBoundingRectangle() }.ok()?;
+            // Offscreen or collapsed elements report an empty rectangle
+            (rect.right > rect.left && rect.bottom > rect.top).then(|| ElementBounds {
+                x: rect.left as f64,
+                y: rect.top as f64,
+                width: (rect.right - rect.left) as f64,
+                height: (rect.bottom - rect.top) as f64,
+            })
+        }
+
+        pub fn children(&self) -> Vec<Element> {
+            let mut children = Vec::new();
+            let mut next = unsafe { self.walker.GetFirstChildElement(&self.element) };
+            while let Ok(element) = next {
+                next = unsafe { self.walker.GetNextSiblingElement(&element) };
+                children.push(self.child(element));
+            }
+            children
+        }
+    }
+
+    fn uia_error(error: windows::core::Error) -> XenotesterError {
+        XenotesterError::AccessibilityError(error.message())
+    }
+
+    /// Root element of the first window of the process with the given executable
+    /// name (e.g., "notepad.exe"), or of the foreground window
+    pub fn application(app_id: Option<&str>) -> Result<Element, XenotesterError> {
+        // Joins the multithreaded apartment; repeated calls on a thread are harmless
+        let _ = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) };
+        let automation: IUIAutomation =
+            unsafe { CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER) }
+                .map_err(uia_error)?;
+        let walker = unsafe { automation.ControlViewWalker() }.map_err(uia_error)?;
+
+        let element = match app_id {
+            None => unsafe { automation.ElementFromHandle(GetForegroundWindow()) }
+                .map_err(uia_error)?,
+            Some(exe_name) => {
+                let pids = process_ids(exe_name)?;
+                let desktop = Element {
+                    element: unsafe { automation.GetRootElement() }.map_err(uia_error)?,
+                    walker: walker.clone(),
+                };
+                desktop
+                    .children()
+                    .into_iter()
+                    .find(|window| {
+                        unsafe { window.element.CurrentProcessId() }
+                            .is_ok_and(|pid| pids.contains(&(pid as u32)))
+                    })
+                    .map(|window| window.element)
+                    .ok_or_else(|| {
+                        XenotesterError::AccessibilityError(format!(
+                            "No window found for application: {}",
+                            exe_name
+                        ))
+                    })?
+            }
+        };
+
+        Ok(Element { element, walker })
+    }
+
+    /// IDs of running processes whose executable name matches (case-insensitive)
+    fn process_ids(exe_name: &str) -> Result<HashSet<u32>, XenotesterError> {
+        let snapshot =
+            unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) }.map_err(uia_error)?;
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut pids = HashSet::new();
+
+        let mut found = unsafe { Process32FirstW(snapshot, &mut entry) };
+        while found.is_ok() {
+            let len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(0);
+            let name = String::from_utf16_lossy(&entry.szExeFile[..len]);
+            if name.eq_ignore_ascii_case(exe_name) {
+                pids.insert(entry.th32ProcessID);
+            }
+            found = unsafe { Process32NextW(snapshot, &mut entry) };
+        }
+        let _ = unsafe { CloseHandle(snapshot) };
+
+        if pids.is_empty() {
+            return Err(XenotesterError::AccessibilityError(format!(
+                "Application is not running: {}",
+                exe_name
+            )));
+        }
+        Ok(pids)
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use super::ElementBounds;
+    use crate::error::XenotesterError;
+
+    /// No accessibility backend on this platform; never constructed
+    pub enum Element {}
+
+    impl Element {
+        pub fn role(&self) -> String {
+            match *self {}
+        }
+
+        pub fn title(&self) -> Option<String> {
+            match *self {}
+        }
+
+        pub fn value(&self) -> Option<String> {
+            match *self {}
+        }
+
+        pub fn bounds(&self) -> Option<ElementBounds> {
+            match *self {}
+        }
+
+        pub fn children(&self) -> Vec<Element> {
+            match *self {}
+        }
+    }
+
+    pub fn application(_app_id: Option<&str>) -> Result<Element, XenotesterError> {
+        Err(XenotesterError::AccessibilityError(
+            "UI inspection is not supported on this platform".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,243 @@
+//! Action audit trail service
+//!
+//! Records every executed input command in the `action_log` table (see migration
+//! 007). Entries of a run form a hash chain: each hash covers the entry's fields
+//! and the previous entry's hash, so edited, reordered or deleted rows (other
+//! than the most recent ones) are detected when the log is read back. Secret
+//! values are redacted before they are stored.
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+use crate::error::XenotesterError;
+use crate::utils::redact;
+
+/// Serializes appends so concurrent commands cannot fork a run's chain
+static APPEND_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Input command to record
+#[derive(Debug, Clone)]
+pub struct ActionRecord {
+    /// Command name, e.g. "left_click"
+    pub action: &'static str,
+    /// Target position for pointer actions
+    pub position: Option<(i32, i32)>,
+    /// Remaining arguments
+    pub details: Value,
+}
+
+impl ActionRecord {
+    pub fn new(action: &'static str, position: Option<(i32, i32)>, details: Value) -> Self {
+        Self {
+            action,
+            position,
+            details,
+        }
+    }
+}
+
+/// Stored audit entry
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionLogEntry {
+    pub id: i64,
+    pub run_id: Option<String>,
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: i64,
+    pub action: String,
+    pub x: Option<i64>,
+    pub y: Option<i64>,
+    /// JSON of the remaining arguments
+    pub details: String,
+    /// "succeeded" or "failed"
+    pub status: String,
+    pub error_message: Option<String>,
+    pub prev_hash: Option<String>,
+    pub hash: String,
+}
+
+/// Audit entries of a run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionLog {
+    pub run_id: Option<String>,
+    pub entries: Vec<ActionLogEntry>,
+    /// False when any entry was modified, removed or reordered after recording
+    pub chain_intact: bool,
+}
+
+/// Hash of an entry's content chained to the previous entry's hash
+fn entry_hash(entry: &ActionLogEntry) -> String {
+    let mut hasher = Sha256::new();
+    let fields = [
+        entry.prev_hash.clone().unwrap_or_default(),
+        entry.run_id.clone().unwrap_or_default(),
+        entry.timestamp_ms.to_string(),
+        entry.action.clone(),
+        entry.x.map(|x| x.to_string()).unwrap_or_default(),
+        entry.y.map(|y| y.to_string()).unwrap_or_default(),
+        entry.details.clone(),
+        entry.status.clone(),
+        entry.error_message.clone().unwrap_or_default(),
+    ];
+    for field in &fields {
+        // Length prefixes keep field boundaries unambiguous
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Check that entries (oldest first) link to each other and match their hashes
+fn verify_chain(entries: &[ActionLogEntry]) -> bool {
+    let mut prev_hash: Option<&str> = None;
+    for entry in entries {
+        if entry.prev_hash.as_deref() != prev_hash || entry_hash(entry) != entry.hash {
+            return false;
+        }
+        prev_hash = Some(&entry.hash);
+    }
+    true
+}
+
+/// Append an executed action to the run's chain
+pub async fn record_action(
+    pool: &SqlitePool,
+    run_id: Option<&str>,
+    record: &ActionRecord,
+    error: Option<&str>,
+) -> Result<(), XenotesterError> {
+    let mut details = record.details.clone();
+    redact::redact_json(&mut details);
+
+    let _guard = APPEND_LOCK.lock().await;
+
+    let prev_hash: Option<String> = sqlx::query_scalar(
+        "SELECT hash FROM action_log WHERE run_id IS ? ORDER BY id DESC LIMIT 1",
+    )
+    .bind(run_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let mut entry = ActionLogEntry {
+        id: 0,
+        run_id: run_id.map(String::from),
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default(),
+        action: record.action.to_string(),
+        x: record.position.map(|(x, _)| x as i64),
+        y: record.position.map(|(_, y)| y as i64),
+        details: details.to_string(),
+        status: if error.is_some() {
+            "failed"
+        } else {
+            "succeeded"
+        }
+        .to_string(),
+        error_message: error.map(|e| redact::redact(e).into_owned()),
+        prev_hash,
+        hash: String::new(),
+    };
+    entry.hash = entry_hash(&entry);
+
+    sqlx::query(
+        "INSERT INTO action_log (run_id, timestamp_ms, action, x, y, details, status,
+             error_message, prev_hash, hash)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&entry.run_id)
+    .bind(entry.timestamp_ms)
+    .bind(&entry.action)
+    .bind(entry.x)
+    .bind(entry.y)
+    .bind(&entry.details)
+    .bind(&entry.status)
+    .bind(&entry.error_message)
+    .bind(&entry.prev_hash)
+    .bind(&entry.hash)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Get the actions of a run (or of actions outside any run when `run_id` is None)
+pub async fn get_action_log(
+    pool: &SqlitePool,
+    run_id: Option<&str>,
+) -> Result<ActionLog, XenotesterError> {
+    let entries: Vec<ActionLogEntry> = sqlx::query_as(
+        "SELECT id, run_id, timestamp_ms, action, x, y, details, status, error_message,
+                prev_hash, hash
+         FROM action_log
+         WHERE run_id IS ?
+         ORDER BY id",
+    )
+    .bind(run_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ActionLog {
+        run_id: run_id.map(String::from),
+        chain_intact: verify_chain(&entries),
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(actions: &[&str]) -> Vec<ActionLogEntry> {
+        let mut entries: Vec<ActionLogEntry> = Vec::new();
+        for (i, action) in actions.iter().enumerate() {
+            let mut entry = ActionLogEntry {
+                id: i as i64 + 1,
+                run_id: Some("run".to_string()),
+                timestamp_ms: 1000 + i as i64,
+                action: action.to_string(),
+                x: Some(10),
+                y: Some(20),
+                details: "{}".to_string(),
+                status: "succeeded".to_string(),
+                error_message: None,
+                prev_hash: entries.last().map(|e| e.hash.clone()),
+                hash: String::new(),
+            };
+            entry.hash = entry_hash(&entry);
+            entries.push(entry);
+        }
+        entries
+    }
+
+    #[test]
+    fn test_intact_chain_verifies() {
+        assert!(verify_chain(&chain(&["left_click", "type_text", "key"])));
+        assert!(verify_chain(&[]));
+    }
+
+    #[test]
+    fn test_tampering_breaks_chain() {
+        let mut modified = chain(&["left_click", "type_text", "key"]);
+        modified[1].x = Some(11);
+        assert!(!verify_chain(&modified));
+
+        let mut removed = chain(&["left_click", "type_text", "key"]);
+        removed.remove(1);
+        assert!(!verify_chain(&removed));
+
+        let mut reordered = chain(&["left_click", "type_text", "key"]);
+        reordered.swap(1, 2);
+        assert!(!verify_chain(&reordered));
+    }
+}
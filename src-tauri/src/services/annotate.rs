@@ -0,0 +1,288 @@
+//! Screenshot annotation (boxes, crosshairs and labels) for failure reports
+//!
+//! Labels use a built-in 5x7 bitmap font so output doesn't depend on system fonts.
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::{
+    draw_filled_rect_mut, draw_hollow_circle_mut, draw_hollow_rect_mut, draw_line_segment_mut,
+};
+use imageproc::rect::Rect;
+use serde::Deserialize;
+use std::io::Cursor;
+
+use crate::error::XenotesterError;
+
+const DEFAULT_COLOR: Rgba<u8> = Rgba([255, 0, 64, 255]);
+const DEFAULT_THICKNESS: u32 = 3;
+const DEFAULT_CROSSHAIR_SIZE: u32 = 24;
+/// Glyph pixels are drawn as SCALE x SCALE blocks
+const FONT_SCALE: u32 = 2;
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+const LABEL_PADDING: u32 = 3;
+
+/// Shape to draw; coordinates are in pixels of the annotated image
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Annotation {
+    /// Bounding box (e.g. a matched template)
+    #[serde(rename_all = "camelCase")]
+    Rect {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        color: Option<String>,
+        label: Option<String>,
+        thickness: Option<u32>,
+    },
+    /// Crosshair centered on a point (e.g. a click target)
+    #[serde(rename_all = "camelCase")]
+    Crosshair {
+        x: i32,
+        y: i32,
+        size: Option<u32>,
+        color: Option<String>,
+        label: Option<String>,
+    },
+    /// Free-standing text with its top-left corner at (x, y)
+    #[serde(rename_all = "camelCase")]
+    Label {
+        x: i32,
+        y: i32,
+        text: String,
+        color: Option<String>,
+    },
+}
+
+/// Draw annotations onto a base64 encoded image and return it as base64 PNG
+pub fn annotate_base64(
+    image_base64: &str,
+    annotations: &[Annotation],
+) -> Result<String, XenotesterError> {
+    let bytes = BASE64_STANDARD
+        .decode(image_base64)
+        .map_err(|e| XenotesterError::ImageError(format!("Base64 decode error: {}", e)))?;
+    let mut image = image::load_from_memory(&bytes)?.into_rgba8();
+
+    for annotation in annotations {
+        draw_annotation(&mut image, annotation)?;
+    }
+
+    let mut buffer = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)
+        .map_err(|e| XenotesterError::ImageError(e.to_string()))?;
+    Ok(BASE64_STANDARD.encode(&buffer))
+}
+
+/// Draw a single annotation
+pub fn draw_annotation(image: &mut RgbaImage, annotation: &Annotation) -> Result<(), XenotesterError> {
+    match annotation {
+        Annotation::Rect {
+            x,
+            y,
+            width,
+            height,
+            color,
+            label,
+            thickness,
+        } => {
+            let color = parse_color(color.as_deref())?;
+            let thickness = thickness.unwrap_or(DEFAULT_THICKNESS).max(1);
+            for i in 0..thickness {
+                let (w, h) = (width + 2 * i, height + 2 * i);
+                draw_hollow_rect_mut(
+                    image,
+                    Rect::at(x - i as i32, y - i as i32).of_size(w.max(1), h.max(1)),
+                    color,
+                );
+            }
+            if let Some(text) = label {
+                // Above the box when there is room, otherwise inside its top edge
+                let label_height = label_size(text).1 as i32;
+                let label_y = if *y - thickness as i32 >= label_height {
+                    y - thickness as i32 - label_height
+                } else {
+                    *y
+                };
+                draw_label(image, *x - thickness as i32 + 1, label_y, text, color);
+            }
+        }
+        Annotation::Crosshair {
+            x,
+            y,
+            size,
+            color,
+            label,
+        } => {
+            let color = parse_color(color.as_deref())?;
+            let half = size.unwrap_or(DEFAULT_CROSSHAIR_SIZE) as f32 / 2.0;
+            let (cx, cy) = (*x as f32, *y as f32);
+            // Two-pixel wide lines so the target stays visible after downscaling
+            for offset in [0.0, 1.0] {
+                draw_line_segment_mut(image, (cx - half, cy + offset), (cx + half, cy + offset), color);
+                draw_line_segment_mut(image, (cx + offset, cy - half), (cx + offset, cy + half), color);
+            }
+            draw_hollow_circle_mut(image, (*x, *y), (half / 2.0) as i32, color);
+            if let Some(text) = label {
+                draw_label(image, x + half as i32 + 2, y + 2, text, color);
+            }
+        }
+        Annotation::Label { x, y, text, color } => {
+            let color = parse_color(color.as_deref())?;
+            draw_label(image, *x, *y, text, color);
+        }
+    }
+    Ok(())
+}
+
+/// Parse "#rrggbb" / "#rrggbbaa" (default: red)
+fn parse_color(color: Option<&str>) -> Result<Rgba<u8>, XenotesterError> {
+    let Some(color) = color else {
+        return Ok(DEFAULT_COLOR);
+    };
+    let hex = color.trim_start_matches('#');
+    if !hex.is_ascii() {
+        return Err(XenotesterError::ImageError(format!("Invalid color: {}", color)));
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16)
+            .map_err(|_| XenotesterError::ImageError(format!("Invalid color: {}", color)))
+    };
+    match hex.len() {
+        6 => Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, 255])),
+        8 => Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, channel(6)?])),
+        _ => Err(XenotesterError::ImageError(format!("Invalid color: {}", color))),
+    }
+}
+
+/// Size of a rendered label including its background padding
+fn label_size(text: &str) -> (u32, u32) {
+    let chars = text.chars().count() as u32;
+    let advance = (GLYPH_WIDTH + 1) * FONT_SCALE;
+    (
+        chars * advance + 2 * LABEL_PADDING,
+        GLYPH_HEIGHT * FONT_SCALE + 2 * LABEL_PADDING,
+    )
+}
+
+/// Draw white text on a filled background of `color`
+fn draw_label(image: &mut RgbaImage, x: i32, y: i32, text: &str, color: Rgba<u8>) {
+    let (width, height) = label_size(text);
+    draw_filled_rect_mut(image, Rect::at(x, y).of_size(width, height), color);
+
+    let white = Rgba([255, 255, 255, 255]);
+    let mut pen_x = x + LABEL_PADDING as i32;
+    let pen_y = y + LABEL_PADDING as i32;
+    for ch in text.chars() {
+        for (row, bits) in glyph(ch).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                    draw_filled_rect_mut(
+                        image,
+                        Rect::at(
+                            pen_x + (col * FONT_SCALE) as i32,
+                            pen_y + (row as u32 * FONT_SCALE) as i32,
+                        )
+                        .of_size(FONT_SCALE, FONT_SCALE),
+                        white,
+                    );
+                }
+            }
+        }
+        pen_x += ((GLYPH_WIDTH + 1) * FONT_SCALE) as i32;
+    }
+}
+
+/// 5x7 glyph rows (low 5 bits, MSB is the leftmost column); lowercase renders as uppercase
+fn glyph(ch: char) -> [u8; 7] {
+    match ch.to_ascii_uppercase() {
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ' ' => [0x00; 7],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '[' => [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E],
+        ']' => [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // '?'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rect_and_label_are_drawn() {
+        let mut image = RgbaImage::from_pixel(200, 200, Rgba([0, 0, 0, 255]));
+        let annotation = Annotation::Rect {
+            x: 50,
+            y: 60,
+            width: 80,
+            height: 40,
+            color: Some("#00ff00".to_string()),
+            label: Some("0.95".to_string()),
+            thickness: Some(2),
+        };
+
+        draw_annotation(&mut image, &annotation).unwrap();
+
+        // Box edge, label background above the box, untouched interior
+        assert_eq!(image.get_pixel(50, 80), &Rgba([0, 255, 0, 255]));
+        assert_eq!(image.get_pixel(51, 40), &Rgba([0, 255, 0, 255]));
+        assert_eq!(image.get_pixel(90, 80), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_invalid_color_is_rejected() {
+        assert!(parse_color(Some("#12345")).is_err());
+        assert_eq!(parse_color(Some("#ff000080")).unwrap(), Rgba([255, 0, 0, 128]));
+    }
+}
@@ -0,0 +1,283 @@
+//! Run artifacts
+//!
+//! Files produced during runs (debug screenshots, exports) are written under a
+//! single artifact directory (the app data `artifacts` directory), one
+//! subdirectory per run plus `unassigned` for files written outside a run.
+//! Callers pass a plain file name, never a path, so nothing is written outside
+//! the directory. A `CleanupPolicy` bounds how much is kept.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::XenotesterError;
+
+/// Subdirectory for artifacts written outside a run
+const UNASSIGNED_DIR: &str = "unassigned";
+
+/// Stored artifact
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactInfo {
+    /// Run the artifact belongs to (None for unassigned artifacts)
+    pub run_id: Option<String>,
+    pub name: String,
+    pub path: String,
+    pub size_bytes: u64,
+    /// Milliseconds since the Unix epoch
+    pub modified_at: u64,
+}
+
+/// Limits applied by `cleanup`; unset limits are not enforced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CleanupPolicy {
+    /// Artifacts older than this are removed
+    pub max_age_days: Option<u32>,
+    /// Oldest artifacts are removed until the total size fits
+    pub max_total_bytes: Option<u64>,
+    /// Artifacts of older runs are removed beyond this many runs
+    pub max_runs: Option<usize>,
+}
+
+impl Default for CleanupPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: Some(30),
+            max_total_bytes: Some(1024 * 1024 * 1024),
+            max_runs: None,
+        }
+    }
+}
+
+/// Artifacts removed by `cleanup`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupReport {
+    pub removed_files: usize,
+    pub freed_bytes: u64,
+}
+
+/// Reject names that are not a single plain path component
+fn validate_name(kind: &str, name: &str) -> Result<(), XenotesterError> {
+    let plain =
+        !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', ':', '\0']);
+    if plain {
+        Ok(())
+    } else {
+        Err(XenotesterError::ConfigError(format!(
+            "Invalid artifact {} {:?}: must be a plain file name",
+            kind, name
+        )))
+    }
+}
+
+fn run_dir(root: &Path, run_id: Option<&str>) -> Result<PathBuf, XenotesterError> {
+    match run_id {
+        Some(run_id) => {
+            validate_name("run ID", run_id)?;
+            Ok(root.join(run_id))
+        }
+        None => Ok(root.join(UNASSIGNED_DIR)),
+    }
+}
+
+/// Write an artifact, replacing one with the same name in the same run
+pub fn save_artifact(
+    root: &Path,
+    run_id: Option<&str>,
+    name: &str,
+    data: &[u8],
+) -> Result<ArtifactInfo, XenotesterError> {
+    validate_name("name", name)?;
+    let dir = run_dir(root, run_id)?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    fs::write(&path, data)?;
+    artifact_info(run_id.map(str::to_string), &path)
+}
+
+/// Artifacts of one run, or of all runs, oldest first
+pub fn list_artifacts(
+    root: &Path,
+    run_id: Option<&str>,
+) -> Result<Vec<ArtifactInfo>, XenotesterError> {
+    let dirs = match run_id {
+        Some(_) => vec![run_dir(root, run_id)?],
+        None if root.is_dir() => fs::read_dir(root)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let mut artifacts = Vec::new();
+    for dir in dirs {
+        if !dir.is_dir() {
+            continue;
+        }
+        let dir_name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let owner = (dir_name != UNASSIGNED_DIR).then(|| dir_name.to_string());
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                artifacts.push(artifact_info(owner.clone(), &path)?);
+            }
+        }
+    }
+    artifacts.sort_by(|a, b| (a.modified_at, &a.path).cmp(&(b.modified_at, &b.path)));
+    Ok(artifacts)
+}
+
+fn artifact_info(run_id: Option<String>, path: &Path) -> Result<ArtifactInfo, XenotesterError> {
+    let metadata = fs::metadata(path)?;
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    Ok(ArtifactInfo {
+        run_id,
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        path: path.to_string_lossy().into_owned(),
+        size_bytes: metadata.len(),
+        modified_at,
+    })
+}
+
+/// Remove artifacts exceeding the policy, oldest first
+pub fn cleanup(root: &Path, policy: &CleanupPolicy) -> Result<CleanupReport, XenotesterError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    let artifacts = list_artifacts(root, None)?;
+    let expired = select_expired(&artifacts, policy, now);
+
+    let mut report = CleanupReport::default();
+    for artifact in expired {
+        fs::remove_file(&artifact.path)?;
+        report.removed_files += 1;
+        report.freed_bytes += artifact.size_bytes;
+    }
+
+    // Drop run directories left empty
+    for entry in fs::read_dir(root).into_iter().flatten().flatten() {
+        let _ = fs::remove_dir(entry.path());
+    }
+    Ok(report)
+}
+
+/// Artifacts (sorted oldest first) that the policy removes at `now` (Unix ms)
+fn select_expired<'a>(
+    artifacts: &'a [ArtifactInfo],
+    policy: &CleanupPolicy,
+    now: u64,
+) -> Vec<&'a ArtifactInfo> {
+    let mut kept: Vec<&ArtifactInfo> = artifacts.iter().collect();
+    let mut expired = Vec::new();
+    let mut expire = |kept: &mut Vec<&'a ArtifactInfo>, remove: &dyn Fn(&ArtifactInfo) -> bool| {
+        kept.retain(|artifact| {
+            let removed = remove(artifact);
+            if removed {
+                expired.push(*artifact);
+            }
+            !removed
+        });
+    };
+
+    if let Some(days) = policy.max_age_days {
+        let max_age = Duration::from_secs(u64::from(days) * 24 * 60 * 60).as_millis() as u64;
+        expire(&mut kept, &|a| now.saturating_sub(a.modified_at) > max_age);
+    }
+
+    if let Some(max_runs) = policy.max_runs {
+        // Runs ordered by their newest artifact, newest first
+        let mut runs: Vec<String> = Vec::new();
+        for run_id in kept.iter().rev().filter_map(|a| a.run_id.as_ref()) {
+            if !runs.contains(run_id) {
+                runs.push(run_id.clone());
+            }
+        }
+        let dropped = runs.split_off(max_runs.min(runs.len()));
+        expire(&mut kept, &|a| {
+            a.run_id.as_ref().is_some_and(|id| dropped.contains(id))
+        });
+    }
+
+    if let Some(max_total) = policy.max_total_bytes {
+        let mut total: u64 = kept.iter().map(|a| a.size_bytes).sum();
+        let mut over = 0;
+        while total > max_total && over < kept.len() {
+            total -= kept[over].size_bytes;
+            over += 1;
+        }
+        expired.extend(kept.drain(..over));
+    }
+
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(run_id: Option<&str>, size_bytes: u64, modified_at: u64) -> ArtifactInfo {
+        ArtifactInfo {
+            run_id: run_id.map(str::to_string),
+            name: format!("{}.png", modified_at),
+            path: format!("{}.png", modified_at),
+            size_bytes,
+            modified_at,
+        }
+    }
+
+    #[test]
+    fn test_save_rejects_paths() {
+        let root = std::env::temp_dir().join(format!("artifacts-{}", uuid::Uuid::new_v4()));
+        assert!(save_artifact(&root, None, "../escape.png", b"x").is_err());
+        assert!(save_artifact(&root, Some("../run"), "a.png", b"x").is_err());
+        assert!(save_artifact(&root, None, "/etc/passwd", b"x").is_err());
+
+        let saved = save_artifact(&root, Some("run-1"), "a.png", b"data").unwrap();
+        assert_eq!(saved.size_bytes, 4);
+        assert_eq!(list_artifacts(&root, Some("run-1")).unwrap().len(), 1);
+        assert!(list_artifacts(&root, Some("run-2")).unwrap().is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_policy_removes_oldest_first() {
+        let day = 24 * 60 * 60 * 1000;
+        let now = 100 * day;
+        let artifacts = vec![
+            artifact(Some("old"), 10, now - 40 * day),
+            artifact(Some("a"), 10, now - 3 * day),
+            artifact(None, 10, now - 2 * day),
+            artifact(Some("b"), 10, now - day),
+        ];
+
+        let by_age = CleanupPolicy {
+            max_age_days: Some(30),
+            max_total_bytes: None,
+            max_runs: None,
+        };
+        let expired = select_expired(&artifacts, &by_age, now);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].run_id.as_deref(), Some("old"));
+
+        let by_size_and_runs = CleanupPolicy {
+            max_age_days: None,
+            max_total_bytes: Some(15),
+            max_runs: Some(2),
+        };
+        let expired = select_expired(&artifacts, &by_size_and_runs, now);
+        let removed: Vec<_> = expired.iter().map(|a| a.modified_at).collect();
+        assert_eq!(removed, vec![now - 40 * day, now - 3 * day, now - 2 * day]);
+    }
+}
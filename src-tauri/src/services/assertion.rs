@@ -0,0 +1,188 @@
+//! Screen assertions with evidence
+//!
+//! Each assertion captures the screen once and returns a pass/fail result with
+//! the evidence it was based on: the match confidence or pixel color and a PNG
+//! crop around the located template or pixel. When nothing was located, the
+//! evidence is a downscaled capture of the whole monitor.
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use image::{DynamicImage, GenericImageView};
+use serde::Serialize;
+
+use crate::error::XenotesterError;
+use crate::services::capture::grab_frame;
+use crate::services::image_processor::{encode_image, ImageEncoding};
+use crate::services::screen_check::{
+    colors_match, frame_at, frame_to_screen, parse_hex_color, to_hex_color,
+};
+use crate::services::template_matcher::{find_template_in_image, MatchOptions};
+
+/// Margin around a matched template in the evidence crop (frame pixels)
+const EVIDENCE_MARGIN: u32 = 16;
+
+/// Edge length of the evidence crop around a checked pixel (frame pixels)
+const PIXEL_EVIDENCE_SIZE: u32 = 32;
+
+/// Longest edge of the whole-monitor evidence image
+const SCREEN_EVIDENCE_MAX_EDGE: u32 = 800;
+
+/// Outcome of an assertion
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResult {
+    pub passed: bool,
+    /// Human-readable description of the outcome
+    pub message: String,
+    /// Best template match score (template assertions)
+    pub confidence: Option<f32>,
+    /// Screen position of the match or the checked pixel
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    /// Color found at the pixel as "#rrggbb" (pixel assertions)
+    pub actual_color: Option<String>,
+    /// Base64 PNG of the screen area the result is based on
+    pub evidence_image: Option<String>,
+}
+
+/// Crop a `width` x `height` area centered on (cx, cy), clamped to the image
+fn crop_around(image: &DynamicImage, cx: u32, cy: u32, width: u32, height: u32) -> DynamicImage {
+    let width = width.clamp(1, image.width());
+    let height = height.clamp(1, image.height());
+    let x = cx.saturating_sub(width / 2).min(image.width() - width);
+    let y = cy.saturating_sub(height / 2).min(image.height() - height);
+    image.crop_imm(x, y, width, height)
+}
+
+/// Encode an evidence image as base64 PNG
+fn encode_evidence(image: &DynamicImage) -> Result<String, XenotesterError> {
+    Ok(BASE64_STANDARD.encode(encode_image(image, &ImageEncoding::default())?))
+}
+
+/// Assert that a template (original-size image data) is visible, or absent when
+/// `expect_visible` is false, on a monitor (primary when None)
+pub fn assert_template(
+    template_base64: &str,
+    monitor_id: Option<u32>,
+    confidence_threshold: f32,
+    expect_visible: bool,
+) -> Result<AssertionResult, XenotesterError> {
+    let frame = grab_frame(monitor_id, false)?;
+    let result = find_template_in_image(
+        &frame.image,
+        template_base64.into(),
+        confidence_threshold,
+        &MatchOptions::default(),
+    );
+    if let Some(error) = result.error {
+        return Err(XenotesterError::ImageError(error));
+    }
+
+    let confidence = result.confidence.unwrap_or(0.0);
+    let message = match (expect_visible, result.found) {
+        (true, true) => format!("Template is visible (confidence {:.2})", confidence),
+        (true, false) => format!(
+            "Template is not visible (best confidence {:.2} < {:.2})",
+            confidence, confidence_threshold
+        ),
+        (false, false) => format!(
+            "Template is absent (best confidence {:.2} < {:.2})",
+            confidence, confidence_threshold
+        ),
+        (false, true) => format!(
+            "Template is unexpectedly visible (confidence {:.2})",
+            confidence
+        ),
+    };
+
+    let located = match (result.center_x, result.center_y) {
+        (Some(cx), Some(cy)) if result.found => Some((cx.max(0) as u32, cy.max(0) as u32)),
+        _ => None,
+    };
+    let evidence = match located {
+        Some((cx, cy)) => crop_around(
+            &frame.image,
+            cx,
+            cy,
+            result.template_width + 2 * EVIDENCE_MARGIN,
+            result.template_height + 2 * EVIDENCE_MARGIN,
+        ),
+        None => frame
+            .image
+            .thumbnail(SCREEN_EVIDENCE_MAX_EDGE, SCREEN_EVIDENCE_MAX_EDGE),
+    };
+    let position = located.map(|(cx, cy)| frame_to_screen(&frame, cx as f64, cy as f64));
+
+    Ok(AssertionResult {
+        passed: result.found == expect_visible,
+        message,
+        confidence: result.confidence,
+        x: position.map(|(x, _)| x),
+        y: position.map(|(_, y)| y),
+        actual_color: None,
+        evidence_image: Some(encode_evidence(&evidence)?),
+    })
+}
+
+/// Assert that the pixel at a screen position has the expected "#rrggbb" color
+/// Each RGB channel may differ by up to `tolerance`.
+pub fn assert_pixel_color(
+    x: i32,
+    y: i32,
+    expected: &str,
+    tolerance: u8,
+) -> Result<AssertionResult, XenotesterError> {
+    let expected_color = parse_hex_color(expected)?;
+    let (frame, (px, py)) = frame_at(x, y)?;
+    let actual = frame.image.get_pixel(px, py);
+    let passed = colors_match(actual, expected_color, tolerance);
+
+    let message = if passed {
+        format!("Pixel ({}, {}) is {}", x, y, to_hex_color(actual))
+    } else {
+        format!(
+            "Pixel ({}, {}) is {}, expected {} (tolerance {})",
+            x,
+            y,
+            to_hex_color(actual),
+            to_hex_color(expected_color),
+            tolerance
+        )
+    };
+    let evidence = crop_around(
+        &frame.image,
+        px,
+        py,
+        PIXEL_EVIDENCE_SIZE,
+        PIXEL_EVIDENCE_SIZE,
+    );
+
+    Ok(AssertionResult {
+        passed,
+        message,
+        confidence: None,
+        x: Some(x),
+        y: Some(y),
+        actual_color: Some(to_hex_color(actual)),
+        evidence_image: Some(encode_evidence(&evidence)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_crop_around_stays_inside_image() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(100, 50));
+
+        let centered = crop_around(&image, 50, 25, 20, 10);
+        assert_eq!(centered.dimensions(), (20, 10));
+
+        let corner = crop_around(&image, 99, 49, 32, 32);
+        assert_eq!(corner.dimensions(), (32, 32));
+
+        let oversized = crop_around(&image, 10, 10, 500, 500);
+        assert_eq!(oversized.dimensions(), (100, 50));
+    }
+}
@@ -0,0 +1,229 @@
+//! Visual regression baselines
+//!
+//! A baseline is a named crop of the screen saved as PNG (under the app data
+//! `baselines` directory) with its region recorded in the `baselines` table
+//! (see migration 006). Assertions re-capture the same region and diff it
+//! against the stored crop.
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::XenotesterError;
+use crate::services::capture::grab_frame;
+use crate::services::image_diff::{compare_images, ChangedRegion, DEFAULT_DIFF_THRESHOLD};
+
+/// Default fraction of pixels allowed to differ from the baseline
+pub const DEFAULT_BASELINE_TOLERANCE: f64 = 0.01;
+
+/// Screen region in physical pixels of the monitor capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Stored baseline
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaselineInfo {
+    pub name: String,
+    pub file_path: String,
+    pub monitor_id: Option<u32>,
+    pub region: Region,
+}
+
+/// Result of comparing the screen against a baseline
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaselineAssertion {
+    pub name: String,
+    pub passed: bool,
+    /// Fraction of unchanged pixels (1.0 = identical)
+    pub similarity: f64,
+    pub tolerance: f64,
+    /// Changed areas relative to the baseline region
+    pub regions: Vec<ChangedRegion>,
+    /// Highlighted diff image written on failure
+    pub diff_path: Option<String>,
+}
+
+/// Capture the screen (or a region of it) and store it as baseline `name`
+/// An existing baseline with the same name is replaced
+pub async fn save_baseline(
+    pool: &SqlitePool,
+    dir: &Path,
+    name: &str,
+    region: Option<Region>,
+    monitor_id: Option<u32>,
+) -> Result<BaselineInfo, XenotesterError> {
+    let (crop, region) = capture_region(monitor_id, region).await?;
+
+    fs::create_dir_all(dir)?;
+    let file_path = dir.join(format!("{}.png", sanitize_name(name)));
+    crop.save(&file_path)?;
+    let file_path = file_path.to_string_lossy().into_owned();
+
+    sqlx::query(
+        "INSERT INTO baselines (name, file_path, monitor_id, region_x, region_y, region_width, region_height)
+         VALUES (?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(name) DO UPDATE SET
+            file_path = excluded.file_path,
+            monitor_id = excluded.monitor_id,
+            region_x = excluded.region_x,
+            region_y = excluded.region_y,
+            region_width = excluded.region_width,
+            region_height = excluded.region_height,
+            updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now')",
+    )
+    .bind(name)
+    .bind(&file_path)
+    .bind(monitor_id)
+    .bind(region.x)
+    .bind(region.y)
+    .bind(region.width)
+    .bind(region.height)
+    .execute(pool)
+    .await?;
+
+    Ok(BaselineInfo {
+        name: name.to_string(),
+        file_path,
+        monitor_id,
+        region,
+    })
+}
+
+/// Compare the current screen against baseline `name`
+/// Passes when at most `tolerance` (0.0 - 1.0) of the pixels differ; on failure a
+/// diff artifact is written to the `diffs` subdirectory of `dir`
+pub async fn assert_matches_baseline(
+    pool: &SqlitePool,
+    dir: &Path,
+    name: &str,
+    tolerance: f64,
+) -> Result<BaselineAssertion, XenotesterError> {
+    let baseline = get_baseline(pool, name)
+        .await?
+        .ok_or_else(|| XenotesterError::NotFound(format!("baseline '{}'", name)))?;
+
+    let (actual, _) = capture_region(baseline.monitor_id, Some(baseline.region)).await?;
+
+    let file_path = baseline.file_path.clone();
+    let diff = tokio::task::spawn_blocking(move || {
+        let expected = image::open(&file_path)?.into_rgba8();
+        compare_images(&expected, &actual, DEFAULT_DIFF_THRESHOLD, true)
+    })
+    .await
+    .map_err(|e| XenotesterError::ImageError(format!("Compare task failed: {}", e)))??;
+
+    let passed = 1.0 - diff.similarity <= tolerance;
+
+    let diff_path = match (passed, diff.diff_image_base64) {
+        (false, Some(diff_image)) => Some(write_diff_artifact(dir, name, &diff_image)?),
+        _ => None,
+    };
+
+    Ok(BaselineAssertion {
+        name: name.to_string(),
+        passed,
+        similarity: diff.similarity,
+        tolerance,
+        regions: diff.regions,
+        diff_path,
+    })
+}
+
+/// Look up a stored baseline by name
+pub async fn get_baseline(
+    pool: &SqlitePool,
+    name: &str,
+) -> Result<Option<BaselineInfo>, XenotesterError> {
+    let row: Option<(String, Option<u32>, u32, u32, u32, u32)> = sqlx::query_as(
+        "SELECT file_path, monitor_id, region_x, region_y, region_width, region_height
+         FROM baselines WHERE name = ?",
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|(file_path, monitor_id, x, y, width, height)| BaselineInfo {
+        name: name.to_string(),
+        file_path,
+        monitor_id,
+        region: Region {
+            x,
+            y,
+            width,
+            height,
+        },
+    }))
+}
+
+/// Capture a monitor at full resolution and crop it to `region` (whole screen if None)
+async fn capture_region(
+    monitor_id: Option<u32>,
+    region: Option<Region>,
+) -> Result<(RgbaImage, Region), XenotesterError> {
+    tokio::task::spawn_blocking(move || {
+        let frame = grab_frame(monitor_id, false)?;
+        let image = frame.image.into_rgba8();
+        let region = region.unwrap_or(Region {
+            x: 0,
+            y: 0,
+            width: image.width(),
+            height: image.height(),
+        });
+
+        if region.width == 0
+            || region.height == 0
+            || region.x + region.width > image.width()
+            || region.y + region.height > image.height()
+        {
+            return Err(XenotesterError::CaptureError(format!(
+                "Region {:?} is outside the {}x{} screen",
+                region,
+                image.width(),
+                image.height()
+            )));
+        }
+
+        let crop =
+            image::imageops::crop_imm(&image, region.x, region.y, region.width, region.height)
+                .to_image();
+        Ok((crop, region))
+    })
+    .await
+    .map_err(|e| XenotesterError::CaptureError(format!("Capture task failed: {}", e)))?
+}
+
+/// Write a base64 diff image to `<dir>/diffs/<name>-<timestamp>.png`
+fn write_diff_artifact(dir: &Path, name: &str, diff_base64: &str) -> Result<String, XenotesterError> {
+    let bytes = BASE64_STANDARD
+        .decode(diff_base64)
+        .map_err(|e| XenotesterError::ImageError(format!("Base64 decode error: {}", e)))?;
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let diff_dir: PathBuf = dir.join("diffs");
+    fs::create_dir_all(&diff_dir)?;
+    let path = diff_dir.join(format!("{}-{}.png", sanitize_name(name), millis));
+    fs::write(&path, bytes)?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Make a baseline name safe to use as a file name
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
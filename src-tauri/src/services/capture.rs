@@ -1,15 +1,75 @@
 //! Screen capture service using xcap
 
-use image::DynamicImage;
-use serde::Serialize;
-use xcap::Monitor;
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, error};
+use xcap::{Monitor, Window};
 
 use crate::error::XenotesterError;
-use crate::services::image_processor::{resize_screenshot, ResizeResult};
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+
+use crate::services::image_processor::{
+    draw_cursor, resize_and_encode, thumbnail_and_encode, EncodedScreenshot, ImageEncoding,
+    ResizeQuality,
+};
+use crate::services::mouse;
+use crate::services::accessibility;
+use crate::services::privacy::{self, PrivacyMode, RedactionMode, RedactionZone};
 
 #[cfg(target_os = "macos")]
 use core_graphics::display::CGDisplay;
 
+/// Interval between monitor layout checks in `watch_monitors`
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What a throttled capture does when requested before the minimum interval has passed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThrottleMode {
+    /// Wait for the interval, then capture (requests queue up)
+    #[default]
+    Wait,
+    /// Return the last frame again if it was grabbed with the same options
+    Reuse,
+}
+
+/// Frame-rate cap for the screenshot commands
+///
+/// Internal consumers that compare successive frames (recording, idle and
+/// stability checks) always grab fresh frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CaptureThrottle {
+    /// Minimum time between captures; 0 disables throttling
+    pub min_interval_ms: u64,
+    pub mode: ThrottleMode,
+}
+
+/// Last frame of a throttled capture
+struct LastFrame {
+    started_at: Instant,
+    monitor_id: Option<u32>,
+    include_cursor: bool,
+    frame: Frame,
+}
+
+static THROTTLE: RwLock<CaptureThrottle> = RwLock::new(CaptureThrottle {
+    min_interval_ms: 0,
+    mode: ThrottleMode::Wait,
+});
+static LAST_FRAME: Mutex<Option<LastFrame>> = Mutex::new(None);
+
+pub fn set_throttle(throttle: CaptureThrottle) {
+    *THROTTLE.write().unwrap_or_else(PoisonError::into_inner) = throttle;
+}
+
+pub fn throttle() -> CaptureThrottle {
+    *THROTTLE.read().unwrap_or_else(PoisonError::into_inner)
+}
+
 /// Get the display scale factor for HiDPI/Retina displays on macOS
 /// Returns 2.0 for Retina displays, 1.0 for standard displays
 ///
@@ -44,7 +104,7 @@ fn get_display_scale_factor() -> f64 {
 }
 
 /// Monitor information for frontend display
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MonitorInfo {
     pub id: u32,
@@ -56,6 +116,29 @@ pub struct MonitorInfo {
     pub is_primary: bool,
 }
 
+/// Focused window at capture time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveWindow {
+    pub app_name: String,
+    pub title: String,
+    /// Window bounds on the desktop (logical points)
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ActiveWindow {
+    /// One-line description for LLM prompts
+    pub fn describe(&self) -> String {
+        format!(
+            "Focused window: {} \"{}\" at ({}, {}) size {}x{}",
+            self.app_name, self.title, self.x, self.y, self.width, self.height
+        )
+    }
+}
+
 /// Capture result including metadata and image
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -66,10 +149,108 @@ pub struct CaptureResult {
     pub resized_height: u32,
     pub scale_factor: f64,
     pub image_base64: String,
+    /// MIME type of `image_base64` (e.g. "image/png")
+    pub media_type: String,
     pub monitor_id: u32,
+    /// Monitor origin on the desktop (needed to map clicks on secondary monitors)
+    pub monitor_x: i32,
+    pub monitor_y: i32,
     /// Display scale factor for HiDPI/Retina displays (e.g., 2.0 for Retina)
     /// This is the ratio of physical pixels to logical points
     pub display_scale_factor: f64,
+    /// Focused window when the frame was grabbed (None if it couldn't be determined)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_window: Option<ActiveWindow>,
+}
+
+/// Options shared by the capture functions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureOptions {
+    pub encoding: ImageEncoding,
+    /// Draw the mouse pointer onto the screenshot
+    pub include_cursor: bool,
+    /// Resampling filter (None: `High` for full captures, `Fast` for thumbnails)
+    pub resize_quality: Option<ResizeQuality>,
+}
+
+/// Capture metadata plus the encoded image bytes, for binary IPC responses
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawCaptureResult {
+    pub original_width: u32,
+    pub original_height: u32,
+    pub resized_width: u32,
+    pub resized_height: u32,
+    pub scale_factor: f64,
+    /// MIME type of `image_bytes`
+    pub media_type: String,
+    pub monitor_id: u32,
+    pub monitor_x: i32,
+    pub monitor_y: i32,
+    pub display_scale_factor: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_window: Option<ActiveWindow>,
+    /// Encoded image (not serialized; sent as the binary body)
+    #[serde(skip)]
+    pub image_bytes: Vec<u8>,
+}
+
+impl From<RawCaptureResult> for CaptureResult {
+    fn from(raw: RawCaptureResult) -> Self {
+        Self {
+            original_width: raw.original_width,
+            original_height: raw.original_height,
+            resized_width: raw.resized_width,
+            resized_height: raw.resized_height,
+            scale_factor: raw.scale_factor,
+            image_base64: BASE64_STANDARD.encode(&raw.image_bytes),
+            media_type: raw.media_type,
+            monitor_id: raw.monitor_id,
+            monitor_x: raw.monitor_x,
+            monitor_y: raw.monitor_y,
+            display_scale_factor: raw.display_scale_factor,
+            active_window: raw.active_window,
+        }
+    }
+}
+
+/// Where a monitor sits in a virtual desktop capture
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorPlacement {
+    pub monitor_id: u32,
+    pub name: String,
+    pub is_primary: bool,
+    /// Monitor bounds on the desktop (logical points)
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Monitor bounds within the resized composite image
+    pub image_x: u32,
+    pub image_y: u32,
+    pub image_width: u32,
+    pub image_height: u32,
+}
+
+/// Composite capture of all monitors stitched by their desktop positions
+///
+/// The composite is laid out in logical points, so a point on the resized image maps to
+/// the desktop as `point / scaleFactor + origin` (pass it to `translate_coordinates`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VirtualDesktopCapture {
+    pub original_width: u32,
+    pub original_height: u32,
+    pub resized_width: u32,
+    pub resized_height: u32,
+    pub scale_factor: f64,
+    pub image_base64: String,
+    pub media_type: String,
+    /// Desktop position of the composite's top-left corner
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub monitors: Vec<MonitorPlacement>,
 }
 
 /// Get list of all available monitors
@@ -92,63 +273,413 @@ pub fn list_monitors() -> Result<Vec<MonitorInfo>, XenotesterError> {
     Ok(result)
 }
 
-/// Capture primary monitor (default for Computer Use API)
-pub fn capture_primary_monitor() -> Result<CaptureResult, XenotesterError> {
-    let monitors = Monitor::all().map_err(|e| XenotesterError::CaptureError(e.to_string()))?;
+/// Monitors and display scale, as reported by `monitors-changed`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorLayout {
+    pub monitors: Vec<MonitorInfo>,
+    pub display_scale_factor: f64,
+}
 
-    // Find primary monitor or use first one
-    let (monitor_id, monitor) = monitors
-        .into_iter()
-        .enumerate()
-        .find(|(_, m)| m.is_primary().unwrap_or(false))
-        .or_else(|| {
-            Monitor::all()
-                .ok()
-                .and_then(|m| m.into_iter().enumerate().next())
-        })
-        .ok_or_else(|| XenotesterError::CaptureError("No monitors found".to_string()))?;
+pub fn monitor_layout() -> Result<MonitorLayout, XenotesterError> {
+    Ok(MonitorLayout {
+        monitors: list_monitors()?,
+        display_scale_factor: get_display_scale_factor(),
+    })
+}
+
+/// Poll the monitor layout in the background and call `on_change` when a display
+/// is connected or disconnected, or changes position, resolution or scale
+pub fn watch_monitors(on_change: impl Fn(&MonitorLayout) + Send + 'static) {
+    let spawned = thread::Builder::new()
+        .name("monitor-watch".to_string())
+        .spawn(move || {
+            let mut previous = monitor_layout().ok();
+            loop {
+                thread::sleep(MONITOR_POLL_INTERVAL);
+                let layout = match monitor_layout() {
+                    Ok(layout) => layout,
+                    // Monitor enumeration fails briefly while displays reconfigure
+                    Err(e) => {
+                        debug!("Monitor layout check failed: {}", e);
+                        continue;
+                    }
+                };
+                if previous.as_ref() != Some(&layout) {
+                    if previous.is_some() {
+                        on_change(&layout);
+                    }
+                    previous = Some(layout);
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        error!("Failed to start monitor watcher: {}", e);
+    }
+}
+
+/// Find the monitor containing the given absolute desktop position
+pub fn find_monitor_at(x: i32, y: i32) -> Result<Option<MonitorInfo>, XenotesterError> {
+    Ok(list_monitors()?.into_iter().find(|m| {
+        x >= m.x && x < m.x + m.width as i32 && y >= m.y && y < m.y + m.height as i32
+    }))
+}
 
-    capture_monitor_internal(monitor_id as u32, monitor)
+/// Capture primary monitor (default for Computer Use API)
+pub fn capture_primary_monitor(options: &CaptureOptions) -> Result<CaptureResult, XenotesterError> {
+    capture_primary_monitor_raw(options).map(CaptureResult::from)
+}
+
+/// Capture primary monitor without base64-encoding the image
+pub fn capture_primary_monitor_raw(
+    options: &CaptureOptions,
+) -> Result<RawCaptureResult, XenotesterError> {
+    encode_frame(grab_frame_throttled(None, options.include_cursor)?, options)
 }
 
 /// Capture specific monitor by ID
-pub fn capture_monitor(monitor_id: u32) -> Result<CaptureResult, XenotesterError> {
-    let monitors = Monitor::all().map_err(|e| XenotesterError::CaptureError(e.to_string()))?;
+pub fn capture_monitor(
+    monitor_id: u32,
+    options: &CaptureOptions,
+) -> Result<CaptureResult, XenotesterError> {
+    capture_monitor_raw(monitor_id, options).map(CaptureResult::from)
+}
 
-    let monitor = monitors
-        .into_iter()
-        .nth(monitor_id as usize)
-        .ok_or_else(|| {
-            XenotesterError::CaptureError(format!("Monitor {} not found", monitor_id))
-        })?;
+/// Capture specific monitor by ID without base64-encoding the image
+pub fn capture_monitor_raw(
+    monitor_id: u32,
+    options: &CaptureOptions,
+) -> Result<RawCaptureResult, XenotesterError> {
+    let frame = grab_frame_throttled(Some(monitor_id), options.include_cursor)?;
+    encode_frame(frame, options)
+}
 
-    capture_monitor_internal(monitor_id, monitor)
+/// Unencoded frame grabbed from a monitor
+#[derive(Clone)]
+pub struct Frame {
+    pub monitor_id: u32,
+    pub monitor_x: i32,
+    pub monitor_y: i32,
+    pub display_scale_factor: f64,
+    pub active_window: Option<ActiveWindow>,
+    pub image: DynamicImage,
 }
 
-/// Internal capture implementation
-fn capture_monitor_internal(monitor_id: u32, monitor: Monitor) -> Result<CaptureResult, XenotesterError> {
+/// Focused, non-minimized window (None when the platform doesn't report one)
+pub fn active_window() -> Option<ActiveWindow> {
+    let windows = Window::all()
+        .map_err(|e| debug!("Window list failed: {}", e))
+        .ok()?;
+    let window = windows.into_iter().find(|window| {
+        window.is_focused().unwrap_or(false) && !window.is_minimized().unwrap_or(false)
+    })?;
+    Some(ActiveWindow {
+        app_name: window.app_name().unwrap_or_default(),
+        title: window.title().unwrap_or_default(),
+        x: window.x().unwrap_or(0),
+        y: window.y().unwrap_or(0),
+        width: window.width().unwrap_or(0),
+        height: window.height().unwrap_or(0),
+    })
+}
+
+/// Zones the privacy mode blurs in the next capture
+/// Detection failures are logged and skipped, so a capture is never refused for them.
+fn detect_sensitive_zones() -> Vec<RedactionZone> {
+    let mode = privacy::mode();
+    let mut zones = Vec::new();
+    if mode == PrivacyMode::Off {
+        return zones;
+    }
+
+    match Window::all() {
+        Ok(windows) => {
+            for window in windows {
+                if window.is_minimized().unwrap_or(false) {
+                    continue;
+                }
+                let app_name = window.app_name().unwrap_or_default();
+                let title = window.title().unwrap_or_default();
+                if privacy::is_sensitive_window(&app_name, &title) {
+                    zones.push(RedactionZone {
+                        x: window.x().unwrap_or(0),
+                        y: window.y().unwrap_or(0),
+                        width: window.width().unwrap_or(0),
+                        height: window.height().unwrap_or(0),
+                        mode: RedactionMode::Blur,
+                        label: Some(app_name),
+                    });
+                }
+            }
+        }
+        Err(e) => debug!("Privacy window scan failed: {}", e),
+    }
+
+    if mode == PrivacyMode::Strict {
+        match accessibility::find_bounds_matching(privacy::contains_pii) {
+            Ok(found) => zones.extend(found.into_iter().map(|bounds| RedactionZone {
+                x: bounds.x.floor() as i32,
+                y: bounds.y.floor() as i32,
+                width: bounds.width.ceil() as u32,
+                height: bounds.height.ceil() as u32,
+                mode: RedactionMode::Blur,
+                label: Some("personal data".to_string()),
+            })),
+            Err(e) => debug!("Privacy text scan failed: {}", e),
+        }
+    }
+
+    zones
+}
+
+/// Grab a frame from a monitor (primary monitor when `monitor_id` is None)
+pub fn grab_frame(monitor_id: Option<u32>, include_cursor: bool) -> Result<Frame, XenotesterError> {
+    let monitors = Monitor::all().map_err(|e| XenotesterError::CaptureError(e.to_string()))?;
+
+    let (monitor_id, monitor) = match monitor_id {
+        Some(id) => {
+            let monitor = monitors
+                .into_iter()
+                .nth(id as usize)
+                .ok_or_else(|| XenotesterError::NotFound(format!("monitor {}", id)))?;
+            (id, monitor)
+        }
+        None => {
+            // Find primary monitor or use first one
+            let (idx, monitor) = monitors
+                .into_iter()
+                .enumerate()
+                .find(|(_, m)| m.is_primary().unwrap_or(false))
+                .or_else(|| {
+                    Monitor::all()
+                        .ok()
+                        .and_then(|m| m.into_iter().enumerate().next())
+                })
+                .ok_or_else(|| XenotesterError::CaptureError("No monitors found".to_string()))?;
+            (idx as u32, monitor)
+        }
+    };
+
     // Get the display scale factor before capture
     let display_scale_factor = get_display_scale_factor();
 
     // Capture the screen
-    let image = monitor
+    let mut image = monitor
         .capture_image()
         .map_err(|e| XenotesterError::CaptureError(e.to_string()))?;
 
-    // Convert to DynamicImage
-    let dynamic_image = DynamicImage::ImageRgba8(image);
+    let monitor_x = monitor.x().unwrap_or(0);
+    let monitor_y = monitor.y().unwrap_or(0);
 
-    // Resize and encode
-    let resize_result: ResizeResult = resize_screenshot(dynamic_image)?;
+    privacy::redact(
+        &mut image,
+        (monitor_x, monitor_y),
+        display_scale_factor,
+        &detect_sensitive_zones(),
+    );
 
-    Ok(CaptureResult {
-        original_width: resize_result.original_width,
-        original_height: resize_result.original_height,
-        resized_width: resize_result.resized_width,
-        resized_height: resize_result.resized_height,
-        scale_factor: resize_result.scale_factor,
-        image_base64: resize_result.image_base64,
+    if include_cursor {
+        // Cursor position is in logical points; the frame is in physical pixels
+        let (cursor_x, cursor_y) = mouse::get_position()?;
+        draw_cursor(
+            &mut image,
+            ((cursor_x - monitor_x) as f64 * display_scale_factor).round() as i32,
+            ((cursor_y - monitor_y) as f64 * display_scale_factor).round() as i32,
+            display_scale_factor,
+        );
+    }
+
+    Ok(Frame {
         monitor_id,
+        monitor_x,
+        monitor_y,
         display_scale_factor,
+        active_window: active_window(),
+        image: DynamicImage::ImageRgba8(image),
+    })
+}
+
+/// Grab a frame, keeping to the configured `CaptureThrottle`
+/// Concurrent requests are served one at a time.
+pub fn grab_frame_throttled(
+    monitor_id: Option<u32>,
+    include_cursor: bool,
+) -> Result<Frame, XenotesterError> {
+    let throttle = throttle();
+    if throttle.min_interval_ms == 0 {
+        return grab_frame(monitor_id, include_cursor);
+    }
+
+    let interval = Duration::from_millis(throttle.min_interval_ms);
+    let mut last = LAST_FRAME.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(previous) = last.as_ref() {
+        let elapsed = previous.started_at.elapsed();
+        if elapsed < interval {
+            let same_request =
+                previous.monitor_id == monitor_id && previous.include_cursor == include_cursor;
+            if throttle.mode == ThrottleMode::Reuse && same_request {
+                return Ok(previous.frame.clone());
+            }
+            thread::sleep(interval - elapsed);
+        }
+    }
+
+    let started_at = Instant::now();
+    let frame = grab_frame(monitor_id, include_cursor)?;
+    *last = Some(LastFrame {
+        started_at,
+        monitor_id,
+        include_cursor,
+        frame: frame.clone(),
+    });
+    Ok(frame)
+}
+
+/// Resize and encode a grabbed frame
+pub fn encode_frame(
+    frame: Frame,
+    options: &CaptureOptions,
+) -> Result<RawCaptureResult, XenotesterError> {
+    let quality = options.resize_quality.unwrap_or(ResizeQuality::High);
+    encode_frame_with(frame, |image| resize_and_encode(image, &options.encoding, quality))
+}
+
+/// Capture a small preview of a monitor (primary when `monitor_id` is None)
+/// The long edge is at most `max_edge`; `scale_factor` maps it back like a full capture.
+pub fn capture_thumbnail(
+    monitor_id: Option<u32>,
+    max_edge: u32,
+    options: &CaptureOptions,
+) -> Result<CaptureResult, XenotesterError> {
+    let frame = grab_frame_throttled(monitor_id, options.include_cursor)?;
+    encode_thumbnail(frame, max_edge, options).map(CaptureResult::from)
+}
+
+/// Shrink a grabbed frame to fit `max_edge` and encode it
+pub fn encode_thumbnail(
+    frame: Frame,
+    max_edge: u32,
+    options: &CaptureOptions,
+) -> Result<RawCaptureResult, XenotesterError> {
+    let quality = options.resize_quality.unwrap_or(ResizeQuality::Fast);
+    encode_frame_with(frame, |image| {
+        thumbnail_and_encode(image, max_edge, &options.encoding, quality)
+    })
+}
+
+fn encode_frame_with(
+    frame: Frame,
+    encode: impl FnOnce(DynamicImage) -> Result<EncodedScreenshot, XenotesterError>,
+) -> Result<RawCaptureResult, XenotesterError> {
+    let encoded = encode(frame.image)?;
+
+    Ok(RawCaptureResult {
+        original_width: encoded.original_width,
+        original_height: encoded.original_height,
+        resized_width: encoded.resized_width,
+        resized_height: encoded.resized_height,
+        scale_factor: encoded.scale_factor,
+        media_type: encoded.media_type,
+        monitor_id: frame.monitor_id,
+        monitor_x: frame.monitor_x,
+        monitor_y: frame.monitor_y,
+        display_scale_factor: frame.display_scale_factor,
+        active_window: frame.active_window,
+        image_bytes: encoded.bytes,
+    })
+}
+
+/// Capture every monitor and stitch them into one composite image
+pub fn capture_virtual_desktop(
+    options: &CaptureOptions,
+) -> Result<VirtualDesktopCapture, XenotesterError> {
+    let monitors = Monitor::all().map_err(|e| XenotesterError::CaptureError(e.to_string()))?;
+    if monitors.is_empty() {
+        return Err(XenotesterError::CaptureError("No monitors found".to_string()));
+    }
+
+    // Capture each monitor at its logical size (Retina frames are downscaled to points)
+    let mut frames = Vec::with_capacity(monitors.len());
+    for (idx, monitor) in monitors.iter().enumerate() {
+        let info = MonitorInfo {
+            id: idx as u32,
+            name: monitor.name().unwrap_or_default(),
+            x: monitor.x().unwrap_or(0),
+            y: monitor.y().unwrap_or(0),
+            width: monitor.width().unwrap_or(0),
+            height: monitor.height().unwrap_or(0),
+            is_primary: monitor.is_primary().unwrap_or(false),
+        };
+        let mut image = monitor
+            .capture_image()
+            .map_err(|e| XenotesterError::CaptureError(e.to_string()))?;
+        if info.width > 0 && info.height > 0 && image.dimensions() != (info.width, info.height) {
+            image = image::imageops::resize(
+                &image,
+                info.width,
+                info.height,
+                image::imageops::FilterType::Triangle,
+            );
+        }
+        frames.push((info, image));
+    }
+
+    // Bounding box of all monitors on the desktop
+    let origin_x = frames.iter().map(|(m, _)| m.x).min().unwrap_or(0);
+    let origin_y = frames.iter().map(|(m, _)| m.y).min().unwrap_or(0);
+    let max_x = frames.iter().map(|(m, img)| m.x + img.width() as i32).max().unwrap_or(0);
+    let max_y = frames.iter().map(|(m, img)| m.y + img.height() as i32).max().unwrap_or(0);
+
+    let mut canvas = RgbaImage::new((max_x - origin_x) as u32, (max_y - origin_y) as u32);
+    for (info, image) in &frames {
+        image::imageops::replace(
+            &mut canvas,
+            image,
+            (info.x - origin_x) as i64,
+            (info.y - origin_y) as i64,
+        );
+    }
+
+    privacy::redact(&mut canvas, (origin_x, origin_y), 1.0, &detect_sensitive_zones());
+
+    if options.include_cursor {
+        // The composite is laid out in logical points, so no display scaling is needed
+        let (cursor_x, cursor_y) = mouse::get_position()?;
+        draw_cursor(&mut canvas, cursor_x - origin_x, cursor_y - origin_y, 1.0);
+    }
+
+    let quality = options.resize_quality.unwrap_or(ResizeQuality::High);
+    let encoded = resize_and_encode(DynamicImage::ImageRgba8(canvas), &options.encoding, quality)?;
+    let scale = encoded.scale_factor;
+    let to_image = |v: i32| (v as f64 * scale).round() as u32;
+
+    let placements = frames
+        .into_iter()
+        .map(|(info, image)| MonitorPlacement {
+            monitor_id: info.id,
+            name: info.name,
+            is_primary: info.is_primary,
+            x: info.x,
+            y: info.y,
+            width: image.width(),
+            height: image.height(),
+            image_x: to_image(info.x - origin_x),
+            image_y: to_image(info.y - origin_y),
+            image_width: to_image(image.width() as i32),
+            image_height: to_image(image.height() as i32),
+        })
+        .collect();
+
+    Ok(VirtualDesktopCapture {
+        original_width: encoded.original_width,
+        original_height: encoded.original_height,
+        resized_width: encoded.resized_width,
+        resized_height: encoded.resized_height,
+        scale_factor: encoded.scale_factor,
+        image_base64: BASE64_STANDARD.encode(&encoded.bytes),
+        media_type: encoded.media_type,
+        origin_x,
+        origin_y,
+        monitors: placements,
     })
 }
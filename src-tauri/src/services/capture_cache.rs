@@ -0,0 +1,98 @@
+//! Per-monitor cache of the last captured frame
+//!
+//! Lets agent loops poll the screen cheaply: frames are compared by a small
+//! grayscale fingerprint and only resized/encoded when something changed.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::error::XenotesterError;
+use crate::services::capture::{encode_frame, grab_frame_throttled, CaptureOptions, CaptureResult};
+use crate::services::image_processor::{fingerprint_change_ratio, frame_fingerprint};
+
+/// Default change threshold: any visible change counts
+pub const DEFAULT_CHANGE_THRESHOLD: f64 = 0.0;
+
+/// Last frame seen for a monitor
+struct CachedFrame {
+    fingerprint: Vec<u8>,
+    options: CaptureOptions,
+}
+
+/// Result of a change-detecting capture
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeCaptureResult {
+    /// True when the screen did not change beyond the threshold (no new image)
+    pub unchanged: bool,
+    /// Fraction of the screen that changed since the cached frame (0.0 - 1.0)
+    pub change_ratio: f64,
+    /// Fresh capture, present only when the screen changed
+    pub capture: Option<CaptureResult>,
+}
+
+/// Last frame fingerprint per monitor
+#[derive(Default)]
+pub struct CaptureCache {
+    frames: Mutex<HashMap<u32, CachedFrame>>,
+}
+
+impl CaptureCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Capture a monitor, skipping resize/encode when it matches the cached frame
+    ///
+    /// The frame counts as unchanged when its change ratio is at most `threshold`
+    /// and it was last captured with the same options.
+    pub fn capture_if_changed(
+        &self,
+        monitor_id: Option<u32>,
+        threshold: f64,
+        options: &CaptureOptions,
+    ) -> Result<ChangeCaptureResult, XenotesterError> {
+        let frame = grab_frame_throttled(monitor_id, options.include_cursor)?;
+        let monitor_id = frame.monitor_id;
+        let fingerprint = frame_fingerprint(&frame.image);
+
+        let change_ratio = {
+            let frames = self.frames.lock().unwrap();
+            match frames.get(&monitor_id) {
+                Some(cached) if cached.options == *options => {
+                    fingerprint_change_ratio(&cached.fingerprint, &fingerprint)
+                }
+                _ => 1.0,
+            }
+        };
+
+        if change_ratio <= threshold {
+            return Ok(ChangeCaptureResult {
+                unchanged: true,
+                change_ratio,
+                capture: None,
+            });
+        }
+
+        let capture = CaptureResult::from(encode_frame(frame, options)?);
+        self.frames.lock().unwrap().insert(
+            monitor_id,
+            CachedFrame {
+                fingerprint,
+                options: *options,
+            },
+        );
+
+        Ok(ChangeCaptureResult {
+            unchanged: false,
+            change_ratio,
+            capture: Some(capture),
+        })
+    }
+
+    /// Forget all cached frames so the next capture is always returned
+    pub fn clear(&self) {
+        self.frames.lock().unwrap().clear();
+    }
+}
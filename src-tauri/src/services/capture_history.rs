@@ -0,0 +1,360 @@
+//! Ring buffer of recent captures
+//!
+//! Keeps the last N screenshots taken by the capture commands and agent
+//! sessions, so a failure can be reviewed with the frames leading up to it
+//! rather than only the final one. Frames are held in memory, or written to a
+//! directory with only their metadata in memory when `storage` is `disk`.
+//! Frames on disk do not outlive the process: the directory is emptied when
+//! it is set up.
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::error::XenotesterError;
+use crate::services::capture::CaptureResult;
+
+/// Frames kept unless configured otherwise
+pub const DEFAULT_CAPTURE_HISTORY_CAPACITY: usize = 20;
+/// Upper bound on the configured capacity
+pub const MAX_CAPTURE_HISTORY_CAPACITY: usize = 500;
+
+/// Where frame images are kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureStorage {
+    #[default]
+    Memory,
+    /// Image files in the history directory; falls back to memory without one
+    Disk,
+}
+
+/// Size and storage of the ring buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CaptureHistorySettings {
+    /// Frames kept; 0 disables the history
+    pub capacity: usize,
+    pub storage: CaptureStorage,
+}
+
+impl Default for CaptureHistorySettings {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPTURE_HISTORY_CAPACITY,
+            storage: CaptureStorage::default(),
+        }
+    }
+}
+
+impl CaptureHistorySettings {
+    pub fn validate(&self) -> Result<(), XenotesterError> {
+        if self.capacity > MAX_CAPTURE_HISTORY_CAPACITY {
+            return Err(XenotesterError::ConfigError(format!(
+                "Capture history capacity must be at most {}",
+                MAX_CAPTURE_HISTORY_CAPACITY
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A frame from the history
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentCapture {
+    /// Increases with every recorded frame
+    pub sequence: u64,
+    /// Unix time of the capture in milliseconds
+    pub captured_at_ms: i64,
+    #[serde(flatten)]
+    pub capture: CaptureResult,
+}
+
+struct Entry {
+    frame: RecentCapture,
+    /// Image file of a frame on disk; its `image_base64` is then empty
+    file: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct Frames {
+    entries: VecDeque<Entry>,
+    next_sequence: u64,
+    settings: CaptureHistorySettings,
+    dir: Option<PathBuf>,
+}
+
+impl Frames {
+    fn evict_to(&mut self, capacity: usize) {
+        while self.entries.len() > capacity {
+            if let Some(file) = self.entries.pop_front().and_then(|entry| entry.file) {
+                remove_frame_file(&file);
+            }
+        }
+    }
+}
+
+/// Last captured frames, oldest first
+#[derive(Default)]
+pub struct CaptureHistory {
+    frames: Mutex<Frames>,
+}
+
+impl std::fmt::Debug for CaptureHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let frames = self.frames.lock().unwrap();
+        f.debug_struct("CaptureHistory")
+            .field("settings", &frames.settings)
+            .field("len", &frames.entries.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl CaptureHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `dir` for frames stored on disk, removing frames left in it
+    pub fn set_dir(&self, dir: PathBuf) -> Result<(), XenotesterError> {
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        self.frames.lock().unwrap().dir = Some(dir);
+        Ok(())
+    }
+
+    pub fn settings(&self) -> CaptureHistorySettings {
+        self.frames.lock().unwrap().settings
+    }
+
+    /// Change the size and storage; frames beyond the new capacity are dropped
+    /// A storage change applies to frames recorded from now on.
+    pub fn set_settings(&self, settings: CaptureHistorySettings) -> Result<(), XenotesterError> {
+        settings.validate()?;
+        let mut frames = self.frames.lock().unwrap();
+        frames.settings = settings;
+        frames.evict_to(settings.capacity);
+        Ok(())
+    }
+
+    /// Add a frame, dropping the oldest one when full
+    /// A frame that cannot be written to disk is kept in memory.
+    pub fn record(&self, capture: &CaptureResult) {
+        let mut frames = self.frames.lock().unwrap();
+        let settings = frames.settings;
+        if settings.capacity == 0 {
+            return;
+        }
+
+        let sequence = frames.next_sequence;
+        frames.next_sequence += 1;
+        let mut frame = RecentCapture {
+            sequence,
+            captured_at_ms: chrono::Utc::now().timestamp_millis(),
+            capture: capture.clone(),
+        };
+        let file = match (settings.storage, &frames.dir) {
+            (CaptureStorage::Disk, Some(dir)) => match write_frame_file(dir, &frame) {
+                Ok(file) => {
+                    frame.capture.image_base64.clear();
+                    Some(file)
+                }
+                Err(e) => {
+                    warn!("Keeping capture {} in memory: {}", sequence, e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        frames.entries.push_back(Entry { frame, file });
+        frames.evict_to(settings.capacity);
+    }
+
+    /// The last `count` frames, oldest first
+    pub fn recent(&self, count: usize) -> Result<Vec<RecentCapture>, XenotesterError> {
+        let entries: Vec<(RecentCapture, Option<PathBuf>)> = {
+            let frames = self.frames.lock().unwrap();
+            let skip = frames.entries.len().saturating_sub(count);
+            frames
+                .entries
+                .iter()
+                .skip(skip)
+                .map(|entry| (entry.frame.clone(), entry.file.clone()))
+                .collect()
+        };
+        entries.into_iter().map(load_frame).collect()
+    }
+
+    /// The last `count` frames captured within a time range (Unix milliseconds),
+    /// oldest first; frames evicted since are missing
+    pub fn between(
+        &self,
+        from_ms: i64,
+        to_ms: Option<i64>,
+        count: usize,
+    ) -> Result<Vec<RecentCapture>, XenotesterError> {
+        let entries: Vec<(RecentCapture, Option<PathBuf>)> = {
+            let frames = self.frames.lock().unwrap();
+            let matching: Vec<&Entry> = frames
+                .entries
+                .iter()
+                .filter(|entry| {
+                    entry.frame.captured_at_ms >= from_ms
+                        && to_ms.is_none_or(|to| entry.frame.captured_at_ms <= to)
+                })
+                .collect();
+            let skip = matching.len().saturating_sub(count);
+            matching
+                .into_iter()
+                .skip(skip)
+                .map(|entry| (entry.frame.clone(), entry.file.clone()))
+                .collect()
+        };
+        entries.into_iter().map(load_frame).collect()
+    }
+
+    /// Drop all frames
+    pub fn clear(&self) {
+        self.frames.lock().unwrap().evict_to(0);
+    }
+}
+
+fn extension(media_type: &str) -> &'static str {
+    match media_type {
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        _ => "png",
+    }
+}
+
+fn write_frame_file(dir: &Path, frame: &RecentCapture) -> Result<PathBuf, XenotesterError> {
+    let bytes = BASE64_STANDARD
+        .decode(&frame.capture.image_base64)
+        .map_err(|e| XenotesterError::ImageError(format!("Invalid base64 capture: {}", e)))?;
+    let path = dir.join(format!(
+        "{}.{}",
+        frame.sequence,
+        extension(&frame.capture.media_type)
+    ));
+    fs::write(&path, bytes)?;
+    Ok(path)
+}
+
+fn remove_frame_file(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        warn!("Failed to remove capture {}: {}", path.display(), e);
+    }
+}
+
+/// Read the image of a frame stored on disk
+fn load_frame(
+    (mut frame, file): (RecentCapture, Option<PathBuf>),
+) -> Result<RecentCapture, XenotesterError> {
+    if let Some(file) = file {
+        frame.capture.image_base64 = BASE64_STANDARD.encode(fs::read(file)?);
+    }
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(image: &[u8]) -> CaptureResult {
+        CaptureResult {
+            original_width: 1,
+            original_height: 1,
+            resized_width: 1,
+            resized_height: 1,
+            scale_factor: 1.0,
+            image_base64: BASE64_STANDARD.encode(image),
+            media_type: "image/png".to_string(),
+            monitor_id: 0,
+            monitor_x: 0,
+            monitor_y: 0,
+            display_scale_factor: 1.0,
+            active_window: None,
+        }
+    }
+
+    fn sequences(frames: &[RecentCapture]) -> Vec<u64> {
+        frames.iter().map(|frame| frame.sequence).collect()
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_last_frames() {
+        let history = CaptureHistory::new();
+        history
+            .set_settings(CaptureHistorySettings {
+                capacity: 3,
+                ..Default::default()
+            })
+            .unwrap();
+        for i in 0..5 {
+            history.record(&capture(&[i]));
+        }
+
+        assert_eq!(sequences(&history.recent(10).unwrap()), vec![2, 3, 4]);
+        assert_eq!(sequences(&history.recent(2).unwrap()), vec![3, 4]);
+
+        history
+            .set_settings(CaptureHistorySettings {
+                capacity: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(sequences(&history.recent(10).unwrap()), vec![4]);
+        assert!(history
+            .set_settings(CaptureHistorySettings {
+                capacity: MAX_CAPTURE_HISTORY_CAPACITY + 1,
+                ..Default::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_disk_storage_round_trip() {
+        let dir = std::env::temp_dir().join(format!("capture-history-{}", uuid::Uuid::new_v4()));
+        let history = CaptureHistory::new();
+        history.set_dir(dir.clone()).unwrap();
+        history
+            .set_settings(CaptureHistorySettings {
+                capacity: 2,
+                storage: CaptureStorage::Disk,
+            })
+            .unwrap();
+        for i in 0..3 {
+            history.record(&capture(&[i, 42]));
+        }
+
+        let frames = history.recent(10).unwrap();
+        assert_eq!(
+            frames[1].capture.image_base64,
+            capture(&[2, 42]).image_base64
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        history.clear();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_between_filters_by_time() {
+        let history = CaptureHistory::new();
+        history.record(&capture(&[1]));
+        let frame = history.recent(1).unwrap().remove(0);
+
+        let at = frame.captured_at_ms;
+        assert_eq!(history.between(at, Some(at), 5).unwrap().len(), 1);
+        assert!(history.between(at + 1, None, 5).unwrap().is_empty());
+    }
+}
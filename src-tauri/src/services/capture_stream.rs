@@ -0,0 +1,182 @@
+//! Live capture stream for watching a run
+//!
+//! A background thread grabs frames at a fixed FPS, shrinks them with the fast
+//! thumbnail filter and hands each one to a callback (the command layer emits
+//! them as events). Frames are skipped rather than queued when encoding falls
+//! behind, so the view never lags further than one frame.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::error::XenotesterError;
+use crate::services::capture::{encode_thumbnail, grab_frame, CaptureOptions, CaptureResult};
+use crate::services::image_processor::{ImageEncoding, ImageFormatKind};
+
+const DEFAULT_FPS: u32 = 5;
+const MAX_FPS: u32 = 30;
+/// Frames are downscaled so their long edge fits this size
+const DEFAULT_MAX_EDGE: u32 = 960;
+/// JPEG quality of streamed frames
+const DEFAULT_STREAM_QUALITY: u8 = 60;
+
+/// Capture stream options
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StreamOptions {
+    /// Frames per second (1-30)
+    pub fps: u32,
+    /// Monitor to stream (primary monitor when omitted)
+    pub monitor_id: Option<u32>,
+    /// Draw the mouse pointer into the frames
+    pub include_cursor: bool,
+    /// Long edge limit for frames
+    pub max_edge: u32,
+    pub encoding: ImageEncoding,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            fps: DEFAULT_FPS,
+            monitor_id: None,
+            include_cursor: true,
+            max_edge: DEFAULT_MAX_EDGE,
+            encoding: ImageEncoding {
+                format: ImageFormatKind::Jpeg,
+                quality: DEFAULT_STREAM_QUALITY,
+            },
+        }
+    }
+}
+
+/// One streamed frame
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamFrame {
+    /// Frame number, starting at 0
+    pub sequence: u64,
+    /// Time since the stream started
+    pub elapsed_ms: u64,
+    #[serde(flatten)]
+    pub capture: CaptureResult,
+}
+
+/// Summary of a stopped stream
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamStats {
+    pub frame_count: u64,
+    pub duration_ms: u64,
+}
+
+/// Receives every captured frame on the stream thread
+pub type FrameCallback = Box<dyn Fn(StreamFrame) + Send>;
+
+/// Stream in progress
+struct ActiveStream {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<StreamStats>,
+}
+
+/// Owns the (single) active capture stream
+#[derive(Default)]
+pub struct CaptureStream {
+    active: Mutex<Option<ActiveStream>>,
+}
+
+impl CaptureStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start streaming frames to `on_frame` on a background thread
+    pub fn start(
+        &self,
+        options: StreamOptions,
+        on_frame: FrameCallback,
+    ) -> Result<(), XenotesterError> {
+        let mut active = self.active.lock().unwrap();
+        if active.is_some() {
+            return Err(XenotesterError::CaptureError(
+                "A capture stream is already running".to_string(),
+            ));
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let handle = thread::Builder::new()
+            .name("capture-stream".to_string())
+            .spawn(move || stream_loop(options, on_frame, thread_stop))
+            .map_err(|e| XenotesterError::CaptureError(e.to_string()))?;
+
+        info!(
+            "Capture stream started at {} fps",
+            options.fps.clamp(1, MAX_FPS)
+        );
+        *active = Some(ActiveStream { stop, handle });
+        Ok(())
+    }
+
+    /// Stop the active stream and wait for its thread to finish the current frame
+    pub fn stop(&self) -> Result<StreamStats, XenotesterError> {
+        let stream = self.active.lock().unwrap().take().ok_or_else(|| {
+            XenotesterError::CaptureError("No capture stream is running".to_string())
+        })?;
+
+        stream.stop.store(true, Ordering::SeqCst);
+        let stats = stream.handle.join().map_err(|_| {
+            XenotesterError::CaptureError("Capture stream thread panicked".to_string())
+        })?;
+
+        info!(
+            "Capture stream stopped ({} frames, {}ms)",
+            stats.frame_count, stats.duration_ms
+        );
+        Ok(stats)
+    }
+}
+
+/// Capture and deliver frames until stopped
+fn stream_loop(
+    options: StreamOptions,
+    on_frame: FrameCallback,
+    stop: Arc<AtomicBool>,
+) -> StreamStats {
+    let interval = Duration::from_secs_f64(1.0 / options.fps.clamp(1, MAX_FPS) as f64);
+    let capture_options = CaptureOptions {
+        encoding: options.encoding,
+        include_cursor: options.include_cursor,
+        resize_quality: None,
+    };
+    let started = Instant::now();
+    let mut frame_count = 0u64;
+
+    while !stop.load(Ordering::SeqCst) {
+        let tick = Instant::now();
+
+        let encoded = grab_frame(options.monitor_id, options.include_cursor)
+            .and_then(|frame| encode_thumbnail(frame, options.max_edge.max(1), &capture_options));
+        match encoded {
+            Ok(capture) => {
+                on_frame(StreamFrame {
+                    sequence: frame_count,
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                    capture: capture.into(),
+                });
+                frame_count += 1;
+            }
+            Err(e) => warn!("Stream frame capture failed: {}", e),
+        }
+
+        thread::sleep(interval.saturating_sub(tick.elapsed()));
+    }
+
+    StreamStats {
+        frame_count,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
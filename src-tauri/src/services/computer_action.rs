@@ -0,0 +1,396 @@
+//! Computer Use action execution
+//!
+//! Parses the `computer` tool input returned by the LLM and executes it through
+//! the mouse/keyboard services. Coordinates in actions are in resized-screenshot
+//! space and are converted to logical screen points before input is sent.
+
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::error::XenotesterError;
+use crate::services::capture::CaptureResult;
+use crate::services::coords::{self, CoordinateContext, CoordinateSpace};
+use crate::services::keyboard::{self, TypingOptions};
+use crate::services::mouse::{self, MouseButton, ScrollDirection};
+use crate::utils::cancel::CancellationToken;
+
+/// Coordinate pair as sent by the Computer Use tool ([x, y])
+pub type ToolCoordinate = [i32; 2];
+
+/// Action requested through the Computer Use `computer` tool
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ComputerAction {
+    Screenshot,
+    CursorPosition,
+    MouseMove {
+        coordinate: ToolCoordinate,
+    },
+    LeftClick {
+        coordinate: Option<ToolCoordinate>,
+        /// Modifier keys to hold during the click (e.g., "shift", "ctrl+alt")
+        text: Option<String>,
+    },
+    RightClick {
+        coordinate: Option<ToolCoordinate>,
+    },
+    MiddleClick {
+        coordinate: Option<ToolCoordinate>,
+    },
+    DoubleClick {
+        coordinate: Option<ToolCoordinate>,
+    },
+    TripleClick {
+        coordinate: Option<ToolCoordinate>,
+    },
+    LeftClickDrag {
+        start_coordinate: ToolCoordinate,
+        coordinate: ToolCoordinate,
+    },
+    LeftMouseDown {
+        coordinate: Option<ToolCoordinate>,
+    },
+    LeftMouseUp {
+        coordinate: Option<ToolCoordinate>,
+    },
+    Scroll {
+        coordinate: Option<ToolCoordinate>,
+        scroll_direction: String,
+        scroll_amount: Option<i32>,
+    },
+    Type {
+        text: String,
+    },
+    Key {
+        text: String,
+    },
+    HoldKey {
+        text: String,
+        /// Seconds to hold the key
+        duration: f64,
+    },
+    Wait {
+        /// Seconds to wait
+        duration: f64,
+    },
+}
+
+impl ComputerAction {
+    /// Action name as used in the tool schema (for logs and results)
+    pub fn name(&self) -> &'static str {
+        match self {
+            ComputerAction::Screenshot => "screenshot",
+            ComputerAction::CursorPosition => "cursor_position",
+            ComputerAction::MouseMove { .. } => "mouse_move",
+            ComputerAction::LeftClick { .. } => "left_click",
+            ComputerAction::RightClick { .. } => "right_click",
+            ComputerAction::MiddleClick { .. } => "middle_click",
+            ComputerAction::DoubleClick { .. } => "double_click",
+            ComputerAction::TripleClick { .. } => "triple_click",
+            ComputerAction::LeftClickDrag { .. } => "left_click_drag",
+            ComputerAction::LeftMouseDown { .. } => "left_mouse_down",
+            ComputerAction::LeftMouseUp { .. } => "left_mouse_up",
+            ComputerAction::Scroll { .. } => "scroll",
+            ComputerAction::Type { .. } => "type",
+            ComputerAction::Key { .. } => "key",
+            ComputerAction::HoldKey { .. } => "hold_key",
+            ComputerAction::Wait { .. } => "wait",
+        }
+    }
+
+    /// Coordinate the action targets (the start of a drag)
+    /// None for keyboard actions and for pointer actions at the cursor position.
+    pub fn coordinate(&self) -> Option<ToolCoordinate> {
+        match self {
+            ComputerAction::MouseMove { coordinate } => Some(*coordinate),
+            ComputerAction::LeftClickDrag {
+                start_coordinate, ..
+            } => Some(*start_coordinate),
+            ComputerAction::LeftClick { coordinate, .. }
+            | ComputerAction::RightClick { coordinate }
+            | ComputerAction::MiddleClick { coordinate }
+            | ComputerAction::DoubleClick { coordinate }
+            | ComputerAction::TripleClick { coordinate }
+            | ComputerAction::LeftMouseDown { coordinate }
+            | ComputerAction::LeftMouseUp { coordinate }
+            | ComputerAction::Scroll { coordinate, .. } => *coordinate,
+            _ => None,
+        }
+    }
+
+    /// Whether the action acts on a screen position (its coordinate or the cursor)
+    pub fn uses_pointer(&self) -> bool {
+        !matches!(
+            self,
+            ComputerAction::Screenshot
+                | ComputerAction::CursorPosition
+                | ComputerAction::Type { .. }
+                | ComputerAction::Key { .. }
+                | ComputerAction::HoldKey { .. }
+                | ComputerAction::Wait { .. }
+        )
+    }
+}
+
+/// Convert a tool coordinate (resized screenshot) to logical screen points
+pub fn to_screen_point(coordinate: ToolCoordinate, capture: &CaptureResult) -> (i32, i32) {
+    let point = coords::translate(
+        coordinate[0] as f64,
+        coordinate[1] as f64,
+        CoordinateSpace::Screenshot,
+        CoordinateSpace::Logical,
+        &CoordinateContext::from(capture),
+    );
+    (point.x.round() as i32, point.y.round() as i32)
+}
+
+/// Convert logical screen points to a tool coordinate (resized screenshot)
+pub fn to_tool_coordinate(x: i32, y: i32, capture: &CaptureResult) -> ToolCoordinate {
+    let point = coords::translate(
+        x as f64,
+        y as f64,
+        CoordinateSpace::Logical,
+        CoordinateSpace::Screenshot,
+        &CoordinateContext::from(capture),
+    );
+    [point.x.round() as i32, point.y.round() as i32]
+}
+
+/// Resolve an optional coordinate, falling back to the current cursor position
+fn resolve_point(
+    coordinate: Option<ToolCoordinate>,
+    capture: &CaptureResult,
+) -> Result<(i32, i32), XenotesterError> {
+    match coordinate {
+        Some(c) => Ok(to_screen_point(c, capture)),
+        None => mouse::get_position(),
+    }
+}
+
+/// Left-click with modifier keys (e.g. "shift", "ctrl+alt") held
+/// The modifiers that were pressed are always released, even if a press or the click failed.
+fn click_with_modifiers(
+    x: i32,
+    y: i32,
+    modifiers: &str,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let keys: Vec<&str> = modifiers.split('+').map(str::trim).collect();
+    let mut pressed = 0;
+    let result = (|| {
+        for key in &keys {
+            keyboard::hold_key(key, true, cancel)?;
+            pressed += 1;
+        }
+        mouse::click(x, y, MouseButton::Left, cancel)
+    })();
+
+    let mut released = Ok(());
+    for key in keys[..pressed].iter().rev() {
+        released = released.and(keyboard::hold_key(key, false, cancel));
+    }
+    result.and(released)
+}
+
+/// Execute a computer action (blocking)
+///
+/// `capture` is the screenshot the LLM based its coordinates on; `cancel` is the run's token.
+/// Returns optional text output for the tool result (e.g., cursor position).
+pub fn execute_action(
+    action: &ComputerAction,
+    capture: &CaptureResult,
+    cancel: &CancellationToken,
+) -> Result<Option<String>, XenotesterError> {
+    cancel.check()?;
+
+    match action {
+        ComputerAction::Screenshot => {}
+        ComputerAction::CursorPosition => {
+            let (x, y) = mouse::get_position()?;
+            let [tx, ty] = to_tool_coordinate(x, y, capture);
+            return Ok(Some(format!("X={},Y={}", tx, ty)));
+        }
+        ComputerAction::MouseMove { coordinate } => {
+            let (x, y) = to_screen_point(*coordinate, capture);
+            mouse::move_mouse(x, y, cancel)?;
+        }
+        ComputerAction::LeftClick { coordinate, text } => {
+            let (x, y) = resolve_point(*coordinate, capture)?;
+            match text {
+                Some(modifiers) if !modifiers.trim().is_empty() => {
+                    click_with_modifiers(x, y, modifiers, cancel)?
+                }
+                _ => mouse::click(x, y, MouseButton::Left, cancel)?,
+            }
+        }
+        ComputerAction::RightClick { coordinate } => {
+            let (x, y) = resolve_point(*coordinate, capture)?;
+            mouse::click(x, y, MouseButton::Right, cancel)?;
+        }
+        ComputerAction::MiddleClick { coordinate } => {
+            let (x, y) = resolve_point(*coordinate, capture)?;
+            mouse::click(x, y, MouseButton::Middle, cancel)?;
+        }
+        ComputerAction::DoubleClick { coordinate } => {
+            let (x, y) = resolve_point(*coordinate, capture)?;
+            mouse::double_click(x, y, cancel)?;
+        }
+        ComputerAction::TripleClick { coordinate } => {
+            let (x, y) = resolve_point(*coordinate, capture)?;
+            mouse::triple_click(x, y, cancel)?;
+        }
+        ComputerAction::LeftClickDrag {
+            start_coordinate,
+            coordinate,
+        } => {
+            let (start_x, start_y) = to_screen_point(*start_coordinate, capture);
+            let (end_x, end_y) = to_screen_point(*coordinate, capture);
+            mouse::drag(start_x, start_y, end_x, end_y, cancel)?;
+        }
+        ComputerAction::LeftMouseDown { coordinate } => {
+            let (x, y) = resolve_point(*coordinate, capture)?;
+            mouse::mouse_down(x, y, MouseButton::Left, cancel)?;
+        }
+        ComputerAction::LeftMouseUp { coordinate } => {
+            let (x, y) = resolve_point(*coordinate, capture)?;
+            mouse::mouse_up(x, y, MouseButton::Left, cancel)?;
+        }
+        ComputerAction::Scroll {
+            coordinate,
+            scroll_direction,
+            scroll_amount,
+        } => {
+            let (x, y) = resolve_point(*coordinate, capture)?;
+            let direction = match scroll_direction.to_lowercase().as_str() {
+                "up" => ScrollDirection::Up,
+                "down" => ScrollDirection::Down,
+                "left" => ScrollDirection::Left,
+                "right" => ScrollDirection::Right,
+                _ => {
+                    return Err(XenotesterError::InputError(format!(
+                        "Invalid scroll direction: {}",
+                        scroll_direction
+                    )))
+                }
+            };
+            mouse::scroll(x, y, direction, scroll_amount.unwrap_or(3), cancel)?;
+        }
+        ComputerAction::Type { text } => keyboard::type_text(text, &TypingOptions::default(), cancel)?,
+        ComputerAction::Key { text } => keyboard::key_combination(text, None, cancel)?,
+        ComputerAction::HoldKey { text, duration } => {
+            keyboard::hold_key(text, true, cancel)?;
+            let result = cancel.sleep(Duration::from_secs_f64(duration.max(0.0)));
+            keyboard::hold_key(text, false, cancel)?;
+            result?;
+        }
+        ComputerAction::Wait { duration } => {
+            cancel.sleep(Duration::from_secs_f64(duration.max(0.0)))?;
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: serde_json::Value) -> ComputerAction {
+        serde_json::from_value(input).unwrap()
+    }
+
+    fn capture() -> CaptureResult {
+        CaptureResult {
+            original_width: 2560,
+            original_height: 1600,
+            resized_width: 1280,
+            resized_height: 800,
+            scale_factor: 2.0,
+            image_base64: String::new(),
+            media_type: "image/png".to_string(),
+            monitor_id: 1,
+            monitor_x: 0,
+            monitor_y: 0,
+            display_scale_factor: 2.0,
+            active_window: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_actions() {
+        let click = parse(serde_json::json!({
+            "action": "left_click", "coordinate": [10, 20], "text": "shift+ctrl"
+        }));
+        assert!(matches!(
+            &click,
+            ComputerAction::LeftClick { coordinate: Some([10, 20]), text: Some(text) }
+                if text == "shift+ctrl"
+        ));
+        assert_eq!(click.name(), "left_click");
+        assert_eq!(click.coordinate(), Some([10, 20]));
+        assert!(click.uses_pointer());
+
+        let drag = parse(serde_json::json!({
+            "action": "left_click_drag", "start_coordinate": [1, 2], "coordinate": [3, 4]
+        }));
+        assert_eq!(drag.name(), "left_click_drag");
+        assert_eq!(drag.coordinate(), Some([1, 2]));
+
+        // Pointer actions without a coordinate act at the cursor
+        let scroll = parse(serde_json::json!({ "action": "scroll", "scroll_direction": "down" }));
+        assert_eq!(scroll.coordinate(), None);
+        assert!(scroll.uses_pointer());
+
+        let key = parse(serde_json::json!({ "action": "key", "text": "ctrl+s" }));
+        assert_eq!(key.name(), "key");
+        assert!(!key.uses_pointer());
+
+        let hold =
+            parse(serde_json::json!({ "action": "hold_key", "text": "shift", "duration": 0.5 }));
+        assert_eq!(hold.name(), "hold_key");
+
+        assert!(serde_json::from_value::<ComputerAction>(serde_json::json!({
+            "action": "zoom_in"
+        }))
+        .is_err());
+        assert!(serde_json::from_value::<ComputerAction>(serde_json::json!({
+            "action": "mouse_move"
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_coordinate_round_trip() {
+        let capture = capture();
+        let point = to_screen_point([640, 400], &capture);
+        assert_eq!(to_tool_coordinate(point.0, point.1, &capture), [640, 400]);
+    }
+
+    #[test]
+    fn test_execute_without_input() {
+        let (capture, cancel) = (capture(), CancellationToken::new());
+        let run = |action: serde_json::Value| execute_action(&parse(action), &capture, &cancel);
+
+        assert_eq!(
+            run(serde_json::json!({ "action": "screenshot" })).unwrap(),
+            None
+        );
+        assert_eq!(
+            run(serde_json::json!({ "action": "wait", "duration": 0.0 })).unwrap(),
+            None
+        );
+        let error = run(serde_json::json!({
+            "action": "scroll", "coordinate": [1, 1], "scroll_direction": "sideways"
+        }))
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid scroll direction: sideways"));
+
+        cancel.cancel();
+        assert!(matches!(
+            run(serde_json::json!({ "action": "left_click", "coordinate": [1, 1] })),
+            Err(XenotesterError::Cancelled)
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+//! Human confirmation of dangerous input actions
+//!
+//! Actions matching the configured rules (key combinations such as "cmd+q",
+//! pointer actions inside a danger zone) are held back: a `ConfirmationRequest`
+//! is sent to the notifier (the `confirmation-required` event in the app) and
+//! the action runs only after it is approved with `respond`. Declined actions
+//! fail with an input error; a cancelled run stops waiting.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::error::XenotesterError;
+use crate::services::governor::{presses, ScreenRect};
+use crate::services::keyboard::canonical_key_name;
+use crate::utils::cancel::CancellationToken;
+
+/// Actions that need approval; nothing does by default
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConfirmationRules {
+    /// Key combinations such as "cmd+q"; a combination also matches when
+    /// more keys are pressed with it (e.g. "cmd+shift+q")
+    pub shortcuts: Vec<String>,
+    /// Pointer actions inside these regions (screen coordinates)
+    pub danger_zones: Vec<ScreenRect>,
+}
+
+impl ConfirmationRules {
+    /// Why an action needs approval, or None if it can run right away
+    pub fn reason(
+        &self,
+        action: &str,
+        position: Option<(i32, i32)>,
+        keys: Option<&str>,
+    ) -> Option<String> {
+        if let Some(keys) = keys {
+            let pressed = key_set(keys);
+            if let Some(shortcut) = self
+                .shortcuts
+                .iter()
+                .find(|shortcut| key_set(shortcut).is_subset(&pressed))
+            {
+                return Some(format!(
+                    "\"{}\" matches the shortcut \"{}\"",
+                    keys, shortcut
+                ));
+            }
+        }
+        match position {
+            Some((x, y)) if presses(action) => self
+                .danger_zones
+                .iter()
+                .find(|zone| zone.contains(x, y))
+                .map(|zone| format!("({}, {}) is in danger zone {:?}", x, y, zone)),
+            _ => None,
+        }
+    }
+}
+
+/// Canonical key names of a combination like "Command+Shift+Q"
+fn key_set(combination: &str) -> BTreeSet<String> {
+    combination
+        .split('+')
+        .filter(|key| !key.trim().is_empty())
+        .map(canonical_key_name)
+        .collect()
+}
+
+/// Payload of the `confirmation-required` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmationRequest {
+    /// Pass to `confirm_action` to approve or decline
+    pub action_id: String,
+    pub action: String,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub keys: Option<String>,
+    /// Rule the action matched
+    pub reason: String,
+}
+
+/// Announces a pending confirmation to whoever can answer it
+pub type Notifier = Box<dyn Fn(&ConfirmationRequest) + Send + Sync>;
+
+/// Holds back actions matching the rules until they are approved
+pub struct ConfirmationGate {
+    rules: Mutex<ConfirmationRules>,
+    /// Answer channels of the actions waiting for approval, keyed by action ID
+    pending: Mutex<HashMap<String, oneshot::Sender<bool>>>,
+    notifier: Mutex<Option<Notifier>>,
+}
+
+impl ConfirmationGate {
+    pub fn new() -> Self {
+        Self {
+            rules: Mutex::new(ConfirmationRules::default()),
+            pending: Mutex::new(HashMap::new()),
+            notifier: Mutex::new(None),
+        }
+    }
+
+    pub fn rules(&self) -> ConfirmationRules {
+        self.rules.lock().unwrap().clone()
+    }
+
+    pub fn set_rules(&self, rules: ConfirmationRules) {
+        *self.rules.lock().unwrap() = rules;
+    }
+
+    /// Set where confirmation requests are sent
+    /// Without a notifier, actions that need approval are declined.
+    pub fn set_notifier(&self, notifier: Notifier) {
+        *self.notifier.lock().unwrap() = Some(notifier);
+    }
+
+    /// Wait until the action is approved, if the rules require approval
+    pub async fn confirm(
+        &self,
+        action: &str,
+        position: Option<(i32, i32)>,
+        keys: Option<&str>,
+        cancel: &CancellationToken,
+    ) -> Result<(), XenotesterError> {
+        let Some(reason) = self.rules.lock().unwrap().reason(action, position, keys) else {
+            return Ok(());
+        };
+
+        let request = ConfirmationRequest {
+            action_id: Uuid::new_v4().to_string(),
+            action: action.to_string(),
+            x: position.map(|(x, _)| x),
+            y: position.map(|(_, y)| y),
+            keys: keys.map(str::to_string),
+            reason,
+        };
+        let (answer_tx, answer_rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(request.action_id.clone(), answer_tx);
+
+        let notified = match &*self.notifier.lock().unwrap() {
+            Some(notify) => {
+                notify(&request);
+                true
+            }
+            None => false,
+        };
+        let answer = if notified {
+            cancel.run_until_cancelled(answer_rx).await
+        } else {
+            Ok(Ok(false))
+        };
+        self.pending.lock().unwrap().remove(&request.action_id);
+
+        match answer? {
+            Ok(true) => Ok(()),
+            _ => Err(XenotesterError::Blocked(format!(
+                "{} was not approved: {}",
+                action, request.reason
+            ))),
+        }
+    }
+
+    /// Approve or decline a waiting action
+    /// Returns false if no action with this ID is waiting.
+    pub fn respond(&self, action_id: &str, approved: bool) -> bool {
+        let answer_tx = self.pending.lock().unwrap().remove(action_id);
+        answer_tx.is_some_and(|tx| tx.send(approved).is_ok())
+    }
+}
+
+impl Default for ConfirmationGate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn rules() -> ConfirmationRules {
+        ConfirmationRules {
+            shortcuts: vec!["cmd+q".to_string()],
+            danger_zones: vec![ScreenRect {
+                x: 0,
+                y: 0,
+                width: 100,
+                height: 100,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_rules_match_shortcuts_and_danger_zones() {
+        let rules = rules();
+        assert!(rules.reason("key", None, Some("Command+Q")).is_some());
+        assert!(rules.reason("key", None, Some("cmd+shift+q")).is_some());
+        assert!(rules.reason("key", None, Some("cmd+w")).is_none());
+        assert!(rules.reason("left_click", Some((50, 50)), None).is_some());
+        assert!(rules.reason("mouse_move", Some((50, 50)), None).is_none());
+        assert!(rules.reason("left_click", Some((150, 50)), None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_confirm_waits_for_answer() {
+        let gate = Arc::new(ConfirmationGate::new());
+        gate.set_rules(rules());
+        let (request_tx, request_rx) = std::sync::mpsc::channel();
+        let request_tx = Mutex::new(request_tx);
+        gate.set_notifier(Box::new(move |request| {
+            request_tx
+                .lock()
+                .unwrap()
+                .send(request.action_id.clone())
+                .unwrap();
+        }));
+
+        let responder = gate.clone();
+        let answer = std::thread::spawn(move || {
+            let action_id = request_rx.recv().unwrap();
+            assert!(responder.respond(&action_id, false));
+        });
+        let cancel = CancellationToken::new();
+        let declined = gate.confirm("key", None, Some("cmd+q"), &cancel).await;
+        answer.join().unwrap();
+
+        assert!(declined.is_err());
+        assert!(gate
+            .confirm("key", None, Some("cmd+c"), &cancel)
+            .await
+            .is_ok());
+        assert!(!gate.respond("unknown", true));
+    }
+}
@@ -0,0 +1,145 @@
+//! Coordinate transformation between screenshot, physical and logical space
+//!
+//! - `Screenshot`: pixels in the resized capture sent to the LLM, relative to the monitor
+//! - `Physical`: pixels in the original capture, relative to the monitor
+//! - `Logical`: absolute desktop points used by the input APIs (includes the monitor offset)
+
+use serde::{Deserialize, Serialize};
+
+use crate::services::capture::CaptureResult;
+
+/// Coordinate space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoordinateSpace {
+    Screenshot,
+    Physical,
+    Logical,
+}
+
+/// Capture parameters needed to convert between spaces
+/// Field names match `CaptureResult` (and `VirtualDesktopCapture` via aliases),
+/// so a capture can be passed as-is from the frontend
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoordinateContext {
+    /// Resized / original (from the capture)
+    pub scale_factor: f64,
+    /// Physical pixels per logical point (2.0 for Retina)
+    #[serde(default = "default_display_scale_factor")]
+    pub display_scale_factor: f64,
+    /// Monitor origin on the desktop, in logical points
+    #[serde(default, alias = "originX")]
+    pub monitor_x: i32,
+    #[serde(default, alias = "originY")]
+    pub monitor_y: i32,
+}
+
+fn default_display_scale_factor() -> f64 {
+    1.0
+}
+
+impl From<&CaptureResult> for CoordinateContext {
+    fn from(capture: &CaptureResult) -> Self {
+        Self {
+            scale_factor: capture.scale_factor,
+            display_scale_factor: capture.display_scale_factor,
+            monitor_x: capture.monitor_x,
+            monitor_y: capture.monitor_y,
+        }
+    }
+}
+
+/// A point in some coordinate space
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TranslatedPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Convert a point from one coordinate space to another
+pub fn translate(
+    x: f64,
+    y: f64,
+    from: CoordinateSpace,
+    to: CoordinateSpace,
+    context: &CoordinateContext,
+) -> TranslatedPoint {
+    let (px, py) = to_physical(x, y, from, context);
+    let (x, y) = from_physical(px, py, to, context);
+    TranslatedPoint { x, y }
+}
+
+/// Convert a point into monitor-relative physical pixels
+fn to_physical(x: f64, y: f64, from: CoordinateSpace, context: &CoordinateContext) -> (f64, f64) {
+    match from {
+        CoordinateSpace::Screenshot => (x / context.scale_factor, y / context.scale_factor),
+        CoordinateSpace::Physical => (x, y),
+        CoordinateSpace::Logical => (
+            (x - context.monitor_x as f64) * context.display_scale_factor,
+            (y - context.monitor_y as f64) * context.display_scale_factor,
+        ),
+    }
+}
+
+/// Convert monitor-relative physical pixels into the target space
+fn from_physical(x: f64, y: f64, to: CoordinateSpace, context: &CoordinateContext) -> (f64, f64) {
+    match to {
+        CoordinateSpace::Screenshot => (x * context.scale_factor, y * context.scale_factor),
+        CoordinateSpace::Physical => (x, y),
+        CoordinateSpace::Logical => (
+            x / context.display_scale_factor + context.monitor_x as f64,
+            y / context.display_scale_factor + context.monitor_y as f64,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retina_secondary() -> CoordinateContext {
+        CoordinateContext {
+            scale_factor: 0.5,
+            display_scale_factor: 2.0,
+            monitor_x: 1440,
+            monitor_y: -200,
+        }
+    }
+
+    #[test]
+    fn test_screenshot_to_logical_includes_monitor_offset() {
+        let point = translate(
+            400.0,
+            300.0,
+            CoordinateSpace::Screenshot,
+            CoordinateSpace::Logical,
+            &retina_secondary(),
+        );
+
+        // 400 / 0.5 = 800 physical -> 400 points -> + 1440 offset
+        assert_eq!(point, TranslatedPoint { x: 1840.0, y: 100.0 });
+    }
+
+    #[test]
+    fn test_round_trip_is_identity() {
+        let context = retina_secondary();
+        let logical = translate(
+            123.0,
+            456.0,
+            CoordinateSpace::Screenshot,
+            CoordinateSpace::Logical,
+            &context,
+        );
+        let back = translate(
+            logical.x,
+            logical.y,
+            CoordinateSpace::Logical,
+            CoordinateSpace::Screenshot,
+            &context,
+        );
+
+        assert!((back.x - 123.0).abs() < 1e-9);
+        assert!((back.y - 456.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,59 @@
+//! Database access service
+//!
+//! Reuses the connection pool owned by tauri-plugin-sql so that backend
+//! queries hit the same `xenotester.db` (and migrations) as the frontend.
+//! The headless runner opens the same file directly with `open_pool`.
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_sql::{DbInstances, DbPool};
+
+use crate::error::XenotesterError;
+
+/// Connection URL of the application database (preloaded in tauri.conf.json)
+pub const DB_URL: &str = "sqlite:xenotester.db";
+
+/// Bundle identifier from tauri.conf.json; names the app's config directory
+const APP_IDENTIFIER: &str = "com.satoshizerocolored.xenotester";
+
+/// File name of the application database
+const DB_FILE_NAME: &str = "xenotester.db";
+
+/// Get the SQLite pool for the application database
+pub async fn get_pool<R: Runtime>(app: &AppHandle<R>) -> Result<SqlitePool, XenotesterError> {
+    let instances = app
+        .try_state::<DbInstances>()
+        .ok_or_else(|| XenotesterError::DatabaseError("SQL plugin is not initialized".to_string()))?;
+    let instances = instances.0.read().await;
+
+    match instances.get(DB_URL) {
+        Some(DbPool::Sqlite(pool)) => Ok(pool.clone()),
+        _ => Err(XenotesterError::DatabaseError(format!(
+            "Database {} is not loaded",
+            DB_URL
+        ))),
+    }
+}
+
+/// Location of the application database outside of Tauri
+/// The SQL plugin keeps it in the app config directory.
+pub fn default_db_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_IDENTIFIER).join(DB_FILE_NAME))
+}
+
+/// Open an existing application database (created and migrated by the app)
+pub async fn open_pool(path: &Path) -> Result<SqlitePool, XenotesterError> {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(false)
+        .foreign_keys(true);
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .map_err(|e| {
+            XenotesterError::DatabaseError(format!("Failed to open {}: {}", path.display(), e))
+        })
+}
@@ -0,0 +1,219 @@
+//! Datasets for data-driven runs
+//!
+//! A dataset is a CSV file with a header row or a JSON array of objects. Each
+//! row maps column names to values for `${NAME}` placeholders, and the
+//! scenario is run once per row.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::XenotesterError;
+
+/// Upper bound for the number of rows, to catch a wrong file early
+const MAX_ROWS: usize = 10_000;
+
+/// Column values of one row, in column order
+pub type DatasetRow = Vec<(String, String)>;
+
+/// Variables of a row
+pub fn row_variables(row: &DatasetRow) -> HashMap<String, String> {
+    row.iter().cloned().collect()
+}
+
+/// Short label identifying a row in results ("Row 3 (user=alice)")
+/// Only the first column is shown, later ones may hold passwords.
+pub fn row_label(index: usize, row: &DatasetRow) -> String {
+    match row.first() {
+        Some((name, value)) => format!("Row {} ({}={})", index + 1, name, value),
+        None => format!("Row {}", index + 1),
+    }
+}
+
+fn invalid(message: String) -> XenotesterError {
+    XenotesterError::ConfigError(message)
+}
+
+/// Load a `.csv` or `.json` dataset
+pub fn load_dataset(path: &Path) -> Result<Vec<DatasetRow>, XenotesterError> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| invalid(format!("Failed to read {}: {}", path.display(), e)))?;
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let rows = match extension.as_str() {
+        "csv" => parse_csv(&text)?,
+        "json" => parse_json(&text)?,
+        _ => {
+            return Err(invalid(format!(
+                "Unsupported dataset format: {} (use .csv or .json)",
+                path.display()
+            )))
+        }
+    };
+
+    if rows.is_empty() {
+        return Err(invalid(format!("Dataset {} has no rows", path.display())));
+    }
+    if rows.len() > MAX_ROWS {
+        return Err(invalid(format!(
+            "Dataset {} has {} rows, the limit is {}",
+            path.display(),
+            rows.len(),
+            MAX_ROWS
+        )));
+    }
+    Ok(rows)
+}
+
+/// Split CSV text into records (RFC 4180: quoted fields may contain commas,
+/// doubled quotes and line breaks)
+fn csv_records(text: &str) -> Result<Vec<Vec<String>>, XenotesterError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(invalid("Unterminated quoted field in CSV".to_string()));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    // Blank lines are not rows
+    records.retain(|r| !(r.len() == 1 && r[0].trim().is_empty()));
+    Ok(records)
+}
+
+/// Parse CSV with a header row
+pub fn parse_csv(text: &str) -> Result<Vec<DatasetRow>, XenotesterError> {
+    let mut records = csv_records(text)?.into_iter();
+    let header: Vec<String> = match records.next() {
+        Some(header) => header.into_iter().map(|h| h.trim().to_string()).collect(),
+        None => return Ok(Vec::new()),
+    };
+    if header.iter().any(String::is_empty) {
+        return Err(invalid("CSV header has an empty column name".to_string()));
+    }
+
+    records
+        .enumerate()
+        .map(|(i, record)| {
+            if record.len() != header.len() {
+                return Err(invalid(format!(
+                    "CSV row {} has {} fields, the header has {}",
+                    i + 1,
+                    record.len(),
+                    header.len()
+                )));
+            }
+            Ok(header.iter().cloned().zip(record).collect())
+        })
+        .collect()
+}
+
+/// Parse a JSON array of objects; non-string values are used in their JSON form
+pub fn parse_json(text: &str) -> Result<Vec<DatasetRow>, XenotesterError> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| invalid(format!("Invalid JSON dataset: {}", e)))?;
+    let items = match value {
+        Value::Array(items) => items,
+        _ => {
+            return Err(invalid(
+                "JSON dataset must be an array of objects".to_string(),
+            ))
+        }
+    };
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| match item {
+            Value::Object(map) => Ok(map
+                .into_iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        Value::String(s) => s,
+                        Value::Null => String::new(),
+                        other => other.to_string(),
+                    };
+                    (name, value)
+                })
+                .collect()),
+            _ => Err(invalid(format!(
+                "JSON dataset item {} is not an object",
+                i + 1
+            ))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(row: &DatasetRow) -> Vec<(&str, &str)> {
+        row.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect()
+    }
+
+    #[test]
+    fn test_parse_csv_quotes_and_line_endings() {
+        let rows = parse_csv(
+            "user,password,note\r\nalice,\"p,w\"\"1\",plain\r\n\r\nbob,x,\"two\nlines\"\n",
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            pairs(&rows[0]),
+            vec![("user", "alice"), ("password", "p,w\"1"), ("note", "plain")]
+        );
+        assert_eq!(pairs(&rows[1])[2], ("note", "two\nlines"));
+        assert_eq!(row_label(1, &rows[1]), "Row 2 (user=bob)");
+
+        assert!(parse_csv("a,b\n1\n").is_err());
+        assert!(parse_csv("a,b\n\"1,2\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_json_values() {
+        let rows =
+            parse_json(r#"[{"user": "alice", "age": 30, "admin": true, "x": null}]"#).unwrap();
+        let variables = row_variables(&rows[0]);
+        assert_eq!(variables["user"], "alice");
+        assert_eq!(variables["age"], "30");
+        assert_eq!(variables["admin"], "true");
+        assert_eq!(variables["x"], "");
+        assert!(parse_json(r#"{"user": "alice"}"#).is_err());
+        assert!(parse_json(r#"["alice"]"#).is_err());
+    }
+}
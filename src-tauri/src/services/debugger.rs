@@ -0,0 +1,233 @@
+//! Breakpoints and step-through debugging of scripted runs
+//!
+//! A run pauses before a step that has a breakpoint (set per scenario and step
+//! index) and, after `step_over`, before the step that follows. The pause is
+//! announced to the notifier (the `breakpoint-hit` event in the app) as a
+//! `BreakpointHit` with a screenshot of the screen, and the run waits until it
+//! is resumed with `step_over` or `continue_run`. A cancelled run stops
+//! waiting; without a notifier, runs never pause.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+use crate::error::XenotesterError;
+use crate::services::capture::CaptureResult;
+use crate::utils::cancel::CancellationToken;
+
+/// How a paused run continues
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resume {
+    /// Run the step, then pause before the next one
+    StepOver,
+    /// Run until the next breakpoint
+    Continue,
+}
+
+/// Payload of the `breakpoint-hit` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BreakpointHit {
+    /// Pass to `step_over` or `continue_run` to resume
+    pub pause_id: String,
+    /// Run history ID when the run is recorded
+    pub run_id: Option<String>,
+    pub scenario_id: String,
+    pub scenario_title: String,
+    /// Step about to run (0-based)
+    pub step_index: usize,
+    pub step: String,
+    /// Screen as the run left it, if it could be captured
+    pub screenshot: Option<CaptureResult>,
+}
+
+/// Announces a paused run to whoever can resume it
+pub type Notifier = Box<dyn Fn(&BreakpointHit) + Send + Sync>;
+
+/// Breakpoints of the stored scenarios and the runs paused at them
+pub struct Debugger {
+    /// Step indexes keyed by scenario ID
+    breakpoints: Mutex<HashMap<String, BTreeSet<usize>>>,
+    /// Resume channels of the paused runs, keyed by pause ID
+    paused: Mutex<HashMap<String, oneshot::Sender<Resume>>>,
+    notifier: Mutex<Option<Notifier>>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: Mutex::new(HashMap::new()),
+            paused: Mutex::new(HashMap::new()),
+            notifier: Mutex::new(None),
+        }
+    }
+
+    /// Pause runs of a scenario before its step `step_index` (0-based)
+    pub fn set_breakpoint(&self, scenario_id: &str, step_index: usize) {
+        self.breakpoints
+            .lock()
+            .unwrap()
+            .entry(scenario_id.to_string())
+            .or_default()
+            .insert(step_index);
+    }
+
+    /// Remove a breakpoint; returns false if there was none
+    pub fn clear_breakpoint(&self, scenario_id: &str, step_index: usize) -> bool {
+        let mut breakpoints = self.breakpoints.lock().unwrap();
+        let Some(steps) = breakpoints.get_mut(scenario_id) else {
+            return false;
+        };
+        let removed = steps.remove(&step_index);
+        if steps.is_empty() {
+            breakpoints.remove(scenario_id);
+        }
+        removed
+    }
+
+    /// Step indexes with a breakpoint in a scenario, in order
+    pub fn breakpoints(&self, scenario_id: &str) -> Vec<usize> {
+        self.breakpoints
+            .lock()
+            .unwrap()
+            .get(scenario_id)
+            .map(|steps| steps.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Set where paused runs are announced
+    pub fn set_notifier(&self, notifier: Notifier) {
+        *self.notifier.lock().unwrap() = Some(notifier);
+    }
+
+    /// Whether a run should pause before a step; `stepping` is true after `step_over`
+    pub fn should_pause(&self, scenario_id: &str, step_index: usize, stepping: bool) -> bool {
+        if self.notifier.lock().unwrap().is_none() {
+            return false;
+        }
+        stepping
+            || self
+                .breakpoints
+                .lock()
+                .unwrap()
+                .get(scenario_id)
+                .is_some_and(|steps| steps.contains(&step_index))
+    }
+
+    /// Announce the pause and wait until the run is resumed
+    pub async fn pause(
+        &self,
+        hit: BreakpointHit,
+        cancel: &CancellationToken,
+    ) -> Result<Resume, XenotesterError> {
+        let (resume_tx, resume_rx) = oneshot::channel();
+        self.paused
+            .lock()
+            .unwrap()
+            .insert(hit.pause_id.clone(), resume_tx);
+
+        let notified = match &*self.notifier.lock().unwrap() {
+            Some(notify) => {
+                notify(&hit);
+                true
+            }
+            None => false,
+        };
+        let resume = if notified {
+            cancel.run_until_cancelled(resume_rx).await
+        } else {
+            Ok(Ok(Resume::Continue))
+        };
+        self.paused.lock().unwrap().remove(&hit.pause_id);
+
+        // A dropped channel cannot happen while the entry is held; continue if it does
+        Ok(resume?.unwrap_or(Resume::Continue))
+    }
+
+    /// Resume a paused run
+    /// Returns false if no run with this pause ID is waiting.
+    pub fn resume(&self, pause_id: &str, resume: Resume) -> bool {
+        let resume_tx = self.paused.lock().unwrap().remove(pause_id);
+        resume_tx.is_some_and(|tx| tx.send(resume).is_ok())
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn hit(pause_id: &str) -> BreakpointHit {
+        BreakpointHit {
+            pause_id: pause_id.to_string(),
+            run_id: None,
+            scenario_id: "s1".to_string(),
+            scenario_title: "Login".to_string(),
+            step_index: 2,
+            step: "Wait 100 ms".to_string(),
+            screenshot: None,
+        }
+    }
+
+    #[test]
+    fn test_breakpoints_need_a_notifier() {
+        let debugger = Debugger::new();
+        debugger.set_breakpoint("s1", 2);
+        debugger.set_breakpoint("s1", 0);
+        assert_eq!(debugger.breakpoints("s1"), vec![0, 2]);
+        assert!(!debugger.should_pause("s1", 2, false));
+
+        debugger.set_notifier(Box::new(|_| {}));
+        assert!(debugger.should_pause("s1", 2, false));
+        assert!(!debugger.should_pause("s1", 1, false));
+        assert!(debugger.should_pause("s1", 1, true));
+
+        assert!(debugger.clear_breakpoint("s1", 2));
+        assert!(!debugger.clear_breakpoint("s1", 2));
+        assert!(!debugger.should_pause("s1", 2, false));
+    }
+
+    #[tokio::test]
+    async fn test_pause_waits_for_resume() {
+        let debugger = Arc::new(Debugger::new());
+        let (hit_tx, hit_rx) = std::sync::mpsc::channel();
+        let hit_tx = Mutex::new(hit_tx);
+        debugger.set_notifier(Box::new(move |hit| {
+            hit_tx.lock().unwrap().send(hit.pause_id.clone()).unwrap();
+        }));
+
+        let resumer = debugger.clone();
+        let answer = std::thread::spawn(move || {
+            let pause_id = hit_rx.recv().unwrap();
+            assert!(resumer.resume(&pause_id, Resume::StepOver));
+        });
+        let cancel = CancellationToken::new();
+        let resume = debugger.pause(hit("p1"), &cancel).await.unwrap();
+        answer.join().unwrap();
+
+        assert_eq!(resume, Resume::StepOver);
+        assert!(!debugger.resume("p1", Resume::Continue));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_waiting() {
+        let debugger = Debugger::new();
+        debugger.set_notifier(Box::new(|_| {}));
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        assert!(matches!(
+            debugger.pause(hit("p2"), &cancel).await,
+            Err(XenotesterError::Cancelled)
+        ));
+        assert!(!debugger.resume("p2", Resume::Continue));
+    }
+}
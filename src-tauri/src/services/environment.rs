@@ -0,0 +1,282 @@
+//! Desktop environment snapshot and restore
+//!
+//! Runs move the mouse, overwrite the clipboard, bring other apps to the front
+//! and may change the volume. `snapshot` records these before a run and
+//! `restore` puts them back afterwards. Each part is best effort: what can't be
+//! read is left out of the snapshot, and what can't be restored is reported.
+//! Only text clipboard content is kept (as with `paste_text`).
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use xcap::Window;
+
+use crate::error::XenotesterError;
+use crate::services::mouse;
+use crate::utils::cancel::CancellationToken;
+
+/// App that was in front when the snapshot was taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontmostApp {
+    pub app_name: String,
+    pub title: String,
+    pub pid: u32,
+    /// Platform window ID (HWND on Windows)
+    pub window_id: u32,
+}
+
+/// Output volume
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeState {
+    /// 0.0 (silent) to 1.0 (full)
+    pub level: f64,
+    pub muted: bool,
+}
+
+/// Desktop state recorded before a run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentSnapshot {
+    /// Cursor position (logical points)
+    pub mouse_position: Option<(i32, i32)>,
+    pub clipboard_text: Option<String>,
+    pub frontmost_app: Option<FrontmostApp>,
+    pub volume: Option<VolumeState>,
+}
+
+/// Parts of a snapshot that could not be restored
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub failures: Vec<String>,
+}
+
+/// Record the current desktop state
+pub fn snapshot() -> EnvironmentSnapshot {
+    EnvironmentSnapshot {
+        mouse_position: part("mouse position", mouse::get_position()),
+        clipboard_text: part("clipboard text", clipboard_text()),
+        frontmost_app: part("frontmost app", frontmost_app()),
+        volume: part("volume", platform::volume()),
+    }
+}
+
+/// Part of a snapshot, or None (logged) if it couldn't be read
+fn part<T>(name: &str, result: Result<T, XenotesterError>) -> Option<T> {
+    result
+        .map_err(|e| debug!("Environment snapshot: no {}: {}", name, e))
+        .ok()
+}
+
+/// Put back what `snapshot` recorded (the mouse last, after the app is in front)
+pub fn restore(snapshot: &EnvironmentSnapshot, cancel: &CancellationToken) -> RestoreReport {
+    let mut report = RestoreReport::default();
+    let mut check = |name: &str, result: Result<(), XenotesterError>| {
+        if let Err(e) = result {
+            report.failures.push(format!("{}: {}", name, e));
+        }
+    };
+
+    if let Some(text) = &snapshot.clipboard_text {
+        check("clipboard", set_clipboard_text(text));
+    }
+    if let Some(volume) = snapshot.volume {
+        check("volume", platform::set_volume(volume));
+    }
+    if let Some(app) = &snapshot.frontmost_app {
+        check("frontmost app", platform::activate(app));
+    }
+    if let Some((x, y)) = snapshot.mouse_position {
+        check("mouse position", mouse::move_mouse(x, y, cancel));
+    }
+    report
+}
+
+fn clipboard_error(e: arboard::Error) -> XenotesterError {
+    XenotesterError::InputError(format!("Clipboard error: {}", e))
+}
+
+fn clipboard_text() -> Result<String, XenotesterError> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(clipboard_error)
+}
+
+fn set_clipboard_text(text: &str) -> Result<(), XenotesterError> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(clipboard_error)
+}
+
+fn frontmost_app() -> Result<FrontmostApp, XenotesterError> {
+    let windows = Window::all().map_err(|e| XenotesterError::CaptureError(e.to_string()))?;
+    let window = windows
+        .into_iter()
+        .find(|window| window.is_focused().unwrap_or(false))
+        .ok_or_else(|| XenotesterError::CaptureError("No focused window".to_string()))?;
+    Ok(FrontmostApp {
+        app_name: window.app_name().unwrap_or_default(),
+        title: window.title().unwrap_or_default(),
+        pid: window.pid().unwrap_or_default(),
+        window_id: window.id().unwrap_or_default(),
+    })
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{FrontmostApp, VolumeState};
+    use crate::error::XenotesterError;
+    use crate::services::process::run_tool;
+
+    fn osascript(script: &str) -> Result<String, XenotesterError> {
+        let output = run_tool("osascript", &["-e", script])?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(XenotesterError::ProcessError(format!(
+                "osascript failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+
+    pub fn volume() -> Result<VolumeState, XenotesterError> {
+        // "output volume:50, input volume:75, alert volume:100, output muted:false"
+        let settings = osascript("get volume settings")?;
+        let field = |name: &str| {
+            settings
+                .split(", ")
+                .find_map(|part| part.strip_prefix(name))
+                .map(str::to_string)
+        };
+        match (field("output volume:"), field("output muted:")) {
+            (Some(level), Some(muted)) => Ok(VolumeState {
+                level: level.parse::<f64>().unwrap_or_default() / 100.0,
+                muted: muted == "true",
+            }),
+            _ => Err(XenotesterError::ProcessError(format!(
+                "Unexpected volume settings: {}",
+                settings
+            ))),
+        }
+    }
+
+    pub fn set_volume(volume: VolumeState) -> Result<(), XenotesterError> {
+        let level = (volume.level.clamp(0.0, 1.0) * 100.0).round();
+        osascript(&format!(
+            "set volume output volume {}\nset volume output muted {}",
+            level, volume.muted
+        ))
+        .map(|_| ())
+    }
+
+    pub fn activate(app: &FrontmostApp) -> Result<(), XenotesterError> {
+        osascript(&format!(
+            "tell application \"System Events\" to set frontmost of \
+             (first process whose unix id is {}) to true",
+            app.pid
+        ))
+        .map(|_| ())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ffi::c_void;
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume;
+    use windows::Win32::Media::Audio::{
+        eConsole, eRender, IMMDeviceEnumerator, MMDeviceEnumerator,
+    };
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_ALL, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{IsWindow, SetForegroundWindow};
+
+    use super::{FrontmostApp, VolumeState};
+    use crate::error::XenotesterError;
+
+    fn audio_error(e: windows::core::Error) -> XenotesterError {
+        XenotesterError::ProcessError(format!("Audio endpoint error: {}", e))
+    }
+
+    /// Volume control of the default output device
+    fn endpoint_volume() -> Result<IAudioEndpointVolume, XenotesterError> {
+        // Already initialized on this thread is fine
+        let _ = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) };
+        unsafe {
+            let enumerator: IMMDeviceEnumerator =
+                CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_INPROC_SERVER)
+                    .map_err(audio_error)?;
+            let device = enumerator
+                .GetDefaultAudioEndpoint(eRender, eConsole)
+                .map_err(audio_error)?;
+            device.Activate(CLSCTX_ALL, None).map_err(audio_error)
+        }
+    }
+
+    pub fn volume() -> Result<VolumeState, XenotesterError> {
+        let endpoint = endpoint_volume()?;
+        unsafe {
+            Ok(VolumeState {
+                level: f64::from(endpoint.GetMasterVolumeLevelScalar().map_err(audio_error)?),
+                muted: endpoint.GetMute().map_err(audio_error)?.as_bool(),
+            })
+        }
+    }
+
+    pub fn set_volume(volume: VolumeState) -> Result<(), XenotesterError> {
+        let endpoint = endpoint_volume()?;
+        let level = volume.level.clamp(0.0, 1.0) as f32;
+        unsafe {
+            endpoint
+                .SetMasterVolumeLevelScalar(level, std::ptr::null())
+                .map_err(audio_error)?;
+            endpoint
+                .SetMute(volume.muted, std::ptr::null())
+                .map_err(audio_error)
+        }
+    }
+
+    pub fn activate(app: &FrontmostApp) -> Result<(), XenotesterError> {
+        let hwnd = HWND(app.window_id as usize as *mut c_void);
+        if !unsafe { IsWindow(Some(hwnd)) }.as_bool() {
+            return Err(XenotesterError::ProcessError(format!(
+                "Window of {} no longer exists",
+                app.app_name
+            )));
+        }
+        // Windows may refuse to change the foreground window (focus stealing prevention)
+        if unsafe { SetForegroundWindow(hwnd) }.as_bool() {
+            Ok(())
+        } else {
+            Err(XenotesterError::ProcessError(format!(
+                "Could not bring {} to the front",
+                app.app_name
+            )))
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::{FrontmostApp, VolumeState};
+    use crate::error::XenotesterError;
+
+    fn unsupported(what: &str) -> XenotesterError {
+        XenotesterError::ProcessError(format!("{} is not supported on this platform", what))
+    }
+
+    pub fn volume() -> Result<VolumeState, XenotesterError> {
+        Err(unsupported("Reading the volume"))
+    }
+
+    pub fn set_volume(_volume: VolumeState) -> Result<(), XenotesterError> {
+        Err(unsupported("Setting the volume"))
+    }
+
+    pub fn activate(_app: &FrontmostApp) -> Result<(), XenotesterError> {
+        Err(unsupported("Activating an app"))
+    }
+}
@@ -0,0 +1,208 @@
+//! Do Not Disturb during runs
+//!
+//! Notification banners appearing mid-run cover the app under test and break
+//! template matches. `enable` silences them and remembers the previous state,
+//! `restore` puts it back. Neither OS has a public Focus API:
+//! - Windows: toast banners are turned off through the
+//!   `NOC_GLOBAL_SETTING_TOASTS_ENABLED` registry value
+//! - macOS: the Shortcuts named in the config are run (create them in the
+//!   Shortcuts app with the "Set Focus" action)
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::error::XenotesterError;
+
+/// Do Not Disturb settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FocusModeConfig {
+    /// Enable on `start_run` and restore on `finish_run`
+    pub enable_during_runs: bool,
+    /// macOS Shortcut that turns Do Not Disturb on
+    pub macos_enable_shortcut: String,
+    /// macOS Shortcut that turns Do Not Disturb off
+    pub macos_disable_shortcut: String,
+}
+
+impl Default for FocusModeConfig {
+    fn default() -> Self {
+        Self {
+            enable_during_runs: false,
+            macos_enable_shortcut: "Xenotester Focus On".to_string(),
+            macos_disable_shortcut: "Xenotester Focus Off".to_string(),
+        }
+    }
+}
+
+/// Notification state to return to
+#[derive(Debug, Clone, Copy)]
+enum Previous {
+    /// Registry value before it was set to 0 (None: the value didn't exist)
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    Toasts(Option<u32>),
+    /// Do Not Disturb was turned on with the enable Shortcut
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    Shortcut,
+}
+
+/// Do Not Disturb switched on by the app, restored on request
+pub struct FocusMode {
+    config: Mutex<FocusModeConfig>,
+    previous: Mutex<Option<Previous>>,
+}
+
+impl FocusMode {
+    pub fn new() -> Self {
+        Self {
+            config: Mutex::new(FocusModeConfig::default()),
+            previous: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> FocusModeConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    pub fn set_config(&self, config: FocusModeConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    /// Silence notifications; returns false if already done
+    pub fn enable(&self) -> Result<bool, XenotesterError> {
+        let mut previous = self.previous.lock().unwrap();
+        if previous.is_some() {
+            return Ok(false);
+        }
+        *previous = Some(platform::silence(&self.config())?);
+        Ok(true)
+    }
+
+    /// Return to the state saved by `enable`; returns false if there was nothing to restore
+    pub fn restore(&self) -> Result<bool, XenotesterError> {
+        let mut previous = self.previous.lock().unwrap();
+        match previous.take() {
+            Some(state) => {
+                platform::restore(&self.config(), state)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+impl Default for FocusMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{FocusModeConfig, Previous};
+    use crate::error::XenotesterError;
+    use crate::services::process::run_tool;
+
+    const KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Notifications\Settings";
+    const VALUE: &str = "NOC_GLOBAL_SETTING_TOASTS_ENABLED";
+
+    fn query() -> Option<u32> {
+        let output = run_tool("reg", &["query", KEY, "/v", VALUE]).ok()?;
+        // "    NOC_GLOBAL_SETTING_TOASTS_ENABLED    REG_DWORD    0x1"
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find(|line| line.contains("REG_DWORD"))
+            .and_then(|line| line.split_whitespace().last())
+            .and_then(|value| u32::from_str_radix(value.trim_start_matches("0x"), 16).ok())
+    }
+
+    fn write(value: Option<u32>) -> Result<(), XenotesterError> {
+        let output = match value {
+            Some(value) => {
+                let data = value.to_string();
+                let args = [
+                    "add",
+                    KEY,
+                    "/v",
+                    VALUE,
+                    "/t",
+                    "REG_DWORD",
+                    "/d",
+                    &data,
+                    "/f",
+                ];
+                run_tool("reg", &args)?
+            }
+            None => run_tool("reg", &["delete", KEY, "/v", VALUE, "/f"])?,
+        };
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(XenotesterError::ProcessError(format!(
+                "Failed to update notification settings: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+
+    pub fn silence(_config: &FocusModeConfig) -> Result<Previous, XenotesterError> {
+        let previous = query();
+        write(Some(0))?;
+        Ok(Previous::Toasts(previous))
+    }
+
+    pub fn restore(_config: &FocusModeConfig, previous: Previous) -> Result<(), XenotesterError> {
+        match previous {
+            Previous::Toasts(value) => write(value),
+            Previous::Shortcut => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{FocusModeConfig, Previous};
+    use crate::error::XenotesterError;
+    use crate::services::process::run_tool;
+
+    fn run_shortcut(name: &str) -> Result<(), XenotesterError> {
+        let output = run_tool("shortcuts", &["run", name])?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(XenotesterError::ProcessError(format!(
+                "Shortcut \"{}\" failed (create it in the Shortcuts app): {}",
+                name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+
+    pub fn silence(config: &FocusModeConfig) -> Result<Previous, XenotesterError> {
+        run_shortcut(&config.macos_enable_shortcut)?;
+        Ok(Previous::Shortcut)
+    }
+
+    pub fn restore(config: &FocusModeConfig, previous: Previous) -> Result<(), XenotesterError> {
+        match previous {
+            Previous::Shortcut => run_shortcut(&config.macos_disable_shortcut),
+            Previous::Toasts(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::{FocusModeConfig, Previous};
+    use crate::error::XenotesterError;
+
+    pub fn silence(_config: &FocusModeConfig) -> Result<Previous, XenotesterError> {
+        Err(XenotesterError::ProcessError(
+            "Do Not Disturb is not supported on this platform".to_string(),
+        ))
+    }
+
+    pub fn restore(_config: &FocusModeConfig, _previous: Previous) -> Result<(), XenotesterError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+//! Safety governor for input actions
+//!
+//! Enforced in the backend before an input action reaches the OS, so a runaway
+//! agent loop cannot exceed it: a cap on actions per second and per run, and
+//! screen regions where pointer actions are forbidden (e.g. the menu bar or
+//! system tray). Positions are absolute screen coordinates.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::XenotesterError;
+use crate::services::confirmation::ConfirmationGate;
+use crate::services::user_activity::UserActivity;
+use crate::utils::cancel::CancellationToken;
+
+/// Window of the per-second limit
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Actions that only move the pointer or release a button, never press on a target
+const NON_PRESSING_ACTIONS: &[&str] = &["mouse_move", "mouse_move_smooth", "left_mouse_up"];
+
+/// Whether a positioned action presses on its target (position rules apply to it)
+pub fn presses(action: &str) -> bool {
+    !NON_PRESSING_ACTIONS.contains(&action)
+}
+
+/// Rectangle in screen coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScreenRect {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && ((x - self.x) as u32) < self.width
+            && ((y - self.y) as u32) < self.height
+    }
+}
+
+/// Limits enforced on input actions; everything is unrestricted by default
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GovernorConfig {
+    /// Input actions allowed within any one second
+    pub max_actions_per_second: Option<u32>,
+    /// Input actions allowed per run (see `start_run`)
+    pub max_actions_per_run: Option<u32>,
+    /// Pointer actions inside these regions are refused
+    pub denied_regions: Vec<ScreenRect>,
+    /// When not empty, pointer actions outside these regions are refused
+    pub allowed_regions: Vec<ScreenRect>,
+}
+
+impl GovernorConfig {
+    /// Refuse a pointer action at a forbidden position
+    pub fn check_position(&self, x: i32, y: i32) -> Result<(), XenotesterError> {
+        if let Some(region) = self.denied_regions.iter().find(|r| r.contains(x, y)) {
+            return Err(refused(format!(
+                "({}, {}) is in denied region {:?}",
+                x, y, region
+            )));
+        }
+        if !self.allowed_regions.is_empty()
+            && !self.allowed_regions.iter().any(|r| r.contains(x, y))
+        {
+            return Err(refused(format!(
+                "({}, {}) is outside the allowed regions",
+                x, y
+            )));
+        }
+        Ok(())
+    }
+}
+
+struct GovernorState {
+    config: GovernorConfig,
+    /// Times of the actions admitted within the last `RATE_WINDOW`
+    recent: VecDeque<Instant>,
+    /// Actions admitted per unfinished run
+    run_actions: HashMap<String, u32>,
+}
+
+/// Admits or refuses input actions according to a `GovernorConfig`
+pub struct Governor {
+    state: Mutex<GovernorState>,
+}
+
+impl Governor {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(GovernorState {
+                config: GovernorConfig::default(),
+                recent: VecDeque::new(),
+                run_actions: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> GovernorConfig {
+        self.state.lock().unwrap().config.clone()
+    }
+
+    /// Replace the limits; counts already made still apply
+    pub fn set_config(&self, config: GovernorConfig) {
+        self.state.lock().unwrap().config = config;
+    }
+
+    /// Refuse a pointer action at a forbidden position without counting it
+    pub fn check_position(&self, x: i32, y: i32) -> Result<(), XenotesterError> {
+        self.state.lock().unwrap().config.check_position(x, y)
+    }
+
+    /// Count an action against the limits, or explain why it is refused
+    pub fn admit(
+        &self,
+        action: &str,
+        position: Option<(i32, i32)>,
+        run_id: Option<&str>,
+    ) -> Result<(), XenotesterError> {
+        self.admit_at(action, position, run_id, Instant::now())
+    }
+
+    fn admit_at(
+        &self,
+        action: &str,
+        position: Option<(i32, i32)>,
+        run_id: Option<&str>,
+        now: Instant,
+    ) -> Result<(), XenotesterError> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some((x, y)) = position {
+            if presses(action) {
+                state.config.check_position(x, y)?;
+            }
+        }
+
+        while state
+            .recent
+            .front()
+            .is_some_and(|&time| now.duration_since(time) >= RATE_WINDOW)
+        {
+            state.recent.pop_front();
+        }
+        if let Some(limit) = state.config.max_actions_per_second {
+            if state.recent.len() >= limit as usize {
+                return Err(refused(format!("more than {} actions per second", limit)));
+            }
+        }
+
+        if let (Some(limit), Some(run_id)) = (state.config.max_actions_per_run, run_id) {
+            if state
+                .run_actions
+                .get(run_id)
+                .is_some_and(|&count| count >= limit)
+            {
+                return Err(refused(format!("more than {} actions in this run", limit)));
+            }
+        }
+
+        state.recent.push_back(now);
+        if let Some(run_id) = run_id {
+            *state.run_actions.entry(run_id.to_string()).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    /// Forget the action count of a finished run
+    pub fn finish_run(&self, run_id: &str) {
+        self.state.lock().unwrap().run_actions.remove(run_id);
+    }
+}
+
+impl Default for Governor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Governor, confirmation gate and user activity pause applied to actions of one run
+#[derive(Clone)]
+pub struct ActionGuard {
+    pub governor: Arc<Governor>,
+    pub confirmations: Arc<ConfirmationGate>,
+    pub user_activity: Arc<UserActivity>,
+    /// Run the actions count against
+    pub run_id: Option<String>,
+}
+
+impl std::fmt::Debug for ActionGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActionGuard")
+            .field("run_id", &self.run_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ActionGuard {
+    /// Admit an action, waiting for approval when the confirmation rules require it
+    /// and for the user to let go of the mouse after an intervention
+    pub async fn check(
+        &self,
+        action: &str,
+        position: Option<(i32, i32)>,
+        keys: Option<&str>,
+        cancel: &CancellationToken,
+    ) -> Result<(), XenotesterError> {
+        self.user_activity.wait_until_idle(cancel).await?;
+        self.governor
+            .admit(action, position, self.run_id.as_deref())?;
+        self.confirmations
+            .confirm(action, position, keys, cancel)
+            .await
+    }
+}
+
+fn refused(reason: String) -> XenotesterError {
+    XenotesterError::Blocked(format!("Blocked by safety governor: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn governor(config: GovernorConfig) -> Governor {
+        let governor = Governor::new();
+        governor.set_config(config);
+        governor
+    }
+
+    #[test]
+    fn test_rate_and_run_limits() {
+        let governor = governor(GovernorConfig {
+            max_actions_per_second: Some(2),
+            max_actions_per_run: Some(3),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let admit = |ms: u64| {
+            let now = start + Duration::from_millis(ms);
+            governor.admit_at("left_click", None, Some("run"), now)
+        };
+
+        assert!(admit(0).is_ok());
+        assert!(admit(100).is_ok());
+        assert!(admit(200).is_err());
+        // Actions outside the run do not reset its count
+        let now = start + Duration::from_millis(1000);
+        assert!(governor.admit_at("left_click", None, None, now).is_ok());
+        assert!(admit(2000).is_ok());
+        assert!(admit(3000).is_err());
+        // Other runs count separately
+        let now = start + Duration::from_millis(4000);
+        assert!(governor
+            .admit_at("left_click", None, Some("next"), now)
+            .is_ok());
+        assert!(admit(5000).is_err());
+        // A finished run's count is dropped
+        governor.finish_run("run");
+        assert!(admit(6000).is_ok());
+    }
+
+    #[test]
+    fn test_denied_and_allowed_regions() {
+        let menu_bar = ScreenRect {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 25,
+        };
+        let governor = governor(GovernorConfig {
+            denied_regions: vec![menu_bar],
+            ..Default::default()
+        });
+
+        assert!(governor.admit("left_click", Some((100, 10)), None).is_err());
+        assert!(governor.admit("mouse_move", Some((100, 10)), None).is_ok());
+        assert!(governor.admit("left_click", Some((100, 25)), None).is_ok());
+
+        let config = GovernorConfig {
+            allowed_regions: vec![ScreenRect {
+                x: 100,
+                y: 100,
+                width: 50,
+                height: 50,
+            }],
+            ..Default::default()
+        };
+        assert!(config.check_position(120, 120).is_ok());
+        assert!(config.check_position(150, 120).is_err());
+    }
+}
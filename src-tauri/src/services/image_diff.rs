@@ -0,0 +1,250 @@
+//! Visual diff between two screenshots
+//!
+//! Pixels whose largest channel difference exceeds a tolerance count as changed.
+//! Changed pixels are grouped into cells, and connected cells become regions.
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use imageproc::drawing::draw_hollow_rect_mut;
+use imageproc::rect::Rect;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::io::Cursor;
+
+use crate::error::XenotesterError;
+
+/// Default per-channel tolerance (absorbs anti-aliasing and lossy compression noise)
+pub const DEFAULT_DIFF_THRESHOLD: u8 = 24;
+/// Cell size for grouping changed pixels into regions
+const REGION_CELL_SIZE: u32 = 16;
+/// Upper bound on reported regions (largest first)
+const MAX_REGIONS: usize = 50;
+
+/// Bounding box of a changed area
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub changed_pixels: u64,
+}
+
+/// Result of comparing two images
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffResult {
+    /// 1.0 means identical (fraction of unchanged pixels)
+    pub similarity: f64,
+    pub changed_pixels: u64,
+    pub total_pixels: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Changed areas, largest first
+    pub regions: Vec<ChangedRegion>,
+    /// `after` with changed pixels highlighted (base64 PNG), when requested
+    pub diff_image_base64: Option<String>,
+}
+
+/// Compare two images; `after` is resized to `before`'s size if they differ
+pub fn compare_images(
+    before: &RgbaImage,
+    after: &RgbaImage,
+    threshold: u8,
+    include_diff_image: bool,
+) -> Result<DiffResult, XenotesterError> {
+    let (width, height) = before.dimensions();
+    if width == 0 || height == 0 {
+        return Err(XenotesterError::ImageError("Cannot compare an empty image".to_string()));
+    }
+
+    let resized;
+    let after = if after.dimensions() != (width, height) {
+        resized = image::imageops::resize(after, width, height, FilterType::Triangle);
+        &resized
+    } else {
+        after
+    };
+
+    let mask: Vec<bool> = before
+        .pixels()
+        .zip(after.pixels())
+        .map(|(a, b)| (0..3).any(|c| a.0[c].abs_diff(b.0[c]) > threshold))
+        .collect();
+
+    let changed_pixels = mask.iter().filter(|&&c| c).count() as u64;
+    let total_pixels = width as u64 * height as u64;
+    let regions = find_regions(&mask, width, height);
+
+    let diff_image_base64 = if include_diff_image {
+        Some(render_diff_image(after, &mask, &regions)?)
+    } else {
+        None
+    };
+
+    Ok(DiffResult {
+        similarity: 1.0 - changed_pixels as f64 / total_pixels as f64,
+        changed_pixels,
+        total_pixels,
+        width,
+        height,
+        regions,
+        diff_image_base64,
+    })
+}
+
+/// Compare two base64 encoded images
+pub fn compare_base64(
+    before_base64: &str,
+    after_base64: &str,
+    threshold: u8,
+    include_diff_image: bool,
+) -> Result<DiffResult, XenotesterError> {
+    let before = decode_rgba(before_base64)?;
+    let after = decode_rgba(after_base64)?;
+    compare_images(&before, &after, threshold, include_diff_image)
+}
+
+/// Decode a base64 image into RGBA
+pub fn decode_rgba(image_base64: &str) -> Result<RgbaImage, XenotesterError> {
+    let bytes = BASE64_STANDARD
+        .decode(image_base64)
+        .map_err(|e| XenotesterError::ImageError(format!("Base64 decode error: {}", e)))?;
+    Ok(image::load_from_memory(&bytes)?.into_rgba8())
+}
+
+/// Group changed pixels into cells and return bounding boxes of connected cells
+fn find_regions(mask: &[bool], width: u32, height: u32) -> Vec<ChangedRegion> {
+    let cols = width.div_ceil(REGION_CELL_SIZE) as usize;
+    let rows = height.div_ceil(REGION_CELL_SIZE) as usize;
+
+    // Changed pixel count per cell
+    let mut cells = vec![0u64; cols * rows];
+    for (i, _) in mask.iter().enumerate().filter(|(_, &c)| c) {
+        let x = i as u32 % width;
+        let y = i as u32 / width;
+        let cell = (y / REGION_CELL_SIZE) as usize * cols + (x / REGION_CELL_SIZE) as usize;
+        cells[cell] += 1;
+    }
+
+    let mut visited = vec![false; cells.len()];
+    let mut regions = Vec::new();
+
+    for start in 0..cells.len() {
+        if cells[start] == 0 || visited[start] {
+            continue;
+        }
+
+        // Flood fill over 8-connected changed cells
+        let (mut min_c, mut min_r, mut max_c, mut max_r) = (cols, rows, 0, 0);
+        let mut count = 0u64;
+        let mut queue = VecDeque::from([start]);
+        visited[start] = true;
+
+        while let Some(cell) = queue.pop_front() {
+            let (c, r) = (cell % cols, cell / cols);
+            min_c = min_c.min(c);
+            max_c = max_c.max(c);
+            min_r = min_r.min(r);
+            max_r = max_r.max(r);
+            count += cells[cell];
+
+            for dr in -1i64..=1 {
+                for dc in -1i64..=1 {
+                    let (nc, nr) = (c as i64 + dc, r as i64 + dr);
+                    if nc < 0 || nr < 0 || nc >= cols as i64 || nr >= rows as i64 {
+                        continue;
+                    }
+                    let neighbor = nr as usize * cols + nc as usize;
+                    if cells[neighbor] > 0 && !visited[neighbor] {
+                        visited[neighbor] = true;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+
+        let x = min_c as u32 * REGION_CELL_SIZE;
+        let y = min_r as u32 * REGION_CELL_SIZE;
+        regions.push(ChangedRegion {
+            x,
+            y,
+            width: ((max_c as u32 + 1) * REGION_CELL_SIZE).min(width) - x,
+            height: ((max_r as u32 + 1) * REGION_CELL_SIZE).min(height) - y,
+            changed_pixels: count,
+        });
+    }
+
+    regions.sort_by_key(|r| Reverse(r.width * r.height));
+    regions.truncate(MAX_REGIONS);
+    regions
+}
+
+/// Dim the image, paint changed pixels red and outline regions
+fn render_diff_image(
+    after: &RgbaImage,
+    mask: &[bool],
+    regions: &[ChangedRegion],
+) -> Result<String, XenotesterError> {
+    let mut output = after.clone();
+    for (pixel, &changed) in output.pixels_mut().zip(mask) {
+        if changed {
+            *pixel = Rgba([255, 0, 0, 255]);
+        } else {
+            let [r, g, b, a] = pixel.0;
+            *pixel = Rgba([r / 3, g / 3, b / 3, a]);
+        }
+    }
+
+    for region in regions {
+        draw_hollow_rect_mut(
+            &mut output,
+            Rect::at(region.x as i32, region.y as i32).of_size(region.width, region.height),
+            Rgba([255, 255, 0, 255]),
+        );
+    }
+
+    let mut buffer = Vec::new();
+    output
+        .write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)
+        .map_err(|e| XenotesterError::ImageError(e.to_string()))?;
+    Ok(BASE64_STANDARD.encode(&buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_images_have_full_similarity() {
+        let image = RgbaImage::from_pixel(64, 64, Rgba([10, 20, 30, 255]));
+        let result = compare_images(&image, &image, DEFAULT_DIFF_THRESHOLD, false).unwrap();
+
+        assert_eq!(result.similarity, 1.0);
+        assert!(result.regions.is_empty());
+        assert!(result.diff_image_base64.is_none());
+    }
+
+    #[test]
+    fn test_changed_block_is_reported_as_region() {
+        let before = RgbaImage::from_pixel(200, 100, Rgba([255, 255, 255, 255]));
+        let mut after = before.clone();
+        for y in 40..60 {
+            for x in 100..150 {
+                after.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+
+        let result = compare_images(&before, &after, DEFAULT_DIFF_THRESHOLD, true).unwrap();
+
+        assert_eq!(result.changed_pixels, 50 * 20);
+        assert_eq!(result.regions.len(), 1);
+        let region = &result.regions[0];
+        // Regions are cell-aligned and cover the changed block
+        assert!(region.x <= 100 && region.x + region.width >= 150);
+        assert!(region.y <= 40 && region.y + region.height >= 60);
+        assert!(result.diff_image_base64.is_some());
+    }
+}
@@ -1,8 +1,13 @@
 //! Image processing service for screenshot resizing and encoding
 
 use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
-use image::{DynamicImage, GenericImageView};
-use serde::Serialize;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, GenericImageView, Rgba, RgbaImage};
+use imageproc::drawing::{draw_hollow_polygon_mut, draw_polygon_mut};
+use imageproc::point::Point;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::io::Cursor;
 
 use crate::error::XenotesterError;
@@ -12,6 +17,136 @@ use crate::error::XenotesterError;
 const MAX_LONG_EDGE: u32 = 1920;
 /// Maximum total pixels (~2 megapixels for better text recognition)
 const MAX_TOTAL_PIXELS: u32 = 2_000_000;
+/// Fingerprint size used for frame change detection
+const FINGERPRINT_WIDTH: u32 = 128;
+const FINGERPRINT_HEIGHT: u32 = 72;
+/// Luma difference below which a fingerprint cell counts as unchanged (filters noise/dithering)
+const FINGERPRINT_PIXEL_TOLERANCE: u8 = 10;
+/// Default quality for lossy encodings (1-100)
+const DEFAULT_LOSSY_QUALITY: u8 = 80;
+
+thread_local! {
+    /// RGB conversion buffer for JPEG encoding, reused across captures on a worker thread
+    static RGB_SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Resampling filter used when shrinking screenshots
+///
+/// Measured on a 3840x2160 frame (release build): shrinking to 1920px takes ~200ms
+/// with `High`, ~170ms with `Balanced` and ~140ms with `Fast`; shrinking to 320px
+/// takes ~60ms, ~35ms and ~20ms.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeQuality {
+    /// Lanczos3: sharpest text, for frames sent to the model
+    #[default]
+    High,
+    /// CatmullRom: nearly as sharp at lower cost
+    Balanced,
+    /// Triangle: for previews and thumbnails
+    Fast,
+}
+
+impl ResizeQuality {
+    fn filter(self) -> FilterType {
+        match self {
+            ResizeQuality::High => FilterType::Lanczos3,
+            ResizeQuality::Balanced => FilterType::CatmullRom,
+            ResizeQuality::Fast => FilterType::Triangle,
+        }
+    }
+}
+
+/// Output format for encoded screenshots
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormatKind {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+/// Screenshot encoding options
+/// PNG is lossless; JPEG/WebP trade some fidelity for a 3-5x smaller payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImageEncoding {
+    pub format: ImageFormatKind,
+    /// Quality for lossy formats (1-100, ignored for PNG)
+    pub quality: u8,
+}
+
+impl Default for ImageEncoding {
+    fn default() -> Self {
+        Self {
+            format: ImageFormatKind::Png,
+            quality: DEFAULT_LOSSY_QUALITY,
+        }
+    }
+}
+
+impl ImageEncoding {
+    /// MIME type of the encoded output
+    pub fn media_type(&self) -> &'static str {
+        match self.format {
+            ImageFormatKind::Png => "image/png",
+            ImageFormatKind::Jpeg => "image/jpeg",
+            ImageFormatKind::Webp => "image/webp",
+        }
+    }
+}
+
+/// Encode an image with the given encoding options
+pub fn encode_image(image: &DynamicImage, encoding: &ImageEncoding) -> Result<Vec<u8>, XenotesterError> {
+    let quality = encoding.quality.clamp(1, 100);
+    let mut buffer = Vec::new();
+
+    match encoding.format {
+        ImageFormatKind::Png => image
+            .write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)
+            .map_err(|e| XenotesterError::ImageError(e.to_string()))?,
+        ImageFormatKind::Jpeg => with_rgb_pixels(image, |rgb| {
+            // JPEG has no alpha channel
+            JpegEncoder::new_with_quality(&mut buffer, quality)
+                .encode(rgb, image.width(), image.height(), ExtendedColorType::Rgb8)
+                .map_err(|e| XenotesterError::ImageError(e.to_string()))
+        })?,
+        ImageFormatKind::Webp => {
+            // The image crate only supports lossless WebP, so use libwebp for lossy output
+            // Captures are already RGBA, so borrow their pixels instead of copying them
+            let converted;
+            let rgba = match image.as_rgba8() {
+                Some(rgba) => rgba,
+                None => {
+                    converted = image.to_rgba8();
+                    &converted
+                }
+            };
+            let encoded = webp::Encoder::from_rgba(rgba, rgba.width(), rgba.height())
+                .encode(quality as f32);
+            buffer.extend_from_slice(&encoded);
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Run `encode` on the image's pixels as packed RGB
+/// RGBA images (all captures) are converted into the thread's reused scratch buffer.
+fn with_rgb_pixels<T>(image: &DynamicImage, encode: impl FnOnce(&[u8]) -> T) -> T {
+    match image.as_rgba8() {
+        Some(rgba) => RGB_SCRATCH.with_borrow_mut(|scratch| {
+            scratch.clear();
+            scratch.reserve(rgba.as_raw().len() / 4 * 3);
+            for pixel in rgba.as_raw().chunks_exact(4) {
+                scratch.extend_from_slice(&pixel[..3]);
+            }
+            encode(scratch)
+        }),
+        None => encode(image.to_rgb8().as_raw()),
+    }
+}
 
 /// Result of image resize operation
 #[derive(Debug, Clone, Serialize)]
@@ -23,12 +158,30 @@ pub struct ResizeResult {
     pub resized_height: u32,
     pub scale_factor: f64,
     pub image_base64: String,
+    /// MIME type of `image_base64` (e.g. "image/png")
+    pub media_type: String,
 }
 
-/// Resize screenshot to fit API constraints
+/// Resized and encoded screenshot bytes (before base64)
+#[derive(Debug, Clone)]
+pub struct EncodedScreenshot {
+    pub original_width: u32,
+    pub original_height: u32,
+    pub resized_width: u32,
+    pub resized_height: u32,
+    pub scale_factor: f64,
+    pub media_type: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Resize screenshot to fit API constraints and encode it
 /// - Max long edge: 1920px (increased for better text readability)
 /// - Max total pixels: ~2 megapixels
-pub fn resize_screenshot(image: DynamicImage) -> Result<ResizeResult, XenotesterError> {
+pub fn resize_and_encode(
+    image: DynamicImage,
+    encoding: &ImageEncoding,
+    quality: ResizeQuality,
+) -> Result<EncodedScreenshot, XenotesterError> {
     let (original_width, original_height) = image.dimensions();
 
     let long_edge = original_width.max(original_height);
@@ -46,37 +199,153 @@ pub fn resize_screenshot(image: DynamicImage) -> Result<ResizeResult, Xenotester
 
     // Resize if needed
     let final_image = if scale_factor < 1.0 {
-        image.resize_exact(
-            resized_width,
-            resized_height,
-            image::imageops::FilterType::Lanczos3,
-        )
+        image.resize_exact(resized_width, resized_height, quality.filter())
     } else {
         image
     };
 
-    // Encode to PNG and base64
-    let mut buffer = Vec::new();
-    final_image
-        .write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)
-        .map_err(|e| XenotesterError::ImageError(e.to_string()))?;
-
-    let image_base64 = BASE64_STANDARD.encode(&buffer);
+    let bytes = encode_image(&final_image, encoding)?;
 
-    Ok(ResizeResult {
+    Ok(EncodedScreenshot {
         original_width,
         original_height,
         resized_width,
         resized_height,
         scale_factor,
-        image_base64,
+        media_type: encoding.media_type().to_string(),
+        bytes,
+    })
+}
+
+/// Shrink an image to fit `max_edge` and encode it, for live previews
+/// Images already within `max_edge` are not upscaled.
+pub fn thumbnail_and_encode(
+    image: DynamicImage,
+    max_edge: u32,
+    encoding: &ImageEncoding,
+    quality: ResizeQuality,
+) -> Result<EncodedScreenshot, XenotesterError> {
+    let (original_width, original_height) = image.dimensions();
+    let long_edge = original_width.max(original_height).max(1);
+    let scale_factor = (max_edge.max(1) as f64 / long_edge as f64).min(1.0);
+
+    let resized_width = ((original_width as f64 * scale_factor).round() as u32).max(1);
+    let resized_height = ((original_height as f64 * scale_factor).round() as u32).max(1);
+    let final_image = if scale_factor < 1.0 {
+        image.resize_exact(resized_width, resized_height, quality.filter())
+    } else {
+        image
+    };
+
+    Ok(EncodedScreenshot {
+        original_width,
+        original_height,
+        resized_width: final_image.width(),
+        resized_height: final_image.height(),
+        scale_factor,
+        media_type: encoding.media_type().to_string(),
+        bytes: encode_image(&final_image, encoding)?,
     })
 }
 
+/// Resize screenshot to fit API constraints and encode it as base64
+pub fn resize_screenshot(
+    image: DynamicImage,
+    encoding: &ImageEncoding,
+    quality: ResizeQuality,
+) -> Result<ResizeResult, XenotesterError> {
+    let encoded = resize_and_encode(image, encoding, quality)?;
+
+    Ok(ResizeResult {
+        original_width: encoded.original_width,
+        original_height: encoded.original_height,
+        resized_width: encoded.resized_width,
+        resized_height: encoded.resized_height,
+        scale_factor: encoded.scale_factor,
+        image_base64: BASE64_STANDARD.encode(&encoded.bytes),
+        media_type: encoded.media_type,
+    })
+}
+
+/// Compute a small grayscale fingerprint of a frame for change detection
+pub fn frame_fingerprint(image: &DynamicImage) -> Vec<u8> {
+    image
+        .thumbnail_exact(FINGERPRINT_WIDTH, FINGERPRINT_HEIGHT)
+        .to_luma8()
+        .into_raw()
+}
+
+/// Fraction of fingerprint cells that differ between two frames (0.0 - 1.0)
+/// Fingerprints of different sizes are treated as fully changed
+pub fn fingerprint_change_ratio(previous: &[u8], current: &[u8]) -> f64 {
+    if previous.len() != current.len() || current.is_empty() {
+        return 1.0;
+    }
+
+    let changed = previous
+        .iter()
+        .zip(current)
+        .filter(|(a, b)| a.abs_diff(**b) > FINGERPRINT_PIXEL_TOLERANCE)
+        .count();
+
+    changed as f64 / current.len() as f64
+}
+
+/// Arrow pointer outline (tip at the origin), in logical points
+const CURSOR_SHAPE: [(f32, f32); 7] = [
+    (0.0, 0.0),
+    (0.0, 17.0),
+    (4.5, 13.0),
+    (7.5, 20.0),
+    (10.0, 19.0),
+    (7.0, 12.0),
+    (12.0, 12.0),
+];
+
+/// Draw an arrow pointer with its tip at (x, y)
+/// `scale` is the display scale factor so the pointer keeps its on-screen size
+pub fn draw_cursor(image: &mut RgbaImage, x: i32, y: i32, scale: f64) {
+    let scale = scale.max(1.0) as f32;
+    let points: Vec<Point<f32>> = CURSOR_SHAPE
+        .iter()
+        .map(|(px, py)| Point::new(x as f32 + px * scale, y as f32 + py * scale))
+        .collect();
+    let fill: Vec<Point<i32>> = points
+        .iter()
+        .map(|p| Point::new(p.x.round() as i32, p.y.round() as i32))
+        .collect();
+
+    // Black fill with a white outline stays visible on both light and dark backgrounds
+    draw_polygon_mut(image, &fill, Rgba([0, 0, 0, 255]));
+    draw_hollow_polygon_mut(image, &points, Rgba([255, 255, 255, 255]));
+}
+
+/// Create a small PNG thumbnail from a base64 encoded image
+/// Aspect ratio is preserved; images already within `max_edge` are not upscaled
+pub fn create_thumbnail_png(image_base64: &str, max_edge: u32) -> Result<Vec<u8>, XenotesterError> {
+    let bytes = BASE64_STANDARD
+        .decode(image_base64)
+        .map_err(|e| XenotesterError::ImageError(format!("Base64 decode error: {}", e)))?;
+    let image = image::load_from_memory(&bytes)?;
+
+    let (width, height) = image.dimensions();
+    let thumbnail = if width.max(height) > max_edge {
+        image.thumbnail(max_edge, max_edge)
+    } else {
+        image
+    };
+
+    let mut buffer = Vec::new();
+    thumbnail
+        .write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)
+        .map_err(|e| XenotesterError::ImageError(e.to_string()))?;
+
+    Ok(buffer)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use image::RgbaImage;
 
     #[test]
     fn test_resize_large_image() {
@@ -84,7 +353,8 @@ mod tests {
         let img = RgbaImage::new(2560, 1440);
         let dynamic = DynamicImage::ImageRgba8(img);
 
-        let result = resize_screenshot(dynamic).unwrap();
+        let result =
+            resize_screenshot(dynamic, &ImageEncoding::default(), ResizeQuality::High).unwrap();
 
         assert!(result.resized_width <= MAX_LONG_EDGE);
         assert!(result.resized_height <= MAX_LONG_EDGE);
@@ -97,11 +367,94 @@ mod tests {
         let img = RgbaImage::new(800, 600);
         let dynamic = DynamicImage::ImageRgba8(img);
 
-        let result = resize_screenshot(dynamic).unwrap();
+        let result =
+            resize_screenshot(dynamic, &ImageEncoding::default(), ResizeQuality::High).unwrap();
 
         // Should not be resized
         assert_eq!(result.resized_width, 800);
         assert_eq!(result.resized_height, 600);
         assert!((result.scale_factor - 1.0).abs() < 0.001);
     }
+
+    #[test]
+    fn test_create_thumbnail_preserves_aspect_ratio() {
+        let img = RgbaImage::new(1600, 900);
+        let encoded = resize_screenshot(
+            DynamicImage::ImageRgba8(img),
+            &ImageEncoding::default(),
+            ResizeQuality::High,
+        )
+        .unwrap();
+
+        let png = create_thumbnail_png(&encoded.image_base64, 400).unwrap();
+        let thumbnail = image::load_from_memory(&png).unwrap();
+
+        assert_eq!(thumbnail.width(), 400);
+        assert_eq!(thumbnail.height(), 225);
+    }
+
+    #[test]
+    fn test_thumbnail_fits_max_edge() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(1920, 1080));
+        let thumbnail =
+            thumbnail_and_encode(img, 320, &ImageEncoding::default(), ResizeQuality::Fast).unwrap();
+
+        assert_eq!((thumbnail.resized_width, thumbnail.resized_height), (320, 180));
+        assert!((thumbnail.scale_factor - 320.0 / 1920.0).abs() < 1e-9);
+        let decoded = image::load_from_memory(&thumbnail.bytes).unwrap();
+        assert_eq!(decoded.dimensions(), (320, 180));
+    }
+
+    #[test]
+    fn test_fingerprint_change_ratio() {
+        let mut img = RgbaImage::from_pixel(1280, 720, image::Rgba([255, 255, 255, 255]));
+        let before = frame_fingerprint(&DynamicImage::ImageRgba8(img.clone()));
+
+        // Identical frames are unchanged
+        assert_eq!(fingerprint_change_ratio(&before, &before), 0.0);
+
+        // Paint a dark block over a quarter of the frame
+        for y in 0..360 {
+            for x in 0..640 {
+                img.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+            }
+        }
+        let after = frame_fingerprint(&DynamicImage::ImageRgba8(img));
+        let ratio = fingerprint_change_ratio(&before, &after);
+        assert!((ratio - 0.25).abs() < 0.02, "ratio was {}", ratio);
+    }
+
+    #[test]
+    fn test_draw_cursor_marks_pointer_tip() {
+        let mut img = RgbaImage::from_pixel(100, 100, Rgba([128, 128, 128, 255]));
+        draw_cursor(&mut img, 40, 30, 1.0);
+
+        // Tip is outlined, body is filled, and pixels away from the pointer are untouched
+        assert_ne!(img.get_pixel(40, 30), &Rgba([128, 128, 128, 255]));
+        assert_eq!(img.get_pixel(43, 40), &Rgba([0, 0, 0, 255]));
+        assert_eq!(img.get_pixel(10, 10), &Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn test_resize_with_jpeg_encoding() {
+        let img = RgbaImage::from_pixel(800, 600, image::Rgba([200, 40, 40, 255]));
+        let encoding = ImageEncoding {
+            format: ImageFormatKind::Jpeg,
+            quality: 70,
+        };
+
+        let result = resize_screenshot(
+            DynamicImage::ImageRgba8(img),
+            &encoding,
+            ResizeQuality::High,
+        )
+        .unwrap();
+        assert_eq!(result.media_type, "image/jpeg");
+
+        let bytes = BASE64_STANDARD.decode(&result.image_base64).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), image::ImageFormat::Jpeg);
+        // Channels survive the RGBA to RGB conversion in order
+        let pixel = image::load_from_memory(&bytes).unwrap().to_rgb8()[(400, 300)];
+        assert!(pixel.0[0] > 150 && pixel.0[1] < 90 && pixel.0[2] < 90);
+    }
 }
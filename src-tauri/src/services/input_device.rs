@@ -0,0 +1,106 @@
+//! Shared enigo instance for the mouse and keyboard services
+//!
+//! Constructing `Enigo` per call creates a new event source every time on macOS
+//! and occasionally fails under load. A single instance is created lazily and
+//! guarded by a mutex; it is rebuilt automatically when an input call fails.
+//! When an operation releases the device, the cursor position is remembered so
+//! that movement by the user can be told apart (see `services::user_activity`).
+
+use enigo::{Enigo, InputResult, Mouse, Settings};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+use tracing::warn;
+
+use crate::error::XenotesterError;
+
+/// Lazily constructed instance; `None` until first use or after a failure
+static ENIGO: Mutex<Option<Enigo>> = Mutex::new(None);
+
+/// Where the last operation left the cursor
+static SETTLED: Mutex<Option<SettledCursor>> = Mutex::new(None);
+
+/// Cursor position after an input operation, and when the operation ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettledCursor {
+    pub position: (i32, i32),
+    pub at: Instant,
+}
+
+/// Exclusive handle to the shared enigo instance
+/// Holding it for a whole operation keeps other input from interleaving.
+pub struct InputDevice {
+    guard: MutexGuard<'static, Option<Enigo>>,
+    /// Only the cursor was read; release doesn't count as an operation
+    observing: bool,
+}
+
+impl InputDevice {
+    /// Lock the shared instance (blocks while another operation is running)
+    pub fn acquire() -> Result<Self, XenotesterError> {
+        let guard = ENIGO.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(Self {
+            guard,
+            observing: false,
+        })
+    }
+
+    /// Read the cursor position (a read doesn't count as an input operation)
+    pub fn cursor_position(mut self) -> Result<(i32, i32), XenotesterError> {
+        self.observing = true;
+        self.run(|enigo| enigo.location())
+    }
+
+    /// Read the cursor and compare it with where the last operation left it
+    /// Returns the current position and where the last operation left the cursor.
+    /// The remembered position moves to the current one, so a deviation is seen once.
+    pub fn observe_cursor(
+        mut self,
+    ) -> Result<((i32, i32), Option<SettledCursor>), XenotesterError> {
+        self.observing = true;
+        let position = self.run(|enigo| enigo.location())?;
+        let mut settled = SETTLED.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = *settled;
+        if let Some(settled) = settled.as_mut() {
+            settled.position = position;
+        }
+        Ok((position, previous))
+    }
+
+    /// Run an enigo call, constructing the instance if needed
+    /// On failure the instance is discarded so the next call starts from a fresh one;
+    /// the call itself is not retried since it may have been partially delivered.
+    pub fn run<T>(
+        &mut self,
+        call: impl FnOnce(&mut Enigo) -> InputResult<T>,
+    ) -> Result<T, XenotesterError> {
+        call(self.enigo()?).map_err(|e| {
+            warn!("Enigo call failed, discarding instance: {}", e);
+            *self.guard = None;
+            XenotesterError::InputError(e.to_string())
+        })
+    }
+
+    /// Get the instance, constructing it on first use or after a failure
+    fn enigo(&mut self) -> Result<&mut Enigo, XenotesterError> {
+        let enigo = match self.guard.take() {
+            Some(enigo) => enigo,
+            None => Enigo::new(&Settings::default())
+                .map_err(|e| XenotesterError::InputError(e.to_string()))?,
+        };
+        Ok(self.guard.insert(enigo))
+    }
+}
+
+impl Drop for InputDevice {
+    fn drop(&mut self) {
+        if self.observing {
+            return;
+        }
+        if let Some(position) = self.guard.as_ref().and_then(|enigo| enigo.location().ok()) {
+            *SETTLED.lock().unwrap_or_else(PoisonError::into_inner) = Some(SettledCursor {
+                position,
+                at: Instant::now(),
+            });
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Playback of recorded input scripts
+//!
+//! Replays the actions of an `InputScript` through the mouse/keyboard services,
+//! keeping their recorded offsets (scaled by the playback speed). Waiting and
+//! every action observe the run's cancellation token, and each action is
+//! admitted by the safety governor on its own.
+
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::error::XenotesterError;
+use crate::services::governor::Governor;
+use crate::services::input_recorder::{InputAction, InputScript};
+use crate::services::keyboard::{self, TypingOptions};
+use crate::services::mouse::{self, MouseButton};
+use crate::utils::cancel::CancellationToken;
+
+/// Highest supported playback speed multiplier
+const MAX_SPEED: f64 = 100.0;
+
+/// Play a script (blocking)
+/// `speed` scales the timing: 2.0 replays twice as fast, 0.5 at half speed.
+/// Actions that take longer than the gap to the next one delay the rest of the script.
+/// With a `governor`, every action counts against its limits (under `run_id`) and
+/// pointer actions are refused at forbidden positions, drags at either end.
+pub fn play(
+    script: &InputScript,
+    speed: f64,
+    governor: Option<&Governor>,
+    run_id: Option<&str>,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    if !speed.is_finite() || speed <= 0.0 || speed > MAX_SPEED {
+        return Err(XenotesterError::InvalidArgument(format!(
+            "Playback speed must be between 0 and {}, got {}",
+            MAX_SPEED, speed
+        )));
+    }
+
+    info!("Playing {} actions at {}x", script.actions.len(), speed);
+    let started = Instant::now();
+
+    for recorded in &script.actions {
+        let due = Duration::from_secs_f64(recorded.at_ms as f64 / 1000.0 / speed);
+        cancel.sleep(due.saturating_sub(started.elapsed()))?;
+        if let Some(governor) = governor {
+            admit(governor, &recorded.action, run_id)?;
+        }
+        execute(&recorded.action, cancel)?;
+    }
+
+    Ok(())
+}
+
+/// Count a recorded action against the governor's limits
+fn admit(
+    governor: &Governor,
+    action: &InputAction,
+    run_id: Option<&str>,
+) -> Result<(), XenotesterError> {
+    match action {
+        InputAction::Click { x, y, .. } => governor.admit("click", Some((*x, *y)), run_id),
+        InputAction::Drag {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            ..
+        } => {
+            governor.check_position(*end_x, *end_y)?;
+            governor.admit("drag", Some((*start_x, *start_y)), run_id)
+        }
+        InputAction::Scroll { x, y, .. } => governor.admit("scroll", Some((*x, *y)), run_id),
+        InputAction::Type { .. } => governor.admit("type_text", None, run_id),
+        InputAction::Key { .. } => governor.admit("key", None, run_id),
+    }
+}
+
+/// Execute a single recorded action
+fn execute(action: &InputAction, cancel: &CancellationToken) -> Result<(), XenotesterError> {
+    match action {
+        InputAction::Click { x, y, button, count } => {
+            mouse::multi_click(*x, *y, *button, (*count).max(1), cancel)?
+        }
+        InputAction::Drag {
+            button: MouseButton::Left,
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+        } => mouse::drag(*start_x, *start_y, *end_x, *end_y, cancel)?,
+        InputAction::Drag {
+            button,
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+        } => {
+            mouse::mouse_down(*start_x, *start_y, *button, cancel)?;
+            mouse::mouse_up(*end_x, *end_y, *button, cancel)?;
+        }
+        InputAction::Scroll { x, y, dx, dy } => mouse::scroll_pixels(*x, *y, *dx, *dy, cancel)?,
+        InputAction::Type { text } => {
+            keyboard::type_text(text, &TypingOptions::default(), cancel)?
+        }
+        InputAction::Key { keys } => keyboard::key_combination(keys, None, cancel)?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::governor::{GovernorConfig, ScreenRect};
+    use crate::services::input_recorder::RecordedAction;
+
+    fn script() -> InputScript {
+        InputScript {
+            duration_ms: 1_000,
+            actions: vec![RecordedAction {
+                at_ms: 0,
+                action: InputAction::Key {
+                    keys: "enter".into(),
+                },
+            }],
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_speed() {
+        let cancel = CancellationToken::new();
+        for speed in [0.0, -1.0, 100.5, f64::NAN, f64::INFINITY] {
+            let result = play(&script(), speed, None, None, &cancel);
+            assert!(
+                matches!(result, Err(XenotesterError::InvalidArgument(_))),
+                "speed {} was accepted",
+                speed
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_script() {
+        let cancel = CancellationToken::new();
+        assert!(play(&InputScript::default(), MAX_SPEED, None, None, &cancel).is_ok());
+    }
+
+    #[test]
+    fn test_cancelled_before_input() {
+        // The wait before the first action fails before any input is sent
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert!(matches!(
+            play(&script(), 1.0, None, None, &cancel),
+            Err(XenotesterError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn test_actions_admitted_by_governor() {
+        let governor = Governor::new();
+        governor.set_config(GovernorConfig {
+            denied_regions: vec![ScreenRect {
+                x: 0,
+                y: 0,
+                width: 100,
+                height: 100,
+            }],
+            ..Default::default()
+        });
+        let drag = |end_x| InputAction::Drag {
+            button: MouseButton::Left,
+            start_x: 200,
+            start_y: 200,
+            end_x,
+            end_y: 50,
+        };
+
+        assert!(admit(&governor, &drag(300), None).is_ok());
+        // Dropping inside a denied region is refused
+        assert!(admit(&governor, &drag(50), None).is_err());
+        let click = InputAction::Click {
+            x: 10,
+            y: 10,
+            button: MouseButton::Left,
+            count: 1,
+        };
+        assert!(admit(&governor, &click, None).is_err());
+        let key = InputAction::Key {
+            keys: "enter".into(),
+        };
+        assert!(admit(&governor, &key, None).is_ok());
+    }
+
+    #[test]
+    fn test_script_json() {
+        let json = serde_json::to_value(script()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "durationMs": 1000,
+                "actions": [{ "atMs": 0, "type": "key", "keys": "enter" }],
+            })
+        );
+
+        let drag: RecordedAction = serde_json::from_value(serde_json::json!({
+            "atMs": 5,
+            "type": "drag",
+            "button": "left",
+            "startX": 1,
+            "startY": 2,
+            "endX": 3,
+            "endY": 4,
+        }))
+        .unwrap();
+        assert_eq!(
+            drag.action,
+            InputAction::Drag {
+                button: MouseButton::Left,
+                start_x: 1,
+                start_y: 2,
+                end_x: 3,
+                end_y: 4,
+            }
+        );
+    }
+}
@@ -0,0 +1,741 @@
+//! Recording of user mouse/keyboard input into a replayable action script
+//!
+//! A global hook (rdev) observes every input event while a recording is active
+//! and folds the raw stream into high-level actions: clicks (with multi-click
+//! counts), drags, scrolls, typed text and key combinations, each stamped with
+//! its offset from the start of the recording. The hook cannot be unregistered
+//! once installed, so it keeps running and drops events between recordings.
+
+use rdev::{Event, EventType, Key};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::SystemTime;
+use tracing::{error, info};
+
+use crate::error::XenotesterError;
+use crate::services::mouse::MouseButton;
+
+/// Pointer travel (px) while a button is held before a click becomes a drag
+const DRAG_THRESHOLD_PX: i32 = 5;
+
+/// Maximum gap between clicks at the same spot to count as a multi-click
+const MULTI_CLICK_WINDOW_MS: u64 = 500;
+
+/// Wheel events closer together than this are merged into one scroll
+const SCROLL_MERGE_WINDOW_MS: u64 = 300;
+
+// Scroll distance of one wheel unit reported by the hook: macOS reports pixel
+// deltas, Windows and Linux report whole notches
+#[cfg(target_os = "macos")]
+const PIXELS_PER_WHEEL_UNIT: i64 = 1;
+#[cfg(not(target_os = "macos"))]
+const PIXELS_PER_WHEEL_UNIT: i64 = crate::services::mouse::PIXELS_PER_WHEEL_NOTCH as i64;
+
+/// Recorded action (coordinates are absolute screen positions as used by the mouse service)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum InputAction {
+    Click {
+        x: i32,
+        y: i32,
+        button: MouseButton,
+        /// 2 for a double click, 3 for a triple click
+        count: u32,
+    },
+    Drag {
+        button: MouseButton,
+        start_x: i32,
+        start_y: i32,
+        end_x: i32,
+        end_y: i32,
+    },
+    /// Pixel distance scrolled (positive dx / dy scroll right / down)
+    Scroll { x: i32, y: i32, dx: i32, dy: i32 },
+    Type { text: String },
+    /// Key or combination in `key_combination` syntax (e.g., "ctrl+s", "enter")
+    Key { keys: String },
+}
+
+/// Action with its offset from the start of the recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedAction {
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub action: InputAction,
+}
+
+/// Finished recording
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputScript {
+    /// Length of the recording in milliseconds
+    pub duration_ms: u64,
+    pub actions: Vec<RecordedAction>,
+}
+
+/// Modifier keys currently held
+#[derive(Debug, Default)]
+struct Modifiers {
+    ctrl: bool,
+    alt: bool,
+    alt_gr: bool,
+    shift: bool,
+    meta: bool,
+}
+
+impl Modifiers {
+    /// Track a modifier press/release; returns false for non-modifier keys
+    fn update(&mut self, key: Key, pressed: bool) -> bool {
+        let flag = match key {
+            Key::ControlLeft | Key::ControlRight => &mut self.ctrl,
+            Key::Alt => &mut self.alt,
+            Key::AltGr => &mut self.alt_gr,
+            Key::ShiftLeft | Key::ShiftRight => &mut self.shift,
+            Key::MetaLeft | Key::MetaRight => &mut self.meta,
+            _ => return false,
+        };
+        *flag = pressed;
+        true
+    }
+
+    /// Whether a key press is a shortcut rather than text input
+    /// AltGr is excluded: Windows reports it as Ctrl+Alt while typing characters.
+    fn is_shortcut(&self) -> bool {
+        (self.ctrl || self.alt || self.meta) && !self.alt_gr
+    }
+
+    /// Held modifiers as a `key_combination` prefix (e.g., "ctrl+shift+")
+    fn prefix(&self) -> String {
+        [
+            (self.ctrl, "ctrl+"),
+            (self.alt, "alt+"),
+            (self.shift, "shift+"),
+            (self.meta, "meta+"),
+        ]
+        .iter()
+        .filter(|(held, _)| *held)
+        .map(|(_, name)| *name)
+        .collect()
+    }
+}
+
+/// Mouse button held down since `at_ms`
+#[derive(Debug)]
+struct PressedButton {
+    button: MouseButton,
+    x: i32,
+    y: i32,
+    at_ms: u64,
+    dragged: bool,
+}
+
+/// Folds raw hook events into an action script
+struct ScriptBuilder {
+    started: SystemTime,
+    actions: Vec<RecordedAction>,
+    position: (i32, i32),
+    pressed: Option<PressedButton>,
+    modifiers: Modifiers,
+    last_click_ms: u64,
+    last_wheel_ms: u64,
+}
+
+impl ScriptBuilder {
+    fn new(started: SystemTime) -> Self {
+        Self {
+            started,
+            actions: Vec::new(),
+            position: (0, 0),
+            pressed: None,
+            modifiers: Modifiers::default(),
+            last_click_ms: 0,
+            last_wheel_ms: 0,
+        }
+    }
+
+    fn elapsed_ms(&self, time: SystemTime) -> u64 {
+        time.duration_since(self.started)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default()
+    }
+
+    fn record(&mut self, at_ms: u64, action: InputAction) {
+        self.actions.push(RecordedAction { at_ms, action });
+    }
+
+    fn push(&mut self, event: &Event) {
+        let at_ms = self.elapsed_ms(event.time);
+
+        match event.event_type {
+            EventType::MouseMove { x, y } => {
+                self.position = (x.round() as i32, y.round() as i32);
+                let (x, y) = self.position;
+                if let Some(pressed) = self.pressed.as_mut() {
+                    if (x - pressed.x).abs().max((y - pressed.y).abs()) > DRAG_THRESHOLD_PX {
+                        pressed.dragged = true;
+                    }
+                }
+            }
+            EventType::ButtonPress(button) => {
+                if let Some(button) = map_button(button) {
+                    let (x, y) = self.position;
+                    self.pressed = Some(PressedButton {
+                        button,
+                        x,
+                        y,
+                        at_ms,
+                        dragged: false,
+                    });
+                }
+            }
+            EventType::ButtonRelease(button) => match (map_button(button), self.pressed.take()) {
+                (Some(button), Some(pressed)) if pressed.button == button => {
+                    self.release(pressed, at_ms)
+                }
+                (_, pressed) => self.pressed = pressed,
+            },
+            EventType::Wheel { delta_x, delta_y } => self.scroll(delta_x, delta_y, at_ms),
+            EventType::KeyPress(key) => self.key_press(key, event.name.as_deref(), at_ms),
+            EventType::KeyRelease(key) => {
+                self.modifiers.update(key, false);
+            }
+        }
+    }
+
+    /// Record a click (or extend the previous one into a multi-click) or a drag
+    fn release(&mut self, pressed: PressedButton, at_ms: u64) {
+        let (end_x, end_y) = self.position;
+
+        if pressed.dragged {
+            self.record(
+                pressed.at_ms,
+                InputAction::Drag {
+                    button: pressed.button,
+                    start_x: pressed.x,
+                    start_y: pressed.y,
+                    end_x,
+                    end_y,
+                },
+            );
+            return;
+        }
+
+        let gap_ms = pressed.at_ms.saturating_sub(self.last_click_ms);
+        let within_window = gap_ms <= MULTI_CLICK_WINDOW_MS;
+        self.last_click_ms = at_ms;
+
+        if let Some(RecordedAction {
+            action: InputAction::Click { x, y, button, count },
+            ..
+        }) = self.actions.last_mut()
+        {
+            let distance = (*x - pressed.x).abs().max((*y - pressed.y).abs());
+            let same_spot = distance <= DRAG_THRESHOLD_PX;
+            if within_window && same_spot && *button == pressed.button && *count < 3 {
+                *count += 1;
+                return;
+            }
+        }
+
+        self.record(
+            pressed.at_ms,
+            InputAction::Click {
+                x: pressed.x,
+                y: pressed.y,
+                button: pressed.button,
+                count: 1,
+            },
+        );
+    }
+
+    /// Record a wheel event, merging it into a scroll still in progress at the same spot
+    fn scroll(&mut self, delta_x: i64, delta_y: i64, at_ms: u64) {
+        // The hook reports positive deltas for up / right
+        let step_x = (delta_x * PIXELS_PER_WHEEL_UNIT) as i32;
+        let step_y = (-delta_y * PIXELS_PER_WHEEL_UNIT) as i32;
+        let (x, y) = self.position;
+        let continuing = at_ms.saturating_sub(self.last_wheel_ms) <= SCROLL_MERGE_WINDOW_MS;
+        self.last_wheel_ms = at_ms;
+
+        if let Some(RecordedAction {
+            action: InputAction::Scroll { x: sx, y: sy, dx, dy },
+            ..
+        }) = self.actions.last_mut()
+        {
+            if continuing && (*sx, *sy) == (x, y) {
+                *dx += step_x;
+                *dy += step_y;
+                return;
+            }
+        }
+
+        self.record(at_ms, InputAction::Scroll { x, y, dx: step_x, dy: step_y });
+    }
+
+    /// Record printable characters as text and everything else as a key combination
+    fn key_press(&mut self, key: Key, text: Option<&str>, at_ms: u64) {
+        if self.modifiers.update(key, true) {
+            return;
+        }
+
+        if !self.modifiers.is_shortcut() {
+            if let Some(text) = text.filter(|t| !t.is_empty() && !t.chars().any(char::is_control)) {
+                if let Some(RecordedAction {
+                    action: InputAction::Type { text: typed },
+                    ..
+                }) = self.actions.last_mut()
+                {
+                    typed.push_str(text);
+                } else {
+                    let text = text.to_string();
+                    self.record(at_ms, InputAction::Type { text });
+                }
+                return;
+            }
+        }
+
+        if let Some(name) = key_name(key) {
+            let keys = format!("{}{}", self.modifiers.prefix(), name);
+            self.record(at_ms, InputAction::Key { keys });
+        }
+    }
+
+    fn finish(self, stopped: SystemTime) -> InputScript {
+        InputScript {
+            duration_ms: self.elapsed_ms(stopped),
+            actions: self.actions,
+        }
+    }
+}
+
+/// Map a hook button (extra buttons are 1/2 on Windows and 8/9 on X11)
+fn map_button(button: rdev::Button) -> Option<MouseButton> {
+    match button {
+        rdev::Button::Left => Some(MouseButton::Left),
+        rdev::Button::Right => Some(MouseButton::Right),
+        rdev::Button::Middle => Some(MouseButton::Middle),
+        rdev::Button::Unknown(1 | 8) => Some(MouseButton::Back),
+        rdev::Button::Unknown(2 | 9) => Some(MouseButton::Forward),
+        rdev::Button::Unknown(_) => None,
+    }
+}
+
+/// Key name understood by `key_combination` (US layout position of the key)
+fn key_name(key: Key) -> Option<&'static str> {
+    let name = match key {
+        Key::KeyA => "a",
+        Key::KeyB => "b",
+        Key::KeyC => "c",
+        Key::KeyD => "d",
+        Key::KeyE => "e",
+        Key::KeyF => "f",
+        Key::KeyG => "g",
+        Key::KeyH => "h",
+        Key::KeyI => "i",
+        Key::KeyJ => "j",
+        Key::KeyK => "k",
+        Key::KeyL => "l",
+        Key::KeyM => "m",
+        Key::KeyN => "n",
+        Key::KeyO => "o",
+        Key::KeyP => "p",
+        Key::KeyQ => "q",
+        Key::KeyR => "r",
+        Key::KeyS => "s",
+        Key::KeyT => "t",
+        Key::KeyU => "u",
+        Key::KeyV => "v",
+        Key::KeyW => "w",
+        Key::KeyX => "x",
+        Key::KeyY => "y",
+        Key::KeyZ => "z",
+        Key::Num0 => "0",
+        Key::Num1 => "1",
+        Key::Num2 => "2",
+        Key::Num3 => "3",
+        Key::Num4 => "4",
+        Key::Num5 => "5",
+        Key::Num6 => "6",
+        Key::Num7 => "7",
+        Key::Num8 => "8",
+        Key::Num9 => "9",
+        Key::BackQuote => "`",
+        Key::Minus => "-",
+        Key::Equal => "=",
+        Key::LeftBracket => "[",
+        Key::RightBracket => "]",
+        Key::SemiColon => ";",
+        Key::Quote => "'",
+        Key::BackSlash | Key::IntlBackslash => "\\",
+        Key::Comma => ",",
+        Key::Dot => ".",
+        Key::Slash => "/",
+        Key::Space => "space",
+        Key::Return | Key::KpReturn => "enter",
+        Key::Tab => "tab",
+        Key::Backspace => "backspace",
+        Key::Escape => "escape",
+        Key::Delete | Key::KpDelete => "delete",
+        Key::Insert => "insert",
+        Key::Home => "home",
+        Key::End => "end",
+        Key::PageUp => "pageup",
+        Key::PageDown => "pagedown",
+        Key::UpArrow => "up",
+        Key::DownArrow => "down",
+        Key::LeftArrow => "left",
+        Key::RightArrow => "right",
+        Key::F1 => "f1",
+        Key::F2 => "f2",
+        Key::F3 => "f3",
+        Key::F4 => "f4",
+        Key::F5 => "f5",
+        Key::F6 => "f6",
+        Key::F7 => "f7",
+        Key::F8 => "f8",
+        Key::F9 => "f9",
+        Key::F10 => "f10",
+        Key::F11 => "f11",
+        Key::F12 => "f12",
+        Key::CapsLock => "capslock",
+        Key::PrintScreen => "printscreen",
+        Key::ScrollLock => "scrolllock",
+        Key::Pause => "pause",
+        Key::NumLock => "numlock",
+        Key::Kp0 => "numpad0",
+        Key::Kp1 => "numpad1",
+        Key::Kp2 => "numpad2",
+        Key::Kp3 => "numpad3",
+        Key::Kp4 => "numpad4",
+        Key::Kp5 => "numpad5",
+        Key::Kp6 => "numpad6",
+        Key::Kp7 => "numpad7",
+        Key::Kp8 => "numpad8",
+        Key::Kp9 => "numpad9",
+        Key::KpPlus => "numpadadd",
+        Key::KpMinus => "numpadsubtract",
+        Key::KpMultiply => "numpadmultiply",
+        Key::KpDivide => "numpaddivide",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Recording in progress; the hook drops events while this is `None`
+static SESSION: Mutex<Option<ScriptBuilder>> = Mutex::new(None);
+
+/// Whether the hook thread is running
+static HOOK_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Why the hook thread exited, reported by the next `stop`
+static HOOK_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn handle_event(event: Event) {
+    if let Some(builder) = lock(&SESSION).as_mut() {
+        builder.push(&event);
+    }
+}
+
+/// Install the global hook on its own thread unless it is already running
+fn ensure_hook() -> Result<(), XenotesterError> {
+    if HOOK_RUNNING.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    *lock(&HOOK_ERROR) = None;
+
+    thread::Builder::new()
+        .name("input-hook".to_string())
+        .spawn(|| {
+            // Only returns when the hook could not be installed
+            if let Err(e) = rdev::listen(handle_event) {
+                error!("Input hook failed: {:?}", e);
+                *lock(&HOOK_ERROR) = Some(format!("{:?}", e));
+            }
+            HOOK_RUNNING.store(false, Ordering::SeqCst);
+        })
+        .map_err(|e| {
+            HOOK_RUNNING.store(false, Ordering::SeqCst);
+            XenotesterError::RecordingError(e.to_string())
+        })?;
+
+    Ok(())
+}
+
+/// Start recording user input
+pub fn start() -> Result<(), XenotesterError> {
+    let mut session = lock(&SESSION);
+    if session.is_some() {
+        return Err(XenotesterError::RecordingError(
+            "An input recording is already in progress".to_string(),
+        ));
+    }
+
+    ensure_hook()?;
+    *session = Some(ScriptBuilder::new(SystemTime::now()));
+    info!("Input recording started");
+    Ok(())
+}
+
+/// Stop recording and return the recorded script
+pub fn stop() -> Result<InputScript, XenotesterError> {
+    let builder = lock(&SESSION).take().ok_or_else(|| {
+        XenotesterError::RecordingError("No input recording in progress".to_string())
+    })?;
+
+    if let Some(error) = lock(&HOOK_ERROR).take() {
+        return Err(XenotesterError::RecordingError(format!(
+            "Input hook could not be installed: {}",
+            error
+        )));
+    }
+
+    let script = builder.finish(SystemTime::now());
+    info!(
+        "Input recording stopped: {} actions in {}ms",
+        script.actions.len(),
+        script.duration_ms
+    );
+    Ok(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn start() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_000)
+    }
+
+    /// Feed synthetic hook events (offset in ms, event type, typed text) to a builder
+    fn build(events: &[(u64, EventType, Option<&str>)]) -> Vec<RecordedAction> {
+        let mut builder = ScriptBuilder::new(start());
+        for (ms, event_type, name) in events {
+            builder.push(&Event {
+                time: start() + Duration::from_millis(*ms),
+                name: name.map(str::to_string),
+                event_type: *event_type,
+            });
+        }
+        builder.finish(start() + Duration::from_secs(5)).actions
+    }
+
+    fn click_at(ms: u64, x: f64, y: f64) -> Vec<(u64, EventType, Option<&'static str>)> {
+        vec![
+            (ms, EventType::MouseMove { x, y }, None),
+            (ms, EventType::ButtonPress(rdev::Button::Left), None),
+            (ms + 50, EventType::ButtonRelease(rdev::Button::Left), None),
+        ]
+    }
+
+    fn key(
+        ms: u64,
+        key: Key,
+        name: Option<&'static str>,
+    ) -> (u64, EventType, Option<&'static str>) {
+        (ms, EventType::KeyPress(key), name)
+    }
+
+    #[test]
+    fn test_single_click() {
+        let actions = build(&click_at(100, 10.4, 19.6));
+        assert_eq!(
+            actions,
+            vec![RecordedAction {
+                at_ms: 100,
+                action: InputAction::Click {
+                    x: 10,
+                    y: 20,
+                    button: MouseButton::Left,
+                    count: 1,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_multi_click() {
+        let events: Vec<_> = [0, 200, 400, 600]
+            .into_iter()
+            .flat_map(|ms| click_at(ms, 10.0, 10.0))
+            .collect();
+        let actions = build(&events);
+
+        // Three clicks fold into a triple click, the fourth starts over
+        let counts: Vec<_> = actions
+            .iter()
+            .map(|a| match a.action {
+                InputAction::Click { count, .. } => (a.at_ms, count),
+                ref other => panic!("unexpected action {:?}", other),
+            })
+            .collect();
+        assert_eq!(counts, vec![(0, 3), (600, 1)]);
+    }
+
+    #[test]
+    fn test_clicks_apart_are_separate() {
+        let mut events = click_at(0, 10.0, 10.0);
+        // Too late for a double click
+        events.extend(click_at(1_000, 10.0, 10.0));
+        // In time, but too far away
+        events.extend(click_at(1_200, 40.0, 10.0));
+        let actions = build(&events);
+
+        assert_eq!(actions.len(), 3);
+        assert!(actions
+            .iter()
+            .all(|a| matches!(a.action, InputAction::Click { count: 1, .. })));
+    }
+
+    #[test]
+    fn test_drag() {
+        let events = [
+            (0, EventType::MouseMove { x: 10.0, y: 10.0 }, None),
+            (10, EventType::ButtonPress(rdev::Button::Left), None),
+            // Jitter within the threshold does not start a drag
+            (20, EventType::MouseMove { x: 13.0, y: 12.0 }, None),
+            (30, EventType::MouseMove { x: 80.0, y: 60.0 }, None),
+            (40, EventType::ButtonRelease(rdev::Button::Left), None),
+        ];
+        assert_eq!(
+            build(&events),
+            vec![RecordedAction {
+                at_ms: 10,
+                action: InputAction::Drag {
+                    button: MouseButton::Left,
+                    start_x: 10,
+                    start_y: 10,
+                    end_x: 80,
+                    end_y: 60,
+                },
+            }]
+        );
+
+        let jitter = [
+            (0, EventType::ButtonPress(rdev::Button::Left), None),
+            (10, EventType::MouseMove { x: 3.0, y: 4.0 }, None),
+            (20, EventType::ButtonRelease(rdev::Button::Left), None),
+        ];
+        assert!(matches!(
+            build(&jitter)[..],
+            [RecordedAction {
+                action: InputAction::Click { x: 0, y: 0, .. },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_scroll_merge() {
+        let wheel_down = EventType::Wheel {
+            delta_x: 0,
+            delta_y: -1,
+        };
+        let events = [
+            (0, EventType::MouseMove { x: 50.0, y: 50.0 }, None),
+            (100, wheel_down, None),
+            (200, wheel_down, None),
+            (400, wheel_down, None),
+            // After a pause
+            (1_000, wheel_down, None),
+            // In time, but elsewhere
+            (1_100, EventType::MouseMove { x: 90.0, y: 50.0 }, None),
+            (1_150, wheel_down, None),
+        ];
+        let step = PIXELS_PER_WHEEL_UNIT as i32;
+        let scrolls: Vec<_> = build(&events)
+            .into_iter()
+            .map(|a| match a.action {
+                InputAction::Scroll { x, dx, dy, .. } => (a.at_ms, x, dx, dy),
+                other => panic!("unexpected action {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            scrolls,
+            vec![
+                (100, 50, 0, 3 * step),
+                (1_000, 50, 0, step),
+                (1_150, 90, 0, step)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_folding() {
+        let events = [
+            (0, EventType::KeyPress(Key::ShiftLeft), None),
+            key(10, Key::KeyH, Some("H")),
+            (20, EventType::KeyRelease(Key::ShiftLeft), None),
+            key(30, Key::KeyI, Some("i")),
+            key(40, Key::Return, Some("\r")),
+            key(50, Key::KeyO, Some("o")),
+            key(60, Key::KeyK, Some("k")),
+        ];
+        let actions = build(&events);
+        assert_eq!(
+            actions,
+            vec![
+                RecordedAction {
+                    at_ms: 10,
+                    action: InputAction::Type { text: "Hi".into() },
+                },
+                RecordedAction {
+                    at_ms: 40,
+                    action: InputAction::Key {
+                        keys: "enter".into(),
+                    },
+                },
+                RecordedAction {
+                    at_ms: 50,
+                    action: InputAction::Type { text: "ok".into() },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shortcuts() {
+        let events = [
+            (0, EventType::KeyPress(Key::ControlLeft), None),
+            (5, EventType::KeyPress(Key::ShiftLeft), None),
+            key(10, Key::KeyS, Some("\u{13}")),
+            (20, EventType::KeyRelease(Key::ShiftLeft), None),
+            key(30, Key::KeyA, Some("a")),
+            (40, EventType::KeyRelease(Key::ControlLeft), None),
+            // AltGr characters are text, not Ctrl+Alt shortcuts
+            (50, EventType::KeyPress(Key::ControlLeft), None),
+            (50, EventType::KeyPress(Key::Alt), None),
+            (50, EventType::KeyPress(Key::AltGr), None),
+            key(60, Key::KeyQ, Some("@")),
+        ];
+        let keys: Vec<_> = build(&events)
+            .into_iter()
+            .map(|a| match a.action {
+                InputAction::Key { keys } => keys,
+                InputAction::Type { text } => format!("type:{}", text),
+                other => panic!("unexpected action {:?}", other),
+            })
+            .collect();
+        assert_eq!(keys, vec!["ctrl+shift+s", "ctrl+a", "type:@"]);
+    }
+
+    #[test]
+    fn test_extra_buttons() {
+        assert_eq!(
+            map_button(rdev::Button::Unknown(8)),
+            Some(MouseButton::Back)
+        );
+        assert_eq!(
+            map_button(rdev::Button::Unknown(2)),
+            Some(MouseButton::Forward)
+        );
+        assert_eq!(map_button(rdev::Button::Unknown(5)), None);
+    }
+}
@@ -0,0 +1,93 @@
+//! Dedicated input executor thread
+//!
+//! Mouse and keyboard operations are submitted through a channel and run one
+//! at a time on a single thread, so concurrent IPC calls can no longer
+//! interleave press/release events (which corrupted drags and key combos).
+//! Submitting is non-blocking; the returned future resolves when the
+//! operation has finished.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::OnceLock;
+use std::thread;
+
+use tokio::sync::oneshot;
+use tracing::error;
+
+use crate::error::XenotesterError;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Queue of the worker thread, spawned on first submission
+static QUEUE: OnceLock<Sender<Job>> = OnceLock::new();
+
+/// Start the worker thread and return its queue
+fn spawn_worker() -> Sender<Job> {
+    let (sender, receiver) = mpsc::channel::<Job>();
+
+    let spawned = thread::Builder::new()
+        .name("input-worker".to_string())
+        .spawn(move || {
+            for job in receiver {
+                // A panicking operation must not take the queue down with it
+                if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                    error!("Operation panicked on the input worker");
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        error!("Failed to start input worker: {}", e);
+    }
+
+    sender
+}
+
+/// Queue an input operation and wait for it to complete
+/// Operations run in submission order; the caller's task is not blocked meanwhile.
+pub async fn submit<T: Send + 'static>(
+    operation: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, XenotesterError> {
+    let (done_tx, done_rx) = oneshot::channel();
+    let job: Job = Box::new(move || {
+        // The submitter may have gone away; nothing to report to then
+        let _ = done_tx.send(operation());
+    });
+
+    QUEUE
+        .get_or_init(spawn_worker)
+        .send(job)
+        .map_err(|_| XenotesterError::InputError("Input worker is not running".to_string()))?;
+
+    done_rx.await.map_err(|_| {
+        XenotesterError::InputError("Input operation was aborted by the worker".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_operations_complete_and_survive_panics() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let first = {
+            let log = log.clone();
+            submit(move || log.lock().unwrap().push(1))
+        };
+        let panicking = submit(|| panic!("boom"));
+        let second = {
+            let log = log.clone();
+            submit(move || {
+                log.lock().unwrap().push(2);
+                42
+            })
+        };
+
+        assert!(first.await.is_ok());
+        assert!(panicking.await.is_err());
+        assert_eq!(second.await.unwrap(), 42);
+        assert_eq!(*log.lock().unwrap(), vec![1, 2]);
+    }
+}
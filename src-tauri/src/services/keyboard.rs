@@ -1,25 +1,412 @@
 //! Keyboard operation service using enigo
+//!
+//! Operations take the run's cancellation token so a stop interrupts long text
+//! input; pressed modifiers are always released before returning. All calls go
+//! through the shared `InputDevice`.
+//!
+//! Characters whose position differs from the US layout (e.g. `@` and `:` on
+//! German or Japanese keyboards) are sent as raw scan codes for the detected or
+//! requested layout, so they don't depend on how the OS maps virtual keys.
 
-use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+use enigo::{Direction, Key, Keyboard};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
 
 use crate::error::XenotesterError;
+use crate::services::input_device::InputDevice;
+use crate::utils::cancel::CancellationToken;
 
-/// Create a new Enigo instance
-fn create_enigo() -> Result<Enigo, XenotesterError> {
-    Enigo::new(&Settings::default()).map_err(|e| XenotesterError::InputError(e.to_string()))
+/// Text is typed in chunks of this many characters, checking for cancellation in between
+const TYPE_CHUNK_CHARS: usize = 16;
+
+/// Options for `type_text`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypingOptions {
+    /// Overrides the detected keyboard layout for characters that need remapping
+    pub layout: Option<KeyboardLayout>,
+    /// Typing speed; characters are sent one at a time when set
+    pub chars_per_second: Option<f64>,
+    /// Fixed delay between characters (takes precedence over `chars_per_second`)
+    pub per_char_delay_ms: Option<u64>,
+}
+
+impl TypingOptions {
+    /// Delay between characters, or None to type as fast as possible
+    fn char_delay(&self) -> Option<Duration> {
+        match (self.per_char_delay_ms, self.chars_per_second) {
+            (Some(ms), _) => Some(Duration::from_millis(ms)),
+            (None, Some(cps)) if cps > 0.0 && cps.is_finite() => {
+                Some(Duration::from_secs_f64(1.0 / cps))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Keyboard layouts with a scan-code table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyboardLayout {
+    /// US QWERTY (no remapping needed)
+    Us,
+    /// German QWERTZ
+    De,
+    /// Japanese JIS
+    Jp,
+}
+
+/// Physical key position as a PC set-1 scan code, plus the modifiers it needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PhysicalKey {
+    scan: u16,
+    shift: bool,
+    alt_gr: bool,
+}
+
+impl PhysicalKey {
+    const fn plain(scan: u16) -> Self {
+        Self { scan, shift: false, alt_gr: false }
+    }
+    const fn shift(scan: u16) -> Self {
+        Self { scan, shift: true, alt_gr: false }
+    }
+    const fn alt_gr(scan: u16) -> Self {
+        Self { scan, shift: false, alt_gr: true }
+    }
+}
+
+/// Layout detected on first use (detection spawns a process, so it is cached)
+static DETECTED_LAYOUT: OnceLock<KeyboardLayout> = OnceLock::new();
+
+/// Detect the active keyboard layout, falling back to US when unknown
+pub fn detect_layout() -> KeyboardLayout {
+    *DETECTED_LAYOUT.get_or_init(|| {
+        let layout = query_layout_id()
+            .map(|id| layout_from_id(&id))
+            .unwrap_or(KeyboardLayout::Us);
+        info!("Detected layout: {:?}", layout);
+        layout
+    })
+}
+
+/// Map a platform layout identifier to a known layout
+/// Accepts Windows KLIDs ("00000407"), macOS input source IDs and XKB layout names
+fn layout_from_id(id: &str) -> KeyboardLayout {
+    let id = id.trim().to_lowercase();
+
+    // Windows KLID: the low word is the language ID
+    if id.len() == 8 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return match &id[4..] {
+            "0407" | "0807" | "0c07" | "1007" | "1407" => KeyboardLayout::De,
+            "0411" => KeyboardLayout::Jp,
+            _ => KeyboardLayout::Us,
+        };
+    }
+
+    let first = id.split([',', '(']).next().unwrap_or("").trim();
+    if first == "de" || id.contains("german") {
+        KeyboardLayout::De
+    } else if first == "jp" || id.contains("japanese") || id.contains("kotoeri") {
+        KeyboardLayout::Jp
+    } else {
+        KeyboardLayout::Us
+    }
+}
+
+/// Read the platform's current layout identifier
+#[cfg(target_os = "windows")]
+fn query_layout_id() -> Option<String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let output = Command::new("reg")
+        .args(["query", r"HKCU\Keyboard Layout\Preload", "/v", "1"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    // "    1    REG_SZ    00000407"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("REG_SZ"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(str::to_string)
+}
+
+#[cfg(target_os = "macos")]
+fn query_layout_id() -> Option<String> {
+    let output = Command::new("defaults")
+        .args([
+            "read",
+            "com.apple.HIToolbox",
+            "AppleCurrentKeyboardLayoutInputSourceID",
+        ])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn query_layout_id() -> Option<String> {
+    let output = Command::new("setxkbmap").arg("-query").output().ok()?;
+    // "layout:     de"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("layout:"))
+        .map(|layout| layout.trim().to_string())
+}
+
+/// Physical key for a character on a layout, if it differs from the US position
+fn physical_key(layout: KeyboardLayout, c: char) -> Option<PhysicalKey> {
+    use PhysicalKey as P;
+
+    let key = match layout {
+        KeyboardLayout::Us => return None,
+        KeyboardLayout::De => match c {
+            'z' => P::plain(0x15),
+            'Z' => P::shift(0x15),
+            'y' => P::plain(0x2C),
+            'Y' => P::shift(0x2C),
+            '"' => P::shift(0x03),
+            '&' => P::shift(0x07),
+            '/' => P::shift(0x08),
+            '(' => P::shift(0x09),
+            ')' => P::shift(0x0A),
+            '=' => P::shift(0x0B),
+            'ß' => P::plain(0x0C),
+            '?' => P::shift(0x0C),
+            '\\' => P::alt_gr(0x0C),
+            '{' => P::alt_gr(0x08),
+            '[' => P::alt_gr(0x09),
+            ']' => P::alt_gr(0x0A),
+            '}' => P::alt_gr(0x0B),
+            '@' => P::alt_gr(0x10),
+            '€' => P::alt_gr(0x12),
+            'ü' => P::plain(0x1A),
+            'Ü' => P::shift(0x1A),
+            '+' => P::plain(0x1B),
+            '*' => P::shift(0x1B),
+            '~' => P::alt_gr(0x1B),
+            'ö' => P::plain(0x27),
+            'Ö' => P::shift(0x27),
+            'ä' => P::plain(0x28),
+            'Ä' => P::shift(0x28),
+            '#' => P::plain(0x2B),
+            '\'' => P::shift(0x2B),
+            ';' => P::shift(0x33),
+            ':' => P::shift(0x34),
+            '-' => P::plain(0x35),
+            '_' => P::shift(0x35),
+            '<' => P::plain(0x56),
+            '>' => P::shift(0x56),
+            '|' => P::alt_gr(0x56),
+            _ => return None,
+        },
+        KeyboardLayout::Jp => match c {
+            '"' => P::shift(0x03),
+            '&' => P::shift(0x07),
+            '\'' => P::shift(0x08),
+            '(' => P::shift(0x09),
+            ')' => P::shift(0x0A),
+            '=' => P::shift(0x0C),
+            '^' => P::plain(0x0D),
+            '~' => P::shift(0x0D),
+            '@' => P::plain(0x1A),
+            '`' => P::shift(0x1A),
+            '[' => P::plain(0x1B),
+            '{' => P::shift(0x1B),
+            ';' => P::plain(0x27),
+            '+' => P::shift(0x27),
+            ':' => P::plain(0x28),
+            '*' => P::shift(0x28),
+            ']' => P::plain(0x2B),
+            '}' => P::shift(0x2B),
+            '\\' => P::plain(0x73),
+            '_' => P::shift(0x73),
+            '¥' => P::plain(0x7D),
+            '|' => P::shift(0x7D),
+            _ => return None,
+        },
+    };
+    Some(key)
+}
+
+/// Platform raw keycode for a set-1 scan code (see `Keyboard::raw`)
+/// macOS layouts don't follow the PC tables, so no raw fallback is used there.
+#[cfg(target_os = "windows")]
+fn raw_keycode(scan: u16) -> Option<u16> {
+    Some(scan)
+}
+
+#[cfg(target_os = "macos")]
+fn raw_keycode(_scan: u16) -> Option<u16> {
+    None
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn raw_keycode(scan: u16) -> Option<u16> {
+    // X keycodes are evdev codes offset by 8. Evdev codes equal set-1 scan codes
+    // in the main block, but the JIS keys are renumbered.
+    let evdev = match scan {
+        0x73 => 89,  // KEY_RO
+        0x7D => 124, // KEY_YEN
+        scan => scan,
+    };
+    Some(evdev + 8)
+}
+
+/// Modifiers that make up AltGr
+#[cfg(target_os = "windows")]
+fn alt_gr_keys() -> Vec<Key> {
+    vec![Key::Control, Key::Alt]
+}
+
+#[cfg(target_os = "macos")]
+fn alt_gr_keys() -> Vec<Key> {
+    vec![Key::Alt]
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn alt_gr_keys() -> Vec<Key> {
+    // ISO_Level3_Shift keysym
+    vec![Key::Other(0xfe03)]
+}
+
+/// Raw keycode and layout modifiers for a character that needs the scan-code path
+fn fallback_key(layout: KeyboardLayout, c: char) -> Option<(u16, Vec<Key>)> {
+    let key = physical_key(layout, c)?;
+    let code = raw_keycode(key.scan)?;
+
+    let mut modifiers = Vec::new();
+    if key.shift {
+        modifiers.push(Key::Shift);
+    }
+    if key.alt_gr {
+        modifiers.extend(alt_gr_keys());
+    }
+    Some((code, modifiers))
+}
+
+/// Click a raw keycode with modifiers held, always releasing the modifiers
+fn click_raw(device: &mut InputDevice, code: u16, modifiers: &[Key]) -> Result<(), XenotesterError> {
+    let mut pressed = 0;
+    let result = (|| {
+        for modifier in modifiers {
+            device.run(|enigo| enigo.key(*modifier, Direction::Press))?;
+            pressed += 1;
+        }
+        device.run(|enigo| enigo.raw(code, Direction::Click))
+    })();
+
+    for modifier in modifiers[..pressed].iter().rev() {
+        device.run(|enigo| enigo.key(*modifier, Direction::Release))?;
+    }
+    result
 }
 
 /// Type text string
-pub fn type_text(text: &str) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
-    enigo
-        .text(text)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))
+/// Without a typing speed the text is sent in chunks as fast as possible; with one,
+/// characters are sent individually with a cancellable delay in between, which
+/// apps with debounced input validation keep up with.
+pub fn type_text(
+    text: &str,
+    options: &TypingOptions,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let layout = options.layout.unwrap_or_else(detect_layout);
+    let delay = options.char_delay();
+    let chunk_size = if delay.is_some() { 1 } else { TYPE_CHUNK_CHARS };
+
+    let mut device = InputDevice::acquire()?;
+    let chars: Vec<char> = text.chars().collect();
+
+    for (i, chunk) in chars.chunks(chunk_size).enumerate() {
+        match delay {
+            Some(delay) if i > 0 => cancel.sleep(delay)?,
+            _ => cancel.check()?,
+        }
+
+        // Send runs of plain characters as text, remapped ones as raw scan codes
+        let mut pending = String::new();
+        for &c in chunk {
+            match fallback_key(layout, c) {
+                Some((code, modifiers)) => {
+                    if !pending.is_empty() {
+                        device.run(|enigo| enigo.text(&pending))?;
+                        pending.clear();
+                    }
+                    click_raw(&mut device, code, &modifiers)?;
+                }
+                None => pending.push(c),
+            }
+        }
+        if !pending.is_empty() {
+            device.run(|enigo| enigo.text(&pending))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Platform paste shortcut
+#[cfg(target_os = "macos")]
+const PASTE_SHORTCUT: &str = "cmd+v";
+#[cfg(not(target_os = "macos"))]
+const PASTE_SHORTCUT: &str = "ctrl+v";
+
+/// Time for the target app to read the clipboard before it is restored
+const PASTE_SETTLE_DELAY_MS: u64 = 150;
+
+/// Enter text by pasting it from the clipboard
+/// Works for IME-dependent scripts (CJK) and emoji that `type_text` can mangle.
+/// With `restore_clipboard`, previous text content is put back afterwards
+/// (non-text clipboard content such as images cannot be restored).
+pub fn paste_text(
+    text: &str,
+    restore_clipboard: bool,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    cancel.check()?;
+
+    let clipboard_error =
+        |e: arboard::Error| XenotesterError::InputError(format!("Clipboard error: {}", e));
+    // Must stay alive until the paste is done: on Linux the content is served by this instance
+    let mut clipboard = arboard::Clipboard::new().map_err(clipboard_error)?;
+    let previous = if restore_clipboard {
+        clipboard.get_text().ok()
+    } else {
+        None
+    };
+
+    clipboard.set_text(text).map_err(clipboard_error)?;
+    let pasted = key_combination(PASTE_SHORTCUT, None, cancel);
+    thread::sleep(Duration::from_millis(PASTE_SETTLE_DELAY_MS));
+
+    if let Some(previous) = previous {
+        if let Err(e) = clipboard.set_text(previous) {
+            warn!("Failed to restore clipboard: {}", e);
+        }
+    }
+
+    pasted
 }
 
 /// Press a key combination (e.g., "ctrl+s", "cmd+shift+p")
-pub fn key_combination(key_str: &str) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
+/// `layout` overrides the detected keyboard layout for a remapped main key (e.g. "ctrl+@").
+pub fn key_combination(
+    key_str: &str,
+    layout: Option<KeyboardLayout>,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    cancel.check()?;
+    let layout = layout.unwrap_or_else(detect_layout);
+    let mut device = InputDevice::acquire()?;
 
     // Parse key parts into owned Strings to avoid borrow issues
     let parts: Vec<String> = key_str
@@ -29,8 +416,17 @@ pub fn key_combination(key_str: &str) -> Result<(), XenotesterError> {
 
     let mut modifiers: Vec<Key> = Vec::new();
     let mut main_key: Option<Key> = None;
+    let mut raw_key: Option<(u16, Vec<Key>)> = None;
 
     for part in &parts {
+        let mut chars = part.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if let Some(fallback) = fallback_key(layout, c) {
+                raw_key = Some(fallback);
+                continue;
+            }
+        }
+
         let key = parse_key(part)?;
         if is_modifier(part) {
             modifiers.push(key);
@@ -39,33 +435,41 @@ pub fn key_combination(key_str: &str) -> Result<(), XenotesterError> {
         }
     }
 
-    // Press modifiers
-    for modifier in &modifiers {
-        enigo
-            .key(*modifier, Direction::Press)
-            .map_err(|e| XenotesterError::InputError(e.to_string()))?;
-    }
+    // Press modifiers, then press and release the main key
+    let pressed = (|| {
+        for modifier in &modifiers {
+            device.run(|enigo| enigo.key(*modifier, Direction::Press))?;
+        }
 
-    // Press and release main key
-    if let Some(key) = main_key {
-        enigo
-            .key(key, Direction::Click)
-            .map_err(|e| XenotesterError::InputError(e.to_string()))?;
-    }
+        if let Some((code, layout_modifiers)) = &raw_key {
+            cancel.check()?;
+            click_raw(&mut device, *code, layout_modifiers)?;
+        } else if let Some(key) = main_key {
+            cancel.check()?;
+            device.run(|enigo| enigo.key(key, Direction::Click))?;
+        }
+        Ok(())
+    })();
 
-    // Release modifiers in reverse order
+    // Release modifiers in reverse order, even if pressing failed or was cancelled
     for modifier in modifiers.iter().rev() {
-        enigo
-            .key(*modifier, Direction::Release)
-            .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+        device.run(|enigo| enigo.key(*modifier, Direction::Release))?;
     }
 
-    Ok(())
+    pressed
 }
 
 /// Hold a key (press without release)
-pub fn hold_key(key_str: &str, press: bool) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
+/// Only pressing is cancellable; a release always goes through
+pub fn hold_key(
+    key_str: &str,
+    press: bool,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    if press {
+        cancel.check()?;
+    }
+    let mut device = InputDevice::acquire()?;
     let key = parse_key(key_str)?;
 
     let direction = if press {
@@ -74,74 +478,276 @@ pub fn hold_key(key_str: &str, press: bool) -> Result<(), XenotesterError> {
         Direction::Release
     };
 
-    enigo
-        .key(key, direction)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))
+    device.run(|enigo| enigo.key(key, direction))
+}
+
+/// Named key: accepted names (the first is canonical) and the enigo key
+type NamedKey = (&'static [&'static str], Key);
+
+/// Modifier names accepted in key combinations
+const MODIFIER_KEYS: &[NamedKey] = &[
+    (&["ctrl", "control"], Key::Control),
+    (&["alt", "option"], Key::Alt),
+    (&["shift"], Key::Shift),
+    (&["cmd", "command", "meta", "super", "win"], Key::Meta),
+];
+
+/// Named non-modifier keys available on every platform
+const COMMON_KEYS: &[NamedKey] = &[
+    // Function keys
+    (&["f1"], Key::F1),
+    (&["f2"], Key::F2),
+    (&["f3"], Key::F3),
+    (&["f4"], Key::F4),
+    (&["f5"], Key::F5),
+    (&["f6"], Key::F6),
+    (&["f7"], Key::F7),
+    (&["f8"], Key::F8),
+    (&["f9"], Key::F9),
+    (&["f10"], Key::F10),
+    (&["f11"], Key::F11),
+    (&["f12"], Key::F12),
+    (&["f13"], Key::F13),
+    (&["f14"], Key::F14),
+    (&["f15"], Key::F15),
+    (&["f16"], Key::F16),
+    (&["f17"], Key::F17),
+    (&["f18"], Key::F18),
+    (&["f19"], Key::F19),
+    (&["f20"], Key::F20),
+    // Navigation keys
+    (&["up", "arrowup"], Key::UpArrow),
+    (&["down", "arrowdown"], Key::DownArrow),
+    (&["left", "arrowleft"], Key::LeftArrow),
+    (&["right", "arrowright"], Key::RightArrow),
+    (&["home"], Key::Home),
+    (&["end"], Key::End),
+    (&["pageup", "page_up"], Key::PageUp),
+    (&["pagedown", "page_down"], Key::PageDown),
+    // Special keys
+    (&["enter", "return"], Key::Return),
+    (&["tab"], Key::Tab),
+    (&["space"], Key::Space),
+    (&["backspace"], Key::Backspace),
+    (&["delete", "del"], Key::Delete),
+    (&["escape", "esc"], Key::Escape),
+    (&["capslock", "caps_lock"], Key::CapsLock),
+    (&["help"], Key::Help),
+    // Numpad
+    (&["numpad0", "num0"], Key::Numpad0),
+    (&["numpad1", "num1"], Key::Numpad1),
+    (&["numpad2", "num2"], Key::Numpad2),
+    (&["numpad3", "num3"], Key::Numpad3),
+    (&["numpad4", "num4"], Key::Numpad4),
+    (&["numpad5", "num5"], Key::Numpad5),
+    (&["numpad6", "num6"], Key::Numpad6),
+    (&["numpad7", "num7"], Key::Numpad7),
+    (&["numpad8", "num8"], Key::Numpad8),
+    (&["numpad9", "num9"], Key::Numpad9),
+    (&["numpadadd", "add"], Key::Add),
+    (&["numpadsubtract", "subtract"], Key::Subtract),
+    (&["numpadmultiply", "multiply"], Key::Multiply),
+    (&["numpaddivide", "divide"], Key::Divide),
+    (&["numpaddecimal", "decimal"], Key::Decimal),
+    // Media keys
+    (&["volumeup", "audiovolumeup"], Key::VolumeUp),
+    (&["volumedown", "audiovolumedown"], Key::VolumeDown),
+    (&["volumemute", "audiovolumemute", "mute"], Key::VolumeMute),
+    (&["mediaplaypause", "playpause"], Key::MediaPlayPause),
+    (&["medianexttrack", "nexttrack"], Key::MediaNextTrack),
+    (&["mediaprevtrack", "prevtrack"], Key::MediaPrevTrack),
+];
+
+/// Named keys that macOS keyboards don't have
+#[cfg(not(target_os = "macos"))]
+const PC_KEYS: &[NamedKey] = &[
+    (&["f21"], Key::F21),
+    (&["f22"], Key::F22),
+    (&["f23"], Key::F23),
+    (&["f24"], Key::F24),
+    (&["insert", "ins"], Key::Insert),
+    (&["printscreen", "printscr", "print", "prtsc"], Key::PrintScr),
+    (&["pause", "break"], Key::Pause),
+    (&["numlock", "num_lock"], Key::Numlock),
+    (&["select"], Key::Select),
+    (&["mediastop"], Key::MediaStop),
+];
+#[cfg(target_os = "macos")]
+const PC_KEYS: &[NamedKey] = &[];
+
+/// Named keys specific to the current platform
+#[cfg(target_os = "windows")]
+const PLATFORM_KEYS: &[NamedKey] = &[(&["menu", "apps", "contextmenu"], Key::Apps)];
+#[cfg(all(unix, not(target_os = "macos")))]
+const PLATFORM_KEYS: &[NamedKey] = &[
+    // XK_Menu keysym
+    (&["menu", "apps", "contextmenu"], Key::Other(0xff67)),
+    (&["scrolllock", "scroll_lock"], Key::ScrollLock),
+];
+#[cfg(target_os = "macos")]
+const PLATFORM_KEYS: &[NamedKey] = &[];
+
+/// Supported key name as reported to the frontend / LLM
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyName {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub modifier: bool,
+}
+
+/// All named keys usable on this platform
+/// Single characters (e.g. "a", "@") are accepted in addition to these.
+pub fn supported_keys() -> Vec<KeyName> {
+    let named = |modifier: bool| {
+        move |(names, _): &NamedKey| KeyName {
+            name: names[0].to_string(),
+            aliases: names[1..].iter().map(|n| n.to_string()).collect(),
+            modifier,
+        }
+    };
+
+    MODIFIER_KEYS
+        .iter()
+        .map(named(true))
+        .chain(
+            [COMMON_KEYS, PC_KEYS, PLATFORM_KEYS]
+                .concat()
+                .iter()
+                .map(named(false)),
+        )
+        .collect()
+}
+
+/// Look up a named key in a table
+fn find_key(table: &[NamedKey], name: &str) -> Option<Key> {
+    table
+        .iter()
+        .find(|(names, _)| names.contains(&name))
+        .map(|(_, key)| *key)
+}
+
+/// Canonical lowercase name of a key (e.g. "Command" -> "cmd")
+/// Names that are not in the key tables are only lowercased.
+pub fn canonical_key_name(key_str: &str) -> String {
+    let name = key_str.trim().to_lowercase();
+    [MODIFIER_KEYS, COMMON_KEYS, PC_KEYS, PLATFORM_KEYS]
+        .into_iter()
+        .flatten()
+        .find(|(names, _)| names.contains(&name.as_str()))
+        .map(|(names, _)| names[0].to_string())
+        .unwrap_or(name)
 }
 
 /// Check if a key string represents a modifier
 fn is_modifier(key_str: &str) -> bool {
-    matches!(
-        key_str.to_lowercase().as_str(),
-        "ctrl" | "control" | "alt" | "option" | "shift" | "cmd" | "command" | "meta" | "super" | "win"
-    )
+    find_key(MODIFIER_KEYS, &key_str.to_lowercase()).is_some()
 }
 
 /// Parse a key string to enigo Key
 fn parse_key(key_str: &str) -> Result<Key, XenotesterError> {
-    let key = match key_str.to_lowercase().as_str() {
-        // Modifiers
-        "ctrl" | "control" => Key::Control,
-        "alt" | "option" => Key::Alt,
-        "shift" => Key::Shift,
-        "cmd" | "command" | "meta" | "super" | "win" => Key::Meta,
-
-        // Function keys
-        "f1" => Key::F1,
-        "f2" => Key::F2,
-        "f3" => Key::F3,
-        "f4" => Key::F4,
-        "f5" => Key::F5,
-        "f6" => Key::F6,
-        "f7" => Key::F7,
-        "f8" => Key::F8,
-        "f9" => Key::F9,
-        "f10" => Key::F10,
-        "f11" => Key::F11,
-        "f12" => Key::F12,
-
-        // Navigation keys
-        "up" | "arrowup" => Key::UpArrow,
-        "down" | "arrowdown" => Key::DownArrow,
-        "left" | "arrowleft" => Key::LeftArrow,
-        "right" | "arrowright" => Key::RightArrow,
-        "home" => Key::Home,
-        "end" => Key::End,
-        "pageup" | "page_up" => Key::PageUp,
-        "pagedown" | "page_down" => Key::PageDown,
-
-        // Special keys
-        "enter" | "return" => Key::Return,
-        "tab" => Key::Tab,
-        "space" => Key::Space,
-        "backspace" => Key::Backspace,
-        "delete" | "del" => Key::Delete,
-        "escape" | "esc" => Key::Escape,
-        "capslock" | "caps_lock" => Key::CapsLock,
-
-        // Single characters
-        s if s.len() == 1 => {
-            let c = s.chars().next().unwrap();
-            Key::Unicode(c)
-        }
+    let name = key_str.to_lowercase();
 
-        _ => {
-            return Err(XenotesterError::InputError(format!(
-                "Unknown key: {}",
-                key_str
-            )))
-        }
-    };
+    let named = [MODIFIER_KEYS, COMMON_KEYS, PC_KEYS, PLATFORM_KEYS]
+        .into_iter()
+        .find_map(|table| find_key(table, &name));
+    if let Some(key) = named {
+        return Ok(key);
+    }
+
+    // Single characters
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Key::Unicode(c)),
+        _ => Err(XenotesterError::InputError(format!(
+            "Unknown key: {} (see list_supported_keys)",
+            key_str
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_from_id() {
+        assert_eq!(layout_from_id("00000407"), KeyboardLayout::De);
+        assert_eq!(layout_from_id("00000807"), KeyboardLayout::De);
+        assert_eq!(layout_from_id("00000411"), KeyboardLayout::Jp);
+        assert_eq!(layout_from_id("00000409"), KeyboardLayout::Us);
+        assert_eq!(
+            layout_from_id("com.apple.keylayout.German"),
+            KeyboardLayout::De
+        );
+        assert_eq!(
+            layout_from_id("com.apple.inputmethod.Kotoeri.RomajiTyping.Japanese"),
+            KeyboardLayout::Jp
+        );
+        assert_eq!(layout_from_id("de(nodeadkeys)"), KeyboardLayout::De);
+        assert_eq!(layout_from_id(" jp,us\n"), KeyboardLayout::Jp);
+        assert_eq!(layout_from_id("us,de"), KeyboardLayout::Us);
+        assert_eq!(layout_from_id("fr"), KeyboardLayout::Us);
+    }
 
-    Ok(key)
+    #[test]
+    fn test_physical_key() {
+        assert_eq!(physical_key(KeyboardLayout::Us, '@'), None);
+        assert_eq!(physical_key(KeyboardLayout::De, 'a'), None);
+        assert_eq!(
+            physical_key(KeyboardLayout::De, 'Z'),
+            Some(PhysicalKey::shift(0x15))
+        );
+        assert_eq!(
+            physical_key(KeyboardLayout::De, '@'),
+            Some(PhysicalKey::alt_gr(0x10))
+        );
+        assert_eq!(
+            physical_key(KeyboardLayout::Jp, '@'),
+            Some(PhysicalKey::plain(0x1A))
+        );
+        assert_eq!(
+            physical_key(KeyboardLayout::Jp, '_'),
+            Some(PhysicalKey::shift(0x73))
+        );
+    }
+
+    #[test]
+    fn test_fallback_key() {
+        assert_eq!(fallback_key(KeyboardLayout::Us, '@'), None);
+        assert_eq!(fallback_key(KeyboardLayout::De, 'a'), None);
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn test_fallback_key_x11() {
+        assert_eq!(
+            fallback_key(KeyboardLayout::De, 'ö'),
+            Some((0x27 + 8, vec![]))
+        );
+        assert_eq!(
+            fallback_key(KeyboardLayout::De, '@'),
+            Some((0x10 + 8, vec![Key::Other(0xfe03)]))
+        );
+        // JIS keys map to KEY_RO (89) and KEY_YEN (124), not their scan codes
+        assert_eq!(fallback_key(KeyboardLayout::Jp, '\\'), Some((97, vec![])));
+        assert_eq!(
+            fallback_key(KeyboardLayout::Jp, '_'),
+            Some((97, vec![Key::Shift]))
+        );
+        assert_eq!(
+            fallback_key(KeyboardLayout::Jp, '|'),
+            Some((132, vec![Key::Shift]))
+        );
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_fallback_key_windows() {
+        assert_eq!(
+            fallback_key(KeyboardLayout::De, '@'),
+            Some((0x10, vec![Key::Control, Key::Alt]))
+        );
+        assert_eq!(fallback_key(KeyboardLayout::Jp, '¥'), Some((0x7D, vec![])));
+    }
 }
@@ -0,0 +1,183 @@
+//! Master key storage for encrypted secrets
+//!
+//! The 256-bit key is generated on first use and kept in the macOS Keychain
+//! or the Windows Credential Manager. Other platforms have no credential
+//! store integration yet and keep the key in a file readable only by the
+//! user in the app data directory.
+
+use ring::rand::{SecureRandom, SystemRandom};
+use std::sync::Mutex;
+
+use crate::error::XenotesterError;
+
+/// Length of the AES-256 key in bytes
+pub const KEY_LEN: usize = 32;
+
+/// Keychain service name (the bundle identifier)
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+const SERVICE: &str = "com.satoshizerocolored.xenotester";
+
+/// Keychain account of the master key
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+const ACCOUNT: &str = "secrets-master-key";
+
+/// Key loaded in this process, so the credential store is queried only once
+static CACHED_KEY: Mutex<Option<[u8; KEY_LEN]>> = Mutex::new(None);
+
+/// Get the master key, creating and storing it on first use
+pub fn master_key() -> Result<[u8; KEY_LEN], XenotesterError> {
+    let mut cached = CACHED_KEY
+        .lock()
+        .map_err(|e| XenotesterError::SecretError(e.to_string()))?;
+    if let Some(key) = *cached {
+        return Ok(key);
+    }
+
+    let key = match platform::load()? {
+        Some(stored) => <[u8; KEY_LEN]>::try_from(stored.as_slice()).map_err(|_| {
+            XenotesterError::SecretError("Stored master key has an invalid length".to_string())
+        })?,
+        None => {
+            let mut key = [0u8; KEY_LEN];
+            SystemRandom::new()
+                .fill(&mut key)
+                .map_err(|_| XenotesterError::SecretError("Failed to generate key".to_string()))?;
+            platform::store(&key)?;
+            tracing::info!("Created secrets master key");
+            key
+        }
+    };
+
+    *cached = Some(key);
+    Ok(key)
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use security_framework::passwords::{get_generic_password, set_generic_password};
+
+    use super::{ACCOUNT, SERVICE};
+    use crate::error::XenotesterError;
+
+    /// Status returned when the keychain item does not exist
+    const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+    pub fn load() -> Result<Option<Vec<u8>>, XenotesterError> {
+        match get_generic_password(SERVICE, ACCOUNT) {
+            Ok(key) => Ok(Some(key)),
+            Err(e) if e.code() == ERR_SEC_ITEM_NOT_FOUND => Ok(None),
+            Err(e) => Err(XenotesterError::SecretError(format!(
+                "Keychain read failed: {}",
+                e
+            ))),
+        }
+    }
+
+    pub fn store(key: &[u8]) -> Result<(), XenotesterError> {
+        set_generic_password(SERVICE, ACCOUNT, key)
+            .map_err(|e| XenotesterError::SecretError(format!("Keychain write failed: {}", e)))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::core::{HSTRING, PWSTR};
+    use windows::Win32::Foundation::ERROR_NOT_FOUND;
+    use windows::Win32::Security::Credentials::{
+        CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_FLAGS, CRED_PERSIST_LOCAL_MACHINE,
+        CRED_TYPE_GENERIC,
+    };
+
+    use crate::error::XenotesterError;
+
+    /// Credential Manager target name of the master key
+    const TARGET: &str = "com.satoshizerocolored.xenotester/secrets-master-key";
+
+    pub fn load() -> Result<Option<Vec<u8>>, XenotesterError> {
+        let target = HSTRING::from(TARGET);
+        let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+        // SAFETY: on success `credential` points to a buffer owned by the system,
+        // which is copied and released with CredFree
+        unsafe {
+            match CredReadW(&target, CRED_TYPE_GENERIC, None, &mut credential) {
+                Ok(()) => {
+                    let blob = std::slice::from_raw_parts(
+                        (*credential).CredentialBlob,
+                        (*credential).CredentialBlobSize as usize,
+                    )
+                    .to_vec();
+                    CredFree(credential as *const _);
+                    Ok(Some(blob))
+                }
+                Err(e) if e.code() == ERROR_NOT_FOUND.to_hresult() => Ok(None),
+                Err(e) => Err(XenotesterError::SecretError(format!(
+                    "Credential Manager read failed: {}",
+                    e
+                ))),
+            }
+        }
+    }
+
+    pub fn store(key: &[u8]) -> Result<(), XenotesterError> {
+        let mut target: Vec<u16> = TARGET.encode_utf16().chain(std::iter::once(0)).collect();
+        let mut blob = key.to_vec();
+        let credential = CREDENTIALW {
+            Flags: CRED_FLAGS(0),
+            Type: CRED_TYPE_GENERIC,
+            TargetName: PWSTR(target.as_mut_ptr()),
+            CredentialBlobSize: blob.len() as u32,
+            CredentialBlob: blob.as_mut_ptr(),
+            Persist: CRED_PERSIST_LOCAL_MACHINE,
+            ..Default::default()
+        };
+        // SAFETY: the strings and blob outlive the call
+        unsafe { CredWriteW(&credential, 0) }.map_err(|e| {
+            XenotesterError::SecretError(format!("Credential Manager write failed: {}", e))
+        })
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{ACCOUNT, SERVICE};
+    use crate::error::XenotesterError;
+
+    fn key_path() -> Result<PathBuf, XenotesterError> {
+        dirs::data_local_dir()
+            .map(|dir| dir.join(SERVICE).join(format!("{}.key", ACCOUNT)))
+            .ok_or_else(|| {
+                XenotesterError::SecretError("Cannot locate the data directory".to_string())
+            })
+    }
+
+    pub fn load() -> Result<Option<Vec<u8>>, XenotesterError> {
+        match fs::read(key_path()?) {
+            Ok(key) => Ok(Some(key)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(XenotesterError::SecretError(format!(
+                "Key file read failed: {}",
+                e
+            ))),
+        }
+    }
+
+    pub fn store(key: &[u8]) -> Result<(), XenotesterError> {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let path = key_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .and_then(|mut file| file.write_all(key))
+            .map_err(|e| XenotesterError::SecretError(format!("Key file write failed: {}", e)))
+    }
+}
@@ -0,0 +1,1049 @@
+//! Anthropic Computer Use client
+//!
+//! Implements the Messages API conversation loop natively: send the current
+//! screenshot, receive `computer` tool calls, execute them via the input
+//! services, and reply with tool results plus a fresh screenshot. The session
+//! runs against any `AgentBackend`; its message types are the shared format.
+//!
+//! Screenshots identical to the previous one are reported as text instead of
+//! sent again, and requests mark the system prompt and latest turns for
+//! prompt caching, so each turn pays full price only for what is new.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::error::XenotesterError;
+use crate::services::action_log::{self, ActionRecord};
+use crate::services::capture::{capture_primary_monitor, CaptureOptions, CaptureResult};
+use crate::services::capture_history::CaptureHistory;
+use crate::services::computer_action::{execute_action, to_screen_point, ComputerAction};
+use crate::services::governor::ActionGuard;
+use crate::services::http;
+use crate::services::image_processor::{ImageEncoding, ResizeQuality};
+use crate::services::input_worker;
+use crate::services::llm::{api_error, read_sse, AgentBackend, BackendFuture, Provider};
+use crate::services::mouse;
+use crate::services::variables::Variables;
+use crate::utils::cancel::CancellationToken;
+
+/// Default API endpoint (override with ANTHROPIC_BASE_URL)
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// Matches the frontend's Computer Use request size
+const MAX_TOKENS: u32 = 4096;
+const REQUEST_TIMEOUT_SECS: u64 = 120;
+/// Most recent user turns marked as prompt cache breakpoints, besides the system prompt
+/// The API allows four breakpoints; marking the previous turn too lets a
+/// request read the prefix the previous request wrote.
+const CACHED_USER_TURNS: usize = 2;
+/// Tool result text replacing a screenshot identical to the previous one, pixel for pixel
+const UNCHANGED_SCREEN_TEXT: &str = "The screen has not changed since the last screenshot.";
+
+/// Default system prompt when the caller doesn't provide one
+const DEFAULT_SYSTEM_PROMPT: &str = "You are an E2E test automation agent operating a desktop computer. \
+Carry out the user's test scenario step by step using the computer tool. \
+When the scenario is complete, or cannot be completed, stop calling tools and report the outcome.";
+
+/// Model configuration for Computer Use (mirrors the frontend's ClaudeModelConfig)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelConfig {
+    /// API the model is served by (Anthropic by default)
+    #[serde(default)]
+    pub provider: Provider,
+    /// Model ID
+    pub model: String,
+    /// Value of the `anthropic-beta` header
+    pub beta_header: String,
+    /// Computer tool type version
+    pub tool_type: String,
+    /// Enable zoom action (computer_20251124 only)
+    #[serde(default)]
+    pub enable_zoom: bool,
+    /// Screenshot encoding sent to the API (PNG by default)
+    #[serde(default)]
+    pub image_encoding: ImageEncoding,
+    /// Draw the mouse pointer onto screenshots so the model can track it
+    #[serde(default)]
+    pub include_cursor: bool,
+}
+
+impl ModelConfig {
+    /// Default configuration for a model, with the provider that serves it
+    pub fn for_model(model: String) -> Self {
+        Self {
+            provider: Provider::for_model(&model),
+            model,
+            ..Self::default()
+        }
+    }
+
+    /// Capture options for screenshots sent to the model
+    fn capture_options(&self) -> CaptureOptions {
+        CaptureOptions {
+            encoding: self.image_encoding,
+            include_cursor: self.include_cursor,
+            resize_quality: Some(ResizeQuality::High),
+        }
+    }
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        Self {
+            provider: Provider::Anthropic,
+            model: "claude-opus-4-5-20251101".to_string(),
+            beta_header: "computer-use-2025-11-24".to_string(),
+            tool_type: "computer_20251124".to_string(),
+            enable_zoom: false,
+            image_encoding: ImageEncoding::default(),
+            include_cursor: false,
+        }
+    }
+}
+
+/// Conversation role
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+/// Image source for image content blocks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageSource {
+    #[serde(rename = "type")]
+    pub source_type: String,
+    pub media_type: String,
+    pub data: String,
+}
+
+impl ImageSource {
+    /// Base64 PNG image source
+    pub fn png(data: String) -> Self {
+        Self::base64("image/png", data)
+    }
+
+    /// Base64 image source with an explicit media type
+    pub fn base64(media_type: &str, data: String) -> Self {
+        Self {
+            source_type: "base64".to_string(),
+            media_type: media_type.to_string(),
+            data,
+        }
+    }
+
+    /// Image source for a screen capture, using its encoding
+    pub fn from_capture(capture: &CaptureResult) -> Self {
+        Self::base64(&capture.media_type, capture.image_base64.clone())
+    }
+}
+
+/// Screenshot blocks for a capture: the image plus the focused window, when known
+fn capture_blocks(capture: &CaptureResult) -> Vec<ContentBlock> {
+    let mut blocks = vec![ContentBlock::Image {
+        source: ImageSource::from_capture(capture),
+    }];
+    blocks.extend(capture.active_window.as_ref().map(|window| ContentBlock::Text {
+        text: window.describe(),
+    }));
+    blocks
+}
+
+/// Message content block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: ImageSource,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: Vec<ContentBlock>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        is_error: Option<bool>,
+    },
+    /// Block types this client doesn't handle (e.g., thinking); dropped from history
+    #[serde(other)]
+    Unsupported,
+}
+
+/// Conversation message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: Vec<ContentBlock>,
+}
+
+/// Token usage reported by the API
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    /// Screenshots not sent because the screen had not changed (counted by the session)
+    pub screenshots_skipped: u64,
+    /// Estimated input tokens of the skipped screenshots
+    pub skipped_image_tokens: u64,
+}
+
+impl Usage {
+    /// Accumulate another response's usage into this total
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.screenshots_skipped += other.screenshots_skipped;
+        self.skipped_image_tokens += other.skipped_image_tokens;
+    }
+
+    /// Count a screenshot left out of the conversation
+    fn add_skipped_screenshot(&mut self, capture: &CaptureResult) {
+        self.screenshots_skipped += 1;
+        self.skipped_image_tokens += image_tokens(capture.resized_width, capture.resized_height);
+    }
+}
+
+/// Approximate input tokens of an image (Anthropic's width * height / 750)
+fn image_tokens(width: u32, height: u32) -> u64 {
+    u64::from(width) * u64::from(height) / 750
+}
+
+/// Messages API response
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessagesResponse {
+    pub content: Vec<ContentBlock>,
+    pub stop_reason: Option<String>,
+    #[serde(default)]
+    pub usage: Usage,
+}
+
+/// HTTP client for the Anthropic Messages API
+pub struct AnthropicClient {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl AnthropicClient {
+    /// Create a client from ANTHROPIC_API_KEY / ANTHROPIC_BASE_URL
+    pub fn from_env() -> Result<Self, XenotesterError> {
+        let api_key = env::var("ANTHROPIC_API_KEY").map_err(|_| {
+            XenotesterError::ConfigError("ANTHROPIC_API_KEY is not set in environment".to_string())
+        })?;
+        let base_url = env::var("ANTHROPIC_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+
+        let http = http::client()?;
+
+        Ok(Self {
+            http,
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Build the Messages API request body for a Computer Use turn
+    fn build_request_body(
+        config: &ModelConfig,
+        system_prompt: &str,
+        display_size: (u32, u32),
+        messages: &[Message],
+        stream: bool,
+    ) -> Value {
+        let mut tool = json!({
+            "type": config.tool_type,
+            "name": "computer",
+            "display_width_px": display_size.0,
+            "display_height_px": display_size.1,
+            "display_number": 1,
+        });
+        if config.enable_zoom {
+            tool["enable_zoom"] = json!(true);
+        }
+
+        // The system breakpoint caches the tool definition, which precedes it
+        let mut messages = json!(messages);
+        mark_cache_breakpoints(&mut messages);
+
+        json!({
+            "model": config.model,
+            "max_tokens": MAX_TOKENS,
+            "system": [{
+                "type": "text",
+                "text": system_prompt,
+                "cache_control": { "type": "ephemeral" },
+            }],
+            "tools": [tool],
+            "messages": messages,
+            "stream": stream,
+        })
+    }
+
+    /// POST a request body and return the response, mapping API errors
+    async fn send(
+        &self,
+        config: &ModelConfig,
+        body: &Value,
+    ) -> Result<reqwest::Response, XenotesterError> {
+        let response = self
+            .http
+            .post(format!("{}/v1/messages", self.base_url))
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("anthropic-beta", &config.beta_header)
+            .json(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        Ok(response)
+    }
+
+    /// Send a Computer Use request with the given conversation
+    pub async fn create_message(
+        &self,
+        config: &ModelConfig,
+        system_prompt: &str,
+        display_size: (u32, u32),
+        messages: &[Message],
+    ) -> Result<MessagesResponse, XenotesterError> {
+        let body = Self::build_request_body(config, system_prompt, display_size, messages, false);
+
+        self.send(config, &body)
+            .await?
+            .json::<MessagesResponse>()
+            .await
+            .map_err(|e| XenotesterError::LlmError(format!("Invalid API response: {}", e)))
+    }
+
+    /// Send a Computer Use request with streaming enabled
+    ///
+    /// `on_event` is called as text tokens and completed tool calls arrive.
+    /// The stream is abandoned with `Cancelled` as soon as the run is cancelled.
+    pub async fn create_message_streaming(
+        &self,
+        config: &ModelConfig,
+        system_prompt: &str,
+        display_size: (u32, u32),
+        messages: &[Message],
+        cancel: &CancellationToken,
+        on_event: &mut (dyn FnMut(StreamEvent) + Send),
+    ) -> Result<MessagesResponse, XenotesterError> {
+        let body = Self::build_request_body(config, system_prompt, display_size, messages, true);
+        let response = self.send(config, &body).await?;
+
+        let mut assembler = StreamAssembler::default();
+        read_sse(response, cancel, &mut |data| {
+            assembler.handle(data, on_event)
+        })
+        .await?;
+        Ok(assembler.finish())
+    }
+}
+
+impl AgentBackend for AnthropicClient {
+    fn create_message<'a>(
+        &'a self,
+        config: &'a ModelConfig,
+        system_prompt: &'a str,
+        display_size: (u32, u32),
+        messages: &'a [Message],
+        cancel: &'a CancellationToken,
+        on_event: &'a mut (dyn FnMut(StreamEvent) + Send),
+    ) -> BackendFuture<'a> {
+        Box::pin(self.create_message_streaming(
+            config,
+            system_prompt,
+            display_size,
+            messages,
+            cancel,
+            on_event,
+        ))
+    }
+}
+
+/// Mark the last block of the most recent user turns as prompt cache breakpoints
+/// Each turn's request then reads the conversation so far from the cache
+/// instead of paying for every earlier screenshot again.
+fn mark_cache_breakpoints(messages: &mut Value) {
+    let Some(messages) = messages.as_array_mut() else {
+        return;
+    };
+    for message in messages
+        .iter_mut()
+        .rev()
+        .filter(|message| message["role"] == "user")
+        .take(CACHED_USER_TURNS)
+    {
+        if let Some(block) = message["content"].as_array_mut().and_then(|c| c.last_mut()) {
+            block["cache_control"] = json!({ "type": "ephemeral" });
+        }
+    }
+}
+
+/// Incremental output reported while a response is streaming
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// Text delta from the model
+    Token(String),
+    /// Fully received tool call
+    ToolCall { id: String, name: String, input: Value },
+}
+
+/// Content block being assembled from stream deltas
+enum PartialBlock {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input_json: String,
+    },
+    Unsupported,
+}
+
+/// Rebuilds a complete `MessagesResponse` from server-sent events
+#[derive(Default)]
+struct StreamAssembler {
+    blocks: Vec<PartialBlock>,
+    stop_reason: Option<String>,
+    usage: Usage,
+}
+
+impl StreamAssembler {
+    fn handle(
+        &mut self,
+        data: &str,
+        on_event: &mut (dyn FnMut(StreamEvent) + Send),
+    ) -> Result<(), XenotesterError> {
+        let event: Value = serde_json::from_str(data)
+            .map_err(|e| XenotesterError::LlmError(format!("Invalid stream event: {}", e)))?;
+
+        match event["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                if let Ok(usage) = serde_json::from_value(event["message"]["usage"].clone()) {
+                    self.usage = usage;
+                }
+            }
+            "content_block_start" => {
+                let block = &event["content_block"];
+                self.blocks.push(match block["type"].as_str() {
+                    Some("text") => {
+                        PartialBlock::Text(block["text"].as_str().unwrap_or_default().to_string())
+                    }
+                    Some("tool_use") => PartialBlock::ToolUse {
+                        id: block["id"].as_str().unwrap_or_default().to_string(),
+                        name: block["name"].as_str().unwrap_or_default().to_string(),
+                        input_json: String::new(),
+                    },
+                    _ => PartialBlock::Unsupported,
+                });
+            }
+            "content_block_delta" => {
+                let delta = &event["delta"];
+                match (self.blocks.last_mut(), delta["type"].as_str()) {
+                    (Some(PartialBlock::Text(text)), Some("text_delta")) => {
+                        let token = delta["text"].as_str().unwrap_or_default();
+                        text.push_str(token);
+                        on_event(StreamEvent::Token(token.to_string()));
+                    }
+                    (Some(PartialBlock::ToolUse { input_json, .. }), Some("input_json_delta")) => {
+                        input_json.push_str(delta["partial_json"].as_str().unwrap_or_default());
+                    }
+                    _ => {}
+                }
+            }
+            "content_block_stop" => {
+                if let Some(PartialBlock::ToolUse {
+                    id,
+                    name,
+                    input_json,
+                }) = self.blocks.last()
+                {
+                    on_event(StreamEvent::ToolCall {
+                        id: id.clone(),
+                        name: name.clone(),
+                        input: parse_tool_input(input_json),
+                    });
+                }
+            }
+            "message_delta" => {
+                if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                    self.stop_reason = Some(reason.to_string());
+                }
+                if let Some(output_tokens) = event["usage"]["output_tokens"].as_u64() {
+                    self.usage.output_tokens = output_tokens;
+                }
+            }
+            "error" => {
+                return Err(XenotesterError::LlmError(
+                    event["error"]["message"]
+                        .as_str()
+                        .unwrap_or("Unknown stream error")
+                        .to_string(),
+                ));
+            }
+            // ping, message_stop
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> MessagesResponse {
+        let content = self
+            .blocks
+            .into_iter()
+            .map(|block| match block {
+                PartialBlock::Text(text) => ContentBlock::Text { text },
+                PartialBlock::ToolUse {
+                    id,
+                    name,
+                    input_json,
+                } => ContentBlock::ToolUse {
+                    id,
+                    name,
+                    input: parse_tool_input(&input_json),
+                },
+                PartialBlock::Unsupported => ContentBlock::Unsupported,
+            })
+            .collect();
+
+        MessagesResponse {
+            content,
+            stop_reason: self.stop_reason,
+            usage: self.usage,
+        }
+    }
+}
+
+/// Parse accumulated tool input JSON (tools without parameters stream nothing)
+fn parse_tool_input(input_json: &str) -> Value {
+    if input_json.trim().is_empty() {
+        return json!({});
+    }
+    serde_json::from_str(input_json).unwrap_or_else(|_| json!({}))
+}
+
+/// Result of a single executed tool call
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutedAction {
+    pub tool_use_id: String,
+    pub action: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Result of one agent turn (one API call plus its tool executions)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentStepResult {
+    pub session_id: String,
+    pub iteration: u32,
+    /// Text the model produced in this turn
+    pub text: String,
+    pub actions: Vec<ExecutedAction>,
+    /// True when the model stopped calling tools (scenario finished)
+    pub done: bool,
+    pub usage: Usage,
+}
+
+/// Result of running the agent loop to completion
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentLoopResult {
+    pub session_id: String,
+    pub iterations: u32,
+    /// Final text reported by the model
+    pub final_text: String,
+    /// True when the model finished on its own (not cut off by max iterations)
+    pub completed: bool,
+    pub usage: Usage,
+}
+
+/// Conversation state of a Computer Use agent
+pub struct AgentSession {
+    pub id: String,
+    config: ModelConfig,
+    system_prompt: String,
+    messages: Vec<Message>,
+    /// Screenshot the model last saw (its coordinates refer to this capture)
+    last_capture: CaptureResult,
+    iterations: u32,
+    done: bool,
+    /// Token usage accumulated over all turns
+    usage: Usage,
+    /// Values for placeholders in text the model types
+    variables: Variables,
+    /// Safety checks run before each action (none by default)
+    guard: Option<ActionGuard>,
+    /// Receives every screenshot the model is shown (none by default)
+    capture_history: Option<Arc<CaptureHistory>>,
+    /// Database whose action log records the model's actions (none by default)
+    action_log: Option<SqlitePool>,
+}
+
+/// Capture the primary monitor without blocking the async runtime
+async fn capture_screen(options: CaptureOptions) -> Result<CaptureResult, XenotesterError> {
+    tokio::task::spawn_blocking(move || capture_primary_monitor(&options))
+        .await
+        .map_err(|e| XenotesterError::CaptureError(format!("Capture task failed: {}", e)))?
+}
+
+/// Whether `capture` would show the model exactly the image of `previous`
+/// Compares the encoded image that is sent, so any visible change counts.
+fn same_screenshot(previous: &CaptureResult, capture: &CaptureResult) -> bool {
+    previous.media_type == capture.media_type && previous.image_base64 == capture.image_base64
+}
+
+impl AgentSession {
+    /// Start a new session with the scenario instruction and an initial screenshot
+    pub async fn start(
+        instruction: &str,
+        config: ModelConfig,
+        system_prompt: Option<String>,
+    ) -> Result<Self, XenotesterError> {
+        let capture = capture_screen(config.capture_options()).await?;
+
+        let mut content = vec![ContentBlock::Text {
+            text: instruction.to_string(),
+        }];
+        content.extend(capture_blocks(&capture));
+        let messages = vec![Message {
+            role: Role::User,
+            content,
+        }];
+
+        Ok(Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            config,
+            system_prompt: system_prompt.unwrap_or_else(|| DEFAULT_SYSTEM_PROMPT.to_string()),
+            messages,
+            last_capture: capture,
+            iterations: 0,
+            done: false,
+            usage: Usage::default(),
+            variables: Variables::default(),
+            guard: None,
+            capture_history: None,
+            action_log: None,
+        })
+    }
+
+    /// Whether the model has finished the scenario
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Model used by this session
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    /// Provider serving the session's model
+    pub fn provider(&self) -> Provider {
+        self.config.provider
+    }
+
+    /// Token usage accumulated over all turns so far
+    pub fn total_usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    /// Set the values substituted into text the model types
+    pub fn set_variables(&mut self, variables: Variables) {
+        self.variables = variables;
+    }
+
+    /// Set the safety checks the model's actions have to pass
+    pub fn set_guard(&mut self, guard: ActionGuard) {
+        self.guard = Some(guard);
+    }
+
+    /// Record the model's actions in the action log, under the guard's run
+    pub fn set_action_log(&mut self, pool: SqlitePool) {
+        self.action_log = Some(pool);
+    }
+
+    /// Record the screenshots shown to the model, starting with the current one
+    pub fn set_capture_history(&mut self, history: Arc<CaptureHistory>) {
+        history.record(&self.last_capture);
+        self.capture_history = Some(history);
+    }
+
+    /// Run the safety checks for an action; refusals become tool errors
+    async fn check_action(
+        &self,
+        action: &ComputerAction,
+        cancel: &CancellationToken,
+    ) -> Result<(), XenotesterError> {
+        let Some(guard) = &self.guard else {
+            return Ok(());
+        };
+        let position = match action.coordinate() {
+            Some(coordinate) => Some(to_screen_point(coordinate, &self.last_capture)),
+            None if action.uses_pointer() => {
+                Some(input_worker::submit(mouse::get_position).await??)
+            }
+            None => None,
+        };
+        let keys = match action {
+            ComputerAction::Key { text } | ComputerAction::HoldKey { text, .. } => Some(text),
+            _ => None,
+        };
+        guard
+            .check(action.name(), position, keys.map(String::as_str), cancel)
+            .await
+    }
+
+    /// Record an executed or refused input action; failing to record only logs a warning
+    /// `input` is the tool input as the model sent it, before secrets are filled in.
+    async fn log_action(
+        &self,
+        action: &ComputerAction,
+        input: Value,
+        outcome: &Result<Option<String>, XenotesterError>,
+    ) {
+        let Some(pool) = &self.action_log else {
+            return;
+        };
+        if matches!(
+            action,
+            ComputerAction::Screenshot
+                | ComputerAction::CursorPosition
+                | ComputerAction::Wait { .. }
+        ) {
+            return;
+        }
+        let position = action
+            .coordinate()
+            .map(|coordinate| to_screen_point(coordinate, &self.last_capture));
+        let record = ActionRecord::new(action.name(), position, input);
+        let run_id = self
+            .guard
+            .as_ref()
+            .and_then(|guard| guard.run_id.as_deref());
+        let error = outcome.as_ref().err().map(ToString::to_string);
+        if let Err(e) = action_log::record_action(pool, run_id, &record, error.as_deref()).await {
+            warn!(
+                "Failed to record {} in the action log: {}",
+                record.action, e
+            );
+        }
+    }
+
+    /// Fill the instruction's placeholders (such as secrets) in text the model types
+    fn substitute_variables(&self, action: &mut ComputerAction) {
+        if let ComputerAction::Type { text } = action {
+            *text = self.variables.substitute_typed(text);
+        }
+    }
+
+    /// Run one turn: call the API, execute returned tool calls, and queue the results
+    /// The response is streamed; `on_event` receives tokens and tool calls as they arrive.
+    pub async fn step(
+        &mut self,
+        client: &dyn AgentBackend,
+        cancel: &CancellationToken,
+        on_event: &mut (dyn FnMut(StreamEvent) + Send),
+    ) -> Result<AgentStepResult, XenotesterError> {
+        if self.done {
+            return Err(XenotesterError::LlmError(
+                "Agent session has already finished".to_string(),
+            ));
+        }
+        cancel.check()?;
+
+        let display_size = (
+            self.last_capture.resized_width,
+            self.last_capture.resized_height,
+        );
+        let response = client
+            .create_message(
+                &self.config,
+                &self.system_prompt,
+                display_size,
+                &self.messages,
+                cancel,
+                on_event,
+            )
+            .await?;
+        self.iterations += 1;
+        self.usage.add(&response.usage);
+        let mut usage = response.usage;
+
+        let content: Vec<ContentBlock> = response
+            .content
+            .into_iter()
+            .filter(|block| !matches!(block, ContentBlock::Unsupported))
+            .collect();
+
+        let text = content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let tool_uses: Vec<(String, Value)> = content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse { id, input, .. } => Some((id.clone(), input.clone())),
+                _ => None,
+            })
+            .collect();
+
+        self.messages.push(Message {
+            role: Role::Assistant,
+            content,
+        });
+
+        if tool_uses.is_empty() {
+            self.done = true;
+            return Ok(AgentStepResult {
+                session_id: self.id.clone(),
+                iteration: self.iterations,
+                text,
+                actions: Vec::new(),
+                done: true,
+                usage,
+            });
+        }
+
+        let mut actions = Vec::new();
+        let mut results = Vec::new();
+
+        for (tool_use_id, input) in tool_uses {
+            // Check stop between tool calls so emergency stop takes effect immediately
+            cancel.check()?;
+
+            let parsed = serde_json::from_value::<ComputerAction>(input.clone());
+            let (action_name, outcome) = match parsed {
+                Ok(mut action) => {
+                    let name = action.name().to_string();
+                    let logged = action.clone();
+                    self.substitute_variables(&mut action);
+                    let outcome = match self.check_action(&action, cancel).await {
+                        Ok(()) => {
+                            let capture = self.last_capture.clone();
+                            let cancel = cancel.clone();
+                            input_worker::submit(move || {
+                                execute_action(&action, &capture, &cancel)
+                            })
+                            .await?
+                        }
+                        Err(e) => Err(e),
+                    };
+                    self.log_action(&logged, input, &outcome).await;
+                    (name, outcome)
+                }
+                Err(e) => (
+                    "unknown".to_string(),
+                    Err(XenotesterError::InputError(format!("Invalid tool input: {}", e))),
+                ),
+            };
+
+            if let Err(XenotesterError::Cancelled) = outcome {
+                return Err(XenotesterError::Cancelled);
+            }
+
+            let (content, is_error, error) = match outcome {
+                Ok(output) => (
+                    vec![ContentBlock::Text {
+                        text: output.unwrap_or_else(|| "ok".to_string()),
+                    }],
+                    None,
+                    None,
+                ),
+                Err(e) => (
+                    vec![ContentBlock::Text {
+                        text: e.to_string(),
+                    }],
+                    Some(true),
+                    Some(e.to_string()),
+                ),
+            };
+
+            actions.push(ExecutedAction {
+                tool_use_id: tool_use_id.clone(),
+                action: action_name,
+                success: error.is_none(),
+                error,
+            });
+            results.push(ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            });
+        }
+
+        // Attach a fresh screenshot to the last tool result so the model sees the outcome.
+        // A screen identical to the last screenshot is only reported in text:
+        // the model already has the image, and it would cost the same tokens again.
+        let capture = capture_screen(self.config.capture_options()).await?;
+        let screenshot = if same_screenshot(&self.last_capture, &capture) {
+            usage.add_skipped_screenshot(&self.last_capture);
+            self.usage.add_skipped_screenshot(&self.last_capture);
+            vec![ContentBlock::Text {
+                text: UNCHANGED_SCREEN_TEXT.to_string(),
+            }]
+        } else {
+            self.last_capture = capture;
+            if let Some(history) = &self.capture_history {
+                history.record(&self.last_capture);
+            }
+            capture_blocks(&self.last_capture)
+        };
+        if let Some(ContentBlock::ToolResult { content, .. }) = results.last_mut() {
+            content.extend(screenshot);
+        }
+
+        self.messages.push(Message {
+            role: Role::User,
+            content: results,
+        });
+
+        Ok(AgentStepResult {
+            session_id: self.id.clone(),
+            iteration: self.iterations,
+            text,
+            actions,
+            done: false,
+            usage,
+        })
+    }
+
+    /// Run turns until the model finishes, stop is requested, or `max_iterations` is hit
+    pub async fn run_loop(
+        &mut self,
+        client: &dyn AgentBackend,
+        cancel: &CancellationToken,
+        max_iterations: u32,
+        on_event: &mut (dyn FnMut(StreamEvent) + Send),
+    ) -> Result<AgentLoopResult, XenotesterError> {
+        let mut final_text = String::new();
+
+        while !self.done && self.iterations < max_iterations {
+            let step = self.step(client, cancel, on_event).await?;
+            if !step.text.is_empty() {
+                final_text = step.text;
+            }
+        }
+
+        Ok(AgentLoopResult {
+            session_id: self.id.clone(),
+            iterations: self.iterations,
+            final_text,
+            completed: self.done,
+            usage: self.usage.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(role: Role, text: &str) -> Message {
+        Message {
+            role,
+            content: vec![
+                ContentBlock::Text {
+                    text: format!("{} (first block)", text),
+                },
+                ContentBlock::Text {
+                    text: text.to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_cache_breakpoints_stay_within_the_api_limit() {
+        let messages: Vec<Message> = (0..10)
+            .flat_map(|i| {
+                [
+                    turn(Role::User, &format!("user {}", i)),
+                    turn(Role::Assistant, &format!("assistant {}", i)),
+                ]
+            })
+            .chain([turn(Role::User, "latest")])
+            .collect();
+        let body = AnthropicClient::build_request_body(
+            &ModelConfig::default(),
+            "system",
+            (1280, 800),
+            &messages,
+            false,
+        );
+
+        let breakpoints = body.to_string().matches("cache_control").count();
+        assert_eq!(breakpoints, 1 + CACHED_USER_TURNS);
+        assert!(breakpoints <= 4);
+
+        let sent = body["messages"].as_array().unwrap();
+        let marked: Vec<usize> = sent
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| message["content"][1].get("cache_control").is_some())
+            .map(|(index, _)| index)
+            .collect();
+        // The last block of the two most recent user turns
+        assert_eq!(marked, vec![18, 20]);
+        assert!(sent[20]["content"][0].get("cache_control").is_none());
+    }
+
+    fn capture(image_base64: &str) -> CaptureResult {
+        CaptureResult {
+            original_width: 2560,
+            original_height: 1600,
+            resized_width: 1280,
+            resized_height: 800,
+            scale_factor: 2.0,
+            image_base64: image_base64.to_string(),
+            media_type: "image/png".to_string(),
+            monitor_id: 1,
+            monitor_x: 0,
+            monitor_y: 0,
+            display_scale_factor: 1.0,
+            active_window: None,
+        }
+    }
+
+    #[test]
+    fn test_only_identical_screenshots_are_skipped() {
+        assert!(same_screenshot(&capture("AAAA"), &capture("AAAA")));
+        // A single changed pixel changes the encoded image
+        assert!(!same_screenshot(&capture("AAAA"), &capture("AAAB")));
+
+        let mut usage = Usage::default();
+        usage.add_skipped_screenshot(&capture("AAAA"));
+        usage.add_skipped_screenshot(&capture("AAAA"));
+        assert_eq!(usage.screenshots_skipped, 2);
+        assert_eq!(usage.skipped_image_tokens, 2 * image_tokens(1280, 800));
+        assert_eq!(image_tokens(1280, 800), 1365);
+    }
+}
@@ -0,0 +1,364 @@
+//! Gemini computer-use client
+//!
+//! Runs the agent conversation against the Gemini API. The conversation is
+//! translated to `contents`, the `computer` tool is declared as a function
+//! taking the Computer Use actions, and function calls come back as `tool_use`
+//! blocks, so `AgentSession` executes them like Anthropic's. Tool results are
+//! sent as function responses followed by the fresh screenshot.
+
+use serde_json::{json, Value};
+use std::env;
+use std::time::Duration;
+
+use crate::error::XenotesterError;
+use crate::services::http;
+use crate::services::llm::anthropic::{
+    ContentBlock, ImageSource, Message, MessagesResponse, ModelConfig, Role, StreamEvent, Usage,
+};
+use crate::services::llm::{
+    api_error, computer_function, read_sse, AgentBackend, BackendFuture, COMPUTER_TOOL,
+};
+use crate::utils::cancel::CancellationToken;
+
+/// Default API endpoint (override with GEMINI_BASE_URL)
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+/// Same limit as Anthropic requests
+const MAX_OUTPUT_TOKENS: u32 = 4096;
+const REQUEST_TIMEOUT_SECS: u64 = 120;
+
+/// HTTP client for the Gemini `streamGenerateContent` API
+pub struct GeminiClient {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl GeminiClient {
+    /// Create a client from GEMINI_API_KEY / GEMINI_BASE_URL
+    pub fn from_env() -> Result<Self, XenotesterError> {
+        let api_key = env::var("GEMINI_API_KEY").map_err(|_| {
+            XenotesterError::ConfigError("GEMINI_API_KEY is not set in environment".to_string())
+        })?;
+        let base_url = env::var("GEMINI_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+
+        Ok(Self {
+            http: http::client()?,
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Build the request body for a turn of the conversation
+    fn build_request_body(
+        system_prompt: &str,
+        display_size: (u32, u32),
+        messages: &[Message],
+    ) -> Value {
+        json!({
+            "systemInstruction": { "parts": [{ "text": system_prompt }] },
+            "contents": to_contents(messages),
+            "tools": [{ "functionDeclarations": [computer_function(display_size)] }],
+            "generationConfig": { "maxOutputTokens": MAX_OUTPUT_TOKENS },
+        })
+    }
+
+    /// Send the conversation and stream the reply
+    pub async fn create_message_streaming(
+        &self,
+        config: &ModelConfig,
+        system_prompt: &str,
+        display_size: (u32, u32),
+        messages: &[Message],
+        cancel: &CancellationToken,
+        on_event: &mut (dyn FnMut(StreamEvent) + Send),
+    ) -> Result<MessagesResponse, XenotesterError> {
+        let body = Self::build_request_body(system_prompt, display_size, messages);
+        let response = self
+            .http
+            .post(format!(
+                "{}/v1beta/models/{}:streamGenerateContent?alt=sse",
+                self.base_url, config.model
+            ))
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let mut assembler = StreamAssembler::default();
+        read_sse(response, cancel, &mut |data| {
+            assembler.handle(data, on_event)
+        })
+        .await?;
+        Ok(assembler.finish())
+    }
+}
+
+impl AgentBackend for GeminiClient {
+    fn create_message<'a>(
+        &'a self,
+        config: &'a ModelConfig,
+        system_prompt: &'a str,
+        display_size: (u32, u32),
+        messages: &'a [Message],
+        cancel: &'a CancellationToken,
+        on_event: &'a mut (dyn FnMut(StreamEvent) + Send),
+    ) -> BackendFuture<'a> {
+        Box::pin(self.create_message_streaming(
+            config,
+            system_prompt,
+            display_size,
+            messages,
+            cancel,
+            on_event,
+        ))
+    }
+}
+
+/// Translate the conversation to Gemini `contents`
+fn to_contents(messages: &[Message]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|message| {
+            let role = match message.role {
+                Role::User => "user",
+                Role::Assistant => "model",
+            };
+            json!({ "role": role, "parts": to_parts(&message.content) })
+        })
+        .collect()
+}
+
+/// Parts for content blocks; images in tool results follow the function responses
+fn to_parts(blocks: &[ContentBlock]) -> Vec<Value> {
+    let mut parts = Vec::new();
+    let mut attachments = Vec::new();
+
+    for block in blocks {
+        match block {
+            ContentBlock::Text { text } => parts.push(json!({ "text": text })),
+            ContentBlock::Image { source } => parts.push(inline_data(source)),
+            ContentBlock::ToolUse { id, name, input } => parts.push(json!({
+                "functionCall": { "id": id, "name": name, "args": input },
+            })),
+            ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => {
+                let mut texts = Vec::new();
+                for block in content {
+                    match block {
+                        ContentBlock::Text { text } => texts.push(text.as_str()),
+                        ContentBlock::Image { source } => attachments.push(inline_data(source)),
+                        _ => {}
+                    }
+                }
+                let key = if *is_error == Some(true) {
+                    "error"
+                } else {
+                    "output"
+                };
+                parts.push(json!({
+                    "functionResponse": {
+                        "id": tool_use_id,
+                        "name": COMPUTER_TOOL,
+                        "response": { key: texts.join("\n") },
+                    },
+                }));
+            }
+            ContentBlock::Unsupported => {}
+        }
+    }
+
+    parts.extend(attachments);
+    parts
+}
+
+fn inline_data(source: &ImageSource) -> Value {
+    json!({ "inlineData": { "mimeType": source.media_type, "data": source.data } })
+}
+
+/// Rebuilds a complete response from streamed `GenerateContentResponse` chunks
+#[derive(Default)]
+struct StreamAssembler {
+    text: String,
+    tool_uses: Vec<ContentBlock>,
+    finish_reason: Option<String>,
+    usage: Usage,
+}
+
+impl StreamAssembler {
+    fn handle(
+        &mut self,
+        data: &str,
+        on_event: &mut (dyn FnMut(StreamEvent) + Send),
+    ) -> Result<(), XenotesterError> {
+        let chunk: Value = serde_json::from_str(data)
+            .map_err(|e| XenotesterError::LlmError(format!("Invalid stream event: {}", e)))?;
+        if let Some(message) = chunk["error"]["message"].as_str() {
+            return Err(XenotesterError::LlmError(message.to_string()));
+        }
+
+        let candidate = &chunk["candidates"][0];
+        for part in candidate["content"]["parts"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            if let Some(text) = part["text"].as_str() {
+                // Thought summaries are not part of the answer
+                if part["thought"].as_bool() != Some(true) {
+                    self.text.push_str(text);
+                    on_event(StreamEvent::Token(text.to_string()));
+                }
+            } else if let Some(call) = part.get("functionCall") {
+                let id = call["id"]
+                    .as_str()
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4()));
+                let name = call["name"].as_str().unwrap_or_default().to_string();
+                let input = match &call["args"] {
+                    Value::Object(_) => call["args"].clone(),
+                    _ => json!({}),
+                };
+                on_event(StreamEvent::ToolCall {
+                    id: id.clone(),
+                    name: name.clone(),
+                    input: input.clone(),
+                });
+                self.tool_uses
+                    .push(ContentBlock::ToolUse { id, name, input });
+            }
+        }
+        if let Some(reason) = candidate["finishReason"].as_str() {
+            self.finish_reason = Some(reason.to_string());
+        }
+        // Every chunk reports the totals so far
+        if let Some(metadata) = chunk.get("usageMetadata") {
+            self.usage = usage_from_metadata(metadata);
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> MessagesResponse {
+        let stop_reason = if !self.tool_uses.is_empty() {
+            Some("tool_use".to_string())
+        } else {
+            self.finish_reason.map(|reason| match reason.as_str() {
+                "STOP" => "end_turn".to_string(),
+                "MAX_TOKENS" => "max_tokens".to_string(),
+                _ => reason.to_lowercase(),
+            })
+        };
+
+        let mut content = Vec::new();
+        if !self.text.is_empty() {
+            content.push(ContentBlock::Text { text: self.text });
+        }
+        content.extend(self.tool_uses);
+
+        MessagesResponse {
+            content,
+            stop_reason,
+            usage: self.usage,
+        }
+    }
+}
+
+/// Token counts in Anthropic's terms: cached prompt tokens are not input tokens
+fn usage_from_metadata(metadata: &Value) -> Usage {
+    let count = |key: &str| metadata[key].as_u64().unwrap_or_default();
+    let cached = count("cachedContentTokenCount");
+    Usage {
+        input_tokens: count("promptTokenCount").saturating_sub(cached),
+        output_tokens: count("candidatesTokenCount") + count("thoughtsTokenCount"),
+        cache_creation_input_tokens: 0,
+        cache_read_input_tokens: cached,
+        ..Usage::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_results_become_function_responses() {
+        let messages = vec![
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::ToolUse {
+                    id: "t1".to_string(),
+                    name: COMPUTER_TOOL.to_string(),
+                    input: json!({ "action": "left_click", "coordinate": [10, 20] }),
+                }],
+            },
+            Message {
+                role: Role::User,
+                content: vec![ContentBlock::ToolResult {
+                    tool_use_id: "t1".to_string(),
+                    content: vec![
+                        ContentBlock::Text {
+                            text: "ok".to_string(),
+                        },
+                        ContentBlock::Image {
+                            source: ImageSource::png("AAAA".to_string()),
+                        },
+                    ],
+                    is_error: None,
+                }],
+            },
+        ];
+
+        let contents = to_contents(&messages);
+        assert_eq!(contents[0]["role"], "model");
+        assert_eq!(
+            contents[0]["parts"][0]["functionCall"]["args"]["action"],
+            "left_click"
+        );
+        let parts = &contents[1]["parts"];
+        assert_eq!(parts[0]["functionResponse"]["response"]["output"], "ok");
+        assert_eq!(parts[1]["inlineData"]["mimeType"], "image/png");
+    }
+
+    #[test]
+    fn test_stream_assembles_text_and_calls() {
+        let mut assembler = StreamAssembler::default();
+        let mut tokens = Vec::new();
+        let mut on_event = |event: StreamEvent| {
+            if let StreamEvent::Token(token) = event {
+                tokens.push(token);
+            }
+        };
+        let chunks = [
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Clicking "}]}}]}"#,
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Save"},
+                {"functionCall":{"name":"computer","args":{"action":"key","text":"ctrl+s"}}}]},
+                "finishReason":"STOP"}],
+                "usageMetadata":{"promptTokenCount":1200,"cachedContentTokenCount":1000,
+                "candidatesTokenCount":30}}"#,
+        ];
+        for chunk in chunks {
+            assembler.handle(chunk, &mut on_event).unwrap();
+        }
+
+        let response = assembler.finish();
+        assert_eq!(tokens, vec!["Clicking ", "Save"]);
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+        assert!(matches!(
+            &response.content[0],
+            ContentBlock::Text { text } if text == "Clicking Save"
+        ));
+        assert!(matches!(
+            &response.content[1],
+            ContentBlock::ToolUse { input, .. } if input["text"] == "ctrl+s"
+        ));
+        assert_eq!(response.usage.input_tokens, 200);
+        assert_eq!(response.usage.cache_read_input_tokens, 1000);
+    }
+}
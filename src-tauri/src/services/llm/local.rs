@@ -0,0 +1,441 @@
+//! Local model client (OpenAI-compatible chat completions)
+//!
+//! Runs the agent conversation against a model served on this machine or the
+//! local network, e.g. by Ollama or LM Studio, so no screenshot leaves it. The
+//! `computer` tool is declared as a function and tool calls come back as
+//! `tool_use` blocks. Chat completions carry no images in tool messages, so
+//! each tool result's screenshot follows in a user message.
+//!
+//! The endpoint is reached directly, without the configured proxy.
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::time::Duration;
+
+use crate::error::XenotesterError;
+use crate::services::llm::anthropic::{
+    ContentBlock, ImageSource, Message, MessagesResponse, ModelConfig, Role, StreamEvent, Usage,
+};
+use crate::services::llm::{
+    api_error, computer_function, read_sse, AgentBackend, BackendFuture, LOCAL_MODEL_PREFIX,
+};
+use crate::utils::cancel::CancellationToken;
+
+/// Default endpoint, Ollama's (override with LOCAL_LLM_BASE_URL;
+/// LM Studio serves http://localhost:1234/v1)
+const DEFAULT_BASE_URL: &str = "http://localhost:11434/v1";
+/// Same limit as Anthropic requests
+const MAX_TOKENS: u32 = 4096;
+/// Local models run on consumer hardware and can take minutes per turn
+const REQUEST_TIMEOUT_SECS: u64 = 600;
+
+/// HTTP client for an OpenAI-compatible `chat/completions` API
+pub struct LocalClient {
+    http: reqwest::Client,
+    api_key: Option<String>,
+    base_url: String,
+}
+
+impl LocalClient {
+    /// Create a client from LOCAL_LLM_BASE_URL / LOCAL_LLM_API_KEY (optional)
+    pub fn from_env() -> Result<Self, XenotesterError> {
+        let base_url =
+            env::var("LOCAL_LLM_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+        let api_key = env::var("LOCAL_LLM_API_KEY")
+            .ok()
+            .filter(|key| !key.is_empty());
+        let http = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .map_err(|e| XenotesterError::ConfigError(format!("HTTP client error: {}", e)))?;
+
+        Ok(Self {
+            http,
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Build the request body for a turn of the conversation
+    fn build_request_body(
+        config: &ModelConfig,
+        system_prompt: &str,
+        display_size: (u32, u32),
+        messages: &[Message],
+    ) -> Value {
+        let model = config
+            .model
+            .strip_prefix(LOCAL_MODEL_PREFIX)
+            .unwrap_or(&config.model);
+        let mut chat = vec![json!({ "role": "system", "content": system_prompt })];
+        chat.extend(to_chat_messages(messages));
+
+        json!({
+            "model": model,
+            "messages": chat,
+            "tools": [{ "type": "function", "function": computer_function(display_size) }],
+            "max_tokens": MAX_TOKENS,
+            "stream": true,
+            "stream_options": { "include_usage": true },
+        })
+    }
+
+    /// Send the conversation and stream the reply
+    pub async fn create_message_streaming(
+        &self,
+        config: &ModelConfig,
+        system_prompt: &str,
+        display_size: (u32, u32),
+        messages: &[Message],
+        cancel: &CancellationToken,
+        on_event: &mut (dyn FnMut(StreamEvent) + Send),
+    ) -> Result<MessagesResponse, XenotesterError> {
+        let body = Self::build_request_body(config, system_prompt, display_size, messages);
+        let mut request = self
+            .http
+            .post(format!("{}/chat/completions", self.base_url))
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await.map_err(|e| {
+            if e.is_connect() {
+                XenotesterError::NetworkError(format!(
+                    "Local model server is not reachable at {}: {}",
+                    self.base_url, e
+                ))
+            } else {
+                e.into()
+            }
+        })?;
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let mut assembler = StreamAssembler::default();
+        read_sse(response, cancel, &mut |data| {
+            assembler.handle(data, on_event)
+        })
+        .await?;
+        Ok(assembler.finish(on_event))
+    }
+}
+
+impl AgentBackend for LocalClient {
+    fn create_message<'a>(
+        &'a self,
+        config: &'a ModelConfig,
+        system_prompt: &'a str,
+        display_size: (u32, u32),
+        messages: &'a [Message],
+        cancel: &'a CancellationToken,
+        on_event: &'a mut (dyn FnMut(StreamEvent) + Send),
+    ) -> BackendFuture<'a> {
+        Box::pin(self.create_message_streaming(
+            config,
+            system_prompt,
+            display_size,
+            messages,
+            cancel,
+            on_event,
+        ))
+    }
+}
+
+/// Translate the conversation to chat messages
+/// A message with tool results becomes one `tool` message per result, followed
+/// by a user message with its images and any other content.
+fn to_chat_messages(messages: &[Message]) -> Vec<Value> {
+    let mut chat = Vec::new();
+
+    for message in messages {
+        match message.role {
+            Role::Assistant => {
+                let mut text = String::new();
+                let mut tool_calls = Vec::new();
+                for block in &message.content {
+                    match block {
+                        ContentBlock::Text { text: part } => text.push_str(part),
+                        ContentBlock::ToolUse { id, name, input } => tool_calls.push(json!({
+                            "id": id,
+                            "type": "function",
+                            "function": { "name": name, "arguments": input.to_string() },
+                        })),
+                        _ => {}
+                    }
+                }
+                let mut assistant = json!({ "role": "assistant", "content": text });
+                if !tool_calls.is_empty() {
+                    assistant["tool_calls"] = Value::Array(tool_calls);
+                }
+                chat.push(assistant);
+            }
+            Role::User => {
+                let mut parts = Vec::new();
+                for block in &message.content {
+                    match block {
+                        ContentBlock::Text { text } => {
+                            parts.push(json!({ "type": "text", "text": text }))
+                        }
+                        ContentBlock::Image { source } => parts.push(image_part(source)),
+                        ContentBlock::ToolResult {
+                            tool_use_id,
+                            content,
+                            is_error,
+                        } => {
+                            let mut texts = Vec::new();
+                            for block in content {
+                                match block {
+                                    ContentBlock::Text { text } => texts.push(text.as_str()),
+                                    ContentBlock::Image { source } => {
+                                        parts.push(image_part(source))
+                                    }
+                                    _ => {}
+                                }
+                            }
+                            let mut output = texts.join("\n");
+                            if *is_error == Some(true) {
+                                output = format!("Error: {}", output);
+                            }
+                            chat.push(json!({
+                                "role": "tool",
+                                "tool_call_id": tool_use_id,
+                                "content": output,
+                            }));
+                        }
+                        _ => {}
+                    }
+                }
+                if !parts.is_empty() {
+                    chat.push(json!({ "role": "user", "content": parts }));
+                }
+            }
+        }
+    }
+
+    chat
+}
+
+fn image_part(source: &ImageSource) -> Value {
+    json!({
+        "type": "image_url",
+        "image_url": { "url": format!("data:{};base64,{}", source.media_type, source.data) },
+    })
+}
+
+/// Tool call whose arguments are still streaming in
+#[derive(Default)]
+struct PartialCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Rebuilds a complete response from streamed `chat.completion.chunk`s
+#[derive(Default)]
+struct StreamAssembler {
+    text: String,
+    /// Calls by their index in the choice
+    calls: BTreeMap<u64, PartialCall>,
+    finish_reason: Option<String>,
+    usage: Usage,
+}
+
+impl StreamAssembler {
+    fn handle(
+        &mut self,
+        data: &str,
+        on_event: &mut (dyn FnMut(StreamEvent) + Send),
+    ) -> Result<(), XenotesterError> {
+        if data == "[DONE]" {
+            return Ok(());
+        }
+        let chunk: Value = serde_json::from_str(data)
+            .map_err(|e| XenotesterError::LlmError(format!("Invalid stream event: {}", e)))?;
+        if let Some(error) = chunk.get("error") {
+            let message = error["message"].as_str().or(error.as_str());
+            return Err(XenotesterError::LlmError(
+                message.unwrap_or("Stream error").to_string(),
+            ));
+        }
+
+        let choice = &chunk["choices"][0];
+        let delta = &choice["delta"];
+        if let Some(text) = delta["content"].as_str().filter(|t| !t.is_empty()) {
+            self.text.push_str(text);
+            on_event(StreamEvent::Token(text.to_string()));
+        }
+        for call in delta["tool_calls"].as_array().into_iter().flatten() {
+            let index = call["index"].as_u64().unwrap_or(self.calls.len() as u64);
+            let partial = self.calls.entry(index).or_default();
+            if let Some(id) = call["id"].as_str() {
+                partial.id = id.to_string();
+            }
+            if let Some(name) = call["function"]["name"].as_str() {
+                partial.name.push_str(name);
+            }
+            if let Some(arguments) = call["function"]["arguments"].as_str() {
+                partial.arguments.push_str(arguments);
+            }
+        }
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = Some(reason.to_string());
+        }
+        if let Some(usage) = chunk.get("usage").filter(|u| u.is_object()) {
+            self.usage = usage_from_openai(usage);
+        }
+
+        Ok(())
+    }
+
+    /// Complete the response; tool calls are reported once their arguments are whole
+    fn finish(self, on_event: &mut (dyn FnMut(StreamEvent) + Send)) -> MessagesResponse {
+        let mut content = Vec::new();
+        if !self.text.is_empty() {
+            content.push(ContentBlock::Text { text: self.text });
+        }
+
+        let has_calls = !self.calls.is_empty();
+        for call in self.calls.into_values() {
+            let id = if call.id.is_empty() {
+                format!("call_{}", uuid::Uuid::new_v4())
+            } else {
+                call.id
+            };
+            // Small models sometimes send malformed arguments; the action then
+            // fails to parse and the error goes back to the model
+            let input = serde_json::from_str::<Value>(&call.arguments)
+                .ok()
+                .filter(Value::is_object)
+                .unwrap_or_else(|| json!({}));
+            on_event(StreamEvent::ToolCall {
+                id: id.clone(),
+                name: call.name.clone(),
+                input: input.clone(),
+            });
+            content.push(ContentBlock::ToolUse {
+                id,
+                name: call.name,
+                input,
+            });
+        }
+
+        let stop_reason = if has_calls {
+            Some("tool_use".to_string())
+        } else {
+            self.finish_reason.map(|reason| match reason.as_str() {
+                "stop" => "end_turn".to_string(),
+                "length" => "max_tokens".to_string(),
+                _ => reason,
+            })
+        };
+
+        MessagesResponse {
+            content,
+            stop_reason,
+            usage: self.usage,
+        }
+    }
+}
+
+/// Token counts in Anthropic's terms: cached prompt tokens are not input tokens
+fn usage_from_openai(usage: &Value) -> Usage {
+    let prompt = usage["prompt_tokens"].as_u64().unwrap_or_default();
+    let cached = usage["prompt_tokens_details"]["cached_tokens"]
+        .as_u64()
+        .unwrap_or_default();
+    Usage {
+        input_tokens: prompt.saturating_sub(cached),
+        output_tokens: usage["completion_tokens"].as_u64().unwrap_or_default(),
+        cache_creation_input_tokens: 0,
+        cache_read_input_tokens: cached,
+        ..Usage::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_results_become_tool_messages() {
+        let messages = vec![
+            Message {
+                role: Role::Assistant,
+                content: vec![
+                    ContentBlock::Text {
+                        text: "Clicking".to_string(),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "t1".to_string(),
+                        name: "computer".to_string(),
+                        input: json!({ "action": "left_click", "coordinate": [10, 20] }),
+                    },
+                ],
+            },
+            Message {
+                role: Role::User,
+                content: vec![ContentBlock::ToolResult {
+                    tool_use_id: "t1".to_string(),
+                    content: vec![ContentBlock::Image {
+                        source: ImageSource::png("AAAA".to_string()),
+                    }],
+                    is_error: None,
+                }],
+            },
+        ];
+
+        let chat = to_chat_messages(&messages);
+        assert_eq!(chat[0]["content"], "Clicking");
+        let arguments = chat[0]["tool_calls"][0]["function"]["arguments"]
+            .as_str()
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(arguments).unwrap()["action"],
+            "left_click"
+        );
+        assert_eq!(chat[1]["role"], "tool");
+        assert_eq!(chat[1]["tool_call_id"], "t1");
+        assert_eq!(chat[2]["role"], "user");
+        assert_eq!(
+            chat[2]["content"][0]["image_url"]["url"],
+            "data:image/png;base64,AAAA"
+        );
+    }
+
+    #[test]
+    fn test_stream_assembles_fragmented_tool_call() {
+        let mut assembler = StreamAssembler::default();
+        let mut calls = Vec::new();
+        let mut on_event = |event: StreamEvent| {
+            if let StreamEvent::ToolCall { name, .. } = event {
+                calls.push(name);
+            }
+        };
+        let chunks = [
+            r#"{"choices":[{"index":0,"delta":{"role":"assistant","content":"Saving"}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"c1",
+                "type":"function","function":{"name":"computer","arguments":"{\"action\":"}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,
+                "function":{"arguments":"\"key\",\"text\":\"ctrl+s\"}"}}]},
+                "finish_reason":"tool_calls"}]}"#,
+            r#"{"choices":[],"usage":{"prompt_tokens":900,"completion_tokens":25}}"#,
+            "[DONE]",
+        ];
+        for chunk in chunks {
+            assembler.handle(chunk, &mut on_event).unwrap();
+        }
+
+        let response = assembler.finish(&mut on_event);
+        assert_eq!(calls, vec!["computer"]);
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+        assert!(matches!(
+            &response.content[1],
+            ContentBlock::ToolUse { id, input, .. } if id == "c1" && input["text"] == "ctrl+s"
+        ));
+        assert_eq!(response.usage.input_tokens, 900);
+        assert_eq!(response.usage.output_tokens, 25);
+    }
+}
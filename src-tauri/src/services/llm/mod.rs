@@ -0,0 +1,251 @@
+//! LLM client modules
+//!
+//! Runs the Computer Use conversation loop in the backend so screenshots never
+//! cross the IPC bridge and stop requests can interrupt between tool calls.
+//! `AgentSession` keeps the conversation in the Anthropic message format; each
+//! provider's `AgentBackend` translates it to its own API and returns the reply
+//! as content blocks, with `computer` tool calls in the Computer Use schema.
+
+pub mod anthropic;
+pub mod gemini;
+pub mod local;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::error::XenotesterError;
+use crate::utils::cancel::CancellationToken;
+use anthropic::{AnthropicClient, Message, MessagesResponse, ModelConfig, StreamEvent};
+use gemini::GeminiClient;
+use local::LocalClient;
+
+/// Name of the computer tool, as Anthropic's is named
+pub(crate) const COMPUTER_TOOL: &str = "computer";
+
+/// Prefix of model IDs served by the local endpoint
+pub const LOCAL_MODEL_PREFIX: &str = "local/";
+
+/// Model provider a run talks to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    Anthropic,
+    Gemini,
+    /// OpenAI-compatible server on this machine or network (Ollama, LM Studio)
+    Local,
+}
+
+impl Provider {
+    /// Provider serving a model ID
+    /// "gemini-..." models are Gemini's; "local/<name>" names a model of the local server.
+    pub fn for_model(model: &str) -> Self {
+        if model.starts_with(LOCAL_MODEL_PREFIX) {
+            Provider::Local
+        } else if model.starts_with("gemini") {
+            Provider::Gemini
+        } else {
+            Provider::Anthropic
+        }
+    }
+}
+
+/// Reply of an `AgentBackend`
+pub type BackendFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<MessagesResponse, XenotesterError>> + Send + 'a>>;
+
+/// A model API that can drive an `AgentSession`
+pub trait AgentBackend: Send + Sync {
+    /// Send the conversation and stream the reply
+    /// `on_event` receives text tokens and tool calls as they arrive; the
+    /// request is abandoned with `Cancelled` as soon as the run is cancelled.
+    fn create_message<'a>(
+        &'a self,
+        config: &'a ModelConfig,
+        system_prompt: &'a str,
+        display_size: (u32, u32),
+        messages: &'a [Message],
+        cancel: &'a CancellationToken,
+        on_event: &'a mut (dyn FnMut(StreamEvent) + Send),
+    ) -> BackendFuture<'a>;
+}
+
+/// Client for a provider, configured from the environment
+pub fn create_backend(provider: Provider) -> Result<Box<dyn AgentBackend>, XenotesterError> {
+    Ok(match provider {
+        Provider::Anthropic => Box::new(AnthropicClient::from_env()?),
+        Provider::Gemini => Box::new(GeminiClient::from_env()?),
+        Provider::Local => Box::new(LocalClient::from_env()?),
+    })
+}
+
+/// Error for an unsuccessful API response, with the provider's message when it sent one
+/// Rate limits and overloaded or failing servers clear up on their own, so
+/// they are reported as retryable network errors.
+pub(crate) async fn api_error(response: reqwest::Response) -> XenotesterError {
+    let status = response.status();
+    let error_body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&error_body)
+        .ok()
+        .and_then(|v| v["error"]["message"].as_str().map(String::from))
+        .unwrap_or(error_body);
+    let message = format!("API call failed ({}): {}", status.as_u16(), message);
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        XenotesterError::NetworkError(message)
+    } else {
+        XenotesterError::LlmError(message)
+    }
+}
+
+/// Pass the `data:` payloads of a server-sent event stream to `on_data`
+/// Stops with `Cancelled` as soon as the run is cancelled.
+pub(crate) async fn read_sse(
+    mut response: reqwest::Response,
+    cancel: &CancellationToken,
+    on_data: &mut (dyn FnMut(&str) -> Result<(), XenotesterError> + Send),
+) -> Result<(), XenotesterError> {
+    let mut events = SseBuffer::default();
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = response.chunk() => chunk?,
+            _ = cancel.cancelled() => return Err(XenotesterError::Cancelled),
+        };
+        let Some(chunk) = chunk else { break };
+        events.push(&chunk, on_data)?;
+    }
+
+    Ok(())
+}
+
+/// Bytes of a server-sent event stream that don't form a complete event yet
+/// Only complete events are decoded, so a character split across chunks stays intact.
+#[derive(Default)]
+struct SseBuffer {
+    bytes: Vec<u8>,
+}
+
+impl SseBuffer {
+    /// Append a chunk and pass the `data:` payloads of the events it completes to `on_data`
+    fn push(
+        &mut self,
+        chunk: &[u8],
+        on_data: &mut (dyn FnMut(&str) -> Result<(), XenotesterError> + Send),
+    ) -> Result<(), XenotesterError> {
+        // Events may be separated by CRLF pairs; a pair can span two chunks
+        self.bytes
+            .extend(chunk.iter().copied().filter(|&b| b != b'\r'));
+
+        // SSE events are separated by a blank line
+        while let Some(pos) = self.bytes.windows(2).position(|pair| pair == b"\n\n") {
+            let event: Vec<u8> = self.bytes.drain(..pos + 2).collect();
+            for line in String::from_utf8_lossy(&event).lines() {
+                if let Some(data) = line.strip_prefix("data:") {
+                    on_data(data.trim())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Function declaration of the `computer` tool, with the Computer Use action schema
+/// For providers without a native computer tool; calls are parsed as `ComputerAction`s.
+pub(crate) fn computer_function(display_size: (u32, u32)) -> Value {
+    json!({
+        "name": COMPUTER_TOOL,
+        "description": format!(
+            "Use the mouse and keyboard of a {}x{} screen. Coordinates are [x, y] pixels \
+             of the latest screenshot, which is returned after every call.",
+            display_size.0, display_size.1
+        ),
+        "parameters": {
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": [
+                        "screenshot", "cursor_position", "mouse_move", "left_click",
+                        "right_click", "middle_click", "double_click", "triple_click",
+                        "left_click_drag", "left_mouse_down", "left_mouse_up", "scroll",
+                        "type", "key", "hold_key", "wait",
+                    ],
+                },
+                "coordinate": {
+                    "type": "array",
+                    "items": { "type": "integer" },
+                    "description": "Target [x, y]; the end point of left_click_drag",
+                },
+                "start_coordinate": {
+                    "type": "array",
+                    "items": { "type": "integer" },
+                    "description": "Start [x, y] of left_click_drag",
+                },
+                "text": {
+                    "type": "string",
+                    "description": "Text to type, keys to press (e.g. \"ctrl+s\"), \
+                                    or modifiers to hold during left_click",
+                },
+                "scroll_direction": {
+                    "type": "string",
+                    "enum": ["up", "down", "left", "right"],
+                },
+                "scroll_amount": { "type": "integer" },
+                "duration": {
+                    "type": "number",
+                    "description": "Seconds to wait or to hold the key",
+                },
+            },
+            "required": ["action"],
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(chunks: &[&[u8]]) -> Vec<String> {
+        let mut events = SseBuffer::default();
+        let mut data = Vec::new();
+        for chunk in chunks {
+            events
+                .push(chunk, &mut |payload| {
+                    data.push(payload.to_string());
+                    Ok(())
+                })
+                .unwrap();
+        }
+        data
+    }
+
+    #[test]
+    fn test_sse_keeps_characters_split_across_chunks() {
+        let stream = "data: {\"text\":\"caf\u{e9} \u{65e5}\u{672c}\"}\n\n".as_bytes();
+        // Split inside the two-byte "é" and the three-byte "日"
+        let e_acute = stream.iter().position(|&b| b == 0xC3).unwrap() + 1;
+        let kanji = stream.iter().position(|&b| b == 0xE6).unwrap() + 2;
+        assert_eq!(
+            feed(&[
+                &stream[..e_acute],
+                &stream[e_acute..kanji],
+                &stream[kanji..]
+            ]),
+            vec!["{\"text\":\"caf\u{e9} \u{65e5}\u{672c}\"}"]
+        );
+    }
+
+    #[test]
+    fn test_sse_splits_crlf_events_across_chunks() {
+        assert_eq!(
+            feed(&[
+                b"event: ping\r\ndata: 1\r",
+                b"\n\r",
+                b"\ndata: 2\r\n\r\ndata: 3"
+            ]),
+            vec!["1", "2"]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Language of user-facing error messages
+//!
+//! Commands return `IpcError` with a `message` made of the category from the
+//! catalog below, looked up by error code in the selected language, and the
+//! service's reason; `detail` has the service's own English text. English
+//! keeps the service text as the message. The
+//! selection is stored with `set_locale` and applied at startup.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::Mutex;
+
+use crate::error::XenotesterError;
+use crate::services::settings;
+
+/// Settings key of the selected language
+const LOCALE_KEY: &str = "locale";
+
+/// Language of error messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Ja,
+    De,
+}
+
+static LOCALE: Mutex<Locale> = Mutex::new(Locale::En);
+
+/// Current language
+pub fn locale() -> Locale {
+    *LOCALE.lock().unwrap()
+}
+
+/// Use a language for subsequent errors
+pub fn apply_locale(locale: Locale) {
+    *LOCALE.lock().unwrap() = locale;
+}
+
+/// Apply the stored language, if any
+pub async fn load_locale(pool: &SqlitePool) -> Result<(), XenotesterError> {
+    if let Some(locale) = settings::get_json(pool, LOCALE_KEY).await? {
+        apply_locale(locale);
+    }
+    Ok(())
+}
+
+/// Apply and store a language
+pub async fn save_locale(pool: &SqlitePool, locale: Locale) -> Result<(), XenotesterError> {
+    apply_locale(locale);
+    settings::set_json(pool, LOCALE_KEY, &locale).await
+}
+
+/// Catalog message for an error code, None for English and unknown codes
+pub fn error_message(code: &str, locale: Locale) -> Option<&'static str> {
+    let message = match locale {
+        Locale::En => return None,
+        Locale::Ja => match code {
+            "CAPTURE_ERROR" => "画面をキャプチャできませんでした",
+            "INPUT_ERROR" => "マウスまたはキーボードの操作に失敗しました",
+            "PERMISSION_ERROR" => "必要な権限がありません",
+            "CONFIG_ERROR" => "設定に問題があります",
+            "IMAGE_ERROR" => "画像を処理できませんでした",
+            "DATABASE_ERROR" => "データベースエラーが発生しました",
+            "LLM_ERROR" => "AI モデルへのリクエストに失敗しました",
+            "RECORDING_ERROR" => "録画に失敗しました",
+            "ACCESSIBILITY_ERROR" => "UI 要素を取得できませんでした",
+            "PROCESS_ERROR" => "アプリを起動または終了できませんでした",
+            "SECRET_ERROR" => "シークレットを保存または読み込みできませんでした",
+            "REMOTE_ERROR" => "リモート操作でエラーが発生しました",
+            "AUTH_ERROR" => "認証に失敗しました",
+            "NOTIFICATION_ERROR" => "通知を送信できませんでした",
+            "NETWORK_ERROR" => "ネットワークエラーが発生しました",
+            "NOT_FOUND" => "対象が見つかりません",
+            "INVALID_ARGUMENT" => "入力内容が正しくありません",
+            "BLOCKED" => "操作がブロックされました",
+            "INTERNAL_ERROR" => "内部エラーが発生しました",
+            "TIMEOUT" => "タイムアウトしました",
+            "CANCELLED" => "操作がキャンセルされました",
+            _ => return None,
+        },
+        Locale::De => match code {
+            "CAPTURE_ERROR" => "Der Bildschirm konnte nicht aufgenommen werden",
+            "INPUT_ERROR" => "Die Maus- oder Tastatureingabe ist fehlgeschlagen",
+            "PERMISSION_ERROR" => "Eine erforderliche Berechtigung fehlt",
+            "CONFIG_ERROR" => "Die Konfiguration ist fehlerhaft",
+            "IMAGE_ERROR" => "Das Bild konnte nicht verarbeitet werden",
+            "DATABASE_ERROR" => "Datenbankfehler",
+            "LLM_ERROR" => "Die Anfrage an das KI-Modell ist fehlgeschlagen",
+            "RECORDING_ERROR" => "Die Aufnahme ist fehlgeschlagen",
+            "ACCESSIBILITY_ERROR" => "Das UI-Element konnte nicht abgefragt werden",
+            "PROCESS_ERROR" => "Die App konnte nicht gestartet oder beendet werden",
+            "SECRET_ERROR" => "Das Geheimnis konnte nicht gespeichert oder gelesen werden",
+            "REMOTE_ERROR" => "Fehler bei der Fernsteuerung",
+            "AUTH_ERROR" => "Die Anmeldung ist fehlgeschlagen",
+            "NOTIFICATION_ERROR" => "Die Benachrichtigung konnte nicht gesendet werden",
+            "NETWORK_ERROR" => "Netzwerkfehler",
+            "NOT_FOUND" => "Nicht gefunden",
+            "INVALID_ARGUMENT" => "Ungültige Eingabe",
+            "BLOCKED" => "Die Aktion wurde blockiert",
+            "INTERNAL_ERROR" => "Interner Fehler",
+            "TIMEOUT" => "Zeitüberschreitung",
+            "CANCELLED" => "Der Vorgang wurde abgebrochen",
+            _ => return None,
+        },
+    };
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One of each error kind, to check the catalog covers every code
+    fn all_errors() -> Vec<XenotesterError> {
+        let detail = || "detail".to_string();
+        vec![
+            XenotesterError::CaptureError(detail()),
+            XenotesterError::InputError(detail()),
+            XenotesterError::PermissionError(detail()),
+            XenotesterError::ConfigError(detail()),
+            XenotesterError::ImageError(detail()),
+            XenotesterError::DatabaseError(detail()),
+            XenotesterError::LlmError(detail()),
+            XenotesterError::RecordingError(detail()),
+            XenotesterError::AccessibilityError(detail()),
+            XenotesterError::ProcessError(detail()),
+            XenotesterError::SecretError(detail()),
+            XenotesterError::RemoteError(detail()),
+            XenotesterError::AuthError(detail()),
+            XenotesterError::NotificationError(detail()),
+            XenotesterError::NetworkError(detail()),
+            XenotesterError::NotFound(detail()),
+            XenotesterError::InvalidArgument(detail()),
+            XenotesterError::Blocked(detail()),
+            XenotesterError::Internal(detail()),
+            XenotesterError::Timeout(detail()),
+            XenotesterError::Cancelled,
+        ]
+    }
+
+    #[test]
+    fn test_catalog_covers_every_code() {
+        for code in all_errors().iter().map(XenotesterError::code) {
+            assert!(error_message(code, Locale::Ja).is_some(), "{}", code);
+            assert!(error_message(code, Locale::De).is_some(), "{}", code);
+            assert_eq!(error_message(code, Locale::En), None);
+        }
+        assert_eq!(error_message("NO_SUCH_CODE", Locale::Ja), None);
+    }
+
+    #[test]
+    fn test_locale_serializes_as_language_code() {
+        assert_eq!(serde_json::to_string(&Locale::Ja).unwrap(), "\"ja\"");
+        assert_eq!(
+            serde_json::from_str::<Locale>("\"de\"").unwrap(),
+            Locale::De
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! LRU cache of template match results
+//!
+//! Waits match the same hint images against an unchanged screen over and over.
+//! Results are keyed by a hash of the screenshot, the template (and mask) and
+//! the matching parameters, so a repeated request returns without any NCC work.
+
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::services::template_matcher::{MatchOptions, MatchResult, TemplateSource};
+
+/// Results kept before the least recently used one is evicted
+pub const DEFAULT_MATCH_CACHE_CAPACITY: usize = 256;
+
+/// SHA-256 of a screenshot, template and parameters
+pub type MatchKey = [u8; 32];
+
+struct Entries {
+    results: HashMap<MatchKey, MatchResult>,
+    /// Keys from least to most recently used
+    order: VecDeque<MatchKey>,
+}
+
+/// In-memory LRU cache of match results
+pub struct MatchCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl MatchCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries {
+                results: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Hash of the screenshot data, shared by the keys of one call
+    pub fn hash_screenshot(screenshot_base64: &str) -> MatchKey {
+        Sha256::digest(screenshot_base64.as_bytes()).into()
+    }
+
+    /// Key of one template matched against a screenshot with the given parameters
+    pub fn key(
+        screenshot_hash: &MatchKey,
+        template: TemplateSource<'_>,
+        scale_factor: f64,
+        confidence_threshold: f32,
+        options: &MatchOptions,
+    ) -> MatchKey {
+        let mut hasher = Sha256::new();
+        hasher.update(screenshot_hash);
+        hasher.update(template.image.as_bytes());
+        // Separator keeps "image + mask" from colliding with a longer image
+        hasher.update([0]);
+        if let Some(mask) = template.mask {
+            hasher.update(mask.as_bytes());
+        }
+        hasher.update(scale_factor.to_le_bytes());
+        hasher.update(confidence_threshold.to_le_bytes());
+        hasher.update(format!("{:?}", options).as_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Cached result, marked as most recently used
+    pub fn get(&self, key: &MatchKey) -> Option<MatchResult> {
+        let mut entries = self.entries.lock().unwrap();
+        let result = entries.results.get(key).cloned()?;
+        touch(&mut entries.order, key);
+        Some(result)
+    }
+
+    /// Store a result, evicting the least recently used one when full
+    pub fn insert(&self, key: MatchKey, result: MatchResult) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.results.insert(key, result).is_some() {
+            touch(&mut entries.order, &key);
+            return;
+        }
+        entries.order.push_back(key);
+        while entries.results.len() > self.capacity {
+            match entries.order.pop_front() {
+                Some(oldest) => {
+                    entries.results.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Drop all results, returning how many there were
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        entries.order.clear();
+        let count = entries.results.len();
+        entries.results.clear();
+        count
+    }
+}
+
+impl Default for MatchCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MATCH_CACHE_CAPACITY)
+    }
+}
+
+/// Move a key to the most recently used end
+fn touch(order: &mut VecDeque<MatchKey>, key: &MatchKey) {
+    if let Some(position) = order.iter().position(|k| k == key) {
+        order.remove(position);
+    }
+    order.push_back(*key);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(confidence: f32) -> MatchResult {
+        MatchResult {
+            found: false,
+            center_x: None,
+            center_y: None,
+            original_center_x: None,
+            original_center_y: None,
+            confidence: Some(confidence),
+            template_width: 1,
+            template_height: 1,
+            error: None,
+            error_code: None,
+            target_x: None,
+            target_y: None,
+            quadrilateral: None,
+        }
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = MatchCache::new(2);
+        let screenshot = MatchCache::hash_screenshot("screen");
+        let options = MatchOptions::default();
+        let key = |image: &str| MatchCache::key(&screenshot, image.into(), 1.0, 0.7, &options);
+
+        cache.insert(key("a"), result(0.1));
+        cache.insert(key("b"), result(0.2));
+        assert!(cache.get(&key("a")).is_some());
+        cache.insert(key("c"), result(0.3));
+
+        assert!(cache.get(&key("b")).is_none());
+        assert_eq!(cache.get(&key("a")).unwrap().confidence, Some(0.1));
+        assert_ne!(
+            key("a"),
+            MatchCache::key(&screenshot, "a".into(), 1.0, 0.8, &options)
+        );
+        assert_eq!(cache.clear(), 2);
+        assert!(cache.get(&key("c")).is_none());
+    }
+}
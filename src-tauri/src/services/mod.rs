@@ -1,7 +1,60 @@
 //! Service modules
 
+pub mod accessibility;
+pub mod action_log;
+pub mod annotate;
+pub mod artifacts;
+pub mod assertion;
+pub mod baseline;
 pub mod capture;
+pub mod capture_cache;
+pub mod capture_history;
+pub mod capture_stream;
+pub mod computer_action;
+pub mod confirmation;
+pub mod coords;
+pub mod database;
+pub mod dataset;
+pub mod debugger;
+pub mod environment;
+pub mod focus_mode;
+pub mod governor;
+pub mod http;
+pub mod image_diff;
 pub mod image_processor;
+pub mod input_device;
+pub mod input_player;
+pub mod input_recorder;
+pub mod input_worker;
 pub mod keyboard;
+pub mod keychain;
+pub mod llm;
+pub mod locale;
+pub mod match_cache;
 pub mod mouse;
+pub mod notify;
+pub mod ncc_simd;
+pub mod oauth;
+pub mod orchestrator;
+pub mod privacy;
+pub mod process;
+pub mod recorder;
+pub mod remote;
+pub mod report;
+pub mod retry;
+pub mod run_analytics;
+pub mod run_history;
+pub mod run_journal;
+pub mod runner;
+pub mod scenario_store;
+pub mod schema;
+pub mod screen_check;
+pub mod secrets;
+pub mod settings;
+pub mod steps;
+pub mod sync;
 pub mod template_matcher;
+pub mod usage;
+pub mod user_activity;
+pub mod variables;
+pub mod webhook;
@@ -1,10 +1,18 @@
 //! Mouse operation service using enigo
-
-use enigo::{Button, Coordinate, Direction, Enigo, Mouse, Settings};
+//!
+//! Operations take the run's cancellation token and check it between steps, so
+//! an emergency stop aborts them mid-way with `XenotesterError::Cancelled`.
+//! A held button is always released before returning. All calls go through the
+//! shared `InputDevice`, which is held for the whole operation.
+
+use enigo::{Axis, Button, Coordinate, Direction, Mouse};
+use serde::{Deserialize, Serialize};
 use std::thread;
 use std::time::Duration;
 
 use crate::error::XenotesterError;
+use crate::services::input_device::InputDevice;
+use crate::utils::cancel::CancellationToken;
 
 // Mouse timing constants
 // On macOS, the window manager needs more time to register mouse position
@@ -24,12 +32,45 @@ const MULTI_CLICK_INTERVAL_MS: u64 = 50;
 // involves multiple coordinated actions that require reliable timing
 const DRAG_STEP_DELAY_MS: u64 = 50;
 
+// Interval between intermediate positions during smooth movement (~60 updates/sec)
+pub const SMOOTH_MOVE_INTERVAL_MS: u64 = 16;
+
+// Wheel travel of one notch in pixels (Chromium/Electron default), used to
+// convert pixel scrolling to wheel units
+pub const PIXELS_PER_WHEEL_NOTCH: f64 = 100.0;
+
+// Largest pixel distance sent per wheel event during pixel scrolling
+const PIXEL_SCROLL_STEP: i64 = 20;
+
+// How far the bezier control point bends away from the straight line,
+// as a fraction of the total travel distance
+const BEZIER_CURVE_RATIO: f64 = 0.15;
+
 /// Mouse button types
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum MouseButton {
     Left,
     Right,
     Middle,
+    /// Button 4 (browser back)
+    Back,
+    /// Button 5 (browser forward)
+    Forward,
+}
+
+impl MouseButton {
+    /// Parse a button name ("left", "right", "middle", "back"/"button4", "forward"/"button5")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "left" | "button1" => Some(MouseButton::Left),
+            "right" | "button2" => Some(MouseButton::Right),
+            "middle" | "button3" => Some(MouseButton::Middle),
+            "back" | "button4" | "x1" => Some(MouseButton::Back),
+            "forward" | "button5" | "x2" => Some(MouseButton::Forward),
+            _ => None,
+        }
+    }
 }
 
 impl From<MouseButton> for Button {
@@ -38,6 +79,8 @@ impl From<MouseButton> for Button {
             MouseButton::Left => Button::Left,
             MouseButton::Right => Button::Right,
             MouseButton::Middle => Button::Middle,
+            MouseButton::Back => Button::Back,
+            MouseButton::Forward => Button::Forward,
         }
     }
 }
@@ -51,111 +94,184 @@ pub enum ScrollDirection {
     Right,
 }
 
-/// Create a new Enigo instance
-fn create_enigo() -> Result<Enigo, XenotesterError> {
-    Enigo::new(&Settings::default()).map_err(|e| XenotesterError::InputError(e.to_string()))
+/// Absolute screen position
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
 }
 
-/// Move mouse to absolute position
-pub fn move_mouse(x: i32, y: i32) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
-    enigo
-        .move_mouse(x, y, Coordinate::Abs)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))
+/// Interpolation path for smooth mouse movement
+#[derive(Debug, Clone, Copy)]
+pub enum MovePath {
+    /// Straight line from the current position to the target
+    Linear,
+    /// Gently curved quadratic bezier, closer to how a human moves the mouse
+    Bezier,
 }
 
-/// Click at absolute position
-pub fn click(x: i32, y: i32, button: MouseButton) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
-
-    // Move to position
-    enigo
-        .move_mouse(x, y, Coordinate::Abs)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
-
-    // Wait for window manager to register mouse position
-    thread::sleep(Duration::from_millis(MOUSE_MOVE_SETTLE_DELAY_MS));
+/// Get current absolute cursor position
+pub fn get_position() -> Result<(i32, i32), XenotesterError> {
+    InputDevice::acquire()?.cursor_position()
+}
 
-    // Click
-    enigo
-        .button(button.into(), Direction::Click)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+/// Move mouse to absolute position
+pub fn move_mouse(x: i32, y: i32, cancel: &CancellationToken) -> Result<(), XenotesterError> {
+    cancel.check()?;
+    let mut device = InputDevice::acquire()?;
+    move_to(&mut device, x, y)
+}
 
-    // Wait for system to process the click
-    thread::sleep(Duration::from_millis(POST_ACTION_DELAY_MS));
+/// Move mouse to absolute position along an interpolated path
+///
+/// Instead of teleporting the cursor, intermediate positions are emitted over
+/// `duration_ms` with ease-in-out timing. Some apps (drag handles, hover menus)
+/// ignore instant jumps and only react to a continuous stream of move events.
+pub fn move_mouse_smooth(
+    x: i32,
+    y: i32,
+    duration_ms: u64,
+    path: MovePath,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let mut device = InputDevice::acquire()?;
+
+    let start = device.run(|enigo| enigo.location())?;
+
+    let steps = (duration_ms / SMOOTH_MOVE_INTERVAL_MS).max(1) as usize;
+    let step_delay = Duration::from_millis(duration_ms / steps as u64);
+
+    for (px, py) in interpolate_path(start, (x, y), steps, path) {
+        cancel.check()?;
+        move_to(&mut device, px, py)?;
+        cancel.sleep(step_delay)?;
+    }
 
     Ok(())
 }
 
-/// Double click at absolute position
-pub fn double_click(x: i32, y: i32) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
-
-    // Move to position
-    enigo
-        .move_mouse(x, y, Coordinate::Abs)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
-
-    // Wait for window manager to register mouse position
-    thread::sleep(Duration::from_millis(MOUSE_MOVE_SETTLE_DELAY_MS));
+/// Compute `steps` intermediate points from `start` to `end` (end inclusive)
+fn interpolate_path(
+    start: (i32, i32),
+    end: (i32, i32),
+    steps: usize,
+    path: MovePath,
+) -> Vec<(i32, i32)> {
+    let (sx, sy) = (start.0 as f64, start.1 as f64);
+    let (ex, ey) = (end.0 as f64, end.1 as f64);
+
+    // Control point: midpoint pushed perpendicular to the line of travel
+    let (dx, dy) = (ex - sx, ey - sy);
+    let (cx, cy) = (
+        (sx + ex) / 2.0 - dy * BEZIER_CURVE_RATIO,
+        (sy + ey) / 2.0 + dx * BEZIER_CURVE_RATIO,
+    );
+
+    (1..=steps)
+        .map(|i| {
+            let linear_t = i as f64 / steps as f64;
+            // Smoothstep easing: accelerate at the start, decelerate at the end
+            let t = linear_t * linear_t * (3.0 - 2.0 * linear_t);
+
+            let (px, py) = match path {
+                MovePath::Linear => (sx + dx * t, sy + dy * t),
+                MovePath::Bezier => {
+                    let u = 1.0 - t;
+                    (
+                        u * u * sx + 2.0 * u * t * cx + t * t * ex,
+                        u * u * sy + 2.0 * u * t * cy + t * t * ey,
+                    )
+                }
+            };
+
+            (px.round() as i32, py.round() as i32)
+        })
+        .collect()
+}
 
-    // Double click - interval must be short enough to register as double click
-    enigo
-        .button(Button::Left, Direction::Click)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+/// Move the cursor without any delay
+fn move_to(device: &mut InputDevice, x: i32, y: i32) -> Result<(), XenotesterError> {
+    device.run(|enigo| enigo.move_mouse(x, y, Coordinate::Abs))
+}
 
-    thread::sleep(Duration::from_millis(MULTI_CLICK_INTERVAL_MS));
+/// Send a button event
+fn button_event(
+    device: &mut InputDevice,
+    button: Button,
+    direction: Direction,
+) -> Result<(), XenotesterError> {
+    device.run(|enigo| enigo.button(button, direction))
+}
 
-    enigo
-        .button(Button::Left, Direction::Click)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+/// Move to position and wait for the window manager to register it
+fn move_and_settle(
+    device: &mut InputDevice,
+    x: i32,
+    y: i32,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    cancel.check()?;
+    move_to(device, x, y)?;
+    cancel.sleep(Duration::from_millis(MOUSE_MOVE_SETTLE_DELAY_MS))
+}
 
-    // Wait for system to process the clicks
-    thread::sleep(Duration::from_millis(POST_ACTION_DELAY_MS));
+/// Click at absolute position
+pub fn click(
+    x: i32,
+    y: i32,
+    button: MouseButton,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    multi_click(x, y, button, 1, cancel)
+}
 
-    Ok(())
+/// Double click at absolute position
+pub fn double_click(x: i32, y: i32, cancel: &CancellationToken) -> Result<(), XenotesterError> {
+    multi_click(x, y, MouseButton::Left, 2, cancel)
 }
 
 /// Triple click at absolute position
-pub fn triple_click(x: i32, y: i32) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
-
-    // Move to position
-    enigo
-        .move_mouse(x, y, Coordinate::Abs)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
-
-    // Wait for window manager to register mouse position
-    thread::sleep(Duration::from_millis(MOUSE_MOVE_SETTLE_DELAY_MS));
-
-    // Triple click - interval must be short enough to register as triple click
-    for _ in 0..3 {
-        enigo
-            .button(Button::Left, Direction::Click)
-            .map_err(|e| XenotesterError::InputError(e.to_string()))?;
-        thread::sleep(Duration::from_millis(MULTI_CLICK_INTERVAL_MS));
+pub fn triple_click(x: i32, y: i32, cancel: &CancellationToken) -> Result<(), XenotesterError> {
+    multi_click(x, y, MouseButton::Left, 3, cancel)
+}
+
+/// Click `count` times at absolute position
+/// The interval between clicks must be short enough to register as a multi-click
+pub fn multi_click(
+    x: i32,
+    y: i32,
+    button: MouseButton,
+    count: u32,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let mut device = InputDevice::acquire()?;
+
+    move_and_settle(&mut device, x, y, cancel)?;
+
+    for i in 0..count {
+        if i > 0 {
+            cancel.sleep(Duration::from_millis(MULTI_CLICK_INTERVAL_MS))?;
+        }
+        button_event(&mut device, button.into(), Direction::Click)?;
     }
 
-    // Wait for system to process the clicks
+    // Wait for system to process the click(s)
     thread::sleep(Duration::from_millis(POST_ACTION_DELAY_MS));
 
     Ok(())
 }
 
 /// Mouse down at absolute position
-pub fn mouse_down(x: i32, y: i32, button: MouseButton) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
-
-    enigo
-        .move_mouse(x, y, Coordinate::Abs)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+pub fn mouse_down(
+    x: i32,
+    y: i32,
+    button: MouseButton,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let mut device = InputDevice::acquire()?;
 
-    // Wait for window manager to register mouse position
-    thread::sleep(Duration::from_millis(MOUSE_MOVE_SETTLE_DELAY_MS));
-
-    enigo
-        .button(button.into(), Direction::Press)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+    move_and_settle(&mut device, x, y, cancel)?;
+    button_event(&mut device, button.into(), Direction::Press)?;
 
     // Wait for system to process the press
     thread::sleep(Duration::from_millis(POST_ACTION_DELAY_MS));
@@ -164,19 +280,19 @@ pub fn mouse_down(x: i32, y: i32, button: MouseButton) -> Result<(), XenotesterE
 }
 
 /// Mouse up at absolute position
-pub fn mouse_up(x: i32, y: i32, button: MouseButton) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
-
-    enigo
-        .move_mouse(x, y, Coordinate::Abs)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
-
-    // Wait for window manager to register mouse position
-    thread::sleep(Duration::from_millis(MOUSE_MOVE_SETTLE_DELAY_MS));
-
-    enigo
-        .button(button.into(), Direction::Release)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+/// If the run is cancelled, the button is released at the current position instead
+pub fn mouse_up(
+    x: i32,
+    y: i32,
+    button: MouseButton,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let mut device = InputDevice::acquire()?;
+
+    let moved = move_and_settle(&mut device, x, y, cancel);
+    // Never leave the button held, even when cancelled
+    button_event(&mut device, button.into(), Direction::Release)?;
+    moved?;
 
     // Wait for system to process the release
     thread::sleep(Duration::from_millis(POST_ACTION_DELAY_MS));
@@ -185,72 +301,305 @@ pub fn mouse_up(x: i32, y: i32, button: MouseButton) -> Result<(), XenotesterErr
 }
 
 /// Drag from start position to end position
-pub fn drag(start_x: i32, start_y: i32, end_x: i32, end_y: i32) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
+pub fn drag(
+    start_x: i32,
+    start_y: i32,
+    end_x: i32,
+    end_y: i32,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let points = [
+        Point { x: start_x, y: start_y },
+        Point { x: end_x, y: end_y },
+    ];
+    drag_path(&points, 0, cancel)
+}
+
+/// Drag from start to end, moving through `steps` intermediate positions
+/// while the button is held. Many drag-and-drop implementations ignore a
+/// single jump and need to see the cursor travel.
+pub fn drag_smooth(
+    start_x: i32,
+    start_y: i32,
+    end_x: i32,
+    end_y: i32,
+    steps: u32,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let mut points = vec![Point { x: start_x, y: start_y }];
+    points.extend(
+        interpolate_path(
+            (start_x, start_y),
+            (end_x, end_y),
+            steps.max(1) as usize,
+            MovePath::Linear,
+        )
+        .into_iter()
+        .map(|(x, y)| Point { x, y }),
+    );
+
+    drag_path(&points, SMOOTH_MOVE_INTERVAL_MS, cancel)
+}
+
+/// Drag along a sequence of waypoints
+/// The button is pressed at the first point and released at the last one
+/// (or wherever the cursor is when the run is cancelled)
+pub fn drag_path(
+    points: &[Point],
+    step_delay_ms: u64,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let (first, rest) = match points.split_first() {
+        Some((first, rest)) if !rest.is_empty() => (first, rest),
+        _ => {
+            return Err(XenotesterError::InputError(
+                "Drag path requires at least 2 points".to_string(),
+            ))
+        }
+    };
 
-    // Move to start position
-    enigo
-        .move_mouse(start_x, start_y, Coordinate::Abs)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+    let mut device = InputDevice::acquire()?;
+    let drag_delay = Duration::from_millis(DRAG_STEP_DELAY_MS);
 
-    // Wait for position to settle (consistent timing for reliable drag)
-    thread::sleep(Duration::from_millis(DRAG_STEP_DELAY_MS));
+    // Move to start position and wait for it to settle (consistent timing for reliable drag)
+    cancel.check()?;
+    move_to(&mut device, first.x, first.y)?;
+    cancel.sleep(drag_delay)?;
 
     // Press left button
-    enigo
-        .button(Button::Left, Direction::Press)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+    button_event(&mut device, Button::Left, Direction::Press)?;
+
+    // Move through each waypoint while holding the button
+    let moved = (|| {
+        cancel.sleep(drag_delay)?;
+        for point in rest {
+            cancel.check()?;
+            move_to(&mut device, point.x, point.y)?;
+            cancel.sleep(Duration::from_millis(step_delay_ms))?;
+        }
+        cancel.sleep(drag_delay)
+    })();
 
-    thread::sleep(Duration::from_millis(DRAG_STEP_DELAY_MS));
+    // Release left button, even when cancelled mid-drag
+    button_event(&mut device, Button::Left, Direction::Release)?;
+    moved?;
 
-    // Move to end position
-    enigo
-        .move_mouse(end_x, end_y, Coordinate::Abs)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+    // Wait for system to process the drag completion
+    thread::sleep(Duration::from_millis(POST_ACTION_DELAY_MS));
 
-    thread::sleep(Duration::from_millis(DRAG_STEP_DELAY_MS));
+    Ok(())
+}
 
-    // Release left button
-    enigo
-        .button(Button::Left, Direction::Release)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+/// Scroll at position by whole wheel notches (line-based)
+/// Only the axis being scrolled receives an event.
+pub fn scroll(
+    x: i32,
+    y: i32,
+    direction: ScrollDirection,
+    amount: i32,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let mut device = InputDevice::acquire()?;
+
+    move_and_settle(&mut device, x, y, cancel)?;
+
+    // enigo: positive lengths scroll down / right
+    let (axis, length) = match direction {
+        ScrollDirection::Up => (Axis::Vertical, -amount),
+        ScrollDirection::Down => (Axis::Vertical, amount),
+        ScrollDirection::Left => (Axis::Horizontal, -amount),
+        ScrollDirection::Right => (Axis::Horizontal, amount),
+    };
+    if length != 0 {
+        device.run(|enigo| enigo.scroll(length, axis))?;
+    }
 
-    // Wait for system to process the drag completion
+    // Wait for system to process the scroll
     thread::sleep(Duration::from_millis(POST_ACTION_DELAY_MS));
 
     Ok(())
 }
 
-/// Scroll at position
-pub fn scroll(x: i32, y: i32, direction: ScrollDirection, amount: i32) -> Result<(), XenotesterError> {
-    let mut enigo = create_enigo()?;
+/// Scroll at position by a pixel distance (positive dx / dy scroll right / down)
+///
+/// The distance is split into small wheel events sent at the smooth-move interval,
+/// for apps that need a continuous stream of precise wheel deltas. macOS and
+/// Windows send exact pixel/high-resolution deltas; Linux rounds to wheel notches.
+pub fn scroll_pixels(
+    x: i32,
+    y: i32,
+    dx: i32,
+    dy: i32,
+    cancel: &CancellationToken,
+) -> Result<(), XenotesterError> {
+    let mut device = InputDevice::acquire()?;
+
+    move_and_settle(&mut device, x, y, cancel)?;
+
+    let (dx, dy) = (dx as i64, dy as i64);
+    let steps = ((dx.abs().max(dy.abs()) + PIXEL_SCROLL_STEP - 1) / PIXEL_SCROLL_STEP).max(1);
+    let mut wheel = PixelWheel::default();
+    let mut sent = (0, 0);
+
+    for i in 1..=steps {
+        if i > 1 {
+            cancel.sleep(Duration::from_millis(SMOOTH_MOVE_INTERVAL_MS))?;
+        }
+        let target = (dx * i / steps, dy * i / steps);
+        let step = ((target.0 - sent.0) as i32, (target.1 - sent.1) as i32);
+        sent = target;
+        wheel.scroll(&mut device, step.0, step.1)?;
+    }
+
+    // Wait for system to process the scroll
+    thread::sleep(Duration::from_millis(POST_ACTION_DELAY_MS));
+
+    Ok(())
+}
+
+/// Emits pixel wheel deltas, carrying sub-unit remainders between steps
+/// (macOS scrolls in pixels directly and needs no remainders)
+#[derive(Default)]
+struct PixelWheel {
+    #[cfg(not(target_os = "macos"))]
+    remainder_x: f64,
+    #[cfg(not(target_os = "macos"))]
+    remainder_y: f64,
+}
+
+impl PixelWheel {
+    /// Convert pixels to whole wheel units, keeping the fraction for the next step
+    #[cfg(not(target_os = "macos"))]
+    fn units(pixels: i32, remainder: &mut f64, units_per_notch: f64) -> i32 {
+        let total = pixels as f64 * units_per_notch / PIXELS_PER_WHEEL_NOTCH + *remainder;
+        let whole = total.trunc();
+        *remainder = total - whole;
+        whole as i32
+    }
+
+    #[cfg(target_os = "macos")]
+    fn scroll(
+        &mut self,
+        device: &mut InputDevice,
+        dx: i32,
+        dy: i32,
+    ) -> Result<(), XenotesterError> {
+        // Pixel-unit scroll events
+        if dx != 0 {
+            device.run(|enigo| enigo.smooth_scroll(dx, Axis::Horizontal))?;
+        }
+        if dy != 0 {
+            device.run(|enigo| enigo.smooth_scroll(dy, Axis::Vertical))?;
+        }
+        Ok(())
+    }
 
-    // Move to position
-    enigo
-        .move_mouse(x, y, Coordinate::Abs)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+    #[cfg(target_os = "windows")]
+    fn scroll(
+        &mut self,
+        _device: &mut InputDevice,
+        dx: i32,
+        dy: i32,
+    ) -> Result<(), XenotesterError> {
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+            MOUSEEVENTF_HWHEEL, MOUSEEVENTF_WHEEL,
+        };
+        const WHEEL_DELTA: f64 = 120.0;
+
+        // High-resolution wheel deltas (fractions of WHEEL_DELTA); positive wheel is up
+        let units_x = Self::units(dx, &mut self.remainder_x, WHEEL_DELTA);
+        let units_y = Self::units(dy, &mut self.remainder_y, WHEEL_DELTA);
+        if units_x != 0 {
+            send_wheel(MOUSEEVENTF_HWHEEL, units_x)?;
+        }
+        if units_y != 0 {
+            send_wheel(MOUSEEVENTF_WHEEL, -units_y)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn scroll(
+        &mut self,
+        device: &mut InputDevice,
+        dx: i32,
+        dy: i32,
+    ) -> Result<(), XenotesterError> {
+        // X11 only knows whole wheel clicks
+        let notches_x = Self::units(dx, &mut self.remainder_x, 1.0);
+        let notches_y = Self::units(dy, &mut self.remainder_y, 1.0);
+        if notches_x != 0 {
+            device.run(|enigo| enigo.scroll(notches_x, Axis::Horizontal))?;
+        }
+        if notches_y != 0 {
+            device.run(|enigo| enigo.scroll(notches_y, Axis::Vertical))?;
+        }
+        Ok(())
+    }
+}
 
-    // Wait for window manager to register mouse position
-    thread::sleep(Duration::from_millis(MOUSE_MOVE_SETTLE_DELAY_MS));
+/// Send a single wheel event through SendInput
+#[cfg(target_os = "windows")]
+fn send_wheel(flags: u32, delta: i32) -> Result<(), XenotesterError> {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        SendInput, INPUT, INPUT_0, INPUT_MOUSE, MOUSEINPUT,
+    };
 
-    // Scroll
-    let (dx, dy) = match direction {
-        ScrollDirection::Up => (0, -amount),
-        ScrollDirection::Down => (0, amount),
-        ScrollDirection::Left => (-amount, 0),
-        ScrollDirection::Right => (amount, 0),
+    let input = INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx: 0,
+                dy: 0,
+                // Signed delta reinterpreted as the DWORD the API expects
+                mouseData: delta as u32,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
     };
 
-    enigo
-        .scroll(dx, enigo::Axis::Horizontal)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+    let sent = unsafe { SendInput(1, &input, std::mem::size_of::<INPUT>() as i32) };
+    if sent == 1 {
+        Ok(())
+    } else {
+        Err(XenotesterError::InputError("SendInput rejected the wheel event".to_string()))
+    }
+}
 
-    enigo
-        .scroll(dy, enigo::Axis::Vertical)
-        .map_err(|e| XenotesterError::InputError(e.to_string()))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate_path_linear() {
+        let points = interpolate_path((0, 0), (100, 50), 4, MovePath::Linear);
+        assert_eq!(points.len(), 4);
+        assert_eq!(points.last(), Some(&(100, 50)));
+        // Smoothstep: half way at the midpoint, short first and last steps
+        assert_eq!(points[1], (50, 25));
+        assert!(points[0].0 < 25 && points[3].0 - points[2].0 < 25);
+        // Every point is on the line
+        assert!(points.iter().all(|&(x, y)| (x - 2 * y).abs() <= 1));
+    }
 
-    // Wait for system to process the scroll
-    thread::sleep(Duration::from_millis(POST_ACTION_DELAY_MS));
+    #[test]
+    fn test_interpolate_path_bezier_bends_and_ends_on_target() {
+        let points = interpolate_path((0, 0), (200, 0), 10, MovePath::Bezier);
+        assert_eq!(points.len(), 10);
+        assert_eq!(points.last(), Some(&(200, 0)));
+        // Curves to one side of the straight line, then comes back
+        assert!(points[4].1 > 0);
+        assert!(points.iter().all(|&(_, y)| y >= 0));
+        assert!(points.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
 
-    Ok(())
+    #[test]
+    fn test_interpolate_path_single_step_jumps_to_target() {
+        assert_eq!(
+            interpolate_path((10, 10), (30, 40), 1, MovePath::Bezier),
+            vec![(30, 40)]
+        );
+    }
 }
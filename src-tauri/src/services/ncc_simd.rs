@@ -0,0 +1,133 @@
+//! Vectorized normalized cross-correlation
+//!
+//! Computes the same score as imageproc's `CrossCorrelationNormalized` for an
+//! unmasked template: the template dot products run 8 lanes at a time through
+//! `wide`, output rows are spread over rayon, and window energies come from an
+//! integral image of squares instead of being summed per position.
+
+use image::{GrayImage, ImageBuffer, Luma};
+use rayon::prelude::*;
+use wide::f32x8;
+
+/// Lanes per vector
+const LANES: usize = 8;
+
+/// Whether `wide` maps to real vector instructions on this target
+/// (SSE2 on x86_64 and NEON on aarch64 are always present)
+pub fn is_available() -> bool {
+    cfg!(any(target_arch = "x86_64", target_arch = "aarch64"))
+}
+
+/// NCC score of every template position, as `imageproc::template_matching::match_template`
+///
+/// The template must fit inside the image.
+pub fn match_template(image: &GrayImage, template: &GrayImage) -> ImageBuffer<Luma<f32>, Vec<f32>> {
+    let (image_width, image_height) = image.dimensions();
+    let (template_width, template_height) = template.dimensions();
+    let output_width = image_width - template_width + 1;
+    let output_height = image_height - template_height + 1;
+
+    let image_values: Vec<f32> = image.as_raw().iter().map(|&v| v as f32).collect();
+    let template_values: Vec<f32> = template.as_raw().iter().map(|&v| v as f32).collect();
+    let template_norm: f64 = template_values.iter().map(|&v| (v * v) as f64).sum();
+    let energy = SquaredIntegral::new(image);
+
+    let (image_width, template_width) = (image_width as usize, template_width as usize);
+    let scores: Vec<f32> = (0..output_height)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            let (image_values, template_values, energy) =
+                (&image_values, &template_values, &energy);
+            (0..output_width).map(move |x| {
+                let cross: f32 = template_values
+                    .chunks_exact(template_width)
+                    .enumerate()
+                    .map(|(ty, template_row)| {
+                        let start = (y as usize + ty) * image_width + x as usize;
+                        dot(&image_values[start..start + template_width], template_row)
+                    })
+                    .sum();
+                let window = energy.window(x, y, template_width as u32, template_height);
+                (cross as f64 / (window * template_norm).sqrt()) as f32
+            })
+        })
+        .collect();
+
+    ImageBuffer::from_raw(output_width, output_height, scores)
+        .expect("score buffer matches the output size")
+}
+
+/// Dot product of two equally long slices
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let a_chunks = a.chunks_exact(LANES);
+    let b_chunks = b.chunks_exact(LANES);
+    let tail: f32 = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+
+    let lanes = a_chunks.zip(b_chunks).fold(f32x8::ZERO, |acc, (x, y)| {
+        let x = f32x8::new(x.try_into().expect("chunk of LANES values"));
+        let y = f32x8::new(y.try_into().expect("chunk of LANES values"));
+        x.mul_add(y, acc)
+    });
+    lanes.reduce_add() + tail
+}
+
+/// Summed-area table of squared pixel values
+struct SquaredIntegral {
+    /// Row stride (image width + 1)
+    stride: usize,
+    sums: Vec<f64>,
+}
+
+impl SquaredIntegral {
+    fn new(image: &GrayImage) -> Self {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let stride = width + 1;
+        let mut sums = vec![0.0; stride * (height + 1)];
+        for (y, row) in image.as_raw().chunks_exact(width).enumerate() {
+            let mut row_sum = 0.0;
+            for (x, &value) in row.iter().enumerate() {
+                row_sum += (value as f64) * (value as f64);
+                sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row_sum;
+            }
+        }
+        Self { stride, sums }
+    }
+
+    /// Sum of squares over the `width` x `height` window at (x, y)
+    fn window(&self, x: u32, y: u32, width: u32, height: u32) -> f64 {
+        let at = |x: u32, y: u32| self.sums[y as usize * self.stride + x as usize];
+        at(x + width, y + height) - at(x, y + height) - at(x + width, y) + at(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use imageproc::template_matching::{self, MatchTemplateMethod};
+
+    #[test]
+    fn test_matches_imageproc_scores() {
+        let image = GrayImage::from_fn(61, 47, |x, y| {
+            Luma([((x * 37 + y * 91 + x * y) % 251) as u8])
+        });
+        let template = image::imageops::crop_imm(&image, 20, 11, 19, 13).to_image();
+
+        let expected = template_matching::match_template(
+            &image,
+            &template,
+            MatchTemplateMethod::CrossCorrelationNormalized,
+        );
+        let scores = match_template(&image, &template);
+
+        assert_eq!(scores.dimensions(), expected.dimensions());
+        for (score, expected) in scores.pixels().zip(expected.pixels()) {
+            assert!((score[0] - expected[0]).abs() < 1e-4);
+        }
+        assert!((scores.get_pixel(20, 11)[0] - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,186 @@
+//! Desktop notification channel
+//!
+//! Shows native OS notifications when a backend run finishes and on an
+//! emergency stop, so a minimized app does not hide failures. Notifications
+//! are shown through a callback, which the app backs with the notification
+//! plugin; the headless runner has none. Preferences are stored under the
+//! `desktop_notifications` setting.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+use crate::error::XenotesterError;
+use crate::services::settings;
+use crate::services::webhook::{
+    summarize, WebhookPayload, EMERGENCY_STOP_EVENT, RUN_COMPLETED_EVENT,
+};
+
+/// Settings key of the stored preferences
+const PREFERENCES_KEY: &str = "desktop_notifications";
+
+/// Which events show a desktop notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DesktopPreferences {
+    pub enabled: bool,
+    /// A run passed
+    pub on_success: bool,
+    /// A run failed (stopped runs are not reported)
+    pub on_failure: bool,
+    pub on_emergency_stop: bool,
+}
+
+impl Default for DesktopPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            on_success: true,
+            on_failure: true,
+            on_emergency_stop: true,
+        }
+    }
+}
+
+impl DesktopPreferences {
+    /// Whether an event may show a notification, before its data is known
+    pub fn wants(&self, event: &str) -> bool {
+        self.enabled
+            && match event {
+                RUN_COMPLETED_EVENT => self.on_success || self.on_failure,
+                EMERGENCY_STOP_EVENT => self.on_emergency_stop,
+                _ => false,
+            }
+    }
+
+    /// Whether `payload` shows a notification
+    pub fn accepts(&self, payload: &WebhookPayload) -> bool {
+        if !self.wants(&payload.event) {
+            return false;
+        }
+        match payload.event.as_str() {
+            RUN_COMPLETED_EVENT => {
+                if payload.data["success"].as_bool() == Some(true) {
+                    self.on_success
+                } else {
+                    // Stopped runs were stopped by the user, who knows already
+                    self.on_failure && payload.data["status"].as_str() != Some("stopped")
+                }
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Stored preferences, or the defaults
+pub async fn load_preferences(pool: &SqlitePool) -> Result<DesktopPreferences, XenotesterError> {
+    Ok(settings::get_json(pool, PREFERENCES_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Store preferences
+pub async fn save_preferences(
+    pool: &SqlitePool,
+    preferences: &DesktopPreferences,
+) -> Result<(), XenotesterError> {
+    settings::set_json(pool, PREFERENCES_KEY, preferences).await
+}
+
+/// Shows a notification with a title and body
+pub type ShowNotification = Arc<dyn Fn(&str, &str) + Send + Sync>;
+
+/// Native notifications for the events selected in the preferences
+#[derive(Clone)]
+pub struct DesktopChannel {
+    pub preferences: DesktopPreferences,
+    show: ShowNotification,
+}
+
+impl std::fmt::Debug for DesktopChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DesktopChannel")
+            .field("preferences", &self.preferences)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DesktopChannel {
+    pub fn new(preferences: DesktopPreferences, show: ShowNotification) -> Self {
+        Self { preferences, show }
+    }
+
+    /// Show `payload` if the preferences select it
+    pub fn send(&self, payload: &WebhookPayload) {
+        if !self.preferences.accepts(payload) {
+            return;
+        }
+        let summary = summarize(payload);
+        (self.show)(
+            &summary.headline,
+            summary.message.as_deref().unwrap_or_default(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::webhook::RUN_STARTED_EVENT;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn completed(success: bool, status: &str) -> WebhookPayload {
+        WebhookPayload::new(
+            RUN_COMPLETED_EVENT,
+            json!({
+                "scenario": { "id": "s1", "title": "Login" },
+                "success": success,
+                "status": status,
+                "message": "Button not found",
+            }),
+        )
+    }
+
+    #[test]
+    fn test_preferences_select_outcomes() {
+        let preferences = DesktopPreferences {
+            on_success: false,
+            ..Default::default()
+        };
+        assert!(!preferences.accepts(&completed(true, "success")));
+        assert!(preferences.accepts(&completed(false, "failure")));
+        assert!(!preferences.accepts(&completed(false, "stopped")));
+        assert!(!preferences.wants(RUN_STARTED_EVENT));
+
+        let disabled = DesktopPreferences {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(!disabled.wants(EMERGENCY_STOP_EVENT));
+    }
+
+    #[test]
+    fn test_send_shows_summary() {
+        let shown = Arc::new(Mutex::new(Vec::new()));
+        let sink = shown.clone();
+        let channel = DesktopChannel::new(
+            DesktopPreferences::default(),
+            Arc::new(move |title: &str, body: &str| {
+                sink.lock()
+                    .unwrap()
+                    .push((title.to_string(), body.to_string()));
+            }),
+        );
+
+        channel.send(&completed(false, "failure"));
+        channel.send(&completed(false, "stopped"));
+        assert_eq!(
+            *shown.lock().unwrap(),
+            vec![(
+                "Run failed: Login".to_string(),
+                "Button not found".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! Email notification channel
+//!
+//! Sends events as plain-text emails over SMTP, for environments that cannot
+//! receive webhooks. The configuration is stored under the `email` setting;
+//! the SMTP password is kept in the encrypted secret store. SMTP connections
+//! do not go through the HTTP proxy of `services::http`.
+
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+use crate::error::XenotesterError;
+use crate::services::notify::default_events;
+use crate::services::webhook::{summarize, WebhookPayload, SCREENSHOT_FILE_NAME};
+use crate::services::{secrets, settings};
+
+/// Settings key of the stored configuration
+const CONFIG_KEY: &str = "email";
+/// Internal secret holding the SMTP password
+pub const PASSWORD_SECRET: &str = "smtp.password";
+/// Timeout of an SMTP delivery
+const SEND_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Transport security of the SMTP connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS (usually port 587)
+    #[default]
+    StartTls,
+    /// TLS from the start (usually port 465)
+    Tls,
+    /// Unencrypted, for local relays only
+    None,
+}
+
+/// SMTP server and recipients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EmailConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    /// Login name; no authentication when unset
+    pub username: Option<String>,
+    /// Sender, e.g. "Xenotester <tests@example.com>"
+    pub from: String,
+    pub to: Vec<String>,
+    /// Subscribed events; "*" subscribes to all
+    pub events: Vec<String>,
+    /// Attach a screenshot of the screen to failure events
+    pub attach_screenshot: bool,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: 587,
+            security: SmtpSecurity::default(),
+            username: None,
+            from: String::new(),
+            to: Vec::new(),
+            events: default_events(),
+            attach_screenshot: false,
+        }
+    }
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, XenotesterError> {
+    address.trim().parse().map_err(|e| {
+        XenotesterError::ConfigError(format!("Invalid email address {}: {}", address, e))
+    })
+}
+
+impl EmailConfig {
+    pub fn validate(&self) -> Result<(), XenotesterError> {
+        if self.host.trim().is_empty() {
+            return Err(XenotesterError::ConfigError(
+                "SMTP host is required".to_string(),
+            ));
+        }
+        if self.port == 0 {
+            return Err(XenotesterError::ConfigError(
+                "SMTP port must not be 0".to_string(),
+            ));
+        }
+        mailbox(&self.from)?;
+        if self.to.is_empty() {
+            return Err(XenotesterError::ConfigError(
+                "At least one recipient is required".to_string(),
+            ));
+        }
+        self.to.iter().try_for_each(|to| mailbox(to).map(drop))
+    }
+}
+
+/// Stored configuration, if any
+pub async fn load_config(pool: &SqlitePool) -> Result<Option<EmailConfig>, XenotesterError> {
+    settings::get_json(pool, CONFIG_KEY).await
+}
+
+/// Validate and store a configuration
+pub async fn save_config(pool: &SqlitePool, config: &EmailConfig) -> Result<(), XenotesterError> {
+    config.validate()?;
+    settings::set_json(pool, CONFIG_KEY, config).await
+}
+
+/// Stored SMTP password, if any
+pub async fn load_password(pool: &SqlitePool) -> Result<Option<String>, XenotesterError> {
+    secrets::resolve_internal(pool, PASSWORD_SECRET).await
+}
+
+/// An SMTP server receiving notifications
+#[derive(Clone)]
+pub struct EmailChannel {
+    pub config: EmailConfig,
+    password: Option<String>,
+}
+
+// The password stays out of logs
+impl std::fmt::Debug for EmailChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmailChannel")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EmailChannel {
+    pub fn new(config: EmailConfig, password: Option<String>) -> Self {
+        Self { config, password }
+    }
+
+    /// Send an event as an email
+    pub async fn send(
+        &self,
+        payload: &WebhookPayload,
+        screenshot_png: Option<&[u8]>,
+    ) -> Result<(), XenotesterError> {
+        let screenshot = screenshot_png.filter(|_| self.config.attach_screenshot);
+        let message = build_message(&self.config, payload, screenshot)?;
+        self.transport()?.send(message).await.map_err(|e| {
+            XenotesterError::NotificationError(format!("SMTP delivery failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, XenotesterError> {
+        let host = self.config.host.trim();
+        let builder = match self.config.security {
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+            SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                host,
+            )),
+        }
+        .map_err(|e| XenotesterError::ConfigError(format!("Invalid SMTP server: {}", e)))?
+        .port(self.config.port)
+        .timeout(Some(SEND_TIMEOUT));
+
+        let builder = match self.config.username.as_deref().filter(|u| !u.is_empty()) {
+            Some(username) => builder.credentials(Credentials::new(
+                username.to_string(),
+                self.password.clone().unwrap_or_default(),
+            )),
+            None => builder,
+        };
+        Ok(builder.build())
+    }
+}
+
+/// Plain-text body: the summary, its facts and the timestamp
+fn render_text(payload: &WebhookPayload) -> (String, String) {
+    let summary = summarize(payload);
+    let mut body = format!("{}\n\n", summary.headline);
+    if let Some(message) = &summary.message {
+        body.push_str(message);
+        body.push_str("\n\n");
+    }
+    for (label, value) in &summary.facts {
+        body.push_str(&format!("{}: {}\n", label, value));
+    }
+    body.push_str(&format!(
+        "Event: {}\nTime: {}\n",
+        payload.event, payload.timestamp
+    ));
+    (summary.headline, body)
+}
+
+fn build_message(
+    config: &EmailConfig,
+    payload: &WebhookPayload,
+    screenshot_png: Option<&[u8]>,
+) -> Result<Message, XenotesterError> {
+    let (subject, body) = render_text(payload);
+    let mut builder = Message::builder()
+        .from(mailbox(&config.from)?)
+        .subject(subject);
+    for to in &config.to {
+        builder = builder.to(mailbox(to)?);
+    }
+
+    let message = match screenshot_png {
+        Some(png) => {
+            let attachment = Attachment::new(SCREENSHOT_FILE_NAME.to_string()).body(
+                png.to_vec(),
+                ContentType::parse("image/png").expect("valid MIME type"),
+            );
+            builder.multipart(
+                MultiPart::mixed()
+                    .singlepart(SinglePart::plain(body))
+                    .singlepart(attachment),
+            )
+        }
+        None => builder.header(ContentType::TEXT_PLAIN).body(body),
+    };
+    message.map_err(|e| XenotesterError::NotificationError(format!("Invalid email: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::webhook::TEST_FAILURE_EVENT;
+    use serde_json::json;
+
+    fn config() -> EmailConfig {
+        EmailConfig {
+            enabled: true,
+            host: "smtp.example.com".to_string(),
+            from: "Xenotester <tests@example.com>".to_string(),
+            to: vec!["qa@example.com".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(config().validate().is_ok());
+        assert!(EmailConfig::default().validate().is_err());
+
+        let mut bad_recipient = config();
+        bad_recipient.to.push("not an address".to_string());
+        assert!(bad_recipient.validate().is_err());
+    }
+
+    #[test]
+    fn test_render_text() {
+        let payload = WebhookPayload::new(
+            TEST_FAILURE_EVENT,
+            json!({
+                "scenario": { "id": "s1", "title": "Login" },
+                "error": { "message": "Button not found", "completed_actions": 3 },
+            }),
+        );
+        let (subject, body) = render_text(&payload);
+        assert_eq!(subject, "Scenario failed: Login");
+        assert!(body.contains("Button not found"));
+        assert!(body.contains("Completed actions: 3"));
+        assert!(build_message(&config(), &payload, Some(&[1, 2, 3])).is_ok());
+    }
+}
@@ -0,0 +1,289 @@
+//! Notification pipeline
+//!
+//! Backend runs report lifecycle events (the `*_EVENT` constants in `webhook`)
+//! to a `Notifier`, which delivers each event to every channel subscribed to
+//! it: webhooks, email (see `email`) and native desktop notifications (see
+//! `desktop`). The app builds its notifier from the stored settings: the
+//! webhook URL the frontend stores as `failure_webhook_url`, with the format
+//! and event subscriptions under the `webhook` setting, and the `email` setting.
+//! The desktop channel needs the app, so it is added by the app's commands.
+//! The headless runner builds its notifier from the command line.
+//!
+//! Delivery failures are logged and never affect the run.
+
+pub mod desktop;
+pub mod email;
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tracing::warn;
+
+use crate::error::XenotesterError;
+use crate::services::settings;
+use crate::services::webhook::{
+    self, NotificationFormat, ScreenshotDelivery, WebhookPayload, EMERGENCY_STOP_EVENT,
+    STEP_FAILED_EVENT, TEST_FAILURE_EVENT,
+};
+use desktop::DesktopChannel;
+use email::EmailChannel;
+
+/// Setting with the webhook URL (shared with the frontend's settings service)
+const WEBHOOK_URL_KEY: &str = "failure_webhook_url";
+/// Setting with the webhook format and event subscriptions
+const WEBHOOK_SETTINGS_KEY: &str = "webhook";
+
+/// Events a webhook receives unless configured otherwise
+/// Lifecycle events are opt-in, so existing failure hooks are not flooded.
+pub const DEFAULT_EVENTS: &[&str] = &[TEST_FAILURE_EVENT, STEP_FAILED_EVENT, EMERGENCY_STOP_EVENT];
+
+fn default_events() -> Vec<String> {
+    DEFAULT_EVENTS
+        .iter()
+        .map(|event| event.to_string())
+        .collect()
+}
+
+fn subscribed(events: &[String], event: &str) -> bool {
+    events.iter().any(|e| e == event || e == "*")
+}
+
+/// Stored webhook preferences
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebhookSettings {
+    pub format: NotificationFormat,
+    /// Subscribed events; "*" subscribes to all
+    pub events: Vec<String>,
+    /// Attach a screenshot of the screen to failure events
+    pub attach_screenshot: bool,
+    pub screenshot_delivery: ScreenshotDelivery,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            format: NotificationFormat::default(),
+            events: default_events(),
+            attach_screenshot: false,
+            screenshot_delivery: ScreenshotDelivery::default(),
+        }
+    }
+}
+
+/// A webhook receiving notifications
+#[derive(Debug, Clone)]
+pub struct WebhookChannel {
+    pub url: String,
+    pub format: NotificationFormat,
+    pub events: Vec<String>,
+    /// Screenshot delivery for failure events; None sends no screenshot
+    pub screenshot: Option<ScreenshotDelivery>,
+}
+
+impl WebhookChannel {
+    /// Webhook subscribed to the default events, without screenshots
+    pub fn new(url: impl Into<String>, format: NotificationFormat) -> Self {
+        Self {
+            url: url.into(),
+            format,
+            events: default_events(),
+            screenshot: None,
+        }
+    }
+
+    fn accepts(&self, event: &str) -> bool {
+        subscribed(&self.events, event)
+    }
+}
+
+/// Delivers events to the configured channels
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    webhooks: Vec<WebhookChannel>,
+    emails: Vec<EmailChannel>,
+    desktop: Option<DesktopChannel>,
+}
+
+impl Notifier {
+    /// Notifier without channels
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn with_webhook(mut self, channel: WebhookChannel) -> Self {
+        self.webhooks.push(channel);
+        self
+    }
+
+    pub fn with_email(mut self, channel: EmailChannel) -> Self {
+        self.emails.push(channel);
+        self
+    }
+
+    pub fn with_desktop(mut self, channel: DesktopChannel) -> Self {
+        self.desktop = Some(channel);
+        self
+    }
+
+    /// Notifier for the stored settings
+    pub async fn from_settings(pool: &SqlitePool) -> Result<Self, XenotesterError> {
+        let mut notifier = Self::disabled();
+
+        let url = settings::get_setting(pool, WEBHOOK_URL_KEY)
+            .await?
+            .unwrap_or_default();
+        if webhook::is_valid_webhook_url(&url) {
+            let settings = load_webhook_settings(pool).await?;
+            notifier = notifier.with_webhook(WebhookChannel {
+                url,
+                format: settings.format,
+                events: settings.events,
+                screenshot: settings
+                    .attach_screenshot
+                    .then_some(settings.screenshot_delivery),
+            });
+        }
+
+        if let Some(config) = email::load_config(pool).await?.filter(|c| c.enabled) {
+            let password = email::load_password(pool).await?;
+            notifier = notifier.with_email(EmailChannel::new(config, password));
+        }
+        Ok(notifier)
+    }
+
+    /// Whether any channel is subscribed to `event`
+    /// Lets callers skip building event data nobody receives.
+    pub fn wants(&self, event: &str) -> bool {
+        self.webhooks.iter().any(|channel| channel.accepts(event))
+            || self
+                .emails
+                .iter()
+                .any(|channel| subscribed(&channel.config.events, event))
+            || self
+                .desktop
+                .as_ref()
+                .is_some_and(|channel| channel.preferences.wants(event))
+    }
+
+    /// Whether a channel subscribed to `event` wants a screenshot with it
+    /// Lets callers skip capturing one nobody receives.
+    pub fn wants_screenshot(&self, event: &str) -> bool {
+        self.webhooks
+            .iter()
+            .any(|channel| channel.accepts(event) && channel.screenshot.is_some())
+            || self.emails.iter().any(|channel| {
+                subscribed(&channel.config.events, event) && channel.config.attach_screenshot
+            })
+    }
+
+    /// Deliver an event to the subscribed channels
+    /// `screenshot_png` goes to the channels that asked for screenshots.
+    pub async fn notify(&self, payload: &WebhookPayload, screenshot_png: Option<&[u8]>) {
+        if let Some(channel) = &self.desktop {
+            channel.send(payload);
+        }
+
+        for channel in self.webhooks.iter().filter(|c| c.accepts(&payload.event)) {
+            let screenshot = channel.screenshot.zip(screenshot_png);
+            let delivered = webhook::post_webhook(
+                &channel.url,
+                payload,
+                channel.format,
+                screenshot.map(|(_, png)| png.to_vec()),
+                screenshot.map(|(delivery, _)| delivery).unwrap_or_default(),
+            )
+            .await;
+            if let Ok(false) | Err(_) = delivered {
+                warn!("{} notification was not delivered", payload.event);
+            }
+        }
+
+        for channel in self
+            .emails
+            .iter()
+            .filter(|c| subscribed(&c.config.events, &payload.event))
+        {
+            if let Err(e) = channel.send(payload, screenshot_png).await {
+                warn!("{} email was not delivered: {}", payload.event, e);
+            }
+        }
+    }
+
+    /// Build and deliver an event if any channel is subscribed to it
+    pub async fn emit(&self, event: &str, data: impl Serialize) {
+        self.emit_with_screenshot(event, data, None).await;
+    }
+
+    /// `emit` with a screenshot for the channels that asked for one
+    pub async fn emit_with_screenshot(
+        &self,
+        event: &str,
+        data: impl Serialize,
+        screenshot_png: Option<&[u8]>,
+    ) {
+        if self.wants(event) {
+            self.notify(&WebhookPayload::new(event, data), screenshot_png)
+                .await;
+        }
+    }
+}
+
+/// Stored webhook preferences, or the defaults
+pub async fn load_webhook_settings(pool: &SqlitePool) -> Result<WebhookSettings, XenotesterError> {
+    Ok(settings::get_json(pool, WEBHOOK_SETTINGS_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Store webhook preferences
+pub async fn save_webhook_settings(
+    pool: &SqlitePool,
+    settings: &WebhookSettings,
+) -> Result<(), XenotesterError> {
+    settings::set_json(pool, WEBHOOK_SETTINGS_KEY, settings).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::webhook::{RUN_COMPLETED_EVENT, RUN_STARTED_EVENT};
+
+    #[test]
+    fn test_default_subscription() {
+        let notifier = Notifier::disabled().with_webhook(WebhookChannel::new(
+            "https://example.com/hook",
+            NotificationFormat::Raw,
+        ));
+        assert!(notifier.wants(TEST_FAILURE_EVENT));
+        assert!(notifier.wants(EMERGENCY_STOP_EVENT));
+        assert!(!notifier.wants(RUN_STARTED_EVENT));
+        assert!(!Notifier::disabled().wants(TEST_FAILURE_EVENT));
+    }
+
+    #[test]
+    fn test_wildcard_subscription() {
+        let channel = WebhookChannel {
+            url: "https://example.com/hook".to_string(),
+            format: NotificationFormat::Slack,
+            events: vec!["*".to_string()],
+            screenshot: None,
+        };
+        assert!(channel.accepts(RUN_COMPLETED_EVENT));
+        assert!(channel.accepts("custom"));
+    }
+
+    #[test]
+    fn test_screenshot_subscription() {
+        let mut channel = WebhookChannel::new("https://example.com/hook", NotificationFormat::Raw);
+        channel.screenshot = Some(ScreenshotDelivery::Multipart);
+        let notifier = Notifier::disabled().with_webhook(channel);
+        assert!(notifier.wants_screenshot(STEP_FAILED_EVENT));
+        assert!(!notifier.wants_screenshot(RUN_STARTED_EVENT));
+
+        let plain = Notifier::disabled().with_webhook(WebhookChannel::new(
+            "https://example.com/hook",
+            NotificationFormat::Raw,
+        ));
+        assert!(!plain.wants_screenshot(STEP_FAILED_EVENT));
+    }
+}
@@ -0,0 +1,227 @@
+//! OAuth session storage and refresh
+//!
+//! Keeps the Supabase session (access token, refresh token and expiry) in the
+//! encrypted secret store, so it survives restarts, and refreshes it through
+//! the Supabase auth API shortly before the access token expires. Supabase
+//! rotates refresh tokens, so refreshes are serialized: two concurrent ones
+//! would invalidate each other's token.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::error::XenotesterError;
+use crate::services::http;
+use crate::services::secrets;
+
+/// Internal secret holding the session as JSON
+const SESSION_SECRET: &str = "oauth.session";
+/// Access tokens expiring within this many seconds are refreshed first
+const REFRESH_MARGIN_SECS: i64 = 60;
+/// Timeout of the refresh request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Serializes refreshes (see the module documentation)
+static REFRESH_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Stored OAuth session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Expiry of the access token in seconds since the Unix epoch
+    pub expires_at: i64,
+}
+
+impl OAuthTokens {
+    fn needs_refresh(&self, now: i64) -> bool {
+        self.expires_at - now <= REFRESH_MARGIN_SECS
+    }
+}
+
+/// Token response of the Supabase auth API
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    #[serde(default)]
+    expires_in: Option<i64>,
+    #[serde(default)]
+    expires_at: Option<i64>,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Expiry from an absolute `expires_at` or a relative `expires_in` (seconds)
+pub fn expiry(expires_at: Option<i64>, expires_in: Option<i64>) -> Result<i64, XenotesterError> {
+    expires_at
+        .or_else(|| expires_in.map(|seconds| now() + seconds))
+        .ok_or_else(|| {
+            XenotesterError::AuthError("Either expiresAt or expiresIn is required".to_string())
+        })
+}
+
+/// Store a session, replacing the previous one
+pub async fn store_tokens(pool: &SqlitePool, tokens: &OAuthTokens) -> Result<(), XenotesterError> {
+    if tokens.access_token.is_empty() || tokens.refresh_token.is_empty() {
+        return Err(XenotesterError::AuthError(
+            "Access and refresh tokens must not be empty".to_string(),
+        ));
+    }
+    let json = serde_json::to_string(tokens)
+        .map_err(|e| XenotesterError::AuthError(format!("Failed to encode the session: {}", e)))?;
+    secrets::store_internal(pool, SESSION_SECRET, &json).await
+}
+
+/// The stored session, if any
+pub async fn load_tokens(pool: &SqlitePool) -> Result<Option<OAuthTokens>, XenotesterError> {
+    let Some(json) = secrets::resolve_internal(pool, SESSION_SECRET).await? else {
+        return Ok(None);
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| XenotesterError::AuthError(format!("Stored session is invalid: {}", e)))
+}
+
+/// Remove the stored session (sign-out); returns false if there was none
+pub async fn clear_tokens(pool: &SqlitePool) -> Result<bool, XenotesterError> {
+    secrets::delete_internal(pool, SESSION_SECRET).await
+}
+
+/// Whether a rejected refresh means the refresh token is gone for good
+/// Rate limits, timeouts and other client errors leave the session in place.
+fn revokes_session(status: u16, body: &str) -> bool {
+    if !matches!(status, 400 | 401) {
+        return false;
+    }
+    let Ok(body) = serde_json::from_str::<serde_json::Value>(body) else {
+        return false;
+    };
+    ["error", "error_code"]
+        .iter()
+        .any(|field| body.get(field).and_then(|v| v.as_str()) == Some("invalid_grant"))
+}
+
+fn require(tokens: Option<OAuthTokens>) -> Result<OAuthTokens, XenotesterError> {
+    tokens.ok_or_else(|| XenotesterError::AuthError("Not signed in".to_string()))
+}
+
+/// Exchange the refresh token for a new session and store it
+async fn refresh_locked(
+    pool: &SqlitePool,
+    supabase_url: &str,
+    anon_key: &str,
+    tokens: &OAuthTokens,
+) -> Result<OAuthTokens, XenotesterError> {
+    let url = format!(
+        "{}/auth/v1/token?grant_type=refresh_token",
+        supabase_url.trim_end_matches('/')
+    );
+    let response = http::client()?
+        .post(url)
+        .timeout(REQUEST_TIMEOUT)
+        .header("apikey", anon_key)
+        .json(&serde_json::json!({ "refresh_token": tokens.refresh_token }))
+        .send()
+        .await
+        .map_err(|e| XenotesterError::NetworkError(format!("Token refresh failed: {}", e)))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        if revokes_session(status.as_u16(), &body) {
+            clear_tokens(pool).await?;
+        }
+        return Err(XenotesterError::AuthError(format!(
+            "Token refresh was rejected ({}): {}",
+            status,
+            body.trim()
+        )));
+    }
+
+    let response: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| XenotesterError::AuthError(format!("Invalid token response: {}", e)))?;
+    let refreshed = OAuthTokens {
+        expires_at: expiry(response.expires_at, response.expires_in)?,
+        access_token: response.access_token,
+        refresh_token: response.refresh_token,
+    };
+    store_tokens(pool, &refreshed).await?;
+    info!("Refreshed the OAuth session");
+    Ok(refreshed)
+}
+
+/// Refresh the stored session now
+pub async fn refresh_access_token(
+    pool: &SqlitePool,
+    supabase_url: &str,
+    anon_key: &str,
+) -> Result<OAuthTokens, XenotesterError> {
+    let _lock = REFRESH_LOCK.lock().await;
+    let tokens = require(load_tokens(pool).await?)?;
+    refresh_locked(pool, supabase_url, anon_key, &tokens).await
+}
+
+/// Access token of the stored session, refreshed first when about to expire
+pub async fn get_valid_access_token(
+    pool: &SqlitePool,
+    supabase_url: &str,
+    anon_key: &str,
+) -> Result<String, XenotesterError> {
+    let _lock = REFRESH_LOCK.lock().await;
+    // Loaded under the lock, so a refresh that just finished is seen here
+    let tokens = require(load_tokens(pool).await?)?;
+    if !tokens.needs_refresh(now()) {
+        return Ok(tokens.access_token);
+    }
+    Ok(refresh_locked(pool, supabase_url, anon_key, &tokens)
+        .await?
+        .access_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_refresh_within_margin() {
+        let tokens = OAuthTokens {
+            access_token: "a".to_string(),
+            refresh_token: "r".to_string(),
+            expires_at: 1_000,
+        };
+        assert!(!tokens.needs_refresh(1_000 - REFRESH_MARGIN_SECS - 1));
+        assert!(tokens.needs_refresh(1_000 - REFRESH_MARGIN_SECS));
+        assert!(tokens.needs_refresh(2_000));
+    }
+
+    #[test]
+    fn test_only_invalid_grant_revokes_session() {
+        let invalid_grant =
+            r#"{"error":"invalid_grant","error_description":"Invalid Refresh Token"}"#;
+        assert!(revokes_session(400, invalid_grant));
+        assert!(revokes_session(
+            401,
+            r#"{"code":401,"error_code":"invalid_grant"}"#
+        ));
+        assert!(!revokes_session(429, invalid_grant));
+        assert!(!revokes_session(408, invalid_grant));
+        assert!(!revokes_session(400, r#"{"error":"invalid_request"}"#));
+        assert!(!revokes_session(400, "Bad Request"));
+    }
+
+    #[test]
+    fn test_expiry_prefers_absolute_time() {
+        assert_eq!(expiry(Some(42), Some(3600)).unwrap(), 42);
+        let relative = expiry(None, Some(3600)).unwrap();
+        assert!((relative - now() - 3600).abs() <= 1);
+        assert!(expiry(None, None).is_err());
+    }
+}
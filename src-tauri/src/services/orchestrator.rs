@@ -0,0 +1,510 @@
+//! Orchestration agent bridge
+//!
+//! Lets a fleet of test machines be driven from one central server. While
+//! started, the bridge registers this machine with the orchestrator, polls it
+//! for queued scenario jobs, runs them one at a time through `runner` and
+//! uploads each result with the run's artifacts. The protocol is JSON over
+//! HTTP, with a bearer token when one is configured:
+//! - `POST {url}/api/agents` with `{name, platform, version}`, answered with `{"agentId"}`
+//! - `POST {url}/api/agents/{agentId}/jobs/next`, answered with 204 when there
+//!   is nothing to do or with a `Job`
+//! - `POST {url}/api/jobs/{jobId}/result` with `{"result"}` or `{"error"}`
+//! - `POST {url}/api/jobs/{jobId}/artifacts`, multipart with one `file` part per artifact
+//!
+//! Failed requests are retried on the next poll. An unknown agent ID (404)
+//! registers again, so a restarted orchestrator picks the machine up.
+
+use reqwest::multipart::{Form, Part};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::error::XenotesterError;
+use crate::services::artifacts::{self, ArtifactInfo};
+use crate::services::http;
+use crate::services::runner::{self, RunOptions, Scenario, ScenarioRunResult};
+use crate::services::settings;
+use crate::utils::cancel::CancellationToken;
+
+/// Settings key of the bridge configuration
+const CONFIG_KEY: &str = "orchestrator";
+/// Internal secret holding the orchestrator's bearer token
+pub const TOKEN_SECRET: &str = "orchestrator.token";
+/// Timeout of a single request (artifact uploads included)
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Orchestrator connection settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OrchestratorConfig {
+    /// Base URL of the orchestration server
+    pub url: String,
+    /// Name shown on the dashboard (default: the host name)
+    pub machine_name: Option<String>,
+    /// Delay between polls while no job is queued
+    pub poll_interval_secs: u64,
+    /// Start the bridge when the app starts
+    pub auto_start: bool,
+}
+
+impl Default for OrchestratorConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            machine_name: None,
+            poll_interval_secs: 10,
+            auto_start: false,
+        }
+    }
+}
+
+impl OrchestratorConfig {
+    /// Check that the URL is an http(s) URL
+    pub fn validate(&self) -> Result<(), XenotesterError> {
+        let url = Url::parse(self.url.trim()).map_err(|e| {
+            XenotesterError::ConfigError(format!("Invalid orchestrator URL: {}", e))
+        })?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(XenotesterError::ConfigError(format!(
+                "Invalid orchestrator URL scheme: {}",
+                url.scheme()
+            )));
+        }
+        Ok(())
+    }
+
+    fn machine_name(&self) -> String {
+        self.machine_name
+            .clone()
+            .filter(|name| !name.trim().is_empty())
+            .or_else(|| std::env::var("COMPUTERNAME").ok())
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .unwrap_or_else(|| "xenotester".to_string())
+    }
+}
+
+/// Load the stored configuration
+pub async fn load_config(pool: &SqlitePool) -> Result<Option<OrchestratorConfig>, XenotesterError> {
+    settings::get_json(pool, CONFIG_KEY).await
+}
+
+/// Store the configuration
+pub async fn save_config(
+    pool: &SqlitePool,
+    config: &OrchestratorConfig,
+) -> Result<(), XenotesterError> {
+    config.validate()?;
+    settings::set_json(pool, CONFIG_KEY, config).await
+}
+
+/// Scenario job queued on the orchestrator
+/// Runs the inline `scenario`, or the stored scenario `scenario_id`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    #[serde(default)]
+    pub scenario_id: Option<String>,
+    #[serde(default)]
+    pub scenario: Option<Scenario>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub max_iterations: Option<u32>,
+}
+
+/// Progress of the bridge
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeStatus {
+    pub running: bool,
+    pub url: Option<String>,
+    /// ID assigned by the orchestrator on registration
+    pub agent_id: Option<String>,
+    /// Job being executed
+    pub current_job: Option<String>,
+    pub completed_jobs: u32,
+    /// Error of the last poll, cleared by the next successful one
+    pub last_error: Option<String>,
+}
+
+/// Resources jobs run with
+pub struct BridgeContext {
+    /// Application database for stored scenarios and the run history
+    pub pool: Option<SqlitePool>,
+    /// Artifact directory; artifacts are not uploaded without one
+    pub artifacts_dir: Option<PathBuf>,
+    /// Model and iteration limit for jobs that don't set them
+    pub defaults: RunOptions,
+    /// Jobs register their token here so the emergency stop cancels them
+    pub run_tokens: Arc<Mutex<HashMap<String, CancellationToken>>>,
+}
+
+/// Bridge in progress
+struct ActiveBridge {
+    shutdown: CancellationToken,
+    status: Arc<Mutex<BridgeStatus>>,
+}
+
+/// Owns the (single) running bridge
+#[derive(Default)]
+pub struct OrchestratorBridge {
+    active: Mutex<Option<ActiveBridge>>,
+}
+
+impl OrchestratorBridge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start polling the orchestrator in the background
+    pub fn start(
+        &self,
+        config: OrchestratorConfig,
+        token: Option<String>,
+        context: BridgeContext,
+    ) -> Result<BridgeStatus, XenotesterError> {
+        config.validate()?;
+        let mut active = self.active.lock().unwrap();
+        if active.is_some() {
+            return Err(XenotesterError::RemoteError(
+                "The orchestrator bridge is already running".to_string(),
+            ));
+        }
+
+        let client = OrchestratorClient {
+            base: config.url.trim().trim_end_matches('/').to_string(),
+            token,
+        };
+        info!("Orchestrator bridge started for {}", client.base);
+        let status = BridgeStatus {
+            running: true,
+            url: Some(client.base.clone()),
+            ..Default::default()
+        };
+        let shared = Arc::new(Mutex::new(status.clone()));
+        let shutdown = CancellationToken::new();
+        tauri::async_runtime::spawn(run_bridge(
+            client,
+            config,
+            context,
+            shared.clone(),
+            shutdown.clone(),
+        ));
+        *active = Some(ActiveBridge {
+            shutdown,
+            status: shared,
+        });
+        Ok(status)
+    }
+
+    /// Stop polling and cancel the job in progress; returns false if it wasn't running
+    pub fn stop(&self) -> bool {
+        match self.active.lock().unwrap().take() {
+            Some(bridge) => {
+                bridge.shutdown.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Progress of the running bridge (not running when stopped)
+    pub fn status(&self) -> BridgeStatus {
+        self.active
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|bridge| bridge.status.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Answer to a poll for the next job
+enum NextJob {
+    Idle,
+    Job(Job),
+    /// The orchestrator no longer knows this agent
+    UnknownAgent,
+}
+
+struct OrchestratorClient {
+    /// Base URL without a trailing slash
+    base: String,
+    token: Option<String>,
+}
+
+impl OrchestratorClient {
+    /// The client is taken per request, so network settings changed while
+    /// the bridge runs apply to it
+    fn post(&self, path: &str) -> Result<RequestBuilder, XenotesterError> {
+        let request = http::client()?
+            .post(format!("{}/api/{}", self.base, path))
+            .timeout(REQUEST_TIMEOUT);
+        Ok(match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        })
+    }
+
+    async fn send(request: RequestBuilder) -> Result<Response, XenotesterError> {
+        let response = request.send().await.map_err(|e| {
+            XenotesterError::RemoteError(format!("Orchestrator request failed: {}", e))
+        })?;
+        if response.status().is_success() || response.status() == StatusCode::NOT_FOUND {
+            Ok(response)
+        } else {
+            Err(XenotesterError::RemoteError(format!(
+                "Orchestrator responded with {}",
+                response.status()
+            )))
+        }
+    }
+
+    async fn register(&self, name: &str) -> Result<String, XenotesterError> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Registration {
+            agent_id: String,
+        }
+
+        let body = json!({
+            "name": name,
+            "platform": std::env::consts::OS,
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        let response = Self::send(self.post("agents")?.json(&body)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(XenotesterError::RemoteError(
+                "The URL is not an orchestration server".to_string(),
+            ));
+        }
+        let registration: Registration = response.json().await.map_err(|e| {
+            XenotesterError::RemoteError(format!("Invalid registration response: {}", e))
+        })?;
+        Ok(registration.agent_id)
+    }
+
+    async fn next_job(&self, agent_id: &str) -> Result<NextJob, XenotesterError> {
+        let response = Self::send(self.post(&format!("agents/{}/jobs/next", agent_id))?).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(NextJob::UnknownAgent),
+            StatusCode::NO_CONTENT => Ok(NextJob::Idle),
+            _ => response.json().await.map(NextJob::Job).map_err(|e| {
+                XenotesterError::RemoteError(format!("Invalid job from the orchestrator: {}", e))
+            }),
+        }
+    }
+
+    async fn upload_result(&self, job_id: &str, body: &Value) -> Result<(), XenotesterError> {
+        Self::send(self.post(&format!("jobs/{}/result", job_id))?.json(body)).await?;
+        Ok(())
+    }
+
+    async fn upload_artifact(
+        &self,
+        job_id: &str,
+        artifact: &ArtifactInfo,
+    ) -> Result<(), XenotesterError> {
+        let data = tokio::fs::read(&artifact.path).await?;
+        let form = Form::new().part("file", Part::bytes(data).file_name(artifact.name.clone()));
+        Self::send(
+            self.post(&format!("jobs/{}/artifacts", job_id))?
+                .multipart(form),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Poll for jobs until shut down
+async fn run_bridge(
+    client: OrchestratorClient,
+    config: OrchestratorConfig,
+    context: BridgeContext,
+    status: Arc<Mutex<BridgeStatus>>,
+    shutdown: CancellationToken,
+) {
+    let interval = Duration::from_secs(config.poll_interval_secs.max(1));
+    let name = config.machine_name();
+    let mut agent_id = None;
+
+    while !shutdown.is_cancelled() {
+        match poll_once(&client, &name, &mut agent_id, &context, &status, &shutdown).await {
+            // Look for the next job right away
+            Ok(true) => continue,
+            Ok(false) => status.lock().unwrap().last_error = None,
+            Err(XenotesterError::Cancelled) => break,
+            Err(e) => {
+                warn!("Orchestrator poll failed: {}", e);
+                status.lock().unwrap().last_error = Some(e.to_string());
+            }
+        }
+        if shutdown.sleep_async(interval).await.is_err() {
+            break;
+        }
+    }
+
+    let mut status = status.lock().unwrap();
+    status.running = false;
+    status.current_job = None;
+    info!("Orchestrator bridge stopped");
+}
+
+/// Register if needed and run the next queued job; returns whether one ran
+async fn poll_once(
+    client: &OrchestratorClient,
+    name: &str,
+    agent_id: &mut Option<String>,
+    context: &BridgeContext,
+    status: &Mutex<BridgeStatus>,
+    shutdown: &CancellationToken,
+) -> Result<bool, XenotesterError> {
+    let id = match agent_id.clone() {
+        Some(id) => id,
+        None => {
+            let id = shutdown
+                .run_until_cancelled(client.register(name))
+                .await??;
+            info!("Registered with the orchestrator as {}", id);
+            status.lock().unwrap().agent_id = Some(id.clone());
+            agent_id.insert(id).clone()
+        }
+    };
+
+    let job = match shutdown.run_until_cancelled(client.next_job(&id)).await?? {
+        NextJob::Idle => return Ok(false),
+        NextJob::UnknownAgent => {
+            *agent_id = None;
+            status.lock().unwrap().agent_id = None;
+            return Ok(false);
+        }
+        NextJob::Job(job) => job,
+    };
+
+    info!("Running orchestrator job {}", job.id);
+    status.lock().unwrap().current_job = Some(job.id.clone());
+    let outcome = execute_job(&job, context, shutdown).await;
+    status.lock().unwrap().current_job = None;
+
+    let body = match &outcome {
+        Ok(result) => json!({ "result": result }),
+        Err(e) => json!({ "error": e.to_string() }),
+    };
+    client.upload_result(&job.id, &body).await?;
+
+    let run_id = outcome
+        .as_ref()
+        .ok()
+        .and_then(|result| result.run_id.clone());
+    if let (Some(dir), Some(run_id)) = (context.artifacts_dir.clone(), run_id) {
+        let listed =
+            tokio::task::spawn_blocking(move || artifacts::list_artifacts(&dir, Some(&run_id)))
+                .await
+                .map_err(|e| {
+                    XenotesterError::RemoteError(format!("Artifact listing failed: {}", e))
+                })?;
+        for artifact in listed.unwrap_or_default() {
+            if let Err(e) = client.upload_artifact(&job.id, &artifact).await {
+                warn!("Failed to upload artifact {}: {}", artifact.name, e);
+            }
+        }
+    }
+
+    status.lock().unwrap().completed_jobs += 1;
+    Ok(true)
+}
+
+/// Run a job's scenario, observing a run token registered in `context`
+async fn execute_job(
+    job: &Job,
+    context: &BridgeContext,
+    shutdown: &CancellationToken,
+) -> Result<ScenarioRunResult, XenotesterError> {
+    let scenario = match (&job.scenario, &job.scenario_id) {
+        (Some(scenario), _) => scenario.clone(),
+        (None, Some(id)) => {
+            let pool = context.pool.as_ref().ok_or_else(|| {
+                XenotesterError::DatabaseError("The database is not available".to_string())
+            })?;
+            runner::load_scenario(pool, id).await?
+        }
+        (None, None) => {
+            return Err(XenotesterError::ConfigError(
+                "The job has neither a scenario nor a scenario ID".to_string(),
+            ))
+        }
+    };
+
+    let mut options = context.defaults.clone();
+    if let Some(model) = &job.model {
+        options.model_config.model = model.clone();
+    }
+    if let Some(max_iterations) = job.max_iterations {
+        options.max_iterations = max_iterations;
+    }
+    options.variables.extend(job.variables.clone());
+
+    let token_id = format!("orchestrator-{}", job.id);
+    let cancel = CancellationToken::new();
+    context
+        .run_tokens
+        .lock()
+        .unwrap()
+        .insert(token_id.clone(), cancel.clone());
+    // Stopping the bridge cancels the job as well
+    let watcher = tokio::spawn({
+        let (shutdown, cancel) = (shutdown.clone(), cancel.clone());
+        async move {
+            shutdown.cancelled().await;
+            cancel.cancel();
+        }
+    });
+
+    let result = runner::run_scenario(&scenario, &options, context.pool.as_ref(), &cancel).await;
+
+    watcher.abort();
+    context.run_tokens.lock().unwrap().remove(&token_id);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_validation() {
+        let mut config = OrchestratorConfig {
+            url: "https://orchestrator.example.com/".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.url = "ftp://orchestrator.example.com".to_string();
+        assert!(config.validate().is_err());
+        config.url = String::new();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_job_defaults() {
+        let job: Job = serde_json::from_value(json!({
+            "id": "job-1",
+            "scenarioId": "scenario-1"
+        }))
+        .unwrap();
+        assert_eq!(job.scenario_id.as_deref(), Some("scenario-1"));
+        assert!(job.scenario.is_none());
+        assert!(job.variables.is_empty());
+        assert_eq!(job.max_iterations, None);
+    }
+}
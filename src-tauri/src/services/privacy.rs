@@ -0,0 +1,232 @@
+//! Screenshot redaction zones
+//!
+//! Zones are desktop rectangles (logical points, like input coordinates) that
+//! are blacked out or blurred in every grabbed frame, before it is encoded,
+//! matched, recorded or sent to an LLM. Set them with `set_redaction_zones`.
+//! The privacy mode adds zones detected at capture time (see `PrivacyMode`).
+
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::sync::{PoisonError, RwLock};
+
+/// Window app names or titles that mark a window as sensitive (lowercase)
+const SENSITIVE_WINDOW_KEYWORDS: &[&str] = &[
+    "1password",
+    "bitwarden",
+    "dashlane",
+    "enpass",
+    "keepass",
+    "keychain access",
+    "lastpass",
+    "nordpass",
+    "password",
+    "パスワード",
+];
+
+/// Digits in a payment card number
+const CARD_NUMBER_DIGITS: std::ops::RangeInclusive<usize> = 13..=19;
+
+/// Blur radius relative to the zone's shorter side, so text stays unreadable
+const BLUR_SIGMA_RATIO: f32 = 0.25;
+const MIN_BLUR_SIGMA: f32 = 8.0;
+
+/// How a zone is masked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionMode {
+    #[default]
+    Blackout,
+    Blur,
+}
+
+/// Desktop rectangle to mask in captures
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactionZone {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub mode: RedactionMode,
+    /// Free-form description (e.g. "password manager")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Sensitive regions detected automatically at capture time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrivacyMode {
+    /// Only the manual zones are masked
+    #[default]
+    Off,
+    /// Windows of password managers and similar apps are blurred
+    Windows,
+    /// As `Windows`, plus text in the frontmost app that looks like an email
+    /// address or card number (read through the accessibility API)
+    Strict,
+}
+
+static ZONES: RwLock<Vec<RedactionZone>> = RwLock::new(Vec::new());
+static MODE: RwLock<PrivacyMode> = RwLock::new(PrivacyMode::Off);
+
+/// Replace the active redaction zones
+pub fn set_zones(zones: Vec<RedactionZone>) {
+    *ZONES.write().unwrap_or_else(PoisonError::into_inner) = zones;
+}
+
+/// Active redaction zones
+pub fn zones() -> Vec<RedactionZone> {
+    ZONES.read().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+pub fn set_mode(mode: PrivacyMode) {
+    *MODE.write().unwrap_or_else(PoisonError::into_inner) = mode;
+}
+
+pub fn mode() -> PrivacyMode {
+    *MODE.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Mask the active and the `detected` zones in an image showing the desktop
+/// from `origin` (logical points) at `scale` image pixels per point
+pub fn redact(image: &mut RgbaImage, origin: (i32, i32), scale: f64, detected: &[RedactionZone]) {
+    let zones = ZONES.read().unwrap_or_else(PoisonError::into_inner);
+    for zone in zones.iter().chain(detected) {
+        redact_zone(image, zone, origin, scale);
+    }
+}
+
+/// Whether a window belongs to a password manager or similar app
+pub fn is_sensitive_window(app_name: &str, title: &str) -> bool {
+    let (app_name, title) = (app_name.to_lowercase(), title.to_lowercase());
+    SENSITIVE_WINDOW_KEYWORDS
+        .iter()
+        .any(|keyword| app_name.contains(keyword) || title.contains(keyword))
+}
+
+/// Whether text contains something like an email address or a card number
+pub fn contains_pii(text: &str) -> bool {
+    contains_email(text) || contains_card_number(text)
+}
+
+fn contains_email(text: &str) -> bool {
+    let is_address_char = |c: char| c.is_alphanumeric() || "._%+-".contains(c);
+    text.split(|c: char| !is_address_char(c) && c != '@')
+        .any(|word| match word.split_once('@') {
+            Some((local, domain)) => {
+                let domain = domain.trim_end_matches('.');
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain.split('.').count() >= 2
+                    && domain.split('.').all(|label| !label.is_empty())
+            }
+            None => false,
+        })
+}
+
+/// Runs of 13-19 digits, optionally grouped by single spaces or dashes, that pass the Luhn check
+fn contains_card_number(text: &str) -> bool {
+    let mut digits: Vec<u32> = Vec::new();
+    let mut previous_digit = false;
+    for c in text.chars().chain(std::iter::once('\n')) {
+        if let Some(digit) = c.to_digit(10) {
+            digits.push(digit);
+            previous_digit = true;
+            continue;
+        }
+        if previous_digit && (c == ' ' || c == '-') {
+            previous_digit = false;
+            continue;
+        }
+        if CARD_NUMBER_DIGITS.contains(&digits.len()) && luhn_valid(&digits) {
+            return true;
+        }
+        digits.clear();
+        previous_digit = false;
+    }
+    false
+}
+
+fn luhn_valid(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match (i % 2 == 1, digit * 2) {
+            (true, doubled) if doubled > 9 => doubled - 9,
+            (true, doubled) => doubled,
+            (false, _) => digit,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn redact_zone(image: &mut RgbaImage, zone: &RedactionZone, origin: (i32, i32), scale: f64) {
+    let to_pixels = |point: i32, origin: i32| ((point - origin) as f64 * scale).round() as i64;
+    let left = to_pixels(zone.x, origin.0).max(0);
+    let top = to_pixels(zone.y, origin.1).max(0);
+    let right = to_pixels(zone.x + zone.width as i32, origin.0).min(image.width() as i64);
+    let bottom = to_pixels(zone.y + zone.height as i32, origin.1).min(image.height() as i64);
+    if left >= right || top >= bottom {
+        return;
+    }
+    let (x, y) = (left as u32, top as u32);
+    let (width, height) = ((right - left) as u32, (bottom - top) as u32);
+
+    match zone.mode {
+        RedactionMode::Blackout => {
+            for py in y..y + height {
+                for px in x..x + width {
+                    image.put_pixel(px, py, image::Rgba([0, 0, 0, 255]));
+                }
+            }
+        }
+        RedactionMode::Blur => {
+            let sigma = (width.min(height) as f32 * BLUR_SIGMA_RATIO).max(MIN_BLUR_SIGMA);
+            let crop = imageops::crop_imm(image, x, y, width, height).to_image();
+            let blurred = imageops::blur(&crop, sigma);
+            imageops::replace(image, &blurred, x as i64, y as i64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blackout_maps_points_to_pixels() {
+        let mut image = RgbaImage::from_pixel(40, 40, image::Rgba([255, 255, 255, 255]));
+        let zone = RedactionZone {
+            x: 105,
+            y: 205,
+            width: 5,
+            height: 100,
+            mode: RedactionMode::Blackout,
+            label: None,
+        };
+        // Monitor at (100, 200) captured at 2 pixels per point
+        redact_zone(&mut image, &zone, (100, 200), 2.0);
+
+        assert_eq!(image.get_pixel(9, 10)[0], 255);
+        assert_eq!(image.get_pixel(10, 10)[0], 0);
+        assert_eq!(image.get_pixel(19, 39)[0], 0);
+        assert_eq!(image.get_pixel(20, 10)[0], 255);
+    }
+
+    #[test]
+    fn test_detects_emails_and_card_numbers() {
+        assert!(contains_pii("Contact: taro.yamada@example.co.jp."));
+        assert!(contains_pii("Card 4111 1111 1111 1111 exp 12/30"));
+        assert!(contains_pii("4111-1111-1111-1111"));
+        assert!(!contains_pii("4111 1111 1111 1112"));
+        assert!(!contains_pii("Order 12345 shipped @ noon"));
+        assert!(!contains_pii("user@localhost"));
+
+        assert!(is_sensitive_window("1Password 7", "Vault"));
+        assert!(is_sensitive_window("Safari", "Change Password - Example"));
+        assert!(!is_sensitive_window("Finder", "Documents"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Launching and managing the application under test
+//!
+//! Uses the platform's own tools (`open`, `tasklist`/`taskkill`, `pgrep`/`pkill`)
+//! so no extra dependencies are needed. Process names are executable names on
+//! Windows ("notepad.exe", the extension may be omitted) and process names
+//! elsewhere ("Safari", "gedit").
+
+use std::process::{Command, Output};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::error::XenotesterError;
+use crate::utils::cancel::CancellationToken;
+
+/// Interval between checks in `wait_for_app`
+const APP_POLL_INTERVAL_MS: u64 = 250;
+
+/// Run a platform tool and capture its output
+pub fn run_tool(program: &str, args: &[&str]) -> Result<Output, XenotesterError> {
+    let mut command = Command::new(program);
+    command.args(args);
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    command
+        .output()
+        .map_err(|e| XenotesterError::ProcessError(format!("Failed to run {}: {}", program, e)))
+}
+
+/// Executable name with the ".exe" extension Windows tools expect
+#[cfg(target_os = "windows")]
+fn exe_name(name: &str) -> String {
+    if name.to_lowercase().ends_with(".exe") {
+        name.to_string()
+    } else {
+        format!("{}.exe", name)
+    }
+}
+
+/// Launch an application with arguments
+///
+/// On macOS, `.app` bundles and bundle IDs (e.g., "com.apple.TextEdit") are opened
+/// through `open`; anything else is started directly as an executable.
+/// Returns the process ID when the process was started directly.
+pub fn launch_app(target: &str, args: &[String]) -> Result<Option<u32>, XenotesterError> {
+    #[cfg(target_os = "macos")]
+    {
+        let is_bundle = target.trim_end_matches('/').ends_with(".app");
+        let is_bundle_id = !target.contains('/') && target.contains('.');
+        if is_bundle || is_bundle_id {
+            let flag = if is_bundle { "-a" } else { "-b" };
+            let mut open_args = vec![flag, target];
+            if !args.is_empty() {
+                open_args.push("--args");
+                open_args.extend(args.iter().map(String::as_str));
+            }
+
+            let output = run_tool("open", &open_args)?;
+            if !output.status.success() {
+                return Err(XenotesterError::ProcessError(format!(
+                    "Failed to open {}: {}",
+                    target,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            info!("Opened {}", target);
+            return Ok(None);
+        }
+    }
+
+    let mut child = Command::new(target).args(args).spawn().map_err(|e| {
+        XenotesterError::ProcessError(format!("Failed to launch {}: {}", target, e))
+    })?;
+    let pid = child.id();
+    info!("Launched {} (pid {})", target, pid);
+
+    // Reap the process when it exits so it does not linger as a zombie
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+
+    Ok(Some(pid))
+}
+
+/// Terminate processes by process ID or name
+/// Returns whether any process was terminated.
+pub fn terminate_app(pid_or_name: &str) -> Result<bool, XenotesterError> {
+    let target = pid_or_name.trim();
+    let pid = target.parse::<u32>().ok();
+
+    #[cfg(target_os = "windows")]
+    let output = match pid {
+        Some(pid) => run_tool("taskkill", &["/F", "/T", "/PID", &pid.to_string()])?,
+        None => run_tool("taskkill", &["/F", "/T", "/IM", &exe_name(target)])?,
+    };
+
+    #[cfg(not(target_os = "windows"))]
+    let output = match pid {
+        Some(pid) => run_tool("kill", &["-TERM", &pid.to_string()])?,
+        None => run_tool("pkill", &["-TERM", "-x", target])?,
+    };
+
+    let terminated = output.status.success();
+    info!("Terminate {}: {}", target, terminated);
+    Ok(terminated)
+}
+
+/// Check whether a process with the given name is running
+pub fn is_app_running(name: &str) -> Result<bool, XenotesterError> {
+    #[cfg(target_os = "windows")]
+    {
+        let image = exe_name(name.trim());
+        let filter = format!("IMAGENAME eq {}", image);
+        let output = run_tool("tasklist", &["/FI", &filter, "/FO", "CSV", "/NH"])?;
+        // Matching rows look like "notepad.exe","1234",...
+        let quoted = format!("\"{}\"", image.to_lowercase());
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.to_lowercase().starts_with(&quoted)))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // pgrep exits with 1 when nothing matched
+        let output = run_tool("pgrep", &["-x", name.trim()])?;
+        Ok(output.status.success())
+    }
+}
+
+/// Wait until a process with the given name is running
+/// Returns false if it did not appear within `timeout`.
+pub fn wait_for_app(
+    name: &str,
+    timeout: Duration,
+    cancel: &CancellationToken,
+) -> Result<bool, XenotesterError> {
+    let started = Instant::now();
+
+    loop {
+        if is_app_running(name)? {
+            return Ok(true);
+        }
+        let elapsed = started.elapsed();
+        if elapsed >= timeout {
+            return Ok(false);
+        }
+        let poll = Duration::from_millis(APP_POLL_INTERVAL_MS);
+        cancel.sleep(poll.min(timeout - elapsed))?;
+    }
+}
@@ -0,0 +1,369 @@
+//! Screen recording of scenario runs
+//!
+//! Captures frames on a background thread at a fixed FPS and streams them into
+//! an animated GIF (image crate) or an MP4 (piped into an `ffmpeg` process).
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{imageops::FilterType, Delay, DynamicImage, Frame, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::error::XenotesterError;
+use crate::services::capture::grab_frame;
+
+const DEFAULT_FPS: u32 = 2;
+const MAX_FPS: u32 = 30;
+/// Frames are downscaled so their long edge fits this size
+const DEFAULT_MAX_EDGE: u32 = 1280;
+/// GIF quantization speed (1-30, higher is faster with lower quality)
+const GIF_ENCODER_SPEED: i32 = 20;
+/// Environment variable overriding the ffmpeg binary used for MP4
+const FFMPEG_PATH_ENV: &str = "XENOTESTER_FFMPEG";
+
+/// Output container
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    #[default]
+    Gif,
+    Mp4,
+}
+
+impl RecordingFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordingFormat::Gif => "gif",
+            RecordingFormat::Mp4 => "mp4",
+        }
+    }
+}
+
+/// Recording options
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RecordingOptions {
+    /// Frames per second (1-30)
+    pub fps: u32,
+    pub format: RecordingFormat,
+    /// Monitor to record (primary monitor when omitted)
+    pub monitor_id: Option<u32>,
+    /// Draw the mouse pointer into the video
+    pub include_cursor: bool,
+    /// Long edge limit for frames
+    pub max_edge: u32,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        Self {
+            fps: DEFAULT_FPS,
+            format: RecordingFormat::default(),
+            monitor_id: None,
+            include_cursor: true,
+            max_edge: DEFAULT_MAX_EDGE,
+        }
+    }
+}
+
+/// Summary of a finished recording
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingInfo {
+    pub path: String,
+    pub format: RecordingFormat,
+    pub frame_count: u32,
+    pub duration_ms: u64,
+}
+
+/// Recording in progress
+struct ActiveRecording {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Result<RecordingInfo, XenotesterError>>,
+}
+
+/// Owns the (single) active recording and remembers the last finished one
+#[derive(Default)]
+pub struct Recorder {
+    active: Mutex<Option<ActiveRecording>>,
+    last: Mutex<Option<RecordingInfo>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start recording to `path` on a background thread
+    pub fn start(&self, path: PathBuf, options: RecordingOptions) -> Result<(), XenotesterError> {
+        let mut active = self.active.lock().unwrap();
+        if active.is_some() {
+            return Err(XenotesterError::RecordingError(
+                "A recording is already in progress".to_string(),
+            ));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Open the sink up front so a missing ffmpeg is reported to the caller
+        let sink = FrameSink::open(&path, &options)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread_path = path.clone();
+
+        let handle = thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || record_loop(sink, thread_path, options, thread_stop))
+            .map_err(|e| XenotesterError::RecordingError(e.to_string()))?;
+
+        info!("Recording started: {}", path.display());
+        *active = Some(ActiveRecording { path, stop, handle });
+        Ok(())
+    }
+
+    /// Stop the active recording and wait for the file to be finalized
+    pub fn stop(&self) -> Result<RecordingInfo, XenotesterError> {
+        let recording = self.active.lock().unwrap().take().ok_or_else(|| {
+            XenotesterError::RecordingError("No recording in progress".to_string())
+        })?;
+
+        recording.stop.store(true, Ordering::SeqCst);
+        let info = recording
+            .handle
+            .join()
+            .map_err(|_| XenotesterError::RecordingError("Recorder thread panicked".to_string()))??;
+
+        info!(
+            "Recording saved: {} ({} frames, {}ms)",
+            info.path, info.frame_count, info.duration_ms
+        );
+        *self.last.lock().unwrap() = Some(info.clone());
+        Ok(info)
+    }
+
+    /// Whether a recording is in progress
+    pub fn is_recording(&self) -> bool {
+        self.active.lock().unwrap().is_some()
+    }
+
+    /// Path of the active recording, or of the last finished one
+    pub fn current_path(&self) -> Option<String> {
+        if let Some(active) = self.active.lock().unwrap().as_ref() {
+            return Some(active.path.to_string_lossy().into_owned());
+        }
+        self.last.lock().unwrap().as_ref().map(|info| info.path.clone())
+    }
+}
+
+/// Capture frames until stopped, then finalize the output
+fn record_loop(
+    mut sink: FrameSink,
+    path: PathBuf,
+    options: RecordingOptions,
+    stop: Arc<AtomicBool>,
+) -> Result<RecordingInfo, XenotesterError> {
+    let interval = Duration::from_secs_f64(1.0 / options.fps.clamp(1, MAX_FPS) as f64);
+    let started = Instant::now();
+    let mut frame_size: Option<(u32, u32)> = None;
+    let mut frame_count = 0u32;
+
+    while !stop.load(Ordering::SeqCst) {
+        let tick = Instant::now();
+
+        match grab_frame(options.monitor_id, options.include_cursor) {
+            Ok(frame) => {
+                let size = *frame_size.get_or_insert_with(|| {
+                    fit_size(frame.image.width(), frame.image.height(), options.max_edge, &options.format)
+                });
+                let image = resize_frame(frame.image, size);
+                if let Err(e) = sink.push(image, started.elapsed()) {
+                    // Stop on encoder failure but still try to finalize what we have
+                    error!("Failed to encode frame: {}", e);
+                    break;
+                }
+                frame_count += 1;
+            }
+            Err(e) => warn!("Frame capture failed: {}", e),
+        }
+
+        thread::sleep(interval.saturating_sub(tick.elapsed()));
+    }
+
+    let duration = started.elapsed();
+    sink.finish(duration)?;
+
+    Ok(RecordingInfo {
+        path: path.to_string_lossy().into_owned(),
+        format: options.format,
+        frame_count,
+        duration_ms: duration.as_millis() as u64,
+    })
+}
+
+/// Scale (width, height) to fit `max_edge`; MP4 (yuv420p) needs even dimensions
+fn fit_size(width: u32, height: u32, max_edge: u32, format: &RecordingFormat) -> (u32, u32) {
+    let scale = (max_edge as f64 / width.max(height) as f64).min(1.0);
+    let mut w = ((width as f64 * scale).round() as u32).max(2);
+    let mut h = ((height as f64 * scale).round() as u32).max(2);
+    if *format == RecordingFormat::Mp4 {
+        w -= w % 2;
+        h -= h % 2;
+    }
+    (w, h)
+}
+
+/// Resize a frame to the recording size (all frames share the first frame's size)
+fn resize_frame(image: DynamicImage, (width, height): (u32, u32)) -> RgbaImage {
+    if image.width() == width && image.height() == height {
+        image.into_rgba8()
+    } else {
+        image.resize_exact(width, height, FilterType::Triangle).into_rgba8()
+    }
+}
+
+/// Encoder the frames are streamed into
+enum FrameSink {
+    Gif {
+        encoder: GifEncoder<BufWriter<File>>,
+        /// Frames are written once the next one arrives, so their delay is known
+        pending: Option<(RgbaImage, Duration)>,
+    },
+    Mp4 {
+        ffmpeg: Child,
+        fps: u32,
+        written: u64,
+    },
+}
+
+impl FrameSink {
+    fn open(path: &Path, options: &RecordingOptions) -> Result<Self, XenotesterError> {
+        match options.format {
+            RecordingFormat::Gif => {
+                let file = BufWriter::new(File::create(path)?);
+                let mut encoder = GifEncoder::new_with_speed(file, GIF_ENCODER_SPEED);
+                encoder.set_repeat(Repeat::Infinite)?;
+                Ok(FrameSink::Gif {
+                    encoder,
+                    pending: None,
+                })
+            }
+            RecordingFormat::Mp4 => Ok(FrameSink::Mp4 {
+                ffmpeg: spawn_ffmpeg(path, options)?,
+                fps: options.fps.clamp(1, MAX_FPS),
+                written: 0,
+            }),
+        }
+    }
+
+    /// Add a frame captured `timestamp` after the recording started
+    fn push(&mut self, image: RgbaImage, timestamp: Duration) -> Result<(), XenotesterError> {
+        match self {
+            FrameSink::Gif { encoder, pending } => {
+                if let Some((previous, previous_ts)) = pending.take() {
+                    write_gif_frame(encoder, previous, timestamp.saturating_sub(previous_ts))?;
+                }
+                *pending = Some((image, timestamp));
+                Ok(())
+            }
+            FrameSink::Mp4 { ffmpeg, fps, written } => {
+                // Duplicate frames when capture falls behind so playback stays real-time
+                let due = (timestamp.as_secs_f64() * *fps as f64).floor() as u64 + 1;
+                let repeat = due.saturating_sub(*written).max(1);
+                let stdin = ffmpeg.stdin.as_mut().ok_or_else(|| {
+                    XenotesterError::RecordingError("ffmpeg stdin closed".to_string())
+                })?;
+                for _ in 0..repeat {
+                    stdin.write_all(image.as_raw()).map_err(|e| {
+                        XenotesterError::RecordingError(format!("ffmpeg write failed: {}", e))
+                    })?;
+                }
+                *written += repeat;
+                Ok(())
+            }
+        }
+    }
+
+    /// Flush pending frames and close the output
+    fn finish(self, end: Duration) -> Result<(), XenotesterError> {
+        match self {
+            FrameSink::Gif {
+                mut encoder,
+                pending,
+            } => {
+                if let Some((image, timestamp)) = pending {
+                    write_gif_frame(&mut encoder, image, end.saturating_sub(timestamp))?;
+                }
+                Ok(())
+            }
+            FrameSink::Mp4 { mut ffmpeg, .. } => {
+                // Closing stdin signals end of stream
+                drop(ffmpeg.stdin.take());
+                let status = ffmpeg.wait()?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(XenotesterError::RecordingError(format!(
+                        "ffmpeg exited with {}",
+                        status
+                    )))
+                }
+            }
+        }
+    }
+}
+
+fn write_gif_frame(
+    encoder: &mut GifEncoder<BufWriter<File>>,
+    image: RgbaImage,
+    delay: Duration,
+) -> Result<(), XenotesterError> {
+    let delay_ms = (delay.as_millis() as u32).max(20);
+    let frame = Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(delay_ms, 1));
+    encoder.encode_frame(frame)?;
+    Ok(())
+}
+
+fn ffmpeg_binary() -> String {
+    env::var(FFMPEG_PATH_ENV).unwrap_or_else(|_| "ffmpeg".to_string())
+}
+
+/// Spawn ffmpeg reading raw RGBA frames from stdin
+/// rawvideo input needs the frame size up front, so it is probed with one capture
+fn spawn_ffmpeg(path: &Path, options: &RecordingOptions) -> Result<Child, XenotesterError> {
+    let ffmpeg = ffmpeg_binary();
+    let probe = grab_frame(options.monitor_id, false)?;
+    let (width, height) = fit_size(
+        probe.image.width(),
+        probe.image.height(),
+        options.max_edge,
+        &options.format,
+    );
+
+    Command::new(&ffmpeg)
+        .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(["-s", &format!("{}x{}", width, height)])
+        .args(["-r", &options.fps.clamp(1, MAX_FPS).to_string()])
+        .args(["-i", "-", "-c:v", "libx264", "-pix_fmt", "yuv420p"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            XenotesterError::RecordingError(format!(
+                "ffmpeg is required for MP4 recording ({}): {}",
+                ffmpeg, e
+            ))
+        })
+}
@@ -0,0 +1,574 @@
+//! Remote control server
+//!
+//! Lets external test harnesses (pytest, Node scripts) drive the app without
+//! the webview. The server listens on localhost only and speaks two protocols
+//! on one port, both authenticated with a bearer token:
+//! - HTTP: `POST /invoke/<command>` with the command's arguments as a JSON
+//!   object (the same camelCase names as `invoke` in the frontend), answered
+//!   with `{"result": ...}` or `{"error": "..."}`
+//! - WebSocket (`GET /ws`): text messages `{"id", "command", "args"}`, answered
+//!   with `{"id", "result"}` or `{"id", "error"}`; requests run concurrently,
+//!   so a long capture doesn't hold up `cancel_run`
+//!
+//! Browsers can't set headers on WebSockets, so `?token=` is accepted as well.
+//! `GET /health` needs no token. The commands themselves come from the
+//! dispatcher passed to `start` (see `commands::remote`).
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::io;
+use std::net::{Ipv4Addr, TcpListener as StdTcpListener};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info};
+
+use crate::error::XenotesterError;
+use crate::utils::cancel::CancellationToken;
+
+/// Port used when `start` is not given one
+pub const DEFAULT_PORT: u16 = 47820;
+/// Limit on the request line plus headers
+const MAX_HEAD_BYTES: u64 = 16 * 1024;
+/// Limit on request bodies and WebSocket messages (base64 screenshots and templates)
+const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+/// Appended to the client's key in the WebSocket handshake (RFC 6455)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// Result of one remote command call
+pub type CommandFuture = Pin<Box<dyn Future<Output = Result<Value, String>> + Send>>;
+/// Runs a command by name with its JSON arguments
+pub type Dispatcher = Arc<dyn Fn(String, Value) -> CommandFuture + Send + Sync>;
+
+/// Address and credentials of the running server
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteInfo {
+    pub url: String,
+    pub port: u16,
+    /// Bearer token clients must send
+    pub token: String,
+}
+
+/// Server in progress
+struct ActiveServer {
+    info: RemoteInfo,
+    shutdown: CancellationToken,
+}
+
+/// Owns the (single) running remote control server
+#[derive(Default)]
+pub struct RemoteServer {
+    active: Mutex<Option<ActiveServer>>,
+}
+
+impl RemoteServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start listening on 127.0.0.1:`port` (0 picks a free port)
+    /// A random token is generated when `token` is None.
+    pub fn start(
+        &self,
+        port: Option<u16>,
+        token: Option<String>,
+        dispatcher: Dispatcher,
+    ) -> Result<RemoteInfo, XenotesterError> {
+        let mut active = self.active.lock().unwrap();
+        if active.is_some() {
+            return Err(XenotesterError::RemoteError(
+                "The remote control server is already running".to_string(),
+            ));
+        }
+
+        let token = match token {
+            Some(token) if token.trim().is_empty() => {
+                return Err(XenotesterError::RemoteError(
+                    "Token must not be empty".to_string(),
+                ))
+            }
+            Some(token) => token,
+            None => generate_token()?,
+        };
+
+        let port = port.unwrap_or(DEFAULT_PORT);
+        let listener = StdTcpListener::bind((Ipv4Addr::LOCALHOST, port)).map_err(|e| {
+            XenotesterError::RemoteError(format!("Failed to listen on port {}: {}", port, e))
+        })?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+
+        let info = RemoteInfo {
+            url: format!("http://127.0.0.1:{}", port),
+            port,
+            token: token.clone(),
+        };
+        let shutdown = CancellationToken::new();
+        tauri::async_runtime::spawn(serve(listener, token, dispatcher, shutdown.clone()));
+
+        info!("Remote control server listening on {}", info.url);
+        *active = Some(ActiveServer {
+            info: info.clone(),
+            shutdown,
+        });
+        Ok(info)
+    }
+
+    /// Stop the server and close open connections; returns false if it wasn't running
+    pub fn stop(&self) -> bool {
+        match self.active.lock().unwrap().take() {
+            Some(server) => {
+                server.shutdown.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Address and token of the running server
+    pub fn info(&self) -> Option<RemoteInfo> {
+        self.active
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|server| server.info.clone())
+    }
+}
+
+/// 256-bit random token as hex
+fn generate_token() -> Result<String, XenotesterError> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| XenotesterError::RemoteError("Failed to generate a token".to_string()))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Accept connections until shut down
+async fn serve(
+    listener: StdTcpListener,
+    token: String,
+    dispatcher: Dispatcher,
+    shutdown: CancellationToken,
+) {
+    let listener = match TcpListener::from_std(listener) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Remote control server failed to start: {}", e);
+            return;
+        }
+    };
+    let token: Arc<str> = token.into();
+
+    while let Ok(accepted) = shutdown.run_until_cancelled(listener.accept()).await {
+        let (stream, peer) = match accepted {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                debug!("Remote connection failed: {}", e);
+                continue;
+            }
+        };
+        let (token, dispatcher, shutdown) = (token.clone(), dispatcher.clone(), shutdown.clone());
+        tokio::spawn(async move {
+            let connection = handle_connection(stream, &token, &dispatcher);
+            if let Ok(Err(e)) = shutdown.run_until_cancelled(connection).await {
+                debug!("Remote connection {} closed: {}", peer, e);
+            }
+        });
+    }
+    info!("Remote control server stopped");
+}
+
+/// Request line and headers of an HTTP request
+struct RequestHead {
+    method: String,
+    path: String,
+    query: Option<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl RequestHead {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query_param(&self, name: &str) -> Option<&str> {
+        self.query.as_deref()?.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            (key == name).then_some(value)
+        })
+    }
+
+    /// Whether the request carries the server's token (header or `?token=`)
+    fn is_authorized(&self, token: &str) -> bool {
+        let given = self
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| self.query_param("token"));
+        // Comparing digests keeps the comparison time independent of the token
+        given.is_some_and(|given| {
+            Sha256::digest(given.trim().as_bytes()) == Sha256::digest(token.as_bytes())
+        })
+    }
+}
+
+async fn read_head<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> io::Result<RequestHead> {
+    let mut limited = reader.take(MAX_HEAD_BYTES);
+    let mut line = String::new();
+    limited.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(invalid_data("Malformed request line"));
+    };
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (target.to_string(), None),
+    };
+    let method = method.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if limited.read_line(&mut line).await? == 0 {
+            return Err(invalid_data("Request headers are incomplete or too large"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    Ok(RequestHead {
+        method,
+        path,
+        query,
+        headers,
+    })
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Serve one HTTP request, or a WebSocket session when the client upgrades
+async fn handle_connection(
+    stream: TcpStream,
+    token: &str,
+    dispatcher: &Dispatcher,
+) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let head = read_head(&mut reader).await?;
+
+    if head.method == "GET" && head.path == "/health" {
+        return write_response(&mut writer, 200, &json!({ "status": "ok" })).await;
+    }
+    if !head.is_authorized(token) {
+        let body = json!({ "error": "Missing or invalid token" });
+        return write_response(&mut writer, 401, &body).await;
+    }
+
+    match (head.method.as_str(), head.path.as_str()) {
+        ("GET", "/ws") => match head.header("sec-websocket-key") {
+            Some(key) => {
+                let handshake = format!(
+                    "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                     Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                    accept_key(key)
+                );
+                writer.write_all(handshake.as_bytes()).await?;
+                serve_websocket(reader, writer, dispatcher).await
+            }
+            None => {
+                let body = json!({ "error": "Expected a WebSocket upgrade" });
+                write_response(&mut writer, 400, &body).await
+            }
+        },
+        ("POST", path) if path.starts_with("/invoke/") => {
+            let command = path.trim_start_matches("/invoke/").to_string();
+            let length = head
+                .header("content-length")
+                .and_then(|value| value.parse::<usize>().ok())
+                .unwrap_or(0);
+            if length > MAX_MESSAGE_BYTES {
+                let body = json!({ "error": "Request body is too large" });
+                return write_response(&mut writer, 413, &body).await;
+            }
+            let mut body = vec![0u8; length];
+            reader.read_exact(&mut body).await?;
+
+            let args = if body.is_empty() {
+                Ok(Value::Null)
+            } else {
+                serde_json::from_slice(&body).map_err(|e| format!("Invalid JSON body: {}", e))
+            };
+            let (status, response) = match args {
+                Ok(args) => match call(dispatcher, command, args).await {
+                    Ok(result) => (200, json!({ "result": result })),
+                    Err(e) => (400, json!({ "error": e })),
+                },
+                Err(e) => (400, json!({ "error": e })),
+            };
+            write_response(&mut writer, status, &response).await
+        }
+        _ => write_response(&mut writer, 404, &json!({ "error": "Not found" })).await,
+    }
+}
+
+/// Run a command; missing arguments are an empty object
+async fn call(dispatcher: &Dispatcher, command: String, args: Value) -> Result<Value, String> {
+    let args = match args {
+        Value::Null => Value::Object(Map::new()),
+        args => args,
+    };
+    dispatcher(command, args).await
+}
+
+async fn write_response<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    status: u16,
+    body: &Value,
+) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        _ => "Error",
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    writer.write_all(response.as_bytes()).await?;
+    writer.shutdown().await
+}
+
+/// `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.trim().as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    BASE64_STANDARD.encode(hasher.finalize())
+}
+
+/// WebSocket request message
+#[derive(Deserialize)]
+struct WsRequest {
+    /// Echoed in the response so clients can match concurrent requests
+    #[serde(default)]
+    id: Value,
+    command: String,
+    #[serde(default)]
+    args: Value,
+}
+
+/// Answer WebSocket requests until the client closes the connection
+async fn serve_websocket<R, W>(
+    mut reader: R,
+    mut writer: W,
+    dispatcher: &Dispatcher,
+) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let (outgoing, mut queue) = mpsc::unbounded_channel::<(u8, Vec<u8>)>();
+
+    let send = async move {
+        while let Some((opcode, payload)) = queue.recv().await {
+            writer.write_all(&encode_frame(opcode, &payload)).await?;
+            if opcode == OP_CLOSE {
+                break;
+            }
+        }
+        writer.shutdown().await
+    };
+
+    let receive = async move {
+        // Opcode and payload of a fragmented message being assembled
+        let mut message: Option<(u8, Vec<u8>)> = None;
+        loop {
+            let frame = read_frame(&mut reader).await?;
+            match frame.opcode {
+                OP_TEXT | OP_BINARY | OP_CONTINUATION => {
+                    let (opcode, mut payload) = match (frame.opcode, message.take()) {
+                        (OP_CONTINUATION, Some(partial)) => partial,
+                        (OP_CONTINUATION, None) => {
+                            return Err(invalid_data("Unexpected continuation frame"))
+                        }
+                        (opcode, _) => (opcode, Vec::new()),
+                    };
+                    payload.extend_from_slice(&frame.payload);
+                    if payload.len() > MAX_MESSAGE_BYTES {
+                        return Err(invalid_data("Message is too large"));
+                    }
+                    if !frame.fin {
+                        message = Some((opcode, payload));
+                        continue;
+                    }
+                    respond(dispatcher, opcode, payload, &outgoing);
+                }
+                OP_PING => {
+                    let _ = outgoing.send((OP_PONG, frame.payload));
+                }
+                OP_PONG => {}
+                _ => {
+                    // Close (echo it back) or an unknown opcode
+                    let _ = outgoing.send((OP_CLOSE, Vec::new()));
+                    return Ok(());
+                }
+            }
+        }
+    };
+
+    let (sent, received) = tokio::join!(send, receive);
+    received.and(sent)
+}
+
+/// Run a WebSocket request in the background and queue its response
+fn respond(
+    dispatcher: &Dispatcher,
+    opcode: u8,
+    payload: Vec<u8>,
+    outgoing: &mpsc::UnboundedSender<(u8, Vec<u8>)>,
+) {
+    let dispatcher = dispatcher.clone();
+    let outgoing = outgoing.clone();
+    tokio::spawn(async move {
+        let request = if opcode == OP_TEXT {
+            serde_json::from_slice::<WsRequest>(&payload)
+                .map_err(|e| format!("Invalid request: {}", e))
+        } else {
+            Err("Binary messages are not supported".to_string())
+        };
+        let response = match request {
+            Ok(request) => match call(&dispatcher, request.command, request.args).await {
+                Ok(result) => json!({ "id": request.id, "result": result }),
+                Err(e) => json!({ "id": request.id, "error": e }),
+            },
+            Err(e) => json!({ "id": Value::Null, "error": e }),
+        };
+        let _ = outgoing.send((OP_TEXT, response.to_string().into_bytes()));
+    });
+}
+
+/// Single WebSocket frame
+struct WsFrame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// Read one client frame (client frames are always masked)
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<WsFrame> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head).await?;
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0F;
+    if head[1] & 0x80 == 0 {
+        return Err(invalid_data("Client frames must be masked"));
+    }
+
+    let length = match head[1] & 0x7F {
+        126 => u64::from(reader.read_u16().await?),
+        127 => reader.read_u64().await?,
+        length => u64::from(length),
+    };
+    if length > MAX_MESSAGE_BYTES as u64 {
+        return Err(invalid_data("Message is too large"));
+    }
+
+    let mut mask = [0u8; 4];
+    reader.read_exact(&mut mask).await?;
+    let mut payload = vec![0u8; length as usize];
+    reader.read_exact(&mut payload).await?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+
+    Ok(WsFrame {
+        fin,
+        opcode,
+        payload,
+    })
+}
+
+/// Encode an unfragmented, unmasked server frame
+fn encode_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        length @ 0..=125 => frame.push(length as u8),
+        length @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_key_matches_rfc_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[tokio::test]
+    async fn test_frame_round_trip() {
+        // Masked "Hello" from RFC 6455 section 5.7
+        let masked: &[u8] = &[
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let frame = read_frame(&mut &masked[..]).await.unwrap();
+        assert!(frame.fin);
+        assert_eq!(frame.opcode, OP_TEXT);
+        assert_eq!(frame.payload, b"Hello");
+
+        assert_eq!(encode_frame(OP_TEXT, b"Hello"), b"\x81\x05Hello");
+        let long = encode_frame(OP_BINARY, &[0u8; 300]);
+        assert_eq!(&long[..4], &[0x82, 126, 0x01, 0x2c]);
+
+        // Unmasked client frames are rejected
+        let unmasked: &[u8] = b"\x81\x05Hello";
+        assert!(read_frame(&mut &unmasked[..]).await.is_err());
+    }
+}
@@ -0,0 +1,471 @@
+//! Run report export
+//!
+//! Renders a recorded run (see `run_history`) with its step results and
+//! executed input actions as JUnit XML for CI dashboards, or as a standalone
+//! HTML page. Screenshots are not part of the run history; HTML reports of
+//! failed runs show the frames of the run still in the capture history.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::error::XenotesterError;
+use crate::services::action_log::{self, ActionLogEntry};
+use crate::services::capture_history::{CaptureHistory, RecentCapture};
+
+/// Frames leading up to a failure shown in HTML reports
+const REPORT_FRAMES: usize = 5;
+/// Format of the timestamps in the `runs` table
+const RUN_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Output format of `export_run_report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Junit,
+    Html,
+}
+
+/// Row of the `runs` table
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    pub id: String,
+    pub scenario_id: String,
+    pub scenario_title: String,
+    pub status: String,
+    pub error_message: Option<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+/// Row of the `step_results` table
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct StepRecord {
+    pub step_index: i64,
+    pub description: String,
+    pub status: String,
+    pub error_message: Option<String>,
+    pub duration_ms: i64,
+}
+
+/// Everything recorded about a run
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    pub run: RunRecord,
+    pub steps: Vec<StepRecord>,
+    pub actions: Vec<ActionLogEntry>,
+    /// Whether the action log passed its tamper check
+    pub chain_intact: bool,
+    /// Frames captured during a failed run, oldest first
+    pub frames: Vec<RecentCapture>,
+}
+
+impl RunReport {
+    fn count_steps(&self, status: &str) -> usize {
+        self.steps.iter().filter(|s| s.status == status).count()
+    }
+
+    /// A failed run whose failure is not attributed to any step
+    fn has_unattributed_failure(&self) -> bool {
+        self.run.status == "failed" && self.count_steps("failed") == 0
+    }
+}
+
+/// Load a run with its step results and action log
+pub async fn load_run_report(
+    pool: &SqlitePool,
+    run_id: &str,
+) -> Result<RunReport, XenotesterError> {
+    let run: RunRecord = sqlx::query_as(
+        "SELECT id, scenario_id, scenario_title, status, error_message, started_at, finished_at,
+                duration_ms
+         FROM runs WHERE id = ?",
+    )
+    .bind(run_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| XenotesterError::NotFound(format!("run {}", run_id)))?;
+
+    let steps = sqlx::query_as(
+        "SELECT step_index, description, status, error_message, duration_ms
+         FROM step_results WHERE run_id = ? ORDER BY step_index, id",
+    )
+    .bind(run_id)
+    .fetch_all(pool)
+    .await?;
+
+    let log = action_log::get_action_log(pool, Some(run_id)).await?;
+
+    Ok(RunReport {
+        run,
+        steps,
+        actions: log.entries,
+        chain_intact: log.chain_intact,
+        frames: Vec::new(),
+    })
+}
+
+/// The last frames in `history` captured while a failed run was in progress
+fn failure_frames(
+    run: &RunRecord,
+    history: &CaptureHistory,
+) -> Result<Vec<RecentCapture>, XenotesterError> {
+    let timestamp_ms = |time: &str| {
+        chrono::NaiveDateTime::parse_from_str(time, RUN_TIMESTAMP_FORMAT)
+            .map(|time| time.and_utc().timestamp_millis())
+            .ok()
+    };
+    match timestamp_ms(&run.started_at) {
+        Some(started_ms) if run.status == "failed" => history.between(
+            started_ms,
+            run.finished_at.as_deref().and_then(timestamp_ms),
+            REPORT_FRAMES,
+        ),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Render a run and write it to `path`
+/// HTML reports of failed runs include the run's frames from `history`.
+pub async fn export_run_report(
+    pool: &SqlitePool,
+    run_id: &str,
+    format: ReportFormat,
+    path: &Path,
+    history: Option<&CaptureHistory>,
+) -> Result<(), XenotesterError> {
+    let mut report = load_run_report(pool, run_id).await?;
+    if let Some(history) = history.filter(|_| format == ReportFormat::Html) {
+        report.frames = failure_frames(&report.run, history)?;
+    }
+    let content = match format {
+        ReportFormat::Junit => render_junit(&report),
+        ReportFormat::Html => render_html(&report),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Escape text for XML and HTML content and attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab and newlines are not valid XML
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn seconds(ms: i64) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+/// Render a run as a JUnit XML test suite with one test case per step
+///
+/// A failed run without a failed step gets an extra test case carrying the
+/// run's error, so CI still shows the failure.
+pub fn render_junit(report: &RunReport) -> String {
+    let run = &report.run;
+    let extra_case = report.has_unattributed_failure() || report.steps.is_empty();
+    let tests = report.steps.len() + usize::from(extra_case);
+    let failures = report.count_steps("failed") + usize::from(run.status == "failed" && extra_case);
+    let skipped =
+        report.count_steps("skipped") + usize::from(run.status == "stopped" && extra_case);
+    let time = seconds(run.duration_ms.unwrap_or_default());
+    let title = escape(&run.scenario_title);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"Xenotester\" tests=\"{tests}\" failures=\"{failures}\" \
+         skipped=\"{skipped}\" time=\"{time}\">"
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{title}\" id=\"{}\" tests=\"{tests}\" failures=\"{failures}\" \
+         skipped=\"{skipped}\" time=\"{time}\" timestamp=\"{}\">",
+        escape(&run.id),
+        escape(&run.started_at.replacen(' ', "T", 1)),
+    );
+
+    for step in &report.steps {
+        let _ = write!(
+            xml,
+            "    <testcase classname=\"{title}\" name=\"{}. {}\" time=\"{}\"",
+            step.step_index + 1,
+            escape(&step.description),
+            seconds(step.duration_ms),
+        );
+        match step.status.as_str() {
+            "failed" => {
+                let message = escape(step.error_message.as_deref().unwrap_or("Step failed"));
+                let _ = writeln!(
+                    xml,
+                    ">\n      <failure message=\"{message}\">{message}</failure>\n    </testcase>"
+                );
+            }
+            "skipped" => xml.push_str(">\n      <skipped/>\n    </testcase>\n"),
+            _ => xml.push_str("/>\n"),
+        }
+    }
+
+    if extra_case {
+        let _ = write!(
+            xml,
+            "    <testcase classname=\"{title}\" name=\"{title}\" time=\"{time}\""
+        );
+        match run.status.as_str() {
+            "failed" => {
+                let message = escape(run.error_message.as_deref().unwrap_or("Scenario failed"));
+                let _ = writeln!(
+                    xml,
+                    ">\n      <failure message=\"{message}\">{message}</failure>\n    </testcase>"
+                );
+            }
+            "stopped" => {
+                xml.push_str(">\n      <skipped message=\"Run was stopped\"/>\n    </testcase>\n")
+            }
+            _ => xml.push_str("/>\n"),
+        }
+    }
+
+    if !report.actions.is_empty() {
+        xml.push_str("    <system-out>");
+        for action in &report.actions {
+            xml.push_str(&escape(&action_summary(action)));
+            xml.push('\n');
+        }
+        xml.push_str("</system-out>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// One-line description of an executed action
+fn action_summary(action: &ActionLogEntry) -> String {
+    let mut line = action.action.clone();
+    if let (Some(x), Some(y)) = (action.x, action.y) {
+        let _ = write!(line, " ({}, {})", x, y);
+    }
+    if !action.details.is_empty() && action.details != "null" && action.details != "{}" {
+        let _ = write!(line, " {}", action.details);
+    }
+    if let Some(error) = &action.error_message {
+        let _ = write!(line, " - {}", error);
+    }
+    line
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem;color:#222}\
+table{border-collapse:collapse;width:100%;margin-bottom:2rem}\
+th,td{border:1px solid #ddd;padding:.4rem .6rem;text-align:left;vertical-align:top}\
+th{background:#f5f5f5}.passed,.succeeded{color:#1a7f37}.failed{color:#cf222e}\
+.skipped,.stopped,.running{color:#9a6700}.error{white-space:pre-wrap}\
+figure{margin:0 0 1.5rem}figure img{max-width:100%;border:1px solid #ddd}";
+
+/// Render a run as a self-contained HTML page
+pub fn render_html(report: &RunReport) -> String {
+    let run = &report.run;
+    let mut html = String::new();
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title} - Xenotester report</title>\n<style>{HTML_STYLE}</style>\n</head>\n\
+         <body>\n<h1>{title}</h1>\n<table>\n\
+         <tr><th>Status</th><td class=\"{status}\">{status}</td></tr>\n\
+         <tr><th>Run ID</th><td>{id}</td></tr>\n\
+         <tr><th>Scenario ID</th><td>{scenario_id}</td></tr>\n\
+         <tr><th>Started</th><td>{started}</td></tr>\n\
+         <tr><th>Finished</th><td>{finished}</td></tr>\n\
+         <tr><th>Duration</th><td>{duration} s</td></tr>\n",
+        title = escape(&run.scenario_title),
+        status = escape(&run.status),
+        id = escape(&run.id),
+        scenario_id = escape(&run.scenario_id),
+        started = escape(&run.started_at),
+        finished = escape(run.finished_at.as_deref().unwrap_or("-")),
+        duration = seconds(run.duration_ms.unwrap_or_default()),
+    );
+    if let Some(error) = &run.error_message {
+        let _ = writeln!(
+            html,
+            "<tr><th>Error</th><td class=\"error\">{}</td></tr>",
+            escape(error)
+        );
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Steps</h2>\n");
+    if report.steps.is_empty() {
+        html.push_str("<p>No step results were recorded.</p>\n");
+    } else {
+        html.push_str(
+            "<table>\n<tr><th>#</th><th>Step</th><th>Status</th><th>Duration</th>\
+             <th>Error</th></tr>\n",
+        );
+        for step in &report.steps {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td class=\"{status}\">{status}</td><td>{} s</td>\
+                 <td class=\"error\">{}</td></tr>",
+                step.step_index + 1,
+                escape(&step.description),
+                seconds(step.duration_ms),
+                escape(step.error_message.as_deref().unwrap_or("")),
+                status = escape(&step.status),
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    if !report.actions.is_empty() {
+        html.push_str("<h2>Actions</h2>\n");
+        if !report.chain_intact {
+            html.push_str("<p class=\"failed\">The action log failed its tamper check.</p>\n");
+        }
+        html.push_str("<table>\n<tr><th>Time (UTC)</th><th>Action</th><th>Status</th></tr>\n");
+        for action in &report.actions {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td class=\"{status}\">{status}</td></tr>",
+                chrono::DateTime::from_timestamp_millis(action.timestamp_ms)
+                    .map(|time| time.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+                    .unwrap_or_default(),
+                escape(&action_summary(action)),
+                status = escape(&action.status),
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    if !report.frames.is_empty() {
+        html.push_str("<h2>Frames before the failure</h2>\n");
+        for frame in &report.frames {
+            let _ = writeln!(
+                html,
+                "<figure><img src=\"data:{};base64,{}\" alt=\"Frame {}\">\
+                 <figcaption>{}</figcaption></figure>",
+                escape(&frame.capture.media_type),
+                escape(&frame.capture.image_base64),
+                frame.sequence,
+                chrono::DateTime::from_timestamp_millis(frame.captured_at_ms)
+                    .map(|time| time.format("%Y-%m-%d %H:%M:%S%.3f UTC").to_string())
+                    .unwrap_or_default(),
+            );
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(status: &str, steps: &[(&str, Option<&str>)]) -> RunReport {
+        RunReport {
+            run: RunRecord {
+                id: "run-1".to_string(),
+                scenario_id: "s1".to_string(),
+                scenario_title: "Login <admin>".to_string(),
+                status: status.to_string(),
+                error_message: (status == "failed").then(|| "Button not found".to_string()),
+                started_at: "2025-01-02 03:04:05.678".to_string(),
+                finished_at: None,
+                duration_ms: Some(1500),
+            },
+            steps: steps
+                .iter()
+                .enumerate()
+                .map(|(i, (status, error))| StepRecord {
+                    step_index: i as i64,
+                    description: format!("Step & {}", i),
+                    status: status.to_string(),
+                    error_message: error.map(str::to_string),
+                    duration_ms: 250,
+                })
+                .collect(),
+            actions: Vec::new(),
+            chain_intact: true,
+            frames: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_junit_counts_and_escaping() {
+        let xml = render_junit(&report(
+            "failed",
+            &[
+                ("passed", None),
+                ("failed", Some("a < b")),
+                ("skipped", None),
+            ],
+        ));
+        assert!(xml.contains("tests=\"3\" failures=\"1\" skipped=\"1\" time=\"1.500\""));
+        assert!(xml.contains("name=\"Login &lt;admin&gt;\""));
+        assert!(xml.contains("name=\"2. Step &amp; 1\" time=\"0.250\""));
+        assert!(xml.contains("<failure message=\"a &lt; b\">"));
+        assert!(xml.contains("timestamp=\"2025-01-02T03:04:05.678\""));
+    }
+
+    #[test]
+    fn test_junit_unattributed_failure_gets_case() {
+        let xml = render_junit(&report("failed", &[("passed", None)]));
+        assert!(xml.contains("tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("<failure message=\"Button not found\">"));
+
+        let html = render_html(&report("failed", &[]));
+        assert!(html.contains("<h1>Login &lt;admin&gt;</h1>"));
+        assert!(html.contains("No step results were recorded."));
+    }
+
+    #[test]
+    fn test_html_shows_frames_of_failed_run() {
+        let history = CaptureHistory::new();
+        history.record(&crate::services::capture::CaptureResult {
+            original_width: 1,
+            original_height: 1,
+            resized_width: 1,
+            resized_height: 1,
+            scale_factor: 1.0,
+            image_base64: "iVBORw0KGgo=".to_string(),
+            media_type: "image/png".to_string(),
+            monitor_id: 0,
+            monitor_x: 0,
+            monitor_y: 0,
+            display_scale_factor: 1.0,
+            active_window: None,
+        });
+
+        let mut failed = report("failed", &[("failed", Some("Button not found"))]);
+        failed.run.started_at = (chrono::Utc::now() - chrono::Duration::minutes(1))
+            .format(RUN_TIMESTAMP_FORMAT)
+            .to_string();
+        failed.frames = failure_frames(&failed.run, &history).unwrap();
+        assert_eq!(failed.frames.len(), 1);
+        assert!(render_html(&failed).contains("src=\"data:image/png;base64,iVBORw0KGgo=\""));
+
+        let mut passed = failed.clone();
+        passed.run.status = "passed".to_string();
+        assert!(failure_frames(&passed.run, &history).unwrap().is_empty());
+    }
+}
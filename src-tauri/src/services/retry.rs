@@ -0,0 +1,162 @@
+//! Bounded retries for flaky actions
+//!
+//! `with_retry` re-runs an attempt with exponential backoff until it succeeds,
+//! the retries are used up, or the run is cancelled. Keeping the loop in the
+//! backend avoids a round trip per attempt from the frontend.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::error::XenotesterError;
+use crate::utils::cancel::CancellationToken;
+
+/// Upper bound for `RetryPolicy::retries`
+const MAX_RETRIES: u32 = 20;
+
+/// How often and how fast to retry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    /// Attempts after the first one
+    pub retries: u32,
+    /// Wait before the first retry
+    pub delay_ms: u64,
+    /// Multiplier applied to the wait after every retry
+    pub backoff: f64,
+    /// Upper bound for the wait between attempts
+    pub max_delay_ms: u64,
+    /// Wait after an action before verifying its effect
+    pub settle_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            delay_ms: 500,
+            backoff: 2.0,
+            max_delay_ms: 5000,
+            settle_ms: 300,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn validate(&self) -> Result<(), XenotesterError> {
+        if self.retries > MAX_RETRIES {
+            return Err(XenotesterError::ConfigError(format!(
+                "retries must be at most {}, got {}",
+                MAX_RETRIES, self.retries
+            )));
+        }
+        if !self.backoff.is_finite() || self.backoff < 1.0 {
+            return Err(XenotesterError::ConfigError(format!(
+                "backoff must be at least 1.0, got {}",
+                self.backoff
+            )));
+        }
+        Ok(())
+    }
+
+    /// Wait before retry number `retry` (1-based)
+    fn delay_before(&self, retry: u32) -> Duration {
+        let delay_ms = self.delay_ms as f64 * self.backoff.powi(retry as i32 - 1);
+        Duration::from_millis(delay_ms.min(self.max_delay_ms as f64) as u64)
+    }
+}
+
+/// Run `attempt` (given the 1-based attempt number) until it succeeds (blocking)
+///
+/// Returns the value and the number of attempts made, or the last error once the
+/// retries are used up. Errors that are not retryable (see
+/// `XenotesterError::is_retryable`), cancellation included, end the loop at once.
+pub fn with_retry<T>(
+    policy: &RetryPolicy,
+    cancel: &CancellationToken,
+    mut attempt: impl FnMut(u32) -> Result<T, XenotesterError>,
+) -> Result<(T, u32), XenotesterError> {
+    policy.validate()?;
+
+    let mut number = 1;
+    loop {
+        cancel.check()?;
+        match attempt(number) {
+            Ok(value) => return Ok((value, number)),
+            Err(e) if number > policy.retries || !e.is_retryable() => return Err(e),
+            Err(_) => {
+                cancel.sleep(policy.delay_before(number))?;
+                number += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast_policy(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            delay_ms: 0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_delay_grows_up_to_max() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay_before(1), Duration::from_millis(500));
+        assert_eq!(policy.delay_before(2), Duration::from_millis(1000));
+        assert_eq!(policy.delay_before(5), Duration::from_millis(5000));
+    }
+
+    #[test]
+    fn test_retries_until_success() {
+        let cancel = CancellationToken::new();
+        let result = with_retry(&fast_policy(3), &cancel, |n| {
+            if n < 3 {
+                Err(XenotesterError::InputError("flaky".to_string()))
+            } else {
+                Ok(n * 10)
+            }
+        });
+        assert_eq!(result.unwrap(), (30, 3));
+    }
+
+    #[test]
+    fn test_gives_up_after_retries() {
+        let cancel = CancellationToken::new();
+        let mut calls = 0;
+        let result: Result<((), u32), _> = with_retry(&fast_policy(2), &cancel, |_| {
+            calls += 1;
+            Err(XenotesterError::InputError("always".to_string()))
+        });
+        assert!(matches!(result, Err(XenotesterError::InputError(_))));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_cancellation_is_not_retried() {
+        let cancel = CancellationToken::new();
+        let mut calls = 0;
+        let result: Result<((), u32), _> = with_retry(&fast_policy(5), &cancel, |_| {
+            calls += 1;
+            Err(XenotesterError::Cancelled)
+        });
+        assert!(matches!(result, Err(XenotesterError::Cancelled)));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_config_errors_are_not_retried() {
+        let cancel = CancellationToken::new();
+        let mut calls = 0;
+        let result: Result<((), u32), _> = with_retry(&fast_policy(5), &cancel, |_| {
+            calls += 1;
+            Err(XenotesterError::ConfigError("invalid template".to_string()))
+        });
+        assert!(matches!(result, Err(XenotesterError::ConfigError(_))));
+        assert_eq!(calls, 1);
+    }
+}
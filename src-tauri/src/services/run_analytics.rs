@@ -0,0 +1,445 @@
+//! Run analytics
+//!
+//! Aggregates the recorded step results (see `run_history`) into run
+//! summaries, which show where a run spent its time, which steps needed
+//! retries and how confidently image steps matched, and into flakiness
+//! reports, which rank the steps of a scenario that failed intermittently,
+//! needed retries, or matched with low or varying confidence across its
+//! recent runs.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::error::XenotesterError;
+use crate::services::report::RunRecord;
+use crate::services::screen_check::DEFAULT_CONFIDENCE_THRESHOLD;
+
+/// Slowest steps listed in a run summary
+const SLOWEST_STEPS: usize = 5;
+/// Flaky steps listed in a run summary
+const FLAKIEST_STEPS: usize = 5;
+/// Recent runs of the scenario considered for flakiness
+const HISTORY_RUNS: i64 = 50;
+/// Mean confidence below which image steps risk missing their template:
+/// within 0.1 of the default match threshold
+const LOW_CONFIDENCE: f64 = DEFAULT_CONFIDENCE_THRESHOLD as f64 + 0.1;
+/// Standard deviation above which match confidence counts as variable
+const VARIABLE_CONFIDENCE_STDDEV: f64 = 0.05;
+
+/// Timing and metrics of one step of a run
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct StepTiming {
+    pub step_index: i64,
+    pub description: String,
+    pub status: String,
+    pub duration_ms: i64,
+    pub attempts: i64,
+    pub confidence: Option<f64>,
+}
+
+/// A step result of a recent run, as scanned for flakiness
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct StepSample {
+    run_id: String,
+    #[sqlx(flatten)]
+    timing: StepTiming,
+}
+
+/// Outcomes of one step across the recent runs of its scenario
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepHistory {
+    pub step_index: i64,
+    pub description: String,
+    /// Runs that executed the step (skips excluded)
+    pub runs: i64,
+    pub failures: i64,
+    /// Runs in which the step needed at least one retry
+    pub retried_runs: i64,
+    pub total_retries: i64,
+    pub average_duration_ms: f64,
+    /// Match confidence of image steps
+    pub average_confidence: Option<f64>,
+    pub min_confidence: Option<f64>,
+    /// Standard deviation of the match confidence, with two or more matches
+    pub confidence_stddev: Option<f64>,
+}
+
+impl StepHistory {
+    /// Share of runs in which the step failed or needed a retry
+    pub fn instability(&self) -> f64 {
+        if self.runs == 0 {
+            return 0.0;
+        }
+        let unstable = (self.failures + self.retried_runs).min(self.runs);
+        unstable as f64 / self.runs as f64
+    }
+
+    /// Why the step counts as flaky; empty when it does not
+    /// A step that always fails is broken, not flaky.
+    pub fn flakiness_reasons(&self) -> Vec<FlakinessReason> {
+        let mut reasons = Vec::new();
+        if self.runs == 0 || self.failures == self.runs {
+            return reasons;
+        }
+        if self.failures > 0 {
+            reasons.push(FlakinessReason::IntermittentFailure);
+        }
+        if self.retried_runs > 0 {
+            reasons.push(FlakinessReason::NeededRetries);
+        }
+        if self.average_confidence.is_some_and(|c| c < LOW_CONFIDENCE) {
+            reasons.push(FlakinessReason::LowConfidence);
+        }
+        if self
+            .confidence_stddev
+            .is_some_and(|s| s > VARIABLE_CONFIDENCE_STDDEV)
+        {
+            reasons.push(FlakinessReason::VariableConfidence);
+        }
+        reasons
+    }
+
+    /// Risk from the match confidence, from 0 (comfortably above the
+    /// threshold and steady) to 1 (at the threshold or erratic)
+    fn confidence_risk(&self) -> f64 {
+        let low = self.average_confidence.map_or(0.0, |c| {
+            (LOW_CONFIDENCE - c) / (LOW_CONFIDENCE - DEFAULT_CONFIDENCE_THRESHOLD as f64)
+        });
+        let variable = self
+            .confidence_stddev
+            .map_or(0.0, |s| s / (2.0 * VARIABLE_CONFIDENCE_STDDEV));
+        low.max(variable).clamp(0.0, 1.0)
+    }
+}
+
+/// Why a step counts as flaky
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlakinessReason {
+    /// The step both passed and failed
+    IntermittentFailure,
+    /// The step passed only after retries
+    NeededRetries,
+    /// Template matches were close to the match threshold
+    LowConfidence,
+    /// Template match confidence varied between runs
+    VariableConfidence,
+}
+
+/// A flaky step, with why and how flaky it is
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlakyStep {
+    #[serde(flatten)]
+    pub history: StepHistory,
+    pub instability: f64,
+    pub reasons: Vec<FlakinessReason>,
+    /// Ranking score: the instability plus half the confidence risk
+    pub score: f64,
+}
+
+/// Flaky steps of a scenario across its recent runs
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlakinessReport {
+    pub scenario_id: String,
+    pub runs_analyzed: usize,
+    /// Flakiest first
+    pub steps: Vec<FlakyStep>,
+}
+
+/// Aggregate statistics of a run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    pub run: RunRecord,
+    pub step_count: usize,
+    pub passed_steps: usize,
+    pub failed_steps: usize,
+    pub skipped_steps: usize,
+    /// Sum of the step durations
+    pub step_duration_ms: i64,
+    pub retried_steps: usize,
+    pub total_retries: i64,
+    /// Mean match confidence of the image steps, if any
+    pub average_confidence: Option<f64>,
+    /// Slowest steps of this run, slowest first
+    pub slowest_steps: Vec<StepTiming>,
+    /// Flakiest steps of the scenario across its recent runs, flakiest first
+    pub flakiest_steps: Vec<FlakyStep>,
+}
+
+/// Summarize a run with its step timings and the flaky steps of its scenario
+pub async fn get_run_summary(
+    pool: &SqlitePool,
+    run_id: &str,
+) -> Result<RunSummary, XenotesterError> {
+    let run: RunRecord = sqlx::query_as(
+        "SELECT id, scenario_id, scenario_title, status, error_message, started_at, finished_at,
+                duration_ms
+         FROM runs WHERE id = ?",
+    )
+    .bind(run_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| XenotesterError::NotFound(format!("run {}", run_id)))?;
+
+    let steps: Vec<StepTiming> = sqlx::query_as(
+        "SELECT step_index, description, status, duration_ms, attempts, confidence
+         FROM step_results WHERE run_id = ? ORDER BY step_index, id",
+    )
+    .bind(run_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut flakiest_steps = analyze_flakiness(pool, &run.scenario_id).await?.steps;
+    flakiest_steps.truncate(FLAKIEST_STEPS);
+    Ok(summarize(run, steps, flakiest_steps))
+}
+
+/// Rank the steps of a scenario by flakiness over its recent runs
+pub async fn analyze_flakiness(
+    pool: &SqlitePool,
+    scenario_id: &str,
+) -> Result<FlakinessReport, XenotesterError> {
+    let samples: Vec<StepSample> = sqlx::query_as(
+        "SELECT s.run_id, s.step_index, s.description, s.status, s.duration_ms, s.attempts,
+                s.confidence
+         FROM step_results s
+         WHERE s.status != 'skipped'
+           AND s.run_id IN (SELECT id FROM runs WHERE scenario_id = ?
+                            ORDER BY started_at DESC LIMIT ?)
+         ORDER BY s.step_index, s.id",
+    )
+    .bind(scenario_id)
+    .bind(HISTORY_RUNS)
+    .fetch_all(pool)
+    .await?;
+
+    let runs_analyzed = samples
+        .iter()
+        .map(|s| s.run_id.as_str())
+        .collect::<HashSet<_>>()
+        .len();
+    let steps = rank_flaky(aggregate(samples.into_iter().map(|s| s.timing)));
+    Ok(FlakinessReport {
+        scenario_id: scenario_id.to_string(),
+        runs_analyzed,
+        steps,
+    })
+}
+
+/// Per-step outcomes, one entry per step index and description
+fn aggregate(samples: impl IntoIterator<Item = StepTiming>) -> Vec<StepHistory> {
+    let mut groups: BTreeMap<(i64, String), Vec<StepTiming>> = BTreeMap::new();
+    for sample in samples {
+        groups
+            .entry((sample.step_index, sample.description.clone()))
+            .or_default()
+            .push(sample);
+    }
+
+    groups
+        .into_iter()
+        .map(|((step_index, description), samples)| {
+            let runs = samples.len() as f64;
+            let confidences: Vec<f64> = samples.iter().filter_map(|s| s.confidence).collect();
+            let average_confidence = mean(&confidences);
+            let confidence_stddev =
+                average_confidence
+                    .filter(|_| confidences.len() > 1)
+                    .map(|average| {
+                        let variance = confidences
+                            .iter()
+                            .map(|c| (c - average).powi(2))
+                            .sum::<f64>()
+                            / confidences.len() as f64;
+                        variance.sqrt()
+                    });
+            StepHistory {
+                step_index,
+                description,
+                runs: samples.len() as i64,
+                failures: samples.iter().filter(|s| s.status == "failed").count() as i64,
+                retried_runs: samples.iter().filter(|s| s.attempts > 1).count() as i64,
+                total_retries: samples.iter().map(|s| (s.attempts - 1).max(0)).sum(),
+                average_duration_ms: samples.iter().map(|s| s.duration_ms as f64).sum::<f64>()
+                    / runs,
+                average_confidence,
+                min_confidence: confidences.iter().copied().reduce(f64::min),
+                confidence_stddev,
+            }
+        })
+        .collect()
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+fn summarize(run: RunRecord, steps: Vec<StepTiming>, flakiest_steps: Vec<FlakyStep>) -> RunSummary {
+    let count = |status: &str| steps.iter().filter(|s| s.status == status).count();
+    let confidences: Vec<f64> = steps.iter().filter_map(|s| s.confidence).collect();
+
+    let mut slowest_steps = steps.clone();
+    slowest_steps.sort_by(|a, b| b.duration_ms.cmp(&a.duration_ms));
+    slowest_steps.truncate(SLOWEST_STEPS);
+
+    RunSummary {
+        step_count: steps.len(),
+        passed_steps: count("passed"),
+        failed_steps: count("failed"),
+        skipped_steps: count("skipped"),
+        step_duration_ms: steps.iter().map(|s| s.duration_ms).sum(),
+        retried_steps: steps.iter().filter(|s| s.attempts > 1).count(),
+        total_retries: steps.iter().map(|s| (s.attempts - 1).max(0)).sum(),
+        average_confidence: mean(&confidences),
+        slowest_steps,
+        flakiest_steps,
+        run,
+    }
+}
+
+/// Flaky steps, highest score first, then most often executed
+fn rank_flaky(history: Vec<StepHistory>) -> Vec<FlakyStep> {
+    let mut flaky: Vec<FlakyStep> = history
+        .into_iter()
+        .filter_map(|history| {
+            let reasons = history.flakiness_reasons();
+            if reasons.is_empty() {
+                return None;
+            }
+            let instability = history.instability();
+            Some(FlakyStep {
+                score: instability + 0.5 * history.confidence_risk(),
+                instability,
+                reasons,
+                history,
+            })
+        })
+        .collect();
+    flaky.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.history.runs.cmp(&a.history.runs))
+    });
+    flaky
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(step_index: i64, status: &str, duration_ms: i64, attempts: i64) -> StepTiming {
+        StepTiming {
+            step_index,
+            description: format!("Step {}", step_index),
+            status: status.to_string(),
+            duration_ms,
+            attempts,
+            confidence: None,
+        }
+    }
+
+    fn matched(step_index: i64, confidence: f64) -> StepTiming {
+        StepTiming {
+            confidence: Some(confidence),
+            ..timing(step_index, "passed", 100, 1)
+        }
+    }
+
+    fn runs(step_index: i64, outcomes: &[(&str, i64)]) -> Vec<StepTiming> {
+        outcomes
+            .iter()
+            .map(|(status, attempts)| timing(step_index, status, 100, *attempts))
+            .collect()
+    }
+
+    #[test]
+    fn test_rank_flaky() {
+        let mut samples = runs(0, &[("passed", 1); 4]); // stable
+        samples.extend(runs(1, &[("failed", 1); 4])); // always fails
+        samples.extend(runs(
+            2,
+            &[("passed", 1), ("passed", 1), ("passed", 1), ("failed", 1)],
+        ));
+        samples.extend(runs(
+            3,
+            &[("passed", 2), ("passed", 3), ("passed", 1), ("failed", 1)],
+        ));
+
+        let ranked = rank_flaky(aggregate(samples));
+        let order: Vec<i64> = ranked.iter().map(|f| f.history.step_index).collect();
+        assert_eq!(order, vec![3, 2]);
+        assert!((ranked[0].instability - 0.75).abs() < f64::EPSILON);
+        assert_eq!(ranked[0].history.total_retries, 3);
+        assert_eq!(
+            ranked[0].reasons,
+            vec![
+                FlakinessReason::IntermittentFailure,
+                FlakinessReason::NeededRetries
+            ]
+        );
+    }
+
+    #[test]
+    fn test_confidence_flakiness() {
+        let steady = [0.97, 0.98, 0.97].map(|c| matched(0, c));
+        let low = [0.83, 0.84, 0.83].map(|c| matched(1, c));
+        let variable = [0.99, 0.85, 0.98].map(|c| matched(2, c));
+
+        let ranked = rank_flaky(aggregate(steady.into_iter().chain(low).chain(variable)));
+        let reasons: Vec<_> = ranked
+            .iter()
+            .map(|f| (f.history.step_index, f.reasons.clone()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (1, vec![FlakinessReason::LowConfidence]),
+                (2, vec![FlakinessReason::VariableConfidence]),
+            ]
+        );
+        assert_eq!(ranked[1].history.min_confidence, Some(0.85));
+    }
+
+    #[test]
+    fn test_summarize_counts() {
+        let run = RunRecord {
+            id: "run-1".to_string(),
+            scenario_id: "s1".to_string(),
+            scenario_title: "Login".to_string(),
+            status: "failed".to_string(),
+            error_message: None,
+            started_at: "2025-01-02 03:04:05.678".to_string(),
+            finished_at: None,
+            duration_ms: Some(1500),
+        };
+        let mut image_step = timing(1, "passed", 900, 3);
+        image_step.confidence = Some(0.9);
+        let steps = vec![
+            timing(0, "passed", 100, 1),
+            image_step,
+            timing(2, "failed", 300, 1),
+            timing(3, "skipped", 0, 1),
+        ];
+
+        let summary = summarize(run, steps, Vec::new());
+        assert_eq!(summary.step_count, 4);
+        assert_eq!(
+            (
+                summary.passed_steps,
+                summary.failed_steps,
+                summary.skipped_steps
+            ),
+            (2, 1, 1)
+        );
+        assert_eq!(summary.step_duration_ms, 1300);
+        assert_eq!((summary.retried_steps, summary.total_retries), (1, 2));
+        assert_eq!(summary.average_confidence, Some(0.9));
+        assert_eq!(summary.slowest_steps[0].step_index, 1);
+    }
+}
@@ -0,0 +1,143 @@
+//! Run history persistence service
+//!
+//! Records every scenario execution and its per-step results in the
+//! `runs` / `step_results` tables (see migrations 004 and 011).
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::error::XenotesterError;
+
+/// Final (or current) status of a scenario run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Passed,
+    Failed,
+    Stopped,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Running => "running",
+            RunStatus::Passed => "passed",
+            RunStatus::Failed => "failed",
+            RunStatus::Stopped => "stopped",
+        }
+    }
+}
+
+/// Outcome of a single step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl StepStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepStatus::Passed => "passed",
+            StepStatus::Failed => "failed",
+            StepStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// Step result reported by the executor
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepResultInput {
+    pub step_index: u32,
+    pub description: String,
+    pub status: StepStatus,
+    pub error_message: Option<String>,
+    pub duration_ms: u64,
+    /// 1 plus the retries the step needed
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    /// Template match confidence of image steps
+    #[serde(default)]
+    pub confidence: Option<f32>,
+}
+
+fn default_attempts() -> u32 {
+    1
+}
+
+/// Create a new run record in `running` state and return its ID
+pub async fn start_run(
+    pool: &SqlitePool,
+    scenario_id: &str,
+    scenario_title: &str,
+) -> Result<String, XenotesterError> {
+    let run_id = uuid::Uuid::new_v4().to_string();
+
+    sqlx::query("INSERT INTO runs (id, scenario_id, scenario_title, status) VALUES (?, ?, ?, ?)")
+        .bind(&run_id)
+        .bind(scenario_id)
+        .bind(scenario_title)
+        .bind(RunStatus::Running.as_str())
+        .execute(pool)
+        .await?;
+
+    Ok(run_id)
+}
+
+/// Append a step result to a run
+pub async fn record_step_result(
+    pool: &SqlitePool,
+    run_id: &str,
+    step: &StepResultInput,
+) -> Result<(), XenotesterError> {
+    sqlx::query(
+        "INSERT INTO step_results
+             (run_id, step_index, description, status, error_message, duration_ms, attempts,
+              confidence)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(run_id)
+    .bind(step.step_index as i64)
+    .bind(&step.description)
+    .bind(step.status.as_str())
+    .bind(&step.error_message)
+    .bind(step.duration_ms as i64)
+    .bind(step.attempts.max(1) as i64)
+    .bind(step.confidence.map(f64::from))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Mark a run as finished, recording its final status and total duration
+pub async fn finish_run(
+    pool: &SqlitePool,
+    run_id: &str,
+    status: RunStatus,
+    error_message: Option<&str>,
+) -> Result<(), XenotesterError> {
+    let result = sqlx::query(
+        "UPDATE runs
+         SET status = ?,
+             error_message = ?,
+             finished_at = strftime('%Y-%m-%d %H:%M:%f', 'now'),
+             duration_ms = CAST((julianday('now') - julianday(started_at)) * 86400000 AS INTEGER)
+         WHERE id = ?",
+    )
+    .bind(status.as_str())
+    .bind(error_message)
+    .bind(run_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(XenotesterError::NotFound(format!("run {}", run_id)));
+    }
+
+    Ok(())
+}
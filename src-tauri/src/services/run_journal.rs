@@ -0,0 +1,380 @@
+//! Crash-safe journal of runner progress
+//!
+//! The runner appends a JSON line to `<run ID>.jsonl` when a recorded run
+//! starts and around every step, syncing each line to disk, and removes the
+//! file when the run ends. A journal left behind therefore belongs to a run
+//! the app did not see to the end (a crash or a forced quit), and holds what
+//! is needed to resume it: the scenario, its variables and the step it was at.
+//! Dataset runs and sub-scenarios are not journaled.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::error::XenotesterError;
+use crate::services::run_history::{self, RunStatus};
+use crate::services::runner::Scenario;
+
+/// File extension of journals
+const JOURNAL_EXTENSION: &str = "jsonl";
+
+/// Runs journaled by this process that have not ended
+static LIVE_RUNS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+fn is_live(run_id: &str) -> bool {
+    LIVE_RUNS.lock().unwrap().contains(run_id)
+}
+
+/// One line of a journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalEntry {
+    #[serde(rename_all = "camelCase")]
+    RunStarted {
+        run_id: String,
+        scenario: Scenario,
+        variables: HashMap<String, String>,
+        model: String,
+        max_iterations: u32,
+        at_ms: i64,
+    },
+    #[serde(rename_all = "camelCase")]
+    StepStarted {
+        index: usize,
+        description: String,
+        at_ms: i64,
+    },
+    /// A step ended; `next` is the step the run continues with
+    #[serde(rename_all = "camelCase")]
+    StepFinished {
+        index: usize,
+        next: usize,
+        at_ms: i64,
+    },
+}
+
+/// A run whose journal outlived it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedRun {
+    pub run_id: String,
+    pub scenario_id: String,
+    pub scenario_title: String,
+    pub started_at_ms: i64,
+    /// Step a resumed run starts at
+    pub step_index: usize,
+    /// Description of that step; None when the run stopped between steps
+    /// or before its first step
+    pub step_description: Option<String>,
+    /// Time of the last journal entry
+    pub last_activity_ms: i64,
+}
+
+/// What a journal records about an interrupted run
+#[derive(Debug, Clone)]
+pub struct JournalRecord {
+    pub run: InterruptedRun,
+    pub scenario: Scenario,
+    pub variables: HashMap<String, String>,
+    pub model: String,
+    pub max_iterations: u32,
+}
+
+impl JournalRecord {
+    /// Rebuild the run from its entries; None without a `run_started` entry
+    fn from_entries(entries: Vec<JournalEntry>) -> Option<Self> {
+        let mut entries = entries.into_iter();
+        let Some(JournalEntry::RunStarted {
+            run_id,
+            scenario,
+            variables,
+            model,
+            max_iterations,
+            at_ms,
+        }) = entries.next()
+        else {
+            return None;
+        };
+
+        let mut run = InterruptedRun {
+            run_id,
+            scenario_id: scenario.id.clone(),
+            scenario_title: scenario.title.clone(),
+            started_at_ms: at_ms,
+            step_index: 0,
+            step_description: None,
+            last_activity_ms: at_ms,
+        };
+        for entry in entries {
+            match entry {
+                JournalEntry::StepStarted {
+                    index,
+                    description,
+                    at_ms,
+                } => {
+                    run.step_index = index;
+                    run.step_description = Some(description);
+                    run.last_activity_ms = at_ms;
+                }
+                JournalEntry::StepFinished { next, at_ms, .. } => {
+                    run.step_index = next;
+                    run.step_description = None;
+                    run.last_activity_ms = at_ms;
+                }
+                // A second start would be a corrupt journal; keep the first
+                JournalEntry::RunStarted { .. } => {}
+            }
+        }
+        Some(Self {
+            run,
+            scenario,
+            variables,
+            model,
+            max_iterations,
+        })
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Directory of run journals
+#[derive(Debug, Clone)]
+pub struct RunJournal {
+    dir: PathBuf,
+}
+
+impl RunJournal {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, run_id: &str) -> Result<PathBuf, XenotesterError> {
+        // Run IDs are UUIDs; anything else could escape the directory
+        if run_id.is_empty()
+            || !run_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(XenotesterError::ConfigError(format!(
+                "Invalid run ID: {}",
+                run_id
+            )));
+        }
+        Ok(self.dir.join(format!("{}.{}", run_id, JOURNAL_EXTENSION)))
+    }
+
+    /// Append an entry and sync it to disk
+    pub fn append(&self, run_id: &str, entry: &JournalEntry) -> Result<(), XenotesterError> {
+        fs::create_dir_all(&self.dir)?;
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| XenotesterError::ConfigError(format!("Invalid journal entry: {}", e)))?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(run_id)?)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Start the journal of a run
+    pub fn run_started(
+        &self,
+        run_id: &str,
+        scenario: &Scenario,
+        variables: &HashMap<String, String>,
+        model: &str,
+        max_iterations: u32,
+    ) {
+        let entry = JournalEntry::RunStarted {
+            run_id: run_id.to_string(),
+            scenario: scenario.clone(),
+            variables: variables.clone(),
+            model: model.to_string(),
+            max_iterations,
+            at_ms: now_ms(),
+        };
+        LIVE_RUNS.lock().unwrap().insert(run_id.to_string());
+        self.append_or_warn(run_id, &entry);
+    }
+
+    pub fn step_started(&self, run_id: &str, index: usize, description: &str) {
+        let entry = JournalEntry::StepStarted {
+            index,
+            description: description.to_string(),
+            at_ms: now_ms(),
+        };
+        self.append_or_warn(run_id, &entry);
+    }
+
+    pub fn step_finished(&self, run_id: &str, index: usize, next: usize) {
+        let entry = JournalEntry::StepFinished {
+            index,
+            next,
+            at_ms: now_ms(),
+        };
+        self.append_or_warn(run_id, &entry);
+    }
+
+    /// Remove the journal of a run that ended
+    pub fn run_finished(&self, run_id: &str) {
+        LIVE_RUNS.lock().unwrap().remove(run_id);
+        if let Err(e) = self.remove(run_id) {
+            warn!("Failed to remove the journal of run {}: {}", run_id, e);
+        }
+    }
+
+    /// Journaling never fails a run
+    fn append_or_warn(&self, run_id: &str, entry: &JournalEntry) {
+        if let Err(e) = self.append(run_id, entry) {
+            warn!("Failed to journal run {}: {}", run_id, e);
+        }
+    }
+
+    fn remove(&self, run_id: &str) -> Result<(), XenotesterError> {
+        match fs::remove_file(self.path(run_id)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Journal of an interrupted run
+    pub fn load(&self, run_id: &str) -> Result<JournalRecord, XenotesterError> {
+        let path = self.path(run_id)?;
+        if is_live(run_id) || !path.exists() {
+            return Err(XenotesterError::ConfigError(format!(
+                "Run {} was not interrupted",
+                run_id
+            )));
+        }
+        read_journal(&path)?.ok_or_else(|| {
+            XenotesterError::ConfigError(format!("No journal of run {} to resume", run_id))
+        })
+    }
+
+    /// Runs left with a journal by an earlier process, most recent first
+    pub fn interrupted_runs(&self) -> Result<Vec<InterruptedRun>, XenotesterError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut runs = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != JOURNAL_EXTENSION) {
+                continue;
+            }
+            match read_journal(&path) {
+                Ok(Some(record)) if !is_live(&record.run.run_id) => runs.push(record.run),
+                Ok(Some(_)) => {}
+                Ok(None) => warn!("Ignoring journal without a start: {}", path.display()),
+                Err(e) => warn!("Ignoring unreadable journal {}: {}", path.display(), e),
+            }
+        }
+        runs.sort_by(|a, b| b.started_at_ms.cmp(&a.started_at_ms));
+        Ok(runs)
+    }
+
+    /// Drop the journal of an interrupted run and close its history record
+    pub async fn close_interrupted(
+        &self,
+        pool: Option<&SqlitePool>,
+        run: &InterruptedRun,
+        message: &str,
+    ) -> Result<(), XenotesterError> {
+        if let Some(pool) = pool {
+            // The record may be gone (history cleared) or never have existed
+            if let Err(e) =
+                run_history::finish_run(pool, &run.run_id, RunStatus::Failed, Some(message)).await
+            {
+                warn!("Failed to close run {}: {}", run.run_id, e);
+            }
+        }
+        self.remove(&run.run_id)
+    }
+}
+
+/// Entries of a journal; a line cut off by a crash ends the journal
+fn read_journal(path: &Path) -> Result<Option<JournalRecord>, XenotesterError> {
+    let content = fs::read_to_string(path)?;
+    let entries = content
+        .lines()
+        .map_while(|line| serde_json::from_str::<JournalEntry>(line).ok())
+        .collect();
+    Ok(JournalRecord::from_entries(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario() -> Scenario {
+        Scenario {
+            id: "s1".to_string(),
+            title: "Login".to_string(),
+            description: String::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    fn journal() -> RunJournal {
+        RunJournal::new(std::env::temp_dir().join(format!("journal-{}", uuid::Uuid::new_v4())))
+    }
+
+    /// Forget the live runs, as a restarted app would
+    fn restart(run_id: &str) {
+        LIVE_RUNS.lock().unwrap().remove(run_id);
+    }
+
+    #[test]
+    fn test_interrupted_step_is_reported() {
+        let journal = journal();
+        journal.run_started("run-1", &scenario(), &HashMap::new(), "model", 10);
+        journal.step_started("run-1", 0, "Click (1, 2)");
+        journal.step_finished("run-1", 0, 1);
+        journal.step_started("run-1", 1, "Type \"x\"");
+        assert!(journal.interrupted_runs().unwrap().is_empty());
+
+        restart("run-1");
+        let runs = journal.interrupted_runs().unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].scenario_id, "s1");
+        assert_eq!(runs[0].step_index, 1);
+        assert_eq!(runs[0].step_description.as_deref(), Some("Type \"x\""));
+
+        journal.run_finished("run-1");
+        assert!(journal.interrupted_runs().unwrap().is_empty());
+        assert!(journal.load("run-1").is_err());
+        fs::remove_dir_all(&journal.dir).unwrap();
+    }
+
+    #[test]
+    fn test_torn_last_line_is_ignored() {
+        let journal = journal();
+        journal.run_started("run-2", &scenario(), &HashMap::new(), "model", 10);
+        journal.step_started("run-2", 0, "Wait 100 ms");
+        journal.step_finished("run-2", 0, 3);
+        let path = journal.path("run-2").unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"type\":\"step_sta").unwrap();
+        restart("run-2");
+
+        let record = journal.load("run-2").unwrap();
+        assert_eq!(record.run.step_index, 3);
+        assert!(record.run.step_description.is_none());
+        assert_eq!(record.model, "model");
+        fs::remove_dir_all(&journal.dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_path_like_run_ids() {
+        assert!(journal().load("../secrets").is_err());
+    }
+}
@@ -0,0 +1,1189 @@
+//! Scenario runner
+//!
+//! Executes a stored scenario through the backend agent loop, without the
+//! webview. The model is asked to end with a JSON verdict (like the frontend
+//! runner's result schema), which decides whether the scenario passed.
+//! Scenarios with scripted steps (see `steps`) run those instead, calling the
+//! agent only for `agent` steps.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::error::XenotesterError;
+use crate::services::action_log::{self, ActionRecord};
+use crate::services::capture::{
+    capture_primary_monitor, grab_frame, CaptureOptions, CaptureResult,
+};
+use crate::services::capture_history::CaptureHistory;
+use crate::services::dataset::{self, DatasetRow};
+use crate::services::debugger::{BreakpointHit, Debugger, Resume};
+use crate::services::governor::ActionGuard;
+use crate::services::image_processor::{thumbnail_and_encode, ImageEncoding, ResizeQuality};
+use crate::services::input_worker;
+use crate::services::llm;
+use crate::services::llm::anthropic::{AgentSession, ModelConfig, Usage};
+use crate::services::notify::Notifier;
+use crate::services::run_history::{self, RunStatus, StepResultInput, StepStatus};
+use crate::services::run_journal::RunJournal;
+use crate::services::scenario_store::{self, StoredScenario};
+use crate::services::settings;
+use crate::services::steps::{self, Step, StepAction, StepMetrics, MAX_STEP_VISITS};
+use crate::services::usage::record_usage;
+use crate::services::variables::Variables;
+use crate::services::webhook::{
+    ErrorInfo, ScenarioInfo, RUN_COMPLETED_EVENT, RUN_STARTED_EVENT, STEP_FAILED_EVENT,
+    TEST_FAILURE_EVENT,
+};
+use crate::utils::cancel::{CancellationToken, Deadline};
+
+/// Appended to the scenario description so the final answer carries a verdict
+const RESULT_INSTRUCTION: &str = "This is a test. Perform the described UI operations literally \
+and do not look for workarounds. When the scenario is finished, or cannot be completed, stop \
+calling tools and end your answer with a JSON block:\n\
+```json\n{\"status\": \"success\", \"message\": \"...\"}\n```\n\
+or\n\
+```json\n{\"status\": \"failure\", \"message\": \"what went wrong\"}\n```";
+
+/// Scenario as stored in the `scenarios` table or given in a JSON file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scenario {
+    #[serde(default)]
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// Scripted steps; the description is handed to the agent when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<Step>,
+}
+
+/// Settings key of the stored run timeouts
+const TIMEOUTS_KEY: &str = "run_timeouts";
+
+/// Time limits of a run; none by default
+/// A step that runs out of time fails with a timeout, and a run that does is
+/// stopped where it is. Blocking operations stop at their next cancellation check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RunTimeouts {
+    /// Limit of each scripted step, retries included, unless the step sets its own
+    pub step_ms: Option<u64>,
+    /// Limit of the whole run; dataset runs apply only the step limit
+    pub run_ms: Option<u64>,
+}
+
+impl RunTimeouts {
+    pub fn validate(&self) -> Result<(), XenotesterError> {
+        if self.step_ms == Some(0) || self.run_ms == Some(0) {
+            return Err(XenotesterError::ConfigError(
+                "Timeouts must be longer than 0 ms".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn step(&self, step: &Step) -> Option<Duration> {
+        step.timeout_ms.or(self.step_ms).map(Duration::from_millis)
+    }
+}
+
+/// Get the stored run timeouts
+pub async fn load_timeouts(pool: &SqlitePool) -> Result<RunTimeouts, XenotesterError> {
+    Ok(settings::get_json(pool, TIMEOUTS_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Store the run timeouts applied to the app's backend runs
+pub async fn save_timeouts(
+    pool: &SqlitePool,
+    timeouts: &RunTimeouts,
+) -> Result<(), XenotesterError> {
+    timeouts.validate()?;
+    settings::set_json(pool, TIMEOUTS_KEY, timeouts).await
+}
+
+/// Options for a headless run
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    pub model_config: ModelConfig,
+    pub max_iterations: u32,
+    /// Values for `${NAME}` placeholders in the scenario
+    pub variables: HashMap<String, String>,
+    /// Receives the run's lifecycle events
+    pub notifier: Notifier,
+    /// Receives the frames the agent captures
+    pub capture_history: Option<Arc<CaptureHistory>>,
+    /// Records the progress of recorded runs, so they can be resumed after a crash
+    pub journal: Option<RunJournal>,
+    /// Pauses scripted steps at breakpoints
+    pub debugger: Option<Arc<Debugger>>,
+    pub timeouts: RunTimeouts,
+    /// Governor, confirmation gate and user activity pause applied to the run's
+    /// input actions; `None` runs them unchecked
+    pub guard: Option<ActionGuard>,
+}
+
+impl RunOptions {
+    /// These options with the guard counting actions against the recorded run
+    fn counted_against(&self, run_id: Option<&str>) -> Self {
+        let mut options = self.clone();
+        if let (Some(guard), Some(run_id)) = (&mut options.guard, run_id) {
+            guard.run_id = Some(run_id.to_string());
+        }
+        options
+    }
+}
+
+/// Final outcome of a scenario
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScenarioStatus {
+    /// The model reported success
+    Success,
+    /// The model reported failure, or finished without a verdict
+    Failure,
+    /// The iteration limit was reached before the model finished, or a step
+    /// or the run ran out of time
+    Timeout,
+    /// The run was cancelled
+    Stopped,
+    /// The agent loop itself failed (API, capture or input error)
+    Error,
+}
+
+impl ScenarioStatus {
+    fn run_status(&self) -> RunStatus {
+        match self {
+            ScenarioStatus::Success => RunStatus::Passed,
+            ScenarioStatus::Stopped => RunStatus::Stopped,
+            _ => RunStatus::Failed,
+        }
+    }
+}
+
+/// Result of running one scenario
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioRunResult {
+    pub scenario_id: String,
+    pub title: String,
+    pub success: bool,
+    pub status: ScenarioStatus,
+    pub message: String,
+    /// Run history ID when the run was recorded in the database
+    pub run_id: Option<String>,
+    pub iterations: u32,
+    pub duration_ms: u64,
+    pub usage: Usage,
+    /// Per-row results of a dataset run
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<DatasetRowResult>,
+    /// Results of the scenarios run as steps, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ScenarioRunResult>,
+}
+
+impl ScenarioRunResult {
+    fn new(
+        scenario: &Scenario,
+        run_id: Option<String>,
+        execution: Execution,
+        started: Instant,
+    ) -> Self {
+        Self {
+            scenario_id: scenario.id.clone(),
+            title: scenario.title.clone(),
+            success: execution.status == ScenarioStatus::Success,
+            status: execution.status,
+            message: execution.message,
+            run_id,
+            iterations: execution.iterations,
+            duration_ms: started.elapsed().as_millis() as u64,
+            usage: execution.usage,
+            rows: Vec::new(),
+            children: execution.children,
+        }
+    }
+}
+
+/// Result of one dataset row
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetRowResult {
+    /// Row label, e.g. "Row 2 (user=bob)"
+    pub row: String,
+    pub success: bool,
+    pub status: ScenarioStatus,
+    pub message: String,
+    pub iterations: u32,
+    pub duration_ms: u64,
+    /// Results of the scenarios run as steps, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ScenarioRunResult>,
+}
+
+/// Verdict parsed from the model's final text
+#[derive(Debug, Deserialize)]
+struct Verdict {
+    status: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// Find the last JSON object with a "status" field in the model's text
+fn parse_verdict(text: &str) -> Option<Verdict> {
+    text.match_indices('{')
+        .rev()
+        .filter_map(|(start, _)| {
+            let mut stream =
+                serde_json::Deserializer::from_str(&text[start..]).into_iter::<Value>();
+            match stream.next() {
+                Some(Ok(value)) if value.get("status").is_some() => {
+                    serde_json::from_value(value).ok()
+                }
+                _ => None,
+            }
+        })
+        .next()
+}
+
+impl TryFrom<StoredScenario> for Scenario {
+    type Error = XenotesterError;
+
+    fn try_from(stored: StoredScenario) -> Result<Self, Self::Error> {
+        let steps = stored.parsed_steps()?;
+        Ok(Self {
+            id: stored.id,
+            title: stored.title,
+            description: stored.description,
+            steps,
+        })
+    }
+}
+
+/// Load a scenario by ID
+pub async fn load_scenario(pool: &SqlitePool, id: &str) -> Result<Scenario, XenotesterError> {
+    scenario_store::require_scenario(pool, id).await?.try_into()
+}
+
+/// Load all scenarios in their display order
+pub async fn load_all_scenarios(pool: &SqlitePool) -> Result<Vec<Scenario>, XenotesterError> {
+    scenario_store::list_scenarios(pool)
+        .await?
+        .into_iter()
+        .map(Scenario::try_from)
+        .collect()
+}
+
+/// Sub-scenarios nested deeper than this are rejected
+const MAX_SCENARIO_DEPTH: usize = 8;
+
+/// Outcome of one execution, before it is recorded
+struct Execution {
+    status: ScenarioStatus,
+    message: String,
+    iterations: u32,
+    usage: Usage,
+    children: Vec<ScenarioRunResult>,
+}
+
+/// Run a scenario's steps from step `start`, or its description through the
+/// agent loop when it has none. With a `run_id`, step results are recorded in
+/// the run history. `callers` are the IDs of the scenarios that run this one
+/// as a step.
+#[allow(clippy::too_many_arguments)]
+async fn execute(
+    scenario: &Scenario,
+    options: &RunOptions,
+    variables: Variables,
+    pool: Option<&SqlitePool>,
+    run_id: Option<&str>,
+    cancel: &CancellationToken,
+    callers: &[String],
+    start: usize,
+) -> Execution {
+    if scenario.steps.is_empty() && start == 0 {
+        return run_agent(&scenario.description, options, variables, pool, cancel).await;
+    }
+
+    let mut chain = callers.to_vec();
+    chain.push(scenario.id.clone());
+    execute_steps(
+        scenario, options, variables, pool, run_id, cancel, &chain, start,
+    )
+    .await
+}
+
+/// Run a stored scenario as a step of the last scenario in `chain`
+///
+/// The child sees the caller's run variables and secrets, overridden by
+/// `variables` (which may reference the caller's). Its steps are reported in
+/// the result, not recorded in the run history.
+async fn run_scenario_as_step(
+    child_id: &str,
+    variables: &HashMap<String, String>,
+    caller_variables: &Variables,
+    options: &RunOptions,
+    pool: Option<&SqlitePool>,
+    cancel: &CancellationToken,
+    chain: &[String],
+) -> Result<ScenarioRunResult, XenotesterError> {
+    if chain.iter().any(|id| id == child_id) {
+        return Err(XenotesterError::ConfigError(format!(
+            "Scenario cycle: {} -> {}",
+            chain.join(" -> "),
+            child_id
+        )));
+    }
+    if chain.len() >= MAX_SCENARIO_DEPTH {
+        return Err(XenotesterError::ConfigError(format!(
+            "Sub-scenarios are nested more than {} levels deep",
+            MAX_SCENARIO_DEPTH
+        )));
+    }
+    let pool_ref = pool.ok_or_else(|| {
+        XenotesterError::ConfigError("Sub-scenarios need the application database".to_string())
+    })?;
+    let child = load_scenario(pool_ref, child_id).await?;
+
+    let variables = caller_variables.for_child(variables)?;
+
+    let started = Instant::now();
+    let execution = Box::pin(execute(
+        &child, options, variables, pool, None, cancel, chain, 0,
+    ))
+    .await;
+    Ok(ScenarioRunResult::new(&child, None, execution, started))
+}
+
+/// Run an instruction through the agent loop until the model reports a verdict
+async fn run_agent(
+    instruction: &str,
+    options: &RunOptions,
+    mut variables: Variables,
+    pool: Option<&SqlitePool>,
+    cancel: &CancellationToken,
+) -> Execution {
+    let mut usage = Usage::default();
+    let mut iterations = 0;
+
+    let outcome = async {
+        let instruction = variables.prepare_instruction(pool, instruction).await?;
+        let instruction = format!("{}\n\n{}", instruction, RESULT_INSTRUCTION);
+
+        let client = llm::create_backend(options.model_config.provider)?;
+        let mut session =
+            AgentSession::start(&instruction, options.model_config.clone(), None).await?;
+        session.set_variables(variables);
+        if let Some(guard) = &options.guard {
+            session.set_guard(guard.clone());
+        }
+        if let Some(history) = &options.capture_history {
+            session.set_capture_history(history.clone());
+        }
+        if let Some(pool) = pool {
+            session.set_action_log(pool.clone());
+        }
+        let result = session
+            .run_loop(&*client, cancel, options.max_iterations, &mut |_| {})
+            .await;
+        usage = session.total_usage().clone();
+        iterations = result.as_ref().map(|r| r.iterations).unwrap_or_default();
+        result
+    }
+    .await;
+
+    let (status, message) = match outcome {
+        Ok(result) if !result.completed => (
+            ScenarioStatus::Timeout,
+            format!("Stopped after {} iterations", result.iterations),
+        ),
+        Ok(result) => match parse_verdict(&result.final_text) {
+            Some(verdict) if verdict.status == "success" => {
+                (ScenarioStatus::Success, verdict.message)
+            }
+            Some(verdict) => (ScenarioStatus::Failure, verdict.message),
+            None => (
+                ScenarioStatus::Failure,
+                format!("No result reported: {}", result.final_text.trim()),
+            ),
+        },
+        Err(XenotesterError::Cancelled) => {
+            (ScenarioStatus::Stopped, "Run was cancelled".to_string())
+        }
+        Err(e) => (ScenarioStatus::Error, e.to_string()),
+    };
+
+    Execution {
+        status,
+        message,
+        iterations,
+        usage,
+        children: Vec::new(),
+    }
+}
+
+/// How a scripted step ended
+enum StepOutcome {
+    Done,
+    /// A condition did not hold
+    Skipped,
+    Failed(ScenarioStatus, String),
+}
+
+/// Execute one scripted step of the last scenario in `chain`, adding agent
+/// usage and sub-scenario results to `execution` and reporting input step
+/// attempts and match confidence in `metrics`
+#[allow(clippy::too_many_arguments)]
+async fn run_step(
+    step: &Step,
+    options: &RunOptions,
+    variables: &Variables,
+    pool: Option<&SqlitePool>,
+    cancel: &CancellationToken,
+    chain: &[String],
+    execution: &mut Execution,
+    metrics: &mut StepMetrics,
+) -> Result<StepOutcome, XenotesterError> {
+    if step.is_conditional() {
+        let (step, variables) = (step.clone(), variables.clone());
+        let holds = tokio::task::spawn_blocking(move || steps::conditions_hold(&step, &variables))
+            .await
+            .map_err(|e| {
+                XenotesterError::CaptureError(format!("Condition check failed: {}", e))
+            })??;
+        if !holds {
+            return Ok(StepOutcome::Skipped);
+        }
+    }
+
+    match &step.action {
+        StepAction::Agent { instruction } => {
+            let agent = run_agent(instruction, options, variables.clone(), pool, cancel).await;
+            execution.usage.add(&agent.usage);
+            execution.iterations += agent.iterations;
+            Ok(match agent.status {
+                ScenarioStatus::Success => StepOutcome::Done,
+                ScenarioStatus::Stopped => return Err(XenotesterError::Cancelled),
+                status => StepOutcome::Failed(status, agent.message),
+            })
+        }
+        StepAction::RunScenario {
+            scenario_id,
+            variables: child_variables,
+        } => {
+            let child = run_scenario_as_step(
+                scenario_id,
+                child_variables,
+                variables,
+                options,
+                pool,
+                cancel,
+                chain,
+            )
+            .await?;
+            execution.usage.add(&child.usage);
+            execution.iterations += child.iterations;
+            let outcome = match child.status {
+                ScenarioStatus::Success => StepOutcome::Done,
+                ScenarioStatus::Stopped => return Err(XenotesterError::Cancelled),
+                status => {
+                    StepOutcome::Failed(status, format!("{}: {}", child.title, child.message))
+                }
+            };
+            execution.children.push(child);
+            Ok(outcome)
+        }
+        StepAction::Fail { message } => Ok(StepOutcome::Failed(
+            ScenarioStatus::Failure,
+            variables.substitute_public(message)?,
+        )),
+        StepAction::Goto { .. } | StepAction::Skip { .. } => Ok(StepOutcome::Done),
+        _ => {
+            let guarded = step.guarded_action();
+            let result = async {
+                if let (Some(guard), Some((action, position, keys))) = (&options.guard, guarded) {
+                    guard.check(action, position, keys, cancel).await?;
+                }
+                let governor = options.guard.as_ref().map(|guard| guard.governor.clone());
+                let (step, variables, cancel) = (step.clone(), variables.clone(), cancel.clone());
+                let (result, performed) = input_worker::submit(move || {
+                    steps::perform_with_retry(&step, &variables, governor.as_deref(), &cancel)
+                })
+                .await?;
+                *metrics = performed;
+                result
+            }
+            .await;
+            if let (Some(pool), Some((action, position, _))) = (pool, guarded) {
+                log_step_action(pool, options, step, action, position, &result).await;
+            }
+            result?;
+            Ok(StepOutcome::Done)
+        }
+    }
+}
+
+/// Record an executed or refused input step in the action log, under the run
+/// Failing to record only logs a warning.
+async fn log_step_action(
+    pool: &SqlitePool,
+    options: &RunOptions,
+    step: &Step,
+    action: &'static str,
+    position: Option<(i32, i32)>,
+    result: &Result<(), XenotesterError>,
+) {
+    // The description leaves out typed text, which may hold secrets
+    let record = ActionRecord::new(action, position, json!({ "step": step.describe() }));
+    let run_id = options
+        .guard
+        .as_ref()
+        .and_then(|guard| guard.run_id.as_deref());
+    let error = result.as_ref().err().map(ToString::to_string);
+    if let Err(e) = action_log::record_action(pool, run_id, &record, error.as_deref()).await {
+        warn!("Failed to record {} in the action log: {}", action, e);
+    }
+}
+
+/// Execute the scripted steps of `scenario`, the last in `chain`, in order
+/// from step `start`, following conditions, `goto` and `skip`
+/// The progress of a recorded top-level run is journaled.
+#[allow(clippy::too_many_arguments)]
+async fn execute_steps(
+    scenario: &Scenario,
+    options: &RunOptions,
+    mut variables: Variables,
+    pool: Option<&SqlitePool>,
+    run_id: Option<&str>,
+    cancel: &CancellationToken,
+    chain: &[String],
+    start: usize,
+) -> Execution {
+    let mut execution = Execution {
+        status: ScenarioStatus::Success,
+        message: String::new(),
+        iterations: 0,
+        usage: Usage::default(),
+        children: Vec::new(),
+    };
+
+    let steps = &scenario.steps;
+    let journal = options
+        .journal
+        .as_ref()
+        .filter(|_| chain.len() == 1)
+        .zip(run_id);
+    let outcome = async {
+        let labels = steps::validate(steps)?;
+        if start >= steps.len() {
+            return Err(XenotesterError::ConfigError(format!(
+                "Step {} does not exist, the scenario has {} steps",
+                start + 1,
+                steps.len()
+            )));
+        }
+        let texts: Vec<&str> = steps.iter().flat_map(Step::texts).collect();
+        variables.load_secrets(pool, &texts).await?;
+
+        let mut visits = vec![0u32; steps.len()];
+        let mut executed = 0;
+        let mut index = start;
+        // Set by `step_over`: pause before the next step
+        let mut stepping = false;
+        while index < steps.len() {
+            cancel.check()?;
+            let step = &steps[index];
+            let description = step.describe();
+            if let Some(debugger) = options
+                .debugger
+                .as_ref()
+                .filter(|debugger| debugger.should_pause(&scenario.id, index, stepping))
+            {
+                let hit = BreakpointHit {
+                    pause_id: Uuid::new_v4().to_string(),
+                    run_id: run_id.map(str::to_string),
+                    scenario_id: scenario.id.clone(),
+                    scenario_title: scenario.title.clone(),
+                    step_index: index,
+                    step: description.clone(),
+                    screenshot: breakpoint_screenshot().await,
+                };
+                stepping = debugger.pause(hit, cancel).await? == Resume::StepOver;
+            }
+            let started = Instant::now();
+            let mut metrics = StepMetrics::default();
+            if let Some((journal, run_id)) = journal {
+                journal.step_started(run_id, index, &description);
+            }
+
+            visits[index] += 1;
+            let outcome = if visits[index] > MAX_STEP_VISITS {
+                StepOutcome::Failed(
+                    ScenarioStatus::Error,
+                    format!(
+                        "Ran more than {} times, check the goto steps",
+                        MAX_STEP_VISITS
+                    ),
+                )
+            } else {
+                let timeout = options.timeouts.step(step);
+                let (variables, execution, metrics) = (&variables, &mut execution, &mut metrics);
+                let performed = cancel
+                    .with_deadline(timeout, |cancel| async move {
+                        run_step(
+                            step, options, variables, pool, &cancel, chain, execution, metrics,
+                        )
+                        .await
+                    })
+                    .await;
+                let result = match performed {
+                    Deadline::Finished(result) => result,
+                    Deadline::Expired(_) => Err(XenotesterError::Timeout(format!(
+                        "step took longer than {} ms",
+                        timeout.unwrap_or_default().as_millis()
+                    ))),
+                };
+                match result {
+                    Ok(outcome) => outcome,
+                    Err(XenotesterError::Cancelled) => return Err(XenotesterError::Cancelled),
+                    Err(e @ XenotesterError::Timeout(_)) => {
+                        StepOutcome::Failed(ScenarioStatus::Timeout, e.to_string())
+                    }
+                    Err(e) => StepOutcome::Failed(ScenarioStatus::Error, e.to_string()),
+                }
+            };
+
+            let (step_status, error_message) = match &outcome {
+                StepOutcome::Done => (StepStatus::Passed, None),
+                StepOutcome::Skipped => (StepStatus::Skipped, None),
+                StepOutcome::Failed(_, message) => (StepStatus::Failed, Some(message.clone())),
+            };
+            if let (Some(pool), Some(run_id)) = (pool, run_id) {
+                let result = StepResultInput {
+                    step_index: index as u32,
+                    description: description.clone(),
+                    status: step_status,
+                    error_message,
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    attempts: metrics.attempts,
+                    confidence: metrics.confidence,
+                };
+                if let Err(e) = run_history::record_step_result(pool, run_id, &result).await {
+                    warn!("Failed to record step result: {}", e);
+                }
+            }
+
+            let next = match outcome {
+                StepOutcome::Failed(status, message) => {
+                    // Steps of sub-scenarios are reported through their caller's step
+                    if chain.len() == 1 {
+                        let data = json!({
+                            "scenario": scenario_info(scenario),
+                            "runId": run_id,
+                            "stepIndex": index,
+                            "step": description,
+                            "message": message,
+                        });
+                        let screenshot = failure_screenshot(options, STEP_FAILED_EVENT).await;
+                        options
+                            .notifier
+                            .emit_with_screenshot(STEP_FAILED_EVENT, data, screenshot.as_deref())
+                            .await;
+                    }
+                    return Ok((
+                        status,
+                        format!("Step {} ({}): {}", index + 1, description, message),
+                    ));
+                }
+                StepOutcome::Skipped => index + 1,
+                StepOutcome::Done => {
+                    executed += 1;
+                    steps::next_index(steps, index, &labels)?
+                }
+            };
+            if let Some((journal, run_id)) = journal {
+                journal.step_finished(run_id, index, next);
+            }
+            index = next;
+        }
+        Ok((
+            ScenarioStatus::Success,
+            format!("Completed {} steps", executed),
+        ))
+    }
+    .await;
+
+    (execution.status, execution.message) = match outcome {
+        Ok(result) => result,
+        Err(XenotesterError::Cancelled) => {
+            (ScenarioStatus::Stopped, "Run was cancelled".to_string())
+        }
+        Err(e) => (ScenarioStatus::Error, e.to_string()),
+    };
+    execution
+}
+
+/// Execution of a run stopped by the run timeout; None if it did not stop in time
+fn timed_out(execution: Option<Execution>, timeout: Duration) -> Execution {
+    let mut execution = execution.unwrap_or(Execution {
+        status: ScenarioStatus::Timeout,
+        message: String::new(),
+        iterations: 0,
+        usage: Usage::default(),
+        children: Vec::new(),
+    });
+    execution.status = ScenarioStatus::Timeout;
+    execution.message =
+        XenotesterError::Timeout(format!("run took longer than {} ms", timeout.as_millis()))
+            .to_string();
+    execution
+}
+
+async fn start_recording(pool: Option<&SqlitePool>, scenario: &Scenario) -> Option<String> {
+    run_history::start_run(pool?, &scenario.id, &scenario.title)
+        .await
+        .map_err(|e| warn!("Failed to record run start: {}", e))
+        .ok()
+}
+
+/// Long edge of the screenshot attached to failure notifications
+const NOTIFICATION_SCREENSHOT_EDGE: u32 = 640;
+
+/// PNG thumbnail of the primary monitor as the failure left it, when a
+/// channel subscribed to `event` asked for screenshots
+async fn failure_screenshot(options: &RunOptions, event: &str) -> Option<Vec<u8>> {
+    if !options.notifier.wants_screenshot(event) {
+        return None;
+    }
+    let captured = tokio::task::spawn_blocking(|| {
+        let frame = grab_frame(None, false)?;
+        thumbnail_and_encode(
+            frame.image,
+            NOTIFICATION_SCREENSHOT_EDGE,
+            &ImageEncoding::default(),
+            ResizeQuality::Fast,
+        )
+        .map(|encoded| encoded.bytes)
+    })
+    .await;
+    match captured {
+        Ok(Ok(png)) => Some(png),
+        Ok(Err(e)) => {
+            warn!("Failed to capture the failure screenshot: {}", e);
+            None
+        }
+        Err(e) => {
+            warn!("Failure screenshot task failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Primary monitor at a breakpoint
+async fn breakpoint_screenshot() -> Option<CaptureResult> {
+    let captured =
+        tokio::task::spawn_blocking(|| capture_primary_monitor(&CaptureOptions::default())).await;
+    match captured {
+        Ok(Ok(capture)) => Some(capture),
+        Ok(Err(e)) => {
+            warn!("Failed to capture the breakpoint screenshot: {}", e);
+            None
+        }
+        Err(e) => {
+            warn!("Breakpoint screenshot task failed: {}", e);
+            None
+        }
+    }
+}
+
+fn scenario_info(scenario: &Scenario) -> ScenarioInfo {
+    ScenarioInfo {
+        id: scenario.id.clone(),
+        title: scenario.title.clone(),
+    }
+}
+
+async fn notify_started(options: &RunOptions, scenario: &Scenario, run_id: Option<&str>) {
+    let data = json!({ "scenario": scenario_info(scenario), "runId": run_id });
+    options.notifier.emit(RUN_STARTED_EVENT, data).await;
+}
+
+/// Report the finished run, and a `test_failure` unless it passed or was stopped
+async fn notify_finished(options: &RunOptions, result: &ScenarioRunResult) {
+    let scenario = ScenarioInfo {
+        id: result.scenario_id.clone(),
+        title: result.title.clone(),
+    };
+    let data = json!({
+        "scenario": scenario,
+        "runId": result.run_id,
+        "success": result.success,
+        "status": result.status,
+        "message": result.message,
+        "durationMs": result.duration_ms,
+    });
+    options.notifier.emit(RUN_COMPLETED_EVENT, data).await;
+
+    if !result.success && result.status != ScenarioStatus::Stopped {
+        let error = ErrorInfo {
+            message: result.message.clone(),
+            failed_at_action: None,
+            last_successful_action: None,
+            completed_actions: 0,
+        };
+        let data = json!({ "scenario": scenario, "error": error });
+        let screenshot = failure_screenshot(options, TEST_FAILURE_EVENT).await;
+        options
+            .notifier
+            .emit_with_screenshot(TEST_FAILURE_EVENT, data, screenshot.as_deref())
+            .await;
+    }
+}
+
+async fn finish_recording(
+    pool: Option<&SqlitePool>,
+    run_id: Option<&str>,
+    status: ScenarioStatus,
+    message: &str,
+    options: &RunOptions,
+    usage: &Usage,
+) {
+    let (Some(pool), Some(run_id)) = (pool, run_id) else {
+        return;
+    };
+    if let Some(guard) = &options.guard {
+        guard.governor.finish_run(run_id);
+    }
+    let error_message = (status != ScenarioStatus::Success).then_some(message);
+    if let Err(e) = run_history::finish_run(pool, run_id, status.run_status(), error_message).await
+    {
+        warn!("Failed to record run result: {}", e);
+    }
+    if usage.input_tokens > 0 || usage.output_tokens > 0 {
+        if let Err(e) = record_usage(pool, Some(run_id), &options.model_config.model, usage).await {
+            warn!("Failed to record token usage: {}", e);
+        }
+    }
+}
+
+/// Run a scenario to completion
+///
+/// With a `pool`, the run is recorded in the run history and its token usage
+/// is accounted. Agent failures are reported in the result, not as an error.
+pub async fn run_scenario(
+    scenario: &Scenario,
+    options: &RunOptions,
+    pool: Option<&SqlitePool>,
+    cancel: &CancellationToken,
+) -> ScenarioRunResult {
+    run_scenario_from(scenario, options, pool, cancel, 0).await
+}
+
+/// Run a scenario from its step `start` (0-based) to completion, like `run_scenario`
+/// The skipped steps are neither run nor recorded.
+pub async fn run_scenario_from(
+    scenario: &Scenario,
+    options: &RunOptions,
+    pool: Option<&SqlitePool>,
+    cancel: &CancellationToken,
+    start: usize,
+) -> ScenarioRunResult {
+    let started = Instant::now();
+    let run_id = start_recording(pool, scenario).await;
+    let options = &options.counted_against(run_id.as_deref());
+    let journal = options.journal.as_ref().zip(run_id.as_deref());
+    if let Some((journal, run_id)) = journal {
+        journal.run_started(
+            run_id,
+            scenario,
+            &options.variables,
+            &options.model_config.model,
+            options.max_iterations,
+        );
+    }
+    notify_started(options, scenario, run_id.as_deref()).await;
+
+    let timeout = options.timeouts.run_ms.map(Duration::from_millis);
+    let recorded = run_id.as_deref();
+    let execution = match cancel
+        .with_deadline(timeout, |cancel| async move {
+            execute(
+                scenario,
+                options,
+                Variables::new(options.variables.clone()),
+                pool,
+                recorded,
+                &cancel,
+                &[],
+                start,
+            )
+            .await
+        })
+        .await
+    {
+        Deadline::Finished(execution) => execution,
+        Deadline::Expired(execution) => timed_out(execution, timeout.unwrap_or_default()),
+    };
+
+    finish_recording(
+        pool,
+        run_id.as_deref(),
+        execution.status,
+        &execution.message,
+        options,
+        &execution.usage,
+    )
+    .await;
+    if let Some((journal, run_id)) = journal {
+        journal.run_finished(run_id);
+    }
+
+    let result = ScenarioRunResult::new(scenario, run_id, execution, started);
+    notify_finished(options, &result).await;
+    result
+}
+
+/// Run a stored scenario from its step `step_index` (0-based), like `run_scenario_from`
+///
+/// Meant for re-checking a fixed step without replaying the steps before it,
+/// so the application must already be in the state those steps leave it in.
+pub async fn run_scenario_from_step(
+    scenario_id: &str,
+    step_index: usize,
+    options: &RunOptions,
+    pool: &SqlitePool,
+    cancel: &CancellationToken,
+) -> Result<ScenarioRunResult, XenotesterError> {
+    let scenario = load_scenario(pool, scenario_id).await?;
+    if scenario.steps.is_empty() {
+        return Err(XenotesterError::ConfigError(format!(
+            "Scenario {} has no steps to start from",
+            scenario_id
+        )));
+    }
+    if step_index >= scenario.steps.len() {
+        return Err(XenotesterError::ConfigError(format!(
+            "Step {} does not exist, the scenario has {} steps",
+            step_index + 1,
+            scenario.steps.len()
+        )));
+    }
+    Ok(run_scenario_from(&scenario, options, Some(pool), cancel, step_index).await)
+}
+
+/// Run a scenario once per dataset row
+///
+/// Row values override `options.variables`. The rows are recorded as the
+/// steps of a single run, which fails when any row fails. After
+/// cancellation the remaining rows are skipped.
+pub async fn run_dataset(
+    scenario: &Scenario,
+    options: &RunOptions,
+    rows: &[DatasetRow],
+    pool: Option<&SqlitePool>,
+    cancel: &CancellationToken,
+) -> ScenarioRunResult {
+    let started = Instant::now();
+    let run_id = start_recording(pool, scenario).await;
+    let options = &options.counted_against(run_id.as_deref());
+    notify_started(options, scenario, run_id.as_deref()).await;
+
+    let mut usage = Usage::default();
+    let mut iterations = 0;
+    let mut results = Vec::with_capacity(rows.len());
+
+    for (index, row) in rows.iter().enumerate() {
+        let label = dataset::row_label(index, row);
+        let row_started = Instant::now();
+
+        let execution = if cancel.is_cancelled() {
+            None
+        } else {
+            let mut variables = options.variables.clone();
+            variables.extend(dataset::row_variables(row));
+            // Rows are the recorded steps of a dataset run
+            let variables = Variables::new(variables);
+            let execution = execute(scenario, options, variables, pool, None, cancel, &[], 0).await;
+            usage.add(&execution.usage);
+            iterations += execution.iterations;
+            Some(execution)
+        };
+
+        let execution = execution.unwrap_or_else(|| Execution {
+            status: ScenarioStatus::Stopped,
+            message: "Run was cancelled".to_string(),
+            iterations: 0,
+            usage: Usage::default(),
+            children: Vec::new(),
+        });
+        let step_status = match execution.status {
+            ScenarioStatus::Success => StepStatus::Passed,
+            ScenarioStatus::Stopped => StepStatus::Skipped,
+            _ => StepStatus::Failed,
+        };
+        let Execution {
+            status,
+            message,
+            iterations: row_iterations,
+            children,
+            ..
+        } = execution;
+        let duration_ms = row_started.elapsed().as_millis() as u64;
+        info!("{}: {:?}", label, status);
+
+        if let (Some(pool), Some(run_id)) = (pool, run_id.as_deref()) {
+            let step = StepResultInput {
+                step_index: index as u32,
+                description: label.clone(),
+                status: step_status,
+                error_message: (status != ScenarioStatus::Success).then(|| message.clone()),
+                duration_ms,
+                attempts: 1,
+                confidence: None,
+            };
+            if let Err(e) = run_history::record_step_result(pool, run_id, &step).await {
+                warn!("Failed to record row result: {}", e);
+            }
+        }
+
+        results.push(DatasetRowResult {
+            row: label,
+            success: status == ScenarioStatus::Success,
+            status,
+            message,
+            iterations: row_iterations,
+            duration_ms,
+            children,
+        });
+    }
+
+    let failed = results.iter().filter(|r| !r.success).count();
+    let (status, message) = if cancel.is_cancelled() {
+        (ScenarioStatus::Stopped, "Run was cancelled".to_string())
+    } else if failed > 0 {
+        (
+            ScenarioStatus::Failure,
+            format!("{} of {} rows failed", failed, results.len()),
+        )
+    } else {
+        (
+            ScenarioStatus::Success,
+            format!("All {} rows passed", results.len()),
+        )
+    };
+
+    finish_recording(pool, run_id.as_deref(), status, &message, options, &usage).await;
+
+    let result = ScenarioRunResult {
+        scenario_id: scenario.id.clone(),
+        title: scenario.title.clone(),
+        success: status == ScenarioStatus::Success,
+        status,
+        message,
+        run_id,
+        iterations,
+        duration_ms: started.elapsed().as_millis() as u64,
+        usage,
+        rows: results,
+        children: Vec::new(),
+    };
+    notify_finished(options, &result).await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_verdict_uses_last_status_object() {
+        let text =
+            "Clicked {\"x\": 1}.\n```json\n{\"status\": \"failure\", \"message\": \"old\"}\n```\n\
+                    Done.\n```json\n{\"status\": \"success\", \"message\": \"ok\"}\n```";
+        let verdict = parse_verdict(text).unwrap();
+        assert_eq!(verdict.status, "success");
+        assert_eq!(verdict.message, "ok");
+    }
+
+    #[test]
+    fn test_parse_verdict_without_status() {
+        assert!(parse_verdict("All done {\"x\": 1}").is_none());
+        assert!(parse_verdict("no json here").is_none());
+    }
+
+    #[test]
+    fn test_step_timeout_overrides_run_default() {
+        let timeouts = RunTimeouts {
+            step_ms: Some(5000),
+            run_ms: None,
+        };
+        let step: Step = serde_json::from_value(json!({ "type": "wait", "ms": 100 })).unwrap();
+        assert_eq!(timeouts.step(&step), Some(Duration::from_millis(5000)));
+
+        let step: Step =
+            serde_json::from_value(json!({ "type": "wait", "ms": 100, "timeoutMs": 250 })).unwrap();
+        assert_eq!(timeouts.step(&step), Some(Duration::from_millis(250)));
+        assert!(RunTimeouts {
+            run_ms: Some(0),
+            ..timeouts
+        }
+        .validate()
+        .is_err());
+    }
+
+    fn options() -> RunOptions {
+        RunOptions {
+            model_config: ModelConfig::default(),
+            max_iterations: 1,
+            variables: HashMap::new(),
+            notifier: Notifier::disabled(),
+            capture_history: None,
+            journal: None,
+            debugger: None,
+            timeouts: RunTimeouts::default(),
+            guard: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sub_scenario_cycles_and_depth_are_rejected() {
+        let (options, cancel) = (options(), CancellationToken::new());
+        let run = |chain: Vec<String>| {
+            let (options, cancel) = (options.clone(), cancel.clone());
+            async move {
+                run_scenario_as_step(
+                    "login",
+                    &HashMap::new(),
+                    &Variables::default(),
+                    &options,
+                    None,
+                    &cancel,
+                    &chain,
+                )
+                .await
+                .unwrap_err()
+                .to_string()
+            }
+        };
+
+        let chain = vec!["main".to_string(), "login".to_string(), "setup".to_string()];
+        assert!(run(chain)
+            .await
+            .contains("Scenario cycle: main -> login -> setup -> login"));
+
+        let chain = (0..MAX_SCENARIO_DEPTH).map(|i| format!("s{}", i)).collect();
+        assert!(run(chain).await.contains("nested more than"));
+
+        // Past both checks the child has to be loaded from the database
+        let chain = vec!["main".to_string()];
+        assert!(run(chain).await.contains("application database"));
+    }
+}
@@ -0,0 +1,235 @@
+//! Scenario persistence service
+//!
+//! CRUD for the `scenarios` table (see migration 001). Deleting a scenario
+//! removes its step images in the same transaction, so it does not depend on
+//! `PRAGMA foreign_keys` being enabled on the connection.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::error::XenotesterError;
+use crate::services::steps::{self, Step};
+
+/// Maximum length of a title generated from the description
+const GENERATED_TITLE_CHARS: usize = 30;
+
+/// Title used when the description has no first line either
+const FALLBACK_TITLE: &str = "シナリオ";
+
+/// Row of the `scenarios` table
+/// Field names match the frontend's `StoredScenario`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct StoredScenario {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub order_index: i64,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Scripted steps as a JSON array (see migration 009)
+    pub steps: Option<String>,
+}
+
+impl StoredScenario {
+    /// Parsed scripted steps (empty when the scenario has none)
+    pub fn parsed_steps(&self) -> Result<Vec<Step>, XenotesterError> {
+        match &self.steps {
+            Some(json) => serde_json::from_str(json).map_err(|e| {
+                XenotesterError::ConfigError(format!(
+                    "Invalid steps in scenario {}: {}",
+                    self.id, e
+                ))
+            }),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+const SELECT_SCENARIO: &str =
+    "SELECT id, title, description, order_index, created_at, updated_at, steps FROM scenarios";
+
+/// Title derived from the first line of the description, like the scenario form does
+fn title_from_description(description: &str) -> String {
+    let first_line = description.lines().next().unwrap_or("").trim();
+    if first_line.is_empty() {
+        FALLBACK_TITLE.to_string()
+    } else if first_line.chars().count() <= GENERATED_TITLE_CHARS {
+        first_line.to_string()
+    } else {
+        let truncated: String = first_line.chars().take(GENERATED_TITLE_CHARS).collect();
+        format!("{}...", truncated)
+    }
+}
+
+/// Validate the input and fill in an empty title
+fn normalize(title: &str, description: &str) -> Result<String, XenotesterError> {
+    if description.trim().is_empty() {
+        return Err(XenotesterError::ConfigError(
+            "Scenario description must not be empty".to_string(),
+        ));
+    }
+    let title = title.trim();
+    Ok(if title.is_empty() {
+        title_from_description(description)
+    } else {
+        title.to_string()
+    })
+}
+
+/// All scenarios in display order
+pub async fn list_scenarios(pool: &SqlitePool) -> Result<Vec<StoredScenario>, XenotesterError> {
+    Ok(
+        sqlx::query_as(&format!("{} ORDER BY order_index ASC", SELECT_SCENARIO))
+            .fetch_all(pool)
+            .await?,
+    )
+}
+
+/// A single scenario, or `None` when it does not exist
+pub async fn get_scenario(
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<Option<StoredScenario>, XenotesterError> {
+    Ok(sqlx::query_as(&format!("{} WHERE id = ?", SELECT_SCENARIO))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+/// A single scenario, failing when it does not exist
+pub async fn require_scenario(
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<StoredScenario, XenotesterError> {
+    get_scenario(pool, id)
+        .await?
+        .ok_or_else(|| XenotesterError::NotFound(format!("scenario {}", id)))
+}
+
+/// Create a scenario at the end of the list
+/// An empty title is generated from the first line of the description.
+pub async fn create_scenario(
+    pool: &SqlitePool,
+    title: &str,
+    description: &str,
+) -> Result<StoredScenario, XenotesterError> {
+    let title = normalize(title, description)?;
+    let id = uuid::Uuid::new_v4().to_string();
+
+    // Read the next position and insert atomically so concurrent creates don't collide
+    let mut tx = pool.begin().await?;
+    let max_order: Option<i64> = sqlx::query_scalar("SELECT MAX(order_index) FROM scenarios")
+        .fetch_one(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO scenarios (id, title, description, order_index) VALUES (?, ?, ?, ?)")
+        .bind(&id)
+        .bind(&title)
+        .bind(description)
+        .bind(max_order.map_or(0, |max| max + 1))
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    require_scenario(pool, &id).await
+}
+
+/// Update the title and description of a scenario
+pub async fn update_scenario(
+    pool: &SqlitePool,
+    id: &str,
+    title: &str,
+    description: &str,
+) -> Result<StoredScenario, XenotesterError> {
+    let title = normalize(title, description)?;
+
+    let result = sqlx::query(
+        "UPDATE scenarios
+         SET title = ?, description = ?, updated_at = datetime('now')
+         WHERE id = ?",
+    )
+    .bind(&title)
+    .bind(description)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(XenotesterError::NotFound(format!("scenario {}", id)));
+    }
+
+    require_scenario(pool, id).await
+}
+
+/// Replace the scripted steps of a scenario (none clears them)
+pub async fn set_scenario_steps(
+    pool: &SqlitePool,
+    id: &str,
+    steps: &[Step],
+) -> Result<StoredScenario, XenotesterError> {
+    steps::validate(steps)?;
+    let json = if steps.is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::to_string(steps)
+                .map_err(|e| XenotesterError::ConfigError(e.to_string()))?,
+        )
+    };
+
+    let result = sqlx::query(
+        "UPDATE scenarios
+         SET steps = ?, updated_at = datetime('now')
+         WHERE id = ?",
+    )
+    .bind(json)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(XenotesterError::NotFound(format!("scenario {}", id)));
+    }
+
+    require_scenario(pool, id).await
+}
+
+/// Delete a scenario together with its step images
+pub async fn delete_scenario(pool: &SqlitePool, id: &str) -> Result<(), XenotesterError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM step_images WHERE scenario_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    let result = sqlx::query("DELETE FROM scenarios WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        // Dropping the transaction rolls it back
+        return Err(XenotesterError::NotFound(format!("scenario {}", id)));
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_from_description() {
+        assert_eq!(
+            title_from_description("  Open settings \nthen close"),
+            "Open settings"
+        );
+        assert_eq!(
+            title_from_description(&"あ".repeat(31)),
+            format!("{}...", "あ".repeat(30))
+        );
+        assert_eq!(title_from_description("\nsecond line"), FALLBACK_TITLE);
+        assert!(normalize("", "   ").is_err());
+        assert_eq!(normalize("  Login ", "Log in").unwrap(), "Login");
+    }
+}
@@ -0,0 +1,147 @@
+//! Database schema version service
+//!
+//! Reports which migrations are applied and reverts them with the Down
+//! entries of `get_migrations`. tauri-plugin-sql only ever runs Up
+//! migrations, so reverting happens here: run it from the current release
+//! before installing an older one, which would otherwise refuse to start on
+//! a database with migrations it does not know.
+
+use serde::Serialize;
+use sqlx::{Executor, Row, SqlitePool};
+use tauri_plugin_sql::MigrationKind;
+use tracing::info;
+
+use crate::error::XenotesterError;
+
+/// Bookkeeping table written by the sqlx migrator
+const MIGRATIONS_TABLE: &str = "_sqlx_migrations";
+
+/// Migration recorded in the database
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub success: bool,
+}
+
+/// Schema state of the application database
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaVersion {
+    /// Highest applied version (0 when nothing is applied)
+    pub version: i64,
+    /// Highest version this build knows
+    pub latest_version: i64,
+    /// A migration failed part-way and needs attention
+    pub dirty: bool,
+    /// Applied versions this build has no migration for (written by a newer release)
+    pub unknown_versions: Vec<i64>,
+    pub applied: Vec<AppliedMigration>,
+}
+
+fn latest_known_version() -> i64 {
+    crate::get_migrations()
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap_or(0)
+}
+
+async fn applied_migrations(pool: &SqlitePool) -> Result<Vec<AppliedMigration>, XenotesterError> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+    )
+    .bind(MIGRATIONS_TABLE)
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+
+    let rows = sqlx::query(&format!(
+        "SELECT version, description, success FROM {} ORDER BY version",
+        MIGRATIONS_TABLE
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(AppliedMigration {
+                version: row.try_get("version")?,
+                description: row.try_get("description")?,
+                success: row.try_get("success")?,
+            })
+        })
+        .collect()
+}
+
+/// Current schema version and applied migrations
+pub async fn get_schema_version(pool: &SqlitePool) -> Result<SchemaVersion, XenotesterError> {
+    let applied = applied_migrations(pool).await?;
+    let latest_version = latest_known_version();
+
+    Ok(SchemaVersion {
+        version: applied.iter().map(|m| m.version).max().unwrap_or(0),
+        latest_version,
+        dirty: applied.iter().any(|m| !m.success),
+        unknown_versions: applied
+            .iter()
+            .map(|m| m.version)
+            .filter(|v| *v > latest_version)
+            .collect(),
+        applied,
+    })
+}
+
+/// Revert every applied migration above `target_version`, newest first
+///
+/// Each version's Down SQL and the removal of its bookkeeping row run in one
+/// transaction. Restarting this build re-applies the reverted migrations.
+pub async fn revert_to(
+    pool: &SqlitePool,
+    target_version: i64,
+) -> Result<SchemaVersion, XenotesterError> {
+    let migrations = crate::get_migrations();
+    let applied = applied_migrations(pool).await?;
+
+    let mut to_revert: Vec<i64> = applied
+        .iter()
+        .map(|m| m.version)
+        .filter(|v| *v > target_version)
+        .collect();
+    to_revert.sort_unstable_by(|a, b| b.cmp(a));
+
+    // Check up front so a missing Down entry doesn't leave a half-reverted schema
+    let mut steps = Vec::with_capacity(to_revert.len());
+    for version in to_revert {
+        let down = migrations
+            .iter()
+            .find(|m| m.version == version && matches!(m.kind, MigrationKind::Down))
+            .ok_or_else(|| {
+                XenotesterError::DatabaseError(format!(
+                    "No down migration for version {}; revert it with the release that added it",
+                    version
+                ))
+            })?;
+        steps.push((version, down.sql));
+    }
+
+    for (version, sql) in steps {
+        let mut tx = pool.begin().await?;
+        // SQLite runs every statement of the script
+        tx.execute(sql).await?;
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE version = ?",
+            MIGRATIONS_TABLE
+        ))
+        .bind(version)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        info!("Reverted schema migration {}", version);
+    }
+
+    get_schema_version(pool).await
+}
@@ -0,0 +1,293 @@
+//! Checks against the live screen
+//!
+//! Template and pixel checks used to confirm that an action had the intended
+//! effect. Positions are absolute screen coordinates as used by the mouse
+//! service; captured frames are in physical pixels and converted accordingly.
+
+use image::{GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::error::XenotesterError;
+use crate::services::baseline::Region;
+use crate::services::capture::{find_monitor_at, grab_frame, Frame};
+use crate::services::image_diff::{compare_images, DEFAULT_DIFF_THRESHOLD};
+use crate::services::template_matcher::{find_template_in_image, MatchOptions};
+use crate::utils::cancel::CancellationToken;
+
+/// Default minimum confidence for template checks
+pub const DEFAULT_CONFIDENCE_THRESHOLD: f32 = 0.8;
+
+/// Default maximum per-channel difference for pixel checks
+pub const DEFAULT_COLOR_TOLERANCE: u8 = 16;
+
+/// Default fraction of pixels allowed to change between frames of an idle screen
+/// (absorbs a blinking caret or spinner-free clock updates)
+pub const DEFAULT_IDLE_THRESHOLD: f64 = 0.001;
+
+/// Interval between captures in `wait_for_screen_idle`
+const IDLE_POLL_INTERVAL_MS: u64 = 100;
+
+/// Template search result on a live capture
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenMatch {
+    pub found: bool,
+    /// Center of the match in screen coordinates
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    /// Best match score (0.0 - 1.0), also reported when below the threshold
+    pub confidence: Option<f32>,
+    pub monitor_id: u32,
+    /// Size of the template on screen, in screen points
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Condition checked after an action
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Verification {
+    /// The template image is visible on the monitor (primary when omitted)
+    Template {
+        image_data: String,
+        monitor_id: Option<u32>,
+        confidence_threshold: Option<f32>,
+    },
+    /// The pixel at the position has the given "#rrggbb" color
+    Pixel {
+        x: i32,
+        y: i32,
+        color: String,
+        tolerance: Option<u8>,
+    },
+}
+
+impl Verification {
+    /// Check the condition against a fresh capture
+    pub fn check(&self) -> Result<bool, XenotesterError> {
+        match self {
+            Verification::Template {
+                image_data,
+                monitor_id,
+                confidence_threshold,
+            } => Ok(find_template_on_screen(
+                image_data,
+                *monitor_id,
+                confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD),
+            )?
+            .found),
+            Verification::Pixel {
+                x,
+                y,
+                color,
+                tolerance,
+            } => Ok(colors_match(
+                pixel_color(*x, *y)?,
+                parse_hex_color(color)?,
+                tolerance.unwrap_or(DEFAULT_COLOR_TOLERANCE),
+            )),
+        }
+    }
+}
+
+/// Convert a frame pixel position to screen coordinates
+pub fn frame_to_screen(frame: &Frame, px: f64, py: f64) -> (i32, i32) {
+    (
+        frame.monitor_x + (px / frame.display_scale_factor).round() as i32,
+        frame.monitor_y + (py / frame.display_scale_factor).round() as i32,
+    )
+}
+
+/// Capture a monitor and look for the template (original-size image data) on it
+pub fn find_template_on_screen(
+    template_base64: &str,
+    monitor_id: Option<u32>,
+    confidence_threshold: f32,
+) -> Result<ScreenMatch, XenotesterError> {
+    let frame = grab_frame(monitor_id, false)?;
+    let result = find_template_in_image(
+        &frame.image,
+        template_base64.into(),
+        confidence_threshold,
+        &MatchOptions::default(),
+    );
+    if let Some(error) = result.error {
+        return Err(XenotesterError::ImageError(error));
+    }
+
+    let position = match (result.center_x, result.center_y) {
+        (Some(cx), Some(cy)) if result.found => Some(frame_to_screen(&frame, cx as f64, cy as f64)),
+        _ => None,
+    };
+
+    Ok(ScreenMatch {
+        found: result.found,
+        x: position.map(|(x, _)| x),
+        y: position.map(|(_, y)| y),
+        confidence: result.confidence,
+        monitor_id: frame.monitor_id,
+        width: (result.template_width as f64 / frame.display_scale_factor).round() as u32,
+        height: (result.template_height as f64 / frame.display_scale_factor).round() as u32,
+    })
+}
+
+/// Outcome of waiting for the screen to settle
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenIdle {
+    /// False when the screen was still changing at the timeout
+    pub idle: bool,
+    pub waited_ms: u64,
+    pub frames: u32,
+}
+
+/// Wait until the screen (or a region of it) has not changed for `stability`
+///
+/// Consecutive captures are diffed; a frame counts as changed when more than
+/// `threshold` of its pixels differ. `region` is in physical pixels of the capture.
+pub fn wait_for_screen_idle(
+    monitor_id: Option<u32>,
+    region: Option<Region>,
+    stability: Duration,
+    timeout: Duration,
+    threshold: f64,
+    cancel: &CancellationToken,
+) -> Result<ScreenIdle, XenotesterError> {
+    let started = Instant::now();
+    let mut previous = capture_region(monitor_id, region)?;
+    let mut stable_since = Instant::now();
+    let mut frames = 1;
+
+    loop {
+        let waited_ms = started.elapsed().as_millis() as u64;
+        if stable_since.elapsed() >= stability {
+            return Ok(ScreenIdle {
+                idle: true,
+                waited_ms,
+                frames,
+            });
+        }
+        let elapsed = started.elapsed();
+        if elapsed >= timeout {
+            return Ok(ScreenIdle {
+                idle: false,
+                waited_ms,
+                frames,
+            });
+        }
+        let poll = Duration::from_millis(IDLE_POLL_INTERVAL_MS);
+        cancel.sleep(poll.min(timeout - elapsed))?;
+
+        let current = capture_region(monitor_id, region)?;
+        frames += 1;
+        let diff = compare_images(&previous, &current, DEFAULT_DIFF_THRESHOLD, false)?;
+        if 1.0 - diff.similarity > threshold {
+            stable_since = Instant::now();
+        }
+        previous = current;
+    }
+}
+
+/// Capture a monitor and crop it to `region` (whole screen if None)
+fn capture_region(
+    monitor_id: Option<u32>,
+    region: Option<Region>,
+) -> Result<RgbaImage, XenotesterError> {
+    let image = grab_frame(monitor_id, false)?.image.into_rgba8();
+    let Some(region) = region else {
+        return Ok(image);
+    };
+    if region.width == 0
+        || region.height == 0
+        || region.x + region.width > image.width()
+        || region.y + region.height > image.height()
+    {
+        return Err(XenotesterError::CaptureError(format!(
+            "Region {:?} is outside the {}x{} screen",
+            region,
+            image.width(),
+            image.height()
+        )));
+    }
+    let crop = image::imageops::crop_imm(&image, region.x, region.y, region.width, region.height);
+    Ok(crop.to_image())
+}
+
+/// Capture the monitor containing a screen position
+/// Returns the frame and the position in frame pixels.
+pub fn frame_at(x: i32, y: i32) -> Result<(Frame, (u32, u32)), XenotesterError> {
+    let monitor = find_monitor_at(x, y)?.ok_or_else(|| {
+        XenotesterError::CaptureError(format!("Position ({}, {}) is outside all monitors", x, y))
+    })?;
+    let frame = grab_frame(Some(monitor.id), false)?;
+    if frame.image.width() == 0 || frame.image.height() == 0 {
+        return Err(XenotesterError::CaptureError(
+            "Captured frame is empty".to_string(),
+        ));
+    }
+
+    let px = ((x - frame.monitor_x) as f64 * frame.display_scale_factor).round() as u32;
+    let py = ((y - frame.monitor_y) as f64 * frame.display_scale_factor).round() as u32;
+    let position = (
+        px.min(frame.image.width() - 1),
+        py.min(frame.image.height() - 1),
+    );
+    Ok((frame, position))
+}
+
+/// Read the color of the pixel at a screen position
+pub fn pixel_color(x: i32, y: i32) -> Result<Rgba<u8>, XenotesterError> {
+    let (frame, (px, py)) = frame_at(x, y)?;
+    Ok(frame.image.get_pixel(px, py))
+}
+
+/// Parse a "#rrggbb" (or "rrggbb") color
+pub fn parse_hex_color(color: &str) -> Result<Rgba<u8>, XenotesterError> {
+    let hex = color.trim().trim_start_matches('#');
+    let invalid = || XenotesterError::ImageError(format!("Invalid color: {}", color));
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    Ok(Rgba([channel(0)?, channel(2)?, channel(4)?, 255]))
+}
+
+/// Format a color as "#rrggbb"
+pub fn to_hex_color(color: Rgba<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Whether every RGB channel differs by at most `tolerance`
+pub fn colors_match(actual: Rgba<u8>, expected: Rgba<u8>, tolerance: u8) -> bool {
+    (0..3).all(|i| actual[i].abs_diff(expected[i]) <= tolerance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(
+            parse_hex_color("#ff8000").unwrap(),
+            Rgba([255, 128, 0, 255])
+        );
+        assert_eq!(parse_hex_color("00A0ff").unwrap(), Rgba([0, 160, 255, 255]));
+        assert!(parse_hex_color("#fff").is_err());
+        assert!(parse_hex_color("#gg0000").is_err());
+        assert_eq!(to_hex_color(Rgba([255, 128, 0, 255])), "#ff8000");
+    }
+
+    #[test]
+    fn test_colors_match_within_tolerance() {
+        let expected = Rgba([100, 100, 100, 255]);
+        assert!(colors_match(Rgba([110, 90, 100, 0]), expected, 10));
+        assert!(!colors_match(Rgba([111, 100, 100, 255]), expected, 10));
+    }
+}
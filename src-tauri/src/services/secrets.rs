@@ -0,0 +1,232 @@
+//! Encrypted secret storage
+//!
+//! Secrets such as passwords typed by scenarios are stored by name in the
+//! `secrets` table (see migration 008), encrypted with AES-256-GCM under the
+//! master key from `keychain`. The name is bound as associated data, so a
+//! ciphertext copied to another name fails to decrypt. Stored and resolved
+//! values are registered for redaction in logs and the action log.
+//!
+//! The app keeps its own credentials (OAuth session, SMTP password,
+//! orchestrator token) under the reserved `internal.` prefix. Public names
+//! may not use it, so neither scenarios nor the secret commands can read,
+//! list or replace them.
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use sqlx::SqlitePool;
+
+use crate::error::XenotesterError;
+use crate::services::keychain::{self, KEY_LEN};
+use crate::utils::redact;
+
+/// Maximum length of a secret name
+const MAX_NAME_LEN: usize = 64;
+/// Prefix of the app's own credentials (see the module documentation)
+const INTERNAL_PREFIX: &str = "internal.";
+
+/// Names are identifiers so they can be referenced from scenario text
+/// The reserved internal namespace is rejected.
+pub fn validate_name(name: &str) -> Result<(), XenotesterError> {
+    if name.starts_with(INTERNAL_PREFIX) {
+        return Err(XenotesterError::SecretError(format!(
+            "Invalid secret name '{}': '{}' is reserved for the app's own credentials",
+            name, INTERNAL_PREFIX
+        )));
+    }
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(XenotesterError::SecretError(format!(
+            "Invalid secret name '{}': use up to {} letters, digits, '_', '-' or '.'",
+            name, MAX_NAME_LEN
+        )))
+    }
+}
+
+fn cipher(key: &[u8; KEY_LEN]) -> Result<LessSafeKey, XenotesterError> {
+    UnboundKey::new(&AES_256_GCM, key)
+        .map(LessSafeKey::new)
+        .map_err(|_| XenotesterError::SecretError("Invalid encryption key".to_string()))
+}
+
+/// Encrypt `value` for `name`, returning Base64 of nonce || ciphertext
+fn encrypt(key: &[u8; KEY_LEN], name: &str, value: &str) -> Result<String, XenotesterError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| XenotesterError::SecretError("Failed to generate nonce".to_string()))?;
+
+    let mut sealed = value.as_bytes().to_vec();
+    cipher(key)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(name.as_bytes()),
+            &mut sealed,
+        )
+        .map_err(|_| XenotesterError::SecretError("Encryption failed".to_string()))?;
+
+    let mut stored = nonce.to_vec();
+    stored.extend_from_slice(&sealed);
+    Ok(BASE64_STANDARD.encode(stored))
+}
+
+/// Decrypt a value produced by `encrypt` for the same name
+fn decrypt(key: &[u8; KEY_LEN], name: &str, stored: &str) -> Result<String, XenotesterError> {
+    let undecryptable =
+        || XenotesterError::SecretError(format!("Secret '{}' cannot be decrypted", name));
+
+    let mut bytes = BASE64_STANDARD
+        .decode(stored)
+        .map_err(|_| undecryptable())?;
+    if bytes.len() < NONCE_LEN {
+        return Err(undecryptable());
+    }
+    let nonce =
+        Nonce::try_assume_unique_for_key(&bytes[..NONCE_LEN]).map_err(|_| undecryptable())?;
+
+    let plaintext = cipher(key)?
+        .open_in_place(nonce, Aad::from(name.as_bytes()), &mut bytes[NONCE_LEN..])
+        .map_err(|_| undecryptable())?;
+    String::from_utf8(plaintext.to_vec()).map_err(|_| undecryptable())
+}
+
+fn internal_name(name: &str) -> String {
+    format!("{}{}", INTERNAL_PREFIX, name)
+}
+
+/// Encrypt and store a secret, replacing any previous value
+pub async fn store_secret(
+    pool: &SqlitePool,
+    name: &str,
+    value: &str,
+) -> Result<(), XenotesterError> {
+    validate_name(name)?;
+    store(pool, name, value).await
+}
+
+async fn store(pool: &SqlitePool, name: &str, value: &str) -> Result<(), XenotesterError> {
+    let stored = encrypt(&keychain::master_key()?, name, value)?;
+
+    sqlx::query(
+        "INSERT INTO secrets (name, value) VALUES (?, ?)
+         ON CONFLICT(name) DO UPDATE
+         SET value = excluded.value, updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now')",
+    )
+    .bind(name)
+    .bind(stored)
+    .execute(pool)
+    .await?;
+
+    redact::register(value);
+    Ok(())
+}
+
+/// Decrypt a stored secret
+pub async fn resolve_secret(pool: &SqlitePool, name: &str) -> Result<String, XenotesterError> {
+    validate_name(name)?;
+    resolve(pool, name)
+        .await?
+        .ok_or_else(|| XenotesterError::NotFound(format!("secret '{}'", name)))
+}
+
+async fn resolve(pool: &SqlitePool, name: &str) -> Result<Option<String>, XenotesterError> {
+    let stored: Option<String> = sqlx::query_scalar("SELECT value FROM secrets WHERE name = ?")
+        .bind(name)
+        .fetch_optional(pool)
+        .await?;
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+
+    let value = decrypt(&keychain::master_key()?, name, &stored)?;
+    redact::register(&value);
+    Ok(Some(value))
+}
+
+/// Names of the stored secrets, without the app's own credentials
+pub async fn list_secret_names(pool: &SqlitePool) -> Result<Vec<String>, XenotesterError> {
+    let names: Vec<String> = sqlx::query_scalar("SELECT name FROM secrets ORDER BY name")
+        .fetch_all(pool)
+        .await?;
+    Ok(names
+        .into_iter()
+        .filter(|name| !name.starts_with(INTERNAL_PREFIX))
+        .collect())
+}
+
+/// Delete a stored secret
+pub async fn delete_secret(pool: &SqlitePool, name: &str) -> Result<(), XenotesterError> {
+    validate_name(name)?;
+    if !delete(pool, name).await? {
+        return Err(XenotesterError::NotFound(format!("secret '{}'", name)));
+    }
+    Ok(())
+}
+
+async fn delete(pool: &SqlitePool, name: &str) -> Result<bool, XenotesterError> {
+    let result = sqlx::query("DELETE FROM secrets WHERE name = ?")
+        .bind(name)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Store one of the app's own credentials, replacing any previous value
+pub async fn store_internal(
+    pool: &SqlitePool,
+    name: &str,
+    value: &str,
+) -> Result<(), XenotesterError> {
+    store(pool, &internal_name(name), value).await
+}
+
+/// One of the app's own credentials, if stored
+pub async fn resolve_internal(
+    pool: &SqlitePool,
+    name: &str,
+) -> Result<Option<String>, XenotesterError> {
+    resolve(pool, &internal_name(name)).await
+}
+
+/// Remove one of the app's own credentials; returns false if there was none
+pub async fn delete_internal(pool: &SqlitePool, name: &str) -> Result<bool, XenotesterError> {
+    delete(pool, &internal_name(name)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip_bound_to_name() {
+        let key = [7u8; KEY_LEN];
+        let stored = encrypt(&key, "admin_password", "p@ss wörd").unwrap();
+        assert_eq!(
+            decrypt(&key, "admin_password", &stored).unwrap(),
+            "p@ss wörd"
+        );
+        assert!(decrypt(&key, "other_name", &stored).is_err());
+        assert!(decrypt(&[8u8; KEY_LEN], "admin_password", &stored).is_err());
+        // Fresh nonce per encryption
+        assert_ne!(
+            encrypt(&key, "admin_password", "p@ss wörd").unwrap(),
+            stored
+        );
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("login.password_1").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("has space").is_err());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+        assert!(validate_name("internal.oauth.session").is_err());
+        assert!(validate_name("oauth.session").is_ok());
+    }
+}
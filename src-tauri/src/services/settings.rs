@@ -0,0 +1,56 @@
+//! Application settings
+//!
+//! Key-value settings in the `settings` table (see migration 003), shared with
+//! the frontend's settings service. Structured settings are stored as JSON.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::error::XenotesterError;
+
+/// Get a setting value
+pub async fn get_setting(pool: &SqlitePool, key: &str) -> Result<Option<String>, XenotesterError> {
+    let value = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+    Ok(value)
+}
+
+/// Set a setting value, replacing any previous one
+pub async fn set_setting(pool: &SqlitePool, key: &str, value: &str) -> Result<(), XenotesterError> {
+    sqlx::query(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, datetime('now'))
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = datetime('now')",
+    )
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Get a setting stored as JSON
+pub async fn get_json<T: DeserializeOwned>(
+    pool: &SqlitePool,
+    key: &str,
+) -> Result<Option<T>, XenotesterError> {
+    match get_setting(pool, key).await? {
+        Some(value) => serde_json::from_str(&value)
+            .map(Some)
+            .map_err(|e| XenotesterError::ConfigError(format!("Invalid setting {}: {}", key, e))),
+        None => Ok(None),
+    }
+}
+
+/// Store a setting as JSON
+pub async fn set_json<T: Serialize>(
+    pool: &SqlitePool,
+    key: &str,
+    value: &T,
+) -> Result<(), XenotesterError> {
+    let value = serde_json::to_string(value)
+        .map_err(|e| XenotesterError::ConfigError(format!("Invalid setting {}: {}", key, e)))?;
+    set_setting(pool, key, &value).await
+}
@@ -0,0 +1,448 @@
+//! Scripted scenario steps
+//!
+//! A scenario can list steps that the runner executes in order instead of
+//! handing the whole description to the agent. Input steps run without a
+//! model round-trip; `agent` steps hand a single instruction to the agent
+//! loop. Any step can be made conditional with `ifTemplateVisible` or
+//! `ifTextPresent` and is skipped when the condition does not hold, and
+//! `goto` / `skip` steps change the order. Recovery paths such as "dismiss
+//! the cookie banner if it appears" need no model call this way. Input steps
+//! with a `retry` policy are attempted again when they fail.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::error::XenotesterError;
+use crate::services::accessibility;
+use crate::services::governor::Governor;
+use crate::services::keyboard::{self, TypingOptions};
+use crate::services::mouse::{self, MouseButton};
+use crate::services::retry::{with_retry, RetryPolicy};
+use crate::services::screen_check::{find_template_on_screen, DEFAULT_CONFIDENCE_THRESHOLD};
+use crate::services::variables::Variables;
+use crate::utils::cancel::CancellationToken;
+
+/// A step executed more often than this is assumed to be in a `goto` loop
+pub const MAX_STEP_VISITS: u32 = 100;
+
+/// Template that must be visible on a monitor (primary when omitted)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateCondition {
+    pub image_data: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence_threshold: Option<f32>,
+}
+
+/// Text that must appear in an element title or value of an app
+/// (the frontmost app when `appId` is omitted)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextCondition {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+}
+
+/// What a step does
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum StepAction {
+    /// Let the agent carry out an instruction; it must report success
+    Agent { instruction: String },
+    /// Click at a screen position
+    Click {
+        x: i32,
+        y: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        button: Option<MouseButton>,
+    },
+    /// Click the center of a template; fails when it is not visible
+    ClickTemplate {
+        image_data: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        monitor_id: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        confidence_threshold: Option<f32>,
+    },
+    /// Type text; placeholders are substituted
+    Type { text: String },
+    /// Press a key combination (e.g. "ctrl+s")
+    Key { keys: String },
+    /// Pause
+    Wait { ms: u64 },
+    /// Continue at the step with this label
+    Goto { target: String },
+    /// Skip the next `count` steps
+    Skip { count: usize },
+    /// Fail the scenario
+    Fail { message: String },
+    /// Run another stored scenario; values may reference this run's variables
+    RunScenario {
+        scenario_id: String,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        variables: HashMap<String, String>,
+    },
+}
+
+/// Scenario step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Step {
+    /// Name that `goto` steps refer to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Run only when the template is visible
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub if_template_visible: Option<TemplateCondition>,
+    /// Run only when the text is present
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub if_text_present: Option<TextCondition>,
+    /// Retries of a failed input step (agent, sub-scenario and flow steps are not retried)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    /// Time limit of the step, retries included; overrides the run's step timeout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(flatten)]
+    pub action: StepAction,
+}
+
+/// What the execution of a step observed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepMetrics {
+    /// 1 plus the retries the step needed
+    pub attempts: u32,
+    /// Best template match confidence of the last attempt (image steps only)
+    pub confidence: Option<f32>,
+}
+
+impl Default for StepMetrics {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            confidence: None,
+        }
+    }
+}
+
+impl Step {
+    /// Whether the step has a condition to check before it runs
+    pub fn is_conditional(&self) -> bool {
+        self.if_template_visible.is_some() || self.if_text_present.is_some()
+    }
+
+    /// Short description for results and logs
+    /// Typed text is left out, it may hold secrets.
+    pub fn describe(&self) -> String {
+        let action = match &self.action {
+            StepAction::Agent { instruction } => {
+                let first_line = instruction.lines().next().unwrap_or("").trim();
+                format!("Agent: {}", first_line)
+            }
+            StepAction::Click { x, y, .. } => format!("Click ({}, {})", x, y),
+            StepAction::ClickTemplate { .. } => "Click template".to_string(),
+            StepAction::Type { .. } => "Type text".to_string(),
+            StepAction::Key { keys } => format!("Press {}", keys),
+            StepAction::Wait { ms } => format!("Wait {} ms", ms),
+            StepAction::Goto { target } => format!("Go to {}", target),
+            StepAction::Skip { count } => format!("Skip {} steps", count),
+            StepAction::Fail { message } => format!("Fail: {}", message),
+            StepAction::RunScenario { scenario_id, .. } => {
+                format!("Run scenario {}", scenario_id)
+            }
+        };
+        match &self.label {
+            Some(label) => format!("[{}] {}", label, action),
+            None => action,
+        }
+    }
+
+    /// Action name, position and keys of an input step, for `ActionGuard::check`
+    /// Template clicks have no position until the template is found.
+    pub fn guarded_action(&self) -> Option<(&'static str, Option<(i32, i32)>, Option<&str>)> {
+        match &self.action {
+            StepAction::Click { x, y, button } => {
+                let action = match button.unwrap_or(MouseButton::Left) {
+                    MouseButton::Left => "left_click",
+                    MouseButton::Right => "right_click",
+                    MouseButton::Middle => "middle_click",
+                    MouseButton::Back | MouseButton::Forward => "click_button",
+                };
+                Some((action, Some((*x, *y)), None))
+            }
+            StepAction::ClickTemplate { .. } => Some(("left_click", None, None)),
+            StepAction::Type { .. } => Some(("type_text", None, None)),
+            StepAction::Key { keys } => Some(("key", None, Some(keys.as_str()))),
+            _ => None,
+        }
+    }
+
+    /// Texts that may reference secrets, for `Variables::load_secrets`
+    pub fn texts(&self) -> Vec<&str> {
+        let mut texts = Vec::new();
+        match &self.action {
+            StepAction::Agent { instruction } => texts.push(instruction.as_str()),
+            StepAction::Type { text } => texts.push(text.as_str()),
+            StepAction::RunScenario { variables, .. } => {
+                texts.extend(variables.values().map(String::as_str))
+            }
+            _ => {}
+        }
+        if let Some(condition) = &self.if_text_present {
+            texts.push(condition.text.as_str());
+        }
+        texts
+    }
+}
+
+/// Check the steps and return the index of each label
+pub fn validate(steps: &[Step]) -> Result<HashMap<String, usize>, XenotesterError> {
+    let invalid = |index: usize, message: String| {
+        XenotesterError::ConfigError(format!("Step {}: {}", index + 1, message))
+    };
+
+    let mut labels = HashMap::new();
+    for (index, step) in steps.iter().enumerate() {
+        if let Some(label) = &step.label {
+            if labels.insert(label.clone(), index).is_some() {
+                return Err(invalid(index, format!("duplicate label '{}'", label)));
+            }
+        }
+    }
+
+    for (index, step) in steps.iter().enumerate() {
+        match &step.action {
+            StepAction::Goto { target } if !labels.contains_key(target) => {
+                return Err(invalid(index, format!("unknown label '{}'", target)));
+            }
+            StepAction::Agent { instruction } if instruction.trim().is_empty() => {
+                return Err(invalid(index, "empty agent instruction".to_string()));
+            }
+            StepAction::RunScenario { scenario_id, .. } if scenario_id.trim().is_empty() => {
+                return Err(invalid(index, "empty scenario ID".to_string()));
+            }
+            _ => {}
+        }
+    }
+    Ok(labels)
+}
+
+/// Index of the step that follows `index`
+/// Past the last step means the scenario is finished.
+pub fn next_index(
+    steps: &[Step],
+    index: usize,
+    labels: &HashMap<String, usize>,
+) -> Result<usize, XenotesterError> {
+    match &steps[index].action {
+        StepAction::Goto { target } => labels.get(target).copied().ok_or_else(|| {
+            XenotesterError::ConfigError(format!("Unknown step label '{}'", target))
+        }),
+        StepAction::Skip { count } => Ok((index + 1).saturating_add(*count).min(steps.len())),
+        _ => Ok(index + 1),
+    }
+}
+
+/// Check the step's conditions against the screen (blocking)
+/// Steps without conditions always run.
+pub fn conditions_hold(step: &Step, variables: &Variables) -> Result<bool, XenotesterError> {
+    if let Some(condition) = &step.if_template_visible {
+        let found = find_template_on_screen(
+            &condition.image_data,
+            condition.monitor_id,
+            condition
+                .confidence_threshold
+                .unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD),
+        )?
+        .found;
+        if !found {
+            return Ok(false);
+        }
+    }
+    if let Some(condition) = &step.if_text_present {
+        let text = variables.substitute(&condition.text)?;
+        if !accessibility::contains_text(&text, condition.app_id.as_deref())? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Perform an input or wait step with its retry policy (blocking)
+/// The metrics are reported whether the step succeeded or not.
+pub fn perform_with_retry(
+    step: &Step,
+    variables: &Variables,
+    governor: Option<&Governor>,
+    cancel: &CancellationToken,
+) -> (Result<(), XenotesterError>, StepMetrics) {
+    let mut metrics = StepMetrics::default();
+    let result = match &step.retry {
+        Some(policy) => with_retry(policy, cancel, |attempt| {
+            metrics.attempts = attempt;
+            perform(
+                &step.action,
+                variables,
+                governor,
+                cancel,
+                &mut metrics.confidence,
+            )
+        })
+        .map(drop),
+        None => perform(
+            &step.action,
+            variables,
+            governor,
+            cancel,
+            &mut metrics.confidence,
+        ),
+    };
+    (result, metrics)
+}
+
+/// Perform an input or wait step (blocking)
+/// Agent, sub-scenario and flow steps are handled by the runner and do nothing here.
+/// Template steps store the best match confidence in `confidence`, also when they fail,
+/// and click only where `governor` allows.
+pub fn perform(
+    action: &StepAction,
+    variables: &Variables,
+    governor: Option<&Governor>,
+    cancel: &CancellationToken,
+    confidence: &mut Option<f32>,
+) -> Result<(), XenotesterError> {
+    cancel.check()?;
+
+    match action {
+        StepAction::Click { x, y, button } => {
+            mouse::click(*x, *y, button.unwrap_or(MouseButton::Left), cancel)
+        }
+        StepAction::ClickTemplate {
+            image_data,
+            monitor_id,
+            confidence_threshold,
+        } => {
+            let found = find_template_on_screen(
+                image_data,
+                *monitor_id,
+                confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD),
+            )?;
+            *confidence = found.confidence;
+            match (found.found, found.x, found.y) {
+                (true, Some(x), Some(y)) => {
+                    if let Some(governor) = governor {
+                        governor.check_position(x, y)?;
+                    }
+                    mouse::click(x, y, MouseButton::Left, cancel)
+                }
+                _ => Err(XenotesterError::ImageError(format!(
+                    "Template is not visible (best confidence {:.2})",
+                    found.confidence.unwrap_or(0.0)
+                ))),
+            }
+        }
+        StepAction::Type { text } => keyboard::type_text(
+            &variables.substitute(text)?,
+            &TypingOptions::default(),
+            cancel,
+        ),
+        StepAction::Key { keys } => keyboard::key_combination(keys, None, cancel),
+        StepAction::Wait { ms } => cancel.sleep(Duration::from_millis(*ms)),
+        StepAction::Agent { .. }
+        | StepAction::Goto { .. }
+        | StepAction::Skip { .. }
+        | StepAction::Fail { .. }
+        | StepAction::RunScenario { .. } => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Vec<Step> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_parse_conditional_steps() {
+        let steps = parse(
+            r#"[
+                {"type": "clickTemplate", "imageData": "abc", "ifTemplateVisible": {"imageData": "banner"}},
+                {"label": "login", "type": "type", "text": "${USER}"},
+                {"type": "goto", "target": "login", "ifTextPresent": {"text": "Invalid password"}}
+            ]"#,
+        );
+        assert!(steps[0].is_conditional());
+        assert!(matches!(steps[0].action, StepAction::ClickTemplate { .. }));
+        assert_eq!(steps[1].label.as_deref(), Some("login"));
+        assert!(steps[1].retry.is_none());
+        assert_eq!(steps[1].describe(), "[login] Type text");
+        assert_eq!(
+            steps[2].if_text_present.as_ref().unwrap().text,
+            "Invalid password"
+        );
+    }
+
+    #[test]
+    fn test_validate_and_next_index() {
+        let steps = parse(
+            r#"[
+                {"label": "start", "type": "wait", "ms": 10},
+                {"type": "skip", "count": 1},
+                {"type": "key", "keys": "esc"},
+                {"type": "goto", "target": "start"},
+                {"type": "runScenario", "scenarioId": "login", "variables": {"USER": "${ADMIN}"}},
+                {"type": "skip", "count": 5}
+            ]"#,
+        );
+        let labels = validate(&steps).unwrap();
+        assert_eq!(next_index(&steps, 0, &labels).unwrap(), 1);
+        assert_eq!(next_index(&steps, 1, &labels).unwrap(), 3);
+        assert_eq!(next_index(&steps, 3, &labels).unwrap(), 0);
+        assert_eq!(next_index(&steps, 4, &labels).unwrap(), 5);
+        assert_eq!(next_index(&steps, 5, &labels).unwrap(), 6);
+        assert_eq!(steps[4].describe(), "Run scenario login");
+
+        assert!(validate(&parse(r#"[{"type": "goto", "target": "nowhere"}]"#)).is_err());
+        assert!(validate(&parse(
+            r#"[{"label": "a", "type": "wait", "ms": 1}, {"label": "a", "type": "wait", "ms": 1}]"#
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_guarded_actions() {
+        let steps = parse(
+            r#"[
+                {"type": "click", "x": 10, "y": 20},
+                {"type": "click", "x": 1, "y": 2, "button": "back"},
+                {"type": "clickTemplate", "imageData": "abc"},
+                {"type": "key", "keys": "ctrl+s"},
+                {"type": "wait", "ms": 10}
+            ]"#,
+        );
+        assert_eq!(
+            steps[0].guarded_action(),
+            Some(("left_click", Some((10, 20)), None))
+        );
+        assert_eq!(
+            steps[1].guarded_action(),
+            Some(("click_button", Some((1, 2)), None))
+        );
+        assert_eq!(steps[2].guarded_action(), Some(("left_click", None, None)));
+        assert_eq!(
+            steps[3].guarded_action(),
+            Some(("key", None, Some("ctrl+s")))
+        );
+        assert_eq!(steps[4].guarded_action(), None);
+    }
+}
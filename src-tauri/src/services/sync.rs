@@ -0,0 +1,585 @@
+//! Supabase results sync
+//!
+//! Uploads scenarios, finished runs (with their step results) and the
+//! screenshots of failed runs to the Supabase project of `get_supabase_config`.
+//! Each sync first queues what changed since the previous one in the
+//! `sync_queue` table (see migration 010), then works through the queue. A
+//! connection failure ends the sync and leaves the rest queued, so work done
+//! offline is uploaded by the next sync.
+//!
+//! Rows are upserted by ID through PostgREST into the `scenarios` and `runs`
+//! tables; screenshots go to the `failure-screenshots` storage bucket as
+//! `<run ID>/<file name>`. Runs are append-only, but scenarios can be edited on
+//! several machines: the newer `updated_at` wins, and a remote scenario that
+//! is newer than the local one is kept and reported as a conflict.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use reqwest::{Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::error::XenotesterError;
+use crate::services::artifacts;
+use crate::services::http;
+use crate::services::report;
+use crate::services::scenario_store;
+use crate::services::settings;
+
+/// Settings key of the sync progress
+const STATE_KEY: &str = "sync";
+/// Storage bucket for the screenshots of failed runs
+const SCREENSHOT_BUCKET: &str = "failure-screenshots";
+/// Timeout of a single request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Kind of a queued upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntityType {
+    Scenario,
+    Run,
+    /// Screenshot of a failed run, identified as `<run ID>/<file name>`
+    Artifact,
+}
+
+impl EntityType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            EntityType::Scenario => "scenario",
+            EntityType::Run => "run",
+            EntityType::Artifact => "artifact",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "scenario" => Some(EntityType::Scenario),
+            "run" => Some(EntityType::Run),
+            "artifact" => Some(EntityType::Artifact),
+            _ => None,
+        }
+    }
+}
+
+/// Progress stored between syncs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SyncProgress {
+    /// Newest scenario `updated_at` already queued
+    scenarios_queued_until: Option<String>,
+    /// Newest run `finished_at` already queued
+    runs_queued_until: Option<String>,
+    last_sync_at: Option<String>,
+    last_error: Option<String>,
+}
+
+/// State reported by `get_sync_status`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    /// A sync is in progress
+    pub syncing: bool,
+    /// Uploads waiting in the queue
+    pub pending: u32,
+    /// Queued uploads that have failed at least once
+    pub failing: u32,
+    /// When the last sync finished (RFC 3339)
+    pub last_sync_at: Option<String>,
+    /// Error that ended the last sync
+    pub last_error: Option<String>,
+}
+
+/// Outcome of one sync
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub uploaded: u32,
+    /// Scenarios kept as they are because the remote copy is newer
+    pub conflicts: u32,
+    /// Uploads that failed and stay queued
+    pub failed: u32,
+    /// Uploads still queued afterwards
+    pub pending: u32,
+    /// The sync stopped early because Supabase could not be reached
+    pub offline: bool,
+}
+
+/// Ensures only one sync runs at a time
+#[derive(Debug, Default)]
+pub struct SyncLock(AtomicBool);
+
+/// Held while a sync runs
+pub struct SyncGuard<'a>(&'a SyncLock);
+
+impl SyncLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the lock, or None when a sync is already running
+    pub fn try_acquire(&self) -> Option<SyncGuard<'_>> {
+        self.0
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| SyncGuard(self))
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for SyncGuard<'_> {
+    fn drop(&mut self) {
+        (self.0).0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Why an upload failed
+enum UploadError {
+    /// Supabase could not be reached; the sync stops
+    Offline(String),
+    /// The upload was rejected; the sync continues with the next item
+    Rejected(String),
+}
+
+impl From<XenotesterError> for UploadError {
+    fn from(err: XenotesterError) -> Self {
+        UploadError::Rejected(err.to_string())
+    }
+}
+
+/// Outcome of a successful upload
+enum Uploaded {
+    Done,
+    /// The remote copy is newer and was kept
+    Conflict,
+}
+
+/// PostgREST and storage client for a Supabase project
+pub struct SupabaseClient {
+    http: reqwest::Client,
+    /// Project URL without a trailing slash
+    url: String,
+    anon_key: String,
+    /// Signed-in user's token; requests run as anonymous without one
+    access_token: Option<String>,
+}
+
+impl SupabaseClient {
+    pub fn new(
+        url: &str,
+        anon_key: &str,
+        access_token: Option<String>,
+    ) -> Result<Self, XenotesterError> {
+        Ok(Self {
+            http: http::client()?,
+            url: url.trim_end_matches('/').to_string(),
+            anon_key: anon_key.to_string(),
+            access_token,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let bearer = self.access_token.as_deref().unwrap_or(&self.anon_key);
+        self.http
+            .request(method, format!("{}/{}", self.url, path))
+            .timeout(REQUEST_TIMEOUT)
+            .header("apikey", &self.anon_key)
+            .bearer_auth(bearer)
+    }
+
+    async fn send(request: RequestBuilder) -> Result<reqwest::Response, UploadError> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| UploadError::Offline(format!("Supabase is unreachable: {}", e)))?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(response)
+        } else {
+            let body = response.text().await.unwrap_or_default();
+            Err(UploadError::Rejected(format!(
+                "Supabase responded with {}: {}",
+                status,
+                body.trim()
+            )))
+        }
+    }
+
+    /// `updated_at` of the remote row, if it exists
+    async fn remote_updated_at(
+        &self,
+        table: &str,
+        id: &str,
+    ) -> Result<Option<String>, UploadError> {
+        let request = self
+            .request(Method::GET, &format!("rest/v1/{}", table))
+            .query(&[
+                ("id", format!("eq.{}", id)),
+                ("select", "updated_at".to_string()),
+            ]);
+        let rows: Vec<Value> = Self::send(request)
+            .await?
+            .json()
+            .await
+            .map_err(|e| UploadError::Rejected(format!("Invalid Supabase response: {}", e)))?;
+        Ok(rows
+            .first()
+            .and_then(|row| row.get("updated_at"))
+            .and_then(Value::as_str)
+            .map(str::to_string))
+    }
+
+    /// Insert or replace a row by ID
+    async fn upsert(&self, table: &str, row: &Value) -> Result<(), UploadError> {
+        let request = self
+            .request(Method::POST, &format!("rest/v1/{}", table))
+            .query(&[("on_conflict", "id")])
+            .header("Prefer", "resolution=merge-duplicates,return=minimal")
+            .json(row);
+        Self::send(request).await.map(|_| ())
+    }
+
+    /// Upload a file to storage, replacing any previous one
+    async fn upload_object(
+        &self,
+        bucket: &str,
+        path: &str,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), UploadError> {
+        let request = self
+            .request(
+                Method::POST,
+                &format!("storage/v1/object/{}/{}", bucket, path),
+            )
+            .header("x-upsert", "true")
+            .header("Content-Type", content_type)
+            .body(data);
+        Self::send(request).await.map(|_| ())
+    }
+}
+
+/// Parse a SQLite (`YYYY-MM-DD HH:MM:SS[.fff]`, UTC) or RFC 3339 timestamp
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+                .ok()
+                .map(|t| t.and_utc())
+        })
+}
+
+/// Whether a remote `updated_at` is newer than the local one
+fn remote_is_newer(remote: &str, local: &str) -> bool {
+    match (parse_timestamp(remote), parse_timestamp(local)) {
+        (Some(remote), Some(local)) => remote > local,
+        _ => false,
+    }
+}
+
+fn is_screenshot(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".png") || name.ends_with(".jpg") || name.ends_with(".jpeg")
+}
+
+async fn load_progress(pool: &SqlitePool) -> Result<SyncProgress, XenotesterError> {
+    Ok(settings::get_json(pool, STATE_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+async fn enqueue(
+    pool: &SqlitePool,
+    entity_type: EntityType,
+    entity_id: &str,
+) -> Result<(), XenotesterError> {
+    sqlx::query("INSERT OR IGNORE INTO sync_queue (entity_type, entity_id) VALUES (?, ?)")
+        .bind(entity_type.as_str())
+        .bind(entity_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Queue everything changed since the previous sync
+async fn enqueue_changes(
+    pool: &SqlitePool,
+    artifacts_dir: Option<&Path>,
+    progress: &mut SyncProgress,
+) -> Result<(), XenotesterError> {
+    let scenarios: Vec<(String, String)> = sqlx::query_as(
+        "SELECT id, updated_at FROM scenarios WHERE updated_at > ? ORDER BY updated_at",
+    )
+    .bind(progress.scenarios_queued_until.as_deref().unwrap_or(""))
+    .fetch_all(pool)
+    .await?;
+    for (id, updated_at) in scenarios {
+        enqueue(pool, EntityType::Scenario, &id).await?;
+        progress.scenarios_queued_until = Some(updated_at);
+    }
+
+    let runs: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT id, status, finished_at FROM runs
+         WHERE finished_at IS NOT NULL AND finished_at > ? ORDER BY finished_at",
+    )
+    .bind(progress.runs_queued_until.as_deref().unwrap_or(""))
+    .fetch_all(pool)
+    .await?;
+    for (id, status, finished_at) in runs {
+        enqueue(pool, EntityType::Run, &id).await?;
+        if status == "failed" {
+            if let Some(dir) = artifacts_dir {
+                let (dir, run_id) = (dir.to_path_buf(), id.clone());
+                let listed = tokio::task::spawn_blocking(move || {
+                    artifacts::list_artifacts(&dir, Some(&run_id))
+                })
+                .await
+                .map_err(|e| XenotesterError::DatabaseError(format!("Listing failed: {}", e)))??;
+                for artifact in listed.iter().filter(|a| is_screenshot(&a.name)) {
+                    enqueue(
+                        pool,
+                        EntityType::Artifact,
+                        &format!("{}/{}", id, artifact.name),
+                    )
+                    .await?;
+                }
+            }
+        }
+        progress.runs_queued_until = Some(finished_at);
+    }
+    Ok(())
+}
+
+async fn upload_scenario(
+    client: &SupabaseClient,
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<Uploaded, UploadError> {
+    // Deleted since it was queued: nothing to upload
+    let Some(scenario) = scenario_store::get_scenario(pool, id).await? else {
+        return Ok(Uploaded::Done);
+    };
+    if let Some(remote) = client.remote_updated_at("scenarios", id).await? {
+        if remote_is_newer(&remote, &scenario.updated_at) {
+            return Ok(Uploaded::Conflict);
+        }
+    }
+
+    let steps = scenario.parsed_steps()?;
+    let row = json!({
+        "id": scenario.id,
+        "title": scenario.title,
+        "description": scenario.description,
+        "order_index": scenario.order_index,
+        "steps": if steps.is_empty() { Value::Null } else { json!(steps) },
+        "created_at": parse_timestamp(&scenario.created_at).map(|t| t.to_rfc3339()),
+        "updated_at": parse_timestamp(&scenario.updated_at).map(|t| t.to_rfc3339()),
+    });
+    client.upsert("scenarios", &row).await?;
+    Ok(Uploaded::Done)
+}
+
+async fn upload_run(
+    client: &SupabaseClient,
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<Uploaded, UploadError> {
+    let report = report::load_run_report(pool, id).await?;
+    let run = &report.run;
+    let steps: Vec<Value> = report
+        .steps
+        .iter()
+        .map(|step| {
+            json!({
+                "stepIndex": step.step_index,
+                "description": step.description,
+                "status": step.status,
+                "errorMessage": step.error_message,
+                "durationMs": step.duration_ms,
+            })
+        })
+        .collect();
+    let row = json!({
+        "id": run.id,
+        "scenario_id": run.scenario_id,
+        "scenario_title": run.scenario_title,
+        "status": run.status,
+        "error_message": run.error_message,
+        "started_at": parse_timestamp(&run.started_at).map(|t| t.to_rfc3339()),
+        "finished_at": run.finished_at.as_deref().and_then(parse_timestamp).map(|t| t.to_rfc3339()),
+        "duration_ms": run.duration_ms,
+        "steps": steps,
+    });
+    client.upsert("runs", &row).await?;
+    Ok(Uploaded::Done)
+}
+
+async fn upload_artifact(
+    client: &SupabaseClient,
+    artifacts_dir: Option<&Path>,
+    id: &str,
+) -> Result<Uploaded, UploadError> {
+    let (Some(dir), Some((run_id, name))) = (artifacts_dir, id.split_once('/')) else {
+        return Err(UploadError::Rejected(format!(
+            "Cannot locate artifact {}",
+            id
+        )));
+    };
+    let path = dir.join(run_id).join(name);
+    // Removed by the cleanup policy since it was queued
+    if !path.exists() {
+        return Ok(Uploaded::Done);
+    }
+    let data = tokio::fs::read(&path)
+        .await
+        .map_err(|e| UploadError::Rejected(format!("Failed to read {}: {}", path.display(), e)))?;
+    let content_type = if name.to_ascii_lowercase().ends_with(".png") {
+        "image/png"
+    } else {
+        "image/jpeg"
+    };
+    client
+        .upload_object(SCREENSHOT_BUCKET, id, data, content_type)
+        .await?;
+    Ok(Uploaded::Done)
+}
+
+async fn count_queue(pool: &SqlitePool, failing_only: bool) -> Result<u32, XenotesterError> {
+    let sql = if failing_only {
+        "SELECT COUNT(*) FROM sync_queue WHERE attempts > 0"
+    } else {
+        "SELECT COUNT(*) FROM sync_queue"
+    };
+    let count: i64 = sqlx::query_scalar(sql).fetch_one(pool).await?;
+    Ok(count as u32)
+}
+
+/// Queue what changed and upload everything queued
+pub async fn sync_now(
+    pool: &SqlitePool,
+    client: &SupabaseClient,
+    artifacts_dir: Option<PathBuf>,
+) -> Result<SyncReport, XenotesterError> {
+    let artifacts_dir = artifacts_dir.as_deref();
+    let mut progress = load_progress(pool).await?;
+    enqueue_changes(pool, artifacts_dir, &mut progress).await?;
+
+    let queue: Vec<(String, String)> = sqlx::query_as(
+        "SELECT entity_type, entity_id FROM sync_queue ORDER BY queued_at, entity_type, entity_id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut report = SyncReport::default();
+    let mut last_error = None;
+    for (entity_type, entity_id) in queue {
+        let outcome = match EntityType::parse(&entity_type) {
+            Some(EntityType::Scenario) => upload_scenario(client, pool, &entity_id).await,
+            Some(EntityType::Run) => upload_run(client, pool, &entity_id).await,
+            Some(EntityType::Artifact) => upload_artifact(client, artifacts_dir, &entity_id).await,
+            None => Err(UploadError::Rejected(format!(
+                "Unknown entity type {}",
+                entity_type
+            ))),
+        };
+
+        let error = match outcome {
+            Ok(uploaded) => {
+                match uploaded {
+                    Uploaded::Done => report.uploaded += 1,
+                    Uploaded::Conflict => report.conflicts += 1,
+                }
+                sqlx::query("DELETE FROM sync_queue WHERE entity_type = ? AND entity_id = ?")
+                    .bind(&entity_type)
+                    .bind(&entity_id)
+                    .execute(pool)
+                    .await?;
+                continue;
+            }
+            Err(UploadError::Offline(e)) => {
+                report.offline = true;
+                e
+            }
+            Err(UploadError::Rejected(e)) => e,
+        };
+
+        warn!("Failed to sync {} {}: {}", entity_type, entity_id, error);
+        report.failed += 1;
+        sqlx::query(
+            "UPDATE sync_queue SET attempts = attempts + 1, last_error = ?
+             WHERE entity_type = ? AND entity_id = ?",
+        )
+        .bind(&error)
+        .bind(&entity_type)
+        .bind(&entity_id)
+        .execute(pool)
+        .await?;
+        last_error = Some(error);
+        if report.offline {
+            break;
+        }
+    }
+
+    report.pending = count_queue(pool, false).await?;
+    progress.last_sync_at = Some(Utc::now().to_rfc3339());
+    progress.last_error = last_error;
+    settings::set_json(pool, STATE_KEY, &progress).await?;
+    info!(
+        "Sync finished: {} uploaded, {} conflicts, {} pending",
+        report.uploaded, report.conflicts, report.pending
+    );
+    Ok(report)
+}
+
+/// Queue size and outcome of the last sync
+pub async fn get_sync_status(
+    pool: &SqlitePool,
+    syncing: bool,
+) -> Result<SyncStatus, XenotesterError> {
+    let progress = load_progress(pool).await?;
+    Ok(SyncStatus {
+        syncing,
+        pending: count_queue(pool, false).await?,
+        failing: count_queue(pool, true).await?,
+        last_sync_at: progress.last_sync_at,
+        last_error: progress.last_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_is_newer_across_timestamp_formats() {
+        assert!(remote_is_newer(
+            "2026-03-01T10:00:01+00:00",
+            "2026-03-01 10:00:00"
+        ));
+        assert!(!remote_is_newer(
+            "2026-03-01T19:00:00+09:00",
+            "2026-03-01 10:00:00.500"
+        ));
+        // Unparseable timestamps never block the upload
+        assert!(!remote_is_newer("yesterday", "2026-03-01 10:00:00"));
+    }
+
+    #[test]
+    fn test_sync_lock_is_exclusive() {
+        let lock = SyncLock::new();
+        let guard = lock.try_acquire();
+        assert!(guard.is_some());
+        assert!(lock.is_held());
+        assert!(lock.try_acquire().is_none());
+        drop(guard);
+        assert!(lock.try_acquire().is_some());
+    }
+}
@@ -2,14 +2,41 @@
 //!
 //! This module provides functionality to detect hint images within screenshots
 //! using template matching (Normalized Cross-Correlation algorithm).
+//!
+//! Transparent template pixels, or the black pixels of an explicit mask image,
+//! are excluded from the correlation, so the template matches whatever
+//! background it is shown on. In color mode the correlation runs over the RGB
+//! channels, so icons that differ only in color are told apart. In edge mode
+//! it runs over Sobel gradient magnitudes, which stay the same when a light
+//! theme turns dark. Larger templates are searched coarse-to-fine: at half
+//! the matching scale first, then in small windows around the best positions.
+//!
+//! The `features` strategy locates the template by ORB keypoints (oriented FAST
+//! corners with steered BRIEF descriptors) and a RANSAC homography instead,
+//! which copes with scaled, rotated or partly covered templates. `auto` tries
+//! NCC first and falls back to features.
 
 use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
-use image::{DynamicImage, GenericImageView, GrayImage, Rgba, RgbaImage};
-use imageproc::template_matching::{find_extremes, match_template, MatchTemplateMethod};
+use image::{
+    DynamicImage, GenericImageView, GrayImage, ImageBuffer, Luma, Pixel, Rgb, RgbImage, Rgba,
+    RgbaImage,
+};
+use imageproc::corners::oriented_fast;
+use imageproc::filter::box_filter;
+use imageproc::geometric_transformations::Projection;
+use imageproc::gradients::sobel_gradients;
+use imageproc::template_matching::{
+    find_extremes, match_template, match_template_with_mask, MatchTemplateMethod,
+};
 use rayon::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 
 use crate::error::XenotesterError;
+use crate::services::ncc_simd;
 
 /// Error codes for template matching failures
 ///
@@ -30,6 +57,8 @@ pub enum MatchErrorCode {
     NonFiniteConfidence,
     /// Template is larger than screenshot (may resolve when screen changes)
     TemplateTooLarge,
+    /// Mask could not be decoded or does not match the template size (permanent)
+    MaskDecodeError,
 }
 
 impl MatchErrorCode {
@@ -42,6 +71,7 @@ impl MatchErrorCode {
             MatchErrorCode::InsufficientOpacity => true,
             MatchErrorCode::NonFiniteConfidence => true,
             MatchErrorCode::TemplateTooLarge => false, // May resolve when screen changes
+            MatchErrorCode::MaskDecodeError => true,
         }
     }
 
@@ -51,6 +81,189 @@ impl MatchErrorCode {
     }
 }
 
+/// Template image with an optional mask, both Base64 encoded at original size
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateSource<'a> {
+    pub image: &'a str,
+    /// Grayscale weights of the template pixels; black pixels are ignored.
+    /// When omitted, transparent template pixels are ignored.
+    pub mask: Option<&'a str>,
+}
+
+impl<'a> From<&'a str> for TemplateSource<'a> {
+    fn from(image: &'a str) -> Self {
+        Self { image, mask: None }
+    }
+}
+
+/// Pixel values compared by the correlation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Luminance only (fastest)
+    #[default]
+    Grayscale,
+    /// All three color channels
+    Color,
+    /// Edge strength of the luminance (robust to light/dark theme changes)
+    Edges,
+}
+
+/// Options that apply to every template of a call
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MatchOptions {
+    /// Composite transparent template pixels onto white instead of ignoring them
+    pub composite_alpha: bool,
+    pub match_mode: MatchMode,
+    pub match_strategy: MatchStrategy,
+    /// Physical pixels per logical point of the captured monitor (default 1.0),
+    /// used for `originalCenterX/Y`
+    pub display_scale_factor: Option<f64>,
+    /// Refine the NCC peak to sub-pixel precision by quadratic interpolation
+    pub subpixel: bool,
+    pub backend: MatchBackend,
+    /// Report `targetX/Y` for found templates, relative to the matched rectangle
+    pub target: Option<MatchTarget>,
+}
+
+/// Point of the matched rectangle a target is relative to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Anchor {
+    #[default]
+    Center,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Point relative to a match, e.g. "20px right of this label"
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MatchTarget {
+    pub anchor: Anchor,
+    pub offset_x: i32,
+    pub offset_y: i32,
+}
+
+impl MatchTarget {
+    /// Target of a match with the given center and size (offsets in the same units)
+    pub fn resolve(
+        &self,
+        (center_x, center_y): (i32, i32),
+        (width, height): (u32, u32),
+    ) -> (i32, i32) {
+        let (half_width, half_height) = (width as i32 / 2, height as i32 / 2);
+        let (x, y) = match self.anchor {
+            Anchor::Center => (center_x, center_y),
+            Anchor::TopLeft => (center_x - half_width, center_y - half_height),
+            Anchor::TopRight => (center_x + half_width, center_y - half_height),
+            Anchor::BottomLeft => (center_x - half_width, center_y + half_height),
+            Anchor::BottomRight => (center_x + half_width, center_y + half_height),
+        };
+        (x + self.offset_x, y + self.offset_y)
+    }
+}
+
+/// Implementation of the grayscale NCC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchBackend {
+    /// Vectorized where the CPU supports it, otherwise scalar
+    #[default]
+    Auto,
+    /// imageproc's scalar implementation
+    Cpu,
+    /// Vectorized even where `wide` has to emulate the lanes
+    Simd,
+}
+
+impl MatchBackend {
+    fn use_simd(self) -> bool {
+        match self {
+            MatchBackend::Auto => ncc_simd::is_available(),
+            MatchBackend::Cpu => false,
+            MatchBackend::Simd => true,
+        }
+    }
+}
+
+/// How the template is located
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchStrategy {
+    /// Normalized cross-correlation at the template's size and orientation
+    #[default]
+    Ncc,
+    /// ORB keypoints and a homography; confidence is the inlier ratio
+    Features,
+    /// NCC, falling back to features when the template is not found
+    Auto,
+}
+
+/// Screenshot or template converted for the match mode
+enum PreparedImage {
+    Gray(GrayImage),
+    Color(RgbImage),
+    /// Edge map of the luminance
+    Edges(GrayImage),
+}
+
+impl PreparedImage {
+    fn new(screenshot: &DynamicImage, options: &MatchOptions) -> Self {
+        match options.match_mode {
+            MatchMode::Grayscale => Self::Gray(screenshot.to_luma8()),
+            MatchMode::Color => Self::Color(screenshot.to_rgb8()),
+            MatchMode::Edges => Self::Edges(edge_map(&screenshot.to_luma8())),
+        }
+    }
+
+    /// Template in the screenshot's representation
+    /// Transparent pixels are composited onto white.
+    fn template(template: &DynamicImage, screenshot: &PreparedImage) -> Self {
+        match screenshot {
+            Self::Gray(_) => Self::Gray(convert_to_grayscale_with_alpha(template)),
+            Self::Color(_) => Self::Color(convert_to_rgb_with_alpha(template)),
+            Self::Edges(_) => Self::Edges(edge_map(&convert_to_grayscale_with_alpha(template))),
+        }
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        match self {
+            Self::Gray(image) | Self::Edges(image) => image.dimensions(),
+            Self::Color(image) => image.dimensions(),
+        }
+    }
+
+    /// Scaled copy (edge maps are resized, not recomputed)
+    fn scaled(&self, scale: f64) -> Self {
+        match self {
+            Self::Gray(image) => Self::Gray(downscale(image, scale)),
+            Self::Color(image) => Self::Color(downscale(image, scale)),
+            Self::Edges(image) => Self::Edges(downscale(image, scale)),
+        }
+    }
+
+    fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        use image::imageops::crop_imm;
+        match self {
+            Self::Gray(image) => Self::Gray(crop_imm(image, x, y, width, height).to_image()),
+            Self::Color(image) => Self::Color(crop_imm(image, x, y, width, height).to_image()),
+            Self::Edges(image) => Self::Edges(crop_imm(image, x, y, width, height).to_image()),
+        }
+    }
+
+    /// Single-channel image for feature detection
+    fn luma(&self) -> Cow<'_, GrayImage> {
+        match self {
+            Self::Gray(image) | Self::Edges(image) => Cow::Borrowed(image),
+            Self::Color(image) => Cow::Owned(image::imageops::grayscale(image)),
+        }
+    }
+}
+
 /// Result of template matching for a single hint image
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -61,6 +274,12 @@ pub struct MatchResult {
     pub center_x: Option<i32>,
     /// Y coordinate of the center point (in resized screenshot coordinates)
     pub center_y: Option<i32>,
+    /// Center in monitor-relative logical points (undoing `scale_factor` and the
+    /// display scale factor), with sub-pixel precision when refinement is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_center_x: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_center_y: Option<f64>,
     /// Match confidence score (0.0 - 1.0, where 1.0 is perfect match)
     pub confidence: Option<f32>,
     /// Template width after scaling
@@ -71,6 +290,15 @@ pub struct MatchResult {
     pub error: Option<String>,
     /// Error code for programmatic error handling (use this instead of parsing error message)
     pub error_code: Option<MatchErrorCode>,
+    /// Corners of the located template (top-left, top-right, bottom-right, bottom-left)
+    /// when it was found by features
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quadrilateral: Option<[[i32; 2]; 4]>,
+    /// Point to act on when `MatchOptions::target` is set (same space as the center)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_x: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_y: Option<i32>,
 }
 
 /// Find template image within screenshot and return center coordinates
@@ -107,16 +335,21 @@ pub fn find_template_in_screenshot(
             template_height: 0,
             error: Some(e.to_string()),
             error_code: Some(MatchErrorCode::ScreenshotDecodeError),
+            target_x: None,
+            target_y: None,
+            quadrilateral: None,
+            original_center_x: None,
+            original_center_y: None,
         },
     };
 
-    let screenshot_gray = screenshot.to_luma8();
-
+    let options = MatchOptions::default();
     find_template_with_decoded_screenshot(
-        &screenshot_gray,
-        template_base64,
+        &PreparedImage::new(&screenshot, &options),
+        template_base64.into(),
         scale_factor,
         confidence_threshold,
+        &options,
     )
 }
 
@@ -128,18 +361,53 @@ pub fn find_template_in_screenshot(
 ///
 /// # Arguments
 /// * `screenshot_base64` - Base64 encoded screenshot (already resized)
-/// * `templates` - Vector of (base64_data, file_name) tuples for each hint image
+/// * `templates` - Vector of (template, file_name) tuples for each hint image
 /// * `scale_factor` - Scale factor applied to screenshot
 /// * `confidence_threshold` - Minimum confidence score
+/// * `options` - Matching options for all templates
 ///
 /// # Returns
 /// Vector of MatchResults, one per template image
 pub fn match_templates_batch(
     screenshot_base64: &str,
-    templates: Vec<(&str, &str)>,
+    templates: Vec<(TemplateSource<'_>, &str)>,
     scale_factor: f64,
     confidence_threshold: f32,
+    options: &MatchOptions,
 ) -> Vec<(String, MatchResult)> {
+    match_templates_batch_with_progress(
+        screenshot_base64,
+        templates,
+        scale_factor,
+        confidence_threshold,
+        options,
+        |_, _| ControlFlow::Continue(()),
+    )
+    .into_iter()
+    .filter_map(|(file_name, result)| Some((file_name, result?)))
+    .collect()
+}
+
+/// `match_templates_batch` calling `on_result` with the template index as each match completes
+///
+/// Templates are matched in parallel, so completions arrive in no particular order.
+/// Once `on_result` breaks, templates not yet started are skipped and have no result.
+pub fn match_templates_batch_with_progress(
+    screenshot_base64: &str,
+    templates: Vec<(TemplateSource<'_>, &str)>,
+    scale_factor: f64,
+    confidence_threshold: f32,
+    options: &MatchOptions,
+    on_result: impl Fn(usize, &MatchResult) -> ControlFlow<()> + Sync,
+) -> Vec<(String, Option<MatchResult>)> {
+    let stopped = AtomicBool::new(false);
+    let report = |index: usize, result: MatchResult| {
+        if on_result(index, &result).is_break() {
+            stopped.store(true, Ordering::Relaxed);
+        }
+        Some(result)
+    };
+
     // Decode screenshot once
     let screenshot = match decode_base64_image(screenshot_base64) {
         Ok(img) => img,
@@ -154,82 +422,156 @@ pub fn match_templates_batch(
                 template_height: 0,
                 error: Some(format!("Screenshot decode error: {}", e)),
                 error_code: Some(MatchErrorCode::ScreenshotDecodeError),
+                target_x: None,
+                target_y: None,
+                quadrilateral: None,
+                original_center_x: None,
+                original_center_y: None,
             };
             return templates
                 .into_iter()
-                .map(|(_, name)| (name.to_string(), error_result.clone()))
+                .enumerate()
+                .map(|(index, (_, name))| {
+                    let result = if stopped.load(Ordering::Relaxed) {
+                        None
+                    } else {
+                        report(index, error_result.clone())
+                    };
+                    (name.to_string(), result)
+                })
                 .collect();
         }
     };
 
-    let screenshot_gray = screenshot.to_luma8();
-
-    // Apply additional downscaling for faster matching
-    // This reduces CPU load significantly (0.5 scale = 4x fewer pixels to process)
-    let (optimized_screenshot, optimization_scale) = if MATCH_OPTIMIZATION_SCALE < 1.0 {
-        let (w, h) = screenshot_gray.dimensions();
-        let new_w = ((w as f64) * MATCH_OPTIMIZATION_SCALE).round() as u32;
-        let new_h = ((h as f64) * MATCH_OPTIMIZATION_SCALE).round() as u32;
-        let resized = image::imageops::resize(
-            &screenshot_gray,
-            new_w.max(1),
-            new_h.max(1),
-            image::imageops::FilterType::Triangle, // Fast bilinear filter
-        );
-        (resized, MATCH_OPTIMIZATION_SCALE)
-    } else {
-        (screenshot_gray, 1.0)
-    };
+    let (optimized_screenshot, optimization_scale) = downscale_for_matching(&screenshot, options);
 
     // Process templates in parallel using rayon
     // Each template matching is independent, so we can parallelize safely
     templates
         .into_par_iter()
-        .map(|(template_base64, file_name)| {
+        .enumerate()
+        .map(|(index, (template, file_name))| {
+            if stopped.load(Ordering::Relaxed) {
+                return (file_name.to_string(), None);
+            }
             let result = find_template_with_decoded_screenshot_optimized(
                 &optimized_screenshot,
-                template_base64,
+                template,
                 scale_factor,
                 optimization_scale,
                 confidence_threshold,
+                options,
             );
-            (file_name.to_string(), result)
+            (file_name.to_string(), report(index, result))
         })
         .collect()
 }
 
-/// Internal function that matches a template against a pre-decoded grayscale screenshot
+/// Match a template (original size) against a full-resolution image
+///
+/// Both are downscaled by `MATCH_OPTIMIZATION_SCALE` for speed; the returned
+/// coordinates and template size are in the pixel space of `screenshot`.
+pub fn find_template_in_image(
+    screenshot: &DynamicImage,
+    template: TemplateSource<'_>,
+    confidence_threshold: f32,
+    options: &MatchOptions,
+) -> MatchResult {
+    let (optimized_screenshot, optimization_scale) = downscale_for_matching(screenshot, options);
+    find_template_with_decoded_screenshot_optimized(
+        &optimized_screenshot,
+        template,
+        1.0,
+        optimization_scale,
+        confidence_threshold,
+        options,
+    )
+}
+
+/// Apply additional downscaling for faster matching
+/// This reduces CPU load significantly (0.5 scale = 4x fewer pixels to process)
+/// Returns the image to match against and the scale that was applied.
+/// Edges are extracted after downscaling, as they are for the template.
+fn downscale_for_matching(
+    screenshot: &DynamicImage,
+    options: &MatchOptions,
+) -> (PreparedImage, f64) {
+    if MATCH_OPTIMIZATION_SCALE < 1.0 {
+        let scale = MATCH_OPTIMIZATION_SCALE;
+        let resized = match options.match_mode {
+            MatchMode::Grayscale => PreparedImage::Gray(downscale(&screenshot.to_luma8(), scale)),
+            MatchMode::Color => PreparedImage::Color(downscale(&screenshot.to_rgb8(), scale)),
+            MatchMode::Edges => {
+                PreparedImage::Edges(edge_map(&downscale(&screenshot.to_luma8(), scale)))
+            }
+        };
+        (resized, MATCH_OPTIMIZATION_SCALE)
+    } else {
+        (PreparedImage::new(screenshot, options), 1.0)
+    }
+}
+
+/// Resize an image by `scale`
+fn downscale<P>(image: &ImageBuffer<P, Vec<u8>>, scale: f64) -> ImageBuffer<P, Vec<u8>>
+where
+    P: Pixel<Subpixel = u8> + 'static,
+{
+    let (w, h) = image.dimensions();
+    let new_w = ((w as f64) * scale).round() as u32;
+    let new_h = ((h as f64) * scale).round() as u32;
+    image::imageops::resize(
+        image,
+        new_w.max(1),
+        new_h.max(1),
+        image::imageops::FilterType::Triangle, // Fast bilinear filter
+    )
+}
+
+/// Internal function that matches a template against a pre-decoded screenshot
 fn find_template_with_decoded_screenshot(
-    screenshot_gray: &GrayImage,
-    template_base64: &str,
+    screenshot: &PreparedImage,
+    template: TemplateSource<'_>,
     scale_factor: f64,
     confidence_threshold: f32,
+    options: &MatchOptions,
 ) -> MatchResult {
     find_template_with_decoded_screenshot_optimized(
-        screenshot_gray,
-        template_base64,
+        screenshot,
+        template,
         scale_factor,
         1.0, // No additional optimization
         confidence_threshold,
+        options,
     )
 }
 
 /// Internal function with optimization scale support
 /// The optimization_scale is applied on top of the scale_factor for faster matching
 fn find_template_with_decoded_screenshot_optimized(
-    screenshot_gray: &GrayImage,
-    template_base64: &str,
+    screenshot: &PreparedImage,
+    template: TemplateSource<'_>,
     scale_factor: f64,
     optimization_scale: f64,
     confidence_threshold: f32,
+    options: &MatchOptions,
 ) -> MatchResult {
-    find_template_internal_optimized(
-        screenshot_gray,
-        template_base64,
+    let mut result = find_template_internal_optimized(
+        screenshot,
+        template,
         scale_factor,
         optimization_scale,
         confidence_threshold,
-    )
+        options,
+    );
+    if let (Some(target), true, Some(x), Some(y)) =
+        (options.target, result.found, result.center_x, result.center_y)
+    {
+        let size = (result.template_width, result.template_height);
+        let (target_x, target_y) = target.resolve((x, y), size);
+        result.target_x = Some(target_x);
+        result.target_y = Some(target_y);
+    }
+    result
 }
 
 /// Minimum opacity ratio threshold for template matching
@@ -244,19 +586,20 @@ const MIN_OPACITY_RATIO: f32 = 0.1; // At least 10% of pixels must be opaque
 const MATCH_OPTIMIZATION_SCALE: f64 = 0.5;
 
 /// Optimized internal implementation with additional scaling for faster matching
-/// Uses pre-decoded and pre-scaled grayscale screenshot for efficiency
+/// Uses pre-decoded and pre-scaled screenshot for efficiency
 fn find_template_internal_optimized(
-    screenshot_gray: &GrayImage,
-    template_base64: &str,
+    screenshot: &PreparedImage,
+    template_source: TemplateSource<'_>,
     scale_factor: f64,
     optimization_scale: f64,
     confidence_threshold: f32,
+    options: &MatchOptions,
 ) -> MatchResult {
     // Combined scale factor: API scale * optimization scale
     let combined_scale = scale_factor * optimization_scale;
 
     // Decode template image with detailed error code
-    let template_original = match decode_template_image(template_base64) {
+    let template_original = match decode_template_image(template_source.image) {
         Ok(img) => img,
         Err((error_msg, error_code)) => {
             return MatchResult {
@@ -268,6 +611,11 @@ fn find_template_internal_optimized(
                 template_height: 0,
                 error: Some(error_msg),
                 error_code: Some(error_code),
+                target_x: None,
+                target_y: None,
+                quadrilateral: None,
+                original_center_x: None,
+                original_center_y: None,
             };
         }
     };
@@ -277,23 +625,66 @@ fn find_template_internal_optimized(
     let api_template_width = ((orig_w as f64) * scale_factor).round() as u32;
     let api_template_height = ((orig_h as f64) * scale_factor).round() as u32;
 
-    // Scale alignment: resize hint image by combined factor (API + optimization)
-    let template = if combined_scale < 1.0 {
-        let new_w = ((orig_w as f64) * combined_scale).round() as u32;
-        let new_h = ((orig_h as f64) * combined_scale).round() as u32;
+    // The explicit mask must cover the template pixel for pixel
+    let mask_original = match template_source.mask.map(decode_template_image) {
+        None => None,
+        Some(Ok(mask)) if mask.dimensions() == (orig_w, orig_h) => Some(mask),
+        Some(result) => {
+            let error = match result {
+                Ok(mask) => format!(
+                    "Mask size {}x{} does not match template size {}x{}",
+                    mask.width(),
+                    mask.height(),
+                    orig_w,
+                    orig_h
+                ),
+                Err((message, _)) => format!("Mask {}", message.to_lowercase()),
+            };
+            return MatchResult {
+                found: false,
+                center_x: None,
+                center_y: None,
+                confidence: None,
+                template_width: api_template_width,
+                template_height: api_template_height,
+                error: Some(error),
+                error_code: Some(MatchErrorCode::MaskDecodeError),
+                target_x: None,
+                target_y: None,
+                quadrilateral: None,
+                original_center_x: None,
+                original_center_y: None,
+            };
+        }
+    };
 
-        // Ensure minimum size of 1x1 pixel
-        let new_w = new_w.max(1);
-        let new_h = new_h.max(1);
+    // Scale alignment: resize hint image (and mask) by combined factor (API + optimization)
+    let scale_to_match = |image: DynamicImage| {
+        if combined_scale < 1.0 {
+            let new_w = ((orig_w as f64) * combined_scale).round() as u32;
+            let new_h = ((orig_h as f64) * combined_scale).round() as u32;
 
-        // Use fast Triangle filter instead of Lanczos3 for speed
-        template_original.resize_exact(new_w, new_h, image::imageops::FilterType::Triangle)
-    } else {
-        template_original
+            // Ensure minimum size of 1x1 pixel
+            let new_w = new_w.max(1);
+            let new_h = new_h.max(1);
+
+            // Use fast Triangle filter instead of Lanczos3 for speed
+            image.resize_exact(new_w, new_h, image::imageops::FilterType::Triangle)
+        } else {
+            image
+        }
     };
+    let template = scale_to_match(template_original);
+    let mask = mask_original.map(scale_to_match);
+
+    // Pixels that take part in the correlation (None: all of them)
+    let weights = template_weights(&template, mask.as_ref(), options);
 
     // Check opacity ratio before processing
-    let opacity_ratio = calculate_opacity_ratio(&template);
+    let opacity_ratio = match &weights {
+        Some(weights) => weight_coverage(weights),
+        None => calculate_opacity_ratio(&template),
+    };
     if opacity_ratio < MIN_OPACITY_RATIO {
         return MatchResult {
             found: false,
@@ -308,17 +699,19 @@ fn find_template_internal_optimized(
                 MIN_OPACITY_RATIO * 100.0
             )),
             error_code: Some(MatchErrorCode::InsufficientOpacity),
+            target_x: None,
+            target_y: None,
+            quadrilateral: None,
+            original_center_x: None,
+            original_center_y: None,
         };
     }
 
-    // Convert to grayscale with alpha compositing for transparent PNGs
-    let template_gray = convert_to_grayscale_with_alpha(&template);
-
-    let template_width = template_gray.width();
-    let template_height = template_gray.height();
+    let (template_width, template_height) = template.dimensions();
+    let (screenshot_width, screenshot_height) = screenshot.dimensions();
 
     // Check if template is larger than screenshot (cannot match)
-    if template_width > screenshot_gray.width() || template_height > screenshot_gray.height() {
+    if template_width > screenshot_width || template_height > screenshot_height {
         return MatchResult {
             found: false,
             center_x: None,
@@ -328,22 +721,46 @@ fn find_template_internal_optimized(
             template_height: api_template_height,
             error: Some("Template is larger than screenshot after scaling".to_string()),
             error_code: Some(MatchErrorCode::TemplateTooLarge),
+            target_x: None,
+            target_y: None,
+            quadrilateral: None,
+            original_center_x: None,
+            original_center_y: None,
         };
     }
 
-    // Perform template matching using Normalized Cross-Correlation
-    let result = match_template(
-        screenshot_gray,
-        &template_gray,
-        MatchTemplateMethod::CrossCorrelationNormalized,
-    );
+    let prepared_template = PreparedImage::template(&template, screenshot);
+
+    let scales = MatchScales {
+        scale_factor,
+        optimization_scale,
+        display_scale_factor: options.display_scale_factor.unwrap_or(1.0),
+    };
+
+    if options.match_strategy == MatchStrategy::Features {
+        return feature_match_result(
+            find_template_by_features(screenshot, &prepared_template, weights.as_ref()),
+            &scales,
+            confidence_threshold,
+            (api_template_width, api_template_height),
+        );
+    }
 
-    // Find the maximum value location (best match for NCC)
-    let extremes = find_extremes(&result);
+    // Perform template matching using Normalized Cross-Correlation,
+    // coarse-to-fine when the template is large enough
+    let backend = options.backend;
+    let (confidence, (match_x, match_y)) =
+        pyramid_search(screenshot, &prepared_template, weights.as_ref(), backend).unwrap_or_else(
+            || {
+                // Find the maximum value location (best match for NCC)
+                let scores = score_map(screenshot, &prepared_template, weights.as_ref(), backend);
+                let extremes = find_extremes(&scores);
+                (extremes.max_value, extremes.max_value_location)
+            },
+        );
 
-    // NCC: max_value is already in [0, 1] range for normalized images
+    // NCC: confidence is already in [0, 1] range for normalized images
     // Higher values indicate better matches
-    let confidence = extremes.max_value;
 
     // Guard against non-finite values (NaN/Inf) that can occur with
     // low-variance templates (e.g., single-color images)
@@ -360,33 +777,64 @@ fn find_template_internal_optimized(
                 "Template matching produced non-finite confidence value. Template may have insufficient variance (e.g., single-color image).".to_string()
             ),
             error_code: Some(MatchErrorCode::NonFiniteConfidence),
+            target_x: None,
+            target_y: None,
+            quadrilateral: None,
+            original_center_x: None,
+            original_center_y: None,
         };
     }
 
     if confidence >= confidence_threshold {
+        let (offset_x, offset_y) = if options.subpixel {
+            subpixel_offset(
+                screenshot,
+                &prepared_template,
+                weights.as_ref(),
+                (match_x, match_y),
+                backend,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+
         // Calculate center coordinates in optimized scale
         // match_x, match_y is top-left corner of matched region
         // Add half of template dimensions to get center point
-        let (match_x, match_y) = extremes.max_value_location;
-        let opt_center_x = match_x as f64 + (template_width as f64 / 2.0);
-        let opt_center_y = match_y as f64 + (template_height as f64 / 2.0);
+        let opt_center_x = match_x as f64 + offset_x + (template_width as f64 / 2.0);
+        let opt_center_y = match_y as f64 + offset_y + (template_height as f64 / 2.0);
 
         // Scale coordinates back to API scale (divide by optimization_scale)
         // Since screenshot was scaled by optimization_scale, we need to reverse it
-        let center_x = (opt_center_x / optimization_scale).round() as i32;
-        let center_y = (opt_center_y / optimization_scale).round() as i32;
+        let (center_x, center_y) = scales.to_api(opt_center_x, opt_center_y);
 
         MatchResult {
             found: true,
-            center_x: Some(center_x),
-            center_y: Some(center_y),
+            center_x: Some(center_x.round() as i32),
+            center_y: Some(center_y.round() as i32),
+            original_center_x: Some(scales.to_original(center_x)),
+            original_center_y: Some(scales.to_original(center_y)),
             confidence: Some(confidence),
             template_width: api_template_width,
             template_height: api_template_height,
             error: None,
             error_code: None,
+            target_x: None,
+            target_y: None,
+            quadrilateral: None,
         }
     } else {
+        if options.match_strategy == MatchStrategy::Auto {
+            let by_features = feature_match_result(
+                find_template_by_features(screenshot, &prepared_template, weights.as_ref()),
+                &scales,
+                confidence_threshold,
+                (api_template_width, api_template_height),
+            );
+            if by_features.found {
+                return by_features;
+            }
+        }
         MatchResult {
             found: false,
             center_x: None,
@@ -396,10 +844,518 @@ fn find_template_internal_optimized(
             template_height: api_template_height,
             error: None,
             error_code: None,
+            target_x: None,
+            target_y: None,
+            quadrilateral: None,
+            original_center_x: None,
+            original_center_y: None,
         }
     }
 }
 
+/// Scales between the matching level, API screenshot and logical points
+struct MatchScales {
+    scale_factor: f64,
+    optimization_scale: f64,
+    display_scale_factor: f64,
+}
+
+impl MatchScales {
+    /// Matching-level position in API screenshot pixels
+    fn to_api(&self, x: f64, y: f64) -> (f64, f64) {
+        (x / self.optimization_scale, y / self.optimization_scale)
+    }
+
+    /// API screenshot coordinate in monitor-relative logical points
+    fn to_original(&self, value: f64) -> f64 {
+        value / self.scale_factor / self.display_scale_factor
+    }
+}
+
+/// Sub-pixel offset of an NCC peak from a parabola through its neighbours
+///
+/// Each axis is fitted separately and the offset is clamped to half a pixel.
+/// Peaks on the border of the score map are not refined along that axis.
+fn subpixel_offset(
+    screenshot: &PreparedImage,
+    template: &PreparedImage,
+    weights: Option<&GrayImage>,
+    (x, y): (u32, u32),
+    backend: MatchBackend,
+) -> (f64, f64) {
+    let (screen_width, screen_height) = screenshot.dimensions();
+    let (template_width, template_height) = template.dimensions();
+
+    // Scores of the peak and its 8 neighbours (fewer on the border)
+    let left = x.saturating_sub(1);
+    let top = y.saturating_sub(1);
+    let right = (x + 1).min(screen_width - template_width);
+    let bottom = (y + 1).min(screen_height - template_height);
+    let region = screenshot.crop(
+        left,
+        top,
+        right - left + template_width,
+        bottom - top + template_height,
+    );
+    let scores = score_map(&region, template, weights, backend);
+    let score = |x: u32, y: u32| scores.get_pixel(x - left, y - top)[0] as f64;
+
+    let fit = |before: f64, peak: f64, after: f64| {
+        let curvature = before - 2.0 * peak + after;
+        if curvature < 0.0 && before.is_finite() && after.is_finite() {
+            (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        }
+    };
+    let offset_x = if left < x && x < right {
+        fit(score(x - 1, y), score(x, y), score(x + 1, y))
+    } else {
+        0.0
+    };
+    let offset_y = if top < y && y < bottom {
+        fit(score(x, y - 1), score(x, y), score(x, y + 1))
+    } else {
+        0.0
+    };
+    (offset_x, offset_y)
+}
+
+/// The coarse pyramid level is this much smaller than the matching level
+const PYRAMID_SCALE: f64 = 0.5;
+
+/// Templates smaller than this (either side) at the coarse level are matched directly
+const MIN_PYRAMID_TEMPLATE_SIZE: u32 = 12;
+
+/// Best coarse positions refined at the matching level
+const PYRAMID_CANDIDATES: usize = 3;
+
+/// When the coarse candidates score within this of each other, the coarse level
+/// cannot tell them apart (low-texture template) and the search is exhaustive
+const PYRAMID_MIN_PEAK_GAP: f32 = 0.02;
+
+/// Search window around a candidate, in matching-level pixels on each side
+const PYRAMID_REFINE_MARGIN: u32 = 4;
+
+/// NCC score of every template position in the screenshot
+fn score_map(
+    screenshot: &PreparedImage,
+    template: &PreparedImage,
+    weights: Option<&GrayImage>,
+    backend: MatchBackend,
+) -> ImageBuffer<Luma<f32>, Vec<f32>> {
+    match (screenshot, template) {
+        (PreparedImage::Color(screenshot_rgb), PreparedImage::Color(template_rgb)) => {
+            match_template_color(screenshot_rgb, template_rgb, weights)
+        }
+        (PreparedImage::Edges(screenshot_edges), PreparedImage::Edges(template_edges)) => {
+            let mut result =
+                match_template_gray(screenshot_edges, template_edges, weights, backend);
+            // Areas without edges have no correlation rather than an undefined one
+            // (a template without edges still ends up non-finite)
+            if template_edges.pixels().any(|pixel| pixel[0] > 0) {
+                for score in result.pixels_mut() {
+                    if !score[0].is_finite() {
+                        score[0] = 0.0;
+                    }
+                }
+            }
+            result
+        }
+        (screenshot, template) => {
+            match_template_gray(&screenshot.luma(), &template.luma(), weights, backend)
+        }
+    }
+}
+
+/// Coarse-to-fine NCC search
+///
+/// Matches at `PYRAMID_SCALE` first, then refines the best few positions in a
+/// small window at the matching level. Returns the best score and top-left
+/// position, or None when the template is too small for the coarse level or
+/// the coarse scores have no clear peak (the caller then searches exhaustively).
+fn pyramid_search(
+    screenshot: &PreparedImage,
+    template: &PreparedImage,
+    weights: Option<&GrayImage>,
+    backend: MatchBackend,
+) -> Option<(f32, (u32, u32))> {
+    let (template_width, template_height) = template.dimensions();
+    let coarse_width = (template_width as f64 * PYRAMID_SCALE).round() as u32;
+    let coarse_height = (template_height as f64 * PYRAMID_SCALE).round() as u32;
+    if coarse_width.min(coarse_height) < MIN_PYRAMID_TEMPLATE_SIZE {
+        return None;
+    }
+
+    let coarse_screenshot = screenshot.scaled(PYRAMID_SCALE);
+    let coarse_template = template.scaled(PYRAMID_SCALE);
+    let (coarse_screen_width, coarse_screen_height) = coarse_screenshot.dimensions();
+    let (coarse_width, coarse_height) = coarse_template.dimensions();
+    if coarse_width > coarse_screen_width || coarse_height > coarse_screen_height {
+        return None;
+    }
+    let coarse_weights = weights.map(|weights| downscale(weights, PYRAMID_SCALE));
+    let coarse = score_map(
+        &coarse_screenshot,
+        &coarse_template,
+        coarse_weights.as_ref(),
+        backend,
+    );
+
+    let candidates = strongest_positions(&coarse, (coarse_width / 2, coarse_height / 2));
+    match (candidates.first(), candidates.last()) {
+        (Some(best), Some(weakest))
+            if candidates.len() < PYRAMID_CANDIDATES
+                || best.0 - weakest.0 >= PYRAMID_MIN_PEAK_GAP => {}
+        _ => return None,
+    }
+
+    let (screen_width, screen_height) = screenshot.dimensions();
+    candidates
+        .into_iter()
+        .filter_map(|(_, (x, y))| {
+            // Window at the matching level that contains the candidate's neighbourhood
+            let window = |coarse: u32, size: u32, screen_size: u32| {
+                let estimate = (coarse as f64 / PYRAMID_SCALE).round() as u32;
+                let start = estimate
+                    .saturating_sub(PYRAMID_REFINE_MARGIN)
+                    .min(screen_size - size);
+                let length = (size + 2 * PYRAMID_REFINE_MARGIN).min(screen_size - start);
+                (start, length)
+            };
+            let (window_x, window_width) = window(x, template_width, screen_width);
+            let (window_y, window_height) = window(y, template_height, screen_height);
+
+            let region = screenshot.crop(window_x, window_y, window_width, window_height);
+            let extremes = find_extremes(&score_map(&region, template, weights, backend));
+            let (match_x, match_y) = extremes.max_value_location;
+            extremes
+                .max_value
+                .is_finite()
+                .then_some((extremes.max_value, (window_x + match_x, window_y + match_y)))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+/// Highest finite scores and their positions, at least `spacing` apart on one axis
+fn strongest_positions(
+    scores: &ImageBuffer<Luma<f32>, Vec<f32>>,
+    (spacing_x, spacing_y): (u32, u32),
+) -> Vec<(f32, (u32, u32))> {
+    let mut positions: Vec<(f32, (u32, u32))> = Vec::with_capacity(PYRAMID_CANDIDATES);
+    for _ in 0..PYRAMID_CANDIDATES {
+        let best = scores
+            .enumerate_pixels()
+            .filter(|(x, y, score)| {
+                let apart = |(_, (px, py)): &(f32, (u32, u32))| {
+                    x.abs_diff(*px) > spacing_x || y.abs_diff(*py) > spacing_y
+                };
+                score[0].is_finite() && positions.iter().all(apart)
+            })
+            .max_by(|a, b| a.2[0].total_cmp(&b.2[0]));
+        match best {
+            Some((x, y, score)) => positions.push((score[0], (x, y))),
+            None => break,
+        }
+    }
+    positions
+}
+
+/// Radius of the BRIEF sampling pattern around a keypoint
+const FEATURE_PATCH_RADIUS: f32 = 7.0;
+
+/// Keypoints closer to the image border than this have no room for a rotated patch
+const FEATURE_BORDER: u32 = 11;
+
+/// FAST threshold (intensity difference) for keypoints
+const FEATURE_FAST_THRESHOLD: u8 = 20;
+
+/// Keypoints kept per template / screenshot (strongest first)
+const MAX_TEMPLATE_FEATURES: usize = 300;
+const MAX_SCREENSHOT_FEATURES: usize = 5000;
+
+/// Descriptor matches must differ in fewer bits than this (of 256) and be
+/// clearly better than the second best candidate (Lowe's ratio test)
+const MAX_DESCRIPTOR_DISTANCE: u32 = 64;
+const DESCRIPTOR_RATIO: f32 = 0.8;
+
+/// RANSAC settings for the homography
+const RANSAC_ITERATIONS: usize = 1000;
+const RANSAC_TOLERANCE: f32 = 3.0;
+const MIN_FEATURE_INLIERS: usize = 8;
+
+/// Keypoint with its 256-bit steered BRIEF descriptor
+struct Feature {
+    x: f32,
+    y: f32,
+    bits: [u64; 4],
+}
+
+/// Template point and the screenshot point it was matched to
+type PointMatch = ((f32, f32), (f32, f32));
+
+/// Template located by features, in the prepared screenshot's pixel space
+struct FeatureMatch {
+    center: (f32, f32),
+    corners: [(f32, f32); 4],
+    inlier_ratio: f32,
+}
+
+/// Build the MatchResult of a feature search
+/// Coordinates are scaled back from the optimization scale like NCC matches.
+fn feature_match_result(
+    feature_match: Option<FeatureMatch>,
+    scales: &MatchScales,
+    confidence_threshold: f32,
+    (template_width, template_height): (u32, u32),
+) -> MatchResult {
+    let to_api = |value: f32| (value as f64 / scales.optimization_scale).round() as i32;
+    let confidence = feature_match.as_ref().map_or(0.0, |m| m.inlier_ratio);
+
+    match feature_match {
+        Some(feature_match) if confidence >= confidence_threshold => MatchResult {
+            found: true,
+            center_x: Some(to_api(feature_match.center.0)),
+            center_y: Some(to_api(feature_match.center.1)),
+            original_center_x: Some(scales.to_original(
+                feature_match.center.0 as f64 / scales.optimization_scale,
+            )),
+            original_center_y: Some(scales.to_original(
+                feature_match.center.1 as f64 / scales.optimization_scale,
+            )),
+            confidence: Some(confidence),
+            template_width,
+            template_height,
+            error: None,
+            error_code: None,
+            target_x: None,
+            target_y: None,
+            quadrilateral: Some(feature_match.corners.map(|(x, y)| [to_api(x), to_api(y)])),
+        },
+        _ => MatchResult {
+            found: false,
+            center_x: None,
+            center_y: None,
+            confidence: Some(confidence),
+            template_width,
+            template_height,
+            error: None,
+            error_code: None,
+            target_x: None,
+            target_y: None,
+            quadrilateral: None,
+            original_center_x: None,
+            original_center_y: None,
+        },
+    }
+}
+
+/// Locate the template by ORB features and a RANSAC homography
+///
+/// Returns None when the template is too small for feature patches or too few
+/// matches agree on a homography. Masked template pixels have no keypoints.
+fn find_template_by_features(
+    screenshot: &PreparedImage,
+    template: &PreparedImage,
+    weights: Option<&GrayImage>,
+) -> Option<FeatureMatch> {
+    let template_luma = template.luma();
+
+    let template_features = describe_features(&template_luma, MAX_TEMPLATE_FEATURES, weights);
+    if template_features.len() < MIN_FEATURE_INLIERS {
+        return None;
+    }
+    let screenshot_features =
+        describe_features(&screenshot.luma(), MAX_SCREENSHOT_FEATURES, None);
+
+    let matches = match_features(&template_features, &screenshot_features);
+    let (projection, inliers) = estimate_homography(&matches)?;
+
+    let (width, height) = template_luma.dimensions();
+    let (width, height) = (width as f32, height as f32);
+    let corners =
+        [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)].map(|p| projection * p);
+    if !is_convex(&corners) {
+        return None;
+    }
+
+    Some(FeatureMatch {
+        center: projection * (width / 2.0, height / 2.0),
+        corners,
+        inlier_ratio: inliers as f32 / matches.len() as f32,
+    })
+}
+
+/// BRIEF test pairs (offsets from the keypoint), generated once from a fixed seed
+fn brief_test_pairs() -> &'static [[(f32, f32); 2]; 256] {
+    static PAIRS: OnceLock<[[(f32, f32); 2]; 256]> = OnceLock::new();
+    PAIRS.get_or_init(|| {
+        let mut random = XorShift(0x2545_F491_4F6C_DD1D);
+        let mut offset = || {
+            // Inside the patch circle, so rotated offsets stay within FEATURE_BORDER
+            loop {
+                let x = (random.next_f32() * 2.0 - 1.0) * FEATURE_PATCH_RADIUS;
+                let y = (random.next_f32() * 2.0 - 1.0) * FEATURE_PATCH_RADIUS;
+                if x.hypot(y) <= FEATURE_PATCH_RADIUS {
+                    return (x, y);
+                }
+            }
+        };
+        std::array::from_fn(|_| [offset(), offset()])
+    })
+}
+
+/// Detect oriented FAST keypoints and compute steered BRIEF descriptors
+fn describe_features(
+    image: &GrayImage,
+    max_features: usize,
+    weights: Option<&GrayImage>,
+) -> Vec<Feature> {
+    let (width, height) = image.dimensions();
+    if width <= 2 * FEATURE_BORDER || height <= 2 * FEATURE_BORDER {
+        return Vec::new();
+    }
+
+    // Tests compare smoothed pixels, so single-pixel noise does not flip bits
+    let smoothed = box_filter(image, 2, 2);
+    let pairs = brief_test_pairs();
+
+    oriented_fast(
+        image,
+        Some(FEATURE_FAST_THRESHOLD),
+        max_features,
+        FEATURE_BORDER,
+        None,
+    )
+    .into_iter()
+    .filter(|keypoint| {
+        weights.is_none_or(|weights| {
+            weights.get_pixel(keypoint.corner.x, keypoint.corner.y)[0] > 0
+        })
+    })
+    .map(|keypoint| {
+        // Rotate the pattern by the keypoint orientation
+        let (sin, cos) = keypoint.orientation.sin_cos();
+        let (x, y) = (keypoint.corner.x as f32, keypoint.corner.y as f32);
+        let sample = |(dx, dy): (f32, f32)| {
+            let sx = (x + dx * cos - dy * sin).round() as u32;
+            let sy = (y + dx * sin + dy * cos).round() as u32;
+            smoothed.get_pixel(sx, sy)[0]
+        };
+
+        let mut bits = [0u64; 4];
+        for (index, [first, second]) in pairs.iter().enumerate() {
+            if sample(*first) < sample(*second) {
+                bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+        Feature { x, y, bits }
+    })
+    .collect()
+}
+
+/// Pair each template feature with its nearest screenshot feature
+/// Returns (template point, screenshot point) pairs that pass the ratio test.
+fn match_features(template: &[Feature], screenshot: &[Feature]) -> Vec<PointMatch> {
+    template
+        .par_iter()
+        .filter_map(|feature| {
+            let (mut best, mut second_best) = ((u32::MAX, None), u32::MAX);
+            for candidate in screenshot {
+                let distance: u32 = feature
+                    .bits
+                    .iter()
+                    .zip(&candidate.bits)
+                    .map(|(a, b)| (a ^ b).count_ones())
+                    .sum();
+                if distance < best.0 {
+                    second_best = best.0;
+                    best = (distance, Some(candidate));
+                } else if distance < second_best {
+                    second_best = distance;
+                }
+            }
+            let (distance, candidate) = best;
+            let candidate = candidate?;
+            let distinct = (distance as f32) < DESCRIPTOR_RATIO * second_best as f32;
+            (distance < MAX_DESCRIPTOR_DISTANCE && distinct)
+                .then_some(((feature.x, feature.y), (candidate.x, candidate.y)))
+        })
+        .collect()
+}
+
+/// Fit a template-to-screenshot homography with RANSAC
+/// Returns the projection and its inlier count, or None below `MIN_FEATURE_INLIERS`.
+fn estimate_homography(matches: &[PointMatch]) -> Option<(Projection, usize)> {
+    if matches.len() < MIN_FEATURE_INLIERS {
+        return None;
+    }
+
+    // Fixed seed keeps results reproducible between runs
+    let mut random = XorShift(0x9E37_79B9_7F4A_7C15);
+    let mut best: Option<(Projection, usize)> = None;
+    for _ in 0..RANSAC_ITERATIONS {
+        let mut sample = [0usize; 4];
+        for i in 0..4 {
+            sample[i] = loop {
+                let index = random.next_index(matches.len());
+                if !sample[..i].contains(&index) {
+                    break index;
+                }
+            };
+        }
+
+        let from = sample.map(|index| matches[index].0);
+        let to = sample.map(|index| matches[index].1);
+        let Some(projection) = Projection::from_control_points(from, to) else {
+            continue;
+        };
+
+        let inliers = matches
+            .iter()
+            .filter(|(from, to)| {
+                let (x, y) = projection * *from;
+                (x - to.0).hypot(y - to.1) <= RANSAC_TOLERANCE
+            })
+            .count();
+        if best.as_ref().is_none_or(|(_, most)| inliers > *most) {
+            best = Some((projection, inliers));
+        }
+    }
+    best.filter(|(_, inliers)| *inliers >= MIN_FEATURE_INLIERS)
+}
+
+/// Whether the corners form a convex quadrilateral (a plausible projected rectangle)
+fn is_convex(corners: &[(f32, f32); 4]) -> bool {
+    let turns: Vec<f32> = (0..4)
+        .map(|i| {
+            let (a, b, c) = (corners[i], corners[(i + 1) % 4], corners[(i + 2) % 4]);
+            (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0)
+        })
+        .collect();
+    turns.iter().all(|turn| *turn > 0.0) || turns.iter().all(|turn| *turn < 0.0)
+}
+
+/// Small deterministic random number generator for sampling
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn next_index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
 /// Calculate the opacity ratio of an image (proportion of non-transparent pixels)
 ///
 /// Returns a value between 0.0 (fully transparent) and 1.0 (fully opaque).
@@ -424,6 +1380,41 @@ fn calculate_opacity_ratio(image: &DynamicImage) -> f32 {
     opaque_pixels / total_pixels
 }
 
+/// Correlation weights of the template pixels, or None when every pixel counts
+///
+/// An explicit mask's luminance is used as is. Otherwise a template with
+/// transparent pixels is weighted by its alpha channel, unless
+/// `composite_alpha` asks for compositing onto white instead.
+fn template_weights(
+    template: &DynamicImage,
+    mask: Option<&DynamicImage>,
+    options: &MatchOptions,
+) -> Option<GrayImage> {
+    if let Some(mask) = mask {
+        return Some(mask.to_luma8());
+    }
+    if options.composite_alpha || !template.color().has_alpha() {
+        return None;
+    }
+
+    let rgba = template.to_rgba8();
+    if rgba.pixels().all(|pixel| pixel[3] == u8::MAX) {
+        return None;
+    }
+    Some(GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        Luma([rgba.get_pixel(x, y)[3]])
+    }))
+}
+
+/// Proportion of pixels with a non-zero weight
+fn weight_coverage(weights: &GrayImage) -> f32 {
+    let total_pixels = (weights.width() * weights.height()) as f32;
+    if total_pixels == 0.0 {
+        return 0.0;
+    }
+    weights.pixels().filter(|pixel| pixel[0] > 0).count() as f32 / total_pixels
+}
+
 /// Convert DynamicImage to grayscale with proper alpha handling
 ///
 /// For transparent PNGs (icons, buttons with transparency), the alpha channel
@@ -450,6 +1441,110 @@ fn convert_to_grayscale_with_alpha(image: &DynamicImage) -> GrayImage {
     })
 }
 
+/// Grayscale NCC, restricted to the weighted template pixels when given
+/// (masked matching always uses imageproc)
+fn match_template_gray(
+    image: &GrayImage,
+    template: &GrayImage,
+    weights: Option<&GrayImage>,
+    backend: MatchBackend,
+) -> ImageBuffer<Luma<f32>, Vec<f32>> {
+    match weights {
+        None if backend.use_simd() => ncc_simd::match_template(image, template),
+        Some(weights) => match_template_with_mask(
+            image,
+            template,
+            MatchTemplateMethod::CrossCorrelationNormalized,
+            weights,
+        ),
+        None => match_template(
+            image,
+            template,
+            MatchTemplateMethod::CrossCorrelationNormalized,
+        ),
+    }
+}
+
+/// Sobel gradient magnitude, scaled to 8 bits
+///
+/// Only intensity changes count, so inverting the colors (light vs dark theme)
+/// gives the same map.
+fn edge_map(image: &GrayImage) -> GrayImage {
+    let gradients = sobel_gradients(image);
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        Luma([(gradients.get_pixel(x, y)[0] / 4).min(u8::MAX as u16) as u8])
+    })
+}
+
+/// Convert DynamicImage to RGB, compositing transparent pixels onto white
+fn convert_to_rgb_with_alpha(image: &DynamicImage) -> RgbImage {
+    let rgba: RgbaImage = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let pixel: Rgba<u8> = *rgba.get_pixel(x, y);
+        let alpha = pixel[3] as f32 / 255.0;
+        let composite = |value: u8| (value as f32 * alpha + 255.0 * (1.0 - alpha)).round() as u8;
+        Rgb([composite(pixel[0]), composite(pixel[1]), composite(pixel[2])])
+    })
+}
+
+/// Normalized cross-correlation over the three color channels
+///
+/// Each pixel is treated as an RGB vector, so the per-channel correlations are
+/// summed before normalizing. This is the score `CrossCorrelationNormalized`
+/// computes for grayscale (with the same mask weighting), and a template only
+/// reaches 1.0 where the colors agree, not just the brightness.
+fn match_template_color(
+    image: &RgbImage,
+    template: &RgbImage,
+    weights: Option<&GrayImage>,
+) -> ImageBuffer<Luma<f32>, Vec<f32>> {
+    let (template_width, template_height) = template.dimensions();
+    let output_width = image.width() - template_width + 1;
+    let output_height = image.height() - template_height + 1;
+
+    // Template pixels with their squared weight; fully masked pixels are skipped
+    let pixels: Vec<(u32, u32, f32, [f32; 3])> = template
+        .enumerate_pixels()
+        .filter_map(|(x, y, pixel)| {
+            let weight = weights.map_or(1.0, |weights| weights.get_pixel(x, y)[0] as f32 / 255.0);
+            (weight > 0.0).then(|| {
+                let color = [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32];
+                (x, y, weight * weight, color)
+            })
+        })
+        .collect();
+    let template_norm: f32 = pixels
+        .iter()
+        .map(|(_, _, weight, color)| weight * color.iter().map(|c| c * c).sum::<f32>())
+        .sum();
+
+    let scores: Vec<f32> = (0..output_height)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            let pixels = &pixels;
+            (0..output_width).map(move |x| {
+                let (mut cross, mut image_norm) = (0.0f32, 0.0f32);
+                for (tx, ty, weight, color) in pixels {
+                    let pixel = image.get_pixel(x + tx, y + ty);
+                    for channel in 0..3 {
+                        let value = pixel[channel] as f32;
+                        cross += weight * value * color[channel];
+                        image_norm += weight * value * value;
+                    }
+                }
+                // Black areas have no correlation rather than an undefined one
+                if image_norm > 0.0 {
+                    cross / (template_norm * image_norm).sqrt()
+                } else {
+                    0.0
+                }
+            })
+        })
+        .collect();
+
+    ImageBuffer::from_raw(output_width, output_height, scores).expect("one score per position")
+}
+
 /// Decode base64 string to DynamicImage
 fn decode_base64_image(base64_data: &str) -> Result<DynamicImage, XenotesterError> {
     let bytes = BASE64_STANDARD
@@ -771,6 +1866,196 @@ mod tests {
         assert_eq!(gray.get_pixel(1, 1).0[0], 0, "Opaque black should stay 0");
     }
 
+    #[test]
+    fn test_transparent_pixels_are_masked() {
+        // White square framed by transparent pixels, shown on a gray background
+        let screenshot = create_screenshot_with_target(300, 300, 100, 120, 20, 20);
+        let mut img: RgbaImage = ImageBuffer::from_pixel(40, 40, Rgba([0, 0, 0, 0]));
+        for y in 10..30 {
+            for x in 10..30 {
+                img.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+            }
+        }
+        let template = DynamicImage::ImageRgba8(img);
+
+        let weights = template_weights(&template, None, &MatchOptions::default()).unwrap();
+        assert_eq!(weight_coverage(&weights), 0.25);
+        let composite = MatchOptions {
+            composite_alpha: true,
+            ..Default::default()
+        };
+        assert!(template_weights(&template, None, &composite).is_none());
+
+        let mut buffer = Vec::new();
+        template
+            .write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png)
+            .unwrap();
+        let template_base64 = BASE64_STANDARD.encode(&buffer);
+
+        let result = find_template_in_screenshot(&screenshot, &template_base64, 1.0, 0.9);
+        assert!(result.found);
+        assert!(result.confidence.unwrap() > 0.99);
+        assert_eq!((result.center_x, result.center_y), (Some(110), Some(130)));
+
+        // An explicit mask must have the template's size
+        let source = TemplateSource {
+            image: &template_base64,
+            mask: Some(&create_template(20, 20, 255)),
+        };
+        let screenshot_gray = decode_base64_image(&screenshot).unwrap().to_luma8();
+        let prepared = PreparedImage::Gray(screenshot_gray);
+        let result = find_template_with_decoded_screenshot(
+            &prepared,
+            source,
+            1.0,
+            0.9,
+            &MatchOptions::default(),
+        );
+        assert_eq!(result.error_code, Some(MatchErrorCode::MaskDecodeError));
+    }
+
+    #[test]
+    fn test_color_mode_tells_colors_apart() {
+        // Red and green squares of the same luminance on a gray background
+        let mut screenshot = RgbImage::from_pixel(200, 100, Rgb([128, 128, 128]));
+        for y in 40..60 {
+            for x in 40..60 {
+                screenshot.put_pixel(x, y, Rgb([255, 0, 0]));
+                screenshot.put_pixel(x + 100, y, Rgb([0, 130, 0]));
+            }
+        }
+        let screenshot = DynamicImage::ImageRgb8(screenshot);
+        let green = create_test_image(20, 20, [0, 130, 0]);
+
+        let options = MatchOptions {
+            match_mode: MatchMode::Color,
+            ..Default::default()
+        };
+        let result = find_template_in_image(&screenshot, green.as_str().into(), 0.9, &options);
+        assert!(result.found);
+        assert_eq!((result.center_x, result.center_y), (Some(150), Some(50)));
+
+        let red = PreparedImage::new(&screenshot.crop_imm(40, 40, 20, 20), &options);
+        let result =
+            find_template_with_decoded_screenshot(&red, green.as_str().into(), 1.0, 0.5, &options);
+        assert!(!result.found);
+        assert!(result.confidence.unwrap() < 0.1);
+    }
+
+    #[test]
+    fn test_edge_mode_survives_inverted_theme() {
+        // Dark button with a light frame on a light background...
+        let button = |background: u8, frame: u8| {
+            let mut image = GrayImage::from_pixel(30, 30, Luma([background]));
+            for y in 5..25 {
+                for x in 5..25 {
+                    let on_frame = !(8..22).contains(&x) || !(8..22).contains(&y);
+                    image.put_pixel(x, y, Luma([if on_frame { frame } else { background }]));
+                }
+            }
+            image
+        };
+        let mut buffer = Vec::new();
+        DynamicImage::ImageLuma8(button(240, 30))
+            .write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png)
+            .unwrap();
+        let template = BASE64_STANDARD.encode(&buffer);
+
+        // ...found in the dark theme, where the intensities are inverted
+        let mut screenshot = GrayImage::from_pixel(200, 120, Luma([30]));
+        image::imageops::replace(&mut screenshot, &button(30, 240), 120, 60);
+        let screenshot = DynamicImage::ImageLuma8(screenshot);
+
+        let options = MatchOptions {
+            match_mode: MatchMode::Edges,
+            ..Default::default()
+        };
+        let result = find_template_in_image(&screenshot, template.as_str().into(), 0.9, &options);
+        assert!(result.found, "confidence: {:?}", result.confidence);
+        assert_eq!((result.center_x, result.center_y), (Some(135), Some(75)));
+    }
+
+    #[test]
+    fn test_features_find_rotated_template() {
+        // Blocky pattern with plenty of corners
+        let mut random = XorShift(7);
+        let blocks: Vec<u8> = (0..144).map(|_| (random.next_u64() % 4 * 80) as u8).collect();
+        let pattern =
+            GrayImage::from_fn(120, 120, |x, y| Luma([blocks[(y / 10 * 12 + x / 10) as usize]]));
+        let mut buffer = Vec::new();
+        DynamicImage::ImageLuma8(pattern.clone())
+            .write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png)
+            .unwrap();
+        let template = BASE64_STANDARD.encode(&buffer);
+
+        // Shown rotated by 90 degrees, which NCC cannot match
+        let mut screenshot = GrayImage::from_pixel(400, 300, Luma([128]));
+        image::imageops::replace(&mut screenshot, &image::imageops::rotate90(&pattern), 200, 100);
+        let screenshot = DynamicImage::ImageLuma8(screenshot);
+
+        let options = MatchOptions {
+            match_strategy: MatchStrategy::Features,
+            ..Default::default()
+        };
+        let result = find_template_in_image(&screenshot, template.as_str().into(), 0.5, &options);
+        assert!(result.found, "confidence: {:?}", result.confidence);
+        let (x, y) = (result.center_x.unwrap(), result.center_y.unwrap());
+        assert!((x - 260).abs() <= 3 && (y - 160).abs() <= 3, "center: {}, {}", x, y);
+        let quadrilateral = result.quadrilateral.unwrap();
+        // Rotated clockwise: the template's top-left corner is now top-right
+        assert!((quadrilateral[0][0] - 320).abs() <= 4 && (quadrilateral[0][1] - 100).abs() <= 4);
+    }
+
+    #[test]
+    fn test_pyramid_search_matches_exhaustive_search() {
+        let mut random = XorShift(11);
+        let blocks: Vec<u8> = (0..400).map(|_| (random.next_u64() % 5 * 60) as u8).collect();
+        let screenshot = PreparedImage::Gray(GrayImage::from_fn(320, 240, |x, y| {
+            Luma([blocks[(y / 12 * 20 + x / 16) as usize % 400]])
+        }));
+        let template = screenshot.crop(131, 77, 40, 32);
+
+        let backend = MatchBackend::Cpu;
+        let (confidence, position) = pyramid_search(&screenshot, &template, None, backend).unwrap();
+        let exhaustive = find_extremes(&score_map(&screenshot, &template, None, backend));
+        assert_eq!(position, (131, 77));
+        assert_eq!(position, exhaustive.max_value_location);
+        assert!(confidence > 0.999);
+
+        // Small templates are matched directly
+        assert!(pyramid_search(&screenshot, &screenshot.crop(0, 0, 16, 16), None, backend).is_none());
+    }
+
+    #[test]
+    fn test_subpixel_original_coordinates() {
+        let blob = |width: u32, height: u32, (cx, cy): (f32, f32)| {
+            GrayImage::from_fn(width, height, |x, y| {
+                let distance = (x as f32 - cx).powi(2) + (y as f32 - cy).powi(2);
+                Luma([(20.0 + 220.0 * (-distance / 18.0).exp()) as u8])
+            })
+        };
+        let screenshot = PreparedImage::Gray(blob(120, 80, (60.3, 40.0)));
+        let mut buffer = Vec::new();
+        DynamicImage::ImageLuma8(blob(21, 21, (10.0, 10.0)))
+            .write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png)
+            .unwrap();
+        let template = BASE64_STANDARD.encode(&buffer);
+
+        let options = MatchOptions {
+            display_scale_factor: Some(2.0),
+            subpixel: true,
+            ..Default::default()
+        };
+        let source = template.as_str().into();
+        let result = find_template_with_decoded_screenshot(&screenshot, source, 1.0, 0.9, &options);
+        assert_eq!((result.center_x, result.center_y), (Some(61), Some(41)));
+        // Peak at x = 50.3, y = 30 plus half the template, in points at 2x
+        let x = result.original_center_x.unwrap();
+        let y = result.original_center_y.unwrap();
+        assert!((x - 30.4).abs() < 0.05, "x: {}", x);
+        assert!((y - 20.25).abs() < 0.05, "y: {}", y);
+    }
+
     #[test]
     fn test_batch_matching() {
         // Create screenshot with multiple white target regions
@@ -781,11 +2066,12 @@ mod tests {
         let template2 = create_template(30, 30, 0);   // Doesn't match (black)
 
         let templates = vec![
-            (template1.as_str(), "match.png"),
-            (template2.as_str(), "nomatch.png"),
+            (template1.as_str().into(), "match.png"),
+            (template2.as_str().into(), "nomatch.png"),
         ];
 
-        let results = match_templates_batch(&screenshot, templates, 1.0, 0.5);
+        let results =
+            match_templates_batch(&screenshot, templates, 1.0, 0.5, &MatchOptions::default());
 
         assert_eq!(results.len(), 2);
 
@@ -806,12 +2092,13 @@ mod tests {
     #[test]
     fn test_batch_matching_with_screenshot_decode_error() {
         let templates = vec![
-            ("valid-base64", "image1.png"),
-            ("valid-base64", "image2.png"),
+            ("valid-base64".into(), "image1.png"),
+            ("valid-base64".into(), "image2.png"),
         ];
 
         // Invalid screenshot should return error for all templates
-        let results = match_templates_batch("invalid-screenshot!!!", templates, 1.0, 0.5);
+        let options = MatchOptions::default();
+        let results = match_templates_batch("invalid-screenshot!!!", templates, 1.0, 0.5, &options);
 
         assert_eq!(results.len(), 2);
         for (_, result) in &results {
@@ -821,6 +2108,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_match_target_resolves_anchor_and_offset() {
+        let target = MatchTarget {
+            anchor: Anchor::TopRight,
+            offset_x: 20,
+            offset_y: -5,
+        };
+        assert_eq!(target.resolve((100, 50), (40, 20)), (140, 35));
+        assert_eq!(MatchTarget::default().resolve((100, 50), (40, 20)), (100, 50));
+    }
+
+    #[test]
+    fn test_batch_stops_when_progress_breaks() {
+        let templates = vec![
+            ("valid-base64".into(), "image1.png"),
+            ("valid-base64".into(), "image2.png"),
+        ];
+
+        let options = MatchOptions::default();
+        let results = match_templates_batch_with_progress(
+            "invalid-screenshot!!!",
+            templates,
+            1.0,
+            0.5,
+            &options,
+            |_, _| ControlFlow::Break(()),
+        );
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].1.is_some());
+        assert!(results[1].1.is_none());
+    }
+
     #[test]
     fn test_confidence_is_reasonable() {
         // Test that CrossCorrelationNormalized returns reasonable confidence values
@@ -0,0 +1,229 @@
+//! Token usage and cost accounting service
+//!
+//! Records LLM token counts per API call into the `llm_usage` table and
+//! aggregates them per period and per scenario.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::error::XenotesterError;
+use crate::services::llm::anthropic::Usage;
+
+/// USD prices per million tokens: (model ID prefix, input, output)
+/// Longest matching prefix wins; unknown models fall back to the last entry.
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-haiku-4-5", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    // Models of the local endpoint run on the user's own hardware
+    ("local/", 0.0, 0.0),
+    ("", 3.0, 15.0),
+];
+
+/// Prompt cache writes cost 1.25x and cache reads 0.1x the base input price
+const CACHE_WRITE_MULTIPLIER: f64 = 1.25;
+const CACHE_READ_MULTIPLIER: f64 = 0.1;
+
+/// (input, output) USD prices per million tokens of a model
+fn model_prices(model: &str) -> (f64, f64) {
+    let (_, input_price, output_price) = MODEL_PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .copied()
+        .unwrap_or(("", 3.0, 15.0));
+    (input_price, output_price)
+}
+
+fn per_token(price: f64) -> f64 {
+    price / 1_000_000.0
+}
+
+/// Estimate the USD cost of a single API call
+pub fn estimate_cost(model: &str, usage: &Usage) -> f64 {
+    let (input_price, output_price) = model_prices(model);
+
+    usage.input_tokens as f64 * per_token(input_price)
+        + usage.cache_creation_input_tokens as f64 * per_token(input_price * CACHE_WRITE_MULTIPLIER)
+        + usage.cache_read_input_tokens as f64 * per_token(input_price * CACHE_READ_MULTIPLIER)
+        + usage.output_tokens as f64 * per_token(output_price)
+}
+
+/// Estimate the USD cost avoided by prompt caching and skipped screenshots
+/// Cache reads save 90% of the input price, less the surcharge on cache
+/// writes; a skipped screenshot saves its tokens once at the input price,
+/// though an image sent would also have been re-read on every later turn.
+pub fn estimate_savings(model: &str, usage: &Usage) -> f64 {
+    let (input_price, _) = model_prices(model);
+
+    usage.cache_read_input_tokens as f64 * per_token(input_price * (1.0 - CACHE_READ_MULTIPLIER))
+        - usage.cache_creation_input_tokens as f64
+            * per_token(input_price * (CACHE_WRITE_MULTIPLIER - 1.0))
+        + usage.skipped_image_tokens as f64 * per_token(input_price)
+}
+
+/// Record usage of one (or an aggregate of) API call(s)
+pub async fn record_usage(
+    pool: &SqlitePool,
+    run_id: Option<&str>,
+    model: &str,
+    usage: &Usage,
+) -> Result<(), XenotesterError> {
+    sqlx::query(
+        "INSERT INTO llm_usage (run_id, model, input_tokens, output_tokens,
+             cache_creation_input_tokens, cache_read_input_tokens, cost_usd,
+             screenshots_skipped, saved_usd)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(run_id)
+    .bind(model)
+    .bind(usage.input_tokens as i64)
+    .bind(usage.output_tokens as i64)
+    .bind(usage.cache_creation_input_tokens as i64)
+    .bind(usage.cache_read_input_tokens as i64)
+    .bind(estimate_cost(model, usage))
+    .bind(usage.screenshots_skipped as i64)
+    .bind(estimate_savings(model, usage))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Aggregation period for usage summaries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsagePeriod {
+    Day,
+    Week,
+    Month,
+    All,
+}
+
+impl UsagePeriod {
+    /// SQLite datetime modifier for the start of the period
+    fn sqlite_modifier(&self) -> &'static str {
+        match self {
+            UsagePeriod::Day => "-1 day",
+            UsagePeriod::Week => "-7 days",
+            UsagePeriod::Month => "-1 month",
+            UsagePeriod::All => "-1000 years",
+        }
+    }
+}
+
+/// Usage attributed to a single scenario
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ScenarioUsage {
+    pub scenario_id: Option<String>,
+    pub scenario_title: Option<String>,
+    pub run_count: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+}
+
+/// Token and cost totals over a set of API calls
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotals {
+    pub request_count: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_input_tokens: i64,
+    pub cache_read_input_tokens: i64,
+    pub cost_usd: f64,
+    /// Screenshots not sent because the screen had not changed
+    pub screenshots_skipped: i64,
+    /// Estimated cost avoided by prompt caching and skipped screenshots
+    pub saved_usd: f64,
+}
+
+/// Aggregated usage for a period
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSummary {
+    pub period: UsagePeriod,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+    /// Per-scenario breakdown, most expensive first
+    pub by_scenario: Vec<ScenarioUsage>,
+}
+
+/// Summarize usage recorded within the given period
+pub async fn get_usage_summary(
+    pool: &SqlitePool,
+    period: UsagePeriod,
+) -> Result<UsageSummary, XenotesterError> {
+    let since_clause = "u.created_at >= strftime('%Y-%m-%d %H:%M:%f', 'now', ?)";
+
+    let totals: UsageTotals = sqlx::query_as(&format!(
+        "SELECT COUNT(*) AS request_count,
+                COALESCE(SUM(u.input_tokens), 0) AS input_tokens,
+                COALESCE(SUM(u.output_tokens), 0) AS output_tokens,
+                COALESCE(SUM(u.cache_creation_input_tokens), 0) AS cache_creation_input_tokens,
+                COALESCE(SUM(u.cache_read_input_tokens), 0) AS cache_read_input_tokens,
+                COALESCE(SUM(u.cost_usd), 0.0) AS cost_usd,
+                COALESCE(SUM(u.screenshots_skipped), 0) AS screenshots_skipped,
+                COALESCE(SUM(u.saved_usd), 0.0) AS saved_usd
+         FROM llm_usage u
+         WHERE {}",
+        since_clause
+    ))
+    .bind(period.sqlite_modifier())
+    .fetch_one(pool)
+    .await?;
+
+    let by_scenario: Vec<ScenarioUsage> = sqlx::query_as(&format!(
+        "SELECT r.scenario_id AS scenario_id,
+                MAX(r.scenario_title) AS scenario_title,
+                COUNT(DISTINCT u.run_id) AS run_count,
+                COALESCE(SUM(u.input_tokens), 0) AS input_tokens,
+                COALESCE(SUM(u.output_tokens), 0) AS output_tokens,
+                COALESCE(SUM(u.cost_usd), 0.0) AS cost_usd
+         FROM llm_usage u
+         LEFT JOIN runs r ON r.id = u.run_id
+         WHERE {}
+         GROUP BY r.scenario_id
+         ORDER BY SUM(u.cost_usd) DESC",
+        since_clause
+    ))
+    .bind(period.sqlite_modifier())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(UsageSummary {
+        period,
+        totals,
+        by_scenario,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_savings() {
+        let usage = Usage {
+            cache_read_input_tokens: 1_000_000,
+            cache_creation_input_tokens: 1_000_000,
+            skipped_image_tokens: 1_000_000,
+            ..Usage::default()
+        };
+        // $3 input: 90% saved on reads, 25% surcharge on writes, full price per skipped image
+        let saved = estimate_savings("claude-sonnet-4-5", &usage);
+        assert!((saved - (2.7 - 0.75 + 3.0)).abs() < 1e-9, "{}", saved);
+        assert_eq!(estimate_savings("local/llama3", &usage), 0.0);
+        assert_eq!(
+            estimate_savings("claude-sonnet-4-5", &Usage::default()),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Detection of real user input during automation
+//!
+//! The input device remembers where each operation left the cursor. A watcher
+//! thread polls the cursor; if it moved while no operation was running, the user
+//! has taken the mouse. Shortly after automated input that counts as an
+//! intervention: listeners are notified and, with the `pause` response, input
+//! actions wait until the user has left the mouse alone for `resumeAfterMs`.
+//! Keyboard input is not observed.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, error};
+
+use crate::error::XenotesterError;
+use crate::services::input_device::{InputDevice, SettledCursor};
+use crate::utils::cancel::CancellationToken;
+
+/// Interval between cursor polls
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What happens when the user intervenes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterventionResponse {
+    /// Hold input actions until the user is idle again
+    #[default]
+    Pause,
+    /// Only emit the event
+    Notify,
+}
+
+/// User activity detection settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UserActivityConfig {
+    pub enabled: bool,
+    pub response: InterventionResponse,
+    /// Cursor movement (pixels) ignored as jitter
+    pub movement_threshold: u32,
+    /// Idle time after the user's last movement before actions resume
+    pub resume_after_ms: u64,
+    /// Movement counts as an intervention only this soon after automated input
+    pub automation_window_ms: u64,
+}
+
+impl Default for UserActivityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            response: InterventionResponse::Pause,
+            movement_threshold: 3,
+            resume_after_ms: 3000,
+            automation_window_ms: 10_000,
+        }
+    }
+}
+
+/// Payload of the `user-intervention-detected` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Intervention {
+    /// Cursor position moved to by the user
+    pub x: i32,
+    pub y: i32,
+    /// Position the last automated operation left the cursor at
+    pub expected_x: i32,
+    pub expected_y: i32,
+    /// Whether input actions are held until the user is idle
+    pub paused: bool,
+}
+
+/// Called once per intervention (not for every poll while the user keeps moving)
+pub type Notifier = Box<dyn Fn(&Intervention) + Send + Sync>;
+
+/// Tracks user interventions and holds input actions while one is ongoing
+pub struct UserActivity {
+    config: Mutex<UserActivityConfig>,
+    /// Last time the user moved the cursor during automation
+    last_intervention: Mutex<Option<Instant>>,
+    notifier: Mutex<Option<Notifier>>,
+}
+
+impl UserActivity {
+    pub fn new() -> Self {
+        Self {
+            config: Mutex::new(UserActivityConfig::default()),
+            last_intervention: Mutex::new(None),
+            notifier: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> UserActivityConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    pub fn set_config(&self, config: UserActivityConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    pub fn set_notifier(&self, notifier: Notifier) {
+        *self.notifier.lock().unwrap() = Some(notifier);
+    }
+
+    /// Compare the cursor with where the last operation left it
+    pub fn poll(&self) -> Result<(), XenotesterError> {
+        if !self.config().enabled {
+            return Ok(());
+        }
+        let (position, settled) = InputDevice::acquire()?.observe_cursor()?;
+        if let Some(intervention) = self.observe_at(position, settled, Instant::now()) {
+            if let Some(notify) = self.notifier.lock().unwrap().as_ref() {
+                notify(&intervention);
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a cursor observation; returns the intervention if one starts
+    fn observe_at(
+        &self,
+        position: (i32, i32),
+        settled: Option<SettledCursor>,
+        now: Instant,
+    ) -> Option<Intervention> {
+        let config = self.config();
+        let settled = settled?;
+        let automating = now.saturating_duration_since(settled.at)
+            <= Duration::from_millis(config.automation_window_ms);
+        let moved = position.0.abs_diff(settled.position.0) > config.movement_threshold
+            || position.1.abs_diff(settled.position.1) > config.movement_threshold;
+        if !automating || !moved {
+            return None;
+        }
+
+        let ongoing = self.pause_remaining(now).is_some();
+        *self.last_intervention.lock().unwrap() = Some(now);
+        (!ongoing).then(|| Intervention {
+            x: position.0,
+            y: position.1,
+            expected_x: settled.position.0,
+            expected_y: settled.position.1,
+            paused: config.response == InterventionResponse::Pause,
+        })
+    }
+
+    /// Time left until the user counts as idle again (None when not paused)
+    fn pause_remaining(&self, now: Instant) -> Option<Duration> {
+        let config = self.config();
+        if !config.enabled {
+            return None;
+        }
+        let last = (*self.last_intervention.lock().unwrap())?;
+        let resume_at = last + Duration::from_millis(config.resume_after_ms);
+        (resume_at > now).then(|| resume_at - now)
+    }
+
+    /// Wait while the user is intervening (only with the `pause` response)
+    pub async fn wait_until_idle(&self, cancel: &CancellationToken) -> Result<(), XenotesterError> {
+        if self.config().response != InterventionResponse::Pause {
+            return Ok(());
+        }
+        while let Some(remaining) = self.pause_remaining(Instant::now()) {
+            cancel
+                .run_until_cancelled(tokio::time::sleep(remaining))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for UserActivity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Poll the cursor in the background for the lifetime of the app
+pub fn spawn_watcher(activity: Arc<UserActivity>) {
+    let spawned = thread::Builder::new()
+        .name("user-activity".to_string())
+        .spawn(move || loop {
+            if let Err(e) = activity.poll() {
+                debug!("User activity poll failed: {}", e);
+            }
+            thread::sleep(POLL_INTERVAL);
+        });
+    if let Err(e) = spawned {
+        error!("Failed to start user activity watcher: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_movement_after_automation_pauses() {
+        let activity = UserActivity::new();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        // Last operation left the cursor at (100, 100) at time 0
+        let settled = Some(SettledCursor {
+            position: (100, 100),
+            at: start,
+        });
+        let observe =
+            |position: (i32, i32), ms: u64| activity.observe_at(position, settled, at(ms));
+
+        // Jitter and movement long after the last operation are ignored
+        assert!(observe((102, 101), 100).is_none());
+        assert!(observe((300, 300), 20_000).is_none());
+        assert!(activity.observe_at((300, 300), None, at(100)).is_none());
+        assert!(activity.pause_remaining(at(100)).is_none());
+
+        let intervention = observe((300, 300), 1000);
+        assert!(intervention.is_some_and(|i| i.paused && i.expected_x == 100));
+        // Continued movement extends the pause without notifying again
+        assert!(observe((400, 300), 2000).is_none());
+        assert_eq!(
+            activity.pause_remaining(at(4000)),
+            Some(Duration::from_millis(1000))
+        );
+        assert!(activity.pause_remaining(at(5000)).is_none());
+    }
+}
@@ -0,0 +1,431 @@
+//! Variable substitution for parameterized scenarios
+//!
+//! Resolves placeholders in scenario text and step parameters:
+//!
+//! - `${NAME}`: run variable
+//! - `${env:NAME}`: environment variable
+//! - `${secret:NAME}`: stored secret (see `secrets`)
+//!
+//! `$${` produces a literal `${`. The environment is only read through an
+//! explicit `${env:...}` in the scenario. Secret values are loaded up front
+//! for the names a scenario references, and text sent to the model keeps its
+//! secret placeholders. Text the agent types only has the placeholders of its
+//! instruction filled in; anything else it types, including `${...}` read off
+//! the screen, is typed as written.
+
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::XenotesterError;
+use crate::services::secrets;
+
+/// Appended to instructions that reference secrets
+const SECRET_HINT: &str = "Placeholders like ${secret:NAME} stand for confidential values. \
+Type them exactly as written; they are filled in when typed.";
+
+/// Where a placeholder's value comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Variable,
+    Env,
+    Secret,
+}
+
+/// A `${...}` reference in text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Placeholder {
+    pub source: Source,
+    pub name: String,
+}
+
+impl Placeholder {
+    fn parse(inner: &str) -> Result<Self, XenotesterError> {
+        let (source, name) = match inner.split_once(':') {
+            Some(("env", name)) => (Source::Env, name),
+            Some(("secret", name)) => (Source::Secret, name),
+            Some((prefix, _)) => {
+                return Err(XenotesterError::ConfigError(format!(
+                    "Unknown placeholder source '{}' in ${{{}}}",
+                    prefix, inner
+                )))
+            }
+            None => (Source::Variable, inner),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(XenotesterError::ConfigError(format!(
+                "Empty placeholder ${{{}}}",
+                inner
+            )));
+        }
+        Ok(Self {
+            source,
+            name: name.to_string(),
+        })
+    }
+
+    fn text(&self) -> String {
+        match self.source {
+            Source::Variable => format!("${{{}}}", self.name),
+            Source::Env => format!("${{env:{}}}", self.name),
+            Source::Secret => format!("${{secret:{}}}", self.name),
+        }
+    }
+}
+
+/// Piece of parsed text
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment<'a> {
+    Literal(&'a str),
+    Placeholder(Placeholder),
+}
+
+/// Split text into literals and placeholders
+fn parse(text: &str) -> Result<Vec<Segment<'_>>, XenotesterError> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        // "$${" escapes a literal "${"
+        if rest[..start].ends_with('$') {
+            segments.push(Segment::Literal(&rest[..start - 1]));
+            segments.push(Segment::Literal("${"));
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        segments.push(Segment::Literal(&rest[..start]));
+        let end = rest[start..].find('}').ok_or_else(|| {
+            XenotesterError::ConfigError(format!("Unclosed placeholder in: {}", text))
+        })?;
+        let inner = &rest[start + 2..start + end];
+        segments.push(Segment::Placeholder(Placeholder::parse(inner)?));
+        rest = &rest[start + end + 1..];
+    }
+
+    segments.push(Segment::Literal(rest));
+    Ok(segments)
+}
+
+/// Placeholders referenced in text
+pub fn placeholders(text: &str) -> Result<Vec<Placeholder>, XenotesterError> {
+    Ok(parse(text)?
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Placeholder(placeholder) => Some(placeholder),
+            Segment::Literal(_) => None,
+        })
+        .collect())
+}
+
+/// Values available for substitution during a run
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    values: HashMap<String, String>,
+    secrets: HashMap<String, String>,
+    /// Placeholders of the agent instruction, the only ones filled in typed text
+    typeable: HashSet<Placeholder>,
+}
+
+impl Variables {
+    pub fn new(values: HashMap<String, String>) -> Self {
+        Self {
+            values,
+            ..Self::default()
+        }
+    }
+
+    /// Variables of a scenario called from this run: the run variables
+    /// overridden by `overrides`, which may reference them, and the secrets
+    /// loaded so far. Secret placeholders in the overrides stay placeholders
+    /// and are filled in wherever the child substitutes the variable.
+    pub fn for_child(&self, overrides: &HashMap<String, String>) -> Result<Self, XenotesterError> {
+        let mut values = self.values.clone();
+        for (name, value) in overrides {
+            values.insert(name.clone(), self.substitute_public(value)?);
+        }
+        Ok(Self {
+            values,
+            secrets: self.secrets.clone(),
+            ..Self::default()
+        })
+    }
+
+    /// Decrypt the secrets referenced in `texts` so they can be substituted
+    pub async fn load_secrets(
+        &mut self,
+        pool: Option<&SqlitePool>,
+        texts: &[&str],
+    ) -> Result<(), XenotesterError> {
+        for text in texts {
+            for placeholder in placeholders(text)? {
+                if placeholder.source != Source::Secret
+                    || self.secrets.contains_key(&placeholder.name)
+                {
+                    continue;
+                }
+                secrets::validate_name(&placeholder.name)?;
+                let pool = pool.ok_or_else(|| {
+                    XenotesterError::ConfigError(format!(
+                        "Secret '{}' needs the application database",
+                        placeholder.name
+                    ))
+                })?;
+                let value = secrets::resolve_secret(pool, &placeholder.name).await?;
+                self.secrets.insert(placeholder.name, value);
+            }
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, placeholder: &Placeholder, keep_secrets: bool) -> Option<String> {
+        match placeholder.source {
+            Source::Variable if keep_secrets => self.values.get(&placeholder.name).cloned(),
+            Source::Variable => self
+                .values
+                .get(&placeholder.name)
+                .map(|value| self.fill_secrets(value)),
+            Source::Env => std::env::var(&placeholder.name).ok(),
+            Source::Secret => self.secrets.get(&placeholder.name).cloned(),
+        }
+    }
+
+    /// Fill in the loaded secrets a variable value got from a calling scenario
+    /// Anything else in the value is kept as written.
+    fn fill_secrets(&self, value: &str) -> String {
+        const PREFIX: &str = "${secret:";
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value;
+
+        while let Some(start) = rest.find(PREFIX) {
+            let Some(end) = rest[start..].find('}') else {
+                break;
+            };
+            result.push_str(&rest[..start]);
+            let raw = &rest[start..start + end + 1];
+            let secret = self.secrets.get(raw[PREFIX.len()..raw.len() - 1].trim());
+            result.push_str(secret.map_or(raw, String::as_str));
+            rest = &rest[start + end + 1..];
+        }
+
+        result.push_str(rest);
+        result
+    }
+
+    fn render(&self, text: &str, keep_secrets: bool) -> Result<String, XenotesterError> {
+        let mut result = String::with_capacity(text.len());
+        let mut missing = Vec::new();
+
+        for segment in parse(text)? {
+            match segment {
+                Segment::Literal(literal) => result.push_str(literal),
+                Segment::Placeholder(placeholder)
+                    if keep_secrets && placeholder.source == Source::Secret =>
+                {
+                    result.push_str(&placeholder.text())
+                }
+                Segment::Placeholder(placeholder) => {
+                    match self.lookup(&placeholder, keep_secrets) {
+                        Some(value) => result.push_str(&value),
+                        None => missing.push(placeholder.text()),
+                    }
+                }
+            }
+        }
+
+        if missing.is_empty() {
+            Ok(result)
+        } else {
+            Err(XenotesterError::ConfigError(format!(
+                "Unresolved placeholders: {}",
+                missing.join(", ")
+            )))
+        }
+    }
+
+    /// Prepare an agent instruction: load the secrets it references and
+    /// substitute everything else, explaining the secret placeholders to the model
+    pub async fn prepare_instruction(
+        &mut self,
+        pool: Option<&SqlitePool>,
+        instruction: &str,
+    ) -> Result<String, XenotesterError> {
+        self.load_secrets(pool, &[instruction]).await?;
+        let mut prepared = self.substitute_public(instruction)?;
+        self.typeable.extend(placeholders(instruction)?);
+        if placeholders(instruction)?
+            .iter()
+            .any(|p| p.source == Source::Secret)
+        {
+            prepared.push_str("\n\n");
+            prepared.push_str(SECRET_HINT);
+        }
+        Ok(prepared)
+    }
+
+    /// Replace every placeholder; unresolved ones are an error
+    pub fn substitute(&self, text: &str) -> Result<String, XenotesterError> {
+        self.render(text, false)
+    }
+
+    /// Replace every placeholder except secrets, for text sent to the model
+    pub fn substitute_public(&self, text: &str) -> Result<String, XenotesterError> {
+        self.render(text, true)
+    }
+
+    /// Fill the instruction's placeholders in text the agent types
+    /// Other `${...}` (template strings, shell variables, or a placeholder
+    /// shown on screen) and `$${` are typed exactly as written.
+    pub fn substitute_typed(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find("${") {
+            let Some(end) = rest[start..].find('}') else {
+                break;
+            };
+            result.push_str(&rest[..start]);
+            let raw = &rest[start..start + end + 1];
+            let value = Placeholder::parse(&raw[2..raw.len() - 1])
+                .ok()
+                .filter(|placeholder| self.typeable.contains(placeholder))
+                .and_then(|placeholder| self.lookup(&placeholder, false));
+            result.push_str(value.as_deref().unwrap_or(raw));
+            rest = &rest[start + end + 1..];
+        }
+
+        result.push_str(rest);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Variables {
+        let mut variables = Variables::new(HashMap::from([
+            ("USER".to_string(), "alice".to_string()),
+            ("URL".to_string(), "https://example.com".to_string()),
+        ]));
+        variables
+            .secrets
+            .insert("pw".to_string(), "s3cret!".to_string());
+        variables
+    }
+
+    #[test]
+    fn test_substitute_sources_and_escape() {
+        let variables = variables();
+        assert_eq!(
+            variables
+                .substitute("Open ${URL}, log in as ${USER} / ${secret:pw}; $${literal}")
+                .unwrap(),
+            "Open https://example.com, log in as alice / s3cret!; ${literal}"
+        );
+        assert_eq!(
+            variables
+                .substitute_public("Type ${secret:pw} as ${USER}")
+                .unwrap(),
+            "Type ${secret:pw} as alice"
+        );
+    }
+
+    #[test]
+    fn test_substitute_reports_unresolved() {
+        let variables = variables();
+        let error = variables
+            .substitute("${MISSING_VAR_XT} ${secret:other}")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("${MISSING_VAR_XT}"));
+        assert!(error.contains("${secret:other}"));
+        assert!(variables.substitute("${unclosed").is_err());
+        assert!(variables.substitute("${db:name}").is_err());
+    }
+
+    #[test]
+    fn test_plain_names_do_not_read_the_environment() {
+        std::env::set_var("XT_TEST_ENV_ONLY", "from-env");
+        let variables = Variables::default();
+        assert!(variables.substitute("${XT_TEST_ENV_ONLY}").is_err());
+        assert_eq!(
+            variables.substitute("${env:XT_TEST_ENV_ONLY}").unwrap(),
+            "from-env"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_typed_text_fills_only_instruction_placeholders() {
+        std::env::set_var("XT_TEST_TYPED_KEY", "do-not-type");
+        let mut variables = variables();
+        variables
+            .prepare_instruction(None, "Log in as ${USER} with ${secret:pw}")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            variables.substitute_typed("${secret:pw}"),
+            "s3cret!".to_string()
+        );
+        // Placeholders the instruction didn't use are typed literally
+        assert_eq!(
+            variables.substitute_typed("${env:XT_TEST_TYPED_KEY} ${XT_TEST_TYPED_KEY}"),
+            "${env:XT_TEST_TYPED_KEY} ${XT_TEST_TYPED_KEY}"
+        );
+        assert_eq!(
+            variables.substitute_typed("console.log(`${a + b}`); echo $${HOME} ${unclosed"),
+            "console.log(`${a + b}`); echo $${HOME} ${unclosed"
+        );
+    }
+
+    #[test]
+    fn test_child_variables_keep_caller_secrets() {
+        let child = variables()
+            .for_child(&HashMap::from([
+                ("PASSWORD".to_string(), "${secret:pw}".to_string()),
+                ("GREETING".to_string(), "hi ${USER}".to_string()),
+            ]))
+            .unwrap();
+        assert_eq!(child.substitute("${PASSWORD}").unwrap(), "s3cret!");
+        assert_eq!(child.substitute("${secret:pw}").unwrap(), "s3cret!");
+        assert_eq!(
+            child.substitute_public("${GREETING}, ${PASSWORD}").unwrap(),
+            "hi alice, ${secret:pw}"
+        );
+        assert_eq!(child.substitute("${URL}").unwrap(), "https://example.com");
+        assert!(variables()
+            .for_child(&HashMap::from([(
+                "X".to_string(),
+                "${MISSING}".to_string()
+            )]))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_internal_secrets_cannot_be_referenced() {
+        let mut variables = Variables::default();
+        let error = variables
+            .load_secrets(None, &["${secret:internal.oauth.session}"])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, XenotesterError::SecretError(_)));
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            placeholders("a ${X} b ${secret:pw} $${Y}").unwrap(),
+            vec![
+                Placeholder {
+                    source: Source::Variable,
+                    name: "X".to_string()
+                },
+                Placeholder {
+                    source: Source::Secret,
+                    name: "pw".to_string()
+                },
+            ]
+        );
+    }
+}
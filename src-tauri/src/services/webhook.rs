@@ -0,0 +1,596 @@
+//! Webhook payload definitions and chat-service formatters
+//!
+//! Every notification is an event envelope: the event name, a timestamp and
+//! event-specific `data` (see the `*_EVENT` constants). The raw envelope is
+//! posted as-is for custom integrations. Slack, Discord and Teams expect their
+//! own message schemas, so the payload can be rendered into those formats here
+//! instead of requiring a relay service.
+//!
+//! A screenshot thumbnail can go along with a notification. Discord receives
+//! it as a file upload and Teams inline in the card; raw payloads carry it
+//! either way (see `ScreenshotDelivery`). Slack incoming webhooks accept
+//! neither, so it is dropped there.
+
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, warn};
+use url::Url;
+
+use crate::error::XenotesterError;
+use crate::services::http;
+
+/// A scenario failed (sent by the frontend runner and the backend runner)
+/// data: `{scenario, error}`
+pub const TEST_FAILURE_EVENT: &str = "test_failure";
+/// data: `{scenario, runId}`
+pub const RUN_STARTED_EVENT: &str = "run_started";
+/// data: `{scenario, runId, success, status, message, durationMs}`
+pub const RUN_COMPLETED_EVENT: &str = "run_completed";
+/// data: `{scenario, runId, stepIndex, step, message}`
+pub const STEP_FAILED_EVENT: &str = "step_failed";
+/// data: `{activeRun}`
+pub const EMERGENCY_STOP_EVENT: &str = "emergency_stop";
+/// Sent by `send_test_email` to check a channel, data: `{}`
+pub const TEST_EVENT: &str = "test";
+
+/// Webhook event envelope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub event: String,
+    pub timestamp: String,
+    #[serde(default)]
+    pub data: Value,
+}
+
+impl WebhookPayload {
+    /// Envelope for `event`, timestamped now
+    pub fn new(event: &str, data: impl Serialize) -> Self {
+        Self {
+            event: event.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            data: serde_json::to_value(data).unwrap_or(Value::Null),
+        }
+    }
+}
+
+/// `scenario` in event data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioInfo {
+    pub id: String,
+    pub title: String,
+}
+
+/// `error` in `test_failure` data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorInfo {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_at_action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_successful_action: Option<String>,
+    pub completed_actions: i32,
+}
+
+/// Target message format for webhook notifications
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationFormat {
+    /// Post the payload JSON unchanged
+    #[default]
+    Raw,
+    /// Slack incoming webhook (Block Kit)
+    Slack,
+    /// Discord webhook (embed)
+    Discord,
+    /// Microsoft Teams incoming webhook (Adaptive Card)
+    Teams,
+}
+
+/// How a screenshot is attached to raw payloads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenshotDelivery {
+    /// Base64 PNG in `data.screenshot`
+    #[default]
+    Inline,
+    /// multipart/form-data with the envelope in `payload` and the PNG in `screenshot`
+    Multipart,
+}
+
+/// File name used for the screenshot attachment in multipart requests
+pub const SCREENSHOT_FILE_NAME: &str = "screenshot.png";
+
+/// Request body rendered for a specific notification format
+pub enum RenderedNotification {
+    /// Plain JSON body
+    Json(Value),
+    /// Multipart body with a JSON part and a PNG attachment
+    Multipart {
+        /// Form field of the JSON part
+        json_field: &'static str,
+        payload_json: Value,
+        /// Form field of the PNG part
+        file_field: &'static str,
+        image_png: Vec<u8>,
+    },
+}
+
+/// How an event is presented in chat messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tone {
+    Info,
+    Success,
+    Warning,
+    Failure,
+}
+
+impl Tone {
+    fn slack_emoji(self) -> &'static str {
+        match self {
+            Tone::Info => ":arrow_forward:",
+            Tone::Success => ":white_check_mark:",
+            Tone::Warning => ":octagonal_sign:",
+            Tone::Failure => ":x:",
+        }
+    }
+
+    fn discord_color(self) -> u32 {
+        match self {
+            Tone::Info => 0x3498DB,
+            Tone::Success => 0x2ECC71,
+            Tone::Warning => 0xE67E22,
+            Tone::Failure => 0xE74C3C,
+        }
+    }
+
+    fn teams_color(self) -> &'static str {
+        match self {
+            Tone::Info => "Accent",
+            Tone::Success => "Good",
+            Tone::Warning => "Warning",
+            Tone::Failure => "Attention",
+        }
+    }
+}
+
+/// Format-independent content of a message (chat messages and emails)
+#[derive(Debug)]
+pub(crate) struct Summary {
+    /// One-line summary, also used as notification fallback text
+    pub(crate) headline: String,
+    /// Scenario title, or the headline for events without a scenario
+    pub(crate) title: String,
+    pub(crate) message: Option<String>,
+    pub(crate) facts: Vec<(&'static str, String)>,
+    tone: Tone,
+}
+
+fn text(data: &Value, pointer: &str) -> Option<String> {
+    match data.pointer(pointer)? {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+pub(crate) fn summarize(payload: &WebhookPayload) -> Summary {
+    let data = &payload.data;
+    let scenario = text(data, "/scenario/title").or_else(|| text(data, "/scenario/id"));
+    let name = scenario.clone().unwrap_or_else(|| "Scenario".to_string());
+
+    let (headline, tone) = match payload.event.as_str() {
+        TEST_FAILURE_EVENT => (format!("Scenario failed: {}", name), Tone::Failure),
+        RUN_STARTED_EVENT => (format!("Run started: {}", name), Tone::Info),
+        RUN_COMPLETED_EVENT => match (data["success"].as_bool(), data["status"].as_str()) {
+            (Some(true), _) => (format!("Run passed: {}", name), Tone::Success),
+            (_, Some("stopped")) => (format!("Run stopped: {}", name), Tone::Warning),
+            _ => (format!("Run failed: {}", name), Tone::Failure),
+        },
+        STEP_FAILED_EVENT => (format!("Step failed: {}", name), Tone::Failure),
+        EMERGENCY_STOP_EVENT => ("Emergency stop".to_string(), Tone::Warning),
+        TEST_EVENT => ("Xenotester test notification".to_string(), Tone::Info),
+        other => (format!("{}: {}", other, name), Tone::Info),
+    };
+
+    let mut facts = Vec::new();
+    if let Some(index) = data["stepIndex"].as_u64() {
+        let step = text(data, "/step").unwrap_or_default();
+        facts.push(("Step", format!("{}. {}", index + 1, step)));
+    }
+    if let Some(status) = text(data, "/status") {
+        facts.push(("Status", status));
+    }
+    if let Some(duration) = data["durationMs"].as_u64() {
+        facts.push(("Duration", format!("{:.1} s", duration as f64 / 1000.0)));
+    }
+    if let Some(completed) = text(data, "/error/completed_actions") {
+        facts.push(("Completed actions", completed));
+    }
+    if let Some(failed_at) = text(data, "/error/failed_at_action") {
+        facts.push(("Failed at", failed_at));
+    }
+    if let Some(last_ok) = text(data, "/error/last_successful_action") {
+        facts.push(("Last successful", last_ok));
+    }
+    if let Some(run) = text(data, "/activeRun") {
+        facts.push(("Active run", run));
+    }
+
+    Summary {
+        title: scenario.unwrap_or_else(|| headline.clone()),
+        headline,
+        message: text(data, "/error/message").or_else(|| text(data, "/message")),
+        facts,
+        tone,
+    }
+}
+
+/// Render the payload for the given format
+///
+/// `screenshot_png` is an already-downscaled thumbnail; `delivery` only
+/// applies to the raw format.
+pub fn render_notification(
+    payload: &WebhookPayload,
+    format: NotificationFormat,
+    screenshot_png: Option<Vec<u8>>,
+    delivery: ScreenshotDelivery,
+) -> RenderedNotification {
+    let summary = summarize(payload);
+    match format {
+        NotificationFormat::Raw => render_raw(payload, screenshot_png, delivery),
+        NotificationFormat::Slack => RenderedNotification::Json(render_slack(payload, &summary)),
+        NotificationFormat::Discord => {
+            let payload_json = render_discord(payload, &summary, screenshot_png.is_some());
+            match screenshot_png {
+                Some(image_png) => RenderedNotification::Multipart {
+                    json_field: "payload_json",
+                    payload_json,
+                    file_field: "files[0]",
+                    image_png,
+                },
+                None => RenderedNotification::Json(payload_json),
+            }
+        }
+        NotificationFormat::Teams => {
+            RenderedNotification::Json(render_teams(payload, &summary, screenshot_png.as_deref()))
+        }
+    }
+}
+
+fn render_raw(
+    payload: &WebhookPayload,
+    screenshot_png: Option<Vec<u8>>,
+    delivery: ScreenshotDelivery,
+) -> RenderedNotification {
+    let mut body = serde_json::to_value(payload).unwrap_or(Value::Null);
+    match (screenshot_png, delivery) {
+        (None, _) => RenderedNotification::Json(body),
+        (Some(png), ScreenshotDelivery::Inline) => {
+            // `data` of events sent by other clients may not be an object
+            if let Some(data) = body["data"].as_object_mut() {
+                data.insert("screenshot".to_string(), json!(BASE64_STANDARD.encode(png)));
+            }
+            RenderedNotification::Json(body)
+        }
+        (Some(image_png), ScreenshotDelivery::Multipart) => RenderedNotification::Multipart {
+            json_field: "payload",
+            payload_json: body,
+            file_field: "screenshot",
+            image_png,
+        },
+    }
+}
+
+fn render_slack(payload: &WebhookPayload, summary: &Summary) -> Value {
+    let fields: Vec<Value> = summary
+        .facts
+        .iter()
+        .map(|(label, value)| json!({ "type": "mrkdwn", "text": format!("*{}:*\n{}", label, value) }))
+        .collect();
+
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": {
+            "type": "plain_text",
+            "text": format!("{} {}", summary.tone.slack_emoji(), summary.title)
+        }
+    })];
+    if !fields.is_empty() {
+        blocks.push(json!({ "type": "section", "fields": fields }));
+    }
+    if let Some(message) = &summary.message {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("```{}```", message) }
+        }));
+    }
+    blocks.push(json!({
+        "type": "context",
+        "elements": [{ "type": "mrkdwn", "text": payload.timestamp }]
+    }));
+
+    json!({
+        "text": summary.headline,
+        "blocks": blocks
+    })
+}
+
+fn render_discord(payload: &WebhookPayload, summary: &Summary, with_screenshot: bool) -> Value {
+    let fields: Vec<Value> = summary
+        .facts
+        .iter()
+        .map(|(label, value)| json!({ "name": label, "value": value, "inline": value.len() <= 40 }))
+        .collect();
+
+    let mut embed = json!({
+        "title": summary.title,
+        "color": summary.tone.discord_color(),
+        "fields": fields,
+        "timestamp": payload.timestamp,
+        "footer": { "text": payload.event }
+    });
+    if let Some(message) = &summary.message {
+        embed["description"] = json!(message);
+    }
+    if with_screenshot {
+        embed["image"] = json!({ "url": format!("attachment://{}", SCREENSHOT_FILE_NAME) });
+    }
+
+    json!({
+        "content": summary.headline,
+        "embeds": [embed]
+    })
+}
+
+fn render_teams(
+    payload: &WebhookPayload,
+    summary: &Summary,
+    screenshot_png: Option<&[u8]>,
+) -> Value {
+    let facts: Vec<Value> = summary
+        .facts
+        .iter()
+        .map(|(label, value)| json!({ "title": label, "value": value }))
+        .collect();
+
+    let mut body = vec![json!({
+        "type": "TextBlock",
+        "size": "Large",
+        "weight": "Bolder",
+        "color": summary.tone.teams_color(),
+        "text": summary.headline,
+        "wrap": true
+    })];
+    if !facts.is_empty() {
+        body.push(json!({ "type": "FactSet", "facts": facts }));
+    }
+    if let Some(message) = &summary.message {
+        body.push(json!({ "type": "TextBlock", "text": message, "wrap": true }));
+    }
+    body.push(json!({
+        "type": "TextBlock",
+        "text": payload.timestamp,
+        "isSubtle": true,
+        "size": "Small"
+    }));
+    // Teams renders data URIs in Image elements, so the thumbnail is inlined
+    if let Some(png) = screenshot_png {
+        body.push(json!({
+            "type": "Image",
+            "url": format!("data:image/png;base64,{}", BASE64_STANDARD.encode(png)),
+            "altText": "Screenshot"
+        }));
+    }
+
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "body": body
+            }
+        }]
+    })
+}
+
+/// Check that a webhook URL is a non-empty http(s) URL
+pub fn is_valid_webhook_url(url: &str) -> bool {
+    if url.trim().is_empty() {
+        return false;
+    }
+
+    let parsed_url = match Url::parse(url) {
+        Ok(u) => u,
+        Err(e) => {
+            warn!("Invalid URL: {}", e);
+            return false;
+        }
+    };
+
+    // Only allow http/https schemes
+    if parsed_url.scheme() != "http" && parsed_url.scheme() != "https" {
+        warn!("Invalid URL scheme: {}", parsed_url.scheme());
+        return false;
+    }
+
+    true
+}
+
+/// Render and POST a notification to a URL checked with `is_valid_webhook_url`
+/// Failures are logged and reported as false
+pub async fn post_webhook(
+    url: &str,
+    payload: &WebhookPayload,
+    notification_format: NotificationFormat,
+    thumbnail: Option<Vec<u8>>,
+    delivery: ScreenshotDelivery,
+) -> Result<bool, XenotesterError> {
+    let rendered = render_notification(payload, notification_format, thumbnail, delivery);
+
+    let client = http::client()?;
+    let request = client.post(url).timeout(std::time::Duration::from_secs(10));
+
+    let request = match rendered {
+        RenderedNotification::Json(body) => request
+            .header("Content-Type", "application/json")
+            .json(&body),
+        RenderedNotification::Multipart {
+            json_field,
+            payload_json,
+            file_field,
+            image_png,
+        } => {
+            let image_part = Part::bytes(image_png)
+                .file_name(SCREENSHOT_FILE_NAME)
+                .mime_str("image/png")?;
+            let form = Form::new()
+                .text(json_field, payload_json.to_string())
+                .part(file_field, image_part);
+            request.multipart(form)
+        }
+    };
+
+    match request.send().await {
+        Ok(response) => {
+            if response.status().is_success() {
+                Ok(true)
+            } else {
+                warn!(
+                    "Request failed: {} {}",
+                    response.status().as_u16(),
+                    response.status().canonical_reason().unwrap_or("Unknown")
+                );
+                Ok(false)
+            }
+        }
+        Err(e) => {
+            error!("Request error: {}", e);
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure_payload() -> WebhookPayload {
+        WebhookPayload::new(
+            TEST_FAILURE_EVENT,
+            json!({
+                "scenario": ScenarioInfo { id: "s1".to_string(), title: "Login".to_string() },
+                "error": ErrorInfo {
+                    message: "Button not found".to_string(),
+                    failed_at_action: Some("Click submit".to_string()),
+                    last_successful_action: None,
+                    completed_actions: 3,
+                },
+            }),
+        )
+    }
+
+    #[test]
+    fn test_summary_of_failure_event() {
+        let summary = summarize(&failure_payload());
+        assert_eq!(summary.headline, "Scenario failed: Login");
+        assert_eq!(summary.title, "Login");
+        assert_eq!(summary.message.as_deref(), Some("Button not found"));
+        assert_eq!(summary.tone, Tone::Failure);
+        assert!(summary
+            .facts
+            .contains(&("Failed at", "Click submit".to_string())));
+        assert!(summary
+            .facts
+            .contains(&("Completed actions", "3".to_string())));
+    }
+
+    #[test]
+    fn test_summary_of_lifecycle_events() {
+        let completed = WebhookPayload::new(
+            RUN_COMPLETED_EVENT,
+            json!({
+                "scenario": { "id": "s1", "title": "Login" },
+                "success": true,
+                "status": "success",
+                "durationMs": 1500,
+            }),
+        );
+        let summary = summarize(&completed);
+        assert_eq!(summary.headline, "Run passed: Login");
+        assert_eq!(summary.tone, Tone::Success);
+        assert!(summary.facts.contains(&("Duration", "1.5 s".to_string())));
+
+        let stop = WebhookPayload::new(EMERGENCY_STOP_EVENT, json!({ "activeRun": null }));
+        let summary = summarize(&stop);
+        assert_eq!(summary.title, "Emergency stop");
+        assert!(summary.facts.is_empty());
+    }
+
+    #[test]
+    fn test_raw_format_posts_the_envelope() {
+        let payload = failure_payload();
+        let RenderedNotification::Json(body) = render_notification(
+            &payload,
+            NotificationFormat::Raw,
+            None,
+            ScreenshotDelivery::Inline,
+        ) else {
+            panic!("expected JSON");
+        };
+        assert_eq!(body["event"], TEST_FAILURE_EVENT);
+        assert_eq!(body["data"]["scenario"]["title"], "Login");
+        assert_eq!(body["data"]["error"]["failed_at_action"], "Click submit");
+    }
+
+    #[test]
+    fn test_raw_screenshot_delivery() {
+        let payload = failure_payload();
+        let png = vec![0x89, b'P', b'N', b'G'];
+
+        let RenderedNotification::Json(body) = render_notification(
+            &payload,
+            NotificationFormat::Raw,
+            Some(png.clone()),
+            ScreenshotDelivery::Inline,
+        ) else {
+            panic!("expected JSON");
+        };
+        assert_eq!(body["data"]["screenshot"], BASE64_STANDARD.encode(&png));
+
+        let RenderedNotification::Multipart {
+            json_field,
+            payload_json,
+            file_field,
+            image_png,
+        } = render_notification(
+            &payload,
+            NotificationFormat::Raw,
+            Some(png.clone()),
+            ScreenshotDelivery::Multipart,
+        )
+        else {
+            panic!("expected multipart");
+        };
+        assert_eq!((json_field, file_field), ("payload", "screenshot"));
+        assert!(payload_json["data"].get("screenshot").is_none());
+        assert_eq!(image_png, png);
+    }
+
+    #[test]
+    fn test_slack_drops_screenshot() {
+        let RenderedNotification::Json(body) = render_notification(
+            &failure_payload(),
+            NotificationFormat::Slack,
+            Some(vec![1, 2, 3]),
+            ScreenshotDelivery::Multipart,
+        ) else {
+            panic!("expected JSON");
+        };
+        assert_eq!(body["text"], "Scenario failed: Login");
+    }
+}
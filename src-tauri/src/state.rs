@@ -1,35 +1,186 @@
 //! Application state management
 
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::error::XenotesterError;
+use crate::services::artifacts::CleanupPolicy;
+use crate::services::capture_cache::CaptureCache;
+use crate::services::capture_history::CaptureHistory;
+use crate::services::capture_stream::CaptureStream;
+use crate::services::confirmation::ConfirmationGate;
+use crate::services::debugger::Debugger;
+use crate::services::environment::EnvironmentSnapshot;
+use crate::services::focus_mode::FocusMode;
+use crate::services::governor::{ActionGuard, Governor};
+use crate::services::llm::anthropic::AgentSession;
+use crate::services::match_cache::MatchCache;
+use crate::services::orchestrator::OrchestratorBridge;
+use crate::services::recorder::Recorder;
+use crate::services::remote::RemoteServer;
+use crate::services::sync::SyncLock;
+use crate::services::user_activity::UserActivity;
+use crate::utils::cancel::CancellationToken;
 
 /// Global application state shared across commands
 #[derive(Clone)]
 pub struct AppState {
-    /// Flag to request stop of all operations
-    pub stop_requested: Arc<AtomicBool>,
+    /// Token for operations not started under a run token; replaced on `clear_stop`
+    pub stop_token: Arc<Mutex<CancellationToken>>,
+    /// Per-run cancellation tokens keyed by token ID
+    pub run_tokens: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    /// Active Computer Use agent sessions keyed by session ID
+    pub agent_sessions: Arc<Mutex<HashMap<String, AgentSession>>>,
+    /// Last captured frame per monitor for change detection
+    pub capture_cache: Arc<CaptureCache>,
+    /// Last captured frames, for reviewing what led up to a failure
+    pub capture_history: Arc<CaptureHistory>,
+    /// Template match results keyed by screenshot, template and parameters
+    pub match_cache: Arc<MatchCache>,
+    /// Screen recorder for run videos
+    pub recorder: Arc<Recorder>,
+    /// Live frame stream for watching runs
+    pub capture_stream: Arc<CaptureStream>,
+    /// Run started with `start_run` and not yet finished; input actions are logged under it
+    pub active_run: Arc<Mutex<Option<String>>>,
+    /// Values for `${NAME}` placeholders, set with `set_run_variables` and cleared by `finish_run`
+    pub run_variables: Arc<Mutex<HashMap<String, String>>>,
+    /// Rate and screen-region limits checked before every input action
+    pub governor: Arc<Governor>,
+    /// Rules for actions that wait for human approval (`confirm_action`)
+    pub confirmations: Arc<ConfirmationGate>,
+    /// Breakpoints of scripted runs and the runs paused at them
+    pub debugger: Arc<Debugger>,
+    /// Limits on kept run artifacts, applied by the artifact commands
+    pub artifact_policy: Arc<Mutex<CleanupPolicy>>,
+    /// Real user input detection; input actions wait while the user intervenes
+    pub user_activity: Arc<UserActivity>,
+    /// Do Not Disturb switched on for runs
+    pub focus_mode: Arc<FocusMode>,
+    /// Desktop state recorded by `snapshot_environment`, for `restore_environment`
+    pub environment_snapshot: Arc<Mutex<Option<EnvironmentSnapshot>>>,
+    /// Localhost server for external test harnesses
+    pub remote: Arc<RemoteServer>,
+    /// Executes scenario jobs queued on a central orchestration server
+    pub orchestrator: Arc<OrchestratorBridge>,
+    /// Held while a Supabase sync runs
+    pub sync_lock: Arc<SyncLock>,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
-            stop_requested: Arc::new(AtomicBool::new(false)),
+            stop_token: Arc::new(Mutex::new(CancellationToken::new())),
+            run_tokens: Arc::new(Mutex::new(HashMap::new())),
+            agent_sessions: Arc::new(Mutex::new(HashMap::new())),
+            capture_cache: Arc::new(CaptureCache::new()),
+            capture_history: Arc::new(CaptureHistory::new()),
+            match_cache: Arc::new(MatchCache::default()),
+            recorder: Arc::new(Recorder::new()),
+            capture_stream: Arc::new(CaptureStream::new()),
+            active_run: Arc::new(Mutex::new(None)),
+            run_variables: Arc::new(Mutex::new(HashMap::new())),
+            governor: Arc::new(Governor::new()),
+            confirmations: Arc::new(ConfirmationGate::new()),
+            debugger: Arc::new(Debugger::new()),
+            artifact_policy: Arc::new(Mutex::new(CleanupPolicy::default())),
+            user_activity: Arc::new(UserActivity::new()),
+            focus_mode: Arc::new(FocusMode::new()),
+            environment_snapshot: Arc::new(Mutex::new(None)),
+            remote: Arc::new(RemoteServer::new()),
+            orchestrator: Arc::new(OrchestratorBridge::new()),
+            sync_lock: Arc::new(SyncLock::new()),
         }
     }
 
-    /// Request stop of all operations
+    /// Request stop of all operations (the shared token and every run token)
     pub fn request_stop(&self) {
-        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Ok(token) = self.stop_token.lock() {
+            token.cancel();
+        }
+        if let Ok(tokens) = self.run_tokens.lock() {
+            tokens.values().for_each(CancellationToken::cancel);
+        }
     }
 
-    /// Clear the stop request flag
+    /// Clear the stop request by replacing the shared token with a fresh one
+    /// Cancelled run tokens are dropped; they are never reused.
     pub fn clear_stop(&self) {
-        self.stop_requested.store(false, Ordering::SeqCst);
+        if let Ok(mut token) = self.stop_token.lock() {
+            if token.is_cancelled() {
+                *token = CancellationToken::new();
+            }
+        }
+        if let Ok(mut tokens) = self.run_tokens.lock() {
+            tokens.retain(|_, token| !token.is_cancelled());
+        }
     }
 
-    /// Check if stop has been requested
+    /// Check if stop has been requested on the shared token
     pub fn is_stop_requested(&self) -> bool {
-        self.stop_requested.load(Ordering::SeqCst)
+        self.stop_token
+            .lock()
+            .map(|token| token.is_cancelled())
+            .unwrap_or(false)
+    }
+
+    /// Create and register a token for a new run, returning its ID
+    pub fn create_run_token(&self) -> Result<String, XenotesterError> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.run_tokens
+            .lock()?
+            .insert(id.clone(), CancellationToken::new());
+        Ok(id)
+    }
+
+    /// Cancel a single run; returns false if the token is unknown
+    pub fn cancel_run(&self, token_id: &str) -> Result<bool, XenotesterError> {
+        let tokens = self.run_tokens.lock()?;
+        Ok(match tokens.get(token_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Forget a finished run's token
+    pub fn release_run_token(&self, token_id: &str) -> Result<(), XenotesterError> {
+        self.run_tokens.lock()?.remove(token_id);
+        Ok(())
+    }
+
+    /// ID of the run in progress, if any
+    pub fn active_run(&self) -> Option<String> {
+        self.active_run.lock().ok().and_then(|run| run.clone())
+    }
+
+    /// Safety checks for input actions of the run in progress
+    pub fn action_guard(&self) -> ActionGuard {
+        ActionGuard {
+            governor: self.governor.clone(),
+            confirmations: self.confirmations.clone(),
+            user_activity: self.user_activity.clone(),
+            run_id: self.active_run(),
+        }
+    }
+
+    /// Resolve the token an operation should observe
+    /// `token_id` selects a run token; without one the shared stop token is used.
+    pub fn cancel_token(
+        &self,
+        token_id: Option<&str>,
+    ) -> Result<CancellationToken, XenotesterError> {
+        match token_id {
+            Some(id) => self
+                .run_tokens
+                .lock()?
+                .get(id)
+                .cloned()
+                .ok_or_else(|| XenotesterError::NotFound(format!("run token {}", id))),
+            None => Ok(self.stop_token.lock()?.clone()),
+        }
     }
 }
 
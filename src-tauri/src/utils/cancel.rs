@@ -0,0 +1,206 @@
+//! Cooperative cancellation for blocking and async operations
+//!
+//! Each run owns a `CancellationToken`. Blocking input and wait operations sleep
+//! in short slices and check the token between them; async code awaits
+//! `cancelled()`. Cancelling a token never affects other runs, so a stopped
+//! scenario cannot leave a stale flag behind for the next one.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+use crate::error::XenotesterError;
+
+/// How often the token is polled while sleeping on a blocking thread
+const CANCEL_CHECK_INTERVAL_MS: u64 = 10;
+/// Time an operation past its deadline gets to stop at its next cancellation
+/// check before it is abandoned
+const DEADLINE_GRACE_MS: u64 = 2000;
+
+/// Outcome of an operation run with `with_deadline`
+#[derive(Debug)]
+pub enum Deadline<T> {
+    Finished(T),
+    /// The deadline passed; holds the operation's output if it stopped in time
+    Expired(Option<T>),
+}
+
+#[derive(Debug, Default)]
+struct TokenInner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Cancellation handle shared by everything running on behalf of one run
+/// Clones refer to the same token.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<TokenInner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token and wake everything awaiting it
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Check if the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Return `Cancelled` if the token has been cancelled
+    pub fn check(&self) -> Result<(), XenotesterError> {
+        if self.is_cancelled() {
+            Err(XenotesterError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sleep on the current thread, returning `Cancelled` as soon as the token fires
+    pub fn sleep(&self, duration: Duration) -> Result<(), XenotesterError> {
+        let check_interval = Duration::from_millis(CANCEL_CHECK_INTERVAL_MS);
+        let mut elapsed = Duration::ZERO;
+
+        loop {
+            self.check()?;
+            if elapsed >= duration {
+                return Ok(());
+            }
+            let sleep_time = check_interval.min(duration - elapsed);
+            thread::sleep(sleep_time);
+            elapsed += sleep_time;
+        }
+    }
+
+    /// Resolve once the token has been cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking so a cancel in between is not missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Sleep asynchronously, returning `Cancelled` as soon as the token fires
+    pub async fn sleep_async(&self, duration: Duration) -> Result<(), XenotesterError> {
+        self.run_until_cancelled(tokio::time::sleep(duration)).await
+    }
+
+    /// Run `operation` with a token that fires when this one does or once
+    /// `timeout` has passed; without a timeout it gets this token
+    /// An operation stopped by this token's cancellation finishes normally.
+    pub async fn with_deadline<F, Fut>(
+        &self,
+        timeout: Option<Duration>,
+        operation: F,
+    ) -> Deadline<Fut::Output>
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future,
+    {
+        let Some(timeout) = timeout else {
+            return Deadline::Finished(operation(self.clone()).await);
+        };
+        let limited = CancellationToken::new();
+        let operation = operation(limited.clone());
+        tokio::pin!(operation);
+        let expired = tokio::select! {
+            output = &mut operation => return Deadline::Finished(output),
+            _ = self.cancelled() => false,
+            _ = tokio::time::sleep(timeout) => true,
+        };
+
+        limited.cancel();
+        if !expired {
+            return Deadline::Finished(operation.await);
+        }
+        let grace = Duration::from_millis(DEADLINE_GRACE_MS);
+        Deadline::Expired(tokio::time::timeout(grace, operation).await.ok())
+    }
+
+    /// Drive a future to completion unless the token fires first
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Result<F::Output, XenotesterError> {
+        self.check()?;
+        tokio::select! {
+            output = future => Ok(output),
+            _ = self.cancelled() => Err(XenotesterError::Cancelled),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_is_shared_between_clones_only() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        let other = CancellationToken::new();
+
+        clone.cancel();
+
+        assert!(token.is_cancelled());
+        assert!(matches!(token.check(), Err(XenotesterError::Cancelled)));
+        assert!(!other.is_cancelled());
+        assert!(other.sleep(Duration::from_millis(1)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_sleep_async_wakes_on_cancel() {
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            canceller.cancel();
+        });
+
+        let result = token.sleep_async(Duration::from_secs(30)).await;
+        assert!(matches!(result, Err(XenotesterError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_with_deadline() {
+        let token = CancellationToken::new();
+        let quick = token
+            .with_deadline(Some(Duration::from_secs(30)), |_| async { 1 })
+            .await;
+        assert!(matches!(quick, Deadline::Finished(1)));
+
+        let slow = token
+            .with_deadline(Some(Duration::from_millis(20)), |limited| async move {
+                limited.sleep_async(Duration::from_secs(30)).await
+            })
+            .await;
+        assert!(matches!(
+            slow,
+            Deadline::Expired(Some(Err(XenotesterError::Cancelled)))
+        ));
+        assert!(!token.is_cancelled());
+
+        token.cancel();
+        let stopped = token
+            .with_deadline(Some(Duration::from_secs(30)), |limited| async move {
+                limited.sleep_async(Duration::from_secs(30)).await
+            })
+            .await;
+        assert!(matches!(
+            stopped,
+            Deadline::Finished(Err(XenotesterError::Cancelled))
+        ));
+    }
+}
@@ -6,7 +6,9 @@ use global_hotkey::{
 };
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use tauri::{AppHandle, Emitter, Manager};
+use tracing::{debug, error, info, warn};
 
+use crate::commands::webhook::notify_emergency_stop;
 use crate::state::AppState;
 
 /// Flag to track if hotkey has already been registered (prevents duplicate registration)
@@ -14,6 +16,11 @@ static HOTKEY_REGISTERED: AtomicBool = AtomicBool::new(false);
 /// Stored hotkey ID for filtering events (only respond to our hotkey)
 static HOTKEY_ID: AtomicU32 = AtomicU32::new(0);
 
+/// Check whether the emergency stop hotkey is currently registered
+pub fn is_emergency_stop_registered() -> bool {
+    HOTKEY_REGISTERED.load(Ordering::SeqCst)
+}
+
 /// Register emergency stop hotkey (Shift + Escape)
 pub fn register_emergency_stop(app_handle: AppHandle) {
     // Guard: Use compare_exchange to atomically check and set, preventing race conditions
@@ -22,14 +29,14 @@ pub fn register_emergency_stop(app_handle: AppHandle) {
         .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
         .is_err()
     {
-        println!("[Emergency Stop] Hotkey already registered, skipping");
+        debug!("Hotkey already registered, skipping");
         return;
     }
 
     let manager = match GlobalHotKeyManager::new() {
         Ok(m) => m,
         Err(e) => {
-            eprintln!("[Emergency Stop] Failed to create hotkey manager: {}", e);
+            error!("Failed to create hotkey manager: {}", e);
             HOTKEY_REGISTERED.store(false, Ordering::SeqCst); // Reset flag on failure
             return;
         }
@@ -40,7 +47,7 @@ pub fn register_emergency_stop(app_handle: AppHandle) {
     let hotkey_id = hotkey.id(); // Get the hotkey ID for event filtering
 
     if let Err(e) = manager.register(hotkey) {
-        eprintln!("[Emergency Stop] Failed to register hotkey: {}", e);
+        error!("Failed to register hotkey: {}", e);
         HOTKEY_REGISTERED.store(false, Ordering::SeqCst); // Reset flag on failure
         return;
     }
@@ -68,15 +75,17 @@ pub fn register_emergency_stop(app_handle: AppHandle) {
 
                 // Emit event to frontend
                 if let Err(e) = app_handle_clone.emit("emergency-stop", ()) {
-                    eprintln!("[Emergency Stop] Failed to emit event: {}", e);
+                    error!("Failed to emit event: {}", e);
                 }
 
-                println!("[Emergency Stop] Hotkey triggered, stop requested");
+                notify_emergency_stop(&app_handle_clone);
+
+                warn!("Hotkey triggered, stop requested");
             }
         }
         // Channel disconnected, thread will exit cleanly
-        println!("[Emergency Stop] Event channel closed, listener thread exiting");
+        debug!("Event channel closed, listener thread exiting");
     });
 
-    println!("[Emergency Stop] Registered Shift+Escape as emergency stop");
+    info!("Registered Shift+Escape as emergency stop");
 }
@@ -0,0 +1,294 @@
+//! Structured logging
+//!
+//! Log events go through `tracing` to stderr, to a daily rolling file in the app
+//! log directory, and to an in-memory buffer of recent entries that the frontend
+//! can read with `get_recent_logs`. The level of this crate's events can be
+//! changed at runtime; other crates only log warnings and errors. Registered
+//! secret values are redacted from every output.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt as log_fmt, reload, EnvFilter, Registry};
+
+use crate::error::XenotesterError;
+use crate::utils::redact::redact;
+
+/// Level used until `set_log_level` is called
+const DEFAULT_LEVEL: Level = Level::INFO;
+
+/// Number of entries kept for `get_recent_logs`
+const RECENT_LOG_CAPACITY: usize = 2000;
+
+/// Number of daily log files kept on disk
+const MAX_LOG_FILES: usize = 7;
+
+/// Log entry as returned to the frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// "ERROR", "WARN", "INFO", "DEBUG" or "TRACE"
+    pub level: String,
+    /// Module that emitted the event
+    pub target: String,
+    pub message: String,
+}
+
+/// Bounded buffer of the most recent entries
+struct RecentLogs {
+    entries: VecDeque<(Level, LogEntry)>,
+    capacity: usize,
+}
+
+impl RecentLogs {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, level: Level, entry: LogEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((level, entry));
+    }
+
+    /// Newest `limit` entries at or above `min_level` severity, oldest first
+    fn query(&self, min_level: Level, limit: usize) -> Vec<LogEntry> {
+        let mut entries: Vec<LogEntry> = self
+            .entries
+            .iter()
+            .rev()
+            // More verbose levels compare greater
+            .filter(|(level, _)| *level <= min_level)
+            .take(limit)
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        entries.reverse();
+        entries
+    }
+}
+
+static RECENT_LOGS: Mutex<Option<RecentLogs>> = Mutex::new(None);
+
+/// Handle for changing the filter after initialization
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Collects an event's message and fields into one line
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
+
+/// Layer storing events in `RECENT_LOGS`
+struct RecentLogsLayer;
+
+impl<S: Subscriber> Layer<S> for RecentLogsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let metadata = event.metadata();
+        let entry = LogEntry {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: redact(&(visitor.message + &visitor.fields)).into_owned(),
+        };
+
+        let mut recent = RECENT_LOGS.lock().unwrap_or_else(PoisonError::into_inner);
+        recent
+            .get_or_insert_with(|| RecentLogs::new(RECENT_LOG_CAPACITY))
+            .push(*metadata.level(), entry);
+    }
+}
+
+/// Writer masking secret values; the fmt layer writes each event in one call
+struct RedactingWriter<W>(W);
+
+impl<W: io::Write> io::Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(text) => {
+                self.0.write_all(redact(text).as_bytes())?;
+                Ok(buf.len())
+            }
+            Err(_) => self.0.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// `MakeWriter` wrapping the writers of another one in `RedactingWriter`
+struct Redacting<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+/// Filter logging this crate at `level` and everything else at warnings and above
+fn filter_for(level: Level) -> EnvFilter {
+    EnvFilter::new(format!(
+        "warn,{}={}",
+        env!("CARGO_CRATE_NAME"),
+        level.as_str().to_lowercase()
+    ))
+}
+
+/// Parse a level name ("error", "warn", "info", "debug", "trace")
+pub fn parse_level(level: &str) -> Result<Level, XenotesterError> {
+    Level::from_str(level.trim())
+        .map_err(|_| XenotesterError::ConfigError(format!("Invalid log level: {}", level)))
+}
+
+/// Install the global subscriber, writing log files to `log_dir` when given
+/// Calling it again has no effect.
+pub fn init(log_dir: Option<&Path>) {
+    let (filter, handle) = reload::Layer::new(filter_for(DEFAULT_LEVEL));
+
+    let file_appender = log_dir.map(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("xenotester")
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(dir)
+    });
+    let (file_layer, file_error) = match file_appender {
+        Some(Ok(appender)) => (
+            Some(
+                log_fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(Redacting(appender)),
+            ),
+            None,
+        ),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(log_fmt::layer().with_writer(Redacting(io::stderr)))
+        .with(file_layer)
+        .with(RecentLogsLayer)
+        .try_init();
+
+    if installed.is_ok() {
+        let _ = FILTER_HANDLE.set(handle);
+        tracing::info!(log_dir = ?log_dir, "Logging initialized");
+        if let Some(e) = file_error {
+            tracing::warn!("Log files disabled, cannot write to log directory: {}", e);
+        }
+    }
+}
+
+/// Change the level of this crate's log events
+pub fn set_level(level: Level) -> Result<(), XenotesterError> {
+    let handle = FILTER_HANDLE
+        .get()
+        .ok_or_else(|| XenotesterError::ConfigError("Logging is not initialized".to_string()))?;
+    handle
+        .reload(filter_for(level))
+        .map_err(|e| XenotesterError::ConfigError(e.to_string()))?;
+    tracing::info!("Log level set to {}", level);
+    Ok(())
+}
+
+/// Most recent log entries at or above `min_level`, oldest first
+pub fn recent_logs(min_level: Level, limit: usize) -> Vec<LogEntry> {
+    RECENT_LOGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map(|recent| recent.query(min_level, limit))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str) -> LogEntry {
+        LogEntry {
+            timestamp_ms: 0,
+            level: String::new(),
+            target: String::new(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_recent_logs_filter_by_severity_and_keep_newest() {
+        let mut recent = RecentLogs::new(3);
+        recent.push(Level::ERROR, entry("dropped"));
+        recent.push(Level::INFO, entry("info"));
+        recent.push(Level::DEBUG, entry("debug"));
+        recent.push(Level::WARN, entry("warn"));
+
+        let messages = |entries: Vec<LogEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.message).collect()
+        };
+        assert_eq!(
+            messages(recent.query(Level::INFO, 10)),
+            vec!["info", "warn"]
+        );
+        assert_eq!(
+            messages(recent.query(Level::TRACE, 2)),
+            vec!["debug", "warn"]
+        );
+        assert!(recent.query(Level::ERROR, 10).is_empty());
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug").unwrap(), Level::DEBUG);
+        assert_eq!(parse_level(" WARN ").unwrap(), Level::WARN);
+        assert!(parse_level("verbose").is_err());
+    }
+}
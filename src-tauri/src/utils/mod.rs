@@ -1,3 +1,8 @@
 //! Utility modules
 
+pub mod cancel;
 pub mod hotkey;
+pub mod logging;
+pub mod operation;
+pub mod redact;
+pub mod sandbox;
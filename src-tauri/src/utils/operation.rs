@@ -0,0 +1,187 @@
+//! Lifecycle events of long backend operations
+//!
+//! Capture, matching and agent commands announce their work with
+//! `operation-started`, report `operation-progress` (at least once per
+//! `HEARTBEAT_INTERVAL_MS` while running) and end with `operation-finished`.
+//! All three carry the same operation ID, so the UI can tie a spinner or a
+//! cancel button to one piece of backend work.
+
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::error::{IpcError, XenotesterError};
+
+/// Interval of the progress heartbeat while an operation runs
+const HEARTBEAT_INTERVAL_MS: u64 = 1000;
+
+/// Payload of the `operation-started` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationStarted {
+    pub operation_id: String,
+    /// Command that started the operation (e.g. "capture_screen")
+    pub kind: &'static str,
+    /// Run token the operation observes, when one was given
+    pub token_id: Option<String>,
+}
+
+/// Payload of the `operation-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationProgress {
+    pub operation_id: String,
+    pub kind: &'static str,
+    pub elapsed_ms: u64,
+    /// Units of work done and in total, when the operation can tell (None for heartbeats)
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
+}
+
+/// How an operation ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationStatus {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Payload of the `operation-finished` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationFinished {
+    pub operation_id: String,
+    pub kind: &'static str,
+    pub status: OperationStatus,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// A running operation; reports `operation-finished` once, at the latest when dropped
+pub struct Operation {
+    app: AppHandle,
+    id: String,
+    kind: &'static str,
+    started: Instant,
+    finished: Arc<AtomicBool>,
+}
+
+impl Operation {
+    /// Emit `operation-started` and start the heartbeat
+    pub fn start(app: &AppHandle, kind: &'static str, token_id: Option<&str>) -> Self {
+        let operation = Self {
+            app: app.clone(),
+            id: Uuid::new_v4().to_string(),
+            kind,
+            started: Instant::now(),
+            finished: Arc::new(AtomicBool::new(false)),
+        };
+        operation.emit(
+            "operation-started",
+            OperationStarted {
+                operation_id: operation.id.clone(),
+                kind,
+                token_id: token_id.map(str::to_string),
+            },
+        );
+
+        let app = app.clone();
+        let id = operation.id.clone();
+        let started = operation.started;
+        let finished = operation.finished.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(HEARTBEAT_INTERVAL_MS)).await;
+                if finished.load(Ordering::Relaxed) {
+                    break;
+                }
+                let heartbeat = OperationProgress {
+                    operation_id: id.clone(),
+                    kind,
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                    completed: None,
+                    total: None,
+                };
+                if let Err(e) = app.emit("operation-progress", heartbeat) {
+                    error!("Failed to emit operation heartbeat: {}", e);
+                }
+            }
+        });
+
+        operation
+    }
+
+    /// Report units of work done
+    pub fn progress(&self, completed: u64, total: u64) {
+        self.emit(
+            "operation-progress",
+            OperationProgress {
+                operation_id: self.id.clone(),
+                kind: self.kind,
+                elapsed_ms: self.started.elapsed().as_millis() as u64,
+                completed: Some(completed),
+                total: Some(total),
+            },
+        );
+    }
+
+    /// Report the outcome of the operation (only the first call emits)
+    pub fn finish<T>(&self, result: &Result<T, IpcError>) {
+        let (status, error) = match result {
+            Ok(_) => (OperationStatus::Succeeded, None),
+            Err(e) if e.code == XenotesterError::Cancelled.code() => {
+                (OperationStatus::Cancelled, Some(e.message.clone()))
+            }
+            Err(e) => (OperationStatus::Failed, Some(e.message.clone())),
+        };
+        self.finish_with(status, error);
+    }
+
+    fn finish_with(&self, status: OperationStatus, error: Option<String>) {
+        if self.finished.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        self.emit(
+            "operation-finished",
+            OperationFinished {
+                operation_id: self.id.clone(),
+                kind: self.kind,
+                status,
+                error,
+                duration_ms: self.started.elapsed().as_millis() as u64,
+            },
+        );
+    }
+
+    fn emit(&self, event: &str, payload: impl Serialize + Clone) {
+        if let Err(e) = self.app.emit(event, payload) {
+            error!("Failed to emit {}: {}", event, e);
+        }
+    }
+}
+
+impl Drop for Operation {
+    /// An operation dropped without a result ended early (e.g. an error returned with `?`)
+    fn drop(&mut self) {
+        self.finish_with(OperationStatus::Failed, None);
+    }
+}
+
+/// Run a command body as an operation of the given kind
+pub async fn track<T>(
+    app: &AppHandle,
+    kind: &'static str,
+    token_id: Option<&str>,
+    body: impl Future<Output = Result<T, IpcError>>,
+) -> Result<T, IpcError> {
+    let operation = Operation::start(app, kind, token_id);
+    let result = body.await;
+    operation.finish(&result);
+    result
+}
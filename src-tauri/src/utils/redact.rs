@@ -0,0 +1,93 @@
+//! Redaction of secret values
+//!
+//! Secret values are registered when they are stored or resolved, and from
+//! then on replaced with `REDACTED` in log output and the action audit trail.
+
+use serde_json::Value;
+use std::borrow::Cow;
+use std::sync::{PoisonError, RwLock};
+
+/// Replacement for secret values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Shorter values are not redacted, they would mask unrelated text
+const MIN_SECRET_LEN: usize = 4;
+
+/// Registered values, longest first so overlapping secrets are fully masked
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Mask `value` from now on
+pub fn register(value: &str) {
+    if value.chars().count() < MIN_SECRET_LEN {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap_or_else(PoisonError::into_inner);
+    if !secrets.iter().any(|s| s == value) {
+        secrets.push(value.to_string());
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    }
+}
+
+fn redact_with<'a>(text: &'a str, secrets: &[String]) -> Cow<'a, str> {
+    let mut result = Cow::Borrowed(text);
+    for secret in secrets {
+        if result.contains(secret.as_str()) {
+            result = Cow::Owned(result.replace(secret.as_str(), REDACTED));
+        }
+    }
+    result
+}
+
+/// Replace registered secret values in `text`
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let secrets = SECRETS.read().unwrap_or_else(PoisonError::into_inner);
+    if secrets.is_empty() {
+        return Cow::Borrowed(text);
+    }
+    redact_with(text, &secrets)
+}
+
+/// Replace registered secret values in every string of a JSON value
+pub fn redact_json(value: &mut Value) {
+    match value {
+        Value::String(text) => {
+            if let Cow::Owned(redacted) = redact(text) {
+                *text = redacted;
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        Value::Object(map) => map.values_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_with_prefers_longest_secret() {
+        let secrets = vec!["hunter2-admin".to_string(), "hunter2".to_string()];
+        assert_eq!(
+            redact_with("typed hunter2-admin and hunter2", &secrets),
+            "typed [REDACTED] and [REDACTED]"
+        );
+        assert!(matches!(
+            redact_with("nothing here", &secrets),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_redact_json_nested_strings() {
+        register("s3cret-json-value");
+        let mut value = serde_json::json!({
+            "text": "pw: s3cret-json-value",
+            "keys": ["s3cret-json-value", 1],
+        });
+        redact_json(&mut value);
+        assert_eq!(value["text"], "pw: [REDACTED]");
+        assert_eq!(value["keys"][0], REDACTED);
+        assert_eq!(value["keys"][1], 1);
+    }
+}
@@ -0,0 +1,79 @@
+//! Path sandboxing for file-writing commands
+//!
+//! Paths coming over IPC are resolved (symlinks of existing ancestors followed)
+//! and accepted only inside one of the allowed roots. Paths with `..` are
+//! rejected: removing them before symlinks are resolved could escape a root.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::XenotesterError;
+
+/// Resolve `path` and require it to lie inside one of `roots`
+/// Returns the resolved path, which is what should be written to.
+pub fn resolve_within(path: &Path, roots: &[PathBuf]) -> Result<PathBuf, XenotesterError> {
+    if !path.is_absolute() {
+        return Err(XenotesterError::PermissionError(format!(
+            "{} is not an absolute path",
+            path.display()
+        )));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(XenotesterError::PermissionError(format!(
+            "{} contains '..'",
+            path.display()
+        )));
+    }
+
+    let resolved = resolve(path);
+    if roots.iter().any(|root| resolved.starts_with(resolve(root))) {
+        Ok(resolved)
+    } else {
+        Err(XenotesterError::PermissionError(format!(
+            "{} is outside the allowed directories",
+            path.display()
+        )))
+    }
+}
+
+/// Canonicalize the deepest existing ancestor of `path` (which has no `..`)
+fn resolve(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return missing
+                .iter()
+                .rev()
+                .fold(canonical, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_paths_outside_roots() {
+        let root = std::env::temp_dir().join("sandbox-root");
+        let roots = vec![root.clone()];
+
+        let inside = resolve_within(&root.join("run/shot.png"), &roots).unwrap();
+        assert!(inside.ends_with("sandbox-root/run/shot.png"));
+        assert!(resolve_within(&root.join("run/./shot.png"), &roots).is_ok());
+        // A symlink followed by `..` could leave the root
+        assert!(resolve_within(&root.join("run/../shot.png"), &roots).is_err());
+
+        assert!(resolve_within(&root.join("../escape.png"), &roots).is_err());
+        assert!(resolve_within(&root.join("run/../../escape.png"), &roots).is_err());
+        assert!(resolve_within(Path::new("relative/shot.png"), &roots).is_err());
+        assert!(resolve_within(&std::env::temp_dir().join("sandbox-root2"), &roots).is_err());
+    }
+}
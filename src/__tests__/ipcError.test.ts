@@ -0,0 +1,59 @@
+/**
+ * IpcError Tests
+ * Tests for reading command errors thrown by invoke
+ */
+
+import { describe, it, expect } from 'vitest';
+import type { IpcError } from '../types';
+import { errorLogText, getErrorMessage, isIpcError } from '../utils/ipcError';
+import { mapExecutionErrorToFailureReason } from '../services/resultJudge';
+
+const inputError: IpcError = {
+  code: 'INPUT_ERROR',
+  message: 'マウスまたはキーボードの操作に失敗しました',
+  detail: 'Input error: Invalid key name: foo',
+  retryable: true,
+  subsystem: 'input',
+};
+
+describe('isIpcError', () => {
+  it('recognizes command errors', () => {
+    expect(isIpcError(inputError)).toBe(true);
+  });
+
+  it('rejects other values', () => {
+    expect(isIpcError(new Error('boom'))).toBe(false);
+    expect(isIpcError('boom')).toBe(false);
+    expect(isIpcError(null)).toBe(false);
+    expect(isIpcError({ message: 'no code' })).toBe(false);
+  });
+});
+
+describe('getErrorMessage', () => {
+  it('returns the message of command errors instead of [object Object]', () => {
+    expect(getErrorMessage(inputError)).toBe(inputError.message);
+  });
+
+  it('returns the message of Error objects and stringifies anything else', () => {
+    expect(getErrorMessage(new Error('boom'))).toBe('boom');
+    expect(getErrorMessage('plain')).toBe('plain');
+  });
+});
+
+describe('errorLogText', () => {
+  it('includes the code and English detail of command errors', () => {
+    expect(errorLogText(inputError)).toBe('[INPUT_ERROR] Input error: Invalid key name: foo');
+  });
+});
+
+describe('mapExecutionErrorToFailureReason', () => {
+  it('maps command error codes regardless of the message language', () => {
+    expect(mapExecutionErrorToFailureReason('対象が見つかりません', 'NOT_FOUND')).toBe(
+      'element_not_found'
+    );
+    expect(mapExecutionErrorToFailureReason('Cancelled', 'CANCELLED')).toBe('aborted');
+    expect(mapExecutionErrorToFailureReason(inputError.message, inputError.code)).toBe(
+      'action_execution_error'
+    );
+  });
+});
@@ -114,16 +114,18 @@ describe('webhookService', () => {
         payload: expect.objectContaining({
           event: 'test_failure',
           timestamp: expect.any(String),
-          scenario: {
-            id: 'scenario-1',
-            title: 'Test Scenario',
+          data: {
+            scenario: {
+              id: 'scenario-1',
+              title: 'Test Scenario',
+            },
+            error: expect.objectContaining({
+              message: 'Element not found',
+              failed_at_action: 'Click submit button',
+              last_successful_action: 'Fill form',
+              completed_actions: 2,
+            }),
           },
-          error: expect.objectContaining({
-            message: 'Element not found',
-            failed_at_action: 'Click submit button',
-            last_successful_action: 'Fill form',
-            completed_actions: 2,
-          }),
         }),
       });
     });
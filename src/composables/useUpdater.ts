@@ -18,6 +18,7 @@ import type {
   UpdateProgress,
   UseUpdaterReturn,
 } from '../types/updater';
+import { getErrorMessage } from '../utils/ipcError';
 
 const CHECK_INTERVAL_MS = 60 * 60 * 1000; // 1 hour
 const STARTUP_DELAY_MS = 3000; // 3 seconds
@@ -80,7 +81,7 @@ export function useUpdater(): UseUpdaterReturn {
       }
     } catch (e) {
       console.error('[Updater] Failed to check for updates:', e);
-      error.value = e instanceof Error ? e.message : String(e);
+      error.value = getErrorMessage(e);
       status.value = 'error';
     } finally {
       isChecking = false;
@@ -135,7 +136,7 @@ export function useUpdater(): UseUpdaterReturn {
       await relaunch();
     } catch (e) {
       console.error('[Updater] Failed to download/install update:', e);
-      error.value = e instanceof Error ? e.message : String(e);
+      error.value = getErrorMessage(e);
       status.value = 'error';
     }
   }
@@ -4,6 +4,7 @@
 
 import { callClaudeMessagesViaProxy } from './claudeClient';
 import type { ExpectedAction, ComputerAction } from '../types';
+import { errorLogText, getErrorMessage } from '../utils/ipcError';
 
 /** シナリオから期待アクション列を抽出するためのプロンプト */
 const EXTRACT_ACTIONS_PROMPT = `
@@ -234,14 +235,14 @@ export async function extractExpectedActions(
   } catch (error) {
     console.error('[Action Validator] Failed to extract expected actions:', error);
     console.error('[Action Validator] Error details:', {
-      message: error instanceof Error ? error.message : String(error),
+      message: getErrorMessage(error),
       stack: error instanceof Error ? error.stack : undefined,
     });
 
     // フォールバックなし: エラーをそのままスローしてテスト失敗にする
     throw new Error(
       `Failed to extract expected actions from scenario: ${
-        error instanceof Error ? error.message : String(error)
+        getErrorMessage(error)
       }`
     );
   }
@@ -756,6 +757,6 @@ export async function verifyTextOnScreen(
     return { verified: false, reason: `Could not parse response. Claude said: "${truncatedResponse}"`, isError: true };
   } catch (error) {
     console.warn('[verifyTextOnScreen] Verification error:', error);
-    return { verified: false, reason: `Verification error: ${error}`, isError: true };
+    return { verified: false, reason: `Verification error: ${errorLogText(error)}`, isError: true };
   }
 }
@@ -15,6 +15,7 @@ import { callClaudeAPIViaProxy, RESULT_SCHEMA_INSTRUCTION } from './claudeClient
 import { purgeOldImages } from './historyManager';
 import { toScreenCoordinate } from '../utils/coordinateScaler';
 import { detectLoop, createActionRecord } from '../utils/loopDetector';
+import { errorLogText, getErrorMessage, isIpcError } from '../utils/ipcError';
 import {
   analyzeClaudeResponse,
   checkProgress,
@@ -43,6 +44,7 @@ import type {
   StepImage,
   HintImageMatchResult,
   MatchErrorCode,
+  ArtifactInfo,
 } from '../types';
 import { DEFAULT_AGENT_LOOP_CONFIG, DEFAULT_CLAUDE_MODEL_CONFIG } from '../types';
 
@@ -204,7 +206,7 @@ export async function runAgentLoop(
     } catch (extractError) {
       // Extraction failure should be status: 'failure' (not 'error')
       // This is a test failure, not a system error
-      const errorMessage = extractError instanceof Error ? extractError.message : String(extractError);
+      const errorMessage = getErrorMessage(extractError);
       log(`[Agent Loop] Expected action extraction failed: ${errorMessage}`);
       return {
         success: false,
@@ -250,7 +252,7 @@ export async function runAgentLoop(
     > = [
       {
         type: 'text',
-        text: `${options.scenario.description}\n\n${RESULT_SCHEMA_INSTRUCTION}`,
+        text: `${options.scenario.description}\n\n${RESULT_SCHEMA_INSTRUCTION}${describeActiveWindow(captureResult)}`,
       },
       {
         type: 'image',
@@ -309,7 +311,7 @@ export async function runAgentLoop(
         }
       } catch (error) {
         // Unexpected Rust-side error (should not normally occur) - continue without coordinates
-        log(`[Agent Loop] Template matching unexpected error, continuing without coordinates: ${error}`);
+        log(`[Agent Loop] Template matching unexpected error, continuing without coordinates: ${errorLogText(error)}`);
       }
 
       // Build hint text with coordinate information
@@ -621,8 +623,14 @@ export async function runAgentLoop(
 
         // Action execution error - immediate failure
         if (!actionResult.success) {
-          const failureReason = mapExecutionErrorToFailureReason(actionResult.error || 'Unknown error');
-          log(`[Agent Loop] Action execution failed: ${actionResult.error}`);
+          const failureReason = mapExecutionErrorToFailureReason(
+            actionResult.error || 'Unknown error',
+            actionResult.errorCode
+          );
+          log(
+            `[Agent Loop] Action execution failed: ${actionResult.error}` +
+              (actionResult.retryable ? ' (transient error, may succeed on rerun)' : '')
+          );
 
           // Record failed action
           executedActions.push({
@@ -718,14 +726,13 @@ export async function runAgentLoop(
               if (import.meta.env.VITE_XENOTESTER_DEBUG === '1') {
                 try {
                   const timestamp = new Date().toISOString().replace(/[:.]/g, '-');
-                  // Use OS-agnostic temp path via import.meta.env or fallback
-                  const debugDir = import.meta.env.VITE_XENOTESTER_DEBUG_DIR || '/tmp/xenotester-debug';
-                  await invoke('ensure_directory', { path: debugDir });
-                  const ssPath = `${debugDir}/verification-${timestamp}.png`;
-                  await invoke('save_base64_image', { base64Data: captureResult.imageBase64, filePath: ssPath });
-                  log(`[Agent Loop] Debug screenshot saved: ${ssPath}`);
+                  const artifact = await invoke<ArtifactInfo>('save_artifact', {
+                    name: `verification-${timestamp}.png`,
+                    dataBase64: captureResult.imageBase64,
+                  });
+                  log(`[Agent Loop] Debug screenshot saved: ${artifact.path}`);
                 } catch (e) {
-                  log(`[Agent Loop] Failed to save debug screenshot: ${e}`);
+                  log(`[Agent Loop] Failed to save debug screenshot: ${errorLogText(e)}`);
                 }
               }
 
@@ -1106,6 +1113,7 @@ export async function runAgentLoop(
             'template_image_decode_error',
             'insufficient_opacity',
             'non_finite_confidence',
+            'mask_decode_error',
           ];
 
           // Size-related error codes that may resolve when screen changes
@@ -1240,15 +1248,15 @@ export async function runAgentLoop(
               }
             } catch (error) {
               // Re-matching failed, continue without update
-              log(`[Agent Loop] Re-matching error (continuing): ${error}`);
+              log(`[Agent Loop] Re-matching error (continuing): ${errorLogText(error)}`);
             }
           }
         }
 
         // Build tool result
         const toolResultText = updatedCoordinatesText
-          ? `Action executed successfully${updatedCoordinatesText}`
-          : 'Action executed successfully';
+          ? `Action executed successfully${updatedCoordinatesText}${describeActiveWindow(captureResult)}`
+          : `Action executed successfully${describeActiveWindow(captureResult)}`;
 
         toolResults.push({
           type: 'tool_result',
@@ -1305,8 +1313,8 @@ export async function runAgentLoop(
       lastSuccessfulAction: executedActions.filter((a) => a.success).pop()?.description,
     };
   } catch (error) {
-    const errorMessage = error instanceof Error ? error.message : String(error);
-    log(`[Agent Loop] Error: ${errorMessage}`);
+    const errorMessage = getErrorMessage(error);
+    log(`[Agent Loop] Error: ${errorLogText(error)}`);
     return {
       success: false,
       error: errorMessage,
@@ -1329,6 +1337,16 @@ export async function runAgentLoop(
   }
 }
 
+/**
+ * Describe the focused window of a capture for the prompt
+ * Returns an empty string when the backend could not determine it
+ */
+function describeActiveWindow(capture: CaptureResult): string {
+  const window = capture.activeWindow;
+  if (!window) return '';
+  return `\n\nFocused window: ${window.appName} "${window.title}" at (${window.x}, ${window.y}) size ${window.width}x${window.height}`;
+}
+
 /**
  * Format action details for logging
  * Shows coordinates (both Claude and screen), text, and other parameters
@@ -1422,6 +1440,10 @@ async function callClaudeAPI(
 interface ActionExecutionResult {
   success: boolean;
   error?: string;
+  /** Error code of a failed command (see IpcError) */
+  errorCode?: string;
+  /** Whether the failure was transient (the same action may succeed on a rerun) */
+  retryable?: boolean;
 }
 
 /**
@@ -1539,7 +1561,15 @@ async function executeAction(
 
     return { success: true };
   } catch (error) {
-    const errorMessage = error instanceof Error ? error.message : String(error);
-    return { success: false, error: errorMessage };
+    if (isIpcError(error)) {
+      console.warn(`[Agent Loop] Command failed: ${errorLogText(error)}`);
+      return {
+        success: false,
+        error: error.message,
+        errorCode: error.code,
+        retryable: error.retryable,
+      };
+    }
+    return { success: false, error: getErrorMessage(error) };
   }
 }
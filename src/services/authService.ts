@@ -3,6 +3,7 @@ import { openUrl } from '@tauri-apps/plugin-opener';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { getSupabaseClient, getSession, signOut } from './supabaseClient';
 import type { AuthResult } from '../types/auth';
+import { getErrorMessage } from '../utils/ipcError';
 
 /**
  * OAuth認証完了後にブラウザに表示するHTML
@@ -89,7 +90,7 @@ export async function signInWithGoogle(): Promise<AuthResult> {
       });
     } catch (startError) {
       // ポート競合時は明確なエラーメッセージを返す
-      const message = startError instanceof Error ? startError.message : '';
+      const message = getErrorMessage(startError);
       if (message.includes('Address already in use') || message.includes('port')) {
         return {
           success: false,
@@ -663,7 +663,15 @@ export function analyzeClaudeResponse(
 /**
  * アクション実行エラーをFailureReasonにマッピング
  */
-export function mapExecutionErrorToFailureReason(error: string): FailureReason {
+export function mapExecutionErrorToFailureReason(error: string, code?: string): FailureReason {
+  // Command errors carry a code, which doesn't depend on the message language
+  if (code === 'NOT_FOUND' || code === 'ACCESSIBILITY_ERROR') {
+    return 'element_not_found';
+  }
+  if (code === 'CANCELLED') {
+    return 'aborted';
+  }
+
   const errorLower = error.toLowerCase();
 
   if (
@@ -17,6 +17,7 @@ import type {
 import { mapTestResultStatusToScenarioStatus } from '../types';
 import { validateHintImages } from '../constants/hintImages';
 import { sendFailureNotification } from './webhookService';
+import { errorLogText, getErrorMessage } from '../utils/ipcError';
 
 /** Options for scenario runner */
 export interface ScenarioRunnerOptions {
@@ -154,7 +155,7 @@ export class ScenarioRunner {
           throw imageError;
         }
         // DB read failure is also a critical error - stop execution to prevent running without expected hints
-        const errorMsg = `ヒント画像の読み込みに失敗しました: ${imageError instanceof Error ? imageError.message : String(imageError)}`;
+        const errorMsg = `ヒント画像の読み込みに失敗しました: ${getErrorMessage(imageError)}`;
         this.log(`[Scenario Runner] エラー: ${errorMsg}`);
         throw new Error(errorMsg);
       }
@@ -204,7 +205,7 @@ export class ScenarioRunner {
             timestamp: a.timestamp,
           })),
         }).catch((err) => {
-          this.log(`[Scenario Runner] Webhook通知の送信に失敗: ${err}`);
+          this.log(`[Scenario Runner] Webhook通知の送信に失敗: ${errorLogText(err)}`);
         });
       }
     } catch (error) {
@@ -212,7 +213,7 @@ export class ScenarioRunner {
         scenario.status = 'stopped';
       } else {
         scenario.status = 'failed';
-        scenario.error = error instanceof Error ? error.message : String(error);
+        scenario.error = getErrorMessage(error);
 
         // Send webhook notification on exception
         sendFailureNotification(scenario.id, scenario.title, {
@@ -223,7 +224,7 @@ export class ScenarioRunner {
           completedActions: 0,
           actionHistory: [],
         }).catch((err) => {
-          this.log(`[Scenario Runner] Webhook通知の送信に失敗: ${err}`);
+          this.log(`[Scenario Runner] Webhook通知の送信に失敗: ${errorLogText(err)}`);
         });
       }
       scenario.completedAt = new Date();
@@ -376,7 +377,7 @@ export class ScenarioRunner {
 
             // Webhook通知を送信（非同期、エラーは握りつぶす）
             sendFailureNotification(scenario.id, scenario.title, validationFailureResult).catch((err) => {
-              this.log(`[Batch Runner] Webhook通知の送信に失敗: ${err}`);
+              this.log(`[Batch Runner] Webhook通知の送信に失敗: ${errorLogText(err)}`);
             });
 
             // Record failure for this scenario and stop batch execution
@@ -391,7 +392,7 @@ export class ScenarioRunner {
         }
       } catch (imageError) {
         // DB read failure is a critical error - stop this scenario to prevent running without expected hints
-        const errorMsg = `ヒント画像の読み込みに失敗しました: ${imageError instanceof Error ? imageError.message : String(imageError)}`;
+        const errorMsg = `ヒント画像の読み込みに失敗しました: ${getErrorMessage(imageError)}`;
         this.log(`[Batch Runner] エラー: ${errorMsg}`);
 
         const imageLoadFailureResult: ScenarioExecutionResult = {
@@ -405,7 +406,7 @@ export class ScenarioRunner {
 
         // Webhook通知を送信（非同期、エラーは握りつぶす）
         sendFailureNotification(scenario.id, scenario.title, imageLoadFailureResult).catch((err) => {
-          this.log(`[Batch Runner] Webhook通知の送信に失敗: ${err}`);
+          this.log(`[Batch Runner] Webhook通知の送信に失敗: ${errorLogText(err)}`);
         });
 
         results.push(imageLoadFailureResult);
@@ -464,7 +465,7 @@ export class ScenarioRunner {
         const shouldNotify = ['failure', 'timeout', 'error'].includes(agentResult.testResult.status);
         if (shouldNotify) {
           sendFailureNotification(scenario.id, scenario.title, executionResult).catch((err) => {
-            this.log(`[Batch Runner] Webhook通知の送信に失敗: ${err}`);
+            this.log(`[Batch Runner] Webhook通知の送信に失敗: ${errorLogText(err)}`);
           });
         }
 
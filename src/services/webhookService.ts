@@ -51,15 +51,18 @@ export async function sendFailureNotification(
     // Send via Rust backend to avoid CORS
     const success = await invoke<boolean>('send_webhook', {
       url: webhookUrl,
+      // Event envelope: event-specific fields go under `data`
       payload: {
         event: payload.event,
         timestamp: payload.timestamp,
-        scenario: payload.scenario,
-        error: {
-          message: payload.error.message,
-          failed_at_action: payload.error.failedAtAction,
-          last_successful_action: payload.error.lastSuccessfulAction,
-          completed_actions: payload.error.completedActions,
+        data: {
+          scenario: payload.scenario,
+          error: {
+            message: payload.error.message,
+            failed_at_action: payload.error.failedAtAction,
+            last_successful_action: payload.error.lastSuccessfulAction,
+            completed_actions: payload.error.completedActions,
+          },
         },
       },
     });
@@ -13,6 +13,33 @@ export interface MonitorInfo {
   isPrimary: boolean;
 }
 
+/** Payload of the `monitors-changed` event */
+export interface MonitorLayout {
+  monitors: MonitorInfo[];
+  displayScaleFactor: number;
+}
+
+/** Screenshot encoding options (defaults to PNG) */
+export interface ImageEncoding {
+  format: 'png' | 'jpeg' | 'webp';
+  /** Quality for lossy formats (1-100, ignored for PNG) */
+  quality?: number;
+}
+
+/** Resampling filter for shrinking captures: Lanczos3, CatmullRom or Triangle */
+export type ResizeQuality = 'high' | 'balanced' | 'fast';
+
+/** Focused window at capture time */
+export interface ActiveWindow {
+  appName: string;
+  title: string;
+  /** Window bounds on the desktop (logical points) */
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
 /** Screen capture result */
 export interface CaptureResult {
   originalWidth: number;
@@ -21,15 +48,94 @@ export interface CaptureResult {
   resizedHeight: number;
   scaleFactor: number;
   imageBase64: string;
+  /** MIME type of imageBase64 (e.g. "image/png") */
+  mediaType: string;
   monitorId: number;
+  /** Monitor origin on the desktop (logical points) */
+  monitorX: number;
+  monitorY: number;
   /** Display scale factor for HiDPI/Retina displays (e.g., 2.0 for Retina) */
   displayScaleFactor: number;
+  /** Focused window when the frame was grabbed (absent if it couldn't be determined) */
+  activeWindow?: ActiveWindow;
+}
+
+/** Where a monitor sits in a virtual desktop capture */
+export interface MonitorPlacement {
+  monitorId: number;
+  name: string;
+  isPrimary: boolean;
+  /** Monitor bounds on the desktop (logical points) */
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  /** Monitor bounds within the resized composite image */
+  imageX: number;
+  imageY: number;
+  imageWidth: number;
+  imageHeight: number;
+}
+
+/** Composite capture of all monitors (capture_all_monitors) */
+export interface VirtualDesktopCapture {
+  originalWidth: number;
+  originalHeight: number;
+  resizedWidth: number;
+  resizedHeight: number;
+  scaleFactor: number;
+  imageBase64: string;
+  mediaType: string;
+  /** Desktop position of the composite's top-left corner */
+  originX: number;
+  originY: number;
+  monitors: MonitorPlacement[];
+}
+
+/** Coordinate space for translate_coordinates */
+export type CoordinateSpace = 'screenshot' | 'physical' | 'logical';
+
+/** Result of capture_screen_if_changed */
+export interface ChangeCaptureResult {
+  /** True when the screen did not change beyond the threshold (no new image) */
+  unchanged: boolean;
+  /** Fraction of the screen that changed since the cached frame (0.0 - 1.0) */
+  changeRatio: number;
+  /** Fresh capture, present only when the screen changed */
+  capture: CaptureResult | null;
+}
+
+/** Metadata header of a binary capture_screen_raw response */
+export type RawCaptureMetadata = Omit<CaptureResult, 'imageBase64'>;
+
+/** Decoded capture_screen_raw response */
+export interface RawCaptureResult {
+  metadata: RawCaptureMetadata;
+  /** Encoded image bytes (see metadata.mediaType) */
+  imageBytes: Uint8Array;
+}
+
+/** Linux session capabilities relevant to capture and input */
+export interface LinuxEnvironment {
+  displayServer: 'x11' | 'wayland' | 'unknown';
+  /** X11 clients can connect through XWayland */
+  xwayland: boolean;
+  /** xdg-desktop-portal ScreenCast interface is available */
+  screenCastPortal: boolean;
+  /** xdg-desktop-portal RemoteDesktop interface is available */
+  remoteDesktopPortal: boolean;
+  /** /dev/uinput is writable */
+  uinputAccess: boolean;
+  /** What to fix for capture or input to work */
+  issues: string[];
 }
 
-/** Permission status (macOS) */
+/** Permission status (macOS), or session capabilities (Linux) */
 export interface PermissionStatus {
   screenRecording: boolean;
   accessibility: boolean;
+  /** Present on Linux only */
+  linux?: LinuxEnvironment;
 }
 
 /**
@@ -42,7 +148,8 @@ export type MatchErrorCode =
   | 'template_image_decode_error'  // Template image format is invalid (permanent)
   | 'insufficient_opacity'         // Template is too transparent (permanent)
   | 'non_finite_confidence'        // Template lacks variance (permanent)
-  | 'template_too_large';          // Template larger than screenshot (size-related)
+  | 'template_too_large'           // Template larger than screenshot (size-related)
+  | 'mask_decode_error';           // Mask is invalid or differs in size (permanent)
 
 /** Result of template matching for a single hint image */
 export interface HintImageMatchResult {
@@ -50,6 +157,11 @@ export interface HintImageMatchResult {
   index: number;
   /** Original file name for identification */
   fileName: string;
+  /** Monitor the result comes from (when matching by monitorId or screenshots) */
+  monitorId?: number;
+  /** Match target (or center) in desktop coordinates (when matching by monitorId or screenshots) */
+  screenX?: number;
+  screenY?: number;
   /** Match result with coordinates or error */
   matchResult: {
     /** Whether a match was found above the confidence threshold */
@@ -58,6 +170,9 @@ export interface HintImageMatchResult {
     centerX: number | null;
     /** Y coordinate of the center point (in resized screenshot coordinates) */
     centerY: number | null;
+    /** Center in monitor-relative logical points (sub-pixel when refinement is enabled) */
+    originalCenterX?: number;
+    originalCenterY?: number;
     /** Match confidence score (0.0 - 1.0) */
     confidence: number | null;
     /** Template width after scaling */
@@ -68,5 +183,63 @@ export interface HintImageMatchResult {
     error: string | null;
     /** Error code for programmatic error handling (use this instead of parsing error message) */
     errorCode: MatchErrorCode | null;
+    /** Corners of the located template when found by features (TL, TR, BR, BL) */
+    quadrilateral?: [number, number][];
+    /** Point to act on when a match target was requested (same space as the center) */
+    targetX?: number;
+    targetY?: number;
   };
 }
+
+/** Payload of the `match-progress` event, sent as each template match completes */
+export interface MatchProgressEvent {
+  /** Run token of the batch, to tell concurrent batches apart */
+  tokenId: string | null;
+  /** Template matches finished so far (cache hits included) */
+  completed: number;
+  /** Template matches in the batch (templates x screenshots) */
+  total: number;
+  /** Index of the hint image in the input array */
+  index: number;
+  /** Monitor the template was matched on (when matching by monitorId or screenshots) */
+  monitorId?: number;
+  matchResult: HintImageMatchResult['matchResult'];
+}
+
+/** File saved with `save_artifact` */
+export interface ArtifactInfo {
+  /** Run the artifact belongs to (null outside a run) */
+  runId: string | null;
+  name: string;
+  path: string;
+  sizeBytes: number;
+  /** Milliseconds since the Unix epoch */
+  modifiedAt: number;
+}
+
+/** Desktop rectangle masked in every capture (logical points), see `set_redaction_zones` */
+export interface RedactionZone {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  mode?: 'blackout' | 'blur';
+  label?: string;
+}
+
+export type PrivacyMode = 'off' | 'windows' | 'strict';
+
+/** Options of start_capture_stream (frames default to JPEG, 960px long edge) */
+export interface StreamOptions {
+  fps?: number;
+  monitorId?: number;
+  includeCursor?: boolean;
+  maxEdge?: number;
+  encoding?: ImageEncoding;
+}
+
+/** Payload of the `capture-stream-frame` event */
+export interface StreamFrame extends CaptureResult {
+  sequence: number;
+  elapsedMs: number;
+}
@@ -23,6 +23,8 @@ export interface StoredScenario {
   order_index: number;
   created_at: string;
   updated_at: string;
+  /** Scripted steps as a JSON array, null when run from the description */
+  steps?: string | null;
 }
 
 /** Step image hint attached to a test step */
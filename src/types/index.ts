@@ -9,3 +9,4 @@ export * from './scenario';
 export * from './testResult';
 export * from './auth';
 export * from './settings';
+export * from './ipcError';
@@ -0,0 +1,36 @@
+/**
+ * Error type of Tauri commands
+ */
+
+/** Part of the app an error comes from */
+export type ErrorSubsystem =
+  | 'capture'
+  | 'input'
+  | 'permissions'
+  | 'config'
+  | 'image'
+  | 'database'
+  | 'llm'
+  | 'recording'
+  | 'accessibility'
+  | 'process'
+  | 'secrets'
+  | 'remote'
+  | 'auth'
+  | 'notifications'
+  | 'network'
+  | 'runner'
+  | 'general';
+
+/** Rejection value of every `invoke` call (mirrors the Rust IpcError) */
+export interface IpcError {
+  /** Error code, e.g. "INPUT_ERROR" or "CANCELLED" */
+  code: string;
+  /** Message for the user, in the language selected with set_locale */
+  message: string;
+  /** English description from the failing service, for logs and reports */
+  detail: string;
+  /** Whether the same call may succeed when tried again */
+  retryable: boolean;
+  subsystem: ErrorSubsystem;
+}
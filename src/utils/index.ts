@@ -4,3 +4,5 @@
 
 export * from './coordinateScaler';
 export * from './loopDetector';
+export * from './rawCapture';
+export * from './ipcError';
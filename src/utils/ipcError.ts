@@ -0,0 +1,39 @@
+/**
+ * Reading errors thrown by `invoke`
+ * Commands reject with an IpcError object rather than an Error, so
+ * `error instanceof Error ? error.message : String(error)` would print "[object Object]".
+ */
+
+import type { IpcError } from '../types';
+
+/**
+ * Whether a caught value is an IpcError returned by a command
+ */
+export function isIpcError(error: unknown): error is IpcError {
+  return (
+    typeof error === 'object' &&
+    error !== null &&
+    typeof (error as IpcError).code === 'string' &&
+    typeof (error as IpcError).message === 'string'
+  );
+}
+
+/**
+ * User-facing message of any caught value
+ */
+export function getErrorMessage(error: unknown): string {
+  if (isIpcError(error) || error instanceof Error) {
+    return error.message;
+  }
+  return String(error);
+}
+
+/**
+ * Message for logs: the error code and English detail of command errors
+ */
+export function errorLogText(error: unknown): string {
+  if (isIpcError(error)) {
+    return `[${error.code}] ${error.detail ?? error.message}`;
+  }
+  return getErrorMessage(error);
+}
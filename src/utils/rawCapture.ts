@@ -0,0 +1,24 @@
+/**
+ * Binary screenshot response decoding
+ * capture_screen_raw returns [u32 LE metadata length][metadata JSON][image bytes]
+ */
+
+import type { RawCaptureMetadata, RawCaptureResult } from '../types';
+
+/**
+ * Split a capture_screen_raw response into its metadata and image bytes
+ *
+ * @param buffer ArrayBuffer returned by invoke('capture_screen_raw')
+ * @returns Parsed metadata and a view over the encoded image
+ */
+export function parseRawCapture(buffer: ArrayBuffer): RawCaptureResult {
+  const view = new DataView(buffer);
+  const metadataLength = view.getUint32(0, true);
+  const metadataBytes = new Uint8Array(buffer, 4, metadataLength);
+  const metadata = JSON.parse(new TextDecoder().decode(metadataBytes)) as RawCaptureMetadata;
+
+  return {
+    metadata,
+    imageBytes: new Uint8Array(buffer, 4 + metadataLength),
+  };
+}